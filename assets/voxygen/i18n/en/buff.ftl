@@ -31,6 +31,8 @@ buff-title-wet = Wet
 buff-desc-wet = The ground rejects your feet, making it hard to stop.
 buff-title-ensnared = Ensnared
 buff-desc-ensnared = Vines grasp at your legs, impeding your movement.
+buff-title-potion_sickness = Potion Sickness
+buff-desc-potion_sickness = Potions heal less for each potion you drank recently. Stacks: { $stacks }
 buff-stat-health = Restores { $str_total } Health
 buff-stat-increase_max_energy = Raises Maximum Energy by { $strength }
 buff-stat-increase_max_health = Raises Maximum Health by { $strength }
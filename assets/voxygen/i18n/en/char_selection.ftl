@@ -20,4 +20,7 @@ char_selection-skin = Skin
 char_selection-eyeshape = Eye Details
 char_selection-accessories = Accessories
 char_selection-create_info_name = Your Character needs a name!
+char_selection-hardcore_off = Hardcore: Off
+char_selection-hardcore_on = Hardcore: On
+char_selection-dead = Dead
 char_selection-version_mismatch = WARNING! This server is running a different, possibly incompatible game version. Please update your game.
\ No newline at end of file
@@ -49,6 +49,7 @@ main-login-invalid_character = The selected character is invalid
 main-login-client_crashed = Client crashed
 main-login-not_on_whitelist = You need a Whitelist entry by an Admin to join
 main-login-banned = You have been banned with the following reason
+main-login-banned-until = You have been banned until { $end_date } with the following reason
 main-login-kicked = You have been kicked with the following reason
 main-login-select_language = Select a language
 main-login-client_version = Client Version
@@ -176,6 +176,7 @@ impl BotClient {
                 Some("common.items.weapons.sword.starter".to_string()),
                 None,
                 body.into(),
+                false,
             );
             client.load_character_list();
         }
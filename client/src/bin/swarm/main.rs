@@ -139,6 +139,7 @@ fn run_client(
             Some("common.items.weapons.sword.starter".into()),
             None,
             body(),
+            false,
         );
 
         client.load_character_list();
@@ -26,18 +26,21 @@ use common::{
         group,
         inventory::item::{modular, tool, ItemKind},
         invite::{InviteKind, InviteResponse},
+        pet::PetCommand,
         skills::Skill,
         slot::{EquipSlot, InvSlotId, Slot},
         CharacterState, ChatMode, ControlAction, ControlEvent, Controller, ControllerInputs,
         GroupManip, InputKind, InventoryAction, InventoryEvent, InventoryUpdateEvent,
         MapMarkerChange, UtteranceKind,
     },
+    danger::DangerGrid,
     event::{EventBus, LocalEvent},
     grid::Grid,
     link::Is,
+    location::LocationInfo,
     lod,
     mounting::Rider,
-    outcome::Outcome,
+    outcome::{Outcome, SoundOcclusion},
     recipe::{ComponentRecipeBook, RecipeBook},
     resources::{PlayerEntity, TimeOfDay},
     spiral::Spiral2d,
@@ -56,9 +59,9 @@ use common_net::{
     msg::{
         self,
         world_msg::{EconomyInfo, PoiInfo, SiteId, SiteInfo},
-        ClientGeneral, ClientMsg, ClientRegister, ClientType, DisconnectReason, InviteAnswer,
-        Notification, PingMsg, PlayerInfo, PlayerListUpdate, PresenceKind, RegisterError,
-        ServerGeneral, ServerInit, ServerRegisterAnswer,
+        ClientGeneral, ClientMsg, ClientRegister, ClientType, CustomItemSupport, DisconnectReason,
+        InviteAnswer, Notification, PingMsg, PlayerInfo, PlayerListUpdate, PresenceKind,
+        RegisterError, ServerGeneral, ServerInit, ServerRegisterAnswer,
     },
     sync::WorldSyncExt,
 };
@@ -103,7 +106,8 @@ pub enum Event {
     Kicked(String),
     Notification(Notification),
     SetViewDistance(u32),
-    Outcome(Outcome),
+    /// An outcome and how much its sound is occluded by terrain, if known
+    Outcome(Outcome, Option<SoundOcclusion>),
     CharacterCreated(CharacterId),
     CharacterEdited(CharacterId),
     CharacterError(String),
@@ -209,6 +213,12 @@ pub struct Client {
     server_info: ServerInfo,
     world_data: WorldData,
     weather: WeatherLerp,
+    danger_overlay: Option<DangerGrid>,
+    location: Option<LocationInfo>,
+    hotbar: Option<comp::Hotbar>,
+    /// The character the player possessed another entity from, to know when
+    /// they are back in control of it
+    possessed_from: Option<(Uid, CharacterId)>,
     player_list: HashMap<Uid, PlayerInfo>,
     character_list: CharacterList,
     sites: HashMap<SiteId, SiteInfoRich>,
@@ -222,6 +232,7 @@ pub struct Client {
     force_update_counter: u64,
 
     max_group_size: u32,
+    max_characters_per_player: usize,
     // Client has received an invite (inviter uid, time out instant)
     invite: Option<(Uid, Instant, Duration, InviteKind)>,
     group_leader: Option<Uid>,
@@ -259,6 +270,7 @@ pub struct Client {
 
     pending_chunks: HashMap<Vec2<i32>, Instant>,
     target_time_of_day: Option<TimeOfDay>,
+    low_bandwidth: bool,
 }
 
 /// Holds data related to the current players characters, as well as some
@@ -339,6 +351,7 @@ impl Client {
             recipe_book,
             component_recipe_book,
             max_group_size,
+            max_characters_per_player,
             client_timeout,
         ) = match loop {
             tokio::select! {
@@ -350,12 +363,14 @@ impl Client {
                 entity_package,
                 time_of_day,
                 max_group_size,
+                max_characters_per_player,
                 client_timeout,
                 world_map,
                 recipe_book,
                 component_recipe_book,
                 material_stats,
                 ability_map,
+                interaction_ranges,
             } => {
                 // Initialize `State`
                 let mut state = State::client();
@@ -367,6 +382,7 @@ impl Client {
                 *state.ecs_mut().write_resource() = PlayerEntity(Some(entity));
                 state.ecs_mut().insert(material_stats);
                 state.ecs_mut().insert(ability_map);
+                state.ecs_mut().insert(interaction_ranges);
 
                 let map_size_lg = common::terrain::MapSizeLg::new(world_map.dimensions_lg)
                     .map_err(|_| {
@@ -643,6 +659,7 @@ impl Client {
                     recipe_book,
                     component_recipe_book,
                     max_group_size,
+                    max_characters_per_player,
                     client_timeout,
                 ))
             },
@@ -664,6 +681,10 @@ impl Client {
                 map: world_map,
             },
             weather: WeatherLerp::default(),
+            danger_overlay: None,
+            location: None,
+            hotbar: None,
+            possessed_from: None,
             player_list: HashMap::new(),
             character_list: CharacterList::default(),
             sites: sites
@@ -687,6 +708,7 @@ impl Client {
             force_update_counter: 0,
 
             max_group_size,
+            max_characters_per_player,
             invite: None,
             group_leader: None,
             group_members: HashMap::new(),
@@ -718,6 +740,7 @@ impl Client {
 
             pending_chunks: HashMap::new(),
             target_time_of_day: None,
+            low_bandwidth: false,
         })
     }
 
@@ -759,15 +782,31 @@ impl Client {
             None => Ok(username),
         }?;
 
-        self.send_msg_err(ClientRegister { token_or_username })?;
+        self.send_msg_err(ClientRegister {
+            token_or_username,
+            custom_items: CustomItemSupport::Supported {
+                known_hash: comp::item::registered_custom_items_hash(),
+            },
+        })?;
 
         match self.register_stream.recv::<ServerRegisterAnswer>().await? {
             Err(RegisterError::AuthError(err)) => Err(Error::AuthErr(err)),
             Err(RegisterError::InvalidCharacter) => Err(Error::InvalidCharacter),
             Err(RegisterError::NotOnWhitelist) => Err(Error::NotOnWhitelist),
             Err(RegisterError::Kicked(err)) => Err(Error::Kicked(err)),
-            Err(RegisterError::Banned(reason)) => Err(Error::Banned(reason)),
-            Ok(()) => {
+            Err(RegisterError::Banned(info)) => Err(Error::Banned(info)),
+            Ok(custom_items) => {
+                if let Some(manifest) = custom_items {
+                    match manifest.decode() {
+                        Ok(Some(defs)) => {
+                            debug!("Registering {} custom items", defs.len());
+                            comp::item::register_custom_items(manifest.hash, defs);
+                        },
+                        Ok(None) => debug!("Custom items are already registered"),
+                        // The items are shown as unknown modded items instead
+                        Err(e) => warn!("Failed to register custom items: {}", e),
+                    }
+                }
                 self.registered = true;
                 Ok(())
             },
@@ -808,6 +847,7 @@ impl Client {
                     | ClientGeneral::CreateCharacter { .. }
                     | ClientGeneral::EditCharacter { .. }
                     | ClientGeneral::DeleteCharacter(_)
+                    | ClientGeneral::RestoreCharacter(_)
                     | ClientGeneral::Character(_, _)
                     | ClientGeneral::Spectate(_) => &mut self.character_screen_stream,
                     //Only in game
@@ -824,8 +864,12 @@ impl Client {
                     | ClientGeneral::UnlockSkillGroup(_)
                     | ClientGeneral::RequestPlayerPhysics { .. }
                     | ClientGeneral::RequestLossyTerrainCompression { .. }
+                    | ClientGeneral::RequestLowBandwidth { .. }
                     | ClientGeneral::AcknowledgePersistenceLoadError
                     | ClientGeneral::UpdateMapMarker(_)
+                    | ClientGeneral::SetHotbar(_)
+                    | ClientGeneral::ActivateHotbarSlot(_)
+                    | ClientGeneral::ChangeAppearance(_)
                     | ClientGeneral::SpectatePosition(_) => {
                         #[cfg(feature = "tracy")]
                         {
@@ -870,6 +914,16 @@ impl Client {
         })
     }
 
+    /// Asks the server to reduce the amount of traffic sent to this client at
+    /// the cost of fidelity, e.g. for metered connections. This can be toggled
+    /// at any time while in game.
+    pub fn request_low_bandwidth(&mut self, low_bandwidth: bool) {
+        self.low_bandwidth = low_bandwidth;
+        self.send_msg(ClientGeneral::RequestLowBandwidth { low_bandwidth })
+    }
+
+    pub fn low_bandwidth(&self) -> bool { self.low_bandwidth }
+
     fn send_msg<S>(&mut self, msg: S)
     where
         S: Into<ClientMsg>,
@@ -891,6 +945,8 @@ impl Client {
     ) {
         let view_distances = self.set_view_distances_local(view_distances);
         self.send_msg(ClientGeneral::Character(character_id, view_distances));
+        self.hotbar = None;
+        self.possessed_from = None;
 
         // Assume we are in_game unless server tells us otherwise
         self.presence = Some(PresenceKind::Character(character_id));
@@ -917,6 +973,7 @@ impl Client {
         mainhand: Option<String>,
         offhand: Option<String>,
         body: comp::Body,
+        hardcore: bool,
     ) {
         self.character_list.loading = true;
         self.send_msg(ClientGeneral::CreateCharacter {
@@ -924,6 +981,7 @@ impl Client {
             mainhand,
             offhand,
             body,
+            hardcore,
         });
     }
 
@@ -938,6 +996,25 @@ impl Client {
         self.send_msg(ClientGeneral::DeleteCharacter(character_id));
     }
 
+    /// Restore a recently deleted character
+    pub fn restore_character(&mut self, character_id: CharacterId) {
+        self.character_list.loading = true;
+        self.send_msg(ClientGeneral::RestoreCharacter(character_id));
+    }
+
+    /// Change the alias of a character, keeping its appearance
+    pub fn rename_character(&mut self, id: CharacterId, alias: String) {
+        if let Some(body) = self
+            .character_list
+            .characters
+            .iter()
+            .find(|item| item.character.id == Some(id))
+            .map(|item| item.body)
+        {
+            self.edit_character(alias, id, body);
+        }
+    }
+
     /// Send disconnect message to the server
     pub fn logout(&mut self) {
         debug!("Sending logout from server");
@@ -1258,6 +1335,18 @@ impl Client {
     /// Unstable, likely to be removed in a future release
     pub fn pois(&self) -> &Vec<PoiInfo> { &self.pois }
 
+    /// The danger map overlay, available once the client has entered the
+    /// game.
+    pub fn danger_overlay(&self) -> Option<&DangerGrid> { self.danger_overlay.as_ref() }
+
+    /// The site, biome and danger of the area the player is in, as last told
+    /// by the server
+    pub fn location(&self) -> Option<&LocationInfo> { self.location.as_ref() }
+
+    /// The hotbar bindings of the character, as last confirmed by the server.
+    /// `None` until the server sent them for the current character.
+    pub fn hotbar(&self) -> Option<&comp::Hotbar> { self.hotbar.as_ref() }
+
     pub fn sites_mut(&mut self) -> &mut HashMap<SiteId, SiteInfoRich> { &mut self.sites }
 
     pub fn enable_lantern(&mut self) {
@@ -1280,6 +1369,8 @@ impl Client {
 
     pub fn max_group_size(&self) -> u32 { self.max_group_size }
 
+    pub fn max_characters_per_player(&self) -> usize { self.max_characters_per_player }
+
     pub fn invite(&self) -> Option<(Uid, Instant, Duration, InviteKind)> { self.invite }
 
     pub fn group_info(&self) -> Option<(String, Uid)> {
@@ -1360,6 +1451,31 @@ impl Client {
 
     pub fn unmount(&mut self) { self.send_msg(ClientGeneral::ControlEvent(ControlEvent::Unmount)); }
 
+    /// Sits down on a seat, such as a bench or a chair
+    pub fn sit_on(&mut self, seat: EcsEntity) {
+        if let Some(uid) = self.state.read_component_copied(seat) {
+            self.send_msg(ClientGeneral::ControlEvent(ControlEvent::Sit(uid)));
+        }
+    }
+
+    pub fn stand_up(&mut self) { self.send_msg(ClientGeneral::ControlEvent(ControlEvent::Stand)); }
+
+    /// Gives a command to one of our pets, or to all of them if `pet` is
+    /// `None`
+    pub fn command_pet(&mut self, pet: Option<EcsEntity>, command: PetCommand) {
+        let pet = match pet {
+            Some(pet) => match self.state.read_component_copied(pet) {
+                Some(uid) => Some(uid),
+                None => return,
+            },
+            None => None,
+        };
+        self.send_msg(ClientGeneral::ControlEvent(ControlEvent::CommandPet {
+            pet,
+            command,
+        }));
+    }
+
     pub fn respawn(&mut self) {
         if self
             .state
@@ -1376,6 +1492,26 @@ impl Client {
         self.send_msg(ClientGeneral::UpdateMapMarker(event));
     }
 
+    /// Registers hotbar bindings with the server, which answers with the
+    /// bindings it accepted.
+    pub fn set_hotbar(
+        &mut self,
+        bindings: [Option<comp::HotbarBinding>; comp::hotbar::HOTBAR_SLOTS],
+    ) {
+        self.send_msg(ClientGeneral::SetHotbar(bindings));
+    }
+
+    pub fn activate_hotbar_slot(&mut self, slot: usize) {
+        self.send_msg(ClientGeneral::ActivateHotbarSlot(slot));
+    }
+
+    /// Changes the appearance of the character in the game. Only hair, eyes,
+    /// skin and accessories can be changed, the server ignores the change if
+    /// the species or body type differ.
+    pub fn change_appearance(&mut self, body: comp::Body) {
+        self.send_msg(ClientGeneral::ChangeAppearance(body));
+    }
+
     /// Set the current position to spectate, returns true if the client's
     /// player has a Pos component to write to.
     pub fn spectate_position(&mut self, pos: Vec3<f32>) -> bool {
@@ -1549,6 +1685,9 @@ impl Client {
     }
 
     pub fn current_biome(&self) -> BiomeKind {
+        if let Some(location) = &self.location {
+            return location.biome;
+        }
         match self.current_chunk() {
             Some(chunk) => chunk.meta().biome(),
             _ => BiomeKind::Void,
@@ -1888,9 +2027,14 @@ impl Client {
                             if !skip_mode && !self.pending_chunks.contains_key(key) {
                                 const TOTAL_PENDING_CHUNKS_LIMIT: usize = 12;
                                 const CURRENT_TICK_PENDING_CHUNKS_LIMIT: usize = 2;
+                                // Don't request more than the server is willing to serve
+                                let current_tick_limit = if self.low_bandwidth {
+                                    msg::LOW_BANDWIDTH_CHUNKS_PER_TICK
+                                } else {
+                                    CURRENT_TICK_PENDING_CHUNKS_LIMIT
+                                };
                                 if self.pending_chunks.len() < TOTAL_PENDING_CHUNKS_LIMIT
-                                    && current_tick_send_chunk_requests
-                                        < CURRENT_TICK_PENDING_CHUNKS_LIMIT
+                                    && current_tick_send_chunk_requests < current_tick_limit
                                 {
                                     self.send_msg_err(ClientGeneral::TerrainChunkRequest {
                                         key: *key,
@@ -2072,6 +2216,7 @@ impl Client {
             },
             ServerGeneral::SetPlayerEntity(uid) => {
                 if let Some(entity) = self.state.ecs().entity_from_uid(uid.0) {
+                    let old_uid = self.uid();
                     let old_player_entity = mem::replace(
                         &mut *self.state.ecs_mut().write_resource(),
                         PlayerEntity(Some(entity)),
@@ -2091,8 +2236,17 @@ impl Client {
                     if let Some(presence) = self.presence {
                         self.presence = Some(match presence {
                             PresenceKind::Spectator => PresenceKind::Spectator,
-                            PresenceKind::Character(_) => PresenceKind::Possessor,
-                            PresenceKind::Possessor => PresenceKind::Possessor,
+                            PresenceKind::Character(id) => {
+                                self.possessed_from = old_uid.map(|old_uid| (old_uid, id));
+                                PresenceKind::Possessor
+                            },
+                            PresenceKind::Possessor => match self.possessed_from {
+                                Some((original, id)) if original == uid => {
+                                    self.possessed_from = None;
+                                    PresenceKind::Character(id)
+                                },
+                                _ => PresenceKind::Possessor,
+                            },
                         });
                     }
                     // Clear pending trade
@@ -2126,6 +2280,19 @@ impl Client {
                         .delete_entity_and_clear_from_uid_allocator(entity.0);
                 }
             },
+            ServerGeneral::LanternUpdate(uid, light_emitter) => {
+                if let Some(entity) = self.state.ecs().entity_from_uid(uid.0) {
+                    let mut light_emitters = self.state.ecs().write_storage::<comp::LightEmitter>();
+                    match light_emitter {
+                        Some(light_emitter) => {
+                            let _ = light_emitters.insert(entity, light_emitter);
+                        },
+                        None => {
+                            light_emitters.remove(entity);
+                        },
+                    }
+                }
+            },
             ServerGeneral::Notification(n) => {
                 frontend_events.push(Event::Notification(n));
             },
@@ -2250,6 +2417,7 @@ impl Client {
             // Cleanup for when the client goes back to the `presence = None`
             ServerGeneral::ExitInGameSuccess => {
                 self.presence = None;
+                self.location = None;
                 self.clean_state();
             },
             ServerGeneral::InventoryUpdate(inventory, event) => {
@@ -2288,9 +2456,11 @@ impl Client {
                 // allowed view distance.
                 self.server_view_distance_limit = Some(vd);
             },
-            ServerGeneral::Outcomes(outcomes) => {
-                frontend_events.extend(outcomes.into_iter().map(Event::Outcome))
-            },
+            ServerGeneral::Outcomes(outcomes) => frontend_events.extend(
+                outcomes
+                    .into_iter()
+                    .map(|(outcome, occlusion)| Event::Outcome(outcome, occlusion)),
+            ),
             ServerGeneral::Knockback(impulse) => {
                 self.state
                     .ecs()
@@ -2321,9 +2491,24 @@ impl Client {
             ServerGeneral::WeatherUpdate(weather) => {
                 self.weather.weather_update(weather);
             },
+            ServerGeneral::DangerOverlay(overlay) => {
+                self.danger_overlay = overlay.decompress();
+                if self.danger_overlay.is_none() {
+                    warn!("Failed to decompress the danger map overlay");
+                }
+            },
+            ServerGeneral::LocationChanged(location) => {
+                self.location = Some(location);
+            },
             ServerGeneral::SpectatePosition(pos) => {
                 frontend_events.push(Event::SpectatePosition(pos));
             },
+            ServerGeneral::HotbarUpdate(hotbar, error) => {
+                if let Some(error) = error {
+                    debug!(?error, "Server corrected the hotbar bindings");
+                }
+                self.hotbar = Some(hotbar);
+            },
             _ => unreachable!("Not a in_game message"),
         }
         Ok(())
@@ -2375,7 +2560,8 @@ impl Client {
                 self.clean_state();
                 events.push(Event::CharacterError(error));
             },
-            ServerGeneral::CharacterCreated(character_id) => {
+            ServerGeneral::CharacterCreated(character_id, character) => {
+                self.character_list.characters.push(*character);
                 events.push(Event::CharacterCreated(character_id));
             },
             ServerGeneral::CharacterEdited(character_id) => {
@@ -1,4 +1,4 @@
-use super::{world_msg::SiteId, PingMsg};
+use super::{world_msg::SiteId, CustomItemSupport, PingMsg};
 use common::{
     character::CharacterId,
     comp,
@@ -42,6 +42,7 @@ pub enum ClientType {
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ClientRegister {
     pub token_or_username: String,
+    pub custom_items: CustomItemSupport,
 }
 
 /// Messages sent from the client to the server
@@ -54,8 +55,11 @@ pub enum ClientGeneral {
         mainhand: Option<String>,
         offhand: Option<String>,
         body: comp::Body,
+        /// Whether the character is deleted when it dies
+        hardcore: bool,
     },
     DeleteCharacter(CharacterId),
+    RestoreCharacter(CharacterId),
     EditCharacter {
         id: CharacterId,
         alias: String,
@@ -81,6 +85,11 @@ pub enum ClientGeneral {
     UnlockSkillGroup(SkillGroupKind),
     RequestSiteInfo(SiteId),
     UpdateMapMarker(comp::MapMarkerChange),
+    SetHotbar([Option<comp::HotbarBinding>; comp::hotbar::HOTBAR_SLOTS]),
+    ActivateHotbarSlot(usize),
+    /// Change the features of the character's body that can be changed after
+    /// it was created, such as its hair
+    ChangeAppearance(comp::Body),
 
     SpectatePosition(Vec3<f32>),
     //Only in Game, via terrain stream
@@ -100,6 +109,11 @@ pub enum ClientGeneral {
     RequestLossyTerrainCompression {
         lossy_terrain_compression: bool,
     },
+    /// Trade fidelity for a lower amount of traffic, see
+    /// [`super::LOW_BANDWIDTH_BUDGET`]
+    RequestLowBandwidth {
+        low_bandwidth: bool,
+    },
     AcknowledgePersistenceLoadError,
 }
 
@@ -119,7 +133,8 @@ impl ClientMsg {
                         ClientGeneral::RequestCharacterList
                         | ClientGeneral::CreateCharacter { .. }
                         | ClientGeneral::EditCharacter { .. }
-                        | ClientGeneral::DeleteCharacter(_) => {
+                        | ClientGeneral::DeleteCharacter(_)
+                        | ClientGeneral::RestoreCharacter(_) => {
                             c_type != ClientType::ChatOnly && presence.is_none()
                         },
                         ClientGeneral::Character(_, _) | ClientGeneral::Spectate(_) => {
@@ -141,8 +156,12 @@ impl ClientMsg {
                         | ClientGeneral::UnlockSkillGroup(_)
                         | ClientGeneral::RequestPlayerPhysics { .. }
                         | ClientGeneral::RequestLossyTerrainCompression { .. }
+                        | ClientGeneral::RequestLowBandwidth { .. }
                         | ClientGeneral::AcknowledgePersistenceLoadError
                         | ClientGeneral::UpdateMapMarker(_)
+                        | ClientGeneral::SetHotbar(_)
+                        | ClientGeneral::ActivateHotbarSlot(_)
+                        | ClientGeneral::ChangeAppearance(_)
                         | ClientGeneral::SpectatePosition(_) => {
                             c_type == ClientType::Game && presence.is_some()
                         },
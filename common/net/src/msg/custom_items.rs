@@ -0,0 +1,146 @@
+use common::comp::item::ItemDef;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::hash_map::DefaultHasher,
+    fmt,
+    hash::{Hash, Hasher},
+};
+use tracing::warn;
+
+/// Maximum number of custom items a server sends to clients
+pub const MAX_CUSTOM_ITEMS: usize = 1024;
+/// Maximum size (in bytes) of a single encoded custom item definition
+pub const MAX_CUSTOM_ITEM_SIZE: usize = 16 * 1024;
+
+/// Whether a client can register the definitions of items that a server adds
+/// on top of the vanilla assets. Clients that can't show these items as
+/// [unknown modded items](ItemDef::unknown_modded).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CustomItemSupport {
+    Unsupported,
+    Supported {
+        /// Hash of the custom items the client registered in an earlier
+        /// session, if any
+        known_hash: Option<u64>,
+    },
+}
+
+/// Custom item definitions sent to a client when it registers
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CustomItemManifest {
+    pub hash: u64,
+    /// Encoded item definitions, `None` if the client already registered the
+    /// ones with this hash
+    pub items: Option<Vec<Vec<u8>>>,
+}
+
+#[derive(Debug)]
+pub enum CustomItemError {
+    TooManyItems(usize),
+    ItemTooLarge(usize),
+    Decode(bincode::Error),
+}
+
+impl fmt::Display for CustomItemError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::TooManyItems(count) => write!(
+                f,
+                "Server sent {} custom items, the maximum is {}",
+                count, MAX_CUSTOM_ITEMS
+            ),
+            Self::ItemTooLarge(size) => write!(
+                f,
+                "Server sent a custom item of {} bytes, the maximum is {}",
+                size, MAX_CUSTOM_ITEM_SIZE
+            ),
+            Self::Decode(err) => write!(f, "Failed to decode custom item: {}", err),
+        }
+    }
+}
+
+impl CustomItemManifest {
+    /// Decodes the item definitions of the manifest, `None` if they were left
+    /// out because the client already knows them. The limits are checked again
+    /// here, a client doesn't have to trust the server to respect them.
+    pub fn decode(&self) -> Result<Option<Vec<ItemDef>>, CustomItemError> {
+        let items = match &self.items {
+            Some(items) => items,
+            None => return Ok(None),
+        };
+        if items.len() > MAX_CUSTOM_ITEMS {
+            return Err(CustomItemError::TooManyItems(items.len()));
+        }
+        items
+            .iter()
+            .map(|bytes| {
+                if bytes.len() > MAX_CUSTOM_ITEM_SIZE {
+                    Err(CustomItemError::ItemTooLarge(bytes.len()))
+                } else {
+                    bincode::deserialize(bytes).map_err(CustomItemError::Decode)
+                }
+            })
+            .collect::<Result<_, _>>()
+            .map(Some)
+    }
+}
+
+/// The custom items of a server, encoded once at startup
+#[derive(Debug, Default)]
+pub struct CustomItems {
+    hash: u64,
+    items: Vec<Vec<u8>>,
+}
+
+impl CustomItems {
+    /// Encodes the item definitions, skipping the ones above the limits
+    pub fn new<'a>(defs: impl IntoIterator<Item = &'a ItemDef>) -> Self {
+        let mut items = Vec::new();
+        for def in defs {
+            if items.len() == MAX_CUSTOM_ITEMS {
+                warn!(
+                    "More than {} custom items, skipping {} and the ones after it",
+                    MAX_CUSTOM_ITEMS,
+                    def.id()
+                );
+                break;
+            }
+            match bincode::serialize(def) {
+                Ok(bytes) if bytes.len() <= MAX_CUSTOM_ITEM_SIZE => items.push(bytes),
+                Ok(bytes) => warn!(
+                    "Custom item {} is {} bytes large, the maximum is {}, skipping it",
+                    def.id(),
+                    bytes.len(),
+                    MAX_CUSTOM_ITEM_SIZE
+                ),
+                Err(err) => warn!(?err, "Failed to encode custom item {}", def.id()),
+            }
+        }
+        let mut hasher = DefaultHasher::new();
+        items.hash(&mut hasher);
+        Self {
+            hash: hasher.finish(),
+            items,
+        }
+    }
+
+    pub fn len(&self) -> usize { self.items.len() }
+
+    pub fn is_empty(&self) -> bool { self.items.is_empty() }
+
+    pub fn hash(&self) -> u64 { self.hash }
+
+    /// The manifest to send to a client, `None` if there is nothing it can
+    /// register
+    pub fn manifest(&self, support: CustomItemSupport) -> Option<CustomItemManifest> {
+        match support {
+            CustomItemSupport::Supported { known_hash } if !self.is_empty() => {
+                Some(CustomItemManifest {
+                    hash: self.hash,
+                    items: (known_hash != Some(self.hash)).then(|| self.items.clone()),
+                })
+            },
+            _ => None,
+        }
+    }
+}
@@ -1,5 +1,6 @@
 pub mod client;
 pub mod compression;
+pub mod custom_items;
 pub mod ecs_packet;
 pub mod server;
 pub mod world_msg;
@@ -11,17 +12,31 @@ pub use self::{
         CompressedData, GridLtrPacking, PackingFormula, QuadPngEncoding, TriPngEncoding,
         VoxelImageEncoding, WidePacking, WireChonk,
     },
+    custom_items::{
+        CustomItemError, CustomItemManifest, CustomItemSupport, CustomItems, MAX_CUSTOM_ITEMS,
+        MAX_CUSTOM_ITEM_SIZE,
+    },
     ecs_packet::EcsCompPacket,
     server::{
-        CharacterInfo, DisconnectReason, InviteAnswer, Notification, PlayerInfo, PlayerListUpdate,
-        RegisterError, SerializedTerrainChunk, ServerGeneral, ServerInfo, ServerInit, ServerMsg,
-        ServerRegisterAnswer,
+        BanInfo, CharacterInfo, DisconnectReason, InviteAnswer, Notification, PlayerInfo,
+        PlayerListUpdate, RegisterError, SerializedTerrainChunk, ServerGeneral, ServerInfo,
+        ServerInit, ServerMsg, ServerRegisterAnswer,
     },
     world_msg::WorldMapMsg,
 };
 use common::character::CharacterId;
 use serde::{Deserialize, Serialize};
 
+/// Traffic (in bytes per second) that the server aims to stay below for clients
+/// in low-bandwidth mode while they are moving through populated areas. The
+/// traffic actually sent to each client is exposed by the
+/// `participants_bandwidth` network metric.
+pub const LOW_BANDWIDTH_BUDGET: u64 = 24 * 1024;
+
+/// Maximum number of terrain chunk requests of a low-bandwidth client that the
+/// server serves per tick, the rest are dropped and requested again later.
+pub const LOW_BANDWIDTH_CHUNKS_PER_TICK: usize = 1;
+
 #[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub enum PresenceKind {
     Spectator,
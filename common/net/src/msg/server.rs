@@ -1,14 +1,17 @@
 use super::{
-    world_msg::EconomyInfo, ClientType, CompressedData, EcsCompPacket, PingMsg, QuadPngEncoding,
-    TriPngEncoding, WidePacking, WireChonk,
+    world_msg::EconomyInfo, ClientType, CompressedData, CustomItemManifest, EcsCompPacket, PingMsg,
+    QuadPngEncoding, TriPngEncoding, WidePacking, WireChonk,
 };
 use crate::sync;
 use common::{
     calendar::Calendar,
     character::{self, CharacterItem},
     comp::{self, invite::InviteKind, item::MaterialStatManifest},
+    danger::DangerGrid,
+    interaction::InteractionRanges,
+    location::LocationInfo,
     lod,
-    outcome::Outcome,
+    outcome::{Outcome, SoundOcclusion},
     recipe::{ComponentRecipeBook, RecipeBook},
     resources::TimeOfDay,
     terrain::{Block, TerrainChunk, TerrainChunkMeta, TerrainChunkSize},
@@ -60,16 +63,20 @@ pub enum ServerInit {
         entity_package: sync::EntityPackage<EcsCompPacket>,
         time_of_day: TimeOfDay,
         max_group_size: u32,
+        max_characters_per_player: usize,
         client_timeout: Duration,
         world_map: crate::msg::world_msg::WorldMapMsg,
         recipe_book: RecipeBook,
         component_recipe_book: ComponentRecipeBook,
         material_stats: MaterialStatManifest,
         ability_map: comp::item::tool::AbilityMap,
+        interaction_ranges: InteractionRanges,
     },
 }
 
-pub type ServerRegisterAnswer = Result<(), RegisterError>;
+/// The custom items of the server, if the client supports them, when the
+/// registration was successful
+pub type ServerRegisterAnswer = Result<Option<CustomItemManifest>, RegisterError>;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum SerializedTerrainChunk {
@@ -136,8 +143,8 @@ pub enum ServerGeneral {
     CharacterListUpdate(Vec<CharacterItem>),
     /// An error occurred while creating or deleting a character
     CharacterActionError(String),
-    /// A new character was created
-    CharacterCreated(character::CharacterId),
+    /// A new character was created, to be added to the character list
+    CharacterCreated(character::CharacterId, Box<CharacterItem>),
     CharacterEdited(character::CharacterId),
     CharacterSuccess,
     SpectatorSuccess(Vec3<f32>),
@@ -171,7 +178,8 @@ pub enum ServerGeneral {
     /// modified). So we just need to send the terrain VD back to the client
     /// if corrections are made.
     SetViewDistance(u32),
-    Outcomes(Vec<Outcome>),
+    /// Outcomes with how much their sound is occluded for this client
+    Outcomes(Vec<(Outcome, Option<SoundOcclusion>)>),
     Knockback(Vec3<f32>),
     // Ingame related AND terrain stream
     TerrainChunkUpdate {
@@ -195,6 +203,9 @@ pub enum ServerGeneral {
     CompSync(sync::CompSyncPackage<EcsCompPacket>, u64),
     CreateEntity(sync::EntityPackage<EcsCompPacket>),
     DeleteEntity(Uid),
+    /// An entity's lantern was toggled, sent to clients subscribed to the
+    /// entity's region so they don't have to wait for the next component sync
+    LanternUpdate(Uid, Option<comp::LightEmitter>),
     Disconnect(DisconnectReason),
     /// Send a popup notification such as "Waypoint Saved"
     Notification(Notification),
@@ -204,9 +215,16 @@ pub enum ServerGeneral {
     SiteEconomy(EconomyInfo),
     MapMarker(comp::MapMarkerUpdate),
     WeatherUpdate(WeatherGrid),
+    /// The full danger map overlay, sent once when entering the game
+    DangerOverlay(CompressedData<DangerGrid>),
+    /// The player moved into an area with a different site, biome or danger
+    LocationChanged(LocationInfo),
     /// Suggest the client to spectate a position. Called after client has
     /// requested teleport etc.
     SpectatePosition(Vec3<f32>),
+    /// The hotbar bindings of the character as validated by the server, along
+    /// with the reason they differ from what the client last sent, if any
+    HotbarUpdate(comp::Hotbar, Option<comp::HotbarError>),
 }
 
 impl ServerGeneral {
@@ -268,10 +286,17 @@ pub enum DisconnectReason {
     Kicked(String),
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct BanInfo {
+    pub reason: String,
+    /// Unix timestamp at which the ban will expire, `None` if it is permanent
+    pub until: Option<i64>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub enum RegisterError {
     AuthError(String),
-    Banned(String),
+    Banned(BanInfo),
     Kicked(String),
     InvalidCharacter,
     NotOnWhitelist,
@@ -297,7 +322,7 @@ impl ServerMsg {
                         | ServerGeneral::CharacterListUpdate(_)
                         | ServerGeneral::CharacterActionError(_)
                         | ServerGeneral::CharacterEdited(_)
-                        | ServerGeneral::CharacterCreated(_) => {
+                        | ServerGeneral::CharacterCreated(..) => {
                             c_type != ClientType::ChatOnly && presence.is_none()
                         },
                         ServerGeneral::CharacterSuccess | ServerGeneral::SpectatorSuccess(_) => {
@@ -321,6 +346,9 @@ impl ServerMsg {
                         | ServerGeneral::SiteEconomy(_)
                         | ServerGeneral::MapMarker(_)
                         | ServerGeneral::WeatherUpdate(_)
+                        | ServerGeneral::DangerOverlay(_)
+                        | ServerGeneral::LocationChanged(_)
+                        | ServerGeneral::HotbarUpdate(_, _)
                         | ServerGeneral::SpectatePosition(_) => {
                             c_type == ClientType::Game && presence.is_some()
                         },
@@ -334,6 +362,7 @@ impl ServerMsg {
                         | ServerGeneral::CompSync(_, _)
                         | ServerGeneral::CreateEntity(_)
                         | ServerGeneral::DeleteEntity(_)
+                        | ServerGeneral::LanternUpdate(_, _)
                         | ServerGeneral::Disconnect(_)
                         | ServerGeneral::Notification(_) => true,
                     }
@@ -11,7 +11,7 @@ pub use common::uid::{Uid, UidAllocator};
 pub use net_sync::{NetSync, SyncFrom};
 pub use packet::{
     handle_insert, handle_interp_insert, handle_interp_modify, handle_interp_remove, handle_modify,
-    handle_remove, CompPacket, CompSyncPackage, EntityPackage, EntitySyncPackage,
+    handle_remove, CompPacket, CompSyncPackage, CompUpdateKind, EntityPackage, EntitySyncPackage,
     InterpolatableComponent,
 };
 pub use sync_ext::WorldSyncExt;
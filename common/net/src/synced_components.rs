@@ -38,6 +38,7 @@ macro_rules! synced_components {
             collider: Collider,
             sticky: Sticky,
             immovable: Immovable,
+            mountable: Mountable,
             character_state: CharacterState,
             shockwave: Shockwave,
             beam_segment: BeamSegment,
@@ -194,6 +195,10 @@ impl NetSync for Immovable {
     const SYNC_FROM: SyncFrom = SyncFrom::AnyEntity;
 }
 
+impl NetSync for Mountable {
+    const SYNC_FROM: SyncFrom = SyncFrom::AnyEntity;
+}
+
 impl NetSync for CharacterState {
     const SYNC_FROM: SyncFrom = SyncFrom::AnyEntity;
 }
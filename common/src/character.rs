@@ -3,7 +3,8 @@
 use crate::{comp, comp::inventory::Inventory};
 use serde::{Deserialize, Serialize};
 
-/// The limit on how many characters that a player can have
+/// The default limit on how many characters a player can have, servers can
+/// configure a different one
 pub const MAX_CHARACTERS_PER_PLAYER: usize = 8;
 pub type CharacterId = i64;
 
@@ -23,4 +24,20 @@ pub struct CharacterItem {
     pub character: Character,
     pub body: comp::Body,
     pub inventory: Inventory,
+    /// When the character was created, in seconds since the Unix epoch. Unknown
+    /// for characters created before this was recorded.
+    pub created_at: Option<i64>,
+    /// When the character last entered the game, in seconds since the Unix
+    /// epoch
+    pub last_login: Option<i64>,
+    pub total_playtime_seconds: u64,
+    pub deaths: u32,
+    /// Hardcore characters can't be played anymore once they died
+    pub hardcore: bool,
+}
+
+impl CharacterItem {
+    /// Whether this is a hardcore character that died, and so can't be
+    /// selected anymore
+    pub fn is_dead(&self) -> bool { self.hardcore && self.deaths > 0 }
 }
@@ -152,6 +152,7 @@ lazy_static! {
             BuffKind::Ensnared => "ensnared",
             BuffKind::Poisoned => "poisoned",
             BuffKind::Hastened => "hastened",
+            BuffKind::PotionSickness => "potion_sickness",
         };
         let mut buff_parser = HashMap::new();
         BuffKind::iter().for_each(|kind| {buff_parser.insert(string_from_buff(kind).to_string(), kind);});
@@ -246,6 +247,9 @@ pub enum ServerChatCommand {
     BuildAreaList,
     BuildAreaRemove,
     Campfire,
+    Characters,
+    Danger,
+    DbMaintenance,
     DebugColumn,
     DisconnectAllPlayers,
     DropAll,
@@ -296,6 +300,7 @@ pub enum ServerChatCommand {
     Time,
     Tp,
     Unban,
+    Unpossess,
     Version,
     Waypoint,
     Whitelist,
@@ -401,6 +406,22 @@ impl ServerChatCommand {
                 Some(Admin),
             ),
             ServerChatCommand::Campfire => cmd(vec![], "Spawns a campfire", Some(Admin)),
+            ServerChatCommand::Characters => cmd(
+                vec![Integer("page", 1, Optional)],
+                "Lists the characters of all players, the most recently played first",
+                Some(Admin),
+            ),
+            ServerChatCommand::Danger => cmd(
+                vec![],
+                "Prints the danger rating of your current area",
+                None,
+            ),
+            ServerChatCommand::DbMaintenance => cmd(
+                vec![],
+                "Purges expired deleted characters, then vacuums and analyzes the database in the \
+                 background",
+                Some(Admin),
+            ),
             ServerChatCommand::DebugColumn => cmd(
                 vec![Integer("x", 15000, Required), Integer("y", 15000, Required)],
                 "Prints some debug information about a column",
@@ -668,6 +689,11 @@ impl ServerChatCommand {
                 "Remove the ban for the given username",
                 Some(Moderator),
             ),
+            ServerChatCommand::Unpossess => cmd(
+                vec![],
+                "Return to the entity you possessed your current one from",
+                Some(Admin),
+            ),
             ServerChatCommand::Version => cmd(vec![], "Prints server version", None),
             ServerChatCommand::Waypoint => cmd(
                 vec![],
@@ -732,6 +758,9 @@ impl ServerChatCommand {
             ServerChatCommand::BuildAreaList => "build_area_list",
             ServerChatCommand::BuildAreaRemove => "build_area_remove",
             ServerChatCommand::Campfire => "campfire",
+            ServerChatCommand::Characters => "characters",
+            ServerChatCommand::Danger => "danger",
+            ServerChatCommand::DbMaintenance => "db_maintenance",
             ServerChatCommand::DebugColumn => "debug_column",
             ServerChatCommand::DisconnectAllPlayers => "disconnect_all_players",
             ServerChatCommand::DropAll => "dropall",
@@ -782,6 +811,7 @@ impl ServerChatCommand {
             ServerChatCommand::Time => "time",
             ServerChatCommand::Tp => "tp",
             ServerChatCommand::Unban => "unban",
+            ServerChatCommand::Unpossess => "unpossess",
             ServerChatCommand::Version => "version",
             ServerChatCommand::Waypoint => "waypoint",
             ServerChatCommand::Wiring => "wiring",
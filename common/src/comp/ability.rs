@@ -69,6 +69,9 @@ impl ActiveAbilities {
         }
     }
 
+    /// Puts an ability into a slot of the ability set of the currently
+    /// equipped weapons, returns whether it was changed. Abilities can only
+    /// be chosen once they are unlocked in the skill tree.
     pub fn change_ability(
         &mut self,
         slot: usize,
@@ -76,21 +79,26 @@ impl ActiveAbilities {
         new_ability: AuxiliaryAbility,
         inventory: Option<&Inventory>,
         skill_set: Option<&SkillSet>,
-    ) {
+    ) -> bool {
+        if auxiliary_key != Self::auxiliary_key(inventory)
+            || !Self::is_unlocked(new_ability, inventory, skill_set)
+        {
+            return false;
+        }
         let auxiliary_set = self
             .auxiliary_sets
             .entry(auxiliary_key)
             .or_insert(Self::default_ability_set(inventory, skill_set));
         if let Some(ability) = auxiliary_set.get_mut(slot) {
             *ability = new_ability;
+            true
+        } else {
+            false
         }
     }
 
-    pub fn auxiliary_set(
-        &self,
-        inv: Option<&Inventory>,
-        skill_set: Option<&SkillSet>,
-    ) -> [AuxiliaryAbility; MAX_ABILITIES] {
+    /// The key of the ability set of the currently equipped weapons
+    pub fn auxiliary_key(inv: Option<&Inventory>) -> AuxiliaryKey {
         let tool_kind = |slot| {
             inv.and_then(|inv| inv.equipped(slot))
                 .and_then(|item| match &*item.kind() {
@@ -99,17 +107,63 @@ impl ActiveAbilities {
                 })
         };
 
-        let aux_key = (
+        (
             tool_kind(EquipSlot::ActiveMainhand),
             tool_kind(EquipSlot::ActiveOffhand),
-        );
+        )
+    }
 
+    /// The ability set of the currently equipped weapons. Sets with abilities
+    /// that aren't unlocked (anymore) fall back to the default set.
+    pub fn auxiliary_set(
+        &self,
+        inv: Option<&Inventory>,
+        skill_set: Option<&SkillSet>,
+    ) -> [AuxiliaryAbility; MAX_ABILITIES] {
         self.auxiliary_sets
-            .get(&aux_key)
+            .get(&Self::auxiliary_key(inv))
             .copied()
+            .filter(|set| {
+                set.iter()
+                    .all(|ability| Self::is_unlocked(*ability, inv, skill_set))
+            })
             .unwrap_or_else(|| Self::default_ability_set(inv, skill_set))
     }
 
+    /// Removes the ability set of the currently equipped weapons if it has
+    /// abilities that aren't unlocked anymore, e.g. because the skill group
+    /// was reset. Returns whether it was removed.
+    pub fn remove_locked_abilities(
+        &mut self,
+        inv: Option<&Inventory>,
+        skill_set: Option<&SkillSet>,
+    ) -> bool {
+        let aux_key = Self::auxiliary_key(inv);
+        let locked = self.auxiliary_sets.get(&aux_key).map_or(false, |set| {
+            set.iter()
+                .any(|ability| !Self::is_unlocked(*ability, inv, skill_set))
+        });
+        if locked {
+            self.auxiliary_sets.remove(&aux_key);
+        }
+        locked
+    }
+
+    fn is_unlocked(
+        ability: AuxiliaryAbility,
+        inv: Option<&Inventory>,
+        skill_set: Option<&SkillSet>,
+    ) -> bool {
+        let unlocked_in = |equip_slot, index| {
+            Self::iter_unlocked_abilities(inv, skill_set, equip_slot).any(|i| i == index)
+        };
+        match ability {
+            AuxiliaryAbility::MainWeapon(index) => unlocked_in(EquipSlot::ActiveMainhand, index),
+            AuxiliaryAbility::OffWeapon(index) => unlocked_in(EquipSlot::ActiveOffhand, index),
+            AuxiliaryAbility::Empty => true,
+        }
+    }
+
     pub fn get_ability(
         &self,
         input: AbilityInput,
@@ -2247,3 +2301,133 @@ impl From<(&CharacterAbility, AbilityInfo, &JoinData<'_>)> for CharacterState {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::comp::{
+        inventory::{item::Item, loadout_builder::LoadoutBuilder},
+        skillset::{skills::SwordSkill, SkillGroupKind},
+    };
+
+    fn sword_inventory() -> Inventory {
+        Inventory::with_loadout_humanoid(
+            LoadoutBuilder::empty()
+                .active_mainhand(Some(Item::new_from_asset_expect(
+                    "common.items.weapons.sword.starter",
+                )))
+                .build(),
+        )
+    }
+
+    fn skill_set_with_spin() -> SkillSet {
+        let sword = SkillGroupKind::Weapon(ToolKind::Sword);
+        let mut skill_set = SkillSet::default();
+        skill_set.add_skill_points(SkillGroupKind::General, 1);
+        skill_set.unlock_skill(Skill::UnlockGroup(sword)).unwrap();
+        skill_set.add_skill_points(sword, 1);
+        skill_set
+            .unlock_skill(Skill::Sword(SwordSkill::UnlockSpin))
+            .unwrap();
+        skill_set
+    }
+
+    #[test]
+    fn only_unlocked_abilities_can_be_chosen() {
+        let inv = sword_inventory();
+        let key = (Some(ToolKind::Sword), None);
+        let mut abilities = ActiveAbilities::default();
+
+        let locked = SkillSet::default();
+        assert!(!abilities.change_ability(
+            0,
+            key,
+            AuxiliaryAbility::MainWeapon(0),
+            Some(&inv),
+            Some(&locked)
+        ));
+        assert!(abilities.change_ability(
+            0,
+            key,
+            AuxiliaryAbility::Empty,
+            Some(&inv),
+            Some(&locked)
+        ));
+
+        let unlocked = skill_set_with_spin();
+        assert!(abilities.change_ability(
+            1,
+            key,
+            AuxiliaryAbility::MainWeapon(0),
+            Some(&inv),
+            Some(&unlocked)
+        ));
+        assert_eq!(
+            abilities.auxiliary_set(Some(&inv), Some(&unlocked))[1],
+            AuxiliaryAbility::MainWeapon(0)
+        );
+
+        // Sets are only changed for the weapons that are equipped
+        assert!(!abilities.change_ability(
+            1,
+            (Some(ToolKind::Bow), None),
+            AuxiliaryAbility::Empty,
+            Some(&inv),
+            Some(&unlocked)
+        ));
+    }
+
+    #[test]
+    fn sets_follow_the_equipped_weapons() {
+        let inv = sword_inventory();
+        let skill_set = skill_set_with_spin();
+        let mut abilities = ActiveAbilities::default();
+        let key = (Some(ToolKind::Sword), None);
+        abilities.change_ability(
+            0,
+            key,
+            AuxiliaryAbility::Empty,
+            Some(&inv),
+            Some(&skill_set),
+        );
+        abilities.change_ability(
+            1,
+            key,
+            AuxiliaryAbility::MainWeapon(0),
+            Some(&inv),
+            Some(&skill_set),
+        );
+        let sword_set = abilities.auxiliary_set(Some(&inv), Some(&skill_set));
+        assert_eq!(sword_set[0], AuxiliaryAbility::Empty);
+
+        // Without the sword its set isn't used, but it is back once the sword is
+        let unarmed = Inventory::with_loadout_humanoid(LoadoutBuilder::empty().build());
+        assert_eq!(
+            abilities.auxiliary_set(Some(&unarmed), Some(&skill_set)),
+            [AuxiliaryAbility::Empty; MAX_ABILITIES]
+        );
+        assert_eq!(
+            abilities.auxiliary_set(Some(&inv), Some(&skill_set)),
+            sword_set
+        );
+    }
+
+    #[test]
+    fn reset_skills_invalidate_sets() {
+        let inv = sword_inventory();
+        let key = (Some(ToolKind::Sword), None);
+        let mut set = [AuxiliaryAbility::Empty; MAX_ABILITIES];
+        set[0] = AuxiliaryAbility::MainWeapon(0);
+        // As loaded from the database after the sword skills were reset
+        let mut abilities = ActiveAbilities::new(HashMap::from_iter([(key, set)]));
+        let skill_set = SkillSet::default();
+
+        assert_eq!(
+            abilities.auxiliary_set(Some(&inv), Some(&skill_set)),
+            [AuxiliaryAbility::Empty; MAX_ABILITIES]
+        );
+        assert!(abilities.remove_locked_abilities(Some(&inv), Some(&skill_set)));
+        assert!(abilities.auxiliary_sets.is_empty());
+        assert!(!abilities.remove_locked_abilities(Some(&inv), Some(&skill_set)));
+    }
+}
@@ -9,7 +9,7 @@ arg_enum! {
     }
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug)]
 pub struct Admin(pub AdminRole);
 
 impl Component for Admin {
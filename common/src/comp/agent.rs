@@ -3,6 +3,7 @@ use crate::{
         arthropod, biped_small, bird_medium, humanoid, quadruped_low, quadruped_medium,
         quadruped_small, ship, Body, UtteranceKind,
     },
+    consts::SOUND_TRAVEL_DIST_PER_VOLUME,
     path::Chaser,
     rtsim::{Memory, MemoryItem, RtSimController, RtSimEvent},
     trade::{PendingTrade, ReducedInventory, SiteId, SitePrices, TradeId, TradeResult},
@@ -20,6 +21,15 @@ pub const DEFAULT_INTERACTION_TIME: f32 = 3.0;
 pub const TRADE_INTERACTION_TIME: f32 = 300.0;
 const AWARENESS_DECREMENT_CONSTANT: f32 = 2.1;
 const SECONDS_BEFORE_FORGET_SOUNDS: f64 = 180.0;
+/// How long (in seconds) a startled agent keeps fleeing.
+pub const STARTLE_DURATION: f64 = 6.0;
+/// Volume of the call a startled animal makes to warn its herd.
+pub const ALARM_CALL_VOLUME: f32 = 8.0;
+/// Volume fires are treated as having when deciding whether they startle an
+/// agent, so that prey keep their distance from them.
+pub const FIRE_VOLUME: f32 = 4.0;
+const PREY_STARTLE_VOLUME: f32 = 1.0;
+const PREDATOR_STARTLE_VOLUME: f32 = 40.0;
 
 #[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum Alignment {
@@ -182,6 +192,10 @@ pub struct Psyche {
     /// than `sight_dist`. `None` implied that the agent is always aggro
     /// towards enemies that it is aware of.
     pub aggro_dist: Option<f32>,
+    /// The volume, once it reached the agent, above which an explosion, the
+    /// sound of combat or a fire startles the agent into fleeing from it.
+    /// `None` means the agent is never startled.
+    pub startle_volume: Option<f32>,
 }
 
 impl<'a> From<&'a Body> for Psyche {
@@ -286,6 +300,41 @@ impl<'a> From<&'a Body> for Psyche {
                 Body::Humanoid(_) => Some(20.0),
                 _ => None, // Always aggressive if detected
             },
+            startle_volume: match body {
+                Body::QuadrupedSmall(quadruped_small) => match quadruped_small.species {
+                    quadruped_small::Species::Fox
+                    | quadruped_small::Species::Boar
+                    | quadruped_small::Species::Batfox
+                    | quadruped_small::Species::Dodarock
+                    | quadruped_small::Species::Holladon
+                    | quadruped_small::Species::Hyena
+                    | quadruped_small::Species::Rat
+                    | quadruped_small::Species::Truffler
+                    | quadruped_small::Species::Fungome
+                    | quadruped_small::Species::Dog => Some(PREDATOR_STARTLE_VOLUME),
+                    _ => Some(PREY_STARTLE_VOLUME),
+                },
+                Body::QuadrupedMedium(quadruped_medium) => match quadruped_medium.species {
+                    quadruped_medium::Species::Mouflon
+                    | quadruped_medium::Species::Deer
+                    | quadruped_medium::Species::Hirdrasil
+                    | quadruped_medium::Species::Donkey
+                    | quadruped_medium::Species::Camel
+                    | quadruped_medium::Species::Zebra
+                    | quadruped_medium::Species::Antelope
+                    | quadruped_medium::Species::Kelpie
+                    | quadruped_medium::Species::Horse
+                    | quadruped_medium::Species::Cattle
+                    | quadruped_medium::Species::Highland
+                    | quadruped_medium::Species::Yak
+                    | quadruped_medium::Species::Moose
+                    | quadruped_medium::Species::Llama
+                    | quadruped_medium::Species::Alpaca => Some(PREY_STARTLE_VOLUME),
+                    _ => Some(PREDATOR_STARTLE_VOLUME),
+                },
+                Body::BirdMedium(_) | Body::FishSmall(_) => Some(PREY_STARTLE_VOLUME),
+                _ => None,
+            },
         }
     }
 }
@@ -340,6 +389,16 @@ impl Sound {
 
         self
     }
+
+    /// The sound as heard from `pos`, its volume dropping off with distance.
+    #[must_use]
+    pub fn propagated_to(self, pos: Vec3<f32>) -> Self {
+        // TODO: Use pathfinding for more dropoff around obstacles
+        let dist_sqrd = pos.distance_squared(self.pos);
+        let travel_dist_sqrd = (self.vol * SOUND_TRAVEL_DIST_PER_VOLUME).powi(2);
+
+        self.with_new_vol(self.vol - dist_sqrd / travel_dist_sqrd * self.vol)
+    }
 }
 
 #[derive(Copy, Clone, Debug)]
@@ -352,6 +411,23 @@ pub enum SoundKind {
     Explosion,
     Beam,
     Shockwave,
+    /// A fire close enough to be seen or heard crackling.
+    Fire,
+    /// The call of a startled animal, warning its herd of a danger at
+    /// `source`.
+    AlarmCall {
+        body: Body,
+        source: Vec3<f32>,
+    },
+}
+
+/// Something that made an agent flee, see [`Agent::startle`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Startle {
+    /// Where the danger is
+    pub source: Vec3<f32>,
+    /// The time until which the agent flees
+    pub until: f64,
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -472,6 +548,7 @@ pub struct Agent {
     pub timer: Timer,
     pub bearing: Vec2<f32>,
     pub sounds_heard: Vec<Sound>,
+    pub startled: Option<Startle>,
     pub awareness: f32,
     pub position_pid_controller: Option<PidController<fn(Vec3<f32>, Vec3<f32>) -> f32, 16>>,
 }
@@ -500,6 +577,7 @@ impl Agent {
             timer: Timer::default(),
             bearing: Vec2::zero(),
             sounds_heard: Vec::new(),
+            startled: None,
             awareness: 0.0,
             position_pid_controller: None,
         }
@@ -588,6 +666,71 @@ impl Agent {
         }
     }
 
+    /// Whether a sound makes the agent flee from it. Alarm calls only alarm
+    /// members of the caller's herd, and pets stay with their owner.
+    pub fn is_startled_by(
+        &self,
+        sound: &Sound,
+        body: Option<&Body>,
+        alignment: Option<&Alignment>,
+    ) -> bool {
+        if matches!(alignment, Some(Alignment::Owned(_))) {
+            return false;
+        }
+
+        let alarming = match sound.kind {
+            SoundKind::Melee
+            | SoundKind::Projectile
+            | SoundKind::Explosion
+            | SoundKind::Beam
+            | SoundKind::Shockwave
+            | SoundKind::Fire => true,
+            SoundKind::AlarmCall { body: caller, .. } => {
+                body.map_or(false, |body| body.is_same_species_as(&caller))
+            },
+            SoundKind::Unknown | SoundKind::Utterance(..) | SoundKind::Movement => false,
+        };
+
+        alarming
+            && self
+                .psyche
+                .startle_volume
+                .map_or(false, |startle_volume| sound.vol >= startle_volume)
+    }
+
+    /// Makes the agent flee from the source of a sound for a while if the
+    /// sound is alarming enough. This stacks with fleeing at low health.
+    ///
+    /// Returns whether the agent wasn't startled already, in which case it
+    /// should warn its herd with an alarm call.
+    pub fn startle(
+        &mut self,
+        sound: &Sound,
+        body: Option<&Body>,
+        alignment: Option<&Alignment>,
+        time: f64,
+    ) -> bool {
+        if !self.is_startled_by(sound, body, alignment) {
+            return false;
+        }
+
+        let source = match sound.kind {
+            SoundKind::AlarmCall { source, .. } => source,
+            _ => sound.pos,
+        };
+        let was_startled = self.is_startled(time);
+        self.startled = Some(Startle {
+            source,
+            until: time + STARTLE_DURATION,
+        });
+
+        !was_startled
+    }
+
+    pub fn is_startled(&self, time: f64) -> bool {
+        self.startled.map_or(false, |startle| time < startle.until)
+    }
+
     pub fn allowed_to_speak(&self) -> bool { self.behavior.can(BehaviorCapability::SPEAK) }
 
     pub fn forget_enemy(&mut self, target_name: &str) {
@@ -614,7 +757,19 @@ impl Component for Agent {
 
 #[cfg(test)]
 mod tests {
-    use super::{humanoid, Agent, Behavior, BehaviorCapability, BehaviorState, Body};
+    use super::{
+        humanoid, quadruped_medium, Agent, Alignment, Behavior, BehaviorCapability, BehaviorState,
+        Body, Sound, SoundKind, ALARM_CALL_VOLUME, FIRE_VOLUME,
+    };
+    use crate::uid::Uid;
+    use vek::*;
+
+    fn body_of(species: quadruped_medium::Species) -> Body {
+        Body::QuadrupedMedium(quadruped_medium::Body::random_with(
+            &mut rand::thread_rng(),
+            &species,
+        ))
+    }
 
     /// Test to verify that Behavior is working correctly at its most basic
     /// usages
@@ -669,6 +824,106 @@ mod tests {
         agent = agent.with_aggro_no_warn();
         assert_eq!(agent.psyche.aggro_dist, None);
     }
+
+    #[test]
+    fn herd_flees_from_explosion() {
+        let blast = Vec3::zero();
+        let explosion = Sound::new(SoundKind::Explosion, blast, 25.0, 0.0);
+        let deer = body_of(quadruped_medium::Species::Deer);
+        let wolf = body_of(quadruped_medium::Species::Wolf);
+
+        // Only the first deer is close enough to hear the explosion, the rest of
+        // the herd is warned by its alarm call
+        let herd_pos = [
+            Vec3::new(28.0, 0.0, 0.0),
+            Vec3::new(34.0, 4.0, 0.0),
+            Vec3::new(36.0, -4.0, 0.0),
+        ];
+        let mut herd = herd_pos.map(|_| Agent::from_body(&deer));
+        let mut wolf_agent = Agent::from_body(&wolf);
+
+        assert!(herd[0].startle(
+            &explosion.propagated_to(herd_pos[0]),
+            Some(&deer),
+            None,
+            0.0
+        ));
+        assert!(!wolf_agent.startle(
+            &explosion.propagated_to(herd_pos[0]),
+            Some(&wolf),
+            None,
+            0.0
+        ));
+
+        let alarm_call = Sound::new(
+            SoundKind::AlarmCall {
+                body: deer,
+                source: blast,
+            },
+            herd_pos[0],
+            ALARM_CALL_VOLUME,
+            0.1,
+        );
+        for (agent, pos) in herd.iter_mut().zip(herd_pos).skip(1) {
+            assert!(agent.startle(&alarm_call.propagated_to(pos), Some(&deer), None, 0.1));
+        }
+        assert!(!wolf_agent.startle(
+            &alarm_call.propagated_to(herd_pos[0]),
+            Some(&wolf),
+            None,
+            0.1
+        ));
+        assert!(!wolf_agent.is_startled(0.1));
+
+        // Every deer flees away from the blast, not from the deer that warned it
+        let flee_dirs = herd
+            .iter()
+            .zip(herd_pos)
+            .map(|(agent, pos)| {
+                let startle = agent.startled.unwrap();
+                assert_eq!(startle.source, blast);
+                assert!(agent.is_startled(1.0));
+                (pos - startle.source).normalized()
+            })
+            .collect::<Vec<_>>();
+        for dir in &flee_dirs {
+            assert!(dir.dot(flee_dirs[0]) > 0.9);
+            assert!(dir.x > 0.0);
+        }
+    }
+
+    #[test]
+    fn predators_are_only_startled_by_loud_events() {
+        let wolf = body_of(quadruped_medium::Species::Wolf);
+        let deer = body_of(quadruped_medium::Species::Deer);
+        let pos = Vec3::new(5.0, 0.0, 0.0);
+
+        let fire = Sound::new(SoundKind::Fire, Vec3::zero(), FIRE_VOLUME, 0.0).propagated_to(pos);
+        assert!(!Agent::from_body(&wolf).is_startled_by(&fire, Some(&wolf), None));
+        assert!(Agent::from_body(&deer).is_startled_by(&fire, Some(&deer), None));
+
+        let bomb = Sound::new(SoundKind::Explosion, Vec3::zero(), 75.0, 0.0).propagated_to(pos);
+        let mut wolf_agent = Agent::from_body(&wolf);
+        assert!(wolf_agent.startle(&bomb, Some(&wolf), None, 0.0));
+        // Being startled again only extends the fleeing
+        assert!(!wolf_agent.startle(&bomb, Some(&wolf), None, 1.0));
+        assert!(wolf_agent.is_startled(5.0));
+    }
+
+    #[test]
+    fn pets_are_not_startled() {
+        let deer = body_of(quadruped_medium::Species::Deer);
+        let pos = Vec3::new(5.0, 0.0, 0.0);
+        let bomb = Sound::new(SoundKind::Explosion, Vec3::zero(), 75.0, 0.0).propagated_to(pos);
+
+        let mut wild = Agent::from_body(&deer);
+        assert!(wild.startle(&bomb, Some(&deer), Some(&Alignment::Wild), 0.0));
+
+        let mut pet = Agent::from_body(&deer);
+        let owned = Alignment::Owned(Uid(1));
+        assert!(!pet.startle(&bomb, Some(&deer), Some(&owned), 0.0));
+        assert!(!pet.is_startled(0.0));
+    }
 }
 
 /// PID controllers are used for automatically adapting nonlinear controls (like
@@ -224,6 +224,19 @@ impl Body {
 
     pub fn is_campfire(&self) -> bool { matches!(self, Body::Object(object::Body::CampfireLit)) }
 
+    /// Whether characters can sit down on this
+    pub fn is_seat(&self) -> bool {
+        matches!(
+            self,
+            Body::Object(
+                object::Body::Bench
+                    | object::Body::Chair
+                    | object::Body::Chair2
+                    | object::Body::Chair3
+            )
+        )
+    }
+
     pub fn bleeds(&self) -> bool {
         !matches!(
             self,
@@ -64,6 +64,50 @@ impl Body {
             .min(self.species.num_accessories(self.body_type) - 1);
     }
 
+    /// The first feature of the body that doesn't exist for its species and
+    /// body type, with its value. Unlike [`Body::validate`], which clamps
+    /// them, this is for refusing bodies that can't have come from the
+    /// character creator.
+    pub fn invalid_feature(&self) -> Option<(&'static str, u8)> {
+        let (species, body_type) = (self.species, self.body_type);
+        [
+            (
+                "hair style",
+                self.hair_style,
+                species.num_hair_styles(body_type),
+            ),
+            ("beard", self.beard, species.num_beards(body_type)),
+            ("eyes", self.eyes, species.num_eyes(body_type)),
+            (
+                "accessory",
+                self.accessory,
+                species.num_accessories(body_type),
+            ),
+            ("hair color", self.hair_color, species.num_hair_colors()),
+            ("skin color", self.skin, species.num_skin_colors()),
+            ("eye color", self.eye_color, species.num_eye_colors()),
+        ]
+        .into_iter()
+        .find(|(_, value, count)| value >= count)
+        .map(|(feature, value, _)| (feature, value))
+    }
+
+    /// This body with the features of `other` that can still be changed after
+    /// a character was created, e.g. by a barber. The species and body type
+    /// of a character can't be changed.
+    pub fn with_appearance_of(self, other: &Self) -> Self {
+        Self {
+            hair_style: other.hair_style,
+            beard: other.beard,
+            eyes: other.eyes,
+            accessory: other.accessory,
+            hair_color: other.hair_color,
+            skin: other.skin,
+            eye_color: other.eye_color,
+            ..self
+        }
+    }
+
     pub fn height(&self) -> f32 { (20.0 / 9.0) * self.scaler() }
 
     pub fn scaler(&self) -> f32 {
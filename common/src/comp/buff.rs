@@ -1,5 +1,10 @@
 #![allow(clippy::nonstandard_macro_braces)] //tmp as of false positive !?
 use crate::uid::Uid;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::{
+    assets::{self, Asset, AssetExt, AssetHandle},
+    effect::{self, Effect},
+};
 use core::{cmp::Ordering, time::Duration};
 #[cfg(not(target_arch = "wasm32"))]
 use hashbrown::HashMap;
@@ -79,6 +84,9 @@ pub enum BuffKind {
     /// Drain stamina to a creature over time
     /// Strength should be the energy per second of the debuff
     Poisoned,
+    /// Reduces the healing of potions, see [`PotionSicknessConfig`]
+    /// Strength should be the number of stacks
+    PotionSickness,
 }
 
 #[cfg(not(target_arch = "wasm32"))]
@@ -103,12 +111,17 @@ impl BuffKind {
             | BuffKind::Frozen
             | BuffKind::Wet
             | BuffKind::Ensnared
-            | BuffKind::Poisoned => false,
+            | BuffKind::Poisoned
+            | BuffKind::PotionSickness => false,
         }
     }
 
     /// Checks if buff should queue
     pub fn queues(self) -> bool { matches!(self, BuffKind::Saturation) }
+
+    /// Checks if buffs of this kind add their strength to the existing buff,
+    /// instead of being applied next to it
+    pub fn stacks(self) -> bool { matches!(self, BuffKind::PotionSickness) }
 }
 
 // Struct used to store data relevant to a buff
@@ -366,6 +379,8 @@ impl Buff {
                 ],
                 data.duration,
             ),
+            // Only has an effect when consuming potions
+            BuffKind::PotionSickness => (Vec::new(), data.duration),
         };
         Buff {
             kind,
@@ -482,12 +497,30 @@ impl Buffs {
     }
 
     pub fn insert(&mut self, buff: Buff) -> BuffId {
+        if buff.kind.stacks() {
+            if let Some(id) = self.kinds.get(&buff.kind).and_then(|ids| ids.first()) {
+                if let Some(existing) = self.buffs.get_mut(id) {
+                    // Each stack restarts the duration of the whole buff
+                    existing.data.strength += buff.data.strength;
+                    existing.data.duration = buff.data.duration;
+                    existing.time = buff.time;
+                    return *id;
+                }
+            }
+        }
         self.id_counter += 1;
         self.force_insert(self.id_counter, buff)
     }
 
     pub fn contains(&self, kind: BuffKind) -> bool { self.kinds.contains_key(&kind) }
 
+    /// Number of stacks of a buff kind that [stacks](BuffKind::stacks)
+    pub fn stack_count(&self, kind: BuffKind) -> u32 {
+        self.iter_kind(kind)
+            .next()
+            .map_or(0, |(_, buff)| buff.data.strength.round() as u32)
+    }
+
     // Iterate through buffs of a given kind in effect order (most powerful first)
     pub fn iter_kind(&self, kind: BuffKind) -> impl Iterator<Item = (BuffId, &Buff)> + '_ {
         self.kinds
@@ -525,7 +558,189 @@ impl Buffs {
 
 pub type BuffId = u64;
 
+/// Balance values of [`BuffKind::PotionSickness`]. Each potion that is drunk
+/// adds a stack, and the healing of the next one is reduced by
+/// `reduction_per_stack` for every stack. All stacks wear off `duration`
+/// seconds after the last potion.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PotionSicknessConfig {
+    pub reduction_per_stack: f32,
+    pub duration: f64,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl PotionSicknessConfig {
+    /// Loaded whenever a potion is drunk, so that changes are picked up by
+    /// running servers
+    pub fn load() -> AssetHandle<Self> { Self::load_expect("common.potion_sickness") }
+
+    /// Fraction of the healing of a potion that is left with this many stacks
+    pub fn heal_multiplier(&self, stacks: u32) -> f32 {
+        (1.0 - self.reduction_per_stack)
+            .clamp(0.0, 1.0)
+            .powi(stacks as i32)
+    }
+
+    /// Reduces the healing effects of a potion. Other effects are kept as
+    /// they are.
+    pub fn reduce_healing(&self, effects: &mut [Effect], stacks: u32) {
+        let multiplier = self.heal_multiplier(stacks);
+        effects
+            .iter_mut()
+            .filter(|effect| effect.is_heal())
+            .for_each(|effect| effect.modify_strength(multiplier));
+    }
+
+    /// The effect adding a single stack of potion sickness
+    pub fn stack(&self) -> Effect {
+        Effect::Buff(effect::BuffEffect {
+            kind: BuffKind::PotionSickness,
+            data: BuffData::new(1.0, Some(Duration::from_secs_f64(self.duration))),
+            cat_ids: vec![BuffCategory::Natural],
+        })
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Asset for PotionSicknessConfig {
+    type Loader = assets::RonLoader;
+
+    const EXTENSION: &'static str = "ron";
+}
+
 #[cfg(not(target_arch = "wasm32"))]
 impl Component for Buffs {
     type Storage = DerefFlaggedStorage<Self, VecStorage<Self>>;
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{combat::DamageSource, comp::HealthChange, resources::Time};
+
+    const CONFIG: PotionSicknessConfig = PotionSicknessConfig {
+        reduction_per_stack: 0.25,
+        duration: 30.0,
+    };
+
+    fn potion(strength: f32) -> Vec<Effect> {
+        vec![Effect::Buff(effect::BuffEffect {
+            kind: BuffKind::Potion,
+            data: BuffData::new(strength, Some(Duration::from_secs(1))),
+            cat_ids: vec![BuffCategory::Natural],
+        })]
+    }
+
+    fn strength(effects: &[Effect]) -> f32 {
+        match &effects[0] {
+            Effect::Buff(buff) => buff.data.strength,
+            Effect::Health(change) => change.amount,
+            effect => panic!("Unexpected effect {:?}", effect),
+        }
+    }
+
+    /// Drinks a potion the way the server does, returning how much it heals
+    fn drink(buffs: &mut Buffs, mut effects: Vec<Effect>) -> f32 {
+        CONFIG.reduce_healing(&mut effects, buffs.stack_count(BuffKind::PotionSickness));
+        if let Effect::Buff(sickness) = CONFIG.stack() {
+            buffs.insert(Buff::new(
+                sickness.kind,
+                sickness.data,
+                sickness.cat_ids,
+                BuffSource::Item,
+            ));
+        }
+        strength(&effects)
+    }
+
+    #[test]
+    fn potions_heal_geometrically_less() {
+        let mut buffs = Buffs::default();
+        let healed = (0..4)
+            .map(|_| drink(&mut buffs, potion(64.0)))
+            .collect::<Vec<_>>();
+        assert_eq!(healed, vec![64.0, 48.0, 36.0, 27.0]);
+        assert_eq!(buffs.stack_count(BuffKind::PotionSickness), 4);
+        // All stacks share a single buff
+        assert_eq!(buffs.iter_kind(BuffKind::PotionSickness).count(), 1);
+    }
+
+    #[test]
+    fn potion_sickness_decays_between_fights() {
+        let mut buffs = Buffs::default();
+        drink(&mut buffs, potion(64.0));
+        let (id, _) = buffs.iter_kind(BuffKind::PotionSickness).next().unwrap();
+        buffs.buffs.get_mut(&id).unwrap().time = Some(Duration::from_secs(1));
+
+        // Another potion restarts the decay timer of all stacks
+        drink(&mut buffs, potion(64.0));
+        let (_, sickness) = buffs.iter_kind(BuffKind::PotionSickness).next().unwrap();
+        assert_eq!(
+            sickness.time,
+            Some(Duration::from_secs_f64(CONFIG.duration))
+        );
+
+        // Once the buff system removes the expired buff, potions heal fully again
+        buffs.remove(id);
+        assert_eq!(drink(&mut buffs, potion(64.0)), 64.0);
+    }
+
+    #[test]
+    fn only_healing_of_potions_is_reduced() {
+        let mut buffs = Buffs::default();
+        drink(&mut buffs, potion(64.0));
+
+        let heal = HealthChange {
+            amount: 20.0,
+            by: None,
+            cause: None,
+            time: Time(0.0),
+            crit: false,
+            instance: 0,
+        };
+        let mut effects = vec![
+            Effect::Health(heal),
+            Effect::Health(HealthChange {
+                amount: -20.0,
+                cause: Some(DamageSource::Other),
+                ..heal
+            }),
+            Effect::Poise(10.0),
+        ];
+        CONFIG.reduce_healing(&mut effects, buffs.stack_count(BuffKind::PotionSickness));
+        assert_eq!(
+            effects[0],
+            Effect::Health(HealthChange {
+                amount: 15.0,
+                ..heal
+            })
+        );
+        assert_eq!(effects[1..], [
+            Effect::Health(HealthChange {
+                amount: -20.0,
+                cause: Some(DamageSource::Other),
+                ..heal
+            }),
+            Effect::Poise(10.0),
+        ]);
+
+        // Healing from spells and regeneration is applied as a buff directly, and
+        // doesn't stack with or get reduced by potion sickness
+        let regeneration = Buff::new(
+            BuffKind::Regeneration,
+            BuffData::new(10.0, Some(Duration::from_secs(5))),
+            Vec::new(),
+            BuffSource::Character { by: Uid(1) },
+        );
+        let id = buffs.insert(regeneration);
+        assert_eq!(buffs.buffs[&id].data.strength, 10.0);
+        assert_eq!(buffs.stack_count(BuffKind::PotionSickness), 1);
+    }
+
+    #[test]
+    fn default_config_loads() {
+        let config = PotionSicknessConfig::load().read().clone();
+        assert!(config.heal_multiplier(1) < 1.0);
+        assert!(config.duration > 0.0);
+    }
+}
@@ -6,6 +6,7 @@ use crate::{
             slot::{EquipSlot, InvSlotId, Slot},
         },
         invite::{InviteKind, InviteResponse},
+        pet::PetCommand,
         BuffKind,
     },
     trade::{TradeAction, TradeId},
@@ -147,6 +148,10 @@ pub enum ControlEvent {
     PerformTradeAction(TradeId, TradeAction),
     Mount(Uid),
     Unmount,
+    /// Sits down on a seat
+    Sit(Uid),
+    /// Gets up from a seat
+    Stand,
     InventoryEvent(InventoryEvent),
     GroupManip(GroupManip),
     RemoveBuff(BuffKind),
@@ -157,6 +162,12 @@ pub enum ControlEvent {
         auxiliary_key: ability::AuxiliaryKey,
         new_ability: ability::AuxiliaryAbility,
     },
+    /// Gives a command to one of the pets of the entity, or to all of them if
+    /// `pet` is `None`
+    CommandPet {
+        pet: Option<Uid>,
+        command: PetCommand,
+    },
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
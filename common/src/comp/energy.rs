@@ -121,6 +121,16 @@ impl Energy {
     }
 
     pub fn refresh(&mut self) { self.current = self.maximum; }
+
+    /// Returns a copy of this energy which only reveals the current value
+    /// rounded up to `step` fractions of the maximum.
+    pub fn bucketed(&self, step: f32) -> Self {
+        let step = (self.maximum as f32 * step).max(1.0);
+        Energy {
+            current: (((self.current as f32 / step).ceil() * step) as u32).min(self.maximum),
+            ..*self
+        }
+    }
 }
 
 impl Component for Energy {
@@ -193,6 +193,37 @@ impl Health {
             .map(|(damage_contrib, (damage, _))| (damage_contrib, damage))
     }
 
+    /// Returns a copy of this health which only reveals the current value
+    /// rounded up to `step` fractions of the maximum, e.g. for syncing to
+    /// clients that should only be able to draw a rough health bar. Details of
+    /// the last change are dropped except for its cause and time.
+    pub fn bucketed(&self, step: f32) -> Self {
+        let step = (self.maximum as f32 * step).max(1.0);
+        Health {
+            current: (((self.current as f32 / step).ceil() * step) as u32).min(self.maximum),
+            base_max: self.base_max,
+            maximum: self.maximum,
+            last_change: HealthChange {
+                amount: 0.0,
+                by: None,
+                crit: false,
+                ..self.last_change
+            },
+            is_dead: self.is_dead,
+            damage_contributors: HashMap::new(),
+        }
+    }
+
+    /// Sets the current health to an amount that was persisted, clamped to the
+    /// maximum. Amounts that would leave the entity without health are
+    /// ignored, so that loading a character never kills it.
+    pub fn restore(&mut self, amount: f32) {
+        let amount = (amount * Self::SCALING_FACTOR_FLOAT).min(self.maximum as f32) as u32;
+        if amount > 0 {
+            self.current = amount;
+        }
+    }
+
     pub fn should_die(&self) -> bool { self.current == 0 }
 
     pub fn kill(&mut self) { self.current = 0; }
@@ -264,6 +295,20 @@ mod tests {
         assert_eq!(health_change.time, *time);
     }
 
+    #[test]
+    fn restored_health_is_clamped_to_maximum() {
+        let mut health = Health::empty();
+        health.maximum = 100 * Health::SCALING_FACTOR_INT;
+        health.current = health.maximum;
+
+        health.restore(40.0);
+        assert_eq!(health.current(), 40.0);
+        health.restore(250.0);
+        assert_eq!(health.current(), 100.0);
+        health.restore(0.0);
+        assert_eq!(health.current(), 100.0);
+    }
+
     #[test]
     fn test_change_by_positive_health_change_does_not_add_damage_contributor() {
         let mut health = Health::empty();
@@ -312,6 +357,36 @@ mod tests {
         assert_eq!(1, health.damage_contributors.len());
     }
 
+    #[test]
+    fn test_bucketed_rounds_up_to_step() {
+        let mut health = Health::empty();
+        health.maximum = 100 * Health::SCALING_FACTOR_INT;
+        health.current = health.maximum;
+        health.change_by(HealthChange {
+            amount: -33.0,
+            time: Time(1.0),
+            by: Some(DamageContributor::Solo(Uid(0))),
+            cause: None,
+            crit: true,
+            instance: rand::random(),
+        });
+
+        let bucketed = health.bucketed(0.1);
+        assert_eq!(bucketed.current(), 70.0);
+        assert_eq!(bucketed.maximum(), health.maximum());
+        assert_eq!(bucketed.last_change.amount, 0.0);
+        assert_eq!(bucketed.last_change.by, None);
+        assert!(!bucketed.last_change.crit);
+        assert_eq!(bucketed.last_change.time, Time(1.0));
+        assert_eq!(bucketed.damage_contributions().count(), 0);
+
+        // Barely alive entities don't look dead and dead ones stay dead
+        health.current = 1;
+        assert_eq!(health.bucketed(0.1).current(), 10.0);
+        health.kill();
+        assert_eq!(health.bucketed(0.1).current(), 0.0);
+    }
+
     #[test]
     fn test_change_by_damage_contributor_pruning() {
         let mut health = Health::empty();
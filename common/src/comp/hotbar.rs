@@ -0,0 +1,205 @@
+use crate::comp::{
+    ability::MAX_ABILITIES,
+    inventory::{
+        slot::{EquipSlot, InvSlotId},
+        Inventory,
+    },
+};
+use serde::{Deserialize, Serialize};
+use specs::{Component, VecStorage};
+
+pub const HOTBAR_SLOTS: usize = 10;
+
+/// What a hotbar slot is bound to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HotbarBinding {
+    /// An item, identified by its hash (see
+    /// [`crate::comp::Item::item_hash`]) so that the binding survives the item
+    /// being moved around in the inventory.
+    Item(u64),
+    /// An auxiliary ability, by its index in the active ability set.
+    Ability(usize),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HotbarError {
+    InvalidSlot,
+    /// The bound item isn't in the inventory (anymore).
+    ItemNotFound,
+    UnknownAbility,
+    /// Abilities are activated through the controller, not through the hotbar.
+    NotAnItem,
+    /// There is nothing bound to the slot.
+    Empty,
+}
+
+/// What activating a hotbar slot resolved to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HotbarAction {
+    Use(InvSlotId),
+    /// The bound weapon is in the inactive mainhand, so the weapons should be
+    /// swapped instead of using an item from the inventory.
+    SwapEquippedWeapons,
+}
+
+/// The hotbar of a character. The server validates bindings when they are
+/// set and again when they are activated, since the inventory may have
+/// changed in between.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Hotbar {
+    slots: [Option<HotbarBinding>; HOTBAR_SLOTS],
+}
+
+impl Hotbar {
+    pub fn slots(&self) -> &[Option<HotbarBinding>; HOTBAR_SLOTS] { &self.slots }
+
+    pub fn get(&self, slot: usize) -> Option<HotbarBinding> {
+        self.slots.get(slot).copied().flatten()
+    }
+
+    fn validate(binding: HotbarBinding, inventory: &Inventory) -> Result<(), HotbarError> {
+        match binding {
+            HotbarBinding::Item(hash) => {
+                let equipped = inventory
+                    .equipped(EquipSlot::InactiveMainhand)
+                    .map_or(false, |item| item.item_hash() == hash);
+                if equipped || inventory.get_slot_from_hash(hash).is_some() {
+                    Ok(())
+                } else {
+                    Err(HotbarError::ItemNotFound)
+                }
+            },
+            HotbarBinding::Ability(index) if index < MAX_ABILITIES => Ok(()),
+            HotbarBinding::Ability(_) => Err(HotbarError::UnknownAbility),
+        }
+    }
+
+    /// Sets the bindings sent by a client, dropping the ones that are not
+    /// valid. Returns the first error encountered, if any.
+    pub fn set_bindings(
+        &mut self,
+        bindings: [Option<HotbarBinding>; HOTBAR_SLOTS],
+        inventory: &Inventory,
+    ) -> Result<(), HotbarError> {
+        let mut result = Ok(());
+        for (slot, binding) in self.slots.iter_mut().zip(bindings) {
+            *slot = binding.filter(|binding| match Self::validate(*binding, inventory) {
+                Ok(()) => true,
+                Err(e) => {
+                    result = result.and(Err(e));
+                    false
+                },
+            });
+        }
+        result
+    }
+
+    /// Works out what activating a slot should do given the current state of
+    /// the inventory. Bindings to items that can't be found anymore are
+    /// cleared.
+    pub fn activate(
+        &mut self,
+        slot: usize,
+        inventory: &Inventory,
+    ) -> Result<HotbarAction, HotbarError> {
+        let binding = self.slots.get_mut(slot).ok_or(HotbarError::InvalidSlot)?;
+        match (*binding).ok_or(HotbarError::Empty)? {
+            HotbarBinding::Item(hash) => {
+                if inventory
+                    .equipped(EquipSlot::InactiveMainhand)
+                    .map_or(false, |item| item.item_hash() == hash)
+                {
+                    Ok(HotbarAction::SwapEquippedWeapons)
+                } else if let Some(inv_slot) = inventory.get_slot_from_hash(hash) {
+                    Ok(HotbarAction::Use(inv_slot))
+                } else {
+                    *binding = None;
+                    Err(HotbarError::ItemNotFound)
+                }
+            },
+            HotbarBinding::Ability(_) => Err(HotbarError::NotAnItem),
+        }
+    }
+}
+
+impl Component for Hotbar {
+    type Storage = VecStorage<Self>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::comp::Item;
+
+    fn inventory_with_potion() -> (Inventory, u64) {
+        let mut inventory = Inventory::with_empty();
+        let potion = Item::new_from_asset_expect("common.items.consumable.potion_minor");
+        let hash = potion.item_hash();
+        inventory.push(potion).unwrap();
+        (inventory, hash)
+    }
+
+    #[test]
+    fn invalid_bindings_are_dropped() {
+        let (inventory, hash) = inventory_with_potion();
+        let mut hotbar = Hotbar::default();
+        let mut bindings = [None; HOTBAR_SLOTS];
+        bindings[0] = Some(HotbarBinding::Item(hash));
+        bindings[1] = Some(HotbarBinding::Item(hash.wrapping_add(1)));
+        bindings[2] = Some(HotbarBinding::Ability(0));
+        bindings[3] = Some(HotbarBinding::Ability(MAX_ABILITIES));
+
+        assert_eq!(
+            hotbar.set_bindings(bindings, &inventory),
+            Err(HotbarError::ItemNotFound)
+        );
+        assert_eq!(hotbar.get(0), Some(HotbarBinding::Item(hash)));
+        assert_eq!(hotbar.get(1), None);
+        assert_eq!(hotbar.get(2), Some(HotbarBinding::Ability(0)));
+        assert_eq!(hotbar.get(3), None);
+    }
+
+    #[test]
+    fn item_moved_between_registration_and_use() {
+        let (mut inventory, hash) = inventory_with_potion();
+        let mut hotbar = Hotbar::default();
+        let mut bindings = [None; HOTBAR_SLOTS];
+        bindings[0] = Some(HotbarBinding::Item(hash));
+        hotbar.set_bindings(bindings, &inventory).unwrap();
+
+        let old_slot = inventory.get_slot_from_hash(hash).unwrap();
+        let new_slot = inventory.slots_with_id().nth(5).unwrap().0;
+        inventory.swap_slots(old_slot, new_slot);
+
+        assert_eq!(
+            hotbar.activate(0, &inventory),
+            Ok(HotbarAction::Use(new_slot))
+        );
+
+        // Once the item is gone, the binding is stale
+        inventory.remove(new_slot);
+        assert_eq!(
+            hotbar.activate(0, &inventory),
+            Err(HotbarError::ItemNotFound)
+        );
+        assert_eq!(hotbar.get(0), None);
+        assert_eq!(hotbar.activate(0, &inventory), Err(HotbarError::Empty));
+        assert_eq!(
+            hotbar.activate(HOTBAR_SLOTS, &inventory),
+            Err(HotbarError::InvalidSlot)
+        );
+    }
+
+    #[test]
+    fn survives_serialization() {
+        let (inventory, hash) = inventory_with_potion();
+        let mut hotbar = Hotbar::default();
+        let mut bindings = [None; HOTBAR_SLOTS];
+        bindings[4] = Some(HotbarBinding::Item(hash));
+        bindings[9] = Some(HotbarBinding::Ability(2));
+        hotbar.set_bindings(bindings, &inventory).unwrap();
+
+        let json = serde_json::to_string(&hotbar).unwrap();
+        assert_eq!(serde_json::from_str::<Hotbar>(&json).unwrap(), hotbar);
+    }
+}
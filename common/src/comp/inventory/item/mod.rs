@@ -9,7 +9,11 @@ pub use tool::{AbilitySet, AbilitySpec, Hands, Tool, ToolKind};
 
 use crate::{
     assets::{self, AssetExt, BoxedError, Error},
-    comp::inventory::{item::tool::AbilityMap, InvSlot},
+    comp::{
+        inventory::{item::tool::AbilityMap, InvSlot},
+        trap::TrapKind,
+        LightEmitter,
+    },
     effect::Effect,
     recipe::RecipeInput,
     terrain::Block,
@@ -24,7 +28,7 @@ use serde::{de, Deserialize, Serialize, Serializer};
 use specs::{Component, DenseVecStorage, DerefFlaggedStorage};
 use std::{borrow::Cow, collections::hash_map::DefaultHasher, fmt, sync::Arc};
 use strum::{EnumString, IntoStaticStr};
-use tracing::error;
+use tracing::{error, warn};
 use vek::Rgb;
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
@@ -48,6 +52,9 @@ pub enum Reagent {
 pub enum Utility {
     Coins,
     Collar,
+    Trap(TrapKind),
+    /// Refills the lantern of the character using it
+    LanternFuel,
 }
 
 #[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
@@ -55,12 +62,30 @@ pub struct Lantern {
     color: Rgb<u32>,
     strength_thousandths: u32,
     flicker_thousandths: u32,
+    #[serde(default = "default_burn_rate_thousandths")]
+    burn_rate_thousandths: u32,
 }
 
+fn default_burn_rate_thousandths() -> u32 { 1000 }
+
 impl Lantern {
     pub fn strength(&self) -> f32 { self.strength_thousandths as f32 / 1000_f32 }
 
+    /// Fuel burnt per second while the lantern is lit, see
+    /// [`crate::comp::LanternFuel`]
+    pub fn burn_rate(&self) -> f32 { self.burn_rate_thousandths as f32 / 1000_f32 }
+
     pub fn color(&self) -> Rgb<f32> { self.color.map(|c| c as f32 / 255.0) }
+
+    /// The light given off by the lantern while it is lit
+    pub fn light_emitter(&self) -> LightEmitter {
+        LightEmitter {
+            col: self.color(),
+            strength: self.strength(),
+            flicker: 0.35,
+            animated: true,
+        }
+    }
 }
 
 #[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize, Copy, PartialOrd, Ord)]
@@ -270,6 +295,10 @@ pub enum ItemKind {
     Consumable {
         kind: ConsumableKind,
         effects: Vec<Effect>,
+        /// Whether drinking this gives potion sickness, which reduces the
+        /// healing of the following consumables that give it
+        #[serde(default)]
+        potion_sickness: bool,
     },
     Throwable {
         kind: Throwable,
@@ -414,7 +443,18 @@ impl<'de> Deserialize<'de> for ItemBase {
                     if serialized_item_base.starts_with(crate::modular_item_id_prefix!()) {
                         ItemBase::Modular(ModularBase::load_from_pseudo_id(serialized_item_base))
                     } else {
-                        ItemBase::Simple(Arc::<ItemDef>::load_expect_cloned(serialized_item_base))
+                        ItemBase::Simple(
+                            Arc::<ItemDef>::load_cloned(serialized_item_base).unwrap_or_else(
+                                |err| {
+                                    warn!(
+                                        ?err,
+                                        "Unknown item {}, using a placeholder",
+                                        serialized_item_base
+                                    );
+                                    Arc::new(ItemDef::unknown_modded(serialized_item_base))
+                                },
+                            ),
+                        )
                     },
                 )
             }
@@ -600,6 +640,26 @@ impl ItemDef {
     // currently needed by trade_pricing
     pub fn id(&self) -> &str { &self.item_definition_id }
 
+    /// The placeholder for an item whose definition is neither in the local
+    /// assets nor was sent by the server, e.g. because this client doesn't
+    /// support custom items. It keeps the original id, so the item can still
+    /// be sent back to the server.
+    pub fn unknown_modded(item_definition_id: &str) -> Self {
+        Self {
+            item_definition_id: item_definition_id.to_owned(),
+            name: "Unknown Modded Item".to_owned(),
+            description: "An item added by this server that your client doesn't know about."
+                .to_owned(),
+            kind: ItemKind::Ingredient {
+                descriptor: "Unknown".to_owned(),
+            },
+            quality: Quality::Common,
+            tags: Vec::new(),
+            slots: 0,
+            ability_spec: None,
+        }
+    }
+
     #[cfg(test)]
     pub fn new_test(
         item_definition_id: String,
@@ -1253,6 +1313,37 @@ pub fn try_all_item_defs() -> Result<Vec<String>, Error> {
     Ok(defs.ids().map(|id| id.to_owned()).collect())
 }
 
+/// Loads the definitions of all items in an asset directory and its
+/// subdirectories
+pub fn load_item_defs_in_dir(specifier: &str) -> Result<Vec<Arc<ItemDef>>, Error> {
+    let defs = assets::load_dir::<RawItemDef>(specifier, true)?;
+    defs.ids().map(Arc::<ItemDef>::load_cloned).collect()
+}
+
+/// Hash of the custom items last registered with [`register_custom_items`].
+/// It outlives the connection, so that reconnecting to the same server doesn't
+/// transfer them again.
+static CUSTOM_ITEMS_HASH: AtomicCell<Option<u64>> = AtomicCell::new(None);
+
+/// Registers item definitions sent by the server for the rest of the session,
+/// so that items with their ids resolve instead of becoming
+/// [unknown modded items](ItemDef::unknown_modded). Items that already
+/// resolve, from the local assets or an earlier registration, are kept as they
+/// are.
+pub fn register_custom_items(hash: u64, defs: Vec<ItemDef>) {
+    for def in defs {
+        let id = def.item_definition_id.clone();
+        if id.starts_with("veloren.core.") || Arc::<ItemDef>::load(&id).is_ok() {
+            continue;
+        }
+        Arc::<ItemDef>::get_or_insert(&id, Arc::new(def));
+    }
+    CUSTOM_ITEMS_HASH.store(Some(hash));
+}
+
+/// The hash of the custom items that were registered, if any
+pub fn registered_custom_items_hash() -> Option<u64> { CUSTOM_ITEMS_HASH.load() }
+
 #[cfg(test)]
 mod tests {
     use super::*;
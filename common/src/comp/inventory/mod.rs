@@ -15,7 +15,7 @@ use crate::{
         },
         loot_owner::LootOwnerKind,
         slot::{InvSlotId, SlotId},
-        Item,
+        Item, LightEmitter,
     },
     uid::Uid,
     LoadoutBuilder,
@@ -124,6 +124,17 @@ impl Inventory {
         self.slots.iter_mut().chain(self.loadout.inv_slots_mut())
     }
 
+    /// Replaces the items in inventory slots (including the slots of bags) for
+    /// which `replace` returns a replacement. Equipped items are kept as they
+    /// are.
+    pub fn replace_items(&mut self, mut replace: impl FnMut(&Item) -> Option<Item>) {
+        for slot in self.slots_mut() {
+            if let Some(replacement) = slot.as_ref().and_then(&mut replace) {
+                *slot = Some(replacement);
+            }
+        }
+    }
+
     /// An iterator of all inventory slots and their position
     pub fn slots_with_id(&self) -> impl Iterator<Item = (InvSlotId, &InvSlot)> {
         self.slots
@@ -390,6 +401,25 @@ impl Inventory {
         self.loadout.equipped(equip_slot)
     }
 
+    /// The light of the lantern in the lantern slot, if one is equipped
+    pub fn lantern_light(&self) -> Option<LightEmitter> {
+        self.equipped(EquipSlot::Lantern)
+            .and_then(|item| match &*item.kind() {
+                ItemKind::Lantern(lantern) => Some(lantern.light_emitter()),
+                _ => None,
+            })
+    }
+
+    /// The fuel burnt per second by the lantern in the lantern slot, if one is
+    /// equipped
+    pub fn lantern_burn_rate(&self) -> Option<f32> {
+        self.equipped(EquipSlot::Lantern)
+            .and_then(|item| match &*item.kind() {
+                ItemKind::Lantern(lantern) => Some(lantern.burn_rate()),
+                _ => None,
+            })
+    }
+
     pub fn loadout_items_with_persistence_key(
         &self,
     ) -> impl Iterator<Item = (&str, Option<&Item>)> {
@@ -1039,7 +1039,7 @@ impl TradePricing {
                     format!("{:.4}", t.stats.power * t.stats.speed * p),
                     "dps/val",
                 )
-            } else if let ItemKind::Consumable { kind: _, effects } = &*i.kind() {
+            } else if let ItemKind::Consumable { effects, .. } = &*i.kind() {
                 (
                     effects
                         .iter()
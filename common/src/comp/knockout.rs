@@ -0,0 +1,132 @@
+use crate::{
+    comp::{
+        item::tool::ToolKind, pet::is_tameable, Agent, Alignment, Body, Buff, BuffCategory,
+        BuffData, BuffKind, BuffSource, HealthChange,
+    },
+    resources::Time,
+    uid::Uid,
+};
+use specs::{Component, VecStorage};
+use std::time::Duration;
+
+/// Fraction of its maximum health below which non-lethal damage knocks out a
+/// creature instead of hurting it further.
+pub const KNOCKOUT_THRESHOLD: f32 = 0.2;
+/// How long (in seconds) a creature stays knocked out before waking up.
+pub const KNOCKOUT_DURATION: f64 = 30.0;
+/// How long a creature that woke up without being captured stays enraged.
+pub const ENRAGE_DURATION: Duration = Duration::from_secs(20);
+pub const ENRAGE_STRENGTH: f32 = 0.5;
+
+/// Whether a creature can be knocked out and captured. Only hostile creatures
+/// are, wild ones can be tamed directly with a collar.
+pub fn is_capturable(body: &Body, alignment: &Alignment, is_player: bool) -> bool {
+    !is_player && matches!(alignment, Alignment::Enemy) && is_tameable(body)
+}
+
+/// Whether melee hits with a weapon of this kind are non-lethal, i.e. knock
+/// out capturable creatures instead of killing them.
+pub fn is_nonlethal_weapon(kind: ToolKind) -> bool { matches!(kind, ToolKind::Hammer) }
+
+/// Clamps a non-lethal health change so that it never kills, returning the
+/// clamped change and whether it brought the creature below
+/// [`KNOCKOUT_THRESHOLD`].
+pub fn apply_nonlethal(
+    current: f32,
+    maximum: f32,
+    mut change: HealthChange,
+    nonlethal: bool,
+) -> (HealthChange, bool) {
+    if !nonlethal || change.amount >= 0.0 || current + change.amount >= maximum * KNOCKOUT_THRESHOLD
+    {
+        return (change, false);
+    }
+    change.amount = change.amount.max(1.0 - current).min(0.0);
+    (change, true)
+}
+
+/// A creature that was knocked out by non-lethal damage. It can't act until it
+/// wakes up, and can be captured with a collar by whoever knocked it out in
+/// the meantime.
+#[derive(Clone, Debug)]
+pub struct KnockedOut {
+    pub by: Uid,
+    pub until: Time,
+    /// The agent of the creature, put back when it wakes up
+    pub agent: Option<Agent>,
+}
+
+impl KnockedOut {
+    pub fn new(by: Uid, agent: Option<Agent>, time: Time) -> Self {
+        Self {
+            by,
+            until: Time(time.0 + KNOCKOUT_DURATION),
+            agent,
+        }
+    }
+
+    pub fn has_woken(&self, time: Time) -> bool { time.0 >= self.until.0 }
+
+    /// The buff given to a creature that woke up without being captured.
+    pub fn enrage() -> Buff {
+        Buff::new(
+            BuffKind::Hastened,
+            BuffData::new(ENRAGE_STRENGTH, Some(ENRAGE_DURATION)),
+            vec![BuffCategory::Physical],
+            BuffSource::World,
+        )
+    }
+}
+
+impl Component for KnockedOut {
+    type Storage = VecStorage<Self>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::combat::DamageSource;
+
+    fn hit(amount: f32) -> HealthChange {
+        HealthChange {
+            amount,
+            by: None,
+            cause: Some(DamageSource::Melee),
+            time: Time(0.0),
+            crit: false,
+            instance: 0,
+        }
+    }
+
+    #[test]
+    fn nonlethal_hit_knocks_out_instead_of_killing() {
+        let (change, knocked_out) = apply_nonlethal(30.0, 100.0, hit(-50.0), true);
+        assert!(knocked_out);
+        assert_eq!(change.amount, -29.0);
+
+        // The same hit from a lethal weapon is left alone
+        let (change, knocked_out) = apply_nonlethal(30.0, 100.0, hit(-50.0), false);
+        assert!(!knocked_out);
+        assert_eq!(change.amount, -50.0);
+    }
+
+    #[test]
+    fn hit_above_threshold_does_not_knock_out() {
+        let (change, knocked_out) = apply_nonlethal(100.0, 100.0, hit(-80.0), true);
+        assert!(!knocked_out);
+        assert_eq!(change.amount, -80.0);
+
+        // Healing a creature that is already below the threshold is unchanged
+        let (change, knocked_out) = apply_nonlethal(5.0, 100.0, hit(10.0), true);
+        assert!(!knocked_out);
+        assert_eq!(change.amount, 10.0);
+    }
+
+    #[test]
+    fn wakes_up_enraged() {
+        let knocked_out = KnockedOut::new(Uid(1), None, Time(10.0));
+        assert!(!knocked_out.has_woken(Time(10.0 + KNOCKOUT_DURATION - 0.1)));
+        assert!(knocked_out.has_woken(Time(10.0 + KNOCKOUT_DURATION)));
+        assert_eq!(KnockedOut::enrage().kind, BuffKind::Hastened);
+    }
+}
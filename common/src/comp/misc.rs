@@ -1,5 +1,5 @@
-use super::item::Reagent;
-use crate::uid::Uid;
+use super::{item::Reagent, Admin, Agent, Buffs, Item, Pos, Waypoint};
+use crate::{character::CharacterId, resources::Time, uid::Uid};
 use serde::{Deserialize, Serialize};
 use specs::Component;
 
@@ -17,3 +17,146 @@ pub enum Object {
 impl Component for Object {
     type Storage = specs::VecStorage<Self>;
 }
+
+/// Stored on an entity that is possessed by a player, to be able to give the
+/// player back control of the entity they possessed it from.
+#[derive(Clone, Debug)]
+pub struct Possessing {
+    pub original: Uid,
+    /// The agent of the possessed entity, taken away while the player controls
+    /// it so that the entity can go back to what it was doing afterwards.
+    pub agent: Option<Box<Agent>>,
+    /// The inactive weapon of the possessed entity, which had to make room for
+    /// its active weapon when the possess item was equipped.
+    pub displaced_item: Option<Item>,
+    /// Components of the possessed entity that were replaced by the ones of
+    /// the player, to be given back once the player leaves.
+    pub displaced_components: DisplacedComponents,
+    /// When the player is returned to the entity they possessed this one from,
+    /// if possession is time limited.
+    pub expires_at: Option<Time>,
+    /// The health and energy of the possessed entity, which is given full
+    /// health and energy for the player to start out with, and gets these back
+    /// when the player leaves it alive.
+    pub health: Option<f32>,
+    pub energy: Option<f32>,
+}
+
+impl Component for Possessing {
+    type Storage = specs::VecStorage<Self>;
+}
+
+/// Stored on the character a player possessed another entity from. The
+/// character is taken out of the world until the player returns to it: without
+/// a position it can't be seen, moved or hurt, and without the player's
+/// presence it isn't persisted.
+#[derive(Clone, Debug)]
+pub struct PossessedFrom {
+    pub character_id: CharacterId,
+    pub pos: Pos,
+    /// Taken off as well, so that e.g. burning doesn't go on while the player
+    /// is away.
+    pub buffs: Option<Buffs>,
+}
+
+impl Component for PossessedFrom {
+    type Storage = specs::VecStorage<Self>;
+}
+
+/// Components an entity had of its own before a player was moved onto it (see
+/// [`Possessing`])
+#[derive(Clone, Debug, Default)]
+pub struct DisplacedComponents {
+    pub admin: Option<Admin>,
+    pub waypoint: Option<Waypoint>,
+}
+
+/// Marks the character of a player as hardcore, so that it is gone for good
+/// once it dies instead of respawning.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Hardcore;
+
+impl Component for Hardcore {
+    type Storage = specs::NullStorage<Self>;
+}
+
+/// Spares an entity from fall damage until `until`, e.g. a rider thrown off a
+/// mount that died under them
+#[derive(Clone, Copy, Debug)]
+pub struct FallImmunity {
+    pub until: Time,
+}
+
+impl Component for FallImmunity {
+    type Storage = specs::DenseVecStorage<Self>;
+}
+
+/// A character sitting on a seat (see [`super::Body::is_seat`]). They get up
+/// once they leave the sitting state or the seat.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Sitting {
+    pub seat: Uid,
+}
+
+impl Component for Sitting {
+    type Storage = specs::VecStorage<Self>;
+}
+
+/// The fuel left in the lantern of a character, which burns while the lantern
+/// is lit (see [`super::item::Lantern::burn_rate`]). Entities without it, like
+/// NPCs, never run out.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LanternFuel {
+    pub remaining: f32,
+}
+
+impl LanternFuel {
+    /// Fuel in a full lantern, enough to burn for half an hour at the standard
+    /// rate
+    pub const FULL: f32 = 1800.0;
+
+    pub fn full() -> Self {
+        Self {
+            remaining: Self::FULL,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool { self.remaining <= 0.0 }
+
+    pub fn is_full(&self) -> bool { self.remaining >= Self::FULL }
+
+    /// Burns the fuel used in `dt` seconds at `rate`, returns whether the
+    /// lantern ran out
+    pub fn burn(&mut self, rate: f32, dt: f32) -> bool {
+        self.remaining = (self.remaining - rate * dt).max(0.0);
+        self.is_empty()
+    }
+}
+
+impl Component for LanternFuel {
+    type Storage = specs::VecStorage<Self>;
+}
+
+/// Allows any player to mount the entity, not just its owner
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Mountable;
+
+impl Component for Mountable {
+    type Storage = specs::NullStorage<Self>;
+}
+
+/// Small per-character choices that are persisted with the character but don't
+/// need their own table. Fields have defaults, so that settings saved by other
+/// versions of the server still load.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CharacterSettings {
+    /// Title shown with the name of the character
+    pub title: Option<String>,
+    /// Icon of the waypoint of the character on the map
+    pub waypoint_icon: Option<String>,
+}
+
+impl Component for CharacterSettings {
+    type Storage = specs::VecStorage<Self>;
+}
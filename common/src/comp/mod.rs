@@ -22,11 +22,15 @@ pub mod dialogue;
 pub mod fluid_dynamics;
 #[cfg(not(target_arch = "wasm32"))] pub mod group;
 mod health;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod hotbar;
 #[cfg(not(target_arch = "wasm32"))] mod inputs;
 #[cfg(not(target_arch = "wasm32"))]
 pub mod inventory;
 #[cfg(not(target_arch = "wasm32"))]
 pub mod invite;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod knockout;
 #[cfg(not(target_arch = "wasm32"))] mod last;
 #[cfg(not(target_arch = "wasm32"))] mod location;
 pub mod loot_owner;
@@ -45,6 +49,9 @@ pub mod shockwave;
 pub mod skillset;
 #[cfg(not(target_arch = "wasm32"))] mod stats;
 #[cfg(not(target_arch = "wasm32"))]
+pub mod training_dummy;
+#[cfg(not(target_arch = "wasm32"))] pub mod trap;
+#[cfg(not(target_arch = "wasm32"))]
 pub mod visual;
 
 // Reexports
@@ -80,6 +87,7 @@ pub use self::{
     energy::Energy,
     fluid_dynamics::Fluid,
     group::Group,
+    hotbar::{Hotbar, HotbarBinding, HotbarError},
     inputs::CanBuild,
     inventory::{
         item::{
@@ -90,11 +98,15 @@ pub use self::{
         },
         slot, CollectFailedReason, Inventory, InventoryUpdate, InventoryUpdateEvent,
     },
+    knockout::KnockedOut,
     last::Last,
     location::{MapMarker, MapMarkerChange, MapMarkerUpdate, Waypoint, WaypointArea},
     loot_owner::LootOwner,
     melee::{Melee, MeleeConstructor},
-    misc::Object,
+    misc::{
+        CharacterSettings, DisplacedComponents, FallImmunity, Hardcore, LanternFuel, Mountable,
+        Object, PossessedFrom, Possessing, Sitting,
+    },
     ori::Ori,
     pet::Pet,
     phys::{
@@ -111,6 +123,8 @@ pub use self::{
         SkillGroup, SkillGroupKind, SkillSet,
     },
     stats::{Stats, StatsModifier},
+    training_dummy::TrainingDummy,
+    trap::{Trap, TrapKind},
     visual::{LightAnimation, LightEmitter},
 };
 
@@ -1,14 +1,51 @@
-use crate::comp::{body::Body, phys::Mass, quadruped_low, quadruped_medium, quadruped_small};
+use crate::{
+    assets::{self, Asset, AssetExt, AssetHandle},
+    comp::{body::Body, quadruped_low, quadruped_medium, quadruped_small, ship},
+    uid::Uid,
+};
 use crossbeam_utils::atomic::AtomicCell;
+use serde::{Deserialize, Serialize};
 use specs::Component;
 use std::{num::NonZeroU64, sync::Arc};
+use strum::{Display, EnumString};
+use vek::Vec3;
 
 pub type PetId = AtomicCell<Option<NonZeroU64>>;
 
+/// When a pet starts fighting on its own
+#[derive(Copy, Clone, Debug, Display, EnumString, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PetStance {
+    /// Never starts a fight
+    Passive,
+    /// Fights whoever attacks it or its owner
+    Defensive,
+    /// Also attacks any enemy it notices
+    Aggressive,
+}
+
+impl Default for PetStance {
+    fn default() -> Self { Self::Aggressive }
+}
+
+/// Commands an owner can give to their pets
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum PetCommand {
+    /// Hold the current position
+    Stay,
+    /// Follow the owner around, which is what pets do by default
+    Follow,
+    /// Attack the target of the owner
+    Attack(Uid),
+    SetStance(PetStance),
+}
+
 // TODO: move to server crate
 #[derive(Clone, Debug)]
 pub struct Pet {
     database_id: Arc<PetId>,
+    pub stance: PetStance,
+    /// Where the pet was told to stay, if it was
+    pub stay_at: Option<Vec3<f32>>,
 }
 
 impl Pet {
@@ -18,9 +55,11 @@ impl Pet {
     #[doc(hidden)]
     pub fn get_database_id(&self) -> Arc<PetId> { Arc::clone(&self.database_id) }
 
-    pub fn new_from_database(database_id: NonZeroU64) -> Self {
+    pub fn new_from_database(database_id: NonZeroU64, stance: PetStance) -> Self {
         Self {
             database_id: Arc::new(AtomicCell::new(Some(database_id))),
+            stance,
+            stay_at: None,
         }
     }
 }
@@ -29,6 +68,8 @@ impl Default for Pet {
     fn default() -> Self {
         Self {
             database_id: Arc::new(AtomicCell::new(None)),
+            stance: PetStance::default(),
+            stay_at: None,
         }
     }
 }
@@ -57,48 +98,124 @@ pub fn is_tameable(body: &Body) -> bool {
     }
 }
 
-pub fn is_mountable(mount: &Body, rider: Option<&Body>) -> bool {
-    let is_light_enough =
-        |rider: Option<&Body>| -> bool { rider.map_or(false, |b| b.mass() <= Mass(500.0)) };
-
-    match mount {
-        Body::QuadrupedMedium(body) => match body.species {
-            quadruped_medium::Species::Alpaca
-            | quadruped_medium::Species::Antelope
-            | quadruped_medium::Species::Bear
-            | quadruped_medium::Species::Camel
-            | quadruped_medium::Species::Cattle
-            | quadruped_medium::Species::Deer
-            | quadruped_medium::Species::Donkey
-            | quadruped_medium::Species::Highland
-            | quadruped_medium::Species::Horse
-            | quadruped_medium::Species::Kelpie
-            | quadruped_medium::Species::Llama
-            | quadruped_medium::Species::Moose
-            | quadruped_medium::Species::Tuskram
-            | quadruped_medium::Species::Yak
-            | quadruped_medium::Species::Zebra => true,
-            quadruped_medium::Species::Mouflon => is_light_enough(rider),
-            _ => false,
-        },
-        Body::QuadrupedSmall(body) => match body.species {
-            quadruped_small::Species::Truffler => true,
-            quadruped_small::Species::Boar | quadruped_small::Species::Holladon => {
-                is_light_enough(rider)
+/// A species or vehicle that can be ridden, as listed in
+/// `common.mountable_bodies`
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MountKind {
+    QuadrupedMedium(quadruped_medium::Species),
+    QuadrupedSmall(quadruped_small::Species),
+    QuadrupedLow(quadruped_low::Species),
+    Ship(ship::Body),
+}
+
+impl MountKind {
+    pub fn matches(&self, body: &Body) -> bool {
+        match (self, body) {
+            (Self::QuadrupedMedium(species), Body::QuadrupedMedium(body)) => {
+                body.species == *species
             },
+            (Self::QuadrupedSmall(species), Body::QuadrupedSmall(body)) => body.species == *species,
+            (Self::QuadrupedLow(species), Body::QuadrupedLow(body)) => body.species == *species,
+            (Self::Ship(kind), Body::Ship(body)) => body == kind,
             _ => false,
-        },
-        Body::QuadrupedLow(body) => matches!(
-            body.species,
-            quadruped_low::Species::Salamander | quadruped_low::Species::Tortoise
-        ),
-        _ => false,
+        }
+    }
+}
+
+/// Which bodies can be ridden, and by whom
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MountableBodies {
+    /// Bodies that can carry any rider
+    pub mounts: Vec<MountKind>,
+    /// Bodies that can only carry riders up to `max_light_rider_mass`
+    pub light_mounts: Vec<MountKind>,
+    pub max_light_rider_mass: f32,
+    /// Any other quadruped can be ridden if it is at least this many times as
+    /// heavy as its rider
+    pub min_mass_ratio: Option<f32>,
+}
+
+impl MountableBodies {
+    /// Loaded whenever mounting is checked, so that changes are picked up by
+    /// running servers
+    pub fn load() -> AssetHandle<Self> { Self::load_expect("common.mountable_bodies") }
+
+    pub fn can_ride(&self, mount: &Body, rider: Option<&Body>) -> bool {
+        let rider_mass = rider.map(|rider| rider.mass().0);
+        if self.mounts.iter().any(|kind| kind.matches(mount)) {
+            true
+        } else if self.light_mounts.iter().any(|kind| kind.matches(mount)) {
+            rider_mass.map_or(false, |mass| mass <= self.max_light_rider_mass)
+        } else if matches!(
+            mount,
+            Body::QuadrupedMedium(_) | Body::QuadrupedSmall(_) | Body::QuadrupedLow(_)
+        ) {
+            self.min_mass_ratio
+                .zip(rider_mass)
+                .map_or(false, |(ratio, mass)| mount.mass().0 >= mass * ratio)
+        } else {
+            false
+        }
     }
 }
 
+impl Asset for MountableBodies {
+    type Loader = assets::RonLoader;
+
+    const EXTENSION: &'static str = "ron";
+}
+
+/// Whether `rider` can ride an entity with the body `mount`, see
+/// [`MountableBodies`]
+pub fn is_mountable(mount: &Body, rider: Option<&Body>) -> bool {
+    MountableBodies::load().read().can_ride(mount, rider)
+}
+
+/// How fast a mount carries its rider, and for how long it can keep it up
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MountStats {
+    /// Multiplier of the acceleration of the mount while it is ridden
+    pub speed: f32,
+    /// How many seconds the mount can run for, `None` if it never tires
+    pub stamina: Option<f32>,
+    /// Seconds of running recovered per second the mount doesn't run
+    pub recovery: f32,
+}
+
+/// How all mounts carry their riders, see [`MountStats`]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MountStatsManifest {
+    /// Stats of mounts that aren't listed in `mounts`
+    pub default: MountStats,
+    pub mounts: Vec<(MountKind, MountStats)>,
+    /// Multiplier of the acceleration of exhausted mounts, which can only walk
+    pub exhausted_speed: f32,
+    /// Fraction of its stamina an exhausted mount has to recover before it can
+    /// run again
+    pub rested_fraction: f32,
+}
+
+impl MountStatsManifest {
+    /// Only loaded when an entity is mounted, so that changes are picked up by
+    /// running servers
+    pub fn load() -> AssetHandle<Self> { Self::load_expect("common.mount_stats") }
+
+    pub fn stats(&self, mount: &Body) -> MountStats {
+        self.mounts
+            .iter()
+            .find(|(kind, _)| kind.matches(mount))
+            .map_or(self.default, |(_, stats)| *stats)
+    }
+}
+
+impl Asset for MountStatsManifest {
+    type Loader = assets::RonLoader;
+
+    const EXTENSION: &'static str = "ron";
+}
+
 impl Component for Pet {
-    // Using `DenseVecStorage` has a u64 space overhead per entity and `Pet` just
-    // has an `Arc` pointer which is the same size on 64-bit platforms. So it
-    // isn't worth using `DenseVecStorage` here.
-    type Storage = specs::VecStorage<Self>;
+    // Few entities are pets and `Pet` is larger than the u64 index overhead of
+    // `DenseVecStorage`, so it saves space over `VecStorage`.
+    type Storage = specs::DenseVecStorage<Self>;
 }
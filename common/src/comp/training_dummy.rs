@@ -0,0 +1,158 @@
+use crate::{combat::DamageSource, resources::Time, uid::Uid};
+use hashbrown::HashMap;
+use specs::{Component, VecStorage};
+
+/// How long (in seconds) an attacker has to stop hitting a training dummy for
+/// before they get a report of the damage they dealt.
+pub const REPORT_DELAY: f64 = 4.0;
+/// Lower bound of the duration used to compute the DPS, so that a single hit
+/// isn't reported as an infinite DPS.
+pub const MIN_REPORT_DURATION: f64 = 1.0;
+
+/// The damage an attacker dealt to a training dummy over a series of hits.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DamageReport {
+    pub total: f32,
+    /// Seconds between the first and the last hit.
+    pub duration: f64,
+    /// Damage dealt by each source, highest first.
+    pub by_source: Vec<(DamageSource, f32)>,
+}
+
+impl DamageReport {
+    pub fn dps(&self) -> f32 { self.total / self.duration.max(MIN_REPORT_DURATION) as f32 }
+}
+
+#[derive(Clone, Debug)]
+struct DamageTracker {
+    first_hit: Time,
+    last_hit: Time,
+    by_source: HashMap<DamageSource, f32>,
+}
+
+impl DamageTracker {
+    fn report(self) -> DamageReport {
+        let mut by_source = self.by_source.into_iter().collect::<Vec<_>>();
+        by_source.sort_by(|(_, a), (_, b)| b.partial_cmp(a).unwrap_or(std::cmp::Ordering::Equal));
+        DamageReport {
+            total: by_source.iter().map(|(_, damage)| damage).sum(),
+            duration: self.last_hit.0 - self.first_hit.0,
+            by_source,
+        }
+    }
+}
+
+/// An entity that can't be killed and tells whoever hits it how much damage
+/// they dealt. Every attacker is tracked separately.
+#[derive(Clone, Debug, Default)]
+pub struct TrainingDummy {
+    trackers: HashMap<Uid, DamageTracker>,
+}
+
+impl TrainingDummy {
+    /// Records damage dealt by an attacker, as it came out of the damage
+    /// pipeline (so after damage reduction).
+    pub fn record(&mut self, attacker: Uid, damage: f32, source: Option<DamageSource>, time: Time) {
+        let tracker = self.trackers.entry(attacker).or_insert(DamageTracker {
+            first_hit: time,
+            last_hit: time,
+            by_source: HashMap::new(),
+        });
+        tracker.last_hit = time;
+        *tracker
+            .by_source
+            .entry(source.unwrap_or(DamageSource::Other))
+            .or_default() += damage;
+    }
+
+    /// Takes the reports of the attackers that stopped hitting the dummy for
+    /// at least [`REPORT_DELAY`].
+    pub fn take_reports(&mut self, time: Time) -> Vec<(Uid, DamageReport)> {
+        let finished = self
+            .trackers
+            .iter()
+            .filter(|(_, tracker)| time.0 - tracker.last_hit.0 >= REPORT_DELAY)
+            .map(|(attacker, _)| *attacker)
+            .collect::<Vec<_>>();
+        finished
+            .into_iter()
+            .filter_map(|attacker| {
+                self.trackers
+                    .remove(&attacker)
+                    .map(|tracker| (attacker, tracker.report()))
+            })
+            .collect()
+    }
+}
+
+impl Component for TrainingDummy {
+    type Storage = VecStorage<Self>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::comp::BuffKind;
+
+    const ATTACKER: Uid = Uid(1);
+    const OTHER_ATTACKER: Uid = Uid(2);
+
+    #[test]
+    fn scripted_attack_sequence() {
+        let mut dummy = TrainingDummy::default();
+
+        // Three sword strikes, one of which sets the dummy on fire for three ticks
+        let mut ground_truth = 0.0;
+        for (time, damage, source) in [
+            (0.0, 20.0, DamageSource::Melee),
+            (1.0, 25.0, DamageSource::Melee),
+            (1.5, 3.0, DamageSource::Buff(BuffKind::Burning)),
+            (2.0, 20.0, DamageSource::Melee),
+            (2.5, 3.0, DamageSource::Buff(BuffKind::Burning)),
+            (3.5, 3.0, DamageSource::Buff(BuffKind::Burning)),
+        ] {
+            dummy.record(ATTACKER, damage, Some(source), Time(time));
+            ground_truth += damage;
+        }
+
+        // No report while the attacker is still hitting
+        assert!(dummy
+            .take_reports(Time(3.5 + REPORT_DELAY - 0.1))
+            .is_empty());
+
+        let reports = dummy.take_reports(Time(3.5 + REPORT_DELAY));
+        assert_eq!(reports.len(), 1);
+        let (attacker, report) = &reports[0];
+        assert_eq!(*attacker, ATTACKER);
+        assert_eq!(report.total, ground_truth);
+        assert_eq!(report.duration, 3.5);
+        assert!((report.dps() - ground_truth / 3.5).abs() < f32::EPSILON);
+        assert_eq!(report.by_source, vec![
+            (DamageSource::Melee, 65.0),
+            (DamageSource::Buff(BuffKind::Burning), 9.0),
+        ]);
+
+        // The attacker was reported, so the next hit starts a new series
+        assert!(dummy.take_reports(Time(100.0)).is_empty());
+    }
+
+    #[test]
+    fn attackers_are_tracked_independently() {
+        let mut dummy = TrainingDummy::default();
+        dummy.record(ATTACKER, 10.0, Some(DamageSource::Projectile), Time(0.0));
+        dummy.record(OTHER_ATTACKER, 50.0, None, Time(0.0));
+        dummy.record(OTHER_ATTACKER, 50.0, None, Time(5.0));
+
+        let reports = dummy.take_reports(Time(REPORT_DELAY));
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0].0, ATTACKER);
+        // A single hit doesn't divide by zero
+        assert_eq!(reports[0].1.dps(), 10.0);
+
+        let reports = dummy.take_reports(Time(5.0 + REPORT_DELAY));
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0].0, OTHER_ATTACKER);
+        assert_eq!(reports[0].1.by_source, vec![(DamageSource::Other, 100.0)]);
+        assert_eq!(reports[0].1.dps(), 20.0);
+    }
+}
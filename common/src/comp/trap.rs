@@ -0,0 +1,250 @@
+use crate::{
+    combat::{Damage, DamageContributor, DamageKind, DamageSource},
+    comp::{
+        buff::{Buff, BuffCategory, BuffData, BuffKind, BuffSource},
+        object, Group, HealthChange,
+    },
+    resources::Time,
+    uid::Uid,
+};
+use serde::{Deserialize, Serialize};
+use specs::{Component, VecStorage};
+use std::time::Duration;
+
+/// How long (in seconds) a trap takes to arm after being placed, so that
+/// the placer can step away from it.
+pub const ARMING_DELAY: f64 = 3.0;
+
+/// Distance from a trap inside of which sneaking characters notice it. Traps
+/// are hidden from everyone else but their placer.
+pub const DETECTION_RADIUS: f32 = 8.0;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum TrapKind {
+    /// Roots whoever steps into it in place for a while.
+    Snare,
+    /// Damages whoever steps onto it.
+    Spike,
+    /// Tells the placer where it was triggered.
+    Alarm,
+}
+
+impl TrapKind {
+    /// Distance from the trap inside of which entities trigger it.
+    pub fn trigger_radius(&self) -> f32 {
+        match self {
+            TrapKind::Snare | TrapKind::Spike => 1.5,
+            TrapKind::Alarm => 4.0,
+        }
+    }
+
+    // TODO: Use dedicated models once they exist
+    pub fn body(&self) -> object::Body {
+        match self {
+            TrapKind::Snare => object::Body::Pouch,
+            TrapKind::Spike => object::Body::Dart,
+            TrapKind::Alarm => object::Body::LanternGround,
+        }
+    }
+}
+
+/// What a trap needs to know about an entity close to it to decide whether
+/// it is triggered.
+#[derive(Copy, Clone, Debug)]
+pub struct TrapTarget {
+    pub uid: Uid,
+    pub group: Option<Group>,
+    /// The owner of the entity, if it is a pet.
+    pub owner: Option<Uid>,
+    pub in_safezone: bool,
+    /// Whether the placer of the trap is allowed to harm the entity (see
+    /// [`crate::combat::may_harm`]).
+    pub may_harm: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Trap {
+    pub kind: TrapKind,
+    /// The entity that placed the trap, it gets the credit for anything the
+    /// trap does.
+    pub owner: Uid,
+    pub placed_at: Time,
+    /// The entities the trap was shown to, i.e. its placer and whoever
+    /// noticed it. It is kept hidden from everyone else.
+    pub seen_by: Vec<Uid>,
+}
+
+impl Trap {
+    pub fn new(kind: TrapKind, owner: Uid, placed_at: Time) -> Self {
+        Self {
+            kind,
+            owner,
+            placed_at,
+            seen_by: Vec::new(),
+        }
+    }
+
+    pub fn is_armed(&self, time: Time) -> bool { time.0 - self.placed_at.0 >= ARMING_DELAY }
+
+    pub fn is_expired(&self, time: Time, lifetime: f64) -> bool {
+        time.0 - self.placed_at.0 >= lifetime
+    }
+
+    pub fn is_visible_to(&self, uid: Uid) -> bool { self.seen_by.contains(&uid) }
+
+    /// Whether an entity notices the trap. The placer knows where it is,
+    /// everyone else has to be sneaking close to it, watching their step.
+    pub fn is_detected_by(&self, uid: Uid, distance_squared: f32, is_sneaking: bool) -> bool {
+        uid == self.owner || (is_sneaking && distance_squared < DETECTION_RADIUS.powi(2))
+    }
+
+    /// Whether an entity sets off the trap. The placer, their group and their
+    /// pets never do, and neither do entities inside of a safe zone.
+    pub fn is_triggered_by(
+        &self,
+        time: Time,
+        owner_group: Option<Group>,
+        target: &TrapTarget,
+    ) -> bool {
+        self.is_armed(time)
+            && target.uid != self.owner
+            && target.owner != Some(self.owner)
+            && (owner_group.is_none() || target.group != owner_group)
+            && !target.in_safezone
+            && target.may_harm
+    }
+
+    /// The damage dealt by the trap to whoever triggered it, attributed to
+    /// the placer so that they get the credit for kills.
+    pub fn health_change(
+        &self,
+        damage_reduction: f32,
+        owner_group: Option<Group>,
+        time: Time,
+    ) -> Option<HealthChange> {
+        match self.kind {
+            TrapKind::Spike => Some(
+                Damage {
+                    source: DamageSource::Other,
+                    kind: DamageKind::Piercing,
+                    value: 40.0,
+                }
+                .calculate_health_change(
+                    damage_reduction,
+                    Some(DamageContributor::new(self.owner, owner_group)),
+                    false,
+                    0.0,
+                    1.0,
+                    time,
+                    rand::random(),
+                ),
+            ),
+            TrapKind::Snare | TrapKind::Alarm => None,
+        }
+    }
+
+    /// The debuff applied by the trap to whoever triggered it.
+    pub fn buff(&self) -> Option<Buff> {
+        match self.kind {
+            TrapKind::Snare => Some(Buff::new(
+                BuffKind::Ensnared,
+                BuffData::new(10.0, Some(Duration::from_secs(5))),
+                vec![BuffCategory::Physical],
+                BuffSource::Character { by: self.owner },
+            )),
+            TrapKind::Spike | TrapKind::Alarm => None,
+        }
+    }
+}
+
+impl Component for Trap {
+    type Storage = VecStorage<Self>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::comp::group;
+
+    const OWNER: Uid = Uid(1);
+
+    fn stranger() -> TrapTarget {
+        TrapTarget {
+            uid: Uid(2),
+            group: None,
+            owner: None,
+            in_safezone: false,
+            may_harm: true,
+        }
+    }
+
+    #[test]
+    fn arming_delay() {
+        let trap = Trap::new(TrapKind::Spike, OWNER, Time(10.0));
+
+        assert!(!trap.is_triggered_by(Time(10.0), None, &stranger()));
+        assert!(!trap.is_triggered_by(Time(10.0 + ARMING_DELAY - 0.1), None, &stranger()));
+        assert!(trap.is_triggered_by(Time(10.0 + ARMING_DELAY), None, &stranger()));
+    }
+
+    #[test]
+    fn trigger_filtering() {
+        let trap = Trap::new(TrapKind::Snare, OWNER, Time(0.0));
+        let time = Time(ARMING_DELAY);
+        let group = Some(group::NPC);
+
+        let placer = TrapTarget {
+            uid: OWNER,
+            ..stranger()
+        };
+        let pet = TrapTarget {
+            owner: Some(OWNER),
+            ..stranger()
+        };
+        let group_member = TrapTarget {
+            group,
+            ..stranger()
+        };
+        let safe = TrapTarget {
+            in_safezone: true,
+            ..stranger()
+        };
+        let peaceful = TrapTarget {
+            may_harm: false,
+            ..stranger()
+        };
+
+        for target in [placer, pet, group_member, safe, peaceful] {
+            assert!(!trap.is_triggered_by(time, group, &target), "{:?}", target);
+        }
+        assert!(trap.is_triggered_by(time, group, &stranger()));
+        // Being in a group doesn't protect from traps of players without one
+        assert!(trap.is_triggered_by(time, None, &group_member));
+    }
+
+    #[test]
+    fn traps_are_only_noticed_by_the_placer_and_sneaking_characters() {
+        let trap = Trap::new(TrapKind::Alarm, OWNER, Time(0.0));
+        let far = (DETECTION_RADIUS + 1.0).powi(2);
+        let near = (DETECTION_RADIUS - 1.0).powi(2);
+
+        assert!(trap.is_detected_by(OWNER, far, false));
+        assert!(!trap.is_detected_by(Uid(2), 0.0, false));
+        assert!(!trap.is_detected_by(Uid(2), far, true));
+        assert!(trap.is_detected_by(Uid(2), near, true));
+    }
+
+    #[test]
+    fn trap_damage_is_attributed_to_placer() {
+        let trap = Trap::new(TrapKind::Spike, OWNER, Time(0.0));
+        let change = trap
+            .health_change(0.0, Some(group::NPC), Time(ARMING_DELAY))
+            .unwrap();
+
+        assert!(change.amount < 0.0);
+        assert_eq!(change.by.map(|by| by.uid()), Some(OWNER));
+        assert!(Trap::new(TrapKind::Alarm, OWNER, Time(0.0))
+            .health_change(0.0, None, Time(ARMING_DELAY))
+            .is_none());
+    }
+}
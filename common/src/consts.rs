@@ -1,8 +1,3 @@
-// The limit on distance between the entity and a collectible (squared)
-pub const MAX_PICKUP_RANGE: f32 = 5.0;
-pub const MAX_MOUNT_RANGE: f32 = 5.0;
-pub const MAX_TRADE_RANGE: f32 = 20.0;
-
 pub const GRAVITY: f32 = 25.0;
 pub const FRIC_GROUND: f32 = 0.15;
 
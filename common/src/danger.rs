@@ -0,0 +1,190 @@
+use serde::{Deserialize, Serialize};
+use vek::*;
+
+use crate::{grid::Grid, terrain::TerrainChunkSize, vol::RectVolSize};
+
+// How many chunks wide a danger cell is.
+// So one danger cell has (CHUNKS_PER_CELL * CHUNKS_PER_CELL) chunks.
+pub const CHUNKS_PER_CELL: u32 = 16;
+
+pub const CELL_SIZE: u32 = CHUNKS_PER_CELL * TerrainChunkSize::RECT_SIZE.x;
+
+/// Danger of areas close to a settlement, where guards keep the peace.
+pub const SAFE_DANGER: u8 = 0;
+/// Danger of open wilderness that isn't close to any settlement or dungeon.
+pub const WILDERNESS_DANGER: u8 = 1;
+/// The highest danger value that can be produced by [`danger_at`].
+pub const MAX_DANGER: u8 = WILDERNESS_DANGER + MAX_DUNGEON_TIER + 1;
+/// The highest tier of NPCs that populate dungeons.
+pub const MAX_DUNGEON_TIER: u8 = 5;
+
+/// Distance (in blocks) from a dungeon at which it stops affecting danger.
+const DUNGEON_DANGER_RADIUS: f32 = 2.5 * CELL_SIZE as f32;
+/// Distance (in blocks) from a settlement inside of which the area is safe.
+const SETTLEMENT_SAFE_RADIUS: f32 = 0.5 * CELL_SIZE as f32;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DangerSourceKind {
+    /// The difficulty the dungeon was generated with, see [`dungeon_tier`].
+    Dungeon {
+        difficulty: u32,
+    },
+    Settlement,
+}
+
+/// A place in the world that affects the danger of its surroundings.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DangerSource {
+    pub wpos: Vec2<f32>,
+    pub kind: DangerSourceKind,
+}
+
+/// The tier of the NPCs that populate a dungeon of the given difficulty, or
+/// `None` if the difficulty has no tier of its own and the dungeon is populated
+/// with fallback NPCs.
+///
+/// Dungeons pick their NPCs with this, and [`danger_at`] rates their
+/// surroundings with it, so the danger overlay matches what players meet.
+pub fn dungeon_tier(difficulty: u32) -> Option<u8> {
+    u8::try_from(difficulty)
+        .ok()
+        .filter(|tier| (1..=MAX_DUNGEON_TIER).contains(tier))
+}
+
+/// Computes the danger at a world position.
+///
+/// Dungeons raise the danger of their surroundings according to the tier of
+/// their NPCs, falling off linearly with distance, while settlements make
+/// their immediate surroundings safe.
+pub fn danger_at(wpos: Vec2<f32>, sources: &[DangerSource]) -> u8 {
+    let near_settlement = sources.iter().any(|source| {
+        source.kind == DangerSourceKind::Settlement
+            && source.wpos.distance_squared(wpos) < SETTLEMENT_SAFE_RADIUS.powi(2)
+    });
+    if near_settlement {
+        return SAFE_DANGER;
+    }
+
+    sources
+        .iter()
+        .filter_map(|source| match source.kind {
+            DangerSourceKind::Dungeon { difficulty } => {
+                let tier = dungeon_tier(difficulty).unwrap_or(0);
+                let falloff = 1.0 - source.wpos.distance(wpos) / DUNGEON_DANGER_RADIUS;
+                (falloff > 0.0)
+                    .then(|| WILDERNESS_DANGER + (f32::from(tier + 1) * falloff).round() as u8)
+            },
+            DangerSourceKind::Settlement => None,
+        })
+        .fold(WILDERNESS_DANGER, u8::max)
+        .min(MAX_DANGER)
+}
+
+/// A coarse grid of danger values covering the whole world, used for the
+/// danger map overlay.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DangerGrid {
+    danger: Grid<u8>,
+}
+
+impl DangerGrid {
+    pub fn new(size: Vec2<u32>) -> Self {
+        size.map(|e| debug_assert!(i32::try_from(e).is_ok()));
+        Self {
+            danger: Grid::new(size.as_(), WILDERNESS_DANGER),
+        }
+    }
+
+    /// Creates a grid of the given size (in cells), sampling the danger at the
+    /// center of each cell.
+    pub fn generate(size: Vec2<u32>, sources: &[DangerSource]) -> Self {
+        size.map(|e| debug_assert!(i32::try_from(e).is_ok()));
+        Self {
+            danger: Grid::populate_from(size.as_(), |cell| {
+                danger_at(Self::cell_center(cell), sources)
+            }),
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (Vec2<i32>, &u8)> { self.danger.iter() }
+
+    pub fn size(&self) -> Vec2<u32> { self.danger.size().as_() }
+
+    /// The cell containing a given world position.
+    pub fn cell_pos(wpos: Vec2<f32>) -> Vec2<i32> {
+        (wpos / CELL_SIZE as f32).map(|e| e.floor() as i32)
+    }
+
+    /// The world position of the center of a cell.
+    pub fn cell_center(cell: Vec2<i32>) -> Vec2<f32> {
+        (cell.as_::<f32>() + 0.5) * CELL_SIZE as f32
+    }
+
+    pub fn get(&self, cell: Vec2<i32>) -> Option<u8> { self.danger.get(cell).copied() }
+
+    /// Get the danger of the cell containing a world position.
+    pub fn get_at(&self, wpos: Vec2<f32>) -> Option<u8> { self.get(Self::cell_pos(wpos)) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dungeon(cell: Vec2<i32>, difficulty: u32) -> DangerSource {
+        DangerSource {
+            wpos: DangerGrid::cell_center(cell),
+            kind: DangerSourceKind::Dungeon { difficulty },
+        }
+    }
+
+    #[test]
+    fn high_level_dungeon_is_more_dangerous_than_wilderness() {
+        let dungeon_cell = Vec2::new(8, 8);
+        let grid = DangerGrid::generate(Vec2::new(16, 16), &[dungeon(dungeon_cell, 5)]);
+
+        let dungeon_danger = grid.get(dungeon_cell).unwrap();
+        for cell in [Vec2::new(0, 0), Vec2::new(15, 15), Vec2::new(8, 2)] {
+            assert!(dungeon_danger > grid.get(cell).unwrap());
+            assert_eq!(grid.get(cell), Some(WILDERNESS_DANGER));
+        }
+    }
+
+    #[test]
+    fn harder_dungeons_are_more_dangerous() {
+        let sources = [dungeon(Vec2::new(2, 2), 1), dungeon(Vec2::new(12, 12), 5)];
+        let grid = DangerGrid::generate(Vec2::new(16, 16), &sources);
+
+        assert!(grid.get(Vec2::new(12, 12)) > grid.get(Vec2::new(2, 2)));
+    }
+
+    #[test]
+    fn settlements_are_safe() {
+        let town_cell = Vec2::new(4, 4);
+        let sources = [dungeon(Vec2::new(5, 4), 5), DangerSource {
+            wpos: DangerGrid::cell_center(town_cell),
+            kind: DangerSourceKind::Settlement,
+        }];
+        let grid = DangerGrid::generate(Vec2::new(8, 8), &sources);
+
+        assert_eq!(grid.get(town_cell), Some(SAFE_DANGER));
+        assert!(grid.get(Vec2::new(5, 4)).unwrap() > WILDERNESS_DANGER);
+    }
+
+    #[test]
+    fn danger_follows_dungeon_tiers() {
+        let danger_of = |difficulty| {
+            DangerGrid::generate(Vec2::new(1, 1), &[dungeon(Vec2::zero(), difficulty)])
+                .get(Vec2::zero())
+                .unwrap()
+        };
+
+        for difficulty in 1..u32::from(MAX_DUNGEON_TIER) {
+            assert!(danger_of(difficulty + 1) > danger_of(difficulty));
+        }
+        assert_eq!(danger_of(u32::from(MAX_DUNGEON_TIER)), MAX_DANGER);
+        // Dungeons without a tier of their own have fallback NPCs
+        assert_eq!(dungeon_tier(0), None);
+        assert_eq!(dungeon_tier(u32::from(MAX_DUNGEON_TIER) + 1), None);
+        assert_eq!(danger_of(0), danger_of(u32::from(MAX_DUNGEON_TIER) + 1));
+    }
+}
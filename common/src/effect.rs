@@ -37,6 +37,18 @@ impl Effect {
         }
     }
 
+    /// Whether this effect restores health, either at once or over time
+    pub fn is_heal(&self) -> bool {
+        match self {
+            Effect::Health(c) => c.amount > 0.0,
+            Effect::Buff(e) => matches!(
+                e.kind,
+                comp::BuffKind::Regeneration | comp::BuffKind::Saturation | comp::BuffKind::Potion
+            ),
+            Effect::Poise(_) | Effect::Damage(_) => false,
+        }
+    }
+
     pub fn modify_strength(&mut self, modifier: f32) {
         match self {
             Effect::Health(change) => {
@@ -96,13 +96,21 @@ pub enum ServerEvent {
     },
     EnableLantern(EcsEntity),
     DisableLantern(EcsEntity),
+    /// Fills the lantern of an entity up with fuel
+    RefuelLantern(EcsEntity),
     NpcInteract(EcsEntity, EcsEntity),
     InviteResponse(EcsEntity, InviteResponse),
     InitiateInvite(EcsEntity, Uid, InviteKind),
     ProcessTradeAction(EcsEntity, TradeId, TradeAction),
     Mount(EcsEntity, EcsEntity),
     Unmount(EcsEntity),
+    /// An entity sitting down on a seat
+    Sit(EcsEntity, EcsEntity),
+    /// An entity getting up from the seat it sits on
+    Stand(EcsEntity),
     Possess(Uid, Uid),
+    /// Gives a possessing player back control of the entity they possessed from
+    Unpossess(Uid),
     /// Inserts default components for a character when loading into the game
     InitCharacterData {
         entity: EcsEntity,
@@ -118,9 +126,20 @@ pub enum ServerEvent {
             comp::SkillSet,
             comp::Inventory,
             Option<comp::Waypoint>,
+            Option<comp::Pos>,
             Vec<(comp::Pet, comp::Body, comp::Stats)>,
             comp::ActiveAbilities,
             Option<comp::MapMarker>,
+            comp::Hotbar,
+            comp::CharacterSettings,
+            // Health and energy when the character was last saved
+            Option<(f32, f32)>,
+            // Buffs that didn't run out since the character was last saved
+            Vec<comp::Buff>,
+            // Fuel left in the lantern when the character was last saved
+            Option<f32>,
+            // Whether the character is hardcore
+            bool,
         ),
     },
     ExitIngame {
@@ -206,6 +225,11 @@ pub enum ServerEvent {
         pet_entity: EcsEntity,
         owner_entity: EcsEntity,
     },
+    CommandPet {
+        owner: EcsEntity,
+        pet: Option<Uid>,
+        command: comp::pet::PetCommand,
+    },
     EntityAttackedHook {
         entity: EcsEntity,
     },
@@ -219,6 +243,10 @@ pub enum ServerEvent {
         entity: EcsEntity,
         update: comp::MapMarkerChange,
     },
+    ChangeAppearance {
+        entity: EcsEntity,
+        body: comp::Body,
+    },
 }
 
 pub struct EventBus<E> {
@@ -0,0 +1,109 @@
+use crate::{
+    assets::{self, Asset, AssetExt},
+    comp::Pos,
+};
+use serde::{Deserialize, Serialize};
+use vek::Vec3;
+
+/// Interactions of a character with entities or the world that can only
+/// happen within a certain range.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum InteractionKind {
+    /// Picking up items and collecting sprites
+    Pickup,
+    Mount,
+    Trade,
+    /// Talking to NPCs
+    Talk,
+    /// Giving commands to pets
+    PetCommand,
+    /// Sitting down on seats, which also keeps the character seated
+    Sit,
+}
+
+/// The maximum distance of each kind of interaction. The server uses the
+/// ranges from its balance assets and sends them to clients when they join,
+/// so that clients only show interactions the server will accept.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct InteractionRanges {
+    pub pickup: f32,
+    pub mount: f32,
+    pub trade: f32,
+    pub talk: f32,
+    pub pet_command: f32,
+    pub sit: f32,
+}
+
+impl InteractionRanges {
+    pub fn load_default() -> Self { Self::load_expect_cloned("common.interaction_ranges") }
+
+    pub fn range(&self, kind: InteractionKind) -> f32 {
+        match kind {
+            InteractionKind::Pickup => self.pickup,
+            InteractionKind::Mount => self.mount,
+            InteractionKind::Trade => self.trade,
+            InteractionKind::Talk => self.talk,
+            InteractionKind::PetCommand => self.pet_command,
+            InteractionKind::Sit => self.sit,
+        }
+    }
+
+    /// Whether an interaction of this kind can happen between two positions
+    pub fn in_range(&self, kind: InteractionKind, a: Vec3<f32>, b: Vec3<f32>) -> bool {
+        a.distance_squared(b) < self.range(kind).powi(2)
+    }
+
+    /// Like [`Self::in_range`], for entities that might not have a position
+    pub fn positions_in_range(
+        &self,
+        kind: InteractionKind,
+        a: Option<&Pos>,
+        b: Option<&Pos>,
+    ) -> bool {
+        match (a, b) {
+            (Some(a), Some(b)) => self.in_range(kind, a.0, b.0),
+            _ => false,
+        }
+    }
+}
+
+impl Asset for InteractionRanges {
+    type Loader = assets::RonLoader;
+
+    const EXTENSION: &'static str = "ron";
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn in_range_of_configured_distance() {
+        let ranges = InteractionRanges::load_default();
+        for kind in [
+            InteractionKind::Pickup,
+            InteractionKind::Mount,
+            InteractionKind::Trade,
+            InteractionKind::Talk,
+            InteractionKind::PetCommand,
+            InteractionKind::Sit,
+        ] {
+            let range = ranges.range(kind);
+            let origin = Vec3::new(10.0, -4.0, 100.0);
+            let just_inside = origin + Vec3::new(range - 0.01, 0.0, 0.0);
+            let just_outside = origin + Vec3::new(0.0, range + 0.01, 0.0);
+            assert!(ranges.in_range(kind, origin, just_inside), "{:?}", kind);
+            assert!(!ranges.in_range(kind, origin, just_outside), "{:?}", kind);
+            assert!(!ranges.in_range(kind, origin, origin + Vec3::unit_z() * range));
+        }
+    }
+
+    #[test]
+    fn entities_without_position_are_out_of_range() {
+        let ranges = InteractionRanges::load_default();
+        let pos = Pos(Vec3::zero());
+        assert!(ranges.positions_in_range(InteractionKind::Trade, Some(&pos), Some(&pos)));
+        assert!(!ranges.positions_in_range(InteractionKind::Trade, Some(&pos), None));
+        assert!(!ranges.positions_in_range(InteractionKind::Trade, None, Some(&pos)));
+    }
+}
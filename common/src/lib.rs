@@ -48,6 +48,7 @@ cfg_not_wasm! {
     pub mod character;
     pub mod clock;
     pub mod cmd;
+    pub mod danger;
     pub mod depot;
     pub mod effect;
     pub mod event;
@@ -55,7 +56,9 @@ cfg_not_wasm! {
     pub mod figure;
     pub mod generation;
     pub mod grid;
+    pub mod interaction;
     pub mod link;
+    pub mod location;
     pub mod lod;
     pub mod lottery;
     pub mod mounting;
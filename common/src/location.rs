@@ -0,0 +1,167 @@
+//! Tracking of which site and biome players are in, so that clients can be
+//! told when they enter a new area without having to derive it from terrain.
+
+use crate::{
+    danger,
+    terrain::{BiomeKind, TerrainChunkSize},
+    vol::RectVolSize,
+};
+use serde::{Deserialize, Serialize};
+use specs::Component;
+use vek::*;
+
+/// Size of the cells the location of a player is looked up for, in blocks.
+/// Danger cells are a multiple of this, so a location cell never spans two of
+/// them.
+pub const CELL_SIZE: u32 = 2 * TerrainChunkSize::RECT_SIZE.x;
+
+/// How far a player has to go past the edge of their cell before they are
+/// considered to have left it, so that standing on a border doesn't keep
+/// changing their location
+pub const HYSTERESIS: f32 = 8.0;
+
+/// What is known about the area a player is in
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LocationInfo {
+    /// Name of the site or point of interest the area belongs to, if any
+    pub site_name: Option<String>,
+    pub biome: BiomeKind,
+    /// The danger of the area, as shown on the danger map overlay
+    pub danger: u8,
+}
+
+impl LocationInfo {
+    /// Whether the area is close enough to a settlement to be kept safe
+    pub fn is_safe(&self) -> bool { self.danger == danger::SAFE_DANGER }
+}
+
+/// The location of a player, which is only looked up again when they move
+/// into another cell
+#[derive(Clone, Debug, Default)]
+pub struct LocationTracker {
+    cell: Option<Vec2<i32>>,
+    location: Option<LocationInfo>,
+}
+
+impl LocationTracker {
+    /// The cell containing a given world position
+    pub fn cell_pos(wpos: Vec2<f32>) -> Vec2<i32> {
+        (wpos / CELL_SIZE as f32).map(|e| e.floor() as i32)
+    }
+
+    /// The world position of the center of a cell, which is where the
+    /// location of the whole cell is looked up
+    pub fn cell_center(cell: Vec2<i32>) -> Vec2<f32> {
+        (cell.as_::<f32>() + 0.5) * CELL_SIZE as f32
+    }
+
+    pub fn location(&self) -> Option<&LocationInfo> { self.location.as_ref() }
+
+    /// Updates the tracker for a player at `wpos`. If they left their cell,
+    /// the location of their new cell is looked up with `lookup` (given its
+    /// center), and returned if it differs from the previous location.
+    pub fn update(
+        &mut self,
+        wpos: Vec2<f32>,
+        lookup: impl FnOnce(Vec2<f32>) -> LocationInfo,
+    ) -> Option<&LocationInfo> {
+        if let Some(cell) = self.cell {
+            let min = cell.as_::<f32>() * CELL_SIZE as f32 - HYSTERESIS;
+            let max = (cell + 1).as_::<f32>() * CELL_SIZE as f32 + HYSTERESIS;
+            if wpos.x >= min.x && wpos.y >= min.y && wpos.x < max.x && wpos.y < max.y {
+                return None;
+            }
+        }
+
+        let cell = Self::cell_pos(wpos);
+        self.cell = Some(cell);
+        let location = lookup(Self::cell_center(cell));
+        if self.location.as_ref() == Some(&location) {
+            None
+        } else {
+            self.location = Some(location);
+            self.location.as_ref()
+        }
+    }
+}
+
+impl Component for LocationTracker {
+    type Storage = specs::DenseVecStorage<Self>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A world with a forest west of x = 0 and a grassland to the east of it,
+    /// with the town of Frostwood in the cell around (1000, 1000)
+    fn lookup(wpos: Vec2<f32>) -> LocationInfo {
+        let in_town =
+            LocationTracker::cell_pos(wpos) == LocationTracker::cell_pos(Vec2::broadcast(1000.0));
+        LocationInfo {
+            site_name: in_town.then(|| "Frostwood".to_owned()),
+            biome: if wpos.x < 0.0 {
+                BiomeKind::Forest
+            } else {
+                BiomeKind::Grassland
+            },
+            danger: if in_town {
+                danger::SAFE_DANGER
+            } else {
+                danger::WILDERNESS_DANGER
+            },
+        }
+    }
+
+    #[test]
+    fn standing_on_a_border_doesnt_spam_changes() {
+        let mut tracker = LocationTracker::default();
+        assert_eq!(
+            tracker
+                .update(Vec2::new(10.0, 10.0), lookup)
+                .map(|l| l.biome),
+            Some(BiomeKind::Grassland)
+        );
+
+        // Wandering back and forth over the border to the forest
+        let mut changes = 0;
+        for step in 0..100 {
+            let x = if step % 2 == 0 { -1.0 } else { 1.0 } * (step % 7) as f32;
+            if tracker.update(Vec2::new(x, 10.0), lookup).is_some() {
+                changes += 1;
+            }
+        }
+        assert_eq!(changes, 0);
+
+        // Going well into the forest does change it, once
+        assert_eq!(
+            tracker
+                .update(Vec2::new(-HYSTERESIS - 1.0, 10.0), lookup)
+                .map(|l| l.biome),
+            Some(BiomeKind::Forest)
+        );
+        assert!(tracker
+            .update(Vec2::new(-HYSTERESIS, 10.0), lookup)
+            .is_none());
+        assert!(tracker.update(Vec2::new(-2.0, 10.0), lookup).is_none());
+    }
+
+    #[test]
+    fn teleporting_into_a_site() {
+        let mut tracker = LocationTracker::default();
+        tracker.update(Vec2::new(10.0, 10.0), lookup);
+
+        let town = tracker
+            .update(Vec2::broadcast(1000.0), lookup)
+            .cloned()
+            .unwrap();
+        assert_eq!(town.site_name.as_deref(), Some("Frostwood"));
+        assert!(town.is_safe());
+        assert_eq!(tracker.location(), Some(&town));
+
+        // Moving to another cell with the same location isn't a change
+        let mut other = LocationTracker::default();
+        other.update(Vec2::new(10.0, 10.0), lookup);
+        assert!(other.update(Vec2::new(10.0, 500.0), lookup).is_none());
+    }
+}
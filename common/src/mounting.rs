@@ -1,14 +1,126 @@
 use crate::{
     comp,
-    comp::{pet::is_mountable, Body},
+    comp::{
+        pet::{is_mountable, MountStatsManifest},
+        Body,
+    },
     link::{Is, Link, LinkHandle, Role},
     terrain::TerrainGrid,
     uid::{Uid, UidAllocator},
 };
 use serde::{Deserialize, Serialize};
-use specs::{saveload::MarkerAllocator, Entities, Read, ReadExpect, ReadStorage, WriteStorage};
+use specs::{
+    saveload::MarkerAllocator, Component, DerefFlaggedStorage, Entities, Read, ReadExpect,
+    ReadStorage, WriteStorage,
+};
 use vek::*;
 
+/// Distance between a rider and their mount above which they are separated
+/// (e.g. because one of them was teleported), which dismounts the rider
+pub const MAX_MOUNT_SEPARATION: f32 = 16.0;
+
+/// Whether a rider at `rider_pos` is still close enough to their mount at
+/// `mount_pos` to be riding it
+pub fn within_mount_range(mount_pos: Vec3<f32>, rider_pos: Vec3<f32>) -> bool {
+    mount_pos.distance_squared(rider_pos) < MAX_MOUNT_SEPARATION.powi(2)
+}
+
+/// Where a rider sits relative to the position of their mount, before the
+/// orientation of the mount is applied
+pub fn saddle_offset(mount: Option<&Body>, rider: Option<&Body>) -> Vec3<f32> {
+    mount.map_or(Vec3::unit_z(), Body::mount_offset)
+        + rider.map_or(Vec3::zero(), Body::rider_offset)
+}
+
+/// Where a rider is put down when they dismount, relative to the position of
+/// their mount: on the right of the mount, far enough that they don't overlap
+pub fn dismount_offset(
+    mount: Option<&Body>,
+    rider: Option<&Body>,
+    mount_ori: comp::Ori,
+) -> Vec3<f32> {
+    let right = Vec2::from(*mount_ori.right())
+        .try_normalized()
+        .unwrap_or_else(Vec2::unit_x);
+    let distance = mount.map_or(0.0, Body::max_radius) + rider.map_or(0.0, Body::max_radius) + 0.5;
+    Vec3::from(right * distance)
+}
+
+/// Whether `rider` is allowed to mount an entity with the given alignment.
+/// Players can only mount their own pets and entities that are marked as
+/// [`comp::Mountable`], the body of the mount still has to be mountable.
+pub fn may_mount(rider: Uid, mount_alignment: Option<&comp::Alignment>, mountable: bool) -> bool {
+    mountable || matches!(mount_alignment, Some(comp::Alignment::Owned(owner)) if *owner == rider)
+}
+
+/// How a mount carries its rider, looked up from the [`MountStatsManifest`]
+/// when it is mounted. Mounts run whenever their rider moves, which drains
+/// their stamina (and not the one of their rider) until they are exhausted and
+/// can only walk.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MountData {
+    /// Multiplier of the acceleration of the mount while it runs
+    pub speed: f32,
+    /// Multiplier of the acceleration of the mount once it is exhausted
+    pub exhausted_speed: f32,
+    /// Seconds the mount can still run for
+    pub stamina: f32,
+    /// `None` for mounts that never tire
+    pub max_stamina: Option<f32>,
+    /// Seconds of running recovered per second the mount doesn't run
+    pub recovery: f32,
+    /// Stamina an exhausted mount needs before it can run again
+    pub rested_stamina: f32,
+    pub exhausted: bool,
+}
+
+impl MountData {
+    pub fn new(manifest: &MountStatsManifest, mount: &Body) -> Self {
+        let stats = manifest.stats(mount);
+        let max_stamina = stats.stamina.map(|stamina| stamina.max(0.0));
+        Self {
+            speed: stats.speed,
+            exhausted_speed: manifest.exhausted_speed,
+            stamina: max_stamina.unwrap_or(0.0),
+            max_stamina,
+            recovery: stats.recovery,
+            rested_stamina: max_stamina.unwrap_or(0.0) * manifest.rested_fraction,
+            exhausted: false,
+        }
+    }
+
+    /// Multiplier of the acceleration of the mount
+    pub fn speed(&self) -> f32 {
+        if self.exhausted {
+            self.exhausted_speed
+        } else {
+            self.speed
+        }
+    }
+
+    /// Drains the stamina of the mount over `dt` seconds of running, or
+    /// recovers it while it doesn't run
+    pub fn update(&mut self, dt: f32, running: bool) {
+        let max_stamina = match self.max_stamina {
+            Some(max_stamina) => max_stamina,
+            None => return,
+        };
+        if running && !self.exhausted {
+            self.stamina = (self.stamina - dt).max(0.0);
+            self.exhausted = self.stamina <= 0.0;
+        } else {
+            self.stamina = (self.stamina + self.recovery * dt).min(max_stamina);
+            if self.exhausted && self.stamina >= self.rested_stamina {
+                self.exhausted = false;
+            }
+        }
+    }
+}
+
+impl Component for MountData {
+    type Storage = DerefFlaggedStorage<Self, specs::DenseVecStorage<Self>>;
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct Rider;
 
@@ -45,8 +157,12 @@ impl Link for Mounting {
         Read<'a, UidAllocator>,
         WriteStorage<'a, Is<Mount>>,
         WriteStorage<'a, Is<Rider>>,
+        WriteStorage<'a, MountData>,
         WriteStorage<'a, comp::Pos>,
+        WriteStorage<'a, comp::Vel>,
         WriteStorage<'a, comp::ForceUpdate>,
+        ReadStorage<'a, comp::Ori>,
+        ReadStorage<'a, Body>,
         ReadExpect<'a, TerrainGrid>,
     );
     type Error = MountingError;
@@ -54,6 +170,7 @@ impl Link for Mounting {
         Read<'a, UidAllocator>,
         Entities<'a>,
         ReadStorage<'a, comp::Health>,
+        ReadStorage<'a, comp::Pos>,
         ReadStorage<'a, Is<Mount>>,
         ReadStorage<'a, Is<Rider>>,
     );
@@ -95,7 +212,7 @@ impl Link for Mounting {
 
     fn persist(
         this: &LinkHandle<Self>,
-        (uid_allocator, entities, healths, is_mounts, is_riders): Self::PersistData<'_>,
+        (uid_allocator, entities, healths, positions, is_mounts, is_riders): Self::PersistData<'_>,
     ) -> bool {
         let entity = |uid: Uid| uid_allocator.retrieve_entity_internal(uid.into());
 
@@ -104,11 +221,18 @@ impl Link for Mounting {
                 entities.is_alive(entity) && healths.get(entity).map_or(true, |h| !h.is_dead)
             };
 
-            // Ensure that both entities are alive and that they continue to be linked
+            let within_range = match (positions.get(mount), positions.get(rider)) {
+                (Some(mount_pos), Some(rider_pos)) => within_mount_range(mount_pos.0, rider_pos.0),
+                _ => true,
+            };
+
+            // Ensure that both entities are alive, that they continue to be linked and that
+            // they weren't separated
             is_alive(mount)
                 && is_alive(rider)
                 && is_mounts.get(mount).is_some()
                 && is_riders.get(rider).is_some()
+                && within_range
         } else {
             false
         }
@@ -116,7 +240,18 @@ impl Link for Mounting {
 
     fn delete(
         this: &LinkHandle<Self>,
-        (uid_allocator, mut is_mounts, mut is_riders, mut positions, mut force_update, terrain): Self::DeleteData<'_>,
+        (
+            uid_allocator,
+            mut is_mounts,
+            mut is_riders,
+            mut mount_data,
+            mut positions,
+            mut velocities,
+            mut force_update,
+            orientations,
+            bodies,
+            terrain,
+        ): Self::DeleteData<'_>,
     ) {
         let entity = |uid: Uid| uid_allocator.retrieve_entity_internal(uid.into());
 
@@ -125,12 +260,33 @@ impl Link for Mounting {
 
         // Delete link components
         mount.map(|mount| is_mounts.remove(mount));
+        mount.map(|mount| mount_data.remove(mount));
         rider.map(|rider| is_riders.remove(rider));
 
-        // Try to move the rider to a safe place when dismounting
-        let safe_pos = rider
-            .and_then(|rider| positions.get(rider).copied())
-            .filter(|rider_pos| terrain.is_space(rider_pos.0.map(|e| e.floor() as i32)))
+        let is_space = |pos: Vec3<f32>| terrain.is_space(pos.map(|e| e.floor() as i32));
+        let rider_pos = rider.and_then(|rider| positions.get(rider).copied());
+        // Put the rider down beside the mount, unless they were separated from it
+        let beside_mount = mount
+            .and_then(|mount| Some((*positions.get(mount)?, *orientations.get(mount)?)))
+            .filter(|(mount_pos, _)| {
+                rider_pos.map_or(false, |rider_pos| {
+                    within_mount_range(mount_pos.0, rider_pos.0)
+                })
+            })
+            .and_then(|(mount_pos, mount_ori)| {
+                let offset = dismount_offset(
+                    mount.and_then(|mount| bodies.get(mount)),
+                    rider.and_then(|rider| bodies.get(rider)),
+                    mount_ori,
+                );
+                [offset, -offset]
+                    .into_iter()
+                    .map(|offset| comp::Pos(mount_pos.0 + offset))
+                    .find(|pos| is_space(pos.0))
+            });
+        // Otherwise try to move the rider to another safe place
+        let safe_pos = beside_mount
+            .or_else(|| rider_pos.filter(|rider_pos| is_space(rider_pos.0)))
             .or_else(|| {
                 mount
                     .and_then(|mount| positions.get(mount).copied())
@@ -148,9 +304,150 @@ impl Link for Mounting {
                     .map(|p| p.0.map(|e| e.floor()))
                     .unwrap_or_else(|| terrain.find_space(old_pos).map(|e| e as f32))
                     + Vec3::new(0.5, 0.5, 0.0);
+                // The rider no longer moves along with the mount
+                if let Some(vel) = velocities.get_mut(rider) {
+                    vel.0 = Vec3::zero();
+                }
                 if let Some(force_update) = force_update.get_mut(rider) {
                     force_update.update();
                 }
             });
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_own_pets_and_mountable_entities_can_be_mounted() {
+        let rider = Uid(1);
+        let owned = comp::Alignment::Owned(rider);
+        let someone_elses = comp::Alignment::Owned(Uid(2));
+
+        assert!(may_mount(rider, Some(&owned), false));
+        assert!(!may_mount(rider, Some(&someone_elses), false));
+        assert!(!may_mount(rider, Some(&comp::Alignment::Npc), false));
+        assert!(!may_mount(rider, None, false));
+
+        assert!(may_mount(rider, Some(&someone_elses), true));
+        assert!(may_mount(rider, Some(&comp::Alignment::Wild), true));
+        assert!(may_mount(rider, None, true));
+    }
+
+    #[test]
+    fn riders_dismount_beside_their_mount() {
+        use comp::{humanoid, quadruped_medium};
+
+        let rider = Body::Humanoid(humanoid::Body::random());
+        let horse = Body::QuadrupedMedium(quadruped_medium::Body::random_with(
+            &mut rand::thread_rng(),
+            &quadruped_medium::Species::Horse,
+        ));
+        let ori = comp::Ori::from_unnormalized_vec(Vec3::new(1.0, 1.0, 0.0)).unwrap();
+
+        let offset = dismount_offset(Some(&horse), Some(&rider), ori);
+        assert_eq!(offset.z, 0.0);
+        assert!(offset.dot(*ori.look_dir()).abs() < 0.001);
+        assert!(offset.magnitude() > horse.max_radius() + rider.max_radius());
+        // Riders sit on the mount, not inside it
+        assert!(saddle_offset(Some(&horse), Some(&rider)).z > 0.0);
+    }
+
+    #[test]
+    fn bodies_are_checked_against_the_mountable_bodies() {
+        use comp::{humanoid, pet::MountableBodies, quadruped_medium, quadruped_small};
+
+        let mut rng = rand::thread_rng();
+        let mut config = MountableBodies::load().read().clone();
+        let rider = Body::Humanoid(humanoid::Body::random());
+        let horse = Body::QuadrupedMedium(quadruped_medium::Body::random_with(
+            &mut rng,
+            &quadruped_medium::Species::Horse,
+        ));
+        let cattle = Body::QuadrupedMedium(quadruped_medium::Body::random_with(
+            &mut rng,
+            &quadruped_medium::Species::Cattle,
+        ));
+        let rat = Body::QuadrupedSmall(quadruped_small::Body::random_with(
+            &mut rng,
+            &quadruped_small::Species::Rat,
+        ));
+        let boar = Body::QuadrupedSmall(quadruped_small::Body::random_with(
+            &mut rng,
+            &quadruped_small::Species::Boar,
+        ));
+        let dodarock = Body::QuadrupedSmall(quadruped_small::Body::random_with(
+            &mut rng,
+            &quadruped_small::Species::Dodarock,
+        ));
+
+        assert!(config.can_ride(&horse, Some(&rider)));
+        assert!(!config.can_ride(&rat, Some(&rider)));
+        assert!(!config.can_ride(&rider, Some(&rider)));
+        assert!(config.can_ride(&boar, Some(&rider)));
+        assert!(!config.can_ride(&boar, Some(&cattle)));
+
+        // Unlisted quadrupeds only carry riders much lighter than themselves
+        config.min_mass_ratio = Some(5.0);
+        assert!(config.can_ride(&dodarock, Some(&rider)));
+        assert!(!config.can_ride(&dodarock, Some(&cattle)));
+        config.min_mass_ratio = None;
+        assert!(!config.can_ride(&dodarock, Some(&rider)));
+    }
+
+    #[test]
+    fn mounts_tire_from_running() {
+        use comp::{
+            pet::{MountKind, MountStats},
+            quadruped_medium, ship,
+        };
+
+        let horse = Body::QuadrupedMedium(quadruped_medium::Body::random_with(
+            &mut rand::thread_rng(),
+            &quadruped_medium::Species::Horse,
+        ));
+        let airship = Body::Ship(ship::Body::DefaultAirship);
+        let manifest = MountStatsManifest {
+            default: MountStats {
+                speed: 1.0,
+                stamina: None,
+                recovery: 0.0,
+            },
+            mounts: vec![(
+                MountKind::QuadrupedMedium(quadruped_medium::Species::Horse),
+                MountStats {
+                    speed: 1.5,
+                    stamina: Some(10.0),
+                    recovery: 2.0,
+                },
+            )],
+            exhausted_speed: 0.5,
+            rested_fraction: 0.5,
+        };
+
+        let mut mount_data = MountData::new(&manifest, &horse);
+        assert_eq!(mount_data.speed(), 1.5);
+        for _ in 0..9 {
+            mount_data.update(1.0, true);
+        }
+        assert_eq!(mount_data.speed(), 1.5);
+        mount_data.update(1.0, true);
+        assert_eq!(mount_data.speed(), 0.5);
+
+        // An exhausted mount walks until it recovered half of its stamina,
+        // which it also recovers while walking
+        mount_data.update(2.0, false);
+        mount_data.update(0.25, true);
+        assert_eq!(mount_data.speed(), 0.5);
+        mount_data.update(0.5, false);
+        assert_eq!(mount_data.speed(), 1.5);
+
+        let mut airship_data = MountData::new(&manifest, &airship);
+        airship_data.update(1000.0, true);
+        assert_eq!(airship_data.speed(), 1.0);
+
+        // The stats of all mounts can be loaded
+        MountStatsManifest::load().read().stats(&horse);
+    }
+}
@@ -123,4 +123,51 @@ impl Outcome {
             | Outcome::SkillPointGain { .. } => None,
         }
     }
+
+    /// Whether the outcome is only used for visual effects, so that clients
+    /// short on bandwidth can do without it.
+    pub fn is_cosmetic(&self) -> bool {
+        match self {
+            Outcome::Beam { .. }
+            | Outcome::BreakBlock { .. }
+            | Outcome::SummonedCreature { .. }
+            | Outcome::GroundSlam { .. }
+            | Outcome::Glider { .. } => true,
+            Outcome::Explosion { .. }
+            | Outcome::Lightning { .. }
+            | Outcome::ProjectileShot { .. }
+            | Outcome::ProjectileHit { .. }
+            | Outcome::ExpChange { .. }
+            | Outcome::SkillPointGain { .. }
+            | Outcome::ComboChange { .. }
+            | Outcome::HealthChange { .. }
+            | Outcome::Death { .. }
+            | Outcome::Block { .. }
+            | Outcome::PoiseChange { .. }
+            | Outcome::Utterance { .. } => false,
+        }
+    }
+}
+
+/// How much of the sound of a positional outcome reaches a client through the
+/// terrain, which the server sends along with the outcome so that clients can
+/// muffle it without having the terrain in between loaded.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SoundOcclusion {
+    /// From 0 for outcomes in plain sight to 1 for ones behind thick walls
+    pub factor: f32,
+    /// Straight-line distance between the client and the outcome
+    pub distance: f32,
+}
+
+impl SoundOcclusion {
+    /// How much quieter fully occluded sounds are
+    const MAX_ATTENUATION: f32 = 0.7;
+    /// Occlusion above which sounds are muffled as if they were underwater
+    const MUFFLED_FACTOR: f32 = 0.5;
+
+    /// The factor the volume of the sound is multiplied by
+    pub fn volume(&self) -> f32 { 1.0 - self.factor.clamp(0.0, 1.0) * Self::MAX_ATTENUATION }
+
+    pub fn is_muffled(&self) -> bool { self.factor > Self::MUFFLED_FACTOR }
 }
@@ -161,6 +161,9 @@ impl InternalSlowJobPool {
         mut queued: HashMap<&String, u64>,
         mut limit: usize,
     ) -> Vec<String> {
+        if limit == 0 {
+            return Vec::new();
+        }
         let mut roundrobin = self.last_spawned_configs.clone();
         let mut result = vec![];
         let spawned = self
@@ -687,6 +690,17 @@ mod tests {
         pool.try_run("FOO", func).unwrap_err();
     }
 
+    #[test]
+    fn try_run_global_limit_reached() {
+        let pool = mock_pool(2, 2, 0, 1, 1, 0);
+        let func = || loop {
+            std::thread::sleep(Duration::from_secs(1))
+        };
+        pool.try_run("FOO", func).unwrap();
+        pool.try_run("FOO", func).unwrap();
+        pool.try_run("BAR", func).unwrap_err();
+    }
+
     #[test]
     fn actually_runs_1() {
         let pool = mock_pool(4, 4, 0, 0, 0, 1);
@@ -5,8 +5,9 @@ use crate::{
         Health, InputAttr, InputKind, Inventory, InventoryAction, Mass, Melee, Ori, PhysicsState,
         Pos, SkillSet, StateUpdate, Stats, Vel,
     },
+    interaction::InteractionRanges,
     link::Is,
-    mounting::Rider,
+    mounting::{MountData, Rider},
     resources::DeltaTime,
     terrain::TerrainGrid,
     uid::Uid,
@@ -134,10 +135,13 @@ pub struct JoinData<'a> {
     pub skill_set: &'a SkillSet,
     pub active_abilities: Option<&'a ActiveAbilities>,
     pub msm: &'a MaterialStatManifest,
+    pub interaction_ranges: &'a InteractionRanges,
     pub combo: Option<&'a Combo>,
     pub alignment: Option<&'a comp::Alignment>,
     pub terrain: &'a TerrainGrid,
     pub mount_data: Option<&'a Is<Rider>>,
+    /// Set while the entity is a mount carrying a rider
+    pub ridden: Option<&'a MountData>,
 }
 
 pub struct JoinStruct<'a> {
@@ -164,6 +168,8 @@ pub struct JoinStruct<'a> {
     pub alignment: Option<&'a comp::Alignment>,
     pub terrain: &'a TerrainGrid,
     pub mount_data: Option<&'a Is<Rider>>,
+    /// Set while the entity is a mount carrying a rider
+    pub ridden: Option<&'a MountData>,
 }
 
 impl<'a> JoinData<'a> {
@@ -172,6 +178,7 @@ impl<'a> JoinData<'a> {
         updater: &'a LazyUpdate,
         dt: &'a DeltaTime,
         msm: &'a MaterialStatManifest,
+        interaction_ranges: &'a InteractionRanges,
     ) -> Self {
         Self {
             entity: j.entity,
@@ -195,11 +202,13 @@ impl<'a> JoinData<'a> {
             updater,
             dt,
             msm,
+            interaction_ranges,
             combo: j.combo,
             alignment: j.alignment,
             terrain: j.terrain,
             active_abilities: j.active_abilities,
             mount_data: j.mount_data,
+            ridden: j.ridden,
         }
     }
 }
@@ -11,8 +11,10 @@ use crate::{
         theropod, Body, CharacterAbility, CharacterState, Density, InputAttr, InputKind,
         InventoryAction, StateUpdate,
     },
-    consts::{FRIC_GROUND, GRAVITY, MAX_PICKUP_RANGE},
+    consts::{FRIC_GROUND, GRAVITY},
     event::{LocalEvent, ServerEvent},
+    interaction::InteractionKind,
+    mounting::MountData,
     outcome::Outcome,
     states::{behavior::JoinData, utils::CharacterState::Idle, *},
     util::Dir,
@@ -331,6 +333,8 @@ pub fn handle_skating(data: &JoinData, update: &mut StateUpdate) {
 
 /// Handles updating `Components` to move player based on state of `JoinData`
 pub fn handle_move(data: &JoinData<'_>, update: &mut StateUpdate, efficiency: f32) {
+    // Mounts carry their rider at their own pace, and only walk once exhausted
+    let efficiency = efficiency * data.ridden.map_or(1.0, MountData::speed);
     let submersion = data
         .physics
         .in_liquid()
@@ -784,10 +788,11 @@ pub fn handle_manipulate_loadout(
         InventoryAction::Collect(sprite_pos) => {
             let sprite_pos_f32 = sprite_pos.map(|x| x as f32 + 0.5);
             // Closure to check if distance between a point and the sprite is less than
-            // MAX_PICKUP_RANGE and the radius of the body
+            // the pickup range and the radius of the body
+            let pickup_range = data.interaction_ranges.range(InteractionKind::Pickup);
             let sprite_range_check = |pos: Vec3<f32>| {
                 (sprite_pos_f32 - pos).magnitude_squared()
-                    < (MAX_PICKUP_RANGE + data.body.max_radius()).powi(2)
+                    < (pickup_range + data.body.max_radius()).powi(2)
             };
 
             // Checks if player's feet or head is near to sprite
@@ -329,6 +329,29 @@ impl Block {
         }
     }
 
+    /// Whether this block can catch fire and burn away
+    #[inline]
+    pub fn is_flammable(&self) -> bool {
+        match self.kind() {
+            BlockKind::Wood | BlockKind::Leaves => true,
+            _ => matches!(
+                self.get_sprite(),
+                Some(
+                    SpriteKind::LongGrass
+                        | SpriteKind::MediumGrass
+                        | SpriteKind::ShortGrass
+                        | SpriteKind::LargeGrass
+                        | SpriteKind::SavannaGrass
+                        | SpriteKind::TallSavannaGrass
+                        | SpriteKind::RedSavannaGrass
+                        | SpriteKind::SavannaBush
+                        | SpriteKind::Fern
+                        | SpriteKind::DeadBush
+                )
+            ),
+        }
+    }
+
     /// The tool required to mine this block. For blocks that cannot be mined,
     /// `None` is returned.
     #[inline]
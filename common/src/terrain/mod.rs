@@ -174,6 +174,15 @@ impl TerrainGrid {
         })
     }
 
+    /// Find the first free space above the given position, for freeing entities
+    /// that are stuck inside of terrain.
+    pub fn try_find_space_above(&self, pos: Vec3<i32>) -> Option<Vec3<i32>> {
+        const SEARCH_DIST: i32 = 256;
+        (0..SEARCH_DIST)
+            .map(|z_diff| pos + Vec3::unit_z() * z_diff)
+            .find(|pos| self.is_space(*pos))
+    }
+
     pub fn try_find_space(&self, pos: Vec3<i32>) -> Option<Vec3<i32>> {
         const SEARCH_DIST: i32 = 63;
         (0..SEARCH_DIST * 2 + 1)
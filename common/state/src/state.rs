@@ -9,7 +9,7 @@ use common::{
     comp,
     event::{EventBus, LocalEvent, ServerEvent},
     link::Is,
-    mounting::{Mount, Rider},
+    mounting::{Mount, MountData, Rider},
     outcome::Outcome,
     region::RegionMap,
     resources::{
@@ -146,11 +146,13 @@ impl State {
         ecs.register::<comp::Scale>();
         ecs.register::<Is<Mount>>();
         ecs.register::<Is<Rider>>();
+        ecs.register::<MountData>();
         ecs.register::<comp::Mass>();
         ecs.register::<comp::Density>();
         ecs.register::<comp::Collider>();
         ecs.register::<comp::Sticky>();
         ecs.register::<comp::Immovable>();
+        ecs.register::<comp::Mountable>();
         ecs.register::<comp::CharacterState>();
         ecs.register::<comp::Object>();
         ecs.register::<comp::Group>();
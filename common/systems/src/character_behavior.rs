@@ -11,8 +11,9 @@ use common::{
         StateUpdate, Stats, Vel,
     },
     event::{EventBus, LocalEvent, ServerEvent},
+    interaction::InteractionRanges,
     link::Is,
-    mounting::Rider,
+    mounting::{MountData, Rider},
     outcome::Outcome,
     resources::{DeltaTime, Time},
     states::{
@@ -40,10 +41,12 @@ pub struct ReadData<'a> {
     beams: ReadStorage<'a, Beam>,
     uids: ReadStorage<'a, Uid>,
     is_riders: ReadStorage<'a, Is<Rider>>,
+    mount_data: ReadStorage<'a, MountData>,
     stats: ReadStorage<'a, Stats>,
     skill_sets: ReadStorage<'a, SkillSet>,
     active_abilities: ReadStorage<'a, ActiveAbilities>,
     msm: ReadExpect<'a, MaterialStatManifest>,
+    interaction_ranges: ReadExpect<'a, InteractionRanges>,
     combos: ReadStorage<'a, Combo>,
     alignments: ReadStorage<'a, comp::Alignment>,
     terrain: ReadExpect<'a, TerrainGrid>,
@@ -196,6 +199,7 @@ impl<'a> System<'a> for Sys {
                 alignment: read_data.alignments.get(entity),
                 terrain: &read_data.terrain,
                 mount_data: read_data.is_riders.get(entity),
+                ridden: read_data.mount_data.get(entity),
             };
 
             for action in actions {
@@ -204,6 +208,7 @@ impl<'a> System<'a> for Sys {
                     &read_data.lazy_update,
                     &read_data.dt,
                     &read_data.msm,
+                    &read_data.interaction_ranges,
                 );
                 let state_update = j.character.handle_event(&j, &mut output_events, action);
                 Self::publish_state_update(&mut join_struct, state_update, &mut output_events);
@@ -222,6 +227,7 @@ impl<'a> System<'a> for Sys {
                 &read_data.lazy_update,
                 &read_data.dt,
                 &read_data.msm,
+                &read_data.interaction_ranges,
             );
 
             let state_update = j.character.behavior(&j, &mut output_events);
@@ -58,6 +58,15 @@ impl<'a> System<'a> for Sys {
                         });
                     },
                     ControlEvent::Unmount => server_emitter.emit(ServerEvent::Unmount(entity)),
+                    ControlEvent::Sit(seat_uid) => {
+                        if let Some(seat) = read_data
+                            .uid_allocator
+                            .retrieve_entity_internal(seat_uid.id())
+                        {
+                            server_emitter.emit(ServerEvent::Sit(entity, seat));
+                        }
+                    },
+                    ControlEvent::Stand => server_emitter.emit(ServerEvent::Stand(entity)),
                     ControlEvent::EnableLantern => {
                         server_emitter.emit(ServerEvent::EnableLantern(entity))
                     },
@@ -115,6 +124,13 @@ impl<'a> System<'a> for Sys {
                             new_ability,
                         });
                     },
+                    ControlEvent::CommandPet { pet, command } => {
+                        server_emitter.emit(ServerEvent::CommandPet {
+                            owner: entity,
+                            pet,
+                            command,
+                        });
+                    },
                 }
             }
         }
@@ -1,7 +1,8 @@
 use common::{
     comp::{Body, Controller, InputKind, Ori, Pos, Vel},
     link::Is,
-    mounting::Mount,
+    mounting::{saddle_offset, within_mount_range, Mount, MountData},
+    resources::DeltaTime,
     uid::UidAllocator,
 };
 use common_ecs::{Job, Origin, Phase, System};
@@ -17,6 +18,7 @@ pub struct Sys;
 impl<'a> System<'a> for Sys {
     type SystemData = (
         Read<'a, UidAllocator>,
+        Read<'a, DeltaTime>,
         Entities<'a>,
         WriteStorage<'a, Controller>,
         ReadStorage<'a, Is<Mount>>,
@@ -24,6 +26,7 @@ impl<'a> System<'a> for Sys {
         WriteStorage<'a, Vel>,
         WriteStorage<'a, Ori>,
         ReadStorage<'a, Body>,
+        WriteStorage<'a, MountData>,
     );
 
     const NAME: &'static str = "mount";
@@ -34,6 +37,7 @@ impl<'a> System<'a> for Sys {
         _job: &mut Job<Self>,
         (
             uid_allocator,
+            dt,
             entities,
             mut controllers,
             is_mounts,
@@ -41,6 +45,7 @@ impl<'a> System<'a> for Sys {
             mut velocities,
             mut orientations,
             bodies,
+            mut mount_data,
         ): Self::SystemData,
     ) {
         // For each mount...
@@ -65,14 +70,26 @@ impl<'a> System<'a> for Sys {
             let pos = positions.get(entity).copied();
             let ori = orientations.get(entity).copied();
             let vel = velocities.get(entity).copied();
+            // A rider that was moved away from the mount (e.g. teleported) isn't pulled
+            // back, they are dismounted instead
+            let separated = pos
+                .zip(positions.get(rider))
+                .map_or(false, |(pos, rider_pos)| {
+                    !within_mount_range(pos.0, rider_pos.0)
+                });
+            if separated {
+                continue;
+            }
             if let (Some(pos), Some(ori), Some(vel)) = (pos, ori, vel) {
-                let mounter_body = bodies.get(rider);
-                let mounting_offset = body.map_or(Vec3::unit_z(), Body::mount_offset)
-                    + mounter_body.map_or(Vec3::zero(), Body::rider_offset);
+                let mounting_offset = saddle_offset(body, bodies.get(rider));
                 let _ = positions.insert(rider, Pos(pos.0 + ori.to_quat() * mounting_offset));
                 let _ = orientations.insert(rider, ori);
                 let _ = velocities.insert(rider, vel);
             }
+            // ...tire the mount while the rider makes it run...
+            if let Some(mount_data) = mount_data.get_mut(entity) {
+                mount_data.update(dt.0, inputs.move_dir.magnitude_squared() > 0.0);
+            }
             // ...and apply the rider's inputs to the mount's controller.
             if let Some(controller) = controllers.get_mut(entity) {
                 *controller = Controller {
@@ -5,6 +5,7 @@ mod tests {
             item::MaterialStatManifest, skills::GeneralSkill, CharacterState, Controller, Energy,
             Ori, PhysicsState, Poise, Pos, Skill, Stats, Vel,
         },
+        interaction::InteractionRanges,
         resources::{DeltaTime, GameMode, Time},
         uid::Uid,
         util::Dir,
@@ -22,6 +23,7 @@ mod tests {
         let mut state = State::new(GameMode::Server);
         let msm = MaterialStatManifest::load().cloned();
         state.ecs_mut().insert(msm);
+        state.ecs_mut().insert(InteractionRanges::load_default());
         state.ecs_mut().read_resource::<Time>();
         state.ecs_mut().read_resource::<DeltaTime>();
         state
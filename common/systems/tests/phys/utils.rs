@@ -5,6 +5,7 @@ use common::{
         Auras, Buffs, CharacterState, Collider, Combo, Controller, Energy, Health, Ori, Pos, Stats,
         Vel,
     },
+    interaction::InteractionRanges,
     resources::{DeltaTime, GameMode, Time},
     skillset_builder::SkillSetBuilder,
     terrain::{Block, BlockKind, SpriteKind, TerrainChunk, TerrainChunkMeta, TerrainGrid},
@@ -27,6 +28,7 @@ pub const DT_F64: f64 = DT_MILLIS as f64 / MILLIS_PER_SEC;
 pub fn setup() -> State {
     let mut state = State::new(GameMode::Server);
     state.ecs_mut().insert(MaterialStatManifest::with_empty());
+    state.ecs_mut().insert(InteractionRanges::load_default());
     state.ecs_mut().read_resource::<Time>();
     state.ecs_mut().read_resource::<DeltaTime>();
     state.ecs_mut().insert(TerrainGrid::new());
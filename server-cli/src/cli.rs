@@ -1,7 +1,7 @@
 use clap::StructOpt;
 use common::comp;
 use server::persistence::SqlLogMode;
-use std::sync::mpsc::Sender;
+use std::{path::PathBuf, sync::mpsc::Sender};
 use tracing::error;
 
 #[derive(Clone, Debug, StructOpt)]
@@ -20,6 +20,54 @@ pub enum Admin {
     },
 }
 
+#[derive(Clone, Debug, StructOpt)]
+pub enum Character {
+    /// Exports a character to a JSON file
+    Export {
+        /// Name of the player who owns the character
+        username: String,
+        /// Id of the character in the database
+        character_id: i64,
+        /// File to write the character to
+        file: PathBuf,
+    },
+    /// Creates a new character from a file written by `character export`
+    Import {
+        /// Name of the player who will own the character
+        username: String,
+        /// File to read the character from
+        file: PathBuf,
+    },
+    /// Locks a character and writes a signed bundle of it for another server,
+    /// the character is removed once that server's receipt is finalized
+    TransferOut {
+        /// Name of the player who owns the character
+        username: String,
+        /// Id of the character in the database
+        character_id: i64,
+        /// Name of the destination server in its `transfer` settings
+        destination: String,
+        /// File to write the bundle to
+        file: PathBuf,
+    },
+    /// Imports a character from a bundle written by `character transfer-out`
+    /// on a trusted server
+    TransferAccept {
+        /// File to read the bundle from
+        file: PathBuf,
+        /// File to write the receipt for the source server to
+        receipt: PathBuf,
+    },
+    /// Removes a transferred character with the receipt of the destination
+    /// server
+    TransferFinalize {
+        /// File to read the receipt from
+        receipt: PathBuf,
+    },
+    /// Prints the public key other servers need to trust this one
+    TransferKey,
+}
+
 #[derive(Clone, Debug, StructOpt)]
 pub enum Shutdown {
     /// Closes the server immediately
@@ -86,6 +134,24 @@ pub struct TuiApp {
 pub enum ArgvCommand {
     #[structopt(flatten)]
     Shared(SharedCommand),
+    /// Export, import or transfer characters
+    Character {
+        #[structopt(subcommand)]
+        command: Character,
+    },
+    /// Prints who deleted or renamed characters, banned players or granted
+    /// items, and when
+    AuditLog {
+        /// Only show operations performed by this player
+        #[structopt(long)]
+        player: Option<String>,
+        /// Only show operations at or after this time (RFC 3339 or YYYY-MM-DD)
+        #[structopt(long)]
+        since: Option<String>,
+        /// Only show operations before this time (RFC 3339 or YYYY-MM-DD)
+        #[structopt(long)]
+        until: Option<String>,
+    },
 }
 
 #[derive(StructOpt)]
@@ -112,6 +178,16 @@ pub struct ArgvApp {
     #[structopt(default_value_t, long, short, possible_values = SqlLogMode::variants())]
     /// Enables SQL logging
     pub sql_log_mode: SqlLogMode,
+    #[structopt(long, value_name = "RADIUS")]
+    /// Pregenerates the chunks within RADIUS chunks of the spawn point before
+    /// the server starts ticking
+    ///
+    /// Chunks which were already pregenerated are skipped, so an interrupted
+    /// pregeneration can be resumed by running it again.
+    pub pregen: Option<u32>,
+    #[structopt(long, value_name = "THREADS")]
+    /// Number of threads to pregenerate chunks on, defaults to all of them
+    pub pregen_workers: Option<usize>,
     #[structopt(subcommand)]
     pub command: Option<ArgvCommand>,
 }
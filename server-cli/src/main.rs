@@ -14,7 +14,7 @@ mod shutdown_coordinator;
 mod tui_runner;
 mod tuilog;
 use crate::{
-    cli::{Admin, ArgvApp, ArgvCommand, Message, SharedCommand, Shutdown},
+    cli::{Admin, ArgvApp, ArgvCommand, Character, Message, SharedCommand, Shutdown},
     shutdown_coordinator::ShutdownCoordinator,
     tui_runner::Tui,
     tuilog::TuiLog,
@@ -28,7 +28,7 @@ use std::{
     sync::{atomic::AtomicBool, mpsc, Arc},
     time::Duration,
 };
-use tracing::{info, trace};
+use tracing::{error, info, trace};
 
 lazy_static::lazy_static! {
     pub static ref LOG: TuiLog<'static> = TuiLog::default();
@@ -46,6 +46,8 @@ fn main() -> io::Result<()> {
     let noninteractive = app.non_interactive;
     let no_auth = app.no_auth;
     let sql_log_mode = app.sql_log_mode;
+    let pregen = app.pregen;
+    let pregen_workers = app.pregen_workers;
 
     // noninteractive implies basic
     let basic = basic || noninteractive;
@@ -144,9 +146,111 @@ fn main() -> io::Result<()> {
                 }
                 Ok(())
             },
+            ArgvCommand::Character { command } => {
+                let login_provider = server::login_provider::LoginProvider::new(
+                    server_settings.auth_server_address.clone(),
+                    runtime,
+                );
+
+                let success = match command {
+                    Character::Export {
+                        username,
+                        character_id,
+                        file,
+                    } => server::export_character(
+                        &username,
+                        character_id,
+                        &file,
+                        &login_provider,
+                        &database_settings,
+                    ),
+                    Character::Import { username, file } => server::import_character(
+                        &username,
+                        &file,
+                        &login_provider,
+                        &server_settings,
+                        &database_settings,
+                        &server_data_dir,
+                    ),
+                    Character::TransferOut {
+                        username,
+                        character_id,
+                        destination,
+                        file,
+                    } => server::transfer_character_out(
+                        &username,
+                        character_id,
+                        &destination,
+                        &file,
+                        &login_provider,
+                        &server_settings,
+                        &database_settings,
+                        &server_data_dir,
+                    ),
+                    Character::TransferAccept { file, receipt } => {
+                        server::accept_character_transfer(
+                            &file,
+                            &receipt,
+                            &server_settings,
+                            &database_settings,
+                            &server_data_dir,
+                        )
+                    },
+                    Character::TransferFinalize { receipt } => server::finalize_character_transfer(
+                        &receipt,
+                        &server_settings,
+                        &database_settings,
+                    ),
+                    Character::TransferKey => match server::transfer_public_key(&server_data_dir) {
+                        Some(key) => {
+                            println!("{}", key);
+                            true
+                        },
+                        None => false,
+                    },
+                };
+                if !success {
+                    std::process::exit(1);
+                }
+                Ok(())
+            },
+            ArgvCommand::AuditLog {
+                player,
+                since,
+                until,
+            } => {
+                let login_provider = server::login_provider::LoginProvider::new(
+                    server_settings.auth_server_address.clone(),
+                    runtime,
+                );
+
+                match server::audit_log(
+                    player.as_deref(),
+                    since.as_deref(),
+                    until.as_deref(),
+                    &login_provider,
+                    &database_settings,
+                ) {
+                    Some(entries) => {
+                        for entry in &entries {
+                            println!("{}", entry);
+                        }
+                        info!("{} audit log entries", entries.len());
+                    },
+                    None => std::process::exit(1),
+                }
+                Ok(())
+            },
         };
     }
 
+    // Refuse to start before binding any sockets if the database was migrated by
+    // a newer server
+    if let Err(e) = server::persistence::check_schema_version(&database_settings) {
+        error!("{}", e);
+        std::process::exit(1);
+    }
+
     // Panic hook to ensure that console mode is set back correctly if in non-basic
     // mode
     if !basic {
@@ -173,6 +277,16 @@ fn main() -> io::Result<()> {
     )
     .expect("Failed to create server instance!");
 
+    // Pregeneration can be interrupted like a graceful shutdown, what was generated
+    // so far is kept
+    if let Some(radius) = pregen {
+        server.pregenerate(
+            radius,
+            pregen_workers.unwrap_or_else(num_cpus::get),
+            &sigusr1_signal,
+        );
+    }
+
     // Collect addresses that the server is listening to log.
     let gameserver_addresses = protocols_and_addresses
         .into_iter()
@@ -24,6 +24,14 @@ pub enum CreationError {
     InvalidBody,
 }
 
+/// Whether characters can be created with this body
+pub fn check_body(body: Body) -> Result<(), CreationError> {
+    match body {
+        Body::Humanoid(body) if body.invalid_feature().is_none() => Ok(()),
+        _ => Err(CreationError::InvalidBody),
+    }
+}
+
 pub fn create_character(
     entity: Entity,
     player_uuid: String,
@@ -31,15 +39,14 @@ pub fn create_character(
     character_mainhand: Option<String>,
     character_offhand: Option<String>,
     body: Body,
+    hardcore: bool,
     character_updater: &mut WriteExpect<'_, CharacterUpdater>,
 ) -> Result<(), CreationError> {
     // quick fix whitelist validation for now; eventually replace the
     // `Option<String>` with an index into a server-provided list of starter
     // items, and replace `comp::body::Body` with `comp::body::humanoid::Body`
     // throughout the messages involved
-    if !matches!(body, Body::Humanoid(_)) {
-        return Err(CreationError::InvalidBody);
-    }
+    check_body(body)?;
     if !VALID_STARTER_ITEMS.contains(&[character_mainhand.as_deref(), character_offhand.as_deref()])
     {
         return Err(CreationError::InvalidWeapon);
@@ -72,9 +79,16 @@ pub fn create_character(
         skill_set,
         inventory,
         waypoint,
+        position: None,
         pets: Vec::new(),
         active_abilities: Default::default(),
         map_marker,
+        hotbar: Default::default(),
+        settings: Default::default(),
+        vitals: None,
+        buffs: Vec::new(),
+        lantern_fuel: None,
+        hardcore,
     });
     Ok(())
 }
@@ -87,9 +101,7 @@ pub fn edit_character(
     body: Body,
     character_updater: &mut WriteExpect<'_, CharacterUpdater>,
 ) -> Result<(), CreationError> {
-    if !matches!(body, Body::Humanoid(_)) {
-        return Err(CreationError::InvalidBody);
-    }
+    check_body(body)?;
 
     character_updater.edit_character(entity, player_uuid, id, character_alias, (body,));
     Ok(())
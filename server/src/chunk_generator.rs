@@ -1,6 +1,6 @@
-use crate::metrics::ChunkGenMetrics;
 #[cfg(not(feature = "worldgen"))]
 use crate::test_world::{IndexOwned, World};
+use crate::{metrics::ChunkGenMetrics, pregen::ChunkCache};
 use common::{
     calendar::Calendar, generation::ChunkSupplement, resources::TimeOfDay, slowjob::SlowJobPool,
     terrain::TerrainChunk,
@@ -25,18 +25,23 @@ pub struct ChunkGenerator {
     chunk_rx: crossbeam_channel::Receiver<ChunkGenResult>,
     pending_chunks: HashMap<Vec2<i32>, Arc<AtomicBool>>,
     metrics: Arc<ChunkGenMetrics>,
+    cache: Arc<ChunkCache>,
 }
 impl ChunkGenerator {
-    pub fn new(metrics: ChunkGenMetrics) -> Self {
+    pub fn new(metrics: ChunkGenMetrics, cache: ChunkCache) -> Self {
         let (chunk_tx, chunk_rx) = crossbeam_channel::unbounded();
         Self {
             chunk_tx,
             chunk_rx,
             pending_chunks: HashMap::new(),
             metrics: Arc::new(metrics),
+            cache: Arc::new(cache),
         }
     }
 
+    /// The pregenerated chunks used as a starting point for generation
+    pub fn cache(&self) -> &Arc<ChunkCache> { &self.cache }
+
     pub fn generate_chunk(
         &mut self,
         entity: Option<EcsEntity>,
@@ -54,11 +59,19 @@ impl ChunkGenerator {
         let cancel = Arc::new(AtomicBool::new(false));
         v.insert(Arc::clone(&cancel));
         let chunk_tx = self.chunk_tx.clone();
+        // Pregenerated chunks don't have calendar events applied to them
+        let cache = (time.1.events().len() == 0).then(|| Arc::clone(&self.cache));
         self.metrics.chunks_requested.inc();
         slowjob_pool.spawn("CHUNK_GENERATOR", move || {
             let index = index.as_index_ref();
             let payload = world
-                .generate_chunk(index, key, || cancel.load(Ordering::Relaxed), Some(time))
+                .generate_chunk_with_base(
+                    index,
+                    key,
+                    cache.and_then(|cache| cache.load(key)),
+                    || cancel.load(Ordering::Relaxed),
+                    Some(time),
+                )
                 .map_err(|_| entity);
             let _ = chunk_tx.send((key, payload));
         });
@@ -1,4 +1,4 @@
-use common_net::msg::{ClientType, ServerGeneral, ServerMsg};
+use common_net::msg::{ClientType, CustomItemSupport, ServerGeneral, ServerMsg};
 use network::{Message, Participant, Stream, StreamError, StreamParams};
 use serde::{de::DeserializeOwned, Serialize};
 use specs::Component;
@@ -15,6 +15,8 @@ pub struct Client {
     pub participant: Option<Participant>,
     pub last_ping: Mutex<f64>,
     pub login_msg_sent: AtomicBool,
+    /// Whether the client can register custom items, sent when it registers
+    pub custom_items: Mutex<CustomItemSupport>,
 
     //TODO: improve network crate so that `send` is no longer `&mut self` and we can get rid of
     // this Mutex. This Mutex is just to please the compiler as we do not get into contention
@@ -65,6 +67,7 @@ impl Client {
             participant: Some(participant),
             last_ping: Mutex::new(last_ping),
             login_msg_sent: AtomicBool::new(false),
+            custom_items: Mutex::new(CustomItemSupport::Unsupported),
             general_stream: Mutex::new(general_stream),
             ping_stream: Mutex::new(ping_stream),
             register_stream: Mutex::new(register_stream),
@@ -95,7 +98,7 @@ impl Client {
                     ServerGeneral::CharacterDataLoadError(_)
                     | ServerGeneral::CharacterListUpdate(_)
                     | ServerGeneral::CharacterActionError(_)
-                    | ServerGeneral::CharacterCreated(_)
+                    | ServerGeneral::CharacterCreated(..)
                     | ServerGeneral::CharacterEdited(_)
                     | ServerGeneral::CharacterSuccess => {
                         self.character_screen_stream.lock().unwrap().send(g)
@@ -170,7 +173,7 @@ impl Client {
                     ServerGeneral::CharacterDataLoadError(_)
                     | ServerGeneral::CharacterListUpdate(_)
                     | ServerGeneral::CharacterActionError(_)
-                    | ServerGeneral::CharacterCreated(_)
+                    | ServerGeneral::CharacterCreated(..)
                     | ServerGeneral::CharacterEdited(_)
                     | ServerGeneral::CharacterSuccess
                     | ServerGeneral::SpectatorSuccess(_) => {
@@ -191,6 +194,9 @@ impl Client {
                     | ServerGeneral::FinishedTrade(_)
                     | ServerGeneral::MapMarker(_)
                     | ServerGeneral::WeatherUpdate(_)
+                    | ServerGeneral::DangerOverlay(_)
+                    | ServerGeneral::LocationChanged(_)
+                    | ServerGeneral::HotbarUpdate(_, _)
                     | ServerGeneral::SpectatePosition(_) => {
                         PreparedMsg::new(2, &g, &self.in_game_stream_params)
                     },
@@ -210,6 +216,7 @@ impl Client {
                     | ServerGeneral::CompSync(_, _)
                     | ServerGeneral::CreateEntity(_)
                     | ServerGeneral::DeleteEntity(_)
+                    | ServerGeneral::LanternUpdate(_, _)
                     | ServerGeneral::Disconnect(_)
                     | ServerGeneral::Notification(_) => {
                         PreparedMsg::new(3, &g, &self.general_stream_params)
@@ -4,8 +4,13 @@
 
 use crate::{
     client::Client,
-    location::Locations,
+    danger::DangerOverlay,
+    location::{self, Locations},
     login_provider::LoginProvider,
+    persistence::{
+        audit_log::AuditEventKind, character_loader::CharacterLoader,
+        character_updater::CharacterUpdater,
+    },
     presence::Presence,
     settings::{
         Ban, BanAction, BanInfo, EditableSetting, SettingError, WhitelistInfo, WhitelistRecord,
@@ -34,7 +39,7 @@ use common::{
         invite::InviteKind,
         AdminRole, ChatType, Inventory, Item, LightEmitter, WaypointArea,
     },
-    depot,
+    danger, depot,
     effect::Effect,
     event::{EventBus, ServerEvent},
     generation::{EntityConfig, EntityInfo},
@@ -135,6 +140,9 @@ fn do_command(
         ServerChatCommand::BuildAreaList => handle_build_area_list,
         ServerChatCommand::BuildAreaRemove => handle_build_area_remove,
         ServerChatCommand::Campfire => handle_spawn_campfire,
+        ServerChatCommand::Characters => handle_characters,
+        ServerChatCommand::Danger => handle_danger,
+        ServerChatCommand::DbMaintenance => handle_db_maintenance,
         ServerChatCommand::DebugColumn => handle_debug_column,
         ServerChatCommand::DisconnectAllPlayers => handle_disconnect_all_players,
         ServerChatCommand::DropAll => handle_drop_all,
@@ -185,6 +193,7 @@ fn do_command(
         ServerChatCommand::Time => handle_time,
         ServerChatCommand::Tp => handle_tp,
         ServerChatCommand::Unban => handle_unban,
+        ServerChatCommand::Unpossess => handle_unpossess,
         ServerChatCommand::Version => handle_version,
         ServerChatCommand::Waypoint => handle_waypoint,
         ServerChatCommand::Wiring => handle_spawn_wiring,
@@ -303,6 +312,33 @@ fn real_role(server: &Server, uuid: Uuid, descriptor: &str) -> CmdResult<AdminRo
         .ok_or_else(|| format!("Cannot get administrator roles for {:?} uuid", descriptor))
 }
 
+/// Records an operation of the client in the audit log, along with the
+/// character of the target if it has one. Nothing is recorded for clients that
+/// aren't players.
+fn log_audit_event(
+    server: &Server,
+    client: EcsEntity,
+    target: Option<EcsEntity>,
+    kind: AuditEventKind,
+    detail: String,
+) {
+    let ecs = server.state.ecs();
+    if let Some(player_uuid) = ecs
+        .read_storage::<comp::Player>()
+        .get(client)
+        .map(|player| player.uuid().to_string())
+    {
+        let character_id = target
+            .and_then(|target| ecs.read_storage::<Presence>().get(target).map(|p| p.kind))
+            .and_then(|kind| match kind {
+                PresenceKind::Character(character_id) => Some(character_id),
+                PresenceKind::Spectator | PresenceKind::Possessor => None,
+            });
+        ecs.write_resource::<CharacterUpdater>()
+            .log_event(kind, player_uuid, character_id, detail);
+    }
+}
+
 // Fallibly get uid of entity with the given descriptor (used for error
 // message).
 fn uid(server: &Server, target: EcsEntity, descriptor: &str) -> CmdResult<Uid> {
@@ -510,7 +546,7 @@ fn handle_drop_all(
 
 fn handle_give_item(
     server: &mut Server,
-    _client: EcsEntity,
+    client: EcsEntity,
     target: EcsEntity,
     args: Vec<String>,
     action: &ServerChatCommand,
@@ -573,6 +609,16 @@ fn handle_give_item(
                 comp::InventoryUpdate::new(comp::InventoryUpdateEvent::Given),
                 "target",
             )?;
+            log_audit_event(
+                server,
+                client,
+                Some(target),
+                AuditEventKind::ItemsGranted,
+                match &res {
+                    Ok(()) => format!("{} x {}", give_amount, item_name),
+                    Err(e) => format!("{} x {} ({})", give_amount, item_name, e),
+                },
+            );
             res
         } else {
             Err(format!("Invalid item: {}", item_name))
@@ -1294,6 +1340,7 @@ fn handle_spawn_training_dummy(
             body,
         )
         .with(comp::Vel(vel))
+        .with(comp::TrainingDummy::default())
         .build();
 
     server.notify_client(
@@ -1490,6 +1537,53 @@ fn handle_spawn_campfire(
     Ok(())
 }
 
+/// Number of characters listed per page of `/characters`
+const CHARACTERS_PER_PAGE: u32 = 20;
+
+fn handle_characters(
+    server: &mut Server,
+    client: EcsEntity,
+    _target: EcsEntity,
+    args: Vec<String>,
+    action: &ServerChatCommand,
+) -> CmdResult<()> {
+    let page = parse_cmd_args!(args, u32).unwrap_or(1);
+    if page == 0 {
+        return Err(action.help_string());
+    }
+    // The list is sent to the client once it's loaded, see `Server::tick`
+    server
+        .state
+        .ecs()
+        .read_resource::<CharacterLoader>()
+        .load_character_summaries(
+            client,
+            (page - 1).saturating_mul(CHARACTERS_PER_PAGE),
+            CHARACTERS_PER_PAGE,
+        );
+    Ok(())
+}
+
+fn handle_db_maintenance(
+    server: &mut Server,
+    client: EcsEntity,
+    _target: EcsEntity,
+    _args: Vec<String>,
+    _action: &ServerChatCommand,
+) -> CmdResult<()> {
+    // The summary is sent to the client once it's done, see `Server::tick`
+    server
+        .state
+        .ecs()
+        .write_resource::<CharacterUpdater>()
+        .run_maintenance(Some(client));
+    server.notify_client(
+        client,
+        ServerGeneral::server_msg(ChatType::CommandInfo, "Started database maintenance"),
+    );
+    Ok(())
+}
+
 fn handle_safezone(
     server: &mut Server,
     client: EcsEntity,
@@ -1883,6 +1977,13 @@ fn handle_kit(
             client,
             ServerGeneral::server_msg(ChatType::CommandInfo, format!("Gave kit: {}", kit_name)),
         );
+        log_audit_event(
+            server,
+            client,
+            Some(target),
+            AuditEventKind::ItemsGranted,
+            format!("Kit {}", kit_name),
+        );
     };
     let name = parse_cmd_args!(args, String).ok_or_else(|| action.help_string())?;
 
@@ -2987,6 +3088,29 @@ fn handle_sudo(
     }
 }
 
+fn handle_unpossess(
+    server: &mut Server,
+    _client: EcsEntity,
+    target: EcsEntity,
+    _args: Vec<String>,
+    _action: &ServerChatCommand,
+) -> CmdResult<()> {
+    if !server
+        .state
+        .ecs()
+        .read_storage::<comp::Possessing>()
+        .contains(target)
+    {
+        return Err("You are not possessing anything".into());
+    }
+    let uid = uid(server, target, "target")?;
+    server
+        .state
+        .mut_resource::<EventBus<ServerEvent>>()
+        .emit_now(ServerEvent::Unpossess(uid));
+    Ok(())
+}
+
 fn handle_version(
     server: &mut Server,
     client: EcsEntity,
@@ -3196,6 +3320,19 @@ fn handle_ban(
         edit_setting_feedback(server, client, edit, || {
             format!("{} is already on the banlist", username)
         })?;
+        log_audit_event(
+            server,
+            client,
+            None,
+            AuditEventKind::PlayerBanned,
+            format!(
+                "{} ({}) until {}: {}",
+                username,
+                player_uuid,
+                end_date.map_or_else(|| "forever".to_owned(), |date| date.to_rfc3339()),
+                reason
+            ),
+        );
         // If the player is online kick them (this may fail if the player is a hardcoded
         // admin; we don't care about that case because hardcoded admins can log on even
         // if they're on the ban list).
@@ -3392,7 +3529,15 @@ fn handle_unban(
 
         edit_setting_feedback(server, client, edit, || {
             format!("{} was already unbanned", username)
-        })
+        })?;
+        log_audit_event(
+            server,
+            client,
+            None,
+            AuditEventKind::PlayerUnbanned,
+            format!("{} ({})", username, player_uuid),
+        );
+        Ok(())
     } else {
         Err(action.help_string())
     }
@@ -3714,6 +3859,36 @@ fn handle_lightning(
     Ok(())
 }
 
+fn handle_danger(
+    server: &mut Server,
+    client: EcsEntity,
+    target: EcsEntity,
+    _args: Vec<String>,
+    _action: &ServerChatCommand,
+) -> CmdResult<()> {
+    let pos = position(server, target, "target")?;
+    // The same lookup as the location players are told about
+    let location = location::location_at(
+        &server.world,
+        server.index.as_index_ref(),
+        &server.state.ecs().read_resource::<DangerOverlay>(),
+        pos.0.xy(),
+    );
+    let mut msg = format!(
+        "Danger rating here: {}/{}",
+        location.danger,
+        danger::MAX_DANGER
+    );
+    if let Some(site_name) = &location.site_name {
+        msg.push_str(&format!(" ({})", site_name));
+    }
+    server.notify_client(
+        client,
+        ServerGeneral::server_msg(ChatType::CommandInfo, msg),
+    );
+    Ok(())
+}
+
 fn handle_body(
     server: &mut Server,
     _client: EcsEntity,
@@ -0,0 +1,123 @@
+use common::comp::item::load_item_defs_in_dir;
+use common_net::msg::CustomItems;
+use tracing::{info, warn};
+
+/// Loads the items from the asset directories in
+/// [`Settings::custom_item_dirs`](crate::Settings::custom_item_dirs), which are
+/// sent to clients so they can show items that aren't in the vanilla assets.
+pub fn load_custom_items(dirs: &[String]) -> CustomItems {
+    let defs = dirs
+        .iter()
+        .filter_map(|dir| {
+            load_item_defs_in_dir(dir)
+                .map_err(|err| warn!(?err, "Failed to load custom items from {}", dir))
+                .ok()
+        })
+        .flatten()
+        .collect::<Vec<_>>();
+    let custom_items = CustomItems::new(defs.iter().map(|def| &**def));
+    if !custom_items.is_empty() {
+        info!(
+            "Loaded {} custom items (hash {:x})",
+            custom_items.len(),
+            custom_items.hash()
+        );
+    }
+    custom_items
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use common::comp::item::{
+        register_custom_items, registered_custom_items_hash, Item, ItemBase, ItemDef,
+        ItemDefinitionId, ItemDesc, Quality,
+    };
+    use common_net::msg::{CustomItemManifest, CustomItemSupport, CustomItems};
+
+    pub(crate) const TEST_RELIC: &str = "server.custom_items.test.relic";
+
+    fn relic_def() -> ItemDef {
+        let mut def = ItemDef::unknown_modded(TEST_RELIC);
+        def.name = "Relic of Testing".to_owned();
+        def.quality = Quality::Epic;
+        def
+    }
+
+    /// Sends the manifest through the same encoding as the network does
+    fn transfer(manifest: CustomItemManifest) -> CustomItemManifest {
+        bincode::deserialize(&bincode::serialize(&manifest).unwrap()).unwrap()
+    }
+
+    /// Registers [`TEST_RELIC`] the way a client does when joining a server
+    /// that has it as a custom item
+    pub(crate) fn receive_test_relic() {
+        let def = relic_def();
+        let custom_items = CustomItems::new([&def]);
+        let manifest = transfer(
+            custom_items
+                .manifest(CustomItemSupport::Supported { known_hash: None })
+                .expect("Custom items are sent to clients supporting them"),
+        );
+        let defs = manifest
+            .decode()
+            .unwrap()
+            .expect("Definitions are sent to clients that don't know them");
+        register_custom_items(manifest.hash, defs);
+    }
+
+    #[test]
+    fn client_resolves_received_custom_items() {
+        receive_test_relic();
+
+        let item = Item::new_from_asset_expect(TEST_RELIC);
+        assert_eq!(item.name(), "Relic of Testing");
+        assert_eq!(item.quality(), Quality::Epic);
+
+        // Items are sent as their id, e.g. in inventory updates
+        let received: Item = bincode::deserialize(&bincode::serialize(&item).unwrap()).unwrap();
+        assert_eq!(received.name(), "Relic of Testing");
+        assert_eq!(
+            received.item_definition_id(),
+            ItemDefinitionId::Simple(TEST_RELIC)
+        );
+    }
+
+    #[test]
+    fn reconnect_skips_known_custom_items() {
+        receive_test_relic();
+        let custom_items = CustomItems::new([&relic_def()]);
+
+        let known_hash = registered_custom_items_hash();
+        assert_eq!(known_hash, Some(custom_items.hash()));
+        let manifest = custom_items
+            .manifest(CustomItemSupport::Supported { known_hash })
+            .unwrap();
+        assert_eq!(manifest.items, None);
+        assert!(matches!(manifest.decode(), Ok(None)));
+    }
+
+    #[test]
+    fn unsupported_clients_see_unknown_modded_items() {
+        let custom_items = CustomItems::new([&relic_def()]);
+        assert_eq!(custom_items.manifest(CustomItemSupport::Unsupported), None);
+
+        // An item whose definition the client never received keeps its id
+        let id = "server.custom_items.test.never_sent";
+        match bincode::deserialize(&bincode::serialize(id).unwrap()).unwrap() {
+            ItemBase::Simple(def) => {
+                assert_eq!(def.name, "Unknown Modded Item");
+                assert_eq!(def.id(), id);
+            },
+            ItemBase::Modular(_) => panic!("{} is not a modular item", id),
+        }
+    }
+
+    #[test]
+    fn oversized_manifests_are_rejected() {
+        let manifest = CustomItemManifest {
+            hash: 0,
+            items: Some(vec![vec![0; common_net::msg::MAX_CUSTOM_ITEM_SIZE + 1]]),
+        };
+        assert!(manifest.decode().is_err());
+    }
+}
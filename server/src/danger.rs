@@ -0,0 +1,57 @@
+use common::danger::{self, DangerGrid, DangerSource};
+use common_net::msg::CompressedData;
+use common_state::State;
+
+/// The server's copy of the danger map overlay (see [`common::danger`]).
+///
+/// The overlay is computed once when the world is loaded. Nothing changes the
+/// danger of an area afterwards, so clients are only sent the whole overlay
+/// when they enter the game.
+pub struct DangerOverlay {
+    grid: DangerGrid,
+    /// What the grid was generated from, to rate exact positions rather than
+    /// whole cells
+    sources: Vec<DangerSource>,
+    /// Compressed copy of the grid, sent to each client when they enter the
+    /// game.
+    compressed: CompressedData<DangerGrid>,
+}
+
+impl DangerOverlay {
+    pub fn new(grid: DangerGrid, sources: Vec<DangerSource>) -> Self {
+        Self {
+            compressed: CompressedData::compress(&grid, 5),
+            grid,
+            sources,
+        }
+    }
+
+    pub fn grid(&self) -> &DangerGrid { &self.grid }
+
+    /// The danger at a world position itself, which may differ from the
+    /// danger of its cell near the edge of a settlement or dungeon
+    pub fn danger_at(&self, wpos: vek::Vec2<f32>) -> u8 { danger::danger_at(wpos, &self.sources) }
+
+    pub fn compressed(&self) -> &CompressedData<DangerGrid> { &self.compressed }
+}
+
+#[cfg(feature = "worldgen")]
+pub fn init(state: &mut State, world: &world::World, index: world::IndexRef) {
+    use common::danger::CHUNKS_PER_CELL;
+
+    let size = world
+        .sim()
+        .get_size()
+        .map(|e| (e + CHUNKS_PER_CELL - 1) / CHUNKS_PER_CELL);
+    let sources = world.danger_sources(index);
+    let grid = DangerGrid::generate(size, &sources);
+    state.ecs_mut().insert(DangerOverlay::new(grid, sources));
+}
+
+#[cfg(not(feature = "worldgen"))]
+pub fn init(state: &mut State) {
+    state.ecs_mut().insert(DangerOverlay::new(
+        DangerGrid::new(vek::Vec2::zero()),
+        Vec::new(),
+    ));
+}
@@ -0,0 +1,296 @@
+//! What players drop when they die, depending on where they died (see
+//! [`DeathLootSettings`])
+
+use crate::{
+    danger::DangerOverlay,
+    location::exact_location_at,
+    presence::Presence,
+    settings::{DeathLootPolicy, DeathLootSettings},
+    state_ext::StateExt,
+    Server,
+};
+use common::{
+    comp::{
+        self,
+        inventory::item::{tool::AbilityMap, MaterialStatManifest, Quality},
+        Inventory, Item,
+    },
+    location::LocationInfo,
+};
+use common_net::msg::PresenceKind;
+use common_state::State;
+use rand::{seq::SliceRandom, Rng};
+use specs::{Builder, Entity as EcsEntity, WorldExt};
+use vek::*;
+
+impl DeathLootSettings {
+    /// The policy for a character that died at `location`
+    pub fn policy(&self, location: &LocationInfo, hardcore: bool) -> DeathLootPolicy {
+        self.hardcore
+            .filter(|_| hardcore)
+            .or_else(|| {
+                location
+                    .site_name
+                    .as_ref()
+                    .and_then(|name| self.sites.get(name).copied())
+            })
+            .unwrap_or(if location.is_safe() {
+                self.safe
+            } else {
+                self.wilderness
+            })
+    }
+}
+
+/// The policy for the character of a player dying exactly where it currently
+/// is. `None` for anything else, such as NPCs and possessed entities.
+pub fn death_loot_policy(server: &Server, entity: EcsEntity) -> Option<DeathLootPolicy> {
+    let ecs = server.state.ecs();
+    if !matches!(
+        ecs.read_storage::<Presence>().get(entity).map(|p| p.kind),
+        Some(PresenceKind::Character(_))
+    ) {
+        return None;
+    }
+    let pos = ecs.read_storage::<comp::Pos>().get(entity)?.0;
+    let location = exact_location_at(
+        &server.world,
+        server.index.as_index_ref(),
+        &ecs.read_resource::<DangerOverlay>(),
+        pos.xy(),
+    );
+    let hardcore = ecs.read_storage::<comp::Hardcore>().contains(entity);
+    Some(
+        server
+            .settings()
+            .gameplay
+            .death_loot
+            .policy(&location, hardcore),
+    )
+}
+
+/// Takes the items dropped under `policy` out of an inventory. Debug items are
+/// never dropped.
+pub fn take_death_loot(
+    inventory: &mut Inventory,
+    policy: DeathLootPolicy,
+    ability_map: &AbilityMap,
+    msm: &MaterialStatManifest,
+    rng: &mut impl Rng,
+) -> Vec<Item> {
+    let fraction = match policy {
+        DeathLootPolicy::KeepAll => return Vec::new(),
+        DeathLootPolicy::DropFraction(fraction) => fraction.clamp(0.0, 1.0),
+        DeathLootPolicy::DropAll => 1.0,
+    };
+
+    let mut stacks = Vec::new();
+    let mut unstackables = Vec::new();
+    for (slot, item) in inventory.slots_with_id() {
+        match item {
+            Some(item) if matches!(item.quality(), Quality::Debug) => {},
+            Some(item) if item.is_stackable() => stacks.push(slot),
+            Some(_) => unstackables.push(slot),
+            None => {},
+        }
+    }
+
+    let mut loot = Vec::new();
+    for slot in stacks {
+        if let Some(Some(item)) = inventory.slot_mut(slot) {
+            let amount = portion(item.amount(), fraction);
+            if amount == item.amount() {
+                loot.extend(inventory.remove(slot));
+            } else if amount > 0 {
+                let mut dropped = item.duplicate(ability_map, msm);
+                if dropped.set_amount(amount).is_ok() && item.decrease_amount(amount).is_ok() {
+                    loot.push(dropped);
+                }
+            }
+        }
+    }
+
+    // Which of the items that don't stack are dropped is up to chance
+    unstackables.shuffle(rng);
+    let dropped = portion(unstackables.len() as u32, fraction) as usize;
+    loot.extend(
+        unstackables
+            .into_iter()
+            .take(dropped)
+            .filter_map(|slot| inventory.remove(slot)),
+    );
+    loot
+}
+
+/// `fraction` of `amount`, rounded down. A little leeway is given so that
+/// fractions that should divide evenly (e.g. 0.7 of 10) aren't rounded down
+/// because of floating point errors.
+fn portion(amount: u32, fraction: f32) -> u32 {
+    ((f64::from(amount) * f64::from(fraction) + 1e-4).floor() as u32).min(amount)
+}
+
+/// Scatters the death loot of an entity around where it died
+pub fn drop_death_loot(state: &mut State, entity: EcsEntity, policy: DeathLootPolicy) {
+    let pos = match state.ecs().read_storage::<comp::Pos>().get(entity) {
+        Some(pos) => pos.0,
+        None => return,
+    };
+    let mut rng = rand::thread_rng();
+    let loot = match state.ecs().write_storage::<Inventory>().get_mut(entity) {
+        Some(mut inventory) => take_death_loot(
+            &mut inventory,
+            policy,
+            &state.ecs().read_resource::<AbilityMap>(),
+            &state.ecs().read_resource::<MaterialStatManifest>(),
+            &mut rng,
+        ),
+        None => return,
+    };
+
+    for item in loot {
+        let offset = Vec2::new(rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0));
+        state
+            .create_item_drop(comp::Pos(pos + offset.with_z(0.25)), item)
+            .build();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::{
+        assets::AssetExt,
+        comp::inventory::item::ItemDefinitionId,
+        danger::{self, DangerGrid, DangerSource, DangerSourceKind},
+        terrain::BiomeKind,
+    };
+    use rand::{rngs::StdRng, SeedableRng};
+
+    const APPLE: &str = "common.items.food.apple";
+    const SWORD: &str = "common.items.weapons.sword.starter";
+    const ADMIN_SWORD: &str = "common.items.debug.admin_sword";
+
+    fn settings() -> DeathLootSettings {
+        DeathLootSettings {
+            safe: DeathLootPolicy::KeepAll,
+            wilderness: DeathLootPolicy::DropFraction(0.25),
+            sites: std::iter::once(("Bloodmoor Pit".to_owned(), DeathLootPolicy::DropAll))
+                .collect(),
+            hardcore: Some(DeathLootPolicy::DropFraction(0.5)),
+        }
+    }
+
+    fn location(site_name: Option<&str>, danger: u8) -> LocationInfo {
+        LocationInfo {
+            site_name: site_name.map(str::to_owned),
+            biome: BiomeKind::Grassland,
+            danger,
+        }
+    }
+
+    /// Kills a character carrying 10 apples, 3 swords and an admin sword
+    /// where `policy` applies, returning what it dropped and what it kept
+    fn die(policy: DeathLootPolicy) -> (Vec<Item>, Inventory) {
+        let mut inventory = Inventory::with_empty();
+        let mut apples = Item::new_from_asset_expect(APPLE);
+        apples.set_amount(10).unwrap();
+        inventory.push(apples).unwrap();
+        for _ in 0..3 {
+            inventory.push(Item::new_from_asset_expect(SWORD)).unwrap();
+        }
+        inventory
+            .push(Item::new_from_asset_expect(ADMIN_SWORD))
+            .unwrap();
+
+        let loot = take_death_loot(
+            &mut inventory,
+            policy,
+            &AbilityMap::load().read(),
+            &MaterialStatManifest::load().read(),
+            &mut StdRng::seed_from_u64(0),
+        );
+        (loot, inventory)
+    }
+
+    fn count<'a>(items: impl Iterator<Item = &'a Item>, id: &str) -> u32 {
+        items
+            .filter(|item| item.item_definition_id() == ItemDefinitionId::Simple(id))
+            .map(Item::amount)
+            .sum()
+    }
+
+    fn assert_outcome(policy: DeathLootPolicy, [apples, swords, admin_swords]: [u32; 3]) {
+        let (loot, inventory) = die(policy);
+        let ids = [APPLE, SWORD, ADMIN_SWORD];
+        assert_eq!(
+            ids.map(|id| count(loot.iter(), id)),
+            [apples, swords, admin_swords],
+            "dropped with {:?}",
+            policy
+        );
+        assert_eq!(
+            ids.map(|id| count(inventory.slots().flatten(), id)),
+            [10 - apples, 3 - swords, 1 - admin_swords],
+            "kept with {:?}",
+            policy
+        );
+    }
+
+    #[test]
+    fn policy_depends_on_where_characters_die() {
+        let settings = settings();
+
+        let town = location(Some("Frostwood"), danger::SAFE_DANGER);
+        let pit = location(Some("Bloodmoor Pit"), danger::SAFE_DANGER);
+        let wilderness = location(None, danger::WILDERNESS_DANGER);
+
+        assert_eq!(settings.policy(&town, false), DeathLootPolicy::KeepAll);
+        assert_eq!(settings.policy(&pit, false), DeathLootPolicy::DropAll);
+        assert_eq!(
+            settings.policy(&wilderness, false),
+            DeathLootPolicy::DropFraction(0.25)
+        );
+        for location in [town, pit, wilderness] {
+            assert_eq!(
+                settings.policy(&location, true),
+                DeathLootPolicy::DropFraction(0.5)
+            );
+        }
+    }
+
+    #[test]
+    fn town_edges_are_judged_by_where_characters_die() {
+        let settings = settings();
+        // Near the edge of its cell, so that its safe area reaches into the next one
+        let town = DangerSource {
+            wpos: Vec2::new(2500.0, 2304.0),
+            kind: DangerSourceKind::Settlement,
+        };
+        let overlay =
+            DangerOverlay::new(DangerGrid::generate(Vec2::new(8, 8), &[town]), vec![town]);
+        let policy_at = |wpos| settings.policy(&location(None, overlay.danger_at(wpos)), false);
+
+        // In the next cell, but close to the town
+        let near = Vec2::new(2600.0, 2304.0);
+        assert_eq!(overlay.grid().get_at(near), Some(danger::WILDERNESS_DANGER));
+        assert_eq!(policy_at(near), DeathLootPolicy::KeepAll);
+
+        // In the cell of the town, but far from it
+        let far = Vec2::new(2060.0, 2304.0);
+        assert_eq!(overlay.grid().get_at(far), Some(danger::SAFE_DANGER));
+        assert_eq!(policy_at(far), DeathLootPolicy::DropFraction(0.25));
+    }
+
+    #[test]
+    fn dropped_items_match_the_policy() {
+        assert_outcome(DeathLootPolicy::KeepAll, [0, 0, 0]);
+        assert_outcome(DeathLootPolicy::DropAll, [10, 3, 0]);
+        // 2.5 apples and 0.75 swords are rounded down
+        assert_outcome(DeathLootPolicy::DropFraction(0.25), [2, 0, 0]);
+        assert_outcome(DeathLootPolicy::DropFraction(0.5), [5, 1, 0]);
+        // Exactly 7 apples and 2.1 swords, despite 0.7 not being exact in
+        // floating point
+        assert_outcome(DeathLootPolicy::DropFraction(0.7), [7, 2, 0]);
+        assert_outcome(DeathLootPolicy::DropFraction(1.5), [10, 3, 0]);
+    }
+}
@@ -1,4 +1,11 @@
-use crate::{client::Client, persistence::PersistedComponents, sys, Server, StateExt};
+use crate::{
+    client::Client,
+    persistence::{
+        audit_log::AuditEventKind, character_updater::CharacterUpdater, PersistedComponents,
+    },
+    presence::Presence,
+    sys, Server, StateExt,
+};
 use common::{
     character::CharacterId,
     comp::{
@@ -19,7 +26,10 @@ use common::{
     util::Dir,
     ViewDistances,
 };
-use common_net::{msg::ServerGeneral, sync::WorldSyncExt};
+use common_net::{
+    msg::{PresenceKind, ServerGeneral},
+    sync::WorldSyncExt,
+};
 use specs::{Builder, Entity as EcsEntity, WorldExt};
 use std::time::Duration;
 use vek::{Rgb, Vec3};
@@ -69,6 +79,30 @@ pub fn handle_loaded_character_data(
             ))),
         );
     }
+    // Skill groups that changed since the character was last saved were reset when
+    // it was loaded, the player is told about it by their HUD
+    if let Some(error) = loaded_components.skill_set.persistence_load_error {
+        let ecs = server.state.ecs();
+        let player_uuid = ecs
+            .read_storage::<comp::Player>()
+            .get(entity)
+            .map(|player| player.uuid().to_string());
+        let character_id = ecs
+            .read_storage::<Presence>()
+            .get(entity)
+            .and_then(|presence| match presence.kind {
+                PresenceKind::Character(character_id) => Some(character_id),
+                PresenceKind::Spectator | PresenceKind::Possessor => None,
+            });
+        if let Some(player_uuid) = player_uuid {
+            ecs.write_resource::<CharacterUpdater>().log_event(
+                AuditEventKind::SkillsReset,
+                player_uuid,
+                character_id,
+                format!("{:?}", error),
+            );
+        }
+    }
     server
         .state
         .update_character_data(entity, loaded_components);
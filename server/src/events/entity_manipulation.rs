@@ -7,6 +7,11 @@ use crate::{
         skillset::SkillGroupKind,
         BuffKind, BuffSource, PhysicsState,
     },
+    death_loot::{death_loot_policy, drop_death_loot},
+    events::{interaction::handle_mount_death, player::handle_possessed_death},
+    fire::Fires,
+    persistence::character_updater::CharacterUpdater,
+    presence::Presence,
     rtsim::RtSim,
     sys::terrain::SAFE_ZONE_RADIUS,
     Server, SpawnPoint, StateExt,
@@ -17,8 +22,10 @@ use common::{
     comp::{
         self, aura, buff,
         chat::{KillSource, KillType},
-        inventory::item::MaterialStatManifest,
+        inventory::item::{ItemKind, MaterialStatManifest, Reagent},
+        knockout,
         loot_owner::LootOwnerKind,
+        slot::EquipSlot,
         Alignment, Auras, Body, CharacterState, Energy, Group, Health, HealthChange, Inventory,
         Player, Poise, Pos, SkillSet, Stats,
     },
@@ -32,7 +39,10 @@ use common::{
     vol::ReadVol,
     Damage, DamageKind, DamageSource, Explosion, GroupTarget, RadiusEffect,
 };
-use common_net::{msg::ServerGeneral, sync::WorldSyncExt};
+use common_net::{
+    msg::{PresenceKind, ServerGeneral},
+    sync::WorldSyncExt,
+};
 use common_state::BlockChange;
 use comp::chat::GenericChatMsg;
 use hashbrown::HashSet;
@@ -54,6 +64,10 @@ enum DamageContrib {
 
 pub fn handle_poise(server: &Server, entity: EcsEntity, change: comp::PoiseChange) {
     let ecs = &server.state.ecs();
+    // Training dummies can't be staggered or knocked down
+    if ecs.read_storage::<comp::TrainingDummy>().contains(entity) {
+        return;
+    }
     if let Some(character_state) = ecs.read_storage::<CharacterState>().get(entity) {
         // Entity is invincible to poise change during stunned character state
         if !matches!(character_state, CharacterState::Stunned(_)) {
@@ -64,11 +78,74 @@ pub fn handle_poise(server: &Server, entity: EcsEntity, change: comp::PoiseChang
     }
 }
 
+/// Whether a health change is a melee hit with a non-lethal weapon on a
+/// creature that can be knocked out and captured
+fn is_nonlethal_hit(ecs: &specs::World, entity: EcsEntity, change: &HealthChange) -> bool {
+    let capturable = match (
+        ecs.read_storage::<Body>().get(entity),
+        ecs.read_storage::<Alignment>().get(entity),
+    ) {
+        (Some(body), Some(alignment)) => knockout::is_capturable(
+            body,
+            alignment,
+            ecs.read_storage::<Player>().contains(entity),
+        ),
+        _ => false,
+    };
+    if !capturable || change.cause != Some(DamageSource::Melee) {
+        return false;
+    }
+
+    let attacker = change.by.and_then(|by| {
+        ecs.read_resource::<UidAllocator>()
+            .retrieve_entity_internal(by.uid().0)
+    });
+    let inventories = ecs.read_storage::<Inventory>();
+    attacker
+        .and_then(|attacker| inventories.get(attacker))
+        .and_then(|inventory| inventory.equipped(EquipSlot::ActiveMainhand))
+        .map_or(false, |item| match &*item.kind() {
+            ItemKind::Tool(tool) => knockout::is_nonlethal_weapon(tool.kind),
+            _ => false,
+        })
+}
+
 pub fn handle_health_change(server: &Server, entity: EcsEntity, change: HealthChange) {
     let ecs = &server.state.ecs();
     if let Some(mut health) = ecs.write_storage::<Health>().get_mut(entity) {
+        // Non-lethal weapons knock out capturable creatures instead of killing them
+        let (change, knocked_out) = knockout::apply_nonlethal(
+            health.current(),
+            health.maximum(),
+            change,
+            is_nonlethal_hit(ecs, entity, &change),
+        );
         // If the change amount was not zero
         let changed = health.change_by(change);
+        if let (true, Some(by)) = (knocked_out, change.by) {
+            let mut knocked_out = ecs.write_storage::<comp::KnockedOut>();
+            if !knocked_out.contains(entity) {
+                let agent = ecs.write_storage::<Agent>().remove(entity);
+                let _ =
+                    knocked_out.insert(entity, comp::KnockedOut::new(by.uid(), agent, change.time));
+                // Without its agent nothing would release the inputs it was holding, so
+                // the creature would keep running or attacking while knocked out
+                if let Some(controller) = ecs.write_storage::<comp::Controller>().get_mut(entity) {
+                    controller.reset();
+                }
+                if let Some(character_state) = ecs.write_storage::<CharacterState>().get_mut(entity)
+                {
+                    *character_state = CharacterState::Idle(Default::default());
+                }
+            }
+        }
+        // Training dummies measure the damage they take, but never die from it
+        if let Some(dummy) = ecs.write_storage::<comp::TrainingDummy>().get_mut(entity) {
+            if let (Some(by), true) = (change.by, change.amount < 0.0) {
+                dummy.record(by.uid(), -change.amount, change.cause, change.time);
+            }
+            health.revive();
+        }
         if let (Some(pos), Some(uid)) = (
             ecs.read_storage::<Pos>().get(entity),
             ecs.read_storage::<Uid>().get(entity),
@@ -103,6 +180,10 @@ pub fn handle_knockback(server: &Server, entity: EcsEntity, impulse: Vec3<f32>)
     let ecs = &server.state.ecs();
     let clients = ecs.read_storage::<Client>();
 
+    if ecs.read_storage::<comp::TrainingDummy>().contains(entity) {
+        return;
+    }
+
     if let Some(physics) = ecs.read_storage::<PhysicsState>().get(entity) {
         //Check if the entity is on a surface. If it is not, reduce knockback.
         let mut impulse = impulse
@@ -130,6 +211,11 @@ pub fn handle_knockback(server: &Server, entity: EcsEntity, impulse: Vec3<f32>)
 /// the kill. Experience given is equal to the level of the entity that was
 /// killed times 10.
 pub fn handle_destroy(server: &mut Server, entity: EcsEntity, last_change: HealthChange) {
+    let death_loot_policy = death_loot_policy(server, entity);
+    // A dead mount can't carry its rider any longer
+    handle_mount_death(server, entity);
+    // The possessor of a dying entity gets their own body back
+    handle_possessed_death(server, entity);
     let state = server.state_mut();
 
     // TODO: Investigate duplicate `Destroy` events (but don't remove this).
@@ -204,6 +290,21 @@ pub fn handle_destroy(server: &mut Server, entity: EcsEntity, last_change: Healt
         }
     }
 
+    // Count the deaths of characters, which deletes hardcore characters. Their
+    // player only leaves the game when trying to respawn, so that the client goes
+    // through the usual death screen first.
+    if let Some(PresenceKind::Character(character_id)) = state
+        .ecs()
+        .read_storage::<Presence>()
+        .get(entity)
+        .map(|presence| presence.kind)
+    {
+        state
+            .ecs()
+            .write_resource::<CharacterUpdater>()
+            .record_death(character_id);
+    }
+
     let mut exp_awards = Vec::<(Entity, f32, Option<Group>)>::new();
     // Award EXP to damage contributors
     //
@@ -416,6 +517,9 @@ pub fn handle_destroy(server: &mut Server, entity: EcsEntity, last_change: Healt
             .ecs()
             .write_storage::<CharacterState>()
             .insert(entity, CharacterState::default());
+        if let Some(policy) = death_loot_policy {
+            drop_death_loot(state, entity, policy);
+        }
 
         false
     } else if state.ecs().read_storage::<Agent>().contains(entity)
@@ -541,6 +645,18 @@ pub fn handle_delete(server: &mut Server, entity: EcsEntity) {
 pub fn handle_land_on_ground(server: &Server, entity: EcsEntity, vel: Vec3<f32>) {
     let ecs = server.state.ecs();
 
+    // Riders thrown off a dead mount are spared for a moment, the immunity is
+    // dropped on their first landing after that
+    let now = ecs.read_resource::<Time>().0;
+    let mut fall_immunities = ecs.write_storage::<comp::FallImmunity>();
+    if let Some(immunity) = fall_immunities.get(entity).copied() {
+        if immunity.until.0 > now {
+            return;
+        }
+        fall_immunities.remove(entity);
+    }
+    drop(fall_immunities);
+
     if vel.z <= -30.0 {
         let mass = ecs
             .read_storage::<comp::Mass>()
@@ -601,6 +717,20 @@ pub fn handle_land_on_ground(server: &Server, entity: EcsEntity, vel: Vec3<f32>)
 pub fn handle_respawn(server: &Server, entity: EcsEntity) {
     let state = &server.state;
 
+    // Hardcore characters are gone once they died, so their player goes back to
+    // the character selection instead
+    if state
+        .ecs()
+        .read_storage::<comp::Hardcore>()
+        .contains(entity)
+    {
+        state
+            .ecs()
+            .read_resource::<EventBus<ServerEvent>>()
+            .emit_now(ServerEvent::ExitIngame { entity });
+        return;
+    }
+
     // Only clients can respawn
     if state
         .ecs()
@@ -768,6 +898,19 @@ pub fn handle_explosion(server: &Server, pos: Vec3<f32>, explosion: Explosion, o
                 }
 
                 let terrain = ecs.read_resource::<TerrainGrid>();
+                // Fire explosions set the flammable blocks they touch on fire
+                if explosion.reagent == Some(Reagent::Red) {
+                    let mut fires = ecs.write_resource::<Fires>();
+                    for block_pos in &touched_blocks {
+                        fires.ignite(
+                            *block_pos,
+                            |pos| terrain.get(pos).ok().copied(),
+                            *time,
+                            &settings.gameplay.fire,
+                        );
+                    }
+                }
+
                 let mut block_change = ecs.write_resource::<BlockChange>();
                 for block_pos in touched_blocks {
                     if let Ok(block) = terrain.get(block_pos) {
@@ -1312,13 +1455,22 @@ pub fn handle_change_ability(
 
     if let Some(mut active_abilities) = ecs.write_storage::<comp::ActiveAbilities>().get_mut(entity)
     {
-        active_abilities.change_ability(
+        let changed = active_abilities.change_ability(
             slot,
             auxiliary_key,
             new_ability,
             inventories.get(entity),
             skill_sets.get(entity),
         );
+        if !changed {
+            debug!(
+                ?new_ability,
+                ?auxiliary_key,
+                "Refused to change ability of {:?} in slot {}",
+                entity,
+                slot
+            );
+        }
     }
 }
 
@@ -1359,3 +1511,43 @@ pub fn handle_update_map_marker(
         }
     }
 }
+
+/// Changes the appearance of a character in the game and persists it. Like on
+/// the character screen, the species and body type of a character can't be
+/// changed.
+pub fn handle_change_appearance(server: &mut Server, entity: EcsEntity, body: Body) {
+    let ecs = server.state.ecs();
+    let character_id = match ecs
+        .read_storage::<Presence>()
+        .get(entity)
+        .map(|presence| presence.kind)
+    {
+        Some(PresenceKind::Character(character_id)) => character_id,
+        _ => return,
+    };
+    let player_uuid = match ecs.read_storage::<Player>().get(entity) {
+        Some(player) => player.uuid().to_string(),
+        None => return,
+    };
+    let body = match (ecs.read_storage::<Body>().get(entity), body) {
+        (Some(Body::Humanoid(old)), Body::Humanoid(mut new))
+            if old.with_appearance_of(&new) == new =>
+        {
+            new.validate();
+            Body::Humanoid(new)
+        },
+        _ => {
+            debug!(?entity, ?body, "Denied changing the species or body type");
+            return;
+        },
+    };
+
+    server
+        .state
+        .write_component_ignore_entity_dead(entity, body);
+    server
+        .state
+        .ecs()
+        .write_resource::<CharacterUpdater>()
+        .update_body(player_uuid, character_id, body);
+}
@@ -7,77 +7,136 @@ use common::{
         self,
         agent::{AgentEvent, Sound, SoundKind},
         dialogue::Subject,
-        inventory::slot::EquipSlot,
         loot_owner::LootOwnerKind,
+        pet::{is_mountable, MountStatsManifest, PetCommand},
         tool::ToolKind,
-        Inventory, LootOwner, Pos, SkillGroupKind,
+        ChatType, Inventory, LootOwner, Pos, SkillGroupKind,
     },
-    consts::{MAX_MOUNT_RANGE, SOUND_TRAVEL_DIST_PER_VOLUME},
     event::EventBus,
+    interaction::{InteractionKind, InteractionRanges},
     link::Is,
-    mounting::{Mount, Mounting, Rider},
+    mounting::{may_mount, Mount, MountData, Mounting, Rider},
     outcome::Outcome,
+    region::RegionMap,
+    resources::Time,
     terrain::{Block, SpriteKind},
     uid::Uid,
     vol::ReadVol,
 };
-use common_net::sync::WorldSyncExt;
+use common_net::{msg::ServerGeneral, sync::WorldSyncExt};
 
-use crate::{state_ext::StateExt, Server};
+use crate::{
+    client::Client, events::set_lantern, presence::RegionSubscription, state_ext::StateExt, Server,
+};
 
-use crate::pet::tame_pet;
+use crate::pet::{
+    apply_pet_command, check_pet_command, commanded_pets, find_attack_target, tame_pet,
+    PetCommandError,
+};
 use hashbrown::{HashMap, HashSet};
 use lazy_static::lazy_static;
 use serde::Deserialize;
 use std::iter::FromIterator;
 
 pub fn handle_lantern(server: &mut Server, entity: EcsEntity, enable: bool) {
-    let ecs = server.state_mut().ecs();
-
-    let lantern_exists = ecs
-        .read_storage::<comp::LightEmitter>()
+    let ecs = server.state().ecs();
+    // Only enable lantern if entity is alive
+    let alive = ecs
+        .read_storage::<comp::Health>()
         .get(entity)
-        .map_or(false, |light| light.strength > 0.0);
+        .map_or(true, |h| !h.is_dead);
+    let out_of_fuel = enable
+        && ecs
+            .read_storage::<comp::LanternFuel>()
+            .get(entity)
+            .map_or(false, |fuel| fuel.is_empty());
+    if out_of_fuel {
+        if let Some(client) = ecs.read_storage::<Client>().get(entity) {
+            client.send_fallible(ServerGeneral::server_msg(
+                ChatType::Meta,
+                "Your lantern is out of fuel.",
+            ));
+        }
+        return;
+    }
+    let changed = (alive || !enable)
+        && set_lantern(
+            &mut ecs.write_storage(),
+            entity,
+            ecs.read_storage::<Inventory>().get(entity),
+            enable,
+        );
+    if changed {
+        notify_lantern_update(server, entity);
+    }
+}
 
-    if lantern_exists != enable {
-        if !enable {
-            server
-                .state_mut()
-                .ecs()
-                .write_storage::<comp::LightEmitter>()
-                .remove(entity);
-        } else if ecs // Only enable lantern if entity is alive
-            .read_storage::<comp::Health>()
+/// Fills the lantern of an entity up with fuel, the fuel item was already used
+/// up by then.
+pub fn handle_refuel_lantern(server: &mut Server, entity: EcsEntity) {
+    let ecs = server.state().ecs();
+    if let Some(fuel) = ecs.write_storage::<comp::LanternFuel>().get_mut(entity) {
+        *fuel = comp::LanternFuel::full();
+        if let Some(client) = ecs.read_storage::<Client>().get(entity) {
+            client.send_fallible(ServerGeneral::server_msg(
+                ChatType::Meta,
+                "You refill your lantern.",
+            ));
+        }
+    }
+}
+
+/// Tell clients subscribed to the region of an entity about the current state
+/// of its lantern.
+pub(super) fn notify_lantern_update(server: &Server, entity: EcsEntity) {
+    let ecs = server.state().ecs();
+    let (uid, pos) = match (
+        ecs.read_storage::<Uid>().get(entity).copied(),
+        ecs.read_storage::<Pos>().get(entity).copied(),
+    ) {
+        (Some(uid), Some(pos)) => (uid, pos),
+        _ => return,
+    };
+    let region = match ecs.read_resource::<RegionMap>().find_region(entity, pos.0) {
+        Some(region) => region,
+        None => return,
+    };
+
+    let msg = ServerGeneral::LanternUpdate(
+        uid,
+        ecs.read_storage::<comp::LightEmitter>()
             .get(entity)
-            .map_or(true, |h| !h.is_dead)
-        {
-            let inventory_storage = ecs.read_storage::<Inventory>();
-            let lantern_info = inventory_storage
-                .get(entity)
-                .and_then(|inventory| inventory.equipped(EquipSlot::Lantern))
-                .and_then(|item| {
-                    if let comp::item::ItemKind::Lantern(l) = &*item.kind() {
-                        Some((l.color(), l.strength()))
-                    } else {
-                        None
-                    }
-                });
-            if let Some((col, strength)) = lantern_info {
-                let _ =
-                    ecs.write_storage::<comp::LightEmitter>()
-                        .insert(entity, comp::LightEmitter {
-                            col,
-                            strength,
-                            flicker: 0.35,
-                            animated: true,
-                        });
-            }
+            .copied(),
+    );
+    for (client, subscription) in (
+        &ecs.read_storage::<Client>(),
+        &ecs.read_storage::<RegionSubscription>(),
+    )
+        .join()
+    {
+        if subscription.regions.contains(&region) {
+            client.send_fallible(msg.clone());
         }
     }
 }
 
 pub fn handle_npc_interaction(server: &mut Server, interactor: EcsEntity, npc_entity: EcsEntity) {
     let state = server.state_mut();
+
+    let positions = state.ecs().read_storage::<Pos>();
+    if !state
+        .ecs()
+        .read_resource::<InteractionRanges>()
+        .positions_in_range(
+            InteractionKind::Talk,
+            positions.get(interactor),
+            positions.get(npc_entity),
+        )
+    {
+        return;
+    }
+    drop(positions);
+
     if let Some(agent) = state
         .ecs()
         .write_storage::<comp::Agent>()
@@ -101,7 +160,14 @@ pub fn handle_mount(server: &mut Server, rider: EcsEntity, mount: EcsEntity) {
 
         let within_range = || {
             let positions = state.ecs().read_storage::<Pos>();
-            within_mounting_range(positions.get(rider), positions.get(mount))
+            state
+                .ecs()
+                .read_resource::<InteractionRanges>()
+                .positions_in_range(
+                    InteractionKind::Mount,
+                    positions.get(rider),
+                    positions.get(mount),
+                )
         };
         let healths = state.ecs().read_storage::<comp::Health>();
         let alive = |e| healths.get(e).map_or(true, |h| !h.is_dead);
@@ -111,21 +177,57 @@ pub fn handle_mount(server: &mut Server, rider: EcsEntity, mount: EcsEntity) {
             if let (Some(rider_uid), Some(mount_uid)) =
                 (uids.get(rider).copied(), uids.get(mount).copied())
             {
-                let is_pet = matches!(
-                    state
-                        .ecs()
-                        .read_storage::<comp::Alignment>()
-                        .get(mount),
-                    Some(comp::Alignment::Owned(owner)) if *owner == rider_uid,
-                );
-
-                if is_pet {
+                // Moderators can mount anything, e.g. for debugging
+                let allowed = state.ecs().read_storage::<comp::Admin>().contains(rider)
+                    || may_mount(
+                        rider_uid,
+                        state.ecs().read_storage::<comp::Alignment>().get(mount),
+                        state
+                            .ecs()
+                            .read_storage::<comp::Mountable>()
+                            .contains(mount),
+                    );
+
+                let bodies = state.ecs().read_storage::<comp::Body>();
+                let rejection = if !allowed {
+                    Some("You can only ride your own pets.")
+                } else if !bodies
+                    .get(mount)
+                    .map_or(false, |body| is_mountable(body, bodies.get(rider)))
+                {
+                    Some("This creature can't carry you.")
+                } else {
+                    None
+                };
+                drop(bodies);
+
+                if let Some(rejection) = rejection {
+                    if let Some(client) = state.ecs().read_storage::<Client>().get(rider) {
+                        client.send_fallible(ServerGeneral::server_msg(ChatType::Meta, rejection));
+                    }
+                } else {
                     drop(uids);
                     drop(healths);
-                    let _ = state.link(Mounting {
-                        mount: mount_uid,
-                        rider: rider_uid,
-                    });
+                    let linked = state
+                        .link(Mounting {
+                            mount: mount_uid,
+                            rider: rider_uid,
+                        })
+                        .is_ok();
+                    if linked {
+                        face_mount(state.ecs(), rider, mount);
+                    }
+                    // Looked up once, rather than by the movement of the mount every tick
+                    let mount_data = linked
+                        .then(|| state.ecs().read_storage::<comp::Body>().get(mount).copied())
+                        .flatten()
+                        .map(|body| MountData::new(&MountStatsManifest::load().read(), &body));
+                    if let Some(mount_data) = mount_data {
+                        let _ = state
+                            .ecs()
+                            .write_storage::<MountData>()
+                            .insert(mount, mount_data);
+                    }
                 }
             }
         }
@@ -133,14 +235,197 @@ pub fn handle_mount(server: &mut Server, rider: EcsEntity, mount: EcsEntity) {
 }
 
 pub fn handle_unmount(server: &mut Server, rider: EcsEntity) {
+    unmount(server.state().ecs(), rider);
+}
+
+fn unmount(ecs: &specs::World, rider: EcsEntity) {
+    let is_rider = ecs.write_storage::<Is<Rider>>().remove(rider);
+    if let Some(is_rider) = is_rider {
+        // The mount may already be gone, the rider is still reset
+        let mount = ecs.entity_from_uid(is_rider.mount.into());
+        stop_riding(ecs, rider, mount);
+    }
+}
+
+/// Separates an entity that is about to be deleted from its rider and from its
+/// own mount. This has to happen while the entity is still there, so that its
+/// rider is put down beside it (see [`StateExt::maintain_links`]) and its mount
+/// stops following its inputs.
+pub(crate) fn unmount_before_deletion(state: &mut common_state::State, entity: EcsEntity) {
+    let ecs = state.ecs();
+    // The mount knows its rider, so there is no need to look through all riders
+    let rider = ecs
+        .read_storage::<Is<Mount>>()
+        .get(entity)
+        .and_then(|is_mount| ecs.entity_from_uid(is_mount.rider.into()));
+    let mounted = ecs.read_storage::<Is<Rider>>().contains(entity);
+    if rider.is_none() && !mounted {
+        return;
+    }
+    if let Some(rider) = rider {
+        unmount(ecs, rider);
+    }
+    unmount(ecs, entity);
+    state.maintain_links();
+}
+
+/// How long a rider thrown off a mount that died is spared from fall damage
+const THROWN_RIDER_FALL_IMMUNITY: f64 = 1.5;
+
+/// Throws the rider off a mount that died. They don't take fall damage for a
+/// moment, as the mount may have died in the air or at full gallop.
+pub fn handle_mount_death(server: &mut Server, mount: EcsEntity) {
+    let ecs = server.state().ecs();
+    let rider = ecs
+        .read_storage::<Is<Mount>>()
+        .get(mount)
+        .and_then(|is_mount| ecs.entity_from_uid(is_mount.rider.into()));
+    if let Some(rider) = rider {
+        handle_unmount(server, rider);
+        let ecs = server.state().ecs();
+        let until = Time(ecs.read_resource::<Time>().0 + THROWN_RIDER_FALL_IMMUNITY);
+        let _ = ecs
+            .write_storage()
+            .insert(rider, comp::FallImmunity { until });
+    }
+}
+
+/// Turns a rider that just mounted to face the way their mount does, which the
+/// mount would otherwise only do once it moves them along. Mounts without an
+/// orientation leave the rider as they are.
+fn face_mount(ecs: &specs::World, rider: EcsEntity, mount: EcsEntity) {
+    let mut orientations = ecs.write_storage::<comp::Ori>();
+    if let Some(ori) = orientations.get(mount).copied() {
+        let _ = orientations.insert(rider, ori);
+    }
+}
+
+/// Undoes what riding did to a rider and their mount. The link between them is
+/// removed separately, which also puts the rider down beside the mount.
+fn stop_riding(ecs: &specs::World, rider: EcsEntity, mount: Option<EcsEntity>) {
+    let mut controllers = ecs.write_storage::<comp::Controller>();
+    // The mount was moved by the inputs of the rider, which it would otherwise
+    // keep following
+    if let Some(controller) = mount.and_then(|mount| controllers.get_mut(mount)) {
+        *controller = comp::Controller::default();
+    }
+    // Inputs that were meant for the mount (e.g. jumping) aren't carried out by
+    // the rider
+    if let Some(controller) = controllers.get_mut(rider) {
+        controller.queued_inputs.clear();
+    }
+
+    // Riders move and turn along with their mount, but don't keep its momentum
+    // or tilt once they are off
+    if let Some(vel) = ecs.write_storage::<comp::Vel>().get_mut(rider) {
+        vel.0 = Vec3::zero();
+    }
+    if let Some(ori) = ecs.write_storage::<comp::Ori>().get_mut(rider) {
+        *ori = ori.to_horizontal();
+    }
+    // Abilities used from the saddle don't carry on after dismounting, but
+    // weapons stay wielded
+    if let Some(character_state) = ecs.write_storage::<comp::CharacterState>().get_mut(rider) {
+        if !matches!(
+            character_state,
+            comp::CharacterState::Idle(_) | comp::CharacterState::Wielding(_)
+        ) {
+            *character_state = comp::CharacterState::Idle(Default::default());
+        }
+    }
+}
+
+/// Sits `entity` down on `seat`, facing the same way as the seat. Like
+/// mounting, this only happens within reach of the seat and if it is free.
+pub fn handle_sit(server: &mut Server, entity: EcsEntity, seat: EcsEntity) {
     let state = server.state_mut();
-    state.ecs().write_storage::<Is<Rider>>().remove(rider);
+    let ecs = state.ecs();
+    if ecs.read_storage::<Is<Rider>>().contains(entity)
+        || ecs.read_storage::<comp::Sitting>().contains(entity)
+    {
+        return;
+    }
+
+    let positions = ecs.read_storage::<Pos>();
+    let seat_uid = ecs.read_storage::<Uid>().get(seat).copied();
+    let seat_pos = positions.get(seat).copied();
+    let seat_height = ecs
+        .read_storage::<comp::Body>()
+        .get(seat)
+        .filter(|body| body.is_seat())
+        .map(comp::Body::height);
+    let (seat_uid, seat_pos, seat_height) = match (seat_uid, seat_pos, seat_height) {
+        (Some(seat_uid), Some(seat_pos), Some(seat_height)) => (seat_uid, seat_pos, seat_height),
+        _ => {
+            if let Some(client) = ecs.read_storage::<Client>().get(entity) {
+                client.send_fallible(ServerGeneral::server_msg(
+                    ChatType::Meta,
+                    "You can't sit on that.",
+                ));
+            }
+            return;
+        },
+    };
+    let within_range = ecs.read_resource::<InteractionRanges>().positions_in_range(
+        InteractionKind::Sit,
+        positions.get(entity),
+        Some(&seat_pos),
+    );
+    let alive = ecs
+        .read_storage::<comp::Health>()
+        .get(entity)
+        .map_or(true, |health| !health.is_dead);
+    if !within_range || !alive {
+        return;
+    }
+    drop(positions);
+    if ecs
+        .read_storage::<comp::Sitting>()
+        .join()
+        .any(|sitting| sitting.seat == seat_uid)
+    {
+        if let Some(client) = ecs.read_storage::<Client>().get(entity) {
+            client.send_fallible(ServerGeneral::server_msg(
+                ChatType::Meta,
+                "Someone is already sitting there.",
+            ));
+        }
+        return;
+    }
+
+    let seat_ori = ecs
+        .read_storage::<comp::Ori>()
+        .get(seat)
+        .map_or_else(comp::Ori::default, |ori| ori.to_horizontal());
+    state
+        .write_component_ignore_entity_dead(entity, Pos(seat_pos.0 + Vec3::unit_z() * seat_height));
+    state.write_component_ignore_entity_dead(entity, seat_ori);
+    state.write_component_ignore_entity_dead(entity, comp::Vel(Vec3::zero()));
+    state.write_component_ignore_entity_dead(entity, comp::CharacterState::Sit);
+    state.write_component_ignore_entity_dead(entity, comp::Sitting { seat: seat_uid });
+    if let Some(force_update) = state
+        .ecs()
+        .write_storage::<comp::ForceUpdate>()
+        .get_mut(entity)
+    {
+        force_update.update();
+    }
 }
 
-fn within_mounting_range(player_position: Option<&Pos>, mount_position: Option<&Pos>) -> bool {
-    match (player_position, mount_position) {
-        (Some(ppos), Some(ipos)) => ppos.0.distance_squared(ipos.0) < MAX_MOUNT_RANGE.powi(2),
-        _ => false,
+/// Gets `entity` up from the seat it sits on, e.g. when it is no longer within
+/// reach of the seat after being teleported
+pub fn handle_stand(server: &mut Server, entity: EcsEntity) {
+    let ecs = server.state().ecs();
+    if ecs
+        .write_storage::<comp::Sitting>()
+        .remove(entity)
+        .is_some()
+    {
+        if let Some(character_state) = ecs.write_storage::<comp::CharacterState>().get_mut(entity) {
+            if matches!(character_state, comp::CharacterState::Sit) {
+                *character_state = comp::CharacterState::Idle(Default::default());
+            }
+        }
     }
 }
 
@@ -263,12 +548,8 @@ pub fn handle_sound(server: &mut Server, sound: &Sound) {
     // TODO: Reduce the complexity of this problem by using spatial partitioning
     // system
     for (agent, agent_pos) in (agents, positions).join() {
-        // TODO: Use pathfinding for more dropoff around obstacles
         let agent_dist_sqrd = agent_pos.0.distance_squared(sound.pos);
-        let sound_travel_dist_sqrd = (sound.vol * SOUND_TRAVEL_DIST_PER_VOLUME).powi(2);
-
-        let vol_dropoff = agent_dist_sqrd / sound_travel_dist_sqrd * sound.vol;
-        let propagated_sound = sound.with_new_vol(sound.vol - vol_dropoff);
+        let propagated_sound = sound.propagated_to(agent_pos.0);
 
         let can_hear_sound = propagated_sound.vol > 0.00;
         let should_hear_sound = agent_dist_sqrd < agent.psyche.listen_dist.powi(2);
@@ -312,3 +593,189 @@ pub fn handle_tame_pet(server: &mut Server, pet_entity: EcsEntity, owner_entity:
     // showing taming success?
     tame_pet(server.state.ecs(), pet_entity, owner_entity);
 }
+
+pub fn handle_command_pet(
+    server: &mut Server,
+    owner: EcsEntity,
+    pet: Option<Uid>,
+    command: PetCommand,
+) {
+    let ecs = server.state.ecs();
+    let owner_uid = match ecs.uid_from_entity(owner) {
+        Some(uid) => uid,
+        None => return,
+    };
+    let clients = ecs.read_storage::<Client>();
+    let notify_owner = |msg: String| {
+        if let Some(client) = clients.get(owner) {
+            client.send_fallible(ServerGeneral::server_msg(comp::ChatType::Meta, msg));
+        }
+    };
+
+    let commanded_pets = commanded_pets(ecs, owner_uid, pet);
+    if commanded_pets.is_empty() {
+        notify_owner(if pet.is_some() {
+            "That is not your pet".to_owned()
+        } else {
+            "You have no pets".to_owned()
+        });
+        return;
+    }
+
+    let stats = ecs.read_storage::<comp::Stats>();
+    let target = match command {
+        PetCommand::Attack(target) => match find_attack_target(ecs, owner, target) {
+            Ok(target) => Some(target),
+            Err(error @ PetCommandError::PeacefulTarget) => {
+                let name = match commanded_pets.as_slice() {
+                    [pet] => stats
+                        .get(*pet)
+                        .map_or("Your pet", |stats| stats.name.as_str()),
+                    _ => "Your pets",
+                };
+                notify_owner(error.message(name));
+                return;
+            },
+            // Each pet tells that it has nothing to attack
+            Err(_) => None,
+        },
+        _ => None,
+    };
+
+    let ranges = ecs.read_resource::<InteractionRanges>();
+    let positions = ecs.read_storage::<Pos>();
+    let is_mounts = ecs.read_storage::<Is<Mount>>();
+    let knocked_out = ecs.read_storage::<comp::KnockedOut>();
+    let mut pets = ecs.write_storage::<comp::Pet>();
+    let mut agents = ecs.write_storage::<comp::Agent>();
+    let time = *ecs.read_resource::<Time>();
+    for pet_entity in commanded_pets {
+        let result = check_pet_command(
+            command,
+            is_mounts.contains(pet_entity),
+            knocked_out.contains(pet_entity),
+            ranges.positions_in_range(
+                InteractionKind::PetCommand,
+                positions.get(owner),
+                positions.get(pet_entity),
+            ),
+            target.is_some(),
+        );
+        match (result, pets.get_mut(pet_entity), agents.get_mut(pet_entity)) {
+            (Ok(()), Some(pet), Some(agent)) => apply_pet_command(
+                pet,
+                agent,
+                command,
+                positions.get(pet_entity).map_or(Vec3::zero(), |pos| pos.0),
+                owner,
+                target,
+                time,
+            ),
+            (Err(error), _, _) => {
+                let name = stats
+                    .get(pet_entity)
+                    .map_or("Your pet", |stats| stats.name.as_str());
+                notify_owner(error.message(name));
+            },
+            _ => {},
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::comp::{
+        controller::InputAttr, CharacterState, Controller, ControllerInputs, InputKind, Ori, Vel,
+    };
+
+    #[test]
+    fn riders_are_reset_after_dismounting() {
+        let mut ecs = specs::World::new();
+        ecs.register::<Controller>();
+        ecs.register::<Vel>();
+        ecs.register::<Ori>();
+        ecs.register::<CharacterState>();
+
+        let mut galloping = Controller {
+            inputs: ControllerInputs {
+                move_dir: Vec2::unit_x(),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        galloping.queued_inputs.insert(InputKind::Jump, InputAttr {
+            select_pos: None,
+            target_entity: None,
+        });
+        let ori = Ori::default().pitched_up(0.5).rolled_left(0.3);
+        let mount = ecs
+            .create_entity()
+            .with(galloping.clone())
+            .with(Vel(Vec3::unit_x() * 10.0))
+            .with(ori)
+            .build();
+        let rider = ecs
+            .create_entity()
+            .with(galloping)
+            .with(Vel(Vec3::unit_x() * 10.0))
+            .with(ori)
+            .with(CharacterState::Sit)
+            .build();
+
+        stop_riding(&ecs, rider, Some(mount));
+        assert_eq!(
+            ecs.read_storage::<Controller>().get(mount),
+            Some(&Controller::default())
+        );
+        let controllers = ecs.read_storage::<Controller>();
+        let rider_controller = controllers.get(rider).unwrap();
+        assert!(rider_controller.queued_inputs.is_empty());
+        assert_eq!(rider_controller.inputs.move_dir, Vec2::unit_x());
+        assert_eq!(
+            ecs.read_storage::<Vel>().get(rider),
+            Some(&Vel(Vec3::zero()))
+        );
+        assert!(
+            ecs.read_storage::<Ori>()
+                .get(rider)
+                .unwrap()
+                .look_vec()
+                .z
+                .abs()
+                < 0.001
+        );
+        assert!(matches!(
+            ecs.read_storage::<CharacterState>().get(rider),
+            Some(CharacterState::Idle(_))
+        ));
+        drop(controllers);
+
+        // Riders of mounts that are already gone are reset all the same
+        ecs.write_storage::<Vel>()
+            .insert(rider, Vel(Vec3::unit_z()))
+            .unwrap();
+        stop_riding(&ecs, rider, None);
+        assert_eq!(
+            ecs.read_storage::<Vel>().get(rider),
+            Some(&Vel(Vec3::zero()))
+        );
+    }
+
+    #[test]
+    fn riders_face_the_way_of_their_mount() {
+        let mut ecs = specs::World::new();
+        ecs.register::<Ori>();
+
+        let rider_ori = Ori::default().yawed_left(1.0);
+        let rider = ecs.create_entity().with(rider_ori).build();
+        let mount_ori = Ori::default().yawed_right(2.0);
+        let mount = ecs.create_entity().with(mount_ori).build();
+        let unoriented_mount = ecs.create_entity().build();
+
+        face_mount(&ecs, rider, unoriented_mount);
+        assert_eq!(ecs.read_storage::<Ori>().get(rider), Some(&rider_ori));
+        face_mount(&ecs, rider, mount);
+        assert_eq!(ecs.read_storage::<Ori>().get(rider), Some(&mount_ori));
+    }
+}
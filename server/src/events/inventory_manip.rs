@@ -6,12 +6,14 @@ use vek::{Rgb, Vec3};
 use common::{
     comp::{
         self,
+        buff::PotionSicknessConfig,
         group::members,
         item::{self, tool::AbilityMap, MaterialStatManifest},
-        slot::{self, Slot},
+        slot::Slot,
     },
-    consts::MAX_PICKUP_RANGE,
+    interaction::{InteractionKind, InteractionRanges},
     recipe::{self, default_component_recipe_book, default_recipe_book},
+    resources::Time,
     terrain::SpriteKind,
     trade::Trades,
     uid::Uid,
@@ -22,29 +24,58 @@ use common_net::sync::WorldSyncExt;
 use common_state::State;
 use comp::LightEmitter;
 
-use crate::{client::Client, Server, StateExt};
+use crate::{
+    client::Client, events::interaction::notify_lantern_update, pet::find_tame_target,
+    settings::Settings, Server, StateExt,
+};
 use common::{
-    comp::{
-        pet::is_tameable, Alignment, Body, ChatType, CollectFailedReason, Group,
-        InventoryUpdateEvent, Player,
-    },
+    comp::{Alignment, Body, ChatType, CollectFailedReason, Group, InventoryUpdateEvent, Player},
     event::{EventBus, ServerEvent},
 };
 use common_net::msg::ServerGeneral;
 
-pub fn swap_lantern(
+/// Lights the lantern an entity has equipped, or puts it out. This works for
+/// any entity with a lantern in its loadout, returns whether its light changed.
+pub fn set_lantern(
     storage: &mut WriteStorage<LightEmitter>,
     entity: EcsEntity,
-    (lantern_color, lantern_strength): (Rgb<f32>, f32),
-) {
-    if let Some(mut light) = storage.get_mut(entity) {
-        light.strength = lantern_strength;
-        light.col = lantern_color;
+    inventory: Option<&comp::Inventory>,
+    enable: bool,
+) -> bool {
+    let lantern_lit = storage
+        .get(entity)
+        .map_or(false, |light| light.strength > 0.0);
+    if lantern_lit == enable {
+        false
+    } else if enable {
+        match inventory.and_then(comp::Inventory::lantern_light) {
+            Some(light) => storage.insert(entity, light).is_ok(),
+            None => false,
+        }
+    } else {
+        storage.remove(entity).is_some()
     }
 }
 
-pub fn snuff_lantern(storage: &mut WriteStorage<LightEmitter>, entity: EcsEntity) {
-    storage.remove(entity);
+/// Makes the light of a lit lantern match the lantern equipped now, or puts it
+/// out if there is none anymore. Returns whether its light changed.
+pub fn refresh_lantern(
+    storage: &mut WriteStorage<LightEmitter>,
+    entity: EcsEntity,
+    inventory: Option<&comp::Inventory>,
+) -> bool {
+    let light = match storage.get_mut(entity) {
+        Some(light) => light,
+        None => return false,
+    };
+    match inventory.and_then(comp::Inventory::lantern_light) {
+        Some(lantern_light) if *light == lantern_light => false,
+        Some(lantern_light) => {
+            *light = lantern_light;
+            true
+        },
+        None => storage.remove(entity).is_some(),
+    }
 }
 
 #[allow(clippy::blocks_in_if_conditions)]
@@ -71,6 +102,7 @@ pub fn handle_inventory(server: &mut Server, entity: EcsEntity, manip: comp::Inv
 
     let mut dropped_items = Vec::new();
     let mut thrown_items = Vec::new();
+    let mut placed_traps = Vec::new();
 
     let get_cylinder = |state: &State, entity| {
         let ecs = state.ecs();
@@ -126,7 +158,11 @@ pub fn handle_inventory(server: &mut Server, entity: EcsEntity, manip: comp::Inv
             let entity_cylinder = get_cylinder(state, entity);
 
             // FIXME: Raycast so we can't pick up items through walls.
-            if !within_pickup_range(entity_cylinder, || get_cylinder(state, item_entity)) {
+            if !within_pickup_range(
+                &state.ecs().read_resource::<InteractionRanges>(),
+                entity_cylinder,
+                || get_cylinder(state, item_entity),
+            ) {
                 debug!(
                     ?entity_cylinder,
                     "Failed to pick up item as not within range, Uid: {}", pickup_uid
@@ -314,22 +350,10 @@ pub fn handle_inventory(server: &mut Server, entity: EcsEntity, manip: comp::Inv
                 Slot::Inventory(slot) => {
                     use item::ItemKind;
 
-                    let (is_equippable, lantern_info) =
-                        inventory.get(slot).map_or((false, None), |i| {
-                            let kind = i.kind();
-                            let is_equippable = kind.is_equippable();
-                            let lantern_info = match &*kind {
-                                ItemKind::Lantern(lantern) => {
-                                    Some((lantern.color(), lantern.strength()))
-                                },
-                                _ => None,
-                            };
-                            (is_equippable, lantern_info)
-                        });
+                    let is_equippable = inventory
+                        .get(slot)
+                        .map_or(false, |i| i.kind().is_equippable());
                     if is_equippable {
-                        if let Some(lantern_info) = lantern_info {
-                            swap_lantern(&mut state.ecs().write_storage(), entity, lantern_info);
-                        }
                         if let Some(pos) = state.ecs().read_storage::<comp::Pos>().get(entity) {
                             dropped_items.extend(inventory.equip(slot).into_iter().map(|x| {
                                 (
@@ -348,8 +372,12 @@ pub fn handle_inventory(server: &mut Server, entity: EcsEntity, manip: comp::Inv
                         &state.ecs().read_resource::<MaterialStatManifest>(),
                     ) {
                         match &*item.kind() {
-                            ItemKind::Consumable { effects, .. } => {
-                                maybe_effect = Some(effects.clone());
+                            ItemKind::Consumable {
+                                effects,
+                                potion_sickness,
+                                ..
+                            } => {
+                                maybe_effect = Some((effects.clone(), *potion_sickness));
                                 Some(InventoryUpdateEvent::Consumed(item.name().into_owned()))
                             },
                             ItemKind::Throwable { kind, .. } => {
@@ -376,62 +404,96 @@ pub fn handle_inventory(server: &mut Server, entity: EcsEntity, manip: comp::Inv
                                 kind: item::Utility::Collar,
                                 ..
                             } => {
-                                const MAX_PETS: usize = 3;
-                                let reinsert = if let Some(pos) =
-                                    state.read_storage::<comp::Pos>().get(entity)
-                                {
-                                    if (
-                                        &state.read_storage::<Alignment>(),
-                                        &state.read_storage::<comp::Agent>(),
-                                    )
-                                        .join()
-                                        .filter(|(alignment, _)| {
-                                            alignment == &&comp::Alignment::Owned(uid)
-                                        })
-                                        .count()
-                                        >= MAX_PETS
-                                    {
-                                        true
-                                    } else if let Some(tameable_entity) = {
-                                        let nearest_tameable = (
-                                            &state.ecs().entities(),
-                                            &state.ecs().read_storage::<Body>(),
-                                            &state.ecs().read_storage::<comp::Pos>(),
-                                            &state.ecs().read_storage::<Alignment>(),
-                                        )
-                                            .join()
-                                            .filter(|(_, _, wild_pos, _)| {
-                                                wild_pos.0.distance_squared(pos.0) < 5.0f32.powi(2)
-                                            })
-                                            .filter(|(_, body, _, alignment)| {
-                                                alignment == &&Alignment::Wild && is_tameable(body)
-                                            })
-                                            .min_by_key(|(_, _, wild_pos, _)| {
-                                                (wild_pos.0.distance_squared(pos.0) * 100.0) as i32
-                                            })
-                                            .map(|(entity, _, _, _)| entity);
-                                        nearest_tameable
-                                    } {
+                                match find_tame_target(state.ecs(), entity) {
+                                    Ok(tameable_entity) => {
                                         let server_eventbus =
                                             state.ecs().read_resource::<EventBus<ServerEvent>>();
                                         server_eventbus.emit_now(ServerEvent::TamePet {
                                             owner_entity: entity,
                                             pet_entity: tameable_entity,
                                         });
-                                        false
-                                    } else {
-                                        true
-                                    }
-                                } else {
-                                    true
-                                };
-
-                                if reinsert {
-                                    let _ = inventory.insert_or_stack_at(slot, item);
+                                    },
+                                    Err(error) => {
+                                        if let Some(client) =
+                                            state.ecs().read_storage::<Client>().get(entity)
+                                        {
+                                            client.send_fallible(ServerGeneral::server_msg(
+                                                ChatType::Meta,
+                                                error.message(),
+                                            ));
+                                        }
+                                        let _ = inventory.insert_or_stack_at(slot, item);
+                                    },
                                 }
 
                                 Some(InventoryUpdateEvent::Used)
                             },
+                            ItemKind::Utility {
+                                kind: item::Utility::LanternFuel,
+                                ..
+                            } => {
+                                let refuelable = state
+                                    .read_storage::<comp::LanternFuel>()
+                                    .get(entity)
+                                    .map_or(false, |fuel| !fuel.is_full());
+                                if refuelable {
+                                    state
+                                        .ecs()
+                                        .read_resource::<EventBus<ServerEvent>>()
+                                        .emit_now(ServerEvent::RefuelLantern(entity));
+                                    Some(InventoryUpdateEvent::Used)
+                                } else {
+                                    if let Some(client) =
+                                        state.ecs().read_storage::<Client>().get(entity)
+                                    {
+                                        client.send_fallible(ServerGeneral::server_msg(
+                                            ChatType::Meta,
+                                            "Your lantern is already full.",
+                                        ));
+                                    }
+                                    inventory.insert_or_stack_at(slot, item).expect(
+                                        "slot was just vacated of item, so it definitely fits \
+                                         there.",
+                                    );
+                                    None
+                                }
+                            },
+                            ItemKind::Utility {
+                                kind: item::Utility::Trap(kind),
+                                ..
+                            } => {
+                                let max_traps = state
+                                    .ecs()
+                                    .read_resource::<Settings>()
+                                    .gameplay
+                                    .traps
+                                    .max_per_player;
+                                let on_ground = state
+                                    .read_storage::<comp::PhysicsState>()
+                                    .get(entity)
+                                    .map_or(false, |physics| physics.on_ground.is_some());
+                                let placed = state
+                                    .read_storage::<comp::Trap>()
+                                    .join()
+                                    .filter(|trap| trap.owner == uid)
+                                    .count()
+                                    + placed_traps.len();
+                                let pos = state.read_component_copied::<comp::Pos>(entity);
+
+                                match pos {
+                                    Some(pos) if on_ground && placed < max_traps => {
+                                        placed_traps.push((pos, *kind));
+                                        Some(InventoryUpdateEvent::Used)
+                                    },
+                                    _ => {
+                                        inventory.insert_or_stack_at(slot, item).expect(
+                                            "slot was just vacated of item, so it definitely fits \
+                                             there.",
+                                        );
+                                        None
+                                    },
+                                }
+                            },
                             _ => {
                                 inventory.insert_or_stack_at(slot, item).expect(
                                     "slot was just vacated of item, so it definitely fits there.",
@@ -444,10 +506,6 @@ pub fn handle_inventory(server: &mut Server, entity: EcsEntity, manip: comp::Inv
                     }
                 },
                 Slot::Equip(slot) => {
-                    if slot == slot::EquipSlot::Lantern {
-                        snuff_lantern(&mut state.ecs().write_storage(), entity);
-                    }
-
                     if let Some(pos) = state.ecs().read_storage::<comp::Pos>().get(entity) {
                         // Unequip the item, any items that no longer fit within the inventory (due
                         // to unequipping a bag for example) will be dropped on the floor
@@ -469,7 +527,17 @@ pub fn handle_inventory(server: &mut Server, entity: EcsEntity, manip: comp::Inv
 
             drop(inventories);
 
-            if let Some(effects) = maybe_effect {
+            if let Some((mut effects, potion_sickness)) = maybe_effect {
+                if potion_sickness {
+                    let config = PotionSicknessConfig::load().read();
+                    let stacks = state
+                        .ecs()
+                        .read_storage::<comp::Buffs>()
+                        .get(entity)
+                        .map_or(0, |buffs| buffs.stack_count(comp::BuffKind::PotionSickness));
+                    config.reduce_healing(&mut effects, stacks);
+                    effects.push(config.stack());
+                }
                 for effect in effects {
                     state.apply_effect(entity, effect, None);
                 }
@@ -483,24 +551,8 @@ pub fn handle_inventory(server: &mut Server, entity: EcsEntity, manip: comp::Inv
             }
         },
         comp::InventoryManip::Swap(a, b) => {
-            use item::ItemKind;
             let ecs = state.ecs();
 
-            if let Some(lantern_info) = match (a, b) {
-                // Only current possible lantern swap is between Slot::Inventory and Slot::Equip
-                // add more cases if needed
-                (Slot::Equip(slot::EquipSlot::Lantern), Slot::Inventory(slot))
-                | (Slot::Inventory(slot), Slot::Equip(slot::EquipSlot::Lantern)) => {
-                    inventory.get(slot).and_then(|i| match &*i.kind() {
-                        ItemKind::Lantern(lantern) => Some((lantern.color(), lantern.strength())),
-                        _ => None,
-                    })
-                },
-                _ => None,
-            } {
-                swap_lantern(&mut ecs.write_storage(), entity, lantern_info);
-            }
-
             if let Some(pos) = ecs.read_storage::<comp::Pos>().get(entity) {
                 let mut merged_stacks = false;
 
@@ -651,12 +703,16 @@ pub fn handle_inventory(server: &mut Server, entity: EcsEntity, manip: comp::Inv
                 sprite_pos
                     .filter(|pos| {
                         let entity_cylinder = get_cylinder(state, entity);
-                        let in_range = within_pickup_range(entity_cylinder, || {
-                            Some(find_dist::Cube {
-                                min: pos.as_(),
-                                side_length: 1.0,
-                            })
-                        });
+                        let in_range = within_pickup_range(
+                            &state.ecs().read_resource::<InteractionRanges>(),
+                            entity_cylinder,
+                            || {
+                                Some(find_dist::Cube {
+                                    min: pos.as_(),
+                                    side_length: 1.0,
+                                })
+                            },
+                        );
                         if !in_range {
                             debug!(
                                 ?entity_cylinder,
@@ -885,20 +941,44 @@ pub fn handle_inventory(server: &mut Server, entity: EcsEntity, manip: comp::Inv
         new_entity.build();
     }
 
+    // Place traps
+    let time = *state.ecs().read_resource::<Time>();
+    for (pos, kind) in placed_traps {
+        state
+            .create_object(pos, kind.body())
+            .with(comp::Trap::new(kind, uid, time))
+            .build();
+    }
+
     let mut trades = state.ecs().write_resource::<Trades>();
     if trades.in_mutable_trade(&uid) {
         // manipulating the inventory mutated the trade, so reset the accept flags
         trades.implicit_mutation_occurred(&uid);
     }
+    drop(trades);
+
+    // A lit lantern shines with the light of the lantern equipped now, e.g. after
+    // lanterns were swapped or the lantern was taken off
+    let lantern_changed = refresh_lantern(
+        &mut state.ecs().write_storage(),
+        entity,
+        state.ecs().read_storage::<comp::Inventory>().get(entity),
+    );
+    if lantern_changed {
+        notify_lantern_update(server, entity);
+    }
 }
 
 fn within_pickup_range<S: FindDist<find_dist::Cylinder>>(
+    ranges: &InteractionRanges,
     entity_cylinder: Option<find_dist::Cylinder>,
     shape_fn: impl FnOnce() -> Option<S>,
 ) -> bool {
     entity_cylinder
         .and_then(|entity_cylinder| {
-            shape_fn().map(|shape| shape.min_distance(entity_cylinder) < MAX_PICKUP_RANGE)
+            shape_fn().map(|shape| {
+                shape.min_distance(entity_cylinder) < ranges.range(InteractionKind::Pickup)
+            })
         })
         .unwrap_or(false)
 }
@@ -950,9 +1030,59 @@ mod tests {
         let position = Pos(Vec3::zero());
         let item_position = Pos(Vec3::one());
 
-        assert!(within_pickup_range(test_cylinder(position), || {
-            test_cylinder(item_position)
-        },),);
+        assert!(within_pickup_range(
+            &InteractionRanges::load_default(),
+            test_cylinder(position),
+            || test_cylinder(item_position),
+        ));
+    }
+
+    #[test]
+    fn lit_lanterns_shine_with_the_equipped_lantern() {
+        use comp::{item::Item, slot::EquipSlot};
+
+        let mut world = specs::World::new();
+        world.register::<LightEmitter>();
+        let entity = world.create_entity().build();
+        let blue = Item::new_from_asset_expect("common.items.lantern.blue_0");
+        let red = Item::new_from_asset_expect("common.items.lantern.red_0");
+        let mut inventory = comp::Inventory::with_empty();
+        inventory.replace_loadout_item(EquipSlot::Lantern, Some(blue));
+        let blue_light = inventory.lantern_light().unwrap();
+        assert!(set_lantern(
+            &mut world.write_storage(),
+            entity,
+            Some(&inventory),
+            true
+        ));
+
+        // Swapping lanterns changes the light of the lit one
+        inventory.replace_loadout_item(EquipSlot::Lantern, Some(red));
+        let red_light = inventory.lantern_light().unwrap();
+        assert_ne!(red_light, blue_light);
+        assert!(refresh_lantern(
+            &mut world.write_storage(),
+            entity,
+            Some(&inventory)
+        ));
+        assert_eq!(
+            world.read_storage::<LightEmitter>().get(entity),
+            Some(&red_light)
+        );
+        assert!(!refresh_lantern(
+            &mut world.write_storage(),
+            entity,
+            Some(&inventory)
+        ));
+
+        // Taking the lantern off puts it out
+        inventory.replace_loadout_item(EquipSlot::Lantern, None);
+        assert!(refresh_lantern(
+            &mut world.write_storage(),
+            entity,
+            Some(&inventory)
+        ));
+        assert!(!world.read_storage::<LightEmitter>().contains(entity));
     }
 
     #[test]
@@ -960,8 +1090,10 @@ mod tests {
         let position = Pos(Vec3::zero());
         let item_position = Pos(Vec3::one() * 500.0);
 
-        assert!(!within_pickup_range(test_cylinder(position), || {
-            test_cylinder(item_position)
-        },),);
+        assert!(!within_pickup_range(
+            &InteractionRanges::load_default(),
+            test_cylinder(position),
+            || test_cylinder(item_position),
+        ));
     }
 }
@@ -8,7 +8,7 @@ use common::{
         invite::{Invite, InviteKind, InviteResponse, PendingInvites},
         ChatType, Pos,
     },
-    consts::MAX_TRADE_RANGE,
+    interaction::{InteractionKind, InteractionRanges},
     trade::{TradeResult, Trades},
     uid::Uid,
 };
@@ -62,7 +62,15 @@ pub fn handle_invite(server: &mut Server, inviter: Entity, invitee_uid: Uid, kin
     if let InviteKind::Trade = kind {
         // Check whether the inviter is in range of the invitee
         let positions = state.ecs().read_storage::<Pos>();
-        if !within_trading_range(positions.get(inviter), positions.get(invitee)) {
+        if !state
+            .ecs()
+            .read_resource::<InteractionRanges>()
+            .positions_in_range(
+                InteractionKind::Trade,
+                positions.get(inviter),
+                positions.get(invitee),
+            )
+        {
             return;
         }
     }
@@ -340,10 +348,3 @@ pub fn handle_invite_decline(server: &mut Server, entity: Entity) {
         handle_invite_answer(state, inviter, entity, InviteAnswer::Declined, kind)
     }
 }
-
-fn within_trading_range(requester_position: Option<&Pos>, invitee_position: Option<&Pos>) -> bool {
-    match (requester_position, invitee_position) {
-        (Some(rpos), Some(ipos)) => rpos.0.distance_squared(ipos.0) < MAX_TRADE_RANGE.powi(2),
-        _ => false,
-    }
-}
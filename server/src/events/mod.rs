@@ -10,24 +10,28 @@ use entity_creation::{
     handle_shockwave, handle_shoot,
 };
 use entity_manipulation::{
-    handle_aura, handle_bonk, handle_buff, handle_change_ability, handle_combo_change,
-    handle_delete, handle_destroy, handle_energy_change, handle_entity_attacked_hook,
-    handle_explosion, handle_health_change, handle_knockback, handle_land_on_ground, handle_parry,
-    handle_poise, handle_respawn, handle_teleport_to, handle_update_map_marker,
+    handle_aura, handle_bonk, handle_buff, handle_change_ability, handle_change_appearance,
+    handle_combo_change, handle_delete, handle_destroy, handle_energy_change,
+    handle_entity_attacked_hook, handle_explosion, handle_health_change, handle_knockback,
+    handle_land_on_ground, handle_parry, handle_poise, handle_respawn, handle_teleport_to,
+    handle_update_map_marker,
 };
 use group_manip::handle_group;
 use information::handle_site_info;
 use interaction::{
-    handle_create_sprite, handle_lantern, handle_mine_block, handle_mount, handle_npc_interaction,
-    handle_sound, handle_unmount,
+    handle_command_pet, handle_create_sprite, handle_lantern, handle_mine_block, handle_mount,
+    handle_npc_interaction, handle_refuel_lantern, handle_sit, handle_sound, handle_stand,
+    handle_unmount,
 };
 use inventory_manip::handle_inventory;
 use invite::{handle_invite, handle_invite_response};
-use player::{handle_client_disconnect, handle_exit_ingame, handle_possess};
+use player::{handle_client_disconnect, handle_exit_ingame, handle_possess, handle_unpossess};
 use specs::{Builder, Entity as EcsEntity, WorldExt};
 use trade::handle_process_trade_action;
 
 pub use group_manip::update_map_markers;
+pub(crate) use interaction::unmount_before_deletion;
+pub(crate) use inventory_manip::set_lantern;
 pub(crate) use trade::cancel_trades_for;
 
 mod entity_creation;
@@ -120,6 +124,7 @@ impl Server {
                 },
                 ServerEvent::EnableLantern(entity) => handle_lantern(self, entity, true),
                 ServerEvent::DisableLantern(entity) => handle_lantern(self, entity, false),
+                ServerEvent::RefuelLantern(entity) => handle_refuel_lantern(self, entity),
                 ServerEvent::NpcInteract(interactor, target) => {
                     handle_npc_interaction(self, interactor, target)
                 },
@@ -134,9 +139,12 @@ impl Server {
                 },
                 ServerEvent::Mount(mounter, mountee) => handle_mount(self, mounter, mountee),
                 ServerEvent::Unmount(mounter) => handle_unmount(self, mounter),
+                ServerEvent::Sit(entity, seat) => handle_sit(self, entity, seat),
+                ServerEvent::Stand(entity) => handle_stand(self, entity),
                 ServerEvent::Possess(possessor_uid, possesse_uid) => {
                     handle_possess(self, possessor_uid, possesse_uid)
                 },
+                ServerEvent::Unpossess(possessee_uid) => handle_unpossess(self, possessee_uid),
                 ServerEvent::InitCharacterData {
                     entity,
                     character_id,
@@ -157,9 +165,16 @@ impl Server {
                         skill_set,
                         inventory,
                         waypoint,
+                        position,
                         pets,
                         active_abilities,
                         map_marker,
+                        hotbar,
+                        settings,
+                        vitals,
+                        buffs,
+                        lantern_fuel,
+                        hardcore,
                     ) = components;
                     let components = PersistedComponents {
                         body,
@@ -167,9 +182,16 @@ impl Server {
                         skill_set,
                         inventory,
                         waypoint,
+                        position,
                         pets,
                         active_abilities,
                         map_marker,
+                        hotbar,
+                        settings,
+                        vitals,
+                        buffs,
+                        lantern_fuel,
+                        hardcore,
                     };
                     handle_loaded_character_data(self, entity, components);
                 },
@@ -271,6 +293,11 @@ impl Server {
                     pet_entity,
                     owner_entity,
                 } => handle_tame_pet(self, pet_entity, owner_entity),
+                ServerEvent::CommandPet {
+                    owner,
+                    pet,
+                    command,
+                } => handle_command_pet(self, owner, pet, command),
                 ServerEvent::EntityAttackedHook { entity } => {
                     handle_entity_attacked_hook(self, entity)
                 },
@@ -283,6 +310,9 @@ impl Server {
                 ServerEvent::UpdateMapMarker { entity, update } => {
                     handle_update_map_marker(self, entity, update)
                 },
+                ServerEvent::ChangeAppearance { entity, body } => {
+                    handle_change_appearance(self, entity, body)
+                },
             }
         }
 
@@ -1,11 +1,21 @@
 use super::Event;
 use crate::{
-    client::Client, metrics::PlayerMetrics, persistence::character_updater::CharacterUpdater,
-    presence::Presence, state_ext::StateExt, BattleModeBuffer, Server,
+    client::Client,
+    metrics::PlayerMetrics,
+    persistence::{
+        buffs::buffs_to_persist,
+        character_loader::CharacterListCache,
+        character_updater::{vitals_to_persist, CharacterUpdater},
+    },
+    presence::Presence,
+    settings::Settings,
+    state_ext::StateExt,
+    BattleModeBuffer, Server,
 };
 use common::{
     comp,
     comp::{group, pet::is_tameable},
+    resources::Time,
     uid::{Uid, UidAllocator},
 };
 use common_base::span;
@@ -18,6 +28,10 @@ pub fn handle_exit_ingame(server: &mut Server, entity: EcsEntity) {
     span!(_guard, "handle_exit_ingame");
     let state = server.state_mut();
 
+    // Leave the possessed entity behind before anything is saved, so that the
+    // player's own entity is the one that is persisted and deleted
+    let entity = leave_possessed(state, entity);
+
     // Sync the player's character data to the database. This must be done before
     // removing any components from the entity
     let entity = persist_entity(state, entity);
@@ -53,6 +67,13 @@ pub fn handle_exit_ingame(server: &mut Server, entity: EcsEntity) {
         // Tell client its request was successful
         client.send_fallible(ServerGeneral::ExitInGameSuccess);
 
+        // The character has just been saved, so a list loaded before it was played
+        // is out of date
+        state
+            .ecs()
+            .write_resource::<CharacterListCache>()
+            .invalidate(&player.uuid().to_string());
+
         let entity_builder = state.ecs_mut().create_entity().with(client).with(player);
 
         // Preserve group component if present
@@ -172,12 +193,21 @@ pub fn handle_client_disconnect(
 
     let state = server.state_mut();
 
+    // Leave the possessed entity behind before anything is saved, so that the
+    // player's own entity is the one that is persisted and deleted
+    entity = leave_possessed(state, entity);
+
     // Tell other clients to remove from player list
     // And send a disconnected message
-    if let (Some(uid), Some(_)) = (
+    if let (Some(uid), Some(player)) = (
         state.read_storage::<Uid>().get(entity),
         state.read_storage::<comp::Player>().get(entity),
     ) {
+        state
+            .ecs()
+            .write_resource::<CharacterListCache>()
+            .invalidate(&player.uuid().to_string());
+
         state.notify_players(ServerGeneral::server_msg(comp::ChatType::Offline(*uid), ""));
 
         state.notify_players(ServerGeneral::PlayerListUpdate(PlayerListUpdate::Remove(
@@ -237,6 +267,36 @@ fn persist_entity(state: &mut State, entity: EcsEntity) -> EcsEntity {
                     .read_storage::<comp::MapMarker>()
                     .get(entity)
                     .cloned();
+                let hotbar = state
+                    .ecs()
+                    .read_storage::<comp::Hotbar>()
+                    .get(entity)
+                    .cloned()
+                    .unwrap_or_default();
+                let settings = state
+                    .ecs()
+                    .read_storage::<comp::CharacterSettings>()
+                    .get(entity)
+                    .cloned()
+                    .unwrap_or_default();
+                let healths = state.ecs().read_storage::<comp::Health>();
+                // Dead characters respawn at their waypoint instead of where they died
+                let position = state
+                    .ecs()
+                    .read_storage::<comp::Pos>()
+                    .get(entity)
+                    .copied()
+                    .filter(|_| healths.get(entity).map_or(true, |health| !health.is_dead));
+                let vitals = vitals_to_persist(
+                    healths.get(entity),
+                    state.ecs().read_storage::<comp::Energy>().get(entity),
+                );
+                let buffs = buffs_to_persist(state.ecs().read_storage::<comp::Buffs>().get(entity));
+                let lantern_fuel = state
+                    .ecs()
+                    .read_storage::<comp::LanternFuel>()
+                    .get(entity)
+                    .map(|fuel| fuel.remaining);
                 // Store last battle mode change
                 if let Some(change) = player_info.last_battlemode_change {
                     let mode = player_info.battle_mode;
@@ -264,6 +324,7 @@ fn persist_entity(state: &mut State, entity: EcsEntity) -> EcsEntity {
                     })
                     .collect();
 
+                let playtime = character_updater.end_session(char_id);
                 character_updater.add_pending_logout_update(
                     char_id,
                     (
@@ -271,8 +332,15 @@ fn persist_entity(state: &mut State, entity: EcsEntity) -> EcsEntity {
                         inventory.clone(),
                         pets,
                         waypoint,
+                        position,
                         active_abilities.clone(),
                         map_marker,
+                        hotbar,
+                        settings,
+                        vitals,
+                        buffs,
+                        lantern_fuel,
+                        playtime,
                     ),
                 );
             },
@@ -284,19 +352,17 @@ fn persist_entity(state: &mut State, entity: EcsEntity) -> EcsEntity {
     entity
 }
 
+/// The item given to entities possessed by a player
+const POSSESS_ITEM: &str = "common.items.debug.admin_stick";
+
 /// FIXME: This code is dangerous and needs to be refactored.  We can't just
 /// comment it out, but it needs to be fixed for a variety of reasons.  Get rid
 /// of this ASAP!
 pub fn handle_possess(server: &mut Server, possessor_uid: Uid, possessee_uid: Uid) {
-    use crate::presence::RegionSubscription;
-    use common::{
-        comp::{inventory::slot::EquipSlot, item, slot::Slot, Inventory},
-        region::RegionMap,
-    };
+    use common::comp::Inventory;
     use common_net::sync::WorldSyncExt;
 
     let state = server.state_mut();
-    let mut delete_entity = None;
 
     if let (Some(possessor), Some(possessee)) = (
         state.ecs().entity_from_uid(possessor_uid.into()),
@@ -306,6 +372,21 @@ pub fn handle_possess(server: &mut Server, possessor_uid: Uid, possessee_uid: Ui
         // them are not met.
         {
             let ecs = state.ecs();
+            let notify = |msg: String| {
+                if let Some(client) = ecs.read_storage::<Client>().get(possessor) {
+                    client.send_fallible(ServerGeneral::server_msg(
+                        comp::ChatType::CommandError,
+                        msg,
+                    ));
+                }
+            };
+            // Possession is only meant for admins, don't trust whatever caused this event
+            if ecs.read_storage::<comp::Admin>().get(possessor).is_none() {
+                error!("Non-admin entity {} attempted to possess", possessor_uid);
+                notify("You don't have permission to possess entities".to_owned());
+                return;
+            }
+
             // Check that entities still exist
             if !possessor.gen().is_alive()
                 || !ecs.is_alive(possessor)
@@ -324,6 +405,7 @@ pub fn handle_possess(server: &mut Server, possessor_uid: Uid, possessee_uid: Ui
 
             if clients.contains(possessee) || players.contains(possessee) {
                 error!("Can't possess other players!");
+                notify("You can't possess other players".to_owned());
                 return;
             }
 
@@ -335,178 +417,569 @@ pub fn handle_possess(server: &mut Server, possessor_uid: Uid, possessee_uid: Ui
             // Limit possessible entities to those in the client's subscribed regions (so
             // that the entity already exists on the client, this reduces the
             // amount of syncing edge cases to consider).
-            let subscriptions = ecs.read_storage::<RegionSubscription>();
-            let region_map = ecs.read_resource::<RegionMap>();
-            let possessee_in_subscribed_region = subscriptions
-                .get(possessor)
-                .iter()
-                .flat_map(|s| s.regions.iter())
-                .filter_map(|key| region_map.get(*key))
-                .any(|region| region.entities().contains(possessee.id()));
-            if !possessee_in_subscribed_region {
+            if !in_subscribed_region(ecs, possessor, possessee) {
+                notify("You can only possess entities that are loaded around you".to_owned());
                 return;
             }
 
-            // No early returns allowed after this.
-        }
-
-        // Sync the player's character data to the database. This must be done before
-        // moving any components from the entity.
-        //
-        // NOTE: Below we delete old entity (if PresenceKind::Character) as if logging
-        // out. This is to prevent any potential for item duplication (although
-        // it would only be possible if the player could repossess their entity,
-        // hand off some items, and then crash the server in a particular time
-        // window, and only admins should have access to the item with this ability
-        // in the first place (though that isn't foolproof)). We could potentially fix
-        // this but it would require some tweaks to the CharacterUpdater code
-        // (to be able to deque the pending persistence request issued here if
-        // repossesing the original character), and it seems prudent to be more
-        // conservative with making changes there to support this feature.
-        let possessor = persist_entity(state, possessor);
-        let ecs = state.ecs();
-
-        let mut clients = ecs.write_storage::<Client>();
-
-        // Transfer client component. Note: we require this component for possession.
-        let client = clients
-            .remove(possessor)
-            .expect("Checked client component was present above!");
-        client.send_fallible(ServerGeneral::SetPlayerEntity(possessee_uid));
-        // Note: we check that the `possessor` and `possessee` entities exist above, so
-        // this should never panic.
-        clients
-            .insert(possessee, client)
-            .expect("Checked entity was alive!");
-
-        // Other components to transfer if they exist.
-        fn transfer_component<C: specs::Component>(
-            storage: &mut specs::WriteStorage<'_, C>,
-            possessor: EcsEntity,
-            possessee: EcsEntity,
-            transform: impl FnOnce(C) -> C,
-        ) {
-            if let Some(c) = storage.remove(possessor) {
-                // Note: we check that the `possessor` and `possessee` entities exist above, so
-                // this should never panic.
-                storage
-                    .insert(possessee, transform(c))
-                    .expect("Checked entity was alive!");
+            // Possessing something far away would also load the terrain around it
+            if let Some(range) = ecs.read_resource::<Settings>().gameplay.possession_range {
+                let positions = ecs.read_storage::<comp::Pos>();
+                let distance = positions
+                    .get(possessor)
+                    .zip(positions.get(possessee))
+                    .map(|(possessor, possessee)| possessor.0.distance(possessee.0));
+                if distance.map_or(true, |distance| distance > range) {
+                    notify(format!(
+                        "You can only possess entities within {} blocks of you",
+                        range
+                    ));
+                    return;
+                }
             }
-        }
 
-        let mut players = ecs.write_storage::<comp::Player>();
-        let mut presence = ecs.write_storage::<Presence>();
-        let mut subscriptions = ecs.write_storage::<RegionSubscription>();
-        let mut admins = ecs.write_storage::<comp::Admin>();
-        let mut waypoints = ecs.write_storage::<comp::Waypoint>();
+            // No early returns allowed after this.
+        }
 
-        transfer_component(&mut players, possessor, possessee, |x| x);
-        transfer_component(&mut presence, possessor, possessee, |mut presence| {
-            presence.kind = match presence.kind {
-                PresenceKind::Spectator => PresenceKind::Spectator,
+        let mut character_id = None;
+        let displaced_components = match transfer_player(
+            state,
+            (possessor, possessor_uid),
+            (possessee, possessee_uid),
+            |kind| match kind {
                 // This prevents persistence from overwriting original character info with stuff
                 // from the new character.
-                PresenceKind::Character(_) => {
-                    delete_entity = Some(possessor);
+                PresenceKind::Character(id) => {
+                    character_id = Some(id);
                     PresenceKind::Possessor
                 },
-                PresenceKind::Possessor => PresenceKind::Possessor,
-            };
+                kind => kind,
+            },
+        ) {
+            Some(displaced_components) => displaced_components,
+            None => return,
+        };
 
-            presence
-        });
-        transfer_component(&mut subscriptions, possessor, possessee, |x| x);
-        transfer_component(&mut admins, possessor, possessee, |x| x);
-        transfer_component(&mut waypoints, possessor, possessee, |x| x);
-
-        // If a player is posessing, add possessee to playerlist as player and remove
-        // old player.
-        // Fetches from possessee entity here since we have transferred over the
-        // `Player` component.
-        if let Some(player) = players.get(possessee) {
-            use common_net::msg;
-
-            let add_player_msg = ServerGeneral::PlayerListUpdate(PlayerListUpdate::Add(
-                possessee_uid,
-                msg::server::PlayerInfo {
-                    player_alias: player.alias.clone(),
-                    is_online: true,
-                    is_moderator: admins.contains(possessee),
-                    character: ecs.read_storage::<comp::Stats>().get(possessee).map(|s| {
-                        msg::CharacterInfo {
-                            name: s.name.clone(),
-                        }
-                    }),
-                    uuid: player.uuid(),
-                },
-            ));
-            let remove_player_msg =
-                ServerGeneral::PlayerListUpdate(PlayerListUpdate::Remove(possessor_uid));
+        let ecs = state.ecs();
 
-            drop((clients, players)); // need to drop so we can use `notify_players` below
-            state.notify_players(remove_player_msg);
-            state.notify_players(add_player_msg);
+        // The character stays as it is until the player returns to it, so it is taken
+        // out of the world rather than deleted (see `comp::PossessedFrom`)
+        if let Some(character_id) = character_id {
+            if let Some(pos) = ecs.write_storage::<comp::Pos>().remove(possessor) {
+                let buffs = ecs.write_storage::<comp::Buffs>().remove(possessor);
+                ecs.write_storage::<comp::PossessedFrom>()
+                    .insert(possessor, comp::PossessedFrom {
+                        character_id,
+                        pos,
+                        buffs,
+                    })
+                    .expect("Checked entity was alive!");
+            }
         }
 
         // Put possess item into loadout
-        let mut inventories = ecs.write_storage::<Inventory>();
-        let mut inventory = inventories
-            .entry(possessee)
-            .expect("Nobody has &mut World, so there's no way to delete an entity.")
-            .or_insert(Inventory::with_empty());
-
-        let debug_item = comp::Item::new_from_asset_expect("common.items.debug.admin_stick");
-        if let item::ItemKind::Tool(_) = &*debug_item.kind() {
-            let leftover_items = inventory.swap(
-                Slot::Equip(EquipSlot::ActiveMainhand),
-                Slot::Equip(EquipSlot::InactiveMainhand),
-            );
-            assert!(
-                leftover_items.is_empty(),
-                "Swapping active and inactive mainhands never results in leftover items"
-            );
-            inventory.replace_loadout_item(EquipSlot::ActiveMainhand, Some(debug_item));
-        }
-        drop(inventories);
+        let displaced_item = equip_possess_item(
+            &mut ecs
+                .write_storage::<Inventory>()
+                .entry(possessee)
+                .expect("Nobody has &mut World, so there's no way to delete an entity.")
+                .or_insert(Inventory::with_empty()),
+        );
+
+        // Remember which entity the player came from, so that they can return to it.
+        // When possessing from an already possessed entity, the original one is
+        // kept.
+        let former_possessing = ecs.write_storage::<comp::Possessing>().remove(possessor);
+        let original = match former_possessing {
+            Some(possessing) => {
+                // The former shell was possessed itself, so return it to its former self
+                let original = possessing.original;
+                release_possessed(ecs, possessor, possessing);
+                original
+            },
+            None => possessor_uid,
+        };
+        // Remove will of the entity, it is given back when the player leaves
+        let agent = ecs
+            .write_storage::<comp::Agent>()
+            .remove(possessee)
+            .map(Box::new);
+        let expires_at = ecs
+            .read_resource::<Settings>()
+            .gameplay
+            .possession_timeout
+            .map(|timeout| Time(ecs.read_resource::<Time>().0 + timeout.as_secs_f64()));
+        // The player starts out with full health and energy, rather than taking over
+        // e.g. a creature that is about to die. Possessing is no way to heal, so the
+        // entity gets its own health and energy back afterwards.
+        let health = ecs
+            .write_storage::<comp::Health>()
+            .get_mut(possessee)
+            .filter(|health| !health.is_dead)
+            .map(|mut health| {
+                let current = health.current();
+                health.revive();
+                current
+            });
+        let energy = ecs
+            .write_storage::<comp::Energy>()
+            .get_mut(possessee)
+            .map(|mut energy| {
+                let current = energy.current();
+                energy.refresh();
+                current
+            });
+        ecs.write_storage::<comp::Possessing>()
+            .insert(possessee, comp::Possessing {
+                original,
+                agent,
+                displaced_item,
+                displaced_components,
+                expires_at,
+                health,
+                energy,
+            })
+            .expect("Checked entity was alive!");
 
-        // Remove will of the entity
-        ecs.write_storage::<comp::Agent>().remove(possessee);
         // Reset controller of former shell
         if let Some(c) = ecs.write_storage::<comp::Controller>().get_mut(possessor) {
             *c = Default::default();
         }
 
-        // Send client new `SyncFrom::ClientEntity` components and tell it to
-        // deletes these on the old entity.
-        let clients = ecs.read_storage::<Client>();
-        let client = clients
-            .get(possessee)
-            .expect("We insert this component above and have exclusive access to the world.");
-        use crate::sys::sentinel::TrackedStorages;
-        use specs::SystemData;
-        let tracked_storages = TrackedStorages::fetch(ecs);
-        let comp_sync_package = tracked_storages.create_sync_from_client_entity_switch(
-            possessor_uid,
-            possessee_uid,
-            possessee,
+        sync_client_entity_switch(ecs, possessor_uid, (possessee, possessee_uid));
+    }
+}
+
+/// Gives a player that possessed an entity back control of the entity they
+/// possessed it from. If that entity no longer exists or isn't visible to the
+/// client, the player is sent back to character selection so that they can
+/// spawn again.
+pub fn handle_unpossess(server: &mut Server, possessee_uid: Uid) {
+    use common_net::sync::WorldSyncExt;
+
+    let state = server.state_mut();
+    let possessee = match state.ecs().entity_from_uid(possessee_uid.into()) {
+        Some(possessee) => possessee,
+        None => return,
+    };
+    if !state
+        .ecs()
+        .read_storage::<comp::Possessing>()
+        .contains(possessee)
+    {
+        debug!("Can't unpossess an entity that isn't possessed");
+        return;
+    }
+
+    if return_to_original(state, possessee, possessee_uid).is_none() {
+        handle_exit_ingame(server, possessee);
+    }
+}
+
+/// Ends the possession of an entity that died, instead of the player going
+/// through the respawn of the entity. The player is given back control of the
+/// entity they possessed it from, or sent back to character selection if that
+/// is gone, while the possessed entity dies as what it was before.
+pub fn handle_possessed_death(server: &mut Server, possessee: EcsEntity) {
+    use common_net::sync::WorldSyncExt;
+
+    let state = server.state_mut();
+    if !state
+        .ecs()
+        .read_storage::<comp::Possessing>()
+        .contains(possessee)
+    {
+        return;
+    }
+    let possessee_uid = match state.ecs().uid_from_entity(possessee) {
+        Some(uid) => uid,
+        None => return,
+    };
+
+    if return_to_original(state, possessee, possessee_uid).is_none() {
+        if let Some(shell) = release_into_shell(state, possessee, possessee_uid) {
+            handle_exit_ingame(server, shell);
+        }
+    }
+}
+
+/// Moves the player possessing `possessee` back to the entity they possessed it
+/// from, and returns that entity. `None` if it's gone or not visible to the
+/// client anymore, in which case the player keeps possessing `possessee`.
+fn return_to_original(
+    state: &mut State,
+    possessee: EcsEntity,
+    possessee_uid: Uid,
+) -> Option<EcsEntity> {
+    use common_net::sync::WorldSyncExt;
+
+    let original_uid = state
+        .ecs()
+        .read_storage::<comp::Possessing>()
+        .get(possessee)?
+        .original;
+    let original = state
+        .ecs()
+        .entity_from_uid(original_uid.into())
+        .filter(|original| {
+            state.ecs().is_alive(*original)
+                && !state.ecs().read_storage::<Client>().contains(*original)
+                && (state
+                    .ecs()
+                    .read_storage::<comp::PossessedFrom>()
+                    .contains(*original)
+                    || in_subscribed_region(state.ecs(), possessee, *original))
+        })?;
+
+    // A character that was taken out of the world is gone from the client, and is
+    // only put back into a region on the next tick, so the client is told about it
+    // right away
+    let character_id = {
+        let ecs = state.ecs();
+        let possessed_from = ecs.read_storage::<comp::PossessedFrom>();
+        possessed_from.get(original).map(|possessed_from| {
+            if let Some(client) = ecs.read_storage::<Client>().get(possessee) {
+                use crate::sys::sentinel::TrackedStorages;
+                use specs::SystemData;
+
+                if let Some(pkg) = TrackedStorages::fetch(ecs).create_entity_package(
+                    original,
+                    Some(possessed_from.pos),
+                    ecs.read_storage().get(original).copied(),
+                    ecs.read_storage().get(original).copied(),
+                ) {
+                    client.send_fallible(ServerGeneral::CreateEntity(pkg));
+                }
+            }
+            possessed_from.character_id
+        })
+    };
+
+    // The original entity has nothing to displace, its player components were
+    // moved away from it
+    transfer_player(
+        state,
+        (possessee, possessee_uid),
+        (original, original_uid),
+        |kind| character_id.map_or(kind, PresenceKind::Character),
+    )?;
+
+    let ecs = state.ecs();
+    if let Some(possessed_from) = ecs.write_storage::<comp::PossessedFrom>().remove(original) {
+        let _ = ecs.write_storage().insert(original, possessed_from.pos);
+        if let Some(buffs) = possessed_from.buffs {
+            let _ = ecs.write_storage().insert(original, buffs);
+        }
+    }
+    if let Some(possessing) = ecs.write_storage::<comp::Possessing>().remove(possessee) {
+        release_possessed(ecs, possessee, possessing);
+    }
+    if let Some(c) = ecs.write_storage::<comp::Controller>().get_mut(possessee) {
+        *c = Default::default();
+    }
+
+    sync_client_entity_switch(ecs, possessee_uid, (original, original_uid));
+    Some(original)
+}
+
+/// Ends the possession of a player that is leaving, so that the possessed
+/// entity stays in the world as it was before instead of being deleted with
+/// the player. The player goes back to the entity they possessed from, or to a
+/// new one if that is gone, which is returned to be deleted in its place.
+fn leave_possessed(state: &mut State, entity: EcsEntity) -> EcsEntity {
+    use common_net::sync::WorldSyncExt;
+
+    if !state
+        .ecs()
+        .read_storage::<comp::Possessing>()
+        .contains(entity)
+    {
+        return entity;
+    }
+    let uid = match state.ecs().uid_from_entity(entity) {
+        Some(uid) => uid,
+        None => return entity,
+    };
+    return_to_original(state, entity, uid)
+        .or_else(|| release_into_shell(state, entity, uid))
+        .unwrap_or(entity)
+}
+
+/// Moves the player possessing `possessee` to a new entity without anything but
+/// the player's components, and returns it. `possessee` is returned to its
+/// former self.
+fn release_into_shell(
+    state: &mut State,
+    possessee: EcsEntity,
+    possessee_uid: Uid,
+) -> Option<EcsEntity> {
+    use common_net::sync::WorldSyncExt;
+
+    let shell = state.ecs_mut().create_entity_synced().build();
+    let shell_uid = state.ecs().uid_from_entity(shell)?;
+    if transfer_player(
+        state,
+        (possessee, possessee_uid),
+        (shell, shell_uid),
+        |kind| kind,
+    )
+    .is_none()
+    {
+        if let Err(e) = state.delete_entity_recorded(shell) {
+            error!(?e, ?shell, "Failed to delete unused entity");
+        }
+        return None;
+    }
+    let ecs = state.ecs();
+    if let Some(possessing) = ecs.write_storage::<comp::Possessing>().remove(possessee) {
+        release_possessed(ecs, possessee, possessing);
+    }
+    if let Some(c) = ecs.write_storage::<comp::Controller>().get_mut(possessee) {
+        *c = Default::default();
+    }
+    Some(shell)
+}
+
+/// Whether `entity` is in one of the regions `player` is subscribed to, and
+/// therefore exists on their client.
+fn in_subscribed_region(ecs: &specs::World, player: EcsEntity, entity: EcsEntity) -> bool {
+    use crate::presence::RegionSubscription;
+    use common::region::RegionMap;
+
+    let subscriptions = ecs.read_storage::<RegionSubscription>();
+    let region_map = ecs.read_resource::<RegionMap>();
+    subscriptions
+        .get(player)
+        .iter()
+        .flat_map(|s| s.regions.iter())
+        .filter_map(|key| region_map.get(*key))
+        .any(|region| region.entities().contains(entity.id()))
+}
+
+/// Moves the `Client` and the components describing the player from one
+/// entity to another, and updates the player list accordingly.
+///
+/// Either everything is moved or nothing is: if `from` has no `Client` or
+/// `to` is gone, no component is touched and `None` is returned, so that a
+/// failed transfer can't leave the player without a client or split their
+/// components between the two entities.
+///
+/// Components that `to` had of its own are taken off it, so that it only has
+/// the ones of the player. The ones a creature can have are returned, to be
+/// given back once the player leaves it.
+#[must_use]
+fn transfer_player(
+    state: &mut State,
+    (from, from_uid): (EcsEntity, Uid),
+    (to, to_uid): (EcsEntity, Uid),
+    map_presence: impl FnOnce(PresenceKind) -> PresenceKind,
+) -> Option<comp::DisplacedComponents> {
+    use crate::presence::RegionSubscription;
+
+    let ecs = state.ecs();
+
+    let mut clients = ecs.write_storage::<Client>();
+    let mut players = ecs.write_storage::<comp::Player>();
+    let mut presence = ecs.write_storage::<Presence>();
+    let mut subscriptions = ecs.write_storage::<RegionSubscription>();
+    let mut admins = ecs.write_storage::<comp::Admin>();
+    let mut waypoints = ecs.write_storage::<comp::Waypoint>();
+
+    // Inserting only fails if the entity was deleted, and nothing can delete
+    // entities while we hold the world, so checking this up front means that none
+    // of the moves below can fail halfway through.
+    if !ecs.is_alive(to) || !clients.contains(from) {
+        error!(
+            ?from_uid,
+            ?to_uid,
+            "Can't transfer player, either the target entity is gone or the source entity has no \
+             client"
         );
-        if !comp_sync_package.is_empty() {
-            client.send_fallible(ServerGeneral::CompSync(comp_sync_package, 0)); // TODO: Check if this should be zero
+        return None;
+    }
+
+    /// Moves the component from `from` to `to`, and returns the one `to` had
+    fn transfer_component<C: specs::Component>(
+        storage: &mut specs::WriteStorage<'_, C>,
+        from: EcsEntity,
+        to: EcsEntity,
+        transform: impl FnOnce(C) -> C,
+    ) -> Option<C> {
+        let displaced = storage.remove(to);
+        if let Some(c) = storage.remove(from) {
+            storage
+                .insert(to, transform(c))
+                .expect("Checked entity was alive!");
         }
+        displaced
+    }
+
+    // Players, and therefore clients, can't be possessed, so `to` never has
+    // any of these
+    transfer_component(&mut clients, from, to, |client| {
+        client.send_fallible(ServerGeneral::SetPlayerEntity(to_uid));
+        client
+    });
+    transfer_component(&mut players, from, to, |x| x);
+    transfer_component(&mut presence, from, to, |mut presence| {
+        presence.kind = map_presence(presence.kind);
+        presence
+    });
+    transfer_component(&mut subscriptions, from, to, |x| x);
+    let displaced = comp::DisplacedComponents {
+        admin: transfer_component(&mut admins, from, to, |x| x),
+        waypoint: transfer_component(&mut waypoints, from, to, |x| x),
+    };
+
+    // If a player is posessing, add possessee to playerlist as player and remove
+    // old player.
+    // Fetches from the new entity here since we have transferred over the `Player`
+    // component.
+    if let Some(player) = players.get(to) {
+        use common_net::msg;
+
+        let add_player_msg = ServerGeneral::PlayerListUpdate(PlayerListUpdate::Add(
+            to_uid,
+            msg::server::PlayerInfo {
+                player_alias: player.alias.clone(),
+                is_online: true,
+                is_moderator: admins.contains(to),
+                character: ecs
+                    .read_storage::<comp::Stats>()
+                    .get(to)
+                    .map(|s| msg::CharacterInfo {
+                        name: s.name.clone(),
+                    }),
+                uuid: player.uuid(),
+            },
+        ));
+        let remove_player_msg = ServerGeneral::PlayerListUpdate(PlayerListUpdate::Remove(from_uid));
+
+        drop((clients, players)); // need to drop so we can use `notify_players` below
+        state.notify_players(remove_player_msg);
+        state.notify_players(add_player_msg);
+    }
+
+    Some(displaced)
+}
+
+/// Makes the possess item the active weapon of an entity. The active weapon
+/// becomes the inactive one, and whatever was in the inactive slot is returned
+/// so that it can be given back once possession ends.
+fn equip_possess_item(inventory: &mut comp::Inventory) -> Option<comp::Item> {
+    use common::comp::{inventory::slot::EquipSlot, item::ItemKind, slot::Slot};
+
+    let possess_item = comp::Item::new_from_asset_expect(POSSESS_ITEM);
+    if !matches!(&*possess_item.kind(), ItemKind::Tool(_)) {
+        warn!(
+            "{} is not a tool, possessed entities are left with their own weapons",
+            POSSESS_ITEM
+        );
+        return None;
     }
 
-    // Outside block above to prevent borrow conflicts (i.e. convenient to let
-    // everything drop at the end of the block rather than doing it manually for
-    // this). See note on `persist_entity` call above for why we do this.
-    if let Some(entity) = delete_entity {
-        // Delete old entity
-        if let Err(e) = state.delete_entity_recorded(entity) {
-            error!(
-                ?e,
-                ?entity,
-                "Failed to delete entity when removing character during possession."
+    let displaced_item = inventory.replace_loadout_item(EquipSlot::InactiveMainhand, None);
+    let leftover_items = inventory.swap(
+        Slot::Equip(EquipSlot::ActiveMainhand),
+        Slot::Equip(EquipSlot::InactiveMainhand),
+    );
+    assert!(
+        leftover_items.is_empty(),
+        "Swapping active and inactive mainhands never results in leftover items"
+    );
+    inventory.replace_loadout_item(EquipSlot::ActiveMainhand, Some(possess_item));
+    displaced_item
+}
+
+/// Takes the possess item away from an entity and gives it back the item that
+/// was displaced by it (see [`equip_possess_item`]). The possessor may have
+/// swapped weapons in the meantime, so the possess item is looked for in both
+/// mainhand slots.
+fn unequip_possess_item(inventory: &mut comp::Inventory, displaced_item: Option<comp::Item>) {
+    use common::comp::{inventory::slot::EquipSlot, item::ItemDefinitionId, slot::Slot};
+
+    let is_possess_item = |inventory: &comp::Inventory, slot| {
+        inventory.equipped(slot).map_or(false, |item| {
+            item.item_definition_id() == ItemDefinitionId::Simple(POSSESS_ITEM)
+        })
+    };
+    if is_possess_item(inventory, EquipSlot::ActiveMainhand) {
+        inventory.replace_loadout_item(EquipSlot::ActiveMainhand, None);
+        let leftover_items = inventory.swap(
+            Slot::Equip(EquipSlot::ActiveMainhand),
+            Slot::Equip(EquipSlot::InactiveMainhand),
+        );
+        assert!(
+            leftover_items.is_empty(),
+            "Swapping active and inactive mainhands never results in leftover items"
+        );
+    } else if is_possess_item(inventory, EquipSlot::InactiveMainhand) {
+        inventory.replace_loadout_item(EquipSlot::InactiveMainhand, None);
+    }
+
+    if let Some(item) = displaced_item {
+        let item = if inventory.equipped(EquipSlot::InactiveMainhand).is_none() {
+            inventory.replace_loadout_item(EquipSlot::InactiveMainhand, Some(item))
+        } else {
+            inventory.push(item).err()
+        };
+        if let Some(item) = item {
+            warn!(
+                "No room to give {:?} back to an entity after possession, it is lost",
+                item.item_definition_id()
             );
         }
     }
 }
+
+/// Returns an entity that is no longer controlled by a player to its former
+/// self: the possess item is taken away, and it gets back the agent it had
+/// before it was possessed, if it had one, along with the components the ones
+/// of the player replaced. Unless it died, it also gets back the health and
+/// energy it had.
+fn release_possessed(ecs: &specs::World, entity: EcsEntity, possessing: comp::Possessing) {
+    if let Some(inventory) = ecs.write_storage::<comp::Inventory>().get_mut(entity) {
+        unequip_possess_item(inventory, possessing.displaced_item);
+    }
+    let comp::DisplacedComponents { admin, waypoint } = possessing.displaced_components;
+    if let Some(admin) = admin {
+        let _ = ecs.write_storage().insert(entity, admin);
+    }
+    if let Some(waypoint) = waypoint {
+        let _ = ecs.write_storage().insert(entity, waypoint);
+    }
+    let mut healths = ecs.write_storage::<comp::Health>();
+    if let Some(mut health) = healths.get_mut(entity).filter(|health| !health.is_dead) {
+        if let Some(amount) = possessing.health {
+            health.restore(amount);
+        }
+        if let (Some(amount), Some(mut energy)) = (
+            possessing.energy,
+            ecs.write_storage::<comp::Energy>().get_mut(entity),
+        ) {
+            energy.change_by(amount - energy.current());
+        }
+    }
+    drop(healths);
+    if let Some(agent) = possessing.agent {
+        let _ = ecs.write_storage::<comp::Agent>().insert(entity, *agent);
+    }
+}
+
+/// Send the client new `SyncFrom::ClientEntity` components and tell it to
+/// delete these on the old entity.
+fn sync_client_entity_switch(ecs: &specs::World, from_uid: Uid, (to, to_uid): (EcsEntity, Uid)) {
+    use crate::sys::sentinel::TrackedStorages;
+    use specs::SystemData;
+
+    let clients = ecs.read_storage::<Client>();
+    let client = clients
+        .get(to)
+        .expect("We insert this component above and have exclusive access to the world.");
+    let tracked_storages = TrackedStorages::fetch(ecs);
+    let comp_sync_package =
+        tracked_storages.create_sync_from_client_entity_switch(from_uid, to_uid, to);
+    if !comp_sync_package.is_empty() {
+        client.send_fallible(ServerGeneral::CompSync(comp_sync_package, 0)); // TODO: Check if this should be zero
+    }
+}
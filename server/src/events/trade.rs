@@ -877,4 +877,54 @@ mod tests {
         let traderes = commit_trade(&mockworld, &trade);
         assert_eq!(traderes, TradeResult::Completed);
     }
+
+    #[test]
+    fn commit_trade_with_custom_item() {
+        use crate::custom_items::tests::{receive_test_relic, TEST_RELIC};
+        use common::{assets::AssetExt, comp::item::ItemDef};
+        use std::sync::Arc;
+
+        receive_test_relic();
+        let (mockworld, player, merchant) = create_mock_trading_world(20, 20);
+
+        let invmsg = "inventories.get_mut().is_none() should have returned already";
+        let capmsg = "There should be enough space here";
+        let mut inventories = mockworld.write_component::<Inventory>();
+
+        let relic = common::comp::Item::new_from_asset_expect(TEST_RELIC);
+        let merchantinv = inventories.get_mut(merchant).expect(invmsg);
+        merchantinv
+            .push(common::comp::Item::new_from_asset_expect(TEST_RELIC))
+            .expect(capmsg);
+        let relicinvid = merchantinv
+            .get_slot_of_item(&relic)
+            .expect("expected get_slot_of_item to return");
+
+        let playerid = mockworld
+            .uid_from_entity(player)
+            .expect("mockworld.uid_from_entity(player) should have returned");
+        let merchantid = mockworld
+            .uid_from_entity(merchant)
+            .expect("mockworld.uid_from_entity(player) should have returned");
+
+        let playeroffers: HashMap<InvSlotId, u32> = HashMap::new();
+        let mut merchantoffers: HashMap<InvSlotId, u32> = HashMap::new();
+        merchantoffers.insert(relicinvid, 1);
+        let trade = PendingTrade {
+            parties: [playerid, merchantid],
+            accept_flags: [true, true],
+            offers: [playeroffers, merchantoffers],
+            phase: common::trade::TradePhase::Review,
+        };
+
+        drop(inventories);
+
+        let traderes = commit_trade(&mockworld, &trade);
+        assert_eq!(traderes, TradeResult::Completed);
+
+        let inventories = mockworld.read_component::<Inventory>();
+        let playerinv = inventories.get(player).expect(invmsg);
+        let relic_def = Arc::<ItemDef>::load_expect_cloned(TEST_RELIC);
+        assert_eq!(playerinv.item_count(&relic_def), 1);
+    }
 }
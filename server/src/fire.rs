@@ -0,0 +1,303 @@
+use crate::settings::FireSettings;
+use common::{
+    comp::fluid_dynamics::LiquidKind,
+    region::REGION_SIZE,
+    resources::Time,
+    terrain::{Block, SpriteKind},
+};
+use hashbrown::HashMap;
+use rand::Rng;
+use vek::*;
+
+pub const NEIGHBOURS: [Vec3<i32>; 6] = [
+    Vec3::new(1, 0, 0),
+    Vec3::new(-1, 0, 0),
+    Vec3::new(0, 1, 0),
+    Vec3::new(0, -1, 0),
+    Vec3::new(0, 0, 1),
+    Vec3::new(0, 0, -1),
+];
+
+fn region_key(pos: Vec3<i32>) -> Vec2<i32> { pos.xy().map(|e| e.div_euclid(REGION_SIZE as i32)) }
+
+/// Something that happened to a fire, returned by [`Fires::tick`] so the
+/// terrain can be updated to match
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum FireChange {
+    Ignited(Vec3<i32>),
+    /// Put out by rain or water before the block burnt away
+    Extinguished(Vec3<i32>),
+    BurntOut(Vec3<i32>),
+}
+
+impl FireChange {
+    /// The block edits needed to show this change: burning blocks have embers
+    /// on top of them, and burnt blocks are removed if fire destroys blocks.
+    /// Only the removal of burnt blocks is persistent, so it is returned
+    /// separately.
+    pub fn block_edits(
+        self,
+        get_block: impl Fn(Vec3<i32>) -> Option<Block>,
+        destroy_blocks: bool,
+    ) -> (Vec<(Vec3<i32>, Block)>, Option<(Vec3<i32>, Block)>) {
+        let above = |pos: Vec3<i32>| get_block(pos + Vec3::unit_z());
+        match self {
+            FireChange::Ignited(pos) => match above(pos) {
+                Some(above) if above.get_sprite() == Some(SpriteKind::Empty) => (
+                    vec![(pos + Vec3::unit_z(), Block::air(SpriteKind::Ember))],
+                    None,
+                ),
+                _ => (Vec::new(), None),
+            },
+            FireChange::Extinguished(pos) | FireChange::BurntOut(pos) => {
+                let mut edits = Vec::new();
+                if above(pos).and_then(|above| above.get_sprite()) == Some(SpriteKind::Ember) {
+                    edits.push((pos + Vec3::unit_z(), Block::air(SpriteKind::Empty)));
+                }
+                let burnt = if destroy_blocks && matches!(self, FireChange::BurntOut(_)) {
+                    get_block(pos).map(|block| (pos, block.into_vacant()))
+                } else {
+                    None
+                };
+                (edits, burnt)
+            },
+        }
+    }
+}
+
+/// The blocks that are currently on fire, grouped by region so that each
+/// region can only have a limited number of them
+#[derive(Default)]
+pub struct Fires {
+    regions: HashMap<Vec2<i32>, HashMap<Vec3<i32>, Time>>,
+    /// Ignitions since the last tick
+    pending: Vec<FireChange>,
+}
+
+impl Fires {
+    pub fn is_empty(&self) -> bool { self.regions.is_empty() }
+
+    pub fn len(&self) -> usize { self.regions.values().map(HashMap::len).sum() }
+
+    pub fn is_burning(&self, pos: Vec3<i32>) -> bool {
+        self.regions
+            .get(&region_key(pos))
+            .map_or(false, |region| region.contains_key(&pos))
+    }
+
+    /// The burning block closest to `pos`, if any is within `max_dist` of it
+    pub fn nearest(&self, pos: Vec3<f32>, max_dist: f32) -> Option<Vec3<i32>> {
+        let min = region_key((pos - max_dist).map(|e| e.floor() as i32));
+        let max = region_key((pos + max_dist).map(|e| e.floor() as i32));
+        (min.x..=max.x)
+            .flat_map(|x| (min.y..=max.y).map(move |y| Vec2::new(x, y)))
+            .filter_map(|key| self.regions.get(&key))
+            .flat_map(|region| region.keys().copied())
+            .map(|block| (block, (block.as_::<f32>() + 0.5).distance_squared(pos)))
+            .filter(|(_, dist_sqrd)| *dist_sqrd <= max_dist.powi(2))
+            .min_by_key(|(_, dist_sqrd)| (dist_sqrd * 100.0) as i32)
+            .map(|(block, _)| block)
+    }
+
+    /// Sets a block on fire, returns whether it caught fire. Blocks that aren't
+    /// flammable, are next to water or are in a region that already has
+    /// [`FireSettings::max_burning_per_region`] burning blocks don't.
+    pub fn ignite(
+        &mut self,
+        pos: Vec3<i32>,
+        get_block: impl Fn(Vec3<i32>) -> Option<Block>,
+        time: Time,
+        settings: &FireSettings,
+    ) -> bool {
+        if !get_block(pos).map_or(false, |block| block.is_flammable())
+            || next_to_water(pos, &get_block)
+        {
+            return false;
+        }
+        let region = self.regions.entry(region_key(pos)).or_default();
+        if region.len() >= settings.max_burning_per_region || region.contains_key(&pos) {
+            return false;
+        }
+        region.insert(pos, Time(time.0 + settings.burn_duration.as_secs_f64()));
+        self.pending.push(FireChange::Ignited(pos));
+        true
+    }
+
+    /// Spreads the fires to neighbouring flammable blocks, and ends the ones
+    /// that burnt out or are put out by rain or water. Returns everything that
+    /// changed since the last tick, including ignitions from other sources.
+    pub fn tick(
+        &mut self,
+        time: Time,
+        settings: &FireSettings,
+        rng: &mut impl Rng,
+        get_block: impl Fn(Vec3<i32>) -> Option<Block>,
+        is_raining: impl Fn(Vec3<i32>) -> bool,
+    ) -> Vec<FireChange> {
+        let mut changes = std::mem::take(&mut self.pending);
+        let burning = self
+            .regions
+            .values()
+            .flat_map(|region| region.iter().map(|(pos, until)| (*pos, *until)))
+            .collect::<Vec<_>>();
+        for (pos, until) in burning {
+            let end = if is_raining(pos) || next_to_water(pos, &get_block) {
+                Some(FireChange::Extinguished(pos))
+            } else if time.0 >= until.0 || !get_block(pos).map_or(false, |b| b.is_flammable()) {
+                Some(FireChange::BurntOut(pos))
+            } else {
+                None
+            };
+            if let Some(end) = end {
+                if let Some(region) = self.regions.get_mut(&region_key(pos)) {
+                    region.remove(&pos);
+                }
+                changes.push(end);
+                continue;
+            }
+            for offset in NEIGHBOURS {
+                if rng.gen::<f32>() < settings.spread_chance {
+                    self.ignite(pos + offset, &get_block, time, settings);
+                }
+            }
+        }
+        self.regions.retain(|_, region| !region.is_empty());
+        changes.append(&mut self.pending);
+        changes
+    }
+}
+
+fn next_to_water(pos: Vec3<i32>, get_block: impl Fn(Vec3<i32>) -> Option<Block>) -> bool {
+    NEIGHBOURS.iter().any(|offset| {
+        get_block(pos + offset).map_or(false, |block| {
+            block.liquid_kind() == Some(LiquidKind::Water)
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::terrain::BlockKind;
+    use rand::{rngs::StdRng, SeedableRng};
+    use std::time::Duration;
+
+    fn settings() -> FireSettings {
+        FireSettings {
+            destroy_blocks: true,
+            max_burning_per_region: 32,
+            burn_duration: Duration::from_secs(60),
+            spread_chance: 1.0,
+        }
+    }
+
+    /// A forest floor of wood with air above it
+    fn forest(pos: Vec3<i32>) -> Option<Block> {
+        Some(match pos.z {
+            0 => Block::new(BlockKind::Wood, Rgb::new(100, 60, 20)),
+            _ => Block::air(SpriteKind::Empty),
+        })
+    }
+
+    #[test]
+    fn cap_prevents_runaway_spread() {
+        let settings = settings();
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut fires = Fires::default();
+        assert!(fires.ignite(Vec3::new(100, 100, 0), forest, Time(0.0), &settings));
+
+        for step in 0..50 {
+            fires.tick(Time(step as f64), &settings, &mut rng, forest, |_| false);
+            assert!(fires.len() <= settings.max_burning_per_region);
+        }
+        assert_eq!(fires.len(), settings.max_burning_per_region);
+
+        // Regions are capped separately
+        assert!(fires.ignite(
+            Vec3::new(REGION_SIZE as i32 + 100, 100, 0),
+            forest,
+            Time(50.0),
+            &settings
+        ));
+        assert!(!fires.ignite(Vec3::new(0, 0, 0), forest, Time(50.0), &settings));
+    }
+
+    #[test]
+    fn nearest_fire_is_found_across_regions() {
+        let settings = settings();
+        let mut fires = Fires::default();
+        let edge = REGION_SIZE as i32;
+        fires.ignite(Vec3::new(edge + 2, 10, 0), forest, Time(0.0), &settings);
+        fires.ignite(Vec3::new(edge - 8, 10, 0), forest, Time(0.0), &settings);
+
+        let pos = Vec3::new(edge as f32, 10.5, 0.5);
+        assert_eq!(fires.nearest(pos, 20.0), Some(Vec3::new(edge + 2, 10, 0)));
+        assert_eq!(fires.nearest(pos, 1.0), None);
+    }
+
+    #[test]
+    fn rain_and_water_extinguish() {
+        let settings = settings();
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut fires = Fires::default();
+        let pos = Vec3::new(10, 10, 0);
+        fires.ignite(pos, forest, Time(0.0), &settings);
+        fires.tick(Time(1.0), &settings, &mut rng, forest, |_| false);
+        assert!(fires.len() > 1);
+
+        let changes = fires.tick(Time(2.0), &settings, &mut rng, forest, |_| true);
+        assert!(fires.is_empty());
+        assert!(changes.contains(&FireChange::Extinguished(pos)));
+        assert!(!changes
+            .iter()
+            .any(|change| matches!(change, FireChange::BurntOut(_))));
+
+        // Wood next to water doesn't catch fire
+        let lakeside = |pos: Vec3<i32>| {
+            if pos.x == 11 && pos.z == 0 {
+                Some(Block::new(BlockKind::Water, Rgb::zero()))
+            } else {
+                forest(pos)
+            }
+        };
+        assert!(!fires.ignite(pos, lakeside, Time(3.0), &settings));
+    }
+
+    #[test]
+    fn burnt_blocks_are_removed() {
+        let mut settings = settings();
+        settings.spread_chance = 0.0;
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut fires = Fires::default();
+        let pos = Vec3::new(10, 10, 0);
+        fires.ignite(pos, forest, Time(0.0), &settings);
+
+        let changes = fires.tick(Time(1.0), &settings, &mut rng, forest, |_| false);
+        assert_eq!(changes, vec![FireChange::Ignited(pos)]);
+        let (edits, burnt) = changes[0].block_edits(forest, true);
+        assert_eq!(edits, vec![(
+            pos + Vec3::unit_z(),
+            Block::air(SpriteKind::Ember)
+        )]);
+        assert_eq!(burnt, None);
+
+        let changes = fires.tick(Time(60.0), &settings, &mut rng, forest, |_| false);
+        assert_eq!(changes, vec![FireChange::BurntOut(pos)]);
+        let with_ember = |p: Vec3<i32>| {
+            if p == pos + Vec3::unit_z() {
+                Some(Block::air(SpriteKind::Ember))
+            } else {
+                forest(p)
+            }
+        };
+        let (edits, burnt) = changes[0].block_edits(with_ember, true);
+        assert_eq!(edits, vec![(
+            pos + Vec3::unit_z(),
+            Block::air(SpriteKind::Empty)
+        )]);
+        assert_eq!(burnt, Some((pos, Block::air(SpriteKind::Empty))));
+
+        // Cosmetic fire leaves the block as it was
+        assert_eq!(changes[0].block_edits(with_ember, false).1, None);
+    }
+}
@@ -20,9 +20,13 @@ mod chunk_serialize;
 pub mod client;
 pub mod cmd;
 pub mod connection_handler;
+mod custom_items;
+mod danger;
 mod data_dir;
+mod death_loot;
 pub mod error;
 pub mod events;
+pub mod fire;
 pub mod input;
 pub mod location;
 pub mod lod;
@@ -30,10 +34,13 @@ pub mod login_provider;
 pub mod metrics;
 pub mod persistence;
 mod pet;
+pub mod pregen;
 pub mod presence;
 pub mod rtsim;
 pub mod settings;
+mod sound_occlusion;
 pub mod state_ext;
+pub mod status_api;
 pub mod sys;
 #[cfg(feature = "persistent_world")]
 pub mod terrain_persistence;
@@ -67,6 +74,7 @@ use crate::{
     presence::{Presence, RegionSubscription, RepositionOnChunkLoad},
     rtsim::RtSim,
     state_ext::StateExt,
+    status_api::StatusApi,
     sys::sentinel::{DeletedEntities, TrackedStorages},
 };
 use censor::Censor;
@@ -79,11 +87,13 @@ use common::{
     cmd::ServerChatCommand,
     comp,
     event::{EventBus, ServerEvent},
+    interaction::InteractionRanges,
+    location::LocationTracker,
     recipe::{default_component_recipe_book, default_recipe_book},
     resources::{BattleMode, Time, TimeOfDay},
     rtsim::RtSimEntity,
     slowjob::SlowJobPool,
-    terrain::{TerrainChunk, TerrainChunkSize},
+    terrain::{TerrainChunk, TerrainChunkSize, TerrainGrid},
     vol::RectRasterableVol,
 };
 use common_ecs::run_now;
@@ -98,7 +108,7 @@ use common_systems::add_local_systems;
 use metrics::{EcsSystemMetrics, PhysicsMetrics, TickMetrics};
 use network::{ListenAddr, Network, Pid};
 use persistence::{
-    character_loader::{CharacterLoader, CharacterLoaderResponseKind},
+    character_loader::{CharacterListCache, CharacterLoader, CharacterLoaderResponseKind},
     character_updater::CharacterUpdater,
 };
 use prometheus::Registry;
@@ -107,7 +117,10 @@ use specs::{join::Join, Builder, Entity as EcsEntity, Entity, SystemData, WorldE
 use std::{
     i32,
     ops::{Deref, DerefMut},
-    sync::Arc,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
     time::{Duration, Instant},
 };
 #[cfg(not(feature = "worldgen"))]
@@ -208,6 +221,7 @@ pub struct Server {
     runtime: Arc<Runtime>,
 
     metrics_shutdown: Arc<Notify>,
+    status_api_shutdown: Arc<Notify>,
     database_settings: Arc<RwLock<DatabaseSettings>>,
     disconnect_all_clients_requested: bool,
 }
@@ -225,10 +239,31 @@ impl Server {
         if settings.auth_server_address.is_none() {
             info!("Authentication is disabled");
         }
+        if settings.max_characters_per_player == 0 {
+            return Err(Error::Other(
+                "max_characters_per_player is 0, nobody would be able to create a character"
+                    .to_owned(),
+            ));
+        }
 
         // Run pending DB migrations (if any)
         debug!("Running DB migrations...");
-        persistence::run_migrations(&database_settings);
+        persistence::run_migrations(&database_settings)?;
+
+        // Remove characters that can no longer be restored
+        debug!("Purging deleted characters...");
+        persistence::purge_deleted_characters(
+            &database_settings,
+            settings.character_deletion_retention,
+        );
+
+        // Unlock characters whose transfer to another server expired
+        debug!("Rolling back expired character transfers...");
+        persistence::roll_back_expired_transfers(&database_settings);
+
+        // Characters can't be in the game before the server started
+        debug!("Recording missing logouts...");
+        persistence::clear_in_game_characters(&database_settings);
 
         // Vacuum database
         debug!("Vacuuming database...");
@@ -301,10 +336,12 @@ impl Server {
             let pool = state.ecs_mut().write_resource::<SlowJobPool>();
             pool.configure("CHUNK_GENERATOR", |n| n / 2 + n / 4);
             pool.configure("CHUNK_SERIALIZER", |n| n / 2);
+            pool.configure(pregen::PREGEN_JOB, |_| 1);
         }
-        state
-            .ecs_mut()
-            .insert(ChunkGenerator::new(chunk_gen_metrics));
+        state.ecs_mut().insert(ChunkGenerator::new(
+            chunk_gen_metrics,
+            pregen::ChunkCache::new(data_dir, settings.world_seed),
+        ));
         {
             let (sender, receiver) =
                 crossbeam_channel::bounded::<chunk_serialize::SerializedChunk>(10_000);
@@ -314,7 +351,17 @@ impl Server {
 
         state.ecs_mut().insert(CharacterUpdater::new(
             Arc::<RwLock<DatabaseSettings>>::clone(&database_settings),
+            settings.character_deletion_retention,
+            settings.max_characters_per_player,
+            settings.character_alias_uniqueness,
+            settings.persistence_batch_size,
         )?);
+        if settings.database_maintenance_on_startup {
+            state
+                .ecs()
+                .write_resource::<CharacterUpdater>()
+                .run_maintenance(None);
+        }
 
         let ability_map = comp::item::tool::AbilityMap::<comp::AbilityItem>::load_expect_cloned(
             "common.abilities.ability_set_manifest",
@@ -324,14 +371,30 @@ impl Server {
         let msm = comp::inventory::item::MaterialStatManifest::load().cloned();
         state.ecs_mut().insert(msm);
 
+        state.ecs_mut().insert(InteractionRanges::load_default());
+        state
+            .ecs_mut()
+            .insert(custom_items::load_custom_items(&settings.custom_item_dirs));
+
         state.ecs_mut().insert(CharacterLoader::new(
             Arc::<RwLock<DatabaseSettings>>::clone(&database_settings),
         )?);
+        state.ecs_mut().insert(CharacterListCache::default());
 
         // System schedulers to control execution of systems
         state
             .ecs_mut()
             .insert(sys::PersistenceScheduler::every(Duration::from_secs(10)));
+        state
+            .ecs_mut()
+            .insert(sys::LanternScheduler::every(Duration::from_secs(5)));
+        state
+            .ecs_mut()
+            .insert(sys::FireScheduler::every(Duration::from_secs(1)));
+        state
+            .ecs_mut()
+            .insert(sys::StatusSnapshotScheduler::every(Duration::from_secs(1)));
+        state.ecs_mut().insert(fire::Fires::default());
 
         // Server-only components
         state.ecs_mut().register::<RegionSubscription>();
@@ -341,6 +404,18 @@ impl Server {
         state.ecs_mut().register::<wiring::Circuit>();
         state.ecs_mut().register::<Anchor>();
         state.ecs_mut().register::<comp::Pet>();
+        state.ecs_mut().register::<comp::Trap>();
+        state.ecs_mut().register::<comp::TrainingDummy>();
+        state.ecs_mut().register::<comp::KnockedOut>();
+        state.ecs_mut().register::<comp::Possessing>();
+        state.ecs_mut().register::<comp::PossessedFrom>();
+        state.ecs_mut().register::<comp::Hardcore>();
+        state.ecs_mut().register::<comp::FallImmunity>();
+        state.ecs_mut().register::<comp::Sitting>();
+        state.ecs_mut().register::<comp::LanternFuel>();
+        state.ecs_mut().register::<comp::Hotbar>();
+        state.ecs_mut().register::<comp::CharacterSettings>();
+        state.ecs_mut().register::<LocationTracker>();
         state.ecs_mut().register::<login_provider::PendingLogin>();
         state.ecs_mut().register::<RepositionOnChunkLoad>();
 
@@ -423,6 +498,17 @@ impl Server {
         #[cfg(not(feature = "worldgen"))]
         let spawn_point = SpawnPoint::default();
 
+        if let Some(radius) = settings.background_pregen_radius {
+            info!(
+                "Pregenerating chunks within {} chunks of spawn in the background",
+                radius
+            );
+            state.ecs_mut().insert(pregen::BackgroundPregen::new(
+                TerrainGrid::chunk_key(spawn_point.0.xy().as_::<i32>()),
+                radius as i32,
+            ));
+        }
+
         // Set the spawn point we calculated above
         state.ecs_mut().insert(spawn_point);
 
@@ -549,14 +635,32 @@ impl Server {
 
         let connection_handler = ConnectionHandler::new(network, &runtime);
 
+        let status_api = StatusApi::new(settings.status_api.clone());
+        let status_api_shutdown = Arc::new(Notify::new());
+        if status_api.is_enabled() {
+            let status_api = status_api.clone();
+            let shutdown = Arc::clone(&status_api_shutdown);
+            let addr = settings.status_api.address;
+            runtime.spawn(async move {
+                if let Err(error) = status_api.serve(addr, shutdown.notified()).await {
+                    error!(?error, ?addr, "Failed to serve the status API");
+                }
+            });
+        }
+        state.ecs_mut().insert(status_api);
+
         // Initiate real-time world simulation
         #[cfg(feature = "worldgen")]
         {
             rtsim::init(&mut state, &world, index.as_index_ref(), spawn_point);
             weather::init(&mut state, &world);
+            danger::init(&mut state, &world, index.as_index_ref());
         }
         #[cfg(not(feature = "worldgen"))]
-        rtsim::init(&mut state);
+        {
+            rtsim::init(&mut state);
+            danger::init(&mut state);
+        }
 
         let this = Self {
             state,
@@ -568,6 +672,7 @@ impl Server {
             runtime,
 
             metrics_shutdown,
+            status_api_shutdown,
             database_settings,
             disconnect_all_clients_requested: false,
         };
@@ -630,6 +735,9 @@ impl Server {
     /// Get a reference to the server's world.
     pub fn world(&self) -> &World { &self.world }
 
+    /// Get a handle to the status API, to serve it from another thread.
+    pub fn status_api(&self) -> StatusApi { self.state.ecs().fetch::<StatusApi>().clone() }
+
     /// Get a reference to the server's world map.
     pub fn map(&self) -> &WorldMapMsg { &self.map }
 
@@ -839,106 +947,186 @@ impl Server {
 
         let character_updater = self.state.ecs().read_resource::<CharacterUpdater>();
 
+        let mut character_list_cache = self.state.ecs().write_resource::<CharacterListCache>();
+
+        let players = self.state.ecs().read_storage::<comp::Player>();
+
         // Get character-related database responses and notify the requesting client
         character_loader
             .messages()
             .chain(character_updater.messages())
-            .for_each(|query_result| match query_result.result {
-                CharacterLoaderResponseKind::CharacterList(result) => match result {
-                    Ok(character_list_data) => self.notify_client(
-                        query_result.entity,
-                        ServerGeneral::CharacterListUpdate(character_list_data),
-                    ),
-                    Err(error) => self.notify_client(
-                        query_result.entity,
-                        ServerGeneral::CharacterActionError(error.to_string()),
-                    ),
-                },
-                CharacterLoaderResponseKind::CharacterCreation(result) => match result {
-                    Ok((character_id, list)) => {
-                        self.notify_client(
-                            query_result.entity,
-                            ServerGeneral::CharacterListUpdate(list),
-                        );
-                        self.notify_client(
+            .for_each(|query_result| {
+                let player_uuid = players
+                    .get(query_result.entity)
+                    .map(|player| player.uuid().to_string());
+                // Never keep a list around that might not match the database
+                if query_result.is_err() {
+                    if let Some(player_uuid) = &player_uuid {
+                        character_list_cache.invalidate(player_uuid);
+                    }
+                }
+
+                match query_result.result {
+                    CharacterLoaderResponseKind::CharacterList(result) => match result {
+                        Ok(character_list_data) => {
+                            if let Some(player_uuid) = player_uuid {
+                                character_list_cache
+                                    .insert(player_uuid, character_list_data.clone());
+                            }
+                            self.notify_client(
+                                query_result.entity,
+                                ServerGeneral::CharacterListUpdate(character_list_data),
+                            )
+                        },
+                        Err(error) => self.notify_client(
                             query_result.entity,
-                            ServerGeneral::CharacterCreated(character_id),
-                        );
+                            ServerGeneral::CharacterActionError(error.to_string()),
+                        ),
                     },
-                    Err(error) => self.notify_client(
-                        query_result.entity,
-                        ServerGeneral::CharacterActionError(error.to_string()),
-                    ),
-                },
-                CharacterLoaderResponseKind::CharacterEdit(result) => match result {
-                    Ok((character_id, list)) => {
-                        self.notify_client(
+                    CharacterLoaderResponseKind::CharacterCreation(result) => match result {
+                        Ok((character_id, character)) => {
+                            if let Some(player_uuid) = &player_uuid {
+                                character_list_cache.add_character(player_uuid, character.clone());
+                            }
+                            self.notify_client(
+                                query_result.entity,
+                                ServerGeneral::CharacterCreated(character_id, Box::new(character)),
+                            )
+                        },
+                        Err(error) => self.notify_client(
                             query_result.entity,
-                            ServerGeneral::CharacterListUpdate(list),
-                        );
-                        self.notify_client(
+                            ServerGeneral::CharacterActionError(error.to_string()),
+                        ),
+                    },
+                    CharacterLoaderResponseKind::CharacterDeletion(result) => match result {
+                        Ok(character_id) => match player_uuid.as_deref().and_then(|player_uuid| {
+                            character_list_cache.remove_character(player_uuid, character_id)
+                        }) {
+                            Some(list) => self.notify_client(
+                                query_result.entity,
+                                ServerGeneral::CharacterListUpdate(list.clone()),
+                            ),
+                            None => {
+                                if let Some(player_uuid) = player_uuid {
+                                    character_loader
+                                        .load_character_list(query_result.entity, player_uuid);
+                                }
+                            },
+                        },
+                        Err(error) => self.notify_client(
                             query_result.entity,
-                            ServerGeneral::CharacterEdited(character_id),
-                        );
+                            ServerGeneral::CharacterActionError(error.to_string()),
+                        ),
                     },
-                    Err(error) => self.notify_client(
-                        query_result.entity,
-                        ServerGeneral::CharacterActionError(error.to_string()),
-                    ),
-                },
-                CharacterLoaderResponseKind::CharacterData(result) => {
-                    let message = match *result {
-                        Ok(character_data) => {
-                            let PersistedComponents {
-                                body,
-                                stats,
-                                skill_set,
-                                inventory,
-                                waypoint,
-                                pets,
-                                active_abilities,
-                                map_marker,
-                            } = character_data;
-                            let character_data = (
-                                body,
-                                stats,
-                                skill_set,
-                                inventory,
-                                waypoint,
-                                pets,
-                                active_abilities,
-                                map_marker,
-                            );
-                            ServerEvent::UpdateCharacterData {
-                                entity: query_result.entity,
-                                components: character_data,
+                    CharacterLoaderResponseKind::CharacterEdit(result) => match result {
+                        Ok((character_id, list)) => {
+                            if let Some(player_uuid) = player_uuid {
+                                character_list_cache.insert(player_uuid, list.clone());
                             }
-                        },
-                        Err(error) => {
-                            // We failed to load data for the character from the DB. Notify the
-                            // client to push the state back to character selection, with the error
-                            // to display
                             self.notify_client(
                                 query_result.entity,
-                                ServerGeneral::CharacterDataLoadError(error.to_string()),
+                                ServerGeneral::CharacterListUpdate(list),
+                            );
+                            self.notify_client(
+                                query_result.entity,
+                                ServerGeneral::CharacterEdited(character_id),
                             );
-
-                            // Clean up the entity data on the server
-                            ServerEvent::ExitIngame {
-                                entity: query_result.entity,
-                            }
                         },
-                    };
+                        Err(error) => self.notify_client(
+                            query_result.entity,
+                            ServerGeneral::CharacterActionError(error.to_string()),
+                        ),
+                    },
+                    CharacterLoaderResponseKind::CharacterData(result) => {
+                        let message = match *result {
+                            Ok(character_data) => {
+                                let PersistedComponents {
+                                    body,
+                                    stats,
+                                    skill_set,
+                                    inventory,
+                                    waypoint,
+                                    position,
+                                    pets,
+                                    active_abilities,
+                                    map_marker,
+                                    hotbar,
+                                    settings,
+                                    vitals,
+                                    buffs,
+                                    lantern_fuel,
+                                    hardcore,
+                                } = character_data;
+                                let character_data = (
+                                    body,
+                                    stats,
+                                    skill_set,
+                                    inventory,
+                                    waypoint,
+                                    position,
+                                    pets,
+                                    active_abilities,
+                                    map_marker,
+                                    hotbar,
+                                    settings,
+                                    vitals,
+                                    buffs,
+                                    lantern_fuel,
+                                    hardcore,
+                                );
+                                ServerEvent::UpdateCharacterData {
+                                    entity: query_result.entity,
+                                    components: character_data,
+                                }
+                            },
+                            Err(error) => {
+                                // We failed to load data for the character from the DB. Notify the
+                                // client to push the state back to character selection, with the
+                                // error to display
+                                self.notify_client(
+                                    query_result.entity,
+                                    ServerGeneral::CharacterDataLoadError(error.to_string()),
+                                );
 
-                    self.state
-                        .ecs()
-                        .read_resource::<EventBus<ServerEvent>>()
-                        .emit_now(message);
-                },
+                                // Clean up the entity data on the server
+                                ServerEvent::ExitIngame {
+                                    entity: query_result.entity,
+                                }
+                            },
+                        };
+
+                        self.state
+                            .ecs()
+                            .read_resource::<EventBus<ServerEvent>>()
+                            .emit_now(message);
+                    },
+                    CharacterLoaderResponseKind::CharacterSummaries(result) => {
+                        let msg = match result {
+                            Ok(page) => page.to_string(),
+                            Err(error) => format!("Failed to load characters: {}", error),
+                        };
+                        self.notify_client(
+                            query_result.entity,
+                            ServerGeneral::server_msg(comp::ChatType::CommandInfo, msg),
+                        );
+                    },
+                    CharacterLoaderResponseKind::Maintenance(result) => {
+                        let msg = match result {
+                            Ok(summary) => summary.to_string(),
+                            Err(error) => format!("Database maintenance failed: {}", error),
+                        };
+                        self.notify_client(
+                            query_result.entity,
+                            ServerGeneral::server_msg(comp::ChatType::CommandInfo, msg),
+                        );
+                    },
+                }
             });
 
         drop(character_loader);
         drop(character_updater);
+        drop(character_list_cache);
+        drop(players);
 
         {
             // Check for new chunks; cancel and regenerate all chunks if the asset has been
@@ -980,6 +1168,14 @@ impl Server {
             });
         }
 
+        // Pick up changes to the interaction ranges. Clients only receive them when
+        // they join, so those already connected keep the old ranges until they
+        // reconnect.
+        let interaction_ranges = InteractionRanges::load_expect("common.interaction_ranges");
+        if interaction_ranges.reloaded_global() {
+            self.state.ecs_mut().insert(interaction_ranges.cloned());
+        }
+
         let end_of_server_tick = Instant::now();
 
         // 8) Update Metrics
@@ -1074,6 +1270,7 @@ impl Server {
                     ),
                 time_of_day: *self.state.ecs().read_resource(),
                 max_group_size: self.settings().max_player_group_size,
+                max_characters_per_player: self.settings().max_characters_per_player,
                 client_timeout: self.settings().client_timeout,
                 world_map: self.map.clone(),
                 recipe_book: default_recipe_book().cloned(),
@@ -1088,6 +1285,8 @@ impl Server {
                     .ecs()
                     .read_resource::<comp::item::tool::AbilityMap>())
                     .clone(),
+                interaction_ranges: (&*self.state.ecs().read_resource::<InteractionRanges>())
+                    .clone(),
             })?;
         Ok(Some(entity))
     }
@@ -1301,6 +1500,30 @@ impl Server {
         self.state.ecs().read_storage::<Client>().join().count() as i64
     }
 
+    /// Pregenerates the chunks within `radius` chunks of the spawn point that
+    /// weren't pregenerated yet, on `workers` threads. This blocks until it is
+    /// done or `stop` is set.
+    pub fn pregenerate(&self, radius: u32, workers: usize, stop: &AtomicBool) {
+        let center = TerrainGrid::chunk_key(
+            self.state
+                .ecs()
+                .read_resource::<SpawnPoint>()
+                .0
+                .xy()
+                .as_::<i32>(),
+        );
+        let cache = Arc::clone(self.state.ecs().read_resource::<ChunkGenerator>().cache());
+        let world = &self.world;
+        let index = self.index.as_index_ref();
+        pregen::pregenerate(
+            &cache,
+            pregen::chunks_around(center, radius as i32),
+            workers,
+            |key| world.generate_base_chunk(index, key, || false, None).ok(),
+            || stop.load(Ordering::Relaxed),
+        );
+    }
+
     /// NOTE: Do *not* allow this to be called from any command that doesn't go
     /// through the CLI!
     pub fn add_admin(&mut self, username: &str, role: comp::AdminRole) {
@@ -1407,6 +1630,7 @@ impl Server {
 impl Drop for Server {
     fn drop(&mut self) {
         self.metrics_shutdown.notify_one();
+        self.status_api_shutdown.notify_one();
 
         self.state
             .notify_players(ServerGeneral::Disconnect(DisconnectReason::Shutdown));
@@ -1536,3 +1760,370 @@ pub fn remove_admin(
         },
     }
 }
+
+/// Exports a character of the player to a JSON file, returns whether it
+/// succeeded
+///
+/// NOTE: Do *not* allow this to be called from any command that doesn't go
+/// through the CLI!
+#[must_use]
+pub fn export_character(
+    username: &str,
+    character_id: CharacterId,
+    path: &std::path::Path,
+    login_provider: &LoginProvider,
+    database_settings: &persistence::DatabaseSettings,
+) -> bool {
+    let uuid = match login_provider.username_to_uuid(username) {
+        Ok(uuid) => uuid,
+        Err(err) => {
+            error!(
+                ?err,
+                "Could not find uuid for this name; either the user does not exist or there was \
+                 an error communicating with the auth server."
+            );
+            return false;
+        },
+    };
+    if let Err(err) = persistence::run_migrations(database_settings) {
+        error!("{}", err);
+        return false;
+    }
+
+    let result = persistence::export_character(database_settings, &uuid.to_string(), character_id)
+        .and_then(|json| {
+            let data = serde_json::to_vec_pretty(&json)?;
+            std::fs::write(path, data)
+                .map_err(|err| persistence::error::PersistenceError::OtherError(err.to_string()))
+        });
+    match result {
+        Ok(()) => {
+            info!(
+                "Exported character {} of {} ({}) to {}",
+                character_id,
+                username,
+                uuid,
+                path.display()
+            );
+            true
+        },
+        Err(err) => {
+            error!("Failed to export character: {}", err);
+            false
+        },
+    }
+}
+
+/// Creates a new character for the player from a JSON file written by
+/// [`export_character`], returns whether it succeeded
+///
+/// NOTE: Do *not* allow this to be called from any command that doesn't go
+/// through the CLI!
+#[must_use]
+pub fn import_character(
+    username: &str,
+    path: &std::path::Path,
+    login_provider: &LoginProvider,
+    settings: &Settings,
+    database_settings: &persistence::DatabaseSettings,
+    data_dir: &std::path::Path,
+) -> bool {
+    let uuid = match login_provider.username_to_uuid(username) {
+        Ok(uuid) => uuid,
+        Err(err) => {
+            error!(
+                ?err,
+                "Could not find uuid for this name; either the user does not exist or there was \
+                 an error communicating with the auth server."
+            );
+            return false;
+        },
+    };
+    if let Err(err) = persistence::run_migrations(database_settings) {
+        error!("{}", err);
+        return false;
+    }
+
+    let censor = Censor::Custom(
+        settings
+            .moderation
+            .load_banned_words(data_dir)
+            .into_iter()
+            .collect(),
+    );
+    let result = std::fs::read(path)
+        .map_err(|err| persistence::error::PersistenceError::OtherError(err.to_string()))
+        .and_then(|data| Ok(serde_json::from_slice(&data)?))
+        .and_then(|json| {
+            persistence::import_character(
+                database_settings,
+                &uuid.to_string(),
+                json,
+                &censor,
+                settings.max_characters_per_player,
+                settings.character_alias_uniqueness,
+            )
+        });
+    match result {
+        Ok(character_id) => {
+            info!(
+                "Imported {} as character {} of {} ({})",
+                path.display(),
+                character_id,
+                username,
+                uuid
+            );
+            true
+        },
+        Err(err) => {
+            error!("Failed to import character: {}", err);
+            false
+        },
+    }
+}
+
+fn read_transfer_file(
+    path: &std::path::Path,
+) -> Result<persistence::signed_transfer::SignedMessage, persistence::error::PersistenceError> {
+    let data = std::fs::read(path)
+        .map_err(|err| persistence::error::PersistenceError::OtherError(err.to_string()))?;
+    Ok(serde_json::from_slice(&data)?)
+}
+
+fn write_transfer_file(
+    path: &std::path::Path,
+    message: &persistence::signed_transfer::SignedMessage,
+) -> Result<(), persistence::error::PersistenceError> {
+    let data = serde_json::to_vec_pretty(message)?;
+    std::fs::write(path, data)
+        .map_err(|err| persistence::error::PersistenceError::OtherError(err.to_string()))
+}
+
+/// If successful returns the public key other servers need to trust this one
+/// in character transfers, generating it if there is none yet
+#[must_use]
+pub fn transfer_public_key(data_dir: &std::path::Path) -> Option<String> {
+    match persistence::signed_transfer::TransferKey::load_or_generate(data_dir) {
+        Ok(key) => Some(key.public_key()),
+        Err(err) => {
+            error!("Failed to load the transfer key: {}", err);
+            None
+        },
+    }
+}
+
+/// Exports a character of the player into a signed bundle for the
+/// `destination` server and locks it until the transfer is finalized, returns
+/// whether it succeeded
+///
+/// NOTE: Do *not* allow this to be called from any command that doesn't go
+/// through the CLI!
+#[must_use]
+#[allow(clippy::too_many_arguments)]
+pub fn transfer_character_out(
+    username: &str,
+    character_id: CharacterId,
+    destination: &str,
+    path: &std::path::Path,
+    login_provider: &LoginProvider,
+    settings: &Settings,
+    database_settings: &persistence::DatabaseSettings,
+    data_dir: &std::path::Path,
+) -> bool {
+    let uuid = match login_provider.username_to_uuid(username) {
+        Ok(uuid) => uuid,
+        Err(err) => {
+            error!(
+                ?err,
+                "Could not find uuid for this name; either the user does not exist or there was \
+                 an error communicating with the auth server."
+            );
+            return false;
+        },
+    };
+    if let Err(err) = persistence::run_migrations(database_settings) {
+        error!("{}", err);
+        return false;
+    }
+
+    let result = persistence::signed_transfer::TransferKey::load_or_generate(data_dir)
+        .and_then(|key| {
+            persistence::transfer_character_out(
+                database_settings,
+                &key,
+                &settings.transfer,
+                destination,
+                &uuid.to_string(),
+                character_id,
+            )
+        })
+        .and_then(|bundle| write_transfer_file(path, &bundle));
+    match result {
+        Ok(()) => {
+            info!(
+                "Locked character {} of {} ({}) for its transfer to {}, send {} to that server",
+                character_id,
+                username,
+                uuid,
+                destination,
+                path.display()
+            );
+            true
+        },
+        Err(err) => {
+            error!("Failed to transfer character: {}", err);
+            false
+        },
+    }
+}
+
+/// Imports a character from a bundle written by [`transfer_character_out`] on
+/// another server, and writes the receipt the source server needs to finalize
+/// the transfer. Returns whether it succeeded.
+///
+/// NOTE: Do *not* allow this to be called from any command that doesn't go
+/// through the CLI!
+#[must_use]
+pub fn accept_character_transfer(
+    bundle_path: &std::path::Path,
+    receipt_path: &std::path::Path,
+    settings: &Settings,
+    database_settings: &persistence::DatabaseSettings,
+    data_dir: &std::path::Path,
+) -> bool {
+    if let Err(err) = persistence::run_migrations(database_settings) {
+        error!("{}", err);
+        return false;
+    }
+
+    let censor = Censor::Custom(
+        settings
+            .moderation
+            .load_banned_words(data_dir)
+            .into_iter()
+            .collect(),
+    );
+    let result = persistence::signed_transfer::TransferKey::load_or_generate(data_dir)
+        .and_then(|key| {
+            persistence::accept_character_transfer(
+                database_settings,
+                &key,
+                &settings.transfer,
+                &censor,
+                settings.max_characters_per_player,
+                settings.character_alias_uniqueness,
+                &read_transfer_file(bundle_path)?,
+            )
+        })
+        .and_then(|receipt| write_transfer_file(receipt_path, &receipt));
+    match result {
+        Ok(()) => {
+            info!(
+                "Accepted the transfer in {}, send {} back to the source server",
+                bundle_path.display(),
+                receipt_path.display()
+            );
+            true
+        },
+        Err(err) => {
+            error!("Failed to accept character transfer: {}", err);
+            false
+        },
+    }
+}
+
+/// Purges a character transferred to another server with the receipt written
+/// by [`accept_character_transfer`] on that server, returns whether it
+/// succeeded
+///
+/// NOTE: Do *not* allow this to be called from any command that doesn't go
+/// through the CLI!
+#[must_use]
+pub fn finalize_character_transfer(
+    path: &std::path::Path,
+    settings: &Settings,
+    database_settings: &persistence::DatabaseSettings,
+) -> bool {
+    if let Err(err) = persistence::run_migrations(database_settings) {
+        error!("{}", err);
+        return false;
+    }
+
+    let result = read_transfer_file(path).and_then(|receipt| {
+        persistence::finalize_character_transfer(database_settings, &settings.transfer, &receipt)
+    });
+    match result {
+        Ok(character_id) => {
+            info!("Finalized the transfer of character {}", character_id);
+            true
+        },
+        Err(err) => {
+            error!("Failed to finalize character transfer: {}", err);
+            false
+        },
+    }
+}
+
+/// If successful returns the entries of the audit log, optionally only those
+/// of one player or within a time range. Times are either RFC 3339 timestamps
+/// or dates, which stand for midnight UTC.
+///
+/// NOTE: Do *not* allow this to be called from any command that doesn't go
+/// through the CLI!
+#[must_use]
+pub fn audit_log(
+    username: Option<&str>,
+    since: Option<&str>,
+    until: Option<&str>,
+    login_provider: &LoginProvider,
+    database_settings: &persistence::DatabaseSettings,
+) -> Option<Vec<persistence::audit_log::AuditLogEntry>> {
+    fn parse_time(time: &str) -> Result<i64, chrono::ParseError> {
+        chrono::DateTime::parse_from_rfc3339(time)
+            .map(|time| time.timestamp())
+            .or_else(|_| {
+                chrono::NaiveDate::parse_from_str(time, "%Y-%m-%d")
+                    .map(|date| date.and_hms(0, 0, 0).timestamp())
+            })
+    }
+
+    let player_uuid = match username.map(|username| login_provider.username_to_uuid(username)) {
+        Some(Ok(uuid)) => Some(uuid.to_string()),
+        Some(Err(err)) => {
+            error!(
+                ?err,
+                "Could not find uuid for this name; either the user does not exist or there was \
+                 an error communicating with the auth server."
+            );
+            return None;
+        },
+        None => None,
+    };
+    let (since, until) = match (
+        since.map(parse_time).transpose(),
+        until.map(parse_time).transpose(),
+    ) {
+        (Ok(since), Ok(until)) => (since, until),
+        (Err(err), _) | (_, Err(err)) => {
+            error!("Invalid time: {}", err);
+            return None;
+        },
+    };
+    if let Err(err) = persistence::run_migrations(database_settings) {
+        error!("{}", err);
+        return None;
+    }
+
+    let filter = persistence::audit_log::AuditLogFilter {
+        player_uuid,
+        since,
+        until,
+    };
+    match persistence::query_audit_log(database_settings, &filter) {
+        Ok(entries) => Some(entries),
+        Err(err) => {
+            error!("Failed to read the audit log: {}", err);
+            None
+        },
+    }
+}
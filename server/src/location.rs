@@ -1,6 +1,15 @@
+use crate::danger::DangerOverlay;
+#[cfg(not(feature = "worldgen"))]
+use crate::test_world::{IndexRef, World};
+use common::{
+    danger::WILDERNESS_DANGER,
+    location::{LocationInfo, LocationTracker},
+};
 use hashbrown::HashMap;
 use std::fmt;
 use vek::*;
+#[cfg(feature = "worldgen")]
+use world::{IndexRef, World};
 
 #[derive(Debug)]
 pub enum LocationError<'a> {
@@ -63,3 +72,36 @@ impl Locations {
             .ok_or(LocationError::DoesNotExist(name))
     }
 }
+
+/// The location of the cell containing `wpos` (see [`LocationTracker`]). This
+/// is what players are told about where they are, and is also what the server
+/// should use to decide what area something is in, so that both agree.
+pub fn location_at(
+    world: &World,
+    index: IndexRef,
+    danger: &DangerOverlay,
+    wpos: Vec2<f32>,
+) -> LocationInfo {
+    let center = LocationTracker::cell_center(LocationTracker::cell_pos(wpos));
+    LocationInfo {
+        site_name: world.site_name_at(index, center.as_()),
+        biome: world.biome_at(center.as_()),
+        danger: danger.grid().get_at(center).unwrap_or(WILDERNESS_DANGER),
+    }
+}
+
+/// The location of `wpos` itself rather than of its cell, for decisions that
+/// shouldn't depend on where the cell boundaries fall, such as what a dying
+/// player drops
+pub fn exact_location_at(
+    world: &World,
+    index: IndexRef,
+    danger: &DangerOverlay,
+    wpos: Vec2<f32>,
+) -> LocationInfo {
+    LocationInfo {
+        site_name: world.site_name_at(index, wpos.as_()),
+        biome: world.biome_at(wpos.as_()),
+        danger: danger.danger_at(wpos),
+    }
+}
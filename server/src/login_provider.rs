@@ -2,7 +2,7 @@ use crate::settings::{AdminRecord, BanEntry, WhitelistRecord};
 use authc::{AuthClient, AuthClientError, AuthToken, Uuid};
 use chrono::Utc;
 use common::comp::AdminRole;
-use common_net::msg::RegisterError;
+use common_net::msg::{BanInfo, RegisterError};
 use hashbrown::HashMap;
 use specs::Component;
 use std::{str::FromStr, sync::Arc};
@@ -126,8 +126,11 @@ impl LoginProvider {
                             >= Into::<AdminRole>::into(ban.performed_by_role())
                     };
                     if !ban.is_expired(now) && !admin.map_or(false, exceeds_ban_role) {
-                        // Pull reason string out of ban record and send a copy of it
-                        return Some(Err(RegisterError::Banned(ban.reason.clone())));
+                        // Pull reason string and end date out of ban record and send a copy
+                        return Some(Err(RegisterError::Banned(BanInfo {
+                            reason: ban.reason.clone(),
+                            until: ban.end_date.map(|end_date| end_date.timestamp()),
+                        })));
                     }
                 }
 
@@ -0,0 +1,3 @@
+-- Deleted characters are kept for a retention period so that they can be
+-- restored, the time of deletion is stored as seconds since the Unix epoch.
+ALTER TABLE character ADD COLUMN deleted_at INTEGER;
@@ -0,0 +1,4 @@
+-- Tracks when characters last entered the game (in seconds since the Unix epoch)
+-- and how long they have been played for.
+ALTER TABLE character ADD COLUMN last_login INTEGER;
+ALTER TABLE character ADD COLUMN total_playtime_seconds INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,2 @@
+-- Hotbar bindings validated by the server, stored as JSON.
+ALTER TABLE character ADD COLUMN hotbar TEXT;
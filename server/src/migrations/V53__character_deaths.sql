@@ -0,0 +1,4 @@
+-- Counts how often characters died. Hardcore characters are deleted when they
+-- die for the first time.
+ALTER TABLE character ADD COLUMN deaths INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE character ADD COLUMN hardcore INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,3 @@
+-- Stores the stance pets were given by their owner, which decides when they
+-- start fights on their own.
+ALTER TABLE pet ADD COLUMN stance TEXT NOT NULL DEFAULT 'Aggressive';
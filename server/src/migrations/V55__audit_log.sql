@@ -0,0 +1,14 @@
+-- Record of destructive operations on characters and players. Times are
+-- seconds since the Unix epoch. Entries reference characters by id only, so
+-- that they are kept after the character is purged.
+CREATE TABLE audit_log (
+    audit_log_id INTEGER PRIMARY KEY NOT NULL,
+    time INTEGER NOT NULL,
+    kind TEXT NOT NULL,
+    player_uuid TEXT NOT NULL,
+    character_id INTEGER,
+    detail TEXT NOT NULL
+);
+
+CREATE INDEX idx_audit_log_time ON audit_log(time);
+CREATE INDEX idx_audit_log_player ON audit_log(player_uuid, time);
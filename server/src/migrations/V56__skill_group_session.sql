@@ -0,0 +1,6 @@
+-- Tracks which session of a character wrote its skill groups last, so that a
+-- save of an older session that arrives late can't lower the exp a newer
+-- session already saved. Times are seconds since the Unix epoch.
+ALTER TABLE character ADD COLUMN session_id INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE skill_group ADD COLUMN session_id INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE skill_group ADD COLUMN updated_at INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,3 @@
+-- Lets the list of all characters be paged through by last login without
+-- sorting the whole character table.
+CREATE INDEX idx_character_last_login ON character(last_login);
@@ -0,0 +1,18 @@
+-- Signed transfers of characters between servers. On the source server
+-- (direction 'Out') a character can't be played while its transfer is
+-- 'Pending' and hasn't expired. On the destination (direction 'In') the
+-- receipt is kept, so that it can be sent again if it got lost. Times are
+-- seconds since the Unix epoch. Characters are referenced by id only, as they
+-- are purged once their transfer is finalized.
+CREATE TABLE character_transfer (
+    transfer_id TEXT NOT NULL,
+    direction TEXT NOT NULL,
+    peer TEXT NOT NULL,
+    character_id INTEGER NOT NULL,
+    state TEXT NOT NULL,
+    expires_at INTEGER NOT NULL,
+    receipt TEXT,
+    PRIMARY KEY(transfer_id, direction)
+);
+
+CREATE INDEX idx_character_transfer_character ON character_transfer(character_id, state);
@@ -0,0 +1,8 @@
+-- Small per-character settings stored as a JSON blob, see
+-- `persistence::character_settings`. Characters without a row use the default
+-- settings.
+CREATE TABLE character_settings (
+    character_id INTEGER PRIMARY KEY NOT NULL,
+    settings TEXT NOT NULL,
+    FOREIGN KEY(character_id) REFERENCES character(character_id)
+);
@@ -0,0 +1,3 @@
+-- When characters were created (in seconds since the Unix epoch), unknown for
+-- characters created before this migration.
+ALTER TABLE character ADD COLUMN created_at INTEGER;
@@ -0,0 +1,4 @@
+-- Health and energy of characters when they were last saved, unknown for
+-- characters that were never saved since this migration or that were dead.
+ALTER TABLE character ADD COLUMN health REAL;
+ALTER TABLE character ADD COLUMN energy REAL;
@@ -0,0 +1,17 @@
+-- Characters of a player that aren't deleted can't share an alias. Existing
+-- duplicates keep their alias on the oldest character, and get a space and
+-- the character id appended on the others. Their alias is shortened to keep
+-- it within the 20 characters aliases are limited to.
+UPDATE character
+SET alias = rtrim(substr(alias, 1, 20 - 1 - length(character_id))) || ' ' || character_id
+WHERE deleted_at IS NULL
+AND EXISTS (
+    SELECT 1
+    FROM character other
+    WHERE other.player_uuid = character.player_uuid
+    AND other.alias = character.alias
+    AND other.deleted_at IS NULL
+    AND other.character_id < character.character_id
+);
+
+CREATE UNIQUE INDEX idx_character_player_alias ON character(player_uuid, alias) WHERE deleted_at IS NULL;
@@ -0,0 +1,15 @@
+-- Buffs and debuffs characters had when they were last saved, see
+-- `persistence::buffs`. Times are in seconds, and `saved_at` is a unix
+-- timestamp so that the time spent logged out can be taken off on load.
+CREATE TABLE buffs (
+    character_id INTEGER NOT NULL,
+    kind TEXT NOT NULL,
+    strength REAL NOT NULL,
+    duration REAL,
+    remaining REAL,
+    categories TEXT NOT NULL,
+    saved_at INTEGER NOT NULL,
+    FOREIGN KEY(character_id) REFERENCES character(character_id)
+);
+
+CREATE INDEX idx_buffs_character_id ON buffs(character_id);
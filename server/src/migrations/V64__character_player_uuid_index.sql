@@ -0,0 +1,6 @@
+-- The index on player_uuid was lost when the character table was recreated in
+-- V22, so loading the character list of a player scanned the whole table. The
+-- character id is included to also cover the order of the list. Bodies are
+-- found by their primary key, but pets of a character weren't indexed either.
+CREATE INDEX idx_character_player_uuid ON character(player_uuid, character_id);
+CREATE INDEX idx_pet_character_id ON pet(character_id);
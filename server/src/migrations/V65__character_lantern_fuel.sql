@@ -0,0 +1,4 @@
+-- Fuel left in the lantern of characters when they were last saved, unknown
+-- for characters that were never saved since this migration, whose lantern
+-- starts out full.
+ALTER TABLE character ADD COLUMN lantern_fuel REAL;
@@ -0,0 +1,4 @@
+-- Whether characters are in the game, so that they can't be transferred to
+-- another server while they're played. Servers clear it for all characters
+-- when they start, in case they stopped without recording the logouts.
+ALTER TABLE character ADD COLUMN in_game INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,187 @@
+//! Record of destructive operations on characters and players, so that server
+//! operators can find out who deleted or renamed which character, or banned
+//! whom, and when.
+//!
+//! Writing to the audit log never fails the operation that is recorded, errors
+//! are only logged.
+
+use crate::persistence::error::PersistenceError;
+use chrono::Utc;
+use common::character::CharacterId;
+use rusqlite::{Connection, ToSql};
+use std::fmt;
+use tracing::warn;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AuditEventKind {
+    CharacterDeleted,
+    CharacterRenamed,
+    PlayerBanned,
+    PlayerUnbanned,
+    ItemsGranted,
+    CharacterTransferred,
+    /// Skill groups of a character were reset when it was loaded, because they
+    /// changed since it was last saved
+    SkillsReset,
+}
+
+impl AuditEventKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::CharacterDeleted => "CharacterDeleted",
+            Self::CharacterRenamed => "CharacterRenamed",
+            Self::PlayerBanned => "PlayerBanned",
+            Self::PlayerUnbanned => "PlayerUnbanned",
+            Self::ItemsGranted => "ItemsGranted",
+            Self::CharacterTransferred => "CharacterTransferred",
+            Self::SkillsReset => "SkillsReset",
+        }
+    }
+
+    fn from_str(kind: &str) -> Option<Self> {
+        Some(match kind {
+            "CharacterDeleted" => Self::CharacterDeleted,
+            "CharacterRenamed" => Self::CharacterRenamed,
+            "PlayerBanned" => Self::PlayerBanned,
+            "PlayerUnbanned" => Self::PlayerUnbanned,
+            "ItemsGranted" => Self::ItemsGranted,
+            "CharacterTransferred" => Self::CharacterTransferred,
+            "SkillsReset" => Self::SkillsReset,
+            _ => return None,
+        })
+    }
+}
+
+impl fmt::Display for AuditEventKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result { f.write_str(self.as_str()) }
+}
+
+/// An entry of the audit log, see [`log_event`]
+#[derive(Clone, Debug, PartialEq)]
+pub struct AuditLogEntry {
+    /// Seconds since the Unix epoch
+    pub time: i64,
+    pub kind: AuditEventKind,
+    /// The player who performed the operation
+    pub player_uuid: String,
+    /// The character that was affected, if any
+    pub character_id: Option<CharacterId>,
+    pub detail: String,
+}
+
+impl fmt::Display for AuditLogEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use chrono::TimeZone;
+
+        match Utc.timestamp_opt(self.time, 0).single() {
+            Some(time) => write!(f, "{}", time.to_rfc3339())?,
+            None => write!(f, "{}", self.time)?,
+        }
+        write!(f, " {} {} ", self.kind, self.player_uuid)?;
+        match self.character_id {
+            Some(character_id) => write!(f, "{}", character_id)?,
+            None => f.write_str("-")?,
+        }
+        write!(f, " {}", self.detail)
+    }
+}
+
+/// Filters for [`query_audit_log`], all of them are optional
+#[derive(Clone, Debug, Default)]
+pub struct AuditLogFilter {
+    pub player_uuid: Option<String>,
+    /// Only entries at or after this time, in seconds since the Unix epoch
+    pub since: Option<i64>,
+    /// Only entries before this time, in seconds since the Unix epoch
+    pub until: Option<i64>,
+}
+
+/// Records an operation performed by a player. Failures are logged as a
+/// warning, so that the operation itself still goes through.
+pub(super) fn log_event(
+    connection: &Connection,
+    kind: AuditEventKind,
+    player_uuid: &str,
+    character_id: Option<CharacterId>,
+    detail: &str,
+) {
+    let result = connection
+        .prepare_cached(
+            "
+            INSERT INTO audit_log (time, kind, player_uuid, character_id, detail)
+            VALUES (?1, ?2, ?3, ?4, ?5)",
+        )
+        .and_then(|mut stmt| {
+            stmt.execute(&[
+                &Utc::now().timestamp() as &dyn ToSql,
+                &kind.as_str(),
+                &player_uuid,
+                &character_id,
+                &detail,
+            ])
+        });
+
+    if let Err(e) = result {
+        warn!(
+            ?e,
+            ?kind,
+            ?player_uuid,
+            ?character_id,
+            ?detail,
+            "Failed to write to the audit log"
+        );
+    }
+}
+
+/// Loads the entries of the audit log matching the filter, oldest first
+pub(super) fn query_audit_log(
+    connection: &Connection,
+    filter: &AuditLogFilter,
+) -> Result<Vec<AuditLogEntry>, PersistenceError> {
+    let mut stmt = connection.prepare_cached(
+        "
+        SELECT  time,
+                kind,
+                player_uuid,
+                character_id,
+                detail
+        FROM    audit_log
+        WHERE   (?1 IS NULL OR player_uuid = ?1)
+        AND     (?2 IS NULL OR time >= ?2)
+        AND     (?3 IS NULL OR time < ?3)
+        ORDER BY time, audit_log_id",
+    )?;
+
+    let rows = stmt
+        .query_map(
+            &[
+                &filter.player_uuid as &dyn ToSql,
+                &filter.since,
+                &filter.until,
+            ],
+            |row| {
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, String>(2)?,
+                    row.get::<_, Option<CharacterId>>(3)?,
+                    row.get::<_, String>(4)?,
+                ))
+            },
+        )?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    rows.into_iter()
+        .map(|(time, kind, player_uuid, character_id, detail)| {
+            Ok(AuditLogEntry {
+                time,
+                kind: AuditEventKind::from_str(&kind).ok_or_else(|| {
+                    PersistenceError::ConversionError(format!("Unknown audit event kind {}", kind))
+                })?,
+                player_uuid,
+                character_id,
+                detail,
+            })
+        })
+        .collect()
+}
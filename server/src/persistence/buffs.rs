@@ -0,0 +1,286 @@
+//! Buffs and debuffs of characters, which keep running out while their
+//! character is logged out.
+
+use crate::persistence::error::PersistenceError;
+use chrono::Utc;
+use common::{
+    character::CharacterId,
+    comp::{self, Buff, BuffCategory, BuffData, BuffKind, BuffSource},
+};
+use core::time::Duration;
+use rusqlite::{Connection, ToSql, Transaction};
+use tracing::warn;
+
+/// The buffs of a character to persist. Buffs that already ran out and the
+/// ones given by auras, which the character only has while near their source,
+/// are left out.
+pub fn buffs_to_persist(buffs: Option<&comp::Buffs>) -> Vec<Buff> {
+    buffs
+        .into_iter()
+        .flat_map(|buffs| buffs.buffs.values())
+        .filter(|buff| !buff.time.map_or(false, |time| time.is_zero()))
+        .filter(|buff| {
+            !buff
+                .cat_ids
+                .iter()
+                .any(|cat| matches!(cat, BuffCategory::FromAura(_)))
+        })
+        .cloned()
+        .collect()
+}
+
+/// Loads the buffs of a character, with the time since they were saved taken
+/// off. Buffs that ran out in the meantime aren't loaded, and neither are the
+/// ones that can't be read (e.g. kinds that were removed since).
+pub fn load_buffs(
+    character_id: CharacterId,
+    connection: &Connection,
+) -> Result<Vec<Buff>, PersistenceError> {
+    load_buffs_at(character_id, Utc::now().timestamp(), connection)
+}
+
+fn load_buffs_at(
+    character_id: CharacterId,
+    now: i64,
+    connection: &Connection,
+) -> Result<Vec<Buff>, PersistenceError> {
+    let mut stmt = connection.prepare_cached(
+        "
+        SELECT  kind,
+                strength,
+                duration,
+                remaining,
+                categories,
+                saved_at
+        FROM    buffs
+        WHERE   character_id = ?1",
+    )?;
+
+    let rows = stmt
+        .query_map(&[&character_id], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, f64>(1)?,
+                row.get::<_, Option<f64>>(2)?,
+                row.get::<_, Option<f64>>(3)?,
+                row.get::<_, String>(4)?,
+                row.get::<_, i64>(5)?,
+            ))
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(rows
+        .into_iter()
+        .filter_map(
+            |(kind, strength, duration, remaining, categories, saved_at)| {
+                let offline = now.saturating_sub(saved_at).max(0) as f64;
+                let remaining = match remaining.map(|remaining| remaining - offline) {
+                    Some(remaining) if remaining <= 0.0 => return None,
+                    remaining => remaining.map(Duration::from_secs_f64),
+                };
+                let parsed =
+                    serde_json::from_value::<BuffKind>(kind.clone().into()).and_then(|kind| {
+                        serde_json::from_str::<Vec<BuffCategory>>(&categories)
+                            .map(|categories| (kind, categories))
+                    });
+                match parsed {
+                    Ok((kind, categories)) => {
+                        let mut buff = Buff::new(
+                            kind,
+                            BuffData::new(strength as f32, duration.map(Duration::from_secs_f64)),
+                            categories,
+                            BuffSource::Unknown,
+                        );
+                        buff.time = remaining;
+                        Some(buff)
+                    },
+                    Err(e) => {
+                        warn!(
+                            "Skipping buff '{}' of character ID {} that can't be read. Error: {}",
+                            kind, character_id, e
+                        );
+                        None
+                    },
+                }
+            },
+        )
+        .collect())
+}
+
+/// Replaces the saved buffs of a character
+pub fn save_buffs(
+    character_id: CharacterId,
+    buffs: &[Buff],
+    transaction: &Transaction,
+) -> Result<(), PersistenceError> {
+    delete_buffs(character_id, transaction)?;
+
+    let mut stmt = transaction.prepare_cached(
+        "
+        INSERT
+        INTO    buffs (character_id,
+                       kind,
+                       strength,
+                       duration,
+                       remaining,
+                       categories,
+                       saved_at)
+        VALUES  (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+    )?;
+
+    let saved_at = Utc::now().timestamp();
+    for buff in buffs {
+        if buff.time.map_or(false, |time| time.is_zero()) {
+            continue;
+        }
+        let kind = match serde_json::to_value(buff.kind)? {
+            serde_json::Value::String(kind) => kind,
+            kind => kind.to_string(),
+        };
+        stmt.execute(&[
+            &character_id as &dyn ToSql,
+            &kind,
+            &f64::from(buff.data.strength),
+            &buff.data.duration.map(|duration| duration.as_secs_f64()),
+            &buff.time.map(|time| time.as_secs_f64()),
+            &serde_json::to_string(&buff.cat_ids)?,
+            &saved_at,
+        ])?;
+    }
+
+    Ok(())
+}
+
+/// Removes the saved buffs of a character
+pub(super) fn delete_buffs(
+    character_id: CharacterId,
+    transaction: &Transaction,
+) -> Result<(), PersistenceError> {
+    let mut stmt = transaction.prepare_cached(
+        "
+        DELETE
+        FROM    buffs
+        WHERE   character_id = ?1",
+    )?;
+
+    stmt.execute(&[&character_id])?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::persistence::character::tests::{create_test_character, migrated_connection};
+
+    fn buff(kind: BuffKind, strength: f32, remaining: Option<f64>) -> Buff {
+        let duration = remaining.map(Duration::from_secs_f64);
+        Buff::new(
+            kind,
+            BuffData::new(strength, duration),
+            vec![BuffCategory::Natural],
+            BuffSource::Item,
+        )
+    }
+
+    fn kinds(buffs: &[Buff]) -> Vec<(BuffKind, f32)> {
+        let mut kinds = buffs
+            .iter()
+            .map(|buff| (buff.kind, buff.data.strength))
+            .collect::<Vec<_>>();
+        kinds.sort_by(|a, b| a.0.cmp(&b.0));
+        kinds
+    }
+
+    #[test]
+    fn buffs_keep_running_out_while_logged_out() {
+        let mut connection = migrated_connection();
+        let mut transaction = connection.transaction().unwrap();
+        let char_id = create_test_character(&mut transaction, comp::humanoid::Body::random());
+        assert!(load_buffs(char_id, &transaction).unwrap().is_empty());
+
+        let mut expired = buff(BuffKind::Burning, 5.0, Some(10.0));
+        expired.time = Some(Duration::ZERO);
+        let buffs = [
+            buff(BuffKind::Regeneration, 2.0, Some(30.0)),
+            buff(BuffKind::Bleeding, 1.0, Some(300.0)),
+            buff(BuffKind::PotionSickness, 3.0, Some(600.0)),
+            buff(BuffKind::IncreaseMaxHealth, 50.0, None),
+            expired,
+        ];
+        save_buffs(char_id, &buffs, &transaction).unwrap();
+        let all = vec![
+            (BuffKind::Regeneration, 2.0),
+            (BuffKind::IncreaseMaxHealth, 50.0),
+            (BuffKind::Bleeding, 1.0),
+            (BuffKind::PotionSickness, 3.0),
+        ];
+        assert_eq!(kinds(&load_buffs(char_id, &transaction).unwrap()), all);
+
+        // A minute later the regeneration ran out, and the rest has a minute less
+        let now = Utc::now().timestamp() + 60;
+        let loaded = load_buffs_at(char_id, now, &transaction).unwrap();
+        assert_eq!(kinds(&loaded), all[1..].to_vec());
+        let bleeding = loaded
+            .iter()
+            .find(|buff| buff.kind == BuffKind::Bleeding)
+            .unwrap();
+        assert!(bleeding.time.unwrap().as_secs_f64() <= 240.0);
+        assert_eq!(bleeding.data.duration, Some(Duration::from_secs(300)));
+
+        // Buffs that don't run out are kept however long the character was away
+        let loaded = load_buffs_at(char_id, now + 3600, &transaction).unwrap();
+        assert_eq!(kinds(&loaded), vec![(BuffKind::IncreaseMaxHealth, 50.0)]);
+
+        // Saving replaces the previous buffs
+        save_buffs(char_id, &[], &transaction).unwrap();
+        assert!(load_buffs(char_id, &transaction).unwrap().is_empty());
+    }
+
+    #[test]
+    fn unknown_buffs_are_skipped() {
+        let mut connection = migrated_connection();
+        let mut transaction = connection.transaction().unwrap();
+        let char_id = create_test_character(&mut transaction, comp::humanoid::Body::random());
+        save_buffs(
+            char_id,
+            &[buff(BuffKind::Hastened, 0.5, Some(60.0))],
+            &transaction,
+        )
+        .unwrap();
+        transaction
+            .execute(
+                "INSERT INTO buffs VALUES (?1, 'Petrified', 1.0, 60.0, 60.0, '[]', ?2)",
+                &[&char_id as &dyn ToSql, &Utc::now().timestamp()],
+            )
+            .unwrap();
+        transaction
+            .execute(
+                "INSERT INTO buffs VALUES (?1, 'Frozen', 1.0, 60.0, 60.0, '[\"Cold\"]', ?2)",
+                &[&char_id as &dyn ToSql, &Utc::now().timestamp()],
+            )
+            .unwrap();
+
+        assert_eq!(kinds(&load_buffs(char_id, &transaction).unwrap()), vec![(
+            BuffKind::Hastened,
+            0.5
+        )]);
+    }
+
+    #[test]
+    fn aura_buffs_are_not_persisted() {
+        let mut buffs = comp::Buffs::default();
+        buffs.insert(buff(BuffKind::Regeneration, 2.0, Some(30.0)));
+        buffs.insert(Buff::new(
+            BuffKind::CampfireHeal,
+            BuffData::new(0.01, Some(Duration::from_secs(1))),
+            vec![BuffCategory::FromAura(true)],
+            BuffSource::World,
+        ));
+        assert_eq!(kinds(&buffs_to_persist(Some(&buffs))), vec![(
+            BuffKind::Regeneration,
+            2.0
+        )]);
+        assert!(buffs_to_persist(None).is_empty());
+    }
+}
@@ -10,25 +10,33 @@ use super::{error::PersistenceError, models::*};
 use crate::{
     comp::{self, Inventory},
     persistence::{
+        audit_log::{self, AuditEventKind},
+        buffs,
         character::conversions::{
             convert_active_abilities_from_database, convert_active_abilities_to_database,
             convert_body_from_database, convert_body_to_database_json,
-            convert_character_from_database, convert_inventory_from_database_items,
+            convert_character_from_database, convert_hotbar_from_database_json,
+            convert_hotbar_to_database_json, convert_inventory_from_database_items,
             convert_items_to_database_items, convert_loadout_from_database_items,
-            convert_skill_groups_to_database, convert_skill_set_from_database,
-            convert_stats_from_database, convert_waypoint_from_database_json,
-            convert_waypoint_to_database_json,
+            convert_pet_stance_from_database, convert_skill_groups_to_database,
+            convert_skill_set_from_database, convert_stats_from_database,
+            convert_waypoint_from_database_json, convert_waypoint_to_database_json,
         },
-        character_loader::{CharacterCreationResult, CharacterDataResult, CharacterListResult},
-        character_updater::PetPersistenceData,
-        error::PersistenceError::DatabaseError,
-        EditableComponents, PersistedComponents,
+        character_loader::{
+            CharacterCreationResult, CharacterDataResult, CharacterEditResult, CharacterListResult,
+            CharacterSummary,
+        },
+        character_settings,
+        character_updater::{PetPersistenceData, SessionId, Vitals},
+        signed_transfer, EditableComponents, PersistedComponents,
     },
+    settings::AliasUniqueness,
 };
-use common::character::{CharacterId, CharacterItem, MAX_CHARACTERS_PER_PLAYER};
-use core::ops::Range;
-use rusqlite::{types::Value, Connection, ToSql, Transaction, NO_PARAMS};
-use std::{num::NonZeroU64, rc::Rc};
+use chrono::Utc;
+use common::character::{CharacterId, CharacterItem, MAX_NAME_LENGTH};
+use core::{ops::Range, time::Duration};
+use rusqlite::{types::Value, Connection, OptionalExtension, ToSql, Transaction, NO_PARAMS};
+use std::{collections::HashMap, num::NonZeroU64, rc::Rc};
 use tracing::{debug, error, trace, warn};
 
 /// Private module for very tightly coupled database conversion methods.  In
@@ -114,6 +122,11 @@ pub fn load_character_data(
     char_id: CharacterId,
     connection: &Connection,
 ) -> CharacterDataResult {
+    check_owner(&requesting_player_uuid, char_id, connection)?;
+    if signed_transfer::is_transfer_locked(char_id, Utc::now().timestamp(), connection)? {
+        return Err(PersistenceError::CharacterTransferLocked(char_id));
+    }
+
     let character_containers = get_pseudo_containers(connection, char_id)?;
     let inventory_items = load_items(connection, character_containers.inventory_container_id)?;
     let loadout_items = load_items(connection, character_containers.loadout_container_id)?;
@@ -124,14 +137,20 @@ pub fn load_character_data(
                 c.alias,
                 c.waypoint,
                 b.variant,
-                b.body_data
+                b.body_data,
+                c.hotbar,
+                c.hardcore,
+                c.health,
+                c.energy,
+                c.lantern_fuel
         FROM    character c
         JOIN    body b ON (c.character_id = b.body_id)
         WHERE   c.player_uuid = ?1
-        AND     c.character_id = ?2",
+        AND     c.character_id = ?2
+        AND     c.deleted_at IS NULL",
     )?;
 
-    let (body_data, character_data) = stmt.query_row(
+    let (body_data, character_data, hotbar, hardcore, vitals, lantern_fuel) = stmt.query_row(
         &[requesting_player_uuid.clone(), char_id.to_string()],
         |row| {
             let character_data = Character {
@@ -147,11 +166,20 @@ pub fn load_character_data(
                 body_data: row.get(4)?,
             };
 
-            Ok((body_data, character_data))
+            Ok((
+                body_data,
+                character_data,
+                row.get::<_, Option<String>>(5)?,
+                row.get::<_, bool>(6)?,
+                row.get::<_, Option<f64>>(7)?
+                    .zip(row.get::<_, Option<f64>>(8)?)
+                    .map(|(health, energy)| (health as f32, energy as f32)),
+                row.get::<_, Option<f64>>(9)?.map(|fuel| fuel as f32),
+            ))
         },
     )?;
 
-    let (char_waypoint, char_map_marker) = match character_data
+    let (char_waypoint, char_position, char_map_marker) = match character_data
         .waypoint
         .as_ref()
         .map(|x| convert_waypoint_from_database_json(x))
@@ -163,9 +191,21 @@ pub fn load_character_data(
     {}, error: {}",
                 char_id, e
             );
-            (None, None)
+            (None, None, None)
+        },
+        None => (None, None, None),
+    };
+
+    let hotbar = match hotbar.as_deref().map(convert_hotbar_from_database_json) {
+        Some(Ok(hotbar)) => hotbar,
+        Some(Err(e)) => {
+            warn!(
+                "Error reading hotbar from database for character ID {}, error: {}",
+                char_id, e
+            );
+            comp::Hotbar::default()
         },
-        None => (None, None),
+        None => comp::Hotbar::default(),
     };
 
     let mut stmt = connection.prepare_cached(
@@ -198,7 +238,8 @@ pub fn load_character_data(
         SELECT  p.pet_id,
                 p.name,
                 b.variant,
-                b.body_data
+                b.body_data,
+                p.stance
         FROM    pet p
         JOIN    body b ON (p.pet_id = b.body_id)
         WHERE   p.character_id = ?1",
@@ -211,6 +252,7 @@ pub fn load_character_data(
                 name: row.get(1)?,
                 body_variant: row.get(2)?,
                 body_data: row.get(3)?,
+                stance: row.get(4)?,
             })
         })?
         .filter_map(Result::ok)
@@ -227,6 +269,7 @@ pub fn load_character_data(
             {
                 let pet = comp::Pet::new_from_database(
                     NonZeroU64::new(db_pet.database_id as u64).unwrap(),
+                    convert_pet_stance_from_database(&db_pet.stance),
                 );
                 let pet_stats = comp::Stats::new(db_pet.name.to_owned());
                 Some((pet, pet_body, pet_stats))
@@ -265,9 +308,16 @@ pub fn load_character_data(
             &loadout_items,
         )?,
         waypoint: char_waypoint,
+        position: char_position,
         pets,
         active_abilities: convert_active_abilities_from_database(&ability_set_data),
         map_marker: char_map_marker,
+        hotbar,
+        settings: character_settings::load_settings(char_id, connection)?,
+        vitals,
+        buffs: buffs::load_buffs(char_id, connection)?,
+        lantern_fuel,
+        hardcore,
     })
 }
 
@@ -275,83 +325,381 @@ pub fn load_character_data(
 /// subset of the character's data, and is used to render the character and
 /// their level in the character list.
 ///
-/// In the event that a join fails, for a character (i.e. they lack an entry for
-/// stats, body, etc...) the character is skipped, and no entry will be
-/// returned.
+/// Characters whose body or loadout is missing or can't be read are skipped
+/// with a warning, rather than keeping the player from seeing the rest of
+/// their characters.
 pub fn load_character_list(player_uuid_: &str, connection: &Connection) -> CharacterListResult {
+    load_characters_for_players(&[player_uuid_], connection)
+        .map(|mut lists| lists.remove(player_uuid_).unwrap_or_default())
+}
+
+/// Loads the character lists of several players in one query (e.g. for admin
+/// tools), grouped by player uuid. Players without characters are left out.
+pub fn load_characters_for_players(
+    player_uuids: &[&str],
+    connection: &Connection,
+) -> Result<HashMap<String, Vec<CharacterItem>>, PersistenceError> {
     let mut stmt = connection.prepare_cached(
         "
             SELECT  character_id,
-                    alias
+                    alias,
+                    created_at,
+                    last_login,
+                    total_playtime_seconds,
+                    deaths,
+                    hardcore,
+                    player_uuid
+            FROM    character
+            WHERE   player_uuid IN rarray(?1)
+            AND     (deleted_at IS NULL OR (hardcore AND deaths > 0))
+            ORDER BY player_uuid, character_id",
+    )?;
+
+    let player_uuids = Rc::new(
+        player_uuids
+            .iter()
+            .map(|uuid| Value::from((*uuid).to_owned()))
+            .collect::<Vec<_>>(),
+    );
+    let rows = stmt
+        .query_map(&[player_uuids], |row| {
+            read_character_list_row(row, row.get(7)?)
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    drop(stmt);
+
+    let mut lists = HashMap::<_, Vec<_>>::new();
+    for row in &rows {
+        let list = lists.entry(row.0.player_uuid.clone()).or_default();
+        if let Some(item) = load_character_item(row, connection)? {
+            list.push(item);
+        }
+    }
+    lists.retain(|_, list| !list.is_empty());
+    Ok(lists)
+}
+
+/// Loads up to `limit` characters of [`load_character_list`], skipping the
+/// first `offset`. The total number of characters in the list is returned
+/// along with them, which includes the ones skipped because they can't be
+/// loaded.
+pub fn load_character_list_paged(
+    player_uuid_: &str,
+    offset: u32,
+    limit: u32,
+    connection: &Connection,
+) -> Result<(Vec<CharacterItem>, u64), PersistenceError> {
+    let mut stmt = connection.prepare_cached(
+        "
+            SELECT  COUNT(*)
+            FROM    character
+            WHERE   player_uuid = ?1
+            AND     (deleted_at IS NULL OR (hardcore AND deaths > 0))",
+    )?;
+    let total: i64 = stmt.query_row(&[player_uuid_], |row| row.get(0))?;
+    drop(stmt);
+
+    let mut stmt = connection.prepare_cached(
+        "
+            SELECT  character_id,
+                    alias,
+                    created_at,
+                    last_login,
+                    total_playtime_seconds,
+                    deaths,
+                    hardcore
             FROM    character
             WHERE   player_uuid = ?1
-            ORDER BY character_id",
+            AND     (deleted_at IS NULL OR (hardcore AND deaths > 0))
+            ORDER BY character_id
+            LIMIT   ?2
+            OFFSET  ?3",
     )?;
 
     let characters = stmt
-        .query_map(&[player_uuid_], |row| {
-            Ok(Character {
-                character_id: row.get(0)?,
-                alias: row.get(1)?,
-                player_uuid: player_uuid_.to_owned(),
-                waypoint: None, // Not used for character select
-            })
+        .query_map(&[&player_uuid_ as &dyn ToSql, &limit, &offset], |row| {
+            read_character_list_row(row, player_uuid_.to_owned())
         })?
-        .map(|x| x.unwrap())
-        .collect::<Vec<Character>>();
+        .collect::<Result<Vec<_>, _>>()?;
     drop(stmt);
 
     characters
         .iter()
-        .map(|character_data| {
-            let char = convert_character_from_database(character_data);
-
-            let mut stmt = connection.prepare_cached(
-                "
-                SELECT  body_id,
-                        variant,
-                        body_data
-                FROM    body
-                WHERE   body_id = ?1",
-            )?;
-            let db_body = stmt.query_row(&[char.id], |row| {
-                Ok(Body {
-                    body_id: row.get(0)?,
-                    variant: row.get(1)?,
-                    body_data: row.get(2)?,
-                })
-            })?;
-            drop(stmt);
+        .filter_map(|row| load_character_item(row, connection).transpose())
+        .collect::<Result<Vec<_>, PersistenceError>>()
+        .map(|characters| (characters, u64::try_from(total).unwrap_or(0)))
+}
 
-            let char_body = convert_body_from_database(&db_body.variant, &db_body.body_data)?;
+/// Punctuation allowed in aliases besides letters, digits and single spaces
+const ALIAS_PUNCTUATION: [char; 3] = ['-', '\'', '.'];
 
-            let loadout_container_id = get_pseudo_container_id(
-                connection,
-                character_data.character_id,
-                LOADOUT_PSEUDO_CONTAINER_POSITION,
-            )?;
+/// Checks that an alias can be used for a character. Banned words are checked
+/// separately, as they depend on the server settings.
+///
+/// Aliases are made of letters, digits and a little punctuation, with single
+/// spaces between words, so that names can't be padded or spaced out to pass
+/// for someone else's.
+pub fn check_alias(alias: &str) -> Result<(), PersistenceError> {
+    let length = alias.chars().count();
+    if length == 0 || length > MAX_NAME_LENGTH {
+        Err(PersistenceError::InvalidAlias(format!(
+            "Aliases must be between 1 and {} characters long",
+            MAX_NAME_LENGTH
+        )))
+    } else if alias.trim() != alias || alias.contains("  ") {
+        Err(PersistenceError::InvalidAlias(
+            "Aliases can't start or end with spaces or contain several spaces in a row".to_owned(),
+        ))
+    } else if let Some(c) = alias
+        .chars()
+        .find(|c| !(c.is_alphanumeric() || *c == ' ' || ALIAS_PUNCTUATION.contains(c)))
+    {
+        Err(PersistenceError::InvalidAlias(format!(
+            "Aliases can't contain '{}'",
+            c
+        )))
+    } else {
+        Ok(())
+    }
+}
 
-            let loadout_items = load_items(connection, loadout_container_id)?;
+/// A character of a character list, with its creation and last login times,
+/// playtime, deaths and whether it is hardcore
+type CharacterListRow = (Character, (Option<i64>, Option<i64>), i64, i64, bool);
+
+/// Reads the first 7 columns of a character list query
+fn read_character_list_row(
+    row: &rusqlite::Row,
+    player_uuid: String,
+) -> rusqlite::Result<CharacterListRow> {
+    Ok((
+        Character {
+            character_id: row.get(0)?,
+            alias: row.get(1)?,
+            player_uuid,
+            waypoint: None, // Not used for character select
+        },
+        (row.get::<_, Option<i64>>(2)?, row.get::<_, Option<i64>>(3)?),
+        row.get::<_, i64>(4)?,
+        row.get::<_, i64>(5)?,
+        row.get::<_, bool>(6)?,
+    ))
+}
 
-            let loadout =
-                convert_loadout_from_database_items(loadout_container_id, &loadout_items)?;
+/// Loads the body and loadout of a character of a character list. `None` if
+/// either of them is missing or can't be read, in which case the character
+/// is left out of the list.
+fn load_character_item(
+    (character_data, times, playtime, deaths, hardcore): &CharacterListRow,
+    connection: &Connection,
+) -> Result<Option<CharacterItem>, PersistenceError> {
+    let char = convert_character_from_database(character_data);
+    let (created_at, last_login) = *times;
 
-            Ok(CharacterItem {
-                character: char,
-                body: char_body,
-                inventory: Inventory::with_loadout_humanoid(loadout),
+    let mut stmt = connection.prepare_cached(
+        "
+        SELECT  body_id,
+                variant,
+                body_data
+        FROM    body
+        WHERE   body_id = ?1",
+    )?;
+    let db_body = stmt
+        .query_row(&[char.id], |row| {
+            Ok(Body {
+                body_id: row.get(0)?,
+                variant: row.get(1)?,
+                body_data: row.get(2)?,
             })
         })
-        .collect()
+        .optional()?;
+    drop(stmt);
+
+    let db_body = match db_body {
+        Some(db_body) => db_body,
+        None => {
+            warn!(
+                "Leaving character ID {} out of the character list, it has no body",
+                char.id
+            );
+            return Ok(None);
+        },
+    };
+    let char_body = match convert_body_from_database(&db_body.variant, &db_body.body_data) {
+        Ok(body) => body,
+        Err(e) => {
+            warn!(
+                "Leaving character ID {} out of the character list, its body can't be read. \
+                 Error: {}",
+                char.id, e
+            );
+            return Ok(None);
+        },
+    };
+
+    // Not `get_pseudo_container_id`, a missing loadout only affects this
+    // character rather than being an error
+    let mut stmt = connection.prepare_cached(
+        "
+        SELECT  item_id
+        FROM    item
+        WHERE   parent_container_item_id = ?1
+        AND     position = ?2",
+    )?;
+    let loadout_container_id = stmt
+        .query_row(
+            &[
+                &character_data.character_id as &dyn ToSql,
+                &LOADOUT_PSEUDO_CONTAINER_POSITION,
+            ],
+            |row| row.get::<_, EntityId>(0),
+        )
+        .optional()?;
+    drop(stmt);
+
+    let loadout_container_id = match loadout_container_id {
+        Some(id) => id,
+        None => {
+            warn!(
+                "Leaving character ID {} out of the character list, it has no loadout",
+                char.id
+            );
+            return Ok(None);
+        },
+    };
+    let loadout_items = load_items(connection, loadout_container_id)?;
+    let loadout = match convert_loadout_from_database_items(loadout_container_id, &loadout_items) {
+        Ok(loadout) => loadout,
+        Err(e) => {
+            warn!(
+                "Leaving character ID {} out of the character list, its loadout can't be read. \
+                 Error: {}",
+                char.id, e
+            );
+            return Ok(None);
+        },
+    };
+
+    Ok(Some(CharacterItem {
+        character: char,
+        body: char_body,
+        inventory: Inventory::with_loadout_humanoid(loadout),
+        created_at,
+        last_login,
+        total_playtime_seconds: u64::try_from(*playtime).unwrap_or(0),
+        deaths: u32::try_from(*deaths).unwrap_or(0),
+        hardcore: *hardcore,
+    }))
+}
+
+/// Loads the summaries of up to `limit` characters of all players, skipping the
+/// first `offset`, the most recently played first. Deleted characters are left
+/// out.
+pub fn list_all_characters(
+    offset: u32,
+    limit: u32,
+    connection: &Connection,
+) -> Result<Vec<CharacterSummary>, PersistenceError> {
+    // Subqueries instead of joins keep the paging on `idx_character_last_login`,
+    // each of them only reads the rows of one character or player
+    let mut stmt = connection.prepare_cached(
+        "
+        SELECT  character_id,
+                alias,
+                player_uuid,
+                (SELECT COUNT(*)
+                 FROM   character AS other
+                 WHERE  other.player_uuid = character.player_uuid
+                 AND    other.deleted_at IS NULL),
+                (SELECT COALESCE(SUM(earned_exp), 0)
+                 FROM   skill_group
+                 WHERE  entity_id = character_id),
+                last_login
+        FROM    character
+        WHERE   deleted_at IS NULL
+        ORDER BY last_login DESC, character_id DESC
+        LIMIT   ?1
+        OFFSET  ?2",
+    )?;
+
+    let summaries = stmt
+        .query_map(&[&limit as &dyn ToSql, &offset], |row| {
+            Ok(CharacterSummary {
+                character_id: row.get(0)?,
+                alias: row.get(1)?,
+                player_uuid: row.get(2)?,
+                player_characters: row.get(3)?,
+                total_exp: u64::try_from(row.get::<_, i64>(4)?).unwrap_or(0),
+                last_login: row.get(5)?,
+            })
+        })?
+        .collect::<Result<_, _>>()?;
+
+    Ok(summaries)
+}
+
+/// Counts the characters of all players that aren't deleted
+pub fn count_characters(connection: &Connection) -> Result<u64, PersistenceError> {
+    let mut stmt = connection.prepare_cached(
+        "
+        SELECT  COUNT(*)
+        FROM    character
+        WHERE   deleted_at IS NULL",
+    )?;
+
+    let count: i64 = stmt.query_row(NO_PARAMS, |row| row.get(0))?;
+
+    Ok(u64::try_from(count).unwrap_or(0))
+}
+
+/// Checks that every feature of a humanoid body exists for its species, so that
+/// characters don't end up with features that can't be rendered
+pub fn check_body(body: &comp::Body) -> Result<(), PersistenceError> {
+    match body {
+        comp::Body::Humanoid(body) => match body.invalid_feature() {
+            Some((feature, value)) => Err(PersistenceError::InvalidBody(format!(
+                "There is no {} {} for {:?} {:?} characters",
+                feature, value, body.body_type, body.species
+            ))),
+            None => Ok(()),
+        },
+        _ => Ok(()),
+    }
+}
+
+/// Checks that a character exists, hasn't been deleted and belongs to the
+/// requesting player
+fn check_owner(
+    requesting_player_uuid: &str,
+    char_id: CharacterId,
+    connection: &Connection,
+) -> Result<(), PersistenceError> {
+    let owner: Option<String> = connection
+        .query_row(
+            "SELECT player_uuid FROM character WHERE character_id = ?1 AND deleted_at IS NULL",
+            &[char_id],
+            |row| row.get(0),
+        )
+        .optional()?;
+    match owner {
+        Some(owner) if owner == requesting_player_uuid => Ok(()),
+        Some(_) => Err(PersistenceError::CharacterNotOwned(char_id)),
+        None => Err(PersistenceError::CharacterNotFound(char_id)),
+    }
 }
 
 pub fn create_character(
     uuid: &str,
     character_alias: &str,
     persisted_components: PersistedComponents,
+    max_characters: usize,
+    alias_uniqueness: AliasUniqueness,
     transaction: &mut Transaction,
 ) -> CharacterCreationResult {
-    check_character_limit(uuid, transaction)?;
+    check_alias(character_alias)?;
+    check_body(&persisted_components.body)?;
+    check_character_limit(uuid, max_characters, transaction)?;
+    check_alias_available(uuid, character_alias, None, alias_uniqueness, transaction)?;
 
     let PersistedComponents {
         body,
@@ -359,9 +707,16 @@ pub fn create_character(
         skill_set,
         inventory,
         waypoint,
+        position,
         pets: _,
         active_abilities,
         map_marker,
+        hotbar,
+        settings,
+        vitals: _,
+        buffs: _,
+        lantern_fuel: _,
+        hardcore,
     } = persisted_components;
 
     // Fetch new entity IDs for character, inventory and loadout
@@ -438,18 +793,27 @@ pub fn create_character(
         INSERT INTO character (character_id,
                                player_uuid,
                                alias,
-                               waypoint)
-        VALUES (?1, ?2, ?3, ?4)",
+                               waypoint,
+                               hotbar,
+                               hardcore,
+                               created_at)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
     )?;
 
+    let created_at = Utc::now().timestamp();
     stmt.execute(&[
         &character_id as &dyn ToSql,
         &uuid,
         &character_alias,
-        &convert_waypoint_to_database_json(waypoint, map_marker),
+        &convert_waypoint_to_database_json(waypoint, position, map_marker),
+        &convert_hotbar_to_database_json(&hotbar),
+        &hardcore,
+        &created_at,
     ])?;
     drop(stmt);
 
+    character_settings::save_settings(character_id, &settings, transaction)?;
+
     let db_skill_groups = convert_skill_groups_to_database(character_id, skill_set.skill_groups());
 
     let mut stmt = transaction.prepare_cached(
@@ -525,7 +889,19 @@ pub fn create_character(
     }
     drop(stmt);
 
-    load_character_list(uuid, transaction).map(|list| (character_id, list))
+    Ok((character_id, CharacterItem {
+        character: common::character::Character {
+            id: Some(character_id),
+            alias: character_alias.to_owned(),
+        },
+        body,
+        inventory,
+        created_at: Some(created_at),
+        last_login: None,
+        total_playtime_seconds: 0,
+        deaths: 0,
+        hardcore,
+    }))
 }
 
 pub fn edit_character(
@@ -534,76 +910,272 @@ pub fn edit_character(
     character_id: CharacterId,
     uuid: &str,
     character_alias: &str,
-) -> CharacterCreationResult {
+    alias_uniqueness: AliasUniqueness,
+) -> CharacterEditResult {
     let (body,) = editable_components;
-    let mut char_list = load_character_list(uuid, transaction);
-
-    if let Ok(char_list) = &mut char_list {
-        if let Some(char) = char_list
-            .iter_mut()
-            .find(|c| c.character.id == Some(character_id))
-        {
-            if let (comp::Body::Humanoid(new), comp::Body::Humanoid(old)) = (body, char.body) {
-                if new.species != old.species || new.body_type != old.body_type {
-                    warn!(
-                        "Character edit rejected due to failed validation - Character ID: {} \
-                         Alias: {}",
-                        character_id, character_alias
-                    );
-                    return Err(PersistenceError::CharacterDataError);
-                } else {
-                    char.body = body;
-                }
-            }
-        }
+    if let Err(e) = update_body(uuid, character_id, &body, transaction) {
+        warn!(
+            "Character edit rejected due to failed validation - Character ID: {} Alias: {}",
+            character_id, character_alias
+        );
+        return Err(e);
+    }
+
+    rename_character(
+        uuid,
+        character_id,
+        character_alias,
+        alias_uniqueness,
+        transaction,
+    )
+    .map(|list| (character_id, list))
+}
+
+/// Change the appearance of a humanoid character owned by the requesting
+/// player. Only the features copied by
+/// [`comp::humanoid::Body::with_appearance_of`] may differ from the current
+/// body of the character.
+pub fn update_body(
+    requesting_player_uuid: &str,
+    char_id: CharacterId,
+    body: &comp::Body,
+    transaction: &mut Transaction,
+) -> Result<(), PersistenceError> {
+    check_body(body)?;
+    check_owner(requesting_player_uuid, char_id, transaction)?;
+
+    let mut stmt = transaction.prepare_cached(
+        "
+        SELECT  b.variant,
+                b.body_data
+        FROM    body b
+        JOIN    character c ON (c.character_id = b.body_id)
+        WHERE   c.character_id = ?1
+        AND     c.player_uuid = ?2
+        AND     c.deleted_at IS NULL",
+    )?;
+
+    let (variant, body_data) = stmt
+        .query_row(&[&char_id as &dyn ToSql, &requesting_player_uuid], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+        })
+        .optional()?
+        .ok_or(PersistenceError::CharacterNotOwned(char_id))?;
+    drop(stmt);
+
+    match (convert_body_from_database(&variant, &body_data)?, body) {
+        (comp::Body::Humanoid(old), comp::Body::Humanoid(new))
+            if old.with_appearance_of(new) == *new => {},
+        _ => return Err(PersistenceError::CharacterDataError),
     }
 
     let mut stmt = transaction
         .prepare_cached("UPDATE body SET variant = ?1, body_data = ?2 WHERE body_id = ?3")?;
 
-    let (body_variant, body_data) = convert_body_to_database_json(&body)?;
+    let (body_variant, body_data) = convert_body_to_database_json(body)?;
     stmt.execute(&[
         &body_variant.to_string(),
         &body_data,
-        &character_id as &dyn ToSql,
+        &char_id as &dyn ToSql,
+    ])?;
+
+    Ok(())
+}
+
+/// Delete a character.
+///
+/// The character is only marked as deleted, so that it can be restored with
+/// [`restore_character`] until it is purged by [`purge_deleted_characters`].
+pub fn delete_character(
+    requesting_player_uuid: &str,
+    char_id: CharacterId,
+    transaction: &mut Transaction,
+) -> Result<(), PersistenceError> {
+    check_owner(requesting_player_uuid, char_id, transaction)?;
+
+    let mut stmt = transaction.prepare_cached(
+        "
+        UPDATE  character
+        SET     deleted_at = ?1
+        WHERE   character_id = ?2
+        AND     player_uuid = ?3
+        AND     deleted_at IS NULL",
+    )?;
+
+    let updated_count = stmt.execute(&[
+        &Utc::now().timestamp() as &dyn ToSql,
+        &char_id,
+        &requesting_player_uuid,
     ])?;
     drop(stmt);
 
-    let mut stmt =
-        transaction.prepare_cached("UPDATE character SET alias = ?1 WHERE character_id = ?2")?;
+    if updated_count != 1 {
+        return Err(PersistenceError::CharacterNotFound(char_id));
+    }
+
+    audit_log::log_event(
+        transaction,
+        AuditEventKind::CharacterDeleted,
+        requesting_player_uuid,
+        Some(char_id),
+        &audit_alias(char_id, transaction).unwrap_or_default(),
+    );
+
+    Ok(())
+}
 
-    stmt.execute(&[&character_alias, &character_id as &dyn ToSql])?;
+/// The alias of a character, only used for the audit log so errors are ignored
+fn audit_alias(char_id: CharacterId, connection: &Connection) -> Option<String> {
+    connection
+        .query_row(
+            "SELECT alias FROM character WHERE character_id = ?1",
+            &[char_id],
+            |row| row.get(0),
+        )
+        .ok()
+}
+
+/// Change the alias of a character owned by the requesting player. Returns
+/// the updated character list. This is also how [`edit_character`] changes
+/// the alias.
+pub fn rename_character(
+    requesting_player_uuid: &str,
+    char_id: CharacterId,
+    character_alias: &str,
+    alias_uniqueness: AliasUniqueness,
+    transaction: &mut Transaction,
+) -> CharacterListResult {
+    check_alias(character_alias)?;
+    check_owner(requesting_player_uuid, char_id, transaction)?;
+    check_alias_available(
+        requesting_player_uuid,
+        character_alias,
+        Some(char_id),
+        alias_uniqueness,
+        transaction,
+    )?;
+    let previous_alias = audit_alias(char_id, transaction).unwrap_or_default();
+
+    let mut stmt = transaction.prepare_cached(
+        "
+        UPDATE  character
+        SET     alias = ?1
+        WHERE   character_id = ?2
+        AND     player_uuid = ?3
+        AND     deleted_at IS NULL",
+    )?;
+
+    let updated_count = stmt.execute(&[
+        &character_alias as &dyn ToSql,
+        &char_id,
+        &requesting_player_uuid,
+    ])?;
     drop(stmt);
 
-    char_list.map(|list| (character_id, list))
+    if updated_count != 1 {
+        return Err(PersistenceError::CharacterNotOwned(char_id));
+    }
+
+    // Edits keep the alias more often than not
+    if previous_alias != character_alias {
+        audit_log::log_event(
+            transaction,
+            AuditEventKind::CharacterRenamed,
+            requesting_player_uuid,
+            Some(char_id),
+            &format!("{} -> {}", previous_alias, character_alias),
+        );
+    }
+
+    load_character_list(requesting_player_uuid, transaction)
 }
 
-/// Delete a character. Returns the updated character list.
-pub fn delete_character(
+/// Restore a character that was deleted less than `retention` ago. Returns
+/// the updated character list.
+pub fn restore_character(
     requesting_player_uuid: &str,
     char_id: CharacterId,
+    retention: Duration,
+    max_characters: usize,
+    alias_uniqueness: AliasUniqueness,
     transaction: &mut Transaction,
 ) -> CharacterListResult {
+    check_character_limit(requesting_player_uuid, max_characters, transaction)?;
+    // Another character may have been given the alias since this one was
+    // deleted
+    if let Some(alias) = audit_alias(char_id, transaction) {
+        check_alias_available(
+            requesting_player_uuid,
+            &alias,
+            Some(char_id),
+            alias_uniqueness,
+            transaction,
+        )?;
+    }
+
     let mut stmt = transaction.prepare_cached(
         "
-        SELECT  COUNT(1)
-        FROM    character
+        UPDATE  character
+        SET     deleted_at = NULL
         WHERE   character_id = ?1
-        AND     player_uuid = ?2",
+        AND     player_uuid = ?2
+        AND     deleted_at > ?3
+        AND     NOT (hardcore AND deaths > 0)",
     )?;
 
-    let result = stmt.query_row(&[&char_id as &dyn ToSql, &requesting_player_uuid], |row| {
-        let y: i64 = row.get(0)?;
-        Ok(y)
-    })?;
+    let restored_count = stmt.execute(&[
+        &char_id as &dyn ToSql,
+        &requesting_player_uuid,
+        &retention_cutoff(retention),
+    ])?;
     drop(stmt);
 
-    if result != 1 {
+    if restored_count != 1 {
         return Err(PersistenceError::OtherError(
-            "Requested character to delete does not belong to the requesting player".to_string(),
+            "Requested character to restore does not exist or can no longer be restored"
+                .to_string(),
         ));
     }
 
+    load_character_list(requesting_player_uuid, transaction)
+}
+
+/// Permanently delete all characters that were deleted more than `retention`
+/// ago. Returns the number of purged characters.
+pub fn purge_deleted_characters(
+    retention: Duration,
+    transaction: &mut Transaction,
+) -> Result<usize, PersistenceError> {
+    let mut stmt = transaction.prepare_cached(
+        "
+        SELECT  character_id
+        FROM    character
+        WHERE   deleted_at <= ?1",
+    )?;
+
+    let char_ids = stmt
+        .query_map(&[&retention_cutoff(retention)], |row| row.get(0))?
+        .collect::<Result<Vec<CharacterId>, _>>()?;
+    drop(stmt);
+
+    for char_id in char_ids.iter() {
+        purge_character(*char_id, transaction)?;
+    }
+
+    Ok(char_ids.len())
+}
+
+/// The deletion timestamp before which deleted characters can no longer be
+/// restored.
+fn retention_cutoff(retention: Duration) -> i64 {
+    Utc::now().timestamp() - i64::try_from(retention.as_secs()).unwrap_or(i64::MAX)
+}
+
+/// Remove a character and everything belonging to it from the database.
+pub fn purge_character(
+    char_id: CharacterId,
+    transaction: &mut Transaction,
+) -> Result<(), PersistenceError> {
     // Delete skill groups
     let mut stmt = transaction.prepare_cached(
         "
@@ -634,6 +1206,9 @@ pub fn delete_character(
     stmt.execute(&[&char_id])?;
     drop(stmt);
 
+    character_settings::delete_settings(char_id, transaction)?;
+    buffs::delete_buffs(char_id, transaction)?;
+
     // Delete character
     let mut stmt = transaction.prepare_cached(
         "
@@ -688,30 +1263,154 @@ pub fn delete_character(
         )));
     }
 
-    load_character_list(requesting_player_uuid, transaction)
+    Ok(())
+}
+
+/// Records that a character entered the game, making `session` the latest
+/// session of the character.
+pub fn record_login(
+    char_id: CharacterId,
+    session: SessionId,
+    transaction: &mut Transaction,
+) -> Result<(), PersistenceError> {
+    let mut stmt = transaction.prepare_cached(
+        "
+        UPDATE  character
+        SET     last_login = ?1,
+                session_id = ?2,
+                in_game = 1
+        WHERE   character_id = ?3",
+    )?;
+
+    stmt.execute(&[&Utc::now().timestamp() as &dyn ToSql, &session, &char_id])?;
+
+    Ok(())
+}
+
+/// Records that a character left the game, after its logout save was written.
+pub fn record_logout(
+    char_id: CharacterId,
+    transaction: &mut Transaction,
+) -> Result<(), PersistenceError> {
+    let mut stmt = transaction.prepare_cached(
+        "
+        UPDATE  character
+        SET     in_game = 0
+        WHERE   character_id = ?1",
+    )?;
+
+    stmt.execute(&[&char_id])?;
+
+    Ok(())
+}
+
+/// Records that no character is in the game, for servers that are starting.
+/// Returns the number of characters whose logout wasn't recorded.
+pub fn clear_in_game(transaction: &mut Transaction) -> Result<usize, PersistenceError> {
+    Ok(transaction.execute(
+        "UPDATE character SET in_game = 0 WHERE in_game = 1",
+        NO_PARAMS,
+    )?)
+}
+
+/// Whether a character entered the game and didn't leave it yet
+pub fn is_in_game(char_id: CharacterId, connection: &Connection) -> Result<bool, PersistenceError> {
+    let mut stmt = connection.prepare_cached(
+        "
+        SELECT  in_game
+        FROM    character
+        WHERE   character_id = ?1",
+    )?;
+
+    let in_game = stmt
+        .query_row(&[&char_id], |row| row.get::<_, bool>(0))
+        .optional()?;
+
+    Ok(in_game.unwrap_or(false))
+}
+
+/// Counts a death of the character. Hardcore characters are deleted by their
+/// first death, and can't be restored.
+pub fn record_death(
+    char_id: CharacterId,
+    transaction: &mut Transaction,
+) -> Result<(), PersistenceError> {
+    let mut stmt = transaction.prepare_cached(
+        "
+        UPDATE  character
+        SET     deaths = deaths + 1,
+                deleted_at = CASE WHEN hardcore THEN ?1 ELSE deleted_at END
+        WHERE   character_id = ?2",
+    )?;
+
+    stmt.execute(&[&Utc::now().timestamp() as &dyn ToSql, &char_id])?;
+
+    Ok(())
 }
 
 /// Before creating a character, we ensure that the limit on the number of
 /// characters has not been exceeded
 pub fn check_character_limit(
     uuid: &str,
+    max_characters: usize,
     transaction: &mut Transaction,
 ) -> Result<(), PersistenceError> {
     let mut stmt = transaction.prepare_cached(
         "
         SELECT  COUNT(1)
         FROM    character
-        WHERE   player_uuid = ?1",
+        WHERE   player_uuid = ?1
+        AND     deleted_at IS NULL",
     )?;
 
     #[allow(clippy::needless_question_mark)]
     let character_count: i64 = stmt.query_row(&[&uuid], |row| Ok(row.get(0)?))?;
     drop(stmt);
 
-    if character_count < MAX_CHARACTERS_PER_PLAYER as i64 {
+    if character_count < max_characters as i64 {
         Ok(())
     } else {
-        Err(PersistenceError::CharacterLimitReached)
+        Err(PersistenceError::CharacterLimitReached(max_characters))
+    }
+}
+
+/// Checks that no other character that isn't deleted has the alias, among the
+/// characters of the player or of the whole server. A character being renamed
+/// is left out, so that it can keep its own alias.
+///
+/// This is checked within the transaction that creates or renames the
+/// character, so that two characters created at the same time can't both pass
+/// the check.
+fn check_alias_available(
+    uuid: &str,
+    alias: &str,
+    character_id: Option<CharacterId>,
+    uniqueness: AliasUniqueness,
+    connection: &Connection,
+) -> Result<(), PersistenceError> {
+    let player_uuid = match uniqueness {
+        AliasUniqueness::PerPlayer => Some(uuid),
+        AliasUniqueness::Server => None,
+    };
+    let mut stmt = connection.prepare_cached(
+        "
+        SELECT  EXISTS (
+            SELECT  1
+            FROM    character
+            WHERE   alias = ?1
+            AND     deleted_at IS NULL
+            AND     (?2 IS NULL OR player_uuid = ?2)
+            AND     (?3 IS NULL OR character_id != ?3))",
+    )?;
+    let taken: bool = stmt.query_row(
+        &[&alias as &dyn ToSql, &player_uuid, &character_id],
+        |row| row.get(0),
+    )?;
+
+    if taken {
+        Err(PersistenceError::AliasTaken(alias.to_owned()))
+    } else {
+        Ok(())
     }
 }
 
@@ -813,14 +1512,13 @@ fn get_pseudo_container_id(
                 ?pseudo_container_position,
                 "Failed to retrieve pseudo container ID"
             );
-            Err(DatabaseError(e))
+            Err(e.into())
         },
     }
 }
 
-/// Stores new pets in the database, and removes pets from the database that the
-/// player no longer has. Currently there are no actual updates to pet data
-/// since we don't store any updatable data about pets in the database.
+/// Stores new pets in the database, updates the stance of existing pets, and
+/// removes pets from the database that the player no longer has.
 fn update_pets(
     char_id: CharacterId,
     pets: Vec<PetPersistenceData>,
@@ -849,6 +1547,23 @@ fn update_pets(
         }
     }
 
+    #[rustfmt::skip]
+    let mut stmt = transaction.prepare_cached("
+        UPDATE  pet
+        SET     stance = ?1
+        WHERE   pet_id = ?2",
+    )?;
+
+    for (pet, _, _) in pets.iter() {
+        if let Some(pet_id) = pet.get_database_id().load() {
+            stmt.execute(&[
+                &pet.stance.to_string() as &dyn ToSql,
+                &(pet_id.get() as i64),
+            ])?;
+        }
+    }
+    drop(stmt);
+
     for (pet, body, stats) in pets
         .iter()
         .filter(|(pet, _, _)| pet.get_database_id().load().is_none())
@@ -879,11 +1594,17 @@ fn update_pets(
             INTO    pet (
                     pet_id,
                     character_id,
-                    name)
-            VALUES  (?1, ?2, ?3)",
+                    name,
+                    stance)
+            VALUES  (?1, ?2, ?3, ?4)",
         )?;
 
-        stmt.execute(&[&pet_entity_id as &dyn ToSql, &char_id, &stats.name])?;
+        stmt.execute(&[
+            &pet_entity_id as &dyn ToSql,
+            &char_id,
+            &stats.name,
+            &pet.stance.to_string(),
+        ])?;
         drop(stmt);
 
         pet.get_database_id()
@@ -942,17 +1663,35 @@ fn delete_pets(
     Ok(())
 }
 
+/// Whether [`update`] wrote all skill groups of a character
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StatsWrite {
+    Applied,
+    /// The update came from an older session than the latest one of the
+    /// character, and would have lowered the exp of some skill groups. These
+    /// were kept as they are, everything else was still written.
+    Rejected,
+}
+
 #[allow(clippy::too_many_arguments)]
 pub fn update(
     char_id: CharacterId,
+    session: SessionId,
     char_skill_set: comp::SkillSet,
     inventory: Inventory,
     pets: Vec<PetPersistenceData>,
     char_waypoint: Option<comp::Waypoint>,
+    char_position: Option<comp::Pos>,
     active_abilities: comp::ability::ActiveAbilities,
     map_marker: Option<comp::MapMarker>,
+    hotbar: comp::Hotbar,
+    settings: comp::CharacterSettings,
+    vitals: Option<Vitals>,
+    buffs: Vec<comp::Buff>,
+    lantern_fuel: Option<f32>,
+    playtime: u64,
     transaction: &mut Transaction,
-) -> Result<(), PersistenceError> {
+) -> Result<StatsWrite, PersistenceError> {
     // Run pet persistence
     update_pets(char_id, pets, transaction)?;
 
@@ -1052,40 +1791,78 @@ pub fn update(
 
     let db_skill_groups = convert_skill_groups_to_database(char_id, char_skill_set.skill_groups());
 
+    // A save can arrive after one of a newer session of the character, e.g. the
+    // logout save of a player that logged back in quickly. Only the latest
+    // session may lower the exp of a skill group, so that a stale save can't
+    // undo progress.
     let mut stmt = transaction.prepare_cached(
         "
-        REPLACE
+        INSERT
         INTO    skill_group (entity_id,
                              skill_group_kind,
                              earned_exp,
                              spent_exp,
                              skills,
-                             hash_val)
-        VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                             hash_val,
+                             session_id,
+                             updated_at)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+        ON CONFLICT (entity_id, skill_group_kind) DO UPDATE
+        SET     earned_exp = excluded.earned_exp,
+                spent_exp = excluded.spent_exp,
+                skills = excluded.skills,
+                hash_val = excluded.hash_val,
+                session_id = excluded.session_id,
+                updated_at = excluded.updated_at
+        WHERE   excluded.earned_exp >= skill_group.earned_exp
+        OR      excluded.session_id = (SELECT   session_id
+                                       FROM     character
+                                       WHERE    character_id = excluded.entity_id)",
     )?;
 
+    let now = Utc::now().timestamp();
+    let mut stats_write = StatsWrite::Applied;
     for skill_group in db_skill_groups {
-        stmt.execute(&[
+        let written = stmt.execute(&[
             &skill_group.entity_id as &dyn ToSql,
             &skill_group.skill_group_kind,
             &skill_group.earned_exp,
             &skill_group.spent_exp,
             &skill_group.skills,
             &skill_group.hash_val,
+            &session,
+            &now,
         ])?;
+        if written == 0 {
+            stats_write = StatsWrite::Rejected;
+        }
     }
 
-    let db_waypoint = convert_waypoint_to_database_json(char_waypoint, map_marker);
+    let db_waypoint = convert_waypoint_to_database_json(char_waypoint, char_position, map_marker);
+    let db_hotbar = convert_hotbar_to_database_json(&hotbar);
 
     let mut stmt = transaction.prepare_cached(
         "
         UPDATE  character
-        SET     waypoint = ?1
-        WHERE   character_id = ?2
+        SET     waypoint = ?1,
+                hotbar = ?2,
+                total_playtime_seconds = total_playtime_seconds + ?3,
+                health = ?4,
+                energy = ?5,
+                lantern_fuel = ?6
+        WHERE   character_id = ?7
     ",
     )?;
 
-    let waypoint_count = stmt.execute(&[&db_waypoint as &dyn ToSql, &char_id])?;
+    let waypoint_count = stmt.execute(&[
+        &db_waypoint as &dyn ToSql,
+        &db_hotbar,
+        &(playtime as i64),
+        &vitals.map(|(health, _)| f64::from(health)),
+        &vitals.map(|(_, energy)| f64::from(energy)),
+        &lantern_fuel.map(f64::from),
+        &char_id,
+    ])?;
 
     if waypoint_count != 1 {
         return Err(PersistenceError::OtherError(format!(
@@ -1116,5 +1893,908 @@ pub fn update(
         )));
     }
 
-    Ok(())
+    // Settings that grew too large are left as they were last saved instead of
+    // failing the whole update
+    match character_settings::save_settings(char_id, &settings, transaction) {
+        Err(e @ PersistenceError::CharacterSettingsTooLarge(_)) => {
+            warn!("Not saving settings of character ID {}: {}", char_id, e)
+        },
+        result => result?,
+    }
+
+    buffs::save_buffs(char_id, &buffs, transaction)?;
+
+    Ok(stats_write)
+}
+
+#[cfg(test)]
+pub(super) mod tests {
+    use super::*;
+    use crate::persistence::embedded;
+
+    pub(in crate::persistence) fn migrated_connection() -> Connection {
+        let mut connection = Connection::open_in_memory().unwrap();
+        rusqlite::vtab::array::load_module(&connection).unwrap();
+        connection
+            .pragma_update(None, "foreign_keys", &"ON")
+            .unwrap();
+        embedded::migrations::runner().run(&mut connection).unwrap();
+        connection
+    }
+
+    fn count_rows(transaction: &Transaction, table: &str, column: &str, id: CharacterId) -> i64 {
+        transaction
+            .query_row(
+                &format!("SELECT COUNT(*) FROM {} WHERE {} = ?1", table, column),
+                &[&id],
+                |row| row.get(0),
+            )
+            .unwrap()
+    }
+
+    pub(in crate::persistence) fn create_test_character(
+        transaction: &mut Transaction,
+        body: comp::humanoid::Body,
+    ) -> CharacterId {
+        // Characters of the same player need different aliases
+        let created: i64 = transaction
+            .query_row(
+                "SELECT COUNT(*) FROM character WHERE player_uuid = 'player'",
+                NO_PARAMS,
+                |row| row.get(0),
+            )
+            .unwrap();
+        let alias = if created == 0 {
+            "Tester".to_owned()
+        } else {
+            format!("Tester{}", created + 1)
+        };
+        let (char_id, _) = create_character(
+            "player",
+            &alias,
+            PersistedComponents {
+                body: comp::Body::Humanoid(body),
+                stats: comp::Stats::new(alias.clone()),
+                skill_set: comp::SkillSet::default(),
+                inventory: Inventory::with_empty(),
+                waypoint: None,
+                position: None,
+                pets: Vec::new(),
+                active_abilities: Default::default(),
+                map_marker: None,
+                hotbar: Default::default(),
+                settings: Default::default(),
+                vitals: None,
+                buffs: Vec::new(),
+                lantern_fuel: None,
+                hardcore: false,
+            },
+            8,
+            AliasUniqueness::PerPlayer,
+            transaction,
+        )
+        .unwrap();
+        char_id
+    }
+
+    /// Saves a character with `exp` general exp, as `session` would
+    fn save_general_exp(
+        char_id: CharacterId,
+        session: SessionId,
+        exp: u32,
+        transaction: &mut Transaction,
+    ) -> StatsWrite {
+        let mut skill_set = comp::SkillSet::default();
+        skill_set.add_experience(comp::skillset::SkillGroupKind::General, exp);
+        save_skill_set(char_id, session, skill_set, transaction)
+    }
+
+    fn save_skill_set(
+        char_id: CharacterId,
+        session: SessionId,
+        skill_set: comp::SkillSet,
+        transaction: &mut Transaction,
+    ) -> StatsWrite {
+        update(
+            char_id,
+            session,
+            skill_set,
+            Inventory::with_empty(),
+            Vec::new(),
+            None,
+            None,
+            Default::default(),
+            None,
+            Default::default(),
+            Default::default(),
+            None,
+            Vec::new(),
+            None,
+            0,
+            transaction,
+        )
+        .unwrap()
+    }
+
+    fn stored_general_exp(transaction: &Transaction, char_id: CharacterId) -> i64 {
+        transaction
+            .query_row(
+                "SELECT earned_exp FROM skill_group WHERE entity_id = ?1 AND skill_group_kind = \
+                 'General'",
+                &[&char_id],
+                |row| row.get(0),
+            )
+            .unwrap()
+    }
+
+    #[test]
+    fn stale_session_cannot_lower_exp() {
+        let mut connection = migrated_connection();
+        let mut transaction = connection.transaction().unwrap();
+        let char_id = create_test_character(&mut transaction, comp::humanoid::Body::random());
+        let (old_session, new_session) = (1, 2);
+
+        record_login(char_id, old_session, &mut transaction).unwrap();
+        assert_eq!(
+            save_general_exp(char_id, old_session, 100, &mut transaction),
+            StatsWrite::Applied
+        );
+
+        // The player logs back in before the logout save of the old session
+        // arrives, and the new session saves first
+        record_login(char_id, new_session, &mut transaction).unwrap();
+        assert_eq!(
+            save_general_exp(char_id, new_session, 150, &mut transaction),
+            StatsWrite::Applied
+        );
+        assert_eq!(
+            save_general_exp(char_id, old_session, 120, &mut transaction),
+            StatsWrite::Rejected
+        );
+        assert_eq!(stored_general_exp(&transaction, char_id), 150);
+
+        // A stale save that doesn't lose exp is still written
+        assert_eq!(
+            save_general_exp(char_id, old_session, 180, &mut transaction),
+            StatsWrite::Applied
+        );
+        assert_eq!(stored_general_exp(&transaction, char_id), 180);
+
+        // Saves of the latest session are always written
+        assert_eq!(
+            save_general_exp(char_id, new_session, 160, &mut transaction),
+            StatsWrite::Applied
+        );
+        assert_eq!(stored_general_exp(&transaction, char_id), 160);
+    }
+
+    #[test]
+    fn changed_skill_groups_are_reset_once() {
+        use comp::skillset::{
+            skills::{GeneralSkill, Skill},
+            SkillGroupKind, SkillsPersistenceError,
+        };
+        let mut connection = migrated_connection();
+        let mut transaction = connection.transaction().unwrap();
+        let char_id = create_test_character(&mut transaction, comp::humanoid::Body::random());
+        let health = Skill::General(GeneralSkill::HealthIncrease);
+
+        let mut skill_set = comp::SkillSet::default();
+        skill_set.add_skill_points(SkillGroupKind::General, 2);
+        skill_set.unlock_skill(health).unwrap();
+        save_skill_set(char_id, 0, skill_set, &mut transaction);
+
+        // The general skill tree changes before the next session
+        transaction
+            .execute(
+                "UPDATE skill_group SET hash_val = x'00' WHERE entity_id = ?1 AND \
+                 skill_group_kind = 'General'",
+                &[&char_id],
+            )
+            .unwrap();
+        let load = |transaction: &Transaction| {
+            load_character_data("player".to_owned(), char_id, transaction)
+                .unwrap()
+                .skill_set
+        };
+        let skill_set = load(&transaction);
+        assert_eq!(
+            skill_set.persistence_load_error,
+            Some(SkillsPersistenceError::HashMismatch)
+        );
+        assert!(!skill_set.has_skill(health));
+        assert_eq!(skill_set.available_sp(SkillGroupKind::General), 2);
+
+        // The reset is saved along with the current tree, so it only happens once
+        save_skill_set(char_id, 1, skill_set, &mut transaction);
+        let skill_set = load(&transaction);
+        assert_eq!(skill_set.persistence_load_error, None);
+        assert_eq!(skill_set.available_sp(SkillGroupKind::General), 2);
+        assert_eq!(skill_set.earned_sp(SkillGroupKind::General), 2);
+    }
+
+    #[test]
+    fn vitals_are_restored() {
+        let mut connection = migrated_connection();
+        let mut transaction = connection.transaction().unwrap();
+        let char_id = create_test_character(&mut transaction, comp::humanoid::Body::random());
+        let load = |transaction: &Transaction| {
+            load_character_data("player".to_owned(), char_id, transaction)
+                .unwrap()
+                .vitals
+        };
+        assert_eq!(load(&transaction), None);
+
+        let save = |vitals, transaction: &mut Transaction| {
+            update(
+                char_id,
+                0,
+                comp::SkillSet::default(),
+                Inventory::with_empty(),
+                Vec::new(),
+                None,
+                None,
+                Default::default(),
+                None,
+                Default::default(),
+                Default::default(),
+                vitals,
+                Vec::new(),
+                None,
+                0,
+                transaction,
+            )
+            .unwrap();
+        };
+        save(Some((42.5, 17.0)), &mut transaction);
+        save(Some((40.0, 20.0)), &mut transaction);
+        assert_eq!(load(&transaction), Some((40.0, 20.0)));
+
+        // Dead characters are saved without them
+        save(None, &mut transaction);
+        assert_eq!(load(&transaction), None);
+    }
+
+    #[test]
+    fn lantern_fuel_is_restored() {
+        let mut connection = migrated_connection();
+        let mut transaction = connection.transaction().unwrap();
+        let char_id = create_test_character(&mut transaction, comp::humanoid::Body::random());
+        let load = |transaction: &Transaction| {
+            load_character_data("player".to_owned(), char_id, transaction)
+                .unwrap()
+                .lantern_fuel
+        };
+        assert_eq!(load(&transaction), None);
+
+        let save = |lantern_fuel, transaction: &mut Transaction| {
+            update(
+                char_id,
+                0,
+                comp::SkillSet::default(),
+                Inventory::with_empty(),
+                Vec::new(),
+                None,
+                None,
+                Default::default(),
+                None,
+                Default::default(),
+                Default::default(),
+                None,
+                Vec::new(),
+                lantern_fuel,
+                0,
+                transaction,
+            )
+            .unwrap();
+        };
+        save(Some(1200.0), &mut transaction);
+        save(Some(0.0), &mut transaction);
+        // An empty lantern stays empty after relogging
+        assert_eq!(load(&transaction), Some(0.0));
+    }
+
+    #[test]
+    fn all_characters_are_paged_by_last_login() {
+        let mut connection = migrated_connection();
+        let mut transaction = connection.transaction().unwrap();
+        let ids = (0..4)
+            .map(|_| create_test_character(&mut transaction, comp::humanoid::Body::random()))
+            .collect::<Vec<_>>();
+        for (id, last_login) in ids.iter().zip([Some(30), Some(10), None, Some(20)]) {
+            transaction
+                .execute(
+                    "UPDATE character SET last_login = ?1 WHERE character_id = ?2",
+                    &[&last_login as &dyn ToSql, id],
+                )
+                .unwrap();
+        }
+        save_general_exp(ids[0], 0, 500, &mut transaction);
+        delete_character("player", ids[1], &mut transaction).unwrap();
+
+        assert_eq!(count_characters(&transaction).unwrap(), 3);
+        let page = |offset, limit| {
+            list_all_characters(offset, limit, &transaction)
+                .unwrap()
+                .into_iter()
+                .map(|summary| summary.character_id)
+                .collect::<Vec<_>>()
+        };
+        // Never played characters come last
+        assert_eq!(page(0, 2), vec![ids[0], ids[3]]);
+        assert_eq!(page(2, 2), vec![ids[2]]);
+        assert!(page(3, 2).is_empty());
+
+        let first = &list_all_characters(0, 1, &transaction).unwrap()[0];
+        assert_eq!(first.player_uuid, "player");
+        assert_eq!(first.player_characters, 3);
+        assert_eq!(first.total_exp, 500);
+        assert_eq!(first.last_login, Some(30));
+    }
+
+    #[test]
+    fn character_list_is_paged() {
+        let mut connection = migrated_connection();
+        let mut transaction = connection.transaction().unwrap();
+        let ids = (0..5)
+            .map(|_| create_test_character(&mut transaction, comp::humanoid::Body::random()))
+            .collect::<Vec<_>>();
+        delete_character("player", ids[2], &mut transaction).unwrap();
+
+        let page = |offset, limit| {
+            let (characters, total) =
+                load_character_list_paged("player", offset, limit, &transaction).unwrap();
+            let ids = characters
+                .into_iter()
+                .map(|item| item.character.id.unwrap())
+                .collect::<Vec<_>>();
+            (ids, total)
+        };
+        assert_eq!(page(0, 2), (vec![ids[0], ids[1]], 4));
+        assert_eq!(page(2, 2), (vec![ids[3], ids[4]], 4));
+        assert_eq!(page(4, 2), (vec![], 4));
+        assert_eq!(
+            load_character_list("player", &transaction).unwrap().len(),
+            4
+        );
+        let (others, total) =
+            load_character_list_paged("someone else", 0, 2, &transaction).unwrap();
+        assert!(others.is_empty());
+        assert_eq!(total, 0);
+    }
+
+    #[test]
+    fn character_lists_of_several_players_are_loaded_at_once() {
+        let mut connection = migrated_connection();
+        let mut transaction = connection.transaction().unwrap();
+        let ids = (0..3)
+            .map(|_| create_test_character(&mut transaction, comp::humanoid::Body::random()))
+            .collect::<Vec<_>>();
+        transaction
+            .execute(
+                "UPDATE character SET player_uuid = 'other' WHERE character_id = ?1",
+                &[ids[1]],
+            )
+            .unwrap();
+
+        let lists =
+            load_characters_for_players(&["player", "other", "nobody"], &transaction).unwrap();
+        let ids_of = |uuid| {
+            lists[uuid]
+                .iter()
+                .map(|item| item.character.id.unwrap())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(ids_of("player"), vec![ids[0], ids[2]]);
+        assert_eq!(ids_of("other"), vec![ids[1]]);
+        assert!(!lists.contains_key("nobody"));
+        assert_eq!(load_character_list("other", &transaction).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn broken_characters_are_left_out_of_character_lists() {
+        let mut connection = migrated_connection();
+        let mut transaction = connection.transaction().unwrap();
+        let ids = (0..4)
+            .map(|_| create_test_character(&mut transaction, comp::humanoid::Body::random()))
+            .collect::<Vec<_>>();
+        // The character still references its body, which is only checked if the
+        // transaction were committed
+        transaction
+            .execute_batch("PRAGMA defer_foreign_keys = ON")
+            .unwrap();
+        transaction
+            .execute("DELETE FROM body WHERE body_id = ?1", &[ids[1]])
+            .unwrap();
+        transaction
+            .execute(
+                "UPDATE body SET body_data = 'not a body' WHERE body_id = ?1",
+                &[ids[2]],
+            )
+            .unwrap();
+        transaction
+            .execute(
+                "UPDATE item SET position = 'lost' WHERE parent_container_item_id = ?1 AND \
+                 position = 'loadout'",
+                &[ids[3]],
+            )
+            .unwrap();
+
+        let list = load_character_list("player", &transaction).unwrap();
+        assert_eq!(
+            list.iter()
+                .map(|item| item.character.id.unwrap())
+                .collect::<Vec<_>>(),
+            vec![ids[0]]
+        );
+        let (page, total) = load_character_list_paged("player", 0, 10, &transaction).unwrap();
+        assert_eq!(page.len(), 1);
+        assert_eq!(total, 4);
+    }
+
+    #[test]
+    fn character_lists_are_looked_up_by_index() {
+        let connection = migrated_connection();
+        let plan = |query: &str| {
+            connection
+                .prepare(&format!("EXPLAIN QUERY PLAN {}", query))
+                .unwrap()
+                .query_map(NO_PARAMS, |row| row.get::<_, String>(3))
+                .unwrap()
+                .collect::<Result<Vec<_>, _>>()
+                .unwrap()
+                .join("\n")
+        };
+        for (query, index) in [
+            (
+                "SELECT character_id FROM character WHERE player_uuid = ?1 AND (deleted_at IS \
+                 NULL OR (hardcore AND deaths > 0)) ORDER BY character_id",
+                "idx_character_player_uuid",
+            ),
+            (
+                "SELECT character_id FROM character WHERE player_uuid IN rarray(?1)",
+                "idx_character_player_uuid",
+            ),
+            (
+                "SELECT p.pet_id FROM pet p JOIN body b ON (p.pet_id = b.body_id) WHERE \
+                 p.character_id = ?1",
+                "idx_pet_character_id",
+            ),
+        ] {
+            let plan = plan(query);
+            assert!(plan.contains(&format!("USING INDEX {}", index)), "{}", plan);
+            assert!(!plan.contains("TEMP B-TREE"), "{}", plan);
+        }
+    }
+
+    #[test]
+    fn creation_and_login_times_are_listed() {
+        let mut connection = migrated_connection();
+        let mut transaction = connection.transaction().unwrap();
+        let before = Utc::now().timestamp();
+        let char_id = create_test_character(&mut transaction, comp::humanoid::Body::random());
+
+        let list = load_character_list("player", &transaction).unwrap();
+        let created_at = list[0].created_at.unwrap();
+        assert!(created_at >= before);
+        assert_eq!(list[0].last_login, None);
+
+        record_login(char_id, 1, &mut transaction).unwrap();
+        let list = load_character_list("player", &transaction).unwrap();
+        assert_eq!(list[0].created_at, Some(created_at));
+        assert!(list[0].last_login.unwrap() >= created_at);
+    }
+
+    #[test]
+    fn purged_character_leaves_no_orphaned_rows() {
+        let mut connection = migrated_connection();
+        let mut transaction = connection.transaction().unwrap();
+
+        let char_id = create_test_character(&mut transaction, comp::humanoid::Body::random());
+        buffs::save_buffs(
+            char_id,
+            &[comp::Buff::new(
+                comp::BuffKind::Regeneration,
+                comp::BuffData::new(1.0, None),
+                Vec::new(),
+                comp::BuffSource::Item,
+            )],
+            &transaction,
+        )
+        .unwrap();
+
+        // Deleting only marks the character, so that it can still be restored
+        delete_character("player", char_id, &mut transaction).unwrap();
+        assert_eq!(
+            count_rows(&transaction, "character", "character_id", char_id),
+            1
+        );
+        assert_eq!(count_rows(&transaction, "body", "body_id", char_id), 1);
+
+        assert_eq!(
+            purge_deleted_characters(Duration::ZERO, &mut transaction).unwrap(),
+            1
+        );
+        for (table, column) in [
+            ("character", "character_id"),
+            ("body", "body_id"),
+            ("skill_group", "entity_id"),
+            ("ability_set", "entity_id"),
+            ("character_settings", "character_id"),
+            ("buffs", "character_id"),
+            ("pet", "character_id"),
+            ("item", "item_id"),
+            ("item", "parent_container_item_id"),
+        ] {
+            assert_eq!(
+                count_rows(&transaction, table, column, char_id),
+                0,
+                "orphaned rows in {}",
+                table
+            );
+        }
+    }
+
+    #[test]
+    fn only_appearance_of_owned_characters_can_be_changed() {
+        let mut connection = migrated_connection();
+        let mut transaction = connection.transaction().unwrap();
+        let old = comp::humanoid::Body::random_with(
+            &mut rand::thread_rng(),
+            &comp::humanoid::Species::Dwarf,
+        );
+        let char_id = create_test_character(&mut transaction, old);
+
+        let new_hair = comp::humanoid::Body {
+            hair_style: 0,
+            hair_color: 0,
+            ..old
+        };
+        assert!(matches!(
+            update_body(
+                "other player",
+                char_id,
+                &comp::Body::Humanoid(new_hair),
+                &mut transaction
+            ),
+            Err(PersistenceError::CharacterNotOwned(id)) if id == char_id
+        ));
+        let new_species = comp::humanoid::Body {
+            species: comp::humanoid::Species::Elf,
+            ..new_hair
+        };
+        assert!(matches!(
+            update_body(
+                "player",
+                char_id,
+                &comp::Body::Humanoid(new_species),
+                &mut transaction
+            ),
+            Err(PersistenceError::CharacterDataError)
+        ));
+
+        update_body(
+            "player",
+            char_id,
+            &comp::Body::Humanoid(new_hair),
+            &mut transaction,
+        )
+        .unwrap();
+        let list = load_character_list("player", &transaction).unwrap();
+        assert_eq!(list[0].body, comp::Body::Humanoid(new_hair));
+    }
+
+    #[test]
+    fn bodies_with_missing_features_are_refused() {
+        let mut connection = migrated_connection();
+        let mut transaction = connection.transaction().unwrap();
+        let body = comp::humanoid::Body::random();
+        let char_id = create_test_character(&mut transaction, body);
+        let broken = comp::Body::Humanoid(comp::humanoid::Body {
+            hair_style: u8::MAX,
+            ..body
+        });
+
+        assert!(matches!(
+            check_body(&broken),
+            Err(PersistenceError::InvalidBody(_))
+        ));
+        assert!(matches!(
+            update_body("player", char_id, &broken, &mut transaction),
+            Err(PersistenceError::InvalidBody(_))
+        ));
+
+        // Bodies stored before they were checked get their features clamped
+        let (_, body_data) = convert_body_to_database_json(&broken).unwrap();
+        transaction
+            .execute("UPDATE body SET body_data = ?1 WHERE body_id = ?2", &[
+                &body_data as &dyn ToSql,
+                &char_id,
+            ])
+            .unwrap();
+        let list = load_character_list("player", &transaction).unwrap();
+        assert!(matches!(
+            list[0].body,
+            comp::Body::Humanoid(loaded) if loaded.invalid_feature().is_none()
+        ));
+    }
+
+    #[test]
+    fn missing_and_foreign_characters_are_told_apart() {
+        let mut connection = migrated_connection();
+        let mut transaction = connection.transaction().unwrap();
+        let char_id = create_test_character(&mut transaction, comp::humanoid::Body::random());
+        let missing = char_id + 1;
+
+        assert!(matches!(
+            load_character_data("other player".to_owned(), char_id, &transaction),
+            Err(PersistenceError::CharacterNotOwned(id)) if id == char_id
+        ));
+        assert!(matches!(
+            load_character_data("player".to_owned(), missing, &transaction),
+            Err(PersistenceError::CharacterNotFound(id)) if id == missing
+        ));
+        assert!(matches!(
+            rename_character(
+                "player",
+                char_id,
+                "",
+                AliasUniqueness::PerPlayer,
+                &mut transaction
+            ),
+            Err(PersistenceError::InvalidAlias(_))
+        ));
+        assert!(matches!(
+            rename_character(
+                "player",
+                char_id,
+                &"a".repeat(MAX_NAME_LENGTH + 1),
+                AliasUniqueness::PerPlayer,
+                &mut transaction
+            ),
+            Err(PersistenceError::InvalidAlias(_))
+        ));
+
+        delete_character("player", char_id, &mut transaction).unwrap();
+        assert!(matches!(
+            delete_character("player", char_id, &mut transaction),
+            Err(PersistenceError::CharacterNotFound(id)) if id == char_id
+        ));
+    }
+
+    #[test]
+    fn renaming_and_deleting_characters_is_audited() {
+        let mut connection = migrated_connection();
+        let mut transaction = connection.transaction().unwrap();
+        let char_id = create_test_character(&mut transaction, comp::humanoid::Body::random());
+
+        // Failed operations aren't recorded
+        let rename = |uuid, alias, transaction: &mut Transaction| {
+            rename_character(
+                uuid,
+                char_id,
+                alias,
+                AliasUniqueness::PerPlayer,
+                transaction,
+            )
+        };
+        assert!(rename("other player", "Thief", &mut transaction).is_err());
+        rename("player", "Renamed", &mut transaction).unwrap();
+        delete_character("player", char_id, &mut transaction).unwrap();
+        audit_log::log_event(
+            &transaction,
+            AuditEventKind::PlayerBanned,
+            "admin",
+            None,
+            "player",
+        );
+
+        let entries = audit_log::query_audit_log(&transaction, &audit_log::AuditLogFilter {
+            player_uuid: Some("player".to_owned()),
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(
+            entries
+                .iter()
+                .map(|entry| (entry.kind, entry.character_id, entry.detail.as_str()))
+                .collect::<Vec<_>>(),
+            vec![
+                (
+                    AuditEventKind::CharacterRenamed,
+                    Some(char_id),
+                    "Tester -> Renamed"
+                ),
+                (AuditEventKind::CharacterDeleted, Some(char_id), "Renamed"),
+            ]
+        );
+
+        let now = Utc::now().timestamp();
+        let all = |since, until| {
+            audit_log::query_audit_log(&transaction, &audit_log::AuditLogFilter {
+                player_uuid: None,
+                since,
+                until,
+            })
+            .unwrap()
+            .len()
+        };
+        assert_eq!(all(None, None), 3);
+        assert_eq!(all(Some(now - 60), Some(now + 60)), 3);
+        assert_eq!(all(Some(now + 60), None), 0);
+        assert_eq!(all(None, Some(now - 60)), 0);
+    }
+
+    #[test]
+    fn edits_rename_like_renaming() {
+        let mut connection = migrated_connection();
+        let mut transaction = connection.transaction().unwrap();
+        let body = comp::humanoid::Body::random();
+        let first = create_test_character(&mut transaction, body);
+        let second = create_test_character(&mut transaction, body);
+        let edit = |char_id, alias, transaction: &mut Transaction| {
+            edit_character(
+                (comp::Body::Humanoid(body),),
+                transaction,
+                char_id,
+                "player",
+                alias,
+                AliasUniqueness::PerPlayer,
+            )
+        };
+
+        assert!(matches!(
+            edit(second, "Tester", &mut transaction),
+            Err(PersistenceError::AliasTaken(_))
+        ));
+        assert!(matches!(
+            edit(second, "", &mut transaction),
+            Err(PersistenceError::InvalidAlias(_))
+        ));
+        // Keeping the alias isn't a rename
+        edit(first, "Tester", &mut transaction).unwrap();
+        let (_, list) = edit(first, "Edited", &mut transaction).unwrap();
+        assert!(list
+            .iter()
+            .any(|item| item.character.alias == "Edited" && item.character.id == Some(first)));
+
+        let entries = audit_log::query_audit_log(&transaction, &audit_log::AuditLogFilter {
+            player_uuid: Some("player".to_owned()),
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(
+            entries
+                .iter()
+                .map(|entry| (entry.kind, entry.detail.as_str()))
+                .collect::<Vec<_>>(),
+            vec![(AuditEventKind::CharacterRenamed, "Tester -> Edited")]
+        );
+    }
+
+    #[test]
+    fn aliases_are_unique() {
+        let mut connection = migrated_connection();
+        let mut transaction = connection.transaction().unwrap();
+        let first = create_test_character(&mut transaction, comp::humanoid::Body::random());
+        let second = create_test_character(&mut transaction, comp::humanoid::Body::random());
+        let rename = |uuid, char_id, uniqueness, transaction: &mut Transaction| {
+            rename_character(uuid, char_id, "Tester", uniqueness, transaction)
+        };
+
+        // The first character is already called "Tester", but may keep its alias
+        assert!(matches!(
+            rename(
+                "player",
+                second,
+                AliasUniqueness::PerPlayer,
+                &mut transaction
+            ),
+            Err(PersistenceError::AliasTaken(_))
+        ));
+        rename(
+            "player",
+            first,
+            AliasUniqueness::PerPlayer,
+            &mut transaction,
+        )
+        .unwrap();
+
+        // Characters of other players only clash when aliases are unique
+        // server-wide
+        transaction
+            .execute(
+                "UPDATE character SET player_uuid = 'other player' WHERE character_id = ?1",
+                &[second],
+            )
+            .unwrap();
+        assert!(matches!(
+            rename(
+                "other player",
+                second,
+                AliasUniqueness::Server,
+                &mut transaction
+            ),
+            Err(PersistenceError::AliasTaken(_))
+        ));
+        rename(
+            "other player",
+            second,
+            AliasUniqueness::PerPlayer,
+            &mut transaction,
+        )
+        .unwrap();
+
+        // Deleted characters free their alias, and can't be restored once
+        // another character took it
+        let third = create_test_character(&mut transaction, comp::humanoid::Body::random());
+        delete_character("player", first, &mut transaction).unwrap();
+        rename(
+            "player",
+            third,
+            AliasUniqueness::PerPlayer,
+            &mut transaction,
+        )
+        .unwrap();
+        assert!(matches!(
+            restore_character(
+                "player",
+                first,
+                Duration::from_secs(3600),
+                8,
+                AliasUniqueness::PerPlayer,
+                &mut transaction
+            ),
+            Err(PersistenceError::AliasTaken(_))
+        ));
+    }
+
+    #[test]
+    fn failing_to_audit_does_not_abort_deletion() {
+        let mut connection = migrated_connection();
+        let mut transaction = connection.transaction().unwrap();
+        let char_id = create_test_character(&mut transaction, comp::humanoid::Body::random());
+
+        transaction
+            .execute("DROP TABLE audit_log", NO_PARAMS)
+            .unwrap();
+        delete_character("player", char_id, &mut transaction).unwrap();
+        transaction.commit().unwrap();
+
+        assert!(load_character_list("player", &connection)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn aliases_are_checked() {
+        for alias in [
+            "Frank",
+            "Ana-Lise",
+            "O'Neill",
+            "Ser Arthur Jr.",
+            "Zoë",
+            "Agent 47",
+        ] {
+            assert!(check_alias(alias).is_ok(), "'{}' is refused", alias);
+        }
+        for alias in [
+            "",
+            " Frank",
+            "Frank ",
+            "Fr  ank",
+            "Fr\u{200b}ank",
+            "Frank\n",
+            "<Admin>",
+            "[GM] Frank",
+        ] {
+            assert!(
+                matches!(check_alias(alias), Err(PersistenceError::InvalidAlias(_))),
+                "'{}' is allowed",
+                alias
+            );
+        }
+        assert!(check_alias(&"a".repeat(MAX_NAME_LENGTH)).is_ok());
+        assert!(check_alias(&"a".repeat(MAX_NAME_LENGTH + 1)).is_err());
+    }
 }
@@ -21,7 +21,7 @@ use common::{
     resources::Time,
 };
 use core::{convert::TryFrom, num::NonZeroU64};
-use hashbrown::HashMap;
+use hashbrown::{HashMap, HashSet};
 use lazy_static::lazy_static;
 use std::{collections::VecDeque, str::FromStr, sync::Arc};
 use tracing::{trace, warn};
@@ -213,22 +213,31 @@ pub fn convert_body_to_database_json(
             "bird_medium",
             serde_json::to_string(&GenericBody::from(body))?,
         ),
-        _ => {
-            return Err(PersistenceError::ConversionError(format!(
-                "Unsupported body type for persistence: {:?}",
-                comp_body
-            )));
-        },
+        // Bodies that were added later are stored as they are serialized
+        Body::FishMedium(body) => ("fish_medium", serde_json::to_string(body)?),
+        Body::Dragon(body) => ("dragon", serde_json::to_string(body)?),
+        Body::BirdLarge(body) => ("bird_large", serde_json::to_string(body)?),
+        Body::FishSmall(body) => ("fish_small", serde_json::to_string(body)?),
+        Body::BipedLarge(body) => ("biped_large", serde_json::to_string(body)?),
+        Body::BipedSmall(body) => ("biped_small", serde_json::to_string(body)?),
+        Body::Object(body) => ("object", serde_json::to_string(body)?),
+        Body::Golem(body) => ("golem", serde_json::to_string(body)?),
+        Body::Theropod(body) => ("theropod", serde_json::to_string(body)?),
+        Body::Ship(body) => ("ship", serde_json::to_string(body)?),
+        Body::Arthropod(body) => ("arthropod", serde_json::to_string(body)?),
+        Body::ItemDrop(body) => ("item_drop", serde_json::to_string(body)?),
     })
 }
 
 pub fn convert_waypoint_to_database_json(
     waypoint: Option<Waypoint>,
+    position: Option<Pos>,
     map_marker: Option<MapMarker>,
 ) -> Option<String> {
-    if waypoint.is_some() || map_marker.is_some() {
+    if waypoint.is_some() || position.is_some() || map_marker.is_some() {
         let charpos = CharacterPosition {
             waypoint: waypoint.map(|w| w.get_pos()),
+            position: position.map(|p| p.0),
             map_marker: map_marker.map(|m| m.0),
         };
         Some(
@@ -245,7 +254,7 @@ pub fn convert_waypoint_to_database_json(
 
 pub fn convert_waypoint_from_database_json(
     position: &str,
-) -> Result<(Option<Waypoint>, Option<MapMarker>), PersistenceError> {
+) -> Result<(Option<Waypoint>, Option<Pos>, Option<MapMarker>), PersistenceError> {
     let character_position =
         serde_json::de::from_str::<CharacterPosition>(position).map_err(|err| {
             PersistenceError::ConversionError(format!(
@@ -257,10 +266,26 @@ pub fn convert_waypoint_from_database_json(
         character_position
             .waypoint
             .map(|pos| Waypoint::new(pos, Time(0.0))),
+        character_position.position.map(Pos),
         character_position.map_marker.map(MapMarker),
     ))
 }
 
+pub fn convert_hotbar_to_database_json(hotbar: &Hotbar) -> Option<String> {
+    serde_json::to_string(hotbar)
+        .map_err(|err| warn!("Error encoding hotbar: {:?}", err))
+        .ok()
+}
+
+pub fn convert_hotbar_from_database_json(hotbar: &str) -> Result<Hotbar, PersistenceError> {
+    serde_json::de::from_str::<Hotbar>(hotbar).map_err(|err| {
+        PersistenceError::ConversionError(format!(
+            "Error de-serializing hotbar: {} err: {}",
+            hotbar, err
+        ))
+    })
+}
+
 // Used to handle cases of modular items that are composed of components.
 // When called with the index of a component's parent item, it can get a mutable
 // reference to that parent item so that the component can be added to the
@@ -351,15 +376,18 @@ pub fn convert_inventory_from_database_items(
     let loadout = convert_loadout_from_database_items(loadout_container_id, loadout_items)?;
     let mut inventory = Inventory::with_loadout_humanoid(loadout);
     let mut item_indices = HashMap::new();
+    let mut skipped_items = HashSet::new();
 
     // In order to items with components to properly load, it is important that this
     // item iteration occurs in order so that any modular items are loaded before
     // its components.
     for (i, db_item) in inventory_items.iter().enumerate() {
+        let mut item = match load_item_or_skip(db_item, &mut skipped_items) {
+            Some(item) => item,
+            None => continue,
+        };
         item_indices.insert(db_item.item_id, i);
 
-        let mut item = get_item_from_asset(db_item.item_definition_id.as_str())?;
-
         // NOTE: Since this is freshly loaded, the atomic is *unique.*
         let comp = item.get_item_id_for_database();
 
@@ -452,15 +480,18 @@ pub fn convert_loadout_from_database_items(
     let loadout_builder = LoadoutBuilder::empty();
     let mut loadout = loadout_builder.build();
     let mut item_indices = HashMap::new();
+    let mut skipped_items = HashSet::new();
 
     // In order to items with components to properly load, it is important that this
     // item iteration occurs in order so that any modular items are loaded before
     // its components.
     for (i, db_item) in database_items.iter().enumerate() {
+        let item = match load_item_or_skip(db_item, &mut skipped_items) {
+            Some(item) => item,
+            None => continue,
+        };
         item_indices.insert(db_item.item_id, i);
 
-        let item = get_item_from_asset(db_item.item_definition_id.as_str())?;
-
         // NOTE: item id is currently *unique*, so we can store the ID safely.
         let comp = item.get_item_id_for_database();
         comp.store(Some(NonZeroU64::try_from(db_item.item_id as u64).map_err(
@@ -502,6 +533,30 @@ pub fn convert_loadout_from_database_items(
     Ok(loadout)
 }
 
+/// Loads the item of a database row. Items whose asset doesn't exist anymore
+/// (e.g. because it was removed from the game) are skipped with a warning
+/// rather than making the whole character fail to load, and so are the
+/// components of skipped items.
+fn load_item_or_skip(db_item: &Item, skipped_items: &mut HashSet<i64>) -> Option<VelorenItem> {
+    if skipped_items.contains(&db_item.parent_container_item_id) {
+        warn!(
+            "Skipping item {} ({}) since its parent item {} was skipped",
+            db_item.item_id, db_item.item_definition_id, db_item.parent_container_item_id
+        );
+        skipped_items.insert(db_item.item_id);
+        return None;
+    }
+
+    match get_item_from_asset(db_item.item_definition_id.as_str()) {
+        Ok(item) => Some(item),
+        Err(err) => {
+            warn!("Skipping item {}: {}", db_item.item_id, err);
+            skipped_items.insert(db_item.item_id);
+            None
+        },
+    }
+}
+
 fn get_item_from_asset(item_definition_id: &str) -> Result<common::comp::Item, PersistenceError> {
     common::comp::Item::new_from_asset(item_definition_id).map_err(|err| {
         PersistenceError::AssetError(format!(
@@ -544,7 +599,7 @@ pub fn convert_body_from_database(
         // extra fields on its body struct
         "humanoid" => {
             let json_model = serde_json::de::from_str::<HumanoidBody>(body_data)?;
-            CompBody::Humanoid(humanoid::Body {
+            let mut body = humanoid::Body {
                 species: humanoid::ALL_SPECIES
                     .get(json_model.species as usize)
                     .ok_or_else(|| {
@@ -570,7 +625,11 @@ pub fn convert_body_from_database(
                 hair_color: json_model.hair_color,
                 skin: json_model.skin,
                 eye_color: json_model.eye_color,
-            })
+            };
+            // Bodies stored before their features were checked may have features
+            // that don't exist
+            body.validate();
+            CompBody::Humanoid(body)
         },
         "quadruped_low" => {
             deserialize_body!(body_data, QuadrupedLow, quadruped_low)
@@ -584,6 +643,18 @@ pub fn convert_body_from_database(
         "bird_medium" => {
             deserialize_body!(body_data, BirdMedium, bird_medium)
         },
+        "fish_medium" => CompBody::FishMedium(serde_json::de::from_str(body_data)?),
+        "dragon" => CompBody::Dragon(serde_json::de::from_str(body_data)?),
+        "bird_large" => CompBody::BirdLarge(serde_json::de::from_str(body_data)?),
+        "fish_small" => CompBody::FishSmall(serde_json::de::from_str(body_data)?),
+        "biped_large" => CompBody::BipedLarge(serde_json::de::from_str(body_data)?),
+        "biped_small" => CompBody::BipedSmall(serde_json::de::from_str(body_data)?),
+        "object" => CompBody::Object(serde_json::de::from_str(body_data)?),
+        "golem" => CompBody::Golem(serde_json::de::from_str(body_data)?),
+        "theropod" => CompBody::Theropod(serde_json::de::from_str(body_data)?),
+        "ship" => CompBody::Ship(serde_json::de::from_str(body_data)?),
+        "arthropod" => CompBody::Arthropod(serde_json::de::from_str(body_data)?),
+        "item_drop" => CompBody::ItemDrop(serde_json::de::from_str(body_data)?),
         _ => {
             return Err(PersistenceError::ConversionError(format!(
                 "{} is not a supported body type for deserialization",
@@ -600,6 +671,14 @@ pub fn convert_character_from_database(character: &Character) -> common::charact
     }
 }
 
+/// Pets with a stance that no longer exists fall back to the default one
+pub fn convert_pet_stance_from_database(stance: &str) -> pet::PetStance {
+    pet::PetStance::from_str(stance).unwrap_or_else(|_| {
+        warn!("Unknown pet stance {}, using the default stance", stance);
+        pet::PetStance::default()
+    })
+}
+
 pub fn convert_stats_from_database(alias: String) -> Stats {
     let mut new_stats = Stats::empty();
     new_stats.name = alias;
@@ -722,3 +801,99 @@ pub fn convert_active_abilities_from_database(ability_sets: &AbilitySets) -> Act
         });
     json_models::active_abilities_from_db_model(ability_sets)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn items_with_missing_assets_are_skipped() {
+        const INVENTORY_CONTAINER_ID: i64 = 1;
+        const LOADOUT_CONTAINER_ID: i64 = 2;
+        let db_item = |item_id, parent_container_item_id, item_definition_id: &str, slot| Item {
+            item_id,
+            parent_container_item_id,
+            item_definition_id: item_definition_id.to_owned(),
+            stack_size: 1,
+            position: serde_json::to_string(&InvSlotId::new(0, slot)).unwrap(),
+        };
+        let inventory_items = [
+            db_item(10, INVENTORY_CONTAINER_ID, "common.items.removed_item", 0),
+            // A component of the missing item
+            db_item(11, 10, "common.items.consumable.potion_minor", 0),
+            db_item(
+                12,
+                INVENTORY_CONTAINER_ID,
+                "common.items.consumable.potion_minor",
+                1,
+            ),
+        ];
+
+        let inventory = convert_inventory_from_database_items(
+            INVENTORY_CONTAINER_ID,
+            &inventory_items,
+            LOADOUT_CONTAINER_ID,
+            &[],
+        )
+        .unwrap();
+
+        assert!(inventory.get(InvSlotId::new(0, 0)).is_none());
+        assert_eq!(
+            inventory
+                .get(InvSlotId::new(0, 1))
+                .map(|item| item.item_definition_id()),
+            Some(item::ItemDefinitionId::Simple(
+                "common.items.consumable.potion_minor"
+            ))
+        );
+    }
+
+    #[test]
+    fn all_body_variants_round_trip() {
+        let bodies = [
+            CompBody::Humanoid(humanoid::Body::random()),
+            CompBody::QuadrupedSmall(quadruped_small::Body::random()),
+            CompBody::QuadrupedMedium(quadruped_medium::Body::random()),
+            CompBody::BirdMedium(bird_medium::Body::random()),
+            CompBody::FishMedium(fish_medium::Body::random()),
+            CompBody::Dragon(dragon::Body::random()),
+            CompBody::BirdLarge(bird_large::Body::random()),
+            CompBody::FishSmall(fish_small::Body::random()),
+            CompBody::BipedLarge(biped_large::Body::random()),
+            CompBody::BipedSmall(biped_small::Body::random()),
+            CompBody::Object(object::Body::random()),
+            CompBody::Golem(golem::Body::random()),
+            CompBody::Theropod(theropod::Body::random()),
+            CompBody::QuadrupedLow(quadruped_low::Body::random()),
+            CompBody::Ship(ship::Body::random()),
+            CompBody::Arthropod(arthropod::Body::random()),
+            CompBody::ItemDrop(item_drop::Body::Lantern),
+        ];
+
+        for body in bodies {
+            let (variant, body_data) = convert_body_to_database_json(&body).unwrap();
+            assert_eq!(
+                convert_body_from_database(variant, &body_data).unwrap(),
+                body
+            );
+        }
+    }
+
+    #[test]
+    fn pet_stance_round_trip() {
+        for stance in [
+            pet::PetStance::Passive,
+            pet::PetStance::Defensive,
+            pet::PetStance::Aggressive,
+        ] {
+            assert_eq!(
+                convert_pet_stance_from_database(&stance.to_string()),
+                stance
+            );
+        }
+        assert_eq!(
+            convert_pet_stance_from_database("Berserk"),
+            pet::PetStance::default()
+        );
+    }
+}
@@ -1,19 +1,28 @@
 use crate::persistence::{
-    character::{load_character_data, load_character_list},
+    character::{count_characters, list_all_characters, load_character_data, load_character_list},
     error::PersistenceError,
-    establish_connection, ConnectionMode, DatabaseSettings, PersistedComponents,
+    establish_connection,
+    maintenance::MaintenanceSummary,
+    retry::retry_if_locked,
+    ConnectionMode, DatabaseSettings, PersistedComponents,
 };
+use chrono::{TimeZone, Utc};
 use common::character::{CharacterId, CharacterItem};
 use crossbeam_channel::{self, TryIter};
+use hashbrown::HashMap;
 use rusqlite::Connection;
-use std::sync::{Arc, RwLock};
+use std::{
+    fmt,
+    sync::{Arc, RwLock},
+};
 use tracing::error;
 
 pub(crate) type CharacterListResult = Result<Vec<CharacterItem>, PersistenceError>;
-pub(crate) type CharacterCreationResult =
-    Result<(CharacterId, Vec<CharacterItem>), PersistenceError>;
+pub(crate) type CharacterCreationResult = Result<(CharacterId, CharacterItem), PersistenceError>;
 pub(crate) type CharacterEditResult = Result<(CharacterId, Vec<CharacterItem>), PersistenceError>;
+pub(crate) type CharacterDeletionResult = Result<CharacterId, PersistenceError>;
 pub(crate) type CharacterDataResult = Result<PersistedComponents, PersistenceError>;
+pub(crate) type CharacterSummaryResult = Result<CharacterSummaryPage, PersistenceError>;
 type CharacterLoaderRequest = (specs::Entity, CharacterLoaderRequestKind);
 
 /// Available database operations when modifying a player's character list
@@ -25,6 +34,68 @@ enum CharacterLoaderRequestKind {
         player_uuid: String,
         character_id: CharacterId,
     },
+    LoadCharacterSummaries {
+        offset: u32,
+        limit: u32,
+    },
+}
+
+/// Overview of a character of any player, for admins
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CharacterSummary {
+    pub character_id: CharacterId,
+    pub alias: String,
+    pub player_uuid: String,
+    /// Number of characters of the player, including this one
+    pub player_characters: u32,
+    /// Characters have no level, the exp earned in all skill groups is used
+    /// to tell how far they are instead
+    pub total_exp: u64,
+    pub last_login: Option<i64>,
+}
+
+/// A page of the summaries of all characters, the most recently played first
+#[derive(Debug)]
+pub struct CharacterSummaryPage {
+    pub offset: u32,
+    /// Number of characters on the server
+    pub total: u64,
+    pub characters: Vec<CharacterSummary>,
+}
+
+impl fmt::Display for CharacterSummaryPage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.characters.is_empty() {
+            return write!(f, "No characters after the first {}", self.offset);
+        }
+        write!(
+            f,
+            "Characters {} to {} of {}:",
+            self.offset + 1,
+            self.offset as usize + self.characters.len(),
+            self.total
+        )?;
+        for character in &self.characters {
+            let last_login = character
+                .last_login
+                .and_then(|time| Utc.timestamp_opt(time, 0).single())
+                .map_or_else(
+                    || "never".to_owned(),
+                    |time| time.format("%Y-%m-%d %H:%M").to_string(),
+                );
+            write!(
+                f,
+                "\n{} ({}) of {} ({} characters), {} exp, last played {}",
+                character.alias,
+                character.character_id,
+                character.player_uuid,
+                character.player_characters,
+                character.total_exp,
+                last_login
+            )?;
+        }
+        Ok(())
+    }
 }
 
 /// Wrapper for results for character actions. Can be a list of
@@ -35,6 +106,9 @@ pub enum CharacterLoaderResponseKind {
     CharacterData(Box<CharacterDataResult>),
     CharacterCreation(CharacterCreationResult),
     CharacterEdit(CharacterEditResult),
+    CharacterDeletion(CharacterDeletionResult),
+    CharacterSummaries(CharacterSummaryResult),
+    Maintenance(Result<MaintenanceSummary, PersistenceError>),
 }
 
 /// Common message format dispatched in response to an update request
@@ -51,10 +125,59 @@ impl CharacterLoaderResponse {
             CharacterLoaderResponseKind::CharacterData(box Err(_))
                 | CharacterLoaderResponseKind::CharacterList(Err(_))
                 | CharacterLoaderResponseKind::CharacterCreation(Err(_))
+                | CharacterLoaderResponseKind::CharacterEdit(Err(_))
+                | CharacterLoaderResponseKind::CharacterDeletion(Err(_))
         )
     }
 }
 
+/// The character lists of players on the character screen, so that creating
+/// or deleting a character only changes the cached list instead of loading the
+/// whole list from the database again.
+///
+/// The list of a player is dropped when one of their character requests fails,
+/// so that they are never shown a list that doesn't match the database, and
+/// when they leave the game, since playing changes the details in the list.
+/// Lists are loaded by the [`CharacterUpdater`] while it still has saves to
+/// write, so that they include them.
+///
+/// [`CharacterUpdater`]: super::character_updater::CharacterUpdater
+#[derive(Default)]
+pub struct CharacterListCache {
+    lists: HashMap<String, Vec<CharacterItem>>,
+}
+
+impl CharacterListCache {
+    pub fn get(&self, player_uuid: &str) -> Option<&Vec<CharacterItem>> {
+        self.lists.get(player_uuid)
+    }
+
+    pub fn insert(&mut self, player_uuid: String, list: Vec<CharacterItem>) {
+        self.lists.insert(player_uuid, list);
+    }
+
+    /// Adds a newly created character to the list of its player, if cached
+    pub fn add_character(&mut self, player_uuid: &str, character: CharacterItem) {
+        if let Some(list) = self.lists.get_mut(player_uuid) {
+            list.push(character);
+        }
+    }
+
+    /// Removes a deleted character from the list of its player, returning the
+    /// updated list if it is cached
+    pub fn remove_character(
+        &mut self,
+        player_uuid: &str,
+        character_id: CharacterId,
+    ) -> Option<&Vec<CharacterItem>> {
+        let list = self.lists.get_mut(player_uuid)?;
+        list.retain(|item| item.character.id != Some(character_id));
+        Some(list)
+    }
+
+    pub fn invalidate(&mut self, player_uuid: &str) { self.lists.remove(player_uuid); }
+}
+
 /// A bi-directional messaging resource for making requests to modify or load
 /// character data in a background thread.
 ///
@@ -117,16 +240,17 @@ impl CharacterLoader {
             entity,
             result: match kind {
                 CharacterLoaderRequestKind::LoadCharacterList { player_uuid } => {
-                    CharacterLoaderResponseKind::CharacterList(load_character_list(
-                        &player_uuid,
-                        connection,
-                    ))
+                    CharacterLoaderResponseKind::CharacterList(retry_if_locked(|| {
+                        load_character_list(&player_uuid, connection)
+                    }))
                 },
                 CharacterLoaderRequestKind::LoadCharacterData {
                     player_uuid,
                     character_id,
                 } => {
-                    let result = load_character_data(player_uuid, character_id, connection);
+                    let result = retry_if_locked(|| {
+                        load_character_data(player_uuid.clone(), character_id, connection)
+                    });
                     if result.is_err() {
                         error!(
                             ?result,
@@ -135,6 +259,15 @@ impl CharacterLoader {
                     }
                     CharacterLoaderResponseKind::CharacterData(Box::new(result))
                 },
+                CharacterLoaderRequestKind::LoadCharacterSummaries { offset, limit } => {
+                    CharacterLoaderResponseKind::CharacterSummaries(retry_if_locked(|| {
+                        Ok(CharacterSummaryPage {
+                            offset,
+                            total: count_characters(connection)?,
+                            characters: list_all_characters(offset, limit, connection)?,
+                        })
+                    }))
+                },
             },
         }
     }
@@ -170,6 +303,17 @@ impl CharacterLoader {
         }
     }
 
+    /// Loads a page of the summaries of all characters on the server, see
+    /// [`list_all_characters`]
+    pub fn load_character_summaries(&self, entity: specs::Entity, offset: u32, limit: u32) {
+        if let Err(e) = self.update_tx.send((
+            entity,
+            CharacterLoaderRequestKind::LoadCharacterSummaries { offset, limit },
+        )) {
+            error!(?e, "Could not send character summary load request");
+        }
+    }
+
     /// Returns a non-blocking iterator over CharacterLoaderResponse messages
     pub fn messages(&self) -> TryIter<CharacterLoaderResponse> { self.update_rx.try_iter() }
 }
@@ -0,0 +1,137 @@
+//! Small per-character settings, stored as a JSON blob so that adding a setting
+//! doesn't need a migration.
+
+use crate::persistence::error::PersistenceError;
+use common::{character::CharacterId, comp};
+use rusqlite::{Connection, OptionalExtension, ToSql, Transaction};
+use tracing::warn;
+
+/// Maximum size of the settings of a character, in bytes of JSON
+pub const MAX_SETTINGS_SIZE: usize = 4 * 1024;
+
+/// Loads the settings of a character. Characters without settings and settings
+/// that can't be read use the default settings, so that they never keep a
+/// character from being loaded.
+pub fn load_settings(
+    character_id: CharacterId,
+    connection: &Connection,
+) -> Result<comp::CharacterSettings, PersistenceError> {
+    let mut stmt = connection.prepare_cached(
+        "
+        SELECT  settings
+        FROM    character_settings
+        WHERE   character_id = ?1",
+    )?;
+
+    let settings: Option<String> = stmt
+        .query_row(&[&character_id], |row| row.get(0))
+        .optional()?;
+
+    Ok(match settings.as_deref().map(serde_json::from_str) {
+        Some(Ok(settings)) => settings,
+        Some(Err(e)) => {
+            warn!(
+                "Error reading settings from database for character ID {}, using the defaults \
+                 instead. Error: {}",
+                character_id, e
+            );
+            comp::CharacterSettings::default()
+        },
+        None => comp::CharacterSettings::default(),
+    })
+}
+
+/// Saves the settings of a character, unless they are larger than
+/// [`MAX_SETTINGS_SIZE`].
+pub fn save_settings(
+    character_id: CharacterId,
+    settings: &comp::CharacterSettings,
+    transaction: &Transaction,
+) -> Result<(), PersistenceError> {
+    let json = serde_json::to_string(settings)?;
+    if json.len() > MAX_SETTINGS_SIZE {
+        return Err(PersistenceError::CharacterSettingsTooLarge(json.len()));
+    }
+
+    let mut stmt = transaction.prepare_cached(
+        "
+        REPLACE
+        INTO    character_settings (character_id,
+                                    settings)
+        VALUES  (?1, ?2)",
+    )?;
+
+    stmt.execute(&[&character_id as &dyn ToSql, &json])?;
+
+    Ok(())
+}
+
+/// Removes the settings of a character that is purged
+pub(super) fn delete_settings(
+    character_id: CharacterId,
+    transaction: &Transaction,
+) -> Result<(), PersistenceError> {
+    let mut stmt = transaction.prepare_cached(
+        "
+        DELETE
+        FROM    character_settings
+        WHERE   character_id = ?1",
+    )?;
+
+    stmt.execute(&[&character_id])?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::persistence::character::tests::{create_test_character, migrated_connection};
+
+    #[test]
+    fn settings_are_saved_and_loaded() {
+        let mut connection = migrated_connection();
+        let mut transaction = connection.transaction().unwrap();
+        let char_id = create_test_character(&mut transaction, comp::humanoid::Body::random());
+        assert_eq!(
+            load_settings(char_id, &transaction).unwrap(),
+            comp::CharacterSettings::default()
+        );
+
+        let settings = comp::CharacterSettings {
+            title: Some("the Brave".to_owned()),
+            waypoint_icon: None,
+        };
+        save_settings(char_id, &settings, &transaction).unwrap();
+        assert_eq!(load_settings(char_id, &transaction).unwrap(), settings);
+
+        let too_large = comp::CharacterSettings {
+            title: Some("a".repeat(MAX_SETTINGS_SIZE)),
+            waypoint_icon: None,
+        };
+        assert!(matches!(
+            save_settings(char_id, &too_large, &transaction),
+            Err(PersistenceError::CharacterSettingsTooLarge(_))
+        ));
+        assert_eq!(load_settings(char_id, &transaction).unwrap(), settings);
+    }
+
+    #[test]
+    fn corrupt_settings_load_as_defaults() {
+        let mut connection = migrated_connection();
+        let mut transaction = connection.transaction().unwrap();
+        let char_id = create_test_character(&mut transaction, comp::humanoid::Body::random());
+        transaction
+            .execute(
+                "INSERT INTO character_settings (character_id, settings) VALUES (?1, \
+                 '{\"title\":')",
+                &[&char_id],
+            )
+            .unwrap();
+
+        assert_eq!(
+            load_settings(char_id, &transaction).unwrap(),
+            comp::CharacterSettings::default()
+        );
+    }
+}
@@ -0,0 +1,427 @@
+//! Export of characters to a portable JSON format, and import of them into
+//! another database (e.g. moving from singleplayer to a private server, or
+//! restoring from a backup).
+//!
+//! Only data that doesn't depend on the world is exported, so waypoints,
+//! positions, map markers and pets are left behind.
+
+use crate::{
+    character_creator,
+    persistence::{
+        character::{check_alias, create_character, load_character_data},
+        error::PersistenceError,
+        PersistedComponents,
+    },
+    settings::AliasUniqueness,
+};
+use censor::Censor;
+use common::{
+    assets::AssetExt,
+    character::CharacterId,
+    comp::{
+        self,
+        item::{ItemDef, ItemDefinitionId},
+    },
+};
+use rusqlite::{Connection, Transaction};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+/// Version of the export format, to be bumped whenever it changes in a way
+/// that older servers can't read.
+pub const EXPORT_FORMAT_VERSION: u64 = 1;
+
+#[derive(Serialize, Deserialize)]
+pub(super) struct ExportedCharacter {
+    version: u64,
+    pub alias: String,
+    pub body: comp::Body,
+    #[serde(with = "skill_groups")]
+    pub skill_set: comp::SkillSet,
+    pub inventory: comp::Inventory,
+    #[serde(with = "ability_sets")]
+    pub active_abilities: comp::ActiveAbilities,
+    pub hotbar: comp::Hotbar,
+    #[serde(default)]
+    pub hardcore: bool,
+    #[serde(default)]
+    pub settings: comp::CharacterSettings,
+}
+
+/// Skill sets are exported as their skill groups, and rebuilt on import the
+/// same way as when they are loaded from the database: skill points are earned
+/// again from the experience, and the skills are unlocked in order. Anything
+/// that doesn't add up ends up in `persistence_load_error`.
+mod skill_groups {
+    use common::comp::skillset::{SkillGroup, SkillSet, SkillsPersistenceError};
+    use hashbrown::HashMap;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(
+        skill_set: &SkillSet,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(skill_set.skill_groups())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<SkillSet, D::Error> {
+        let mut skill_groups = HashMap::new();
+        let mut skills = HashMap::new();
+        for exported in Vec::<SkillGroup>::deserialize(deserializer)? {
+            let kind = exported.skill_group_kind;
+            let mut skill_group = SkillGroup {
+                skill_group_kind: kind,
+                available_exp: 0,
+                earned_exp: 0,
+                available_sp: 0,
+                earned_sp: 0,
+                ordered_skills: Vec::new(),
+            };
+            skill_group.add_experience(exported.earned_exp);
+            let spent_exp = exported.earned_exp.checked_sub(exported.available_exp);
+            skills.insert(
+                kind,
+                if spent_exp == Some(skill_group.spent_exp()) {
+                    Ok(exported.ordered_skills)
+                } else {
+                    Err(SkillsPersistenceError::SpentExpMismatch)
+                },
+            );
+            skill_groups.insert(kind, skill_group);
+        }
+        Ok(SkillSet::load_from_database(skill_groups, skills))
+    }
+}
+
+/// Ability sets are keyed by the kinds of the equipped weapons, so they are
+/// exported in the same form as they are stored in the database.
+mod ability_sets {
+    use crate::persistence::json_models::{
+        active_abilities_from_db_model, active_abilities_to_db_model, DatabaseAbilitySet,
+    };
+    use common::comp::ActiveAbilities;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(
+        active_abilities: &ActiveAbilities,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        active_abilities_to_db_model(active_abilities).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<ActiveAbilities, D::Error> {
+        Vec::<DatabaseAbilitySet>::deserialize(deserializer).map(active_abilities_from_db_model)
+    }
+}
+
+pub(super) fn export_character(
+    player_uuid: &str,
+    character_id: CharacterId,
+    connection: &Connection,
+) -> Result<serde_json::Value, PersistenceError> {
+    let PersistedComponents {
+        body,
+        stats,
+        skill_set,
+        inventory,
+        active_abilities,
+        hotbar,
+        hardcore,
+        settings,
+        ..
+    } = load_character_data(player_uuid.to_owned(), character_id, connection)?;
+
+    Ok(serde_json::to_value(ExportedCharacter {
+        version: EXPORT_FORMAT_VERSION,
+        alias: stats.name,
+        body,
+        skill_set,
+        inventory,
+        active_abilities,
+        hotbar,
+        hardcore,
+        settings,
+    })?)
+}
+
+/// Creates a new character for the player from exported data, subject to the
+/// same rules as characters created from the character screen.
+pub(super) fn import_character(
+    player_uuid: &str,
+    json: serde_json::Value,
+    censor: &Censor,
+    max_characters: usize,
+    alias_uniqueness: AliasUniqueness,
+    transaction: &mut Transaction,
+) -> Result<CharacterId, PersistenceError> {
+    create_imported_character(
+        player_uuid,
+        decode_export(json)?,
+        censor,
+        max_characters,
+        alias_uniqueness,
+        transaction,
+    )
+}
+
+/// Reads exported data, checking that this server supports its format version
+pub(super) fn decode_export(
+    json: serde_json::Value,
+) -> Result<ExportedCharacter, PersistenceError> {
+    let version = json
+        .get("version")
+        .and_then(serde_json::Value::as_u64)
+        .ok_or_else(|| PersistenceError::InvalidImport("Missing format version".to_owned()))?;
+    if version > EXPORT_FORMAT_VERSION {
+        return Err(PersistenceError::InvalidImport(format!(
+            "Format version {} is newer than the latest version supported by this server ({})",
+            version, EXPORT_FORMAT_VERSION
+        )));
+    }
+
+    Ok(serde_json::from_value(json)?)
+}
+
+pub(super) fn create_imported_character(
+    player_uuid: &str,
+    exported: ExportedCharacter,
+    censor: &Censor,
+    max_characters: usize,
+    alias_uniqueness: AliasUniqueness,
+    transaction: &mut Transaction,
+) -> Result<CharacterId, PersistenceError> {
+    check_alias(&exported.alias)
+        .map_err(|error| PersistenceError::InvalidImport(error.to_string()))?;
+    if censor.check(&exported.alias) {
+        return Err(PersistenceError::InvalidImport(format!(
+            "Alias '{}' contains a banned word",
+            exported.alias
+        )));
+    }
+    character_creator::check_body(exported.body)
+        .map_err(|error| PersistenceError::InvalidImport(error.to_string()))?;
+    // Characters loaded from the database get a respec when their skills don't
+    // add up, but an import with made up experience or skills is refused
+    if let Some(error) = &exported.skill_set.persistence_load_error {
+        return Err(PersistenceError::InvalidImport(format!(
+            "Skills can't be restored: {:?}",
+            error
+        )));
+    }
+    let items = || {
+        exported
+            .inventory
+            .slots()
+            .flatten()
+            .chain(exported.inventory.equipped_items())
+    };
+    // Items this server doesn't know are read as placeholders, which would be
+    // dropped the next time the character is loaded
+    if let Some(id) = items().find_map(|item| match item.item_definition_id() {
+        ItemDefinitionId::Simple(id) if Arc::<ItemDef>::load(id).is_err() => Some(id.to_owned()),
+        _ => None,
+    }) {
+        return Err(PersistenceError::InvalidImport(format!(
+            "Unknown item '{}'",
+            id
+        )));
+    }
+    if let Some(item) = items().find(|item| item.amount() > item.max_amount()) {
+        return Err(PersistenceError::InvalidImport(format!(
+            "Stack of {} '{}' is larger than the maximum of {}",
+            item.amount(),
+            item.name(),
+            item.max_amount()
+        )));
+    }
+
+    let (character_id, _) = create_character(
+        player_uuid,
+        &exported.alias,
+        PersistedComponents {
+            body: exported.body,
+            stats: comp::Stats::new(exported.alias.clone()),
+            skill_set: exported.skill_set,
+            inventory: exported.inventory,
+            waypoint: None,
+            position: None,
+            pets: Vec::new(),
+            active_abilities: exported.active_abilities,
+            map_marker: None,
+            hotbar: exported.hotbar,
+            settings: exported.settings,
+            vitals: None,
+            buffs: Vec::new(),
+            lantern_fuel: None,
+            hardcore: exported.hardcore,
+        },
+        max_characters,
+        alias_uniqueness,
+        transaction,
+    )?;
+    Ok(character_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::persistence::{
+        character::{
+            delete_character,
+            tests::{create_test_character, migrated_connection},
+        },
+        character_settings::save_settings,
+    };
+    use common::{
+        character::MAX_NAME_LENGTH,
+        comp::{
+            ability::{AuxiliaryAbility, MAX_ABILITIES},
+            item::tool::ToolKind,
+            skillset::{Skill, SkillGroupKind},
+        },
+    };
+    use hashbrown::HashSet;
+    use rusqlite::NO_PARAMS;
+
+    #[derive(Serialize, Deserialize)]
+    struct Progress {
+        #[serde(with = "skill_groups")]
+        skill_set: comp::SkillSet,
+        #[serde(with = "ability_sets")]
+        active_abilities: comp::ActiveAbilities,
+    }
+
+    #[test]
+    fn skills_and_ability_sets_round_trip() {
+        let sword = Skill::UnlockGroup(SkillGroupKind::Weapon(ToolKind::Sword));
+        let mut skill_set = comp::SkillSet::default();
+        skill_set.add_experience(SkillGroupKind::General, 10_000);
+        skill_set.unlock_skill(sword).unwrap();
+        let mut active_abilities = comp::ActiveAbilities::default();
+        active_abilities.auxiliary_sets.insert(
+            (Some(ToolKind::Sword), None),
+            [AuxiliaryAbility::MainWeapon(0); MAX_ABILITIES],
+        );
+        let original = Progress {
+            skill_set,
+            active_abilities,
+        };
+
+        let copy: Progress =
+            serde_json::from_value(serde_json::to_value(&original).unwrap()).unwrap();
+        assert_eq!(copy.skill_set.persistence_load_error, None);
+        assert!(copy.skill_set.has_skill(sword));
+        assert_eq!(
+            copy.skill_set.skill_groups().collect::<HashSet<_>>(),
+            original.skill_set.skill_groups().collect::<HashSet<_>>()
+        );
+        assert_eq!(
+            copy.active_abilities.auxiliary_sets,
+            original.active_abilities.auxiliary_sets
+        );
+    }
+
+    #[test]
+    fn export_round_trip() {
+        let mut connection = migrated_connection();
+        let mut transaction = connection.transaction().unwrap();
+        let char_id = create_test_character(&mut transaction, comp::humanoid::Body::random());
+        let settings = comp::CharacterSettings {
+            title: Some("the Brave".to_owned()),
+            waypoint_icon: None,
+        };
+        save_settings(char_id, &settings, &transaction).unwrap();
+
+        // Only the owner can export a character
+        assert!(export_character("someone else", char_id, &transaction).is_err());
+
+        let json = export_character("player", char_id, &transaction).unwrap();
+        assert_eq!(json["version"], EXPORT_FORMAT_VERSION);
+        let import = |transaction: &mut Transaction| {
+            import_character(
+                "player",
+                json.clone(),
+                &Censor::Standard,
+                usize::MAX,
+                AliasUniqueness::PerPlayer,
+                transaction,
+            )
+        };
+        // The player still has the original
+        assert!(matches!(
+            import(&mut transaction),
+            Err(PersistenceError::AliasTaken(_))
+        ));
+        delete_character("player", char_id, &mut transaction).unwrap();
+        let imported = import(&mut transaction).unwrap();
+        assert_ne!(imported, char_id);
+        let original = decode_export(json).unwrap();
+        let copy =
+            decode_export(export_character("player", imported, &transaction).unwrap()).unwrap();
+        assert_eq!(copy.alias, original.alias);
+        assert_eq!(copy.body, original.body);
+        assert_eq!(copy.settings, settings);
+    }
+
+    #[test]
+    fn tampered_exports_are_refused() {
+        let mut connection = migrated_connection();
+        let mut transaction = connection.transaction().unwrap();
+        let char_id = create_test_character(&mut transaction, comp::humanoid::Body::random());
+        let json = export_character("player", char_id, &transaction).unwrap();
+
+        let mut extra_exp = json.clone();
+        extra_exp["skill_set"][0]["available_exp"] = (u32::MAX).into();
+        let mut long_alias = json.clone();
+        long_alias["alias"] = "a".repeat(MAX_NAME_LENGTH + 1).into();
+        let mut no_alias = json.clone();
+        no_alias["alias"] = "".into();
+        let mut inventory = comp::Inventory::with_empty();
+        inventory
+            .push(comp::Item::new_from_asset_expect("common.items.food.apple"))
+            .unwrap();
+        let mut unknown_item = json;
+        unknown_item["inventory"] = serde_json::from_str(
+            &serde_json::to_string(&inventory)
+                .unwrap()
+                .replace("common.items.food.apple", "common.items.food.not_an_apple"),
+        )
+        .unwrap();
+
+        for tampered in [extra_exp, long_alias, no_alias, unknown_item] {
+            assert!(matches!(
+                import_character(
+                    "player",
+                    tampered,
+                    &Censor::Standard,
+                    usize::MAX,
+                    AliasUniqueness::PerPlayer,
+                    &mut transaction,
+                ),
+                Err(PersistenceError::InvalidImport(_))
+            ));
+        }
+        let characters: i64 = transaction
+            .query_row("SELECT COUNT(*) FROM character", NO_PARAMS, |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert_eq!(characters, 1);
+    }
+
+    #[test]
+    fn exports_from_newer_servers_are_refused() {
+        let mut json = serde_json::json!({ "version": EXPORT_FORMAT_VERSION + 1 });
+        assert!(matches!(
+            decode_export(json.clone()),
+            Err(PersistenceError::InvalidImport(_))
+        ));
+        json.as_object_mut().unwrap().remove("version");
+        assert!(matches!(
+            decode_export(json),
+            Err(PersistenceError::InvalidImport(_))
+        ));
+    }
+}
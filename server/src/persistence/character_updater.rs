@@ -1,11 +1,14 @@
-use crate::comp;
+use crate::{comp, settings::AliasUniqueness};
 use common::character::CharacterId;
 
 use crate::persistence::{
+    audit_log::{self, AuditEventKind},
+    character::{load_character_list, StatsWrite},
     character_loader::{CharacterLoaderResponse, CharacterLoaderResponseKind},
     error::PersistenceError,
-    establish_connection, ConnectionMode, DatabaseSettings, EditableComponents,
-    PersistedComponents, VelorenConnection,
+    establish_connection, maintenance,
+    retry::retry_if_locked,
+    ConnectionMode, DatabaseSettings, EditableComponents, PersistedComponents, VelorenConnection,
 };
 use crossbeam_channel::TryIter;
 use rusqlite::{DropBehavior, Transaction};
@@ -13,9 +16,10 @@ use specs::Entity;
 use std::{
     collections::HashMap,
     sync::{
-        atomic::{AtomicBool, Ordering},
+        atomic::{AtomicBool, AtomicUsize, Ordering},
         Arc, RwLock,
     },
+    time::{Duration, Instant},
 };
 use tracing::{debug, error, info, trace, warn};
 
@@ -24,15 +28,56 @@ pub type CharacterUpdateData = (
     comp::Inventory,
     Vec<PetPersistenceData>,
     Option<comp::Waypoint>,
+    Option<comp::Pos>,
     comp::ability::ActiveAbilities,
     Option<comp::MapMarker>,
+    comp::Hotbar,
+    comp::CharacterSettings,
+    Option<Vitals>,
+    Vec<comp::Buff>,
+    // Fuel left in the lantern, see `comp::LanternFuel`
+    Option<f32>,
+    // Seconds played since the last update
+    u64,
 );
 
 pub type PetPersistenceData = (comp::Pet, comp::Body, comp::Stats);
 
+/// Current health and energy of a character
+pub type Vitals = (f32, f32);
+
+/// The vitals of a character to persist. Dead characters are saved without
+/// them, so that they respawn with full health just like in the game.
+pub fn vitals_to_persist(
+    health: Option<&comp::Health>,
+    energy: Option<&comp::Energy>,
+) -> Option<Vitals> {
+    let health = health.filter(|health| !health.is_dead)?;
+    Some((health.current(), energy?.current()))
+}
+
+/// Random token identifying one session of a character in the game, so that
+/// saves of a session that already ended can be told apart from the ones of
+/// the latest session
+pub type SessionId = i64;
+
+/// Number of queued events above which players can't change their character
+/// list anymore, so that a slow disk makes them wait instead of growing the
+/// queue without bound. Saving characters that are in the game is never
+/// refused.
+const MAX_QUEUED_CHARACTER_CHANGES: usize = 256;
+
 #[allow(clippy::large_enum_variant)]
 pub enum CharacterUpdaterEvent {
-    BatchUpdate(Vec<(CharacterId, CharacterUpdateData)>),
+    BatchUpdate(Vec<(CharacterId, SessionId, CharacterUpdateData)>),
+    RecordLogin(CharacterId, SessionId),
+    RecordLogout(CharacterId),
+    RecordDeath(CharacterId),
+    UpdateBody {
+        requesting_player_uuid: String,
+        character_id: CharacterId,
+        body: comp::Body,
+    },
     CreateCharacter {
         entity: Entity,
         player_uuid: String,
@@ -51,9 +96,42 @@ pub enum CharacterUpdaterEvent {
         requesting_player_uuid: String,
         character_id: CharacterId,
     },
+    RestoreCharacter {
+        entity: Entity,
+        requesting_player_uuid: String,
+        character_id: CharacterId,
+    },
+    AuditLog {
+        kind: AuditEventKind,
+        player_uuid: String,
+        character_id: Option<CharacterId>,
+        detail: String,
+    },
+    /// The summary is sent to `entity` if it's given, and only logged
+    /// otherwise
+    Maintenance {
+        entity: Option<Entity>,
+    },
+    LoadCharacterList {
+        entity: Entity,
+        player_uuid: String,
+    },
     DisconnectedSuccess,
 }
 
+impl CharacterUpdaterEvent {
+    /// Whether this saves a character that is or was just in the game
+    fn is_save(&self) -> bool {
+        matches!(
+            self,
+            CharacterUpdaterEvent::BatchUpdate(_)
+                | CharacterUpdaterEvent::RecordLogin(..)
+                | CharacterUpdaterEvent::RecordDeath(_)
+                | CharacterUpdaterEvent::UpdateBody { .. }
+        )
+    }
+}
+
 /// A unidirectional messaging resource for saving characters in a
 /// background thread.
 ///
@@ -63,19 +141,33 @@ pub struct CharacterUpdater {
     update_tx: Option<crossbeam_channel::Sender<CharacterUpdaterEvent>>,
     response_rx: crossbeam_channel::Receiver<CharacterLoaderResponse>,
     handle: Option<std::thread::JoinHandle<()>>,
-    pending_logout_updates: HashMap<CharacterId, CharacterUpdateData>,
+    pending_logout_updates: HashMap<CharacterId, (SessionId, CharacterUpdateData)>,
+    /// The session of each character in the game
+    sessions: HashMap<CharacterId, SessionId>,
+    /// When the playtime of each character in the game was last accounted for
+    playtime_since: HashMap<CharacterId, Instant>,
     /// Will disconnect all characters (without persistence) on the next tick if
     /// set to true
     disconnect_all_clients_requested: Arc<AtomicBool>,
+    /// Saves that were sent to the persistence thread but aren't written yet
+    unwritten_saves: Arc<AtomicUsize>,
 }
 
 impl CharacterUpdater {
-    pub fn new(settings: Arc<RwLock<DatabaseSettings>>) -> rusqlite::Result<Self> {
+    pub fn new(
+        settings: Arc<RwLock<DatabaseSettings>>,
+        deletion_retention: Duration,
+        max_characters: usize,
+        alias_uniqueness: AliasUniqueness,
+        batch_size: usize,
+    ) -> rusqlite::Result<Self> {
         let (update_tx, update_rx) = crossbeam_channel::unbounded::<CharacterUpdaterEvent>();
         let (response_tx, response_rx) = crossbeam_channel::unbounded::<CharacterLoaderResponse>();
 
         let disconnect_all_clients_requested = Arc::new(AtomicBool::new(false));
         let disconnect_all_clients_requested_clone = Arc::clone(&disconnect_all_clients_requested);
+        let unwritten_saves = Arc::new(AtomicUsize::new(0));
+        let unwritten_saves_clone = Arc::clone(&unwritten_saves);
 
         let builder = std::thread::Builder::new().name("persistence_updater".into());
         let handle = builder
@@ -85,6 +177,7 @@ impl CharacterUpdater {
                 let mut conn =
                     establish_connection(&*settings.read().unwrap(), ConnectionMode::ReadWrite);
                 while let Ok(updates) = update_rx.recv() {
+                    let is_save = updates.is_save();
                     match updates {
                         CharacterUpdaterEvent::BatchUpdate(updates) => {
                             if disconnect_all_clients_requested_clone.load(Ordering::Relaxed) {
@@ -92,18 +185,60 @@ impl CharacterUpdater {
                                     "Skipping persistence due to pending disconnection of all \
                                      clients"
                                 );
-                                continue;
+                            } else {
+                                conn.update_log_mode(&settings);
+                                if let Err(e) = execute_batch_update(updates, batch_size, &mut conn)
+                                {
+                                    error!(
+                                        "Error during character batch update, disconnecting all \
+                                         clients to avoid loss of data integrity. Error: {:?}",
+                                        e
+                                    );
+                                    disconnect_all_clients_requested_clone
+                                        .store(true, Ordering::Relaxed);
+                                };
                             }
-                            conn.update_log_mode(&settings);
-                            if let Err(e) = execute_batch_update(updates, &mut conn) {
+                        },
+                        CharacterUpdaterEvent::RecordLogin(character_id, session) => {
+                            if let Err(e) = execute_record_login(character_id, session, &mut conn) {
                                 error!(
-                                    "Error during character batch update, disconnecting all \
-                                     clients to avoid loss of data integrity. Error: {:?}",
-                                    e
+                                    "Error recording login of character ID {}, error: {:?}",
+                                    character_id, e
                                 );
-                                disconnect_all_clients_requested_clone
-                                    .store(true, Ordering::Relaxed);
-                            };
+                            }
+                        },
+                        CharacterUpdaterEvent::RecordLogout(character_id) => {
+                            if let Err(e) = execute_record_logout(character_id, &mut conn) {
+                                error!(
+                                    "Error recording logout of character ID {}, error: {:?}",
+                                    character_id, e
+                                );
+                            }
+                        },
+                        CharacterUpdaterEvent::RecordDeath(character_id) => {
+                            if let Err(e) = execute_record_death(character_id, &mut conn) {
+                                error!(
+                                    "Error recording death of character ID {}, error: {:?}",
+                                    character_id, e
+                                );
+                            }
+                        },
+                        CharacterUpdaterEvent::UpdateBody {
+                            requesting_player_uuid,
+                            character_id,
+                            body,
+                        } => {
+                            if let Err(e) = execute_update_body(
+                                &requesting_player_uuid,
+                                character_id,
+                                &body,
+                                &mut conn,
+                            ) {
+                                error!(
+                                    "Error updating body of character ID {}, error: {:?}",
+                                    character_id, e
+                                );
+                            }
                         },
                         CharacterUpdaterEvent::CreateCharacter {
                             entity,
@@ -116,6 +251,8 @@ impl CharacterUpdater {
                                 character_alias,
                                 &player_uuid,
                                 persisted_components,
+                                max_characters,
+                                alias_uniqueness,
                                 &mut conn,
                             ) {
                                 Ok(response) => {
@@ -147,6 +284,7 @@ impl CharacterUpdater {
                                 character_alias,
                                 &player_uuid,
                                 editable_components,
+                                alias_uniqueness,
                                 &mut conn,
                             ) {
                                 Ok(response) => {
@@ -192,6 +330,79 @@ impl CharacterUpdater {
                                 ),
                             }
                         },
+                        CharacterUpdaterEvent::RestoreCharacter {
+                            entity,
+                            requesting_player_uuid,
+                            character_id,
+                        } => {
+                            match execute_character_restore(
+                                entity,
+                                &requesting_player_uuid,
+                                character_id,
+                                deletion_retention,
+                                max_characters,
+                                alias_uniqueness,
+                                &mut conn,
+                            ) {
+                                Ok(response) => {
+                                    if let Err(e) = response_tx.send(response) {
+                                        error!(?e, "Could not send character restore response");
+                                    } else {
+                                        debug!(
+                                            "Processed character restore for character ID {}",
+                                            character_id
+                                        );
+                                    }
+                                },
+                                Err(e) => error!(
+                                    "Error restoring character ID {}, error: {:?}",
+                                    character_id, e
+                                ),
+                            }
+                        },
+                        CharacterUpdaterEvent::AuditLog {
+                            kind,
+                            player_uuid,
+                            character_id,
+                            detail,
+                        } => audit_log::log_event(
+                            &conn.connection,
+                            kind,
+                            &player_uuid,
+                            character_id,
+                            &detail,
+                        ),
+                        CharacterUpdaterEvent::Maintenance { entity } => {
+                            let result =
+                                maintenance::maintenance(deletion_retention, &mut conn.connection);
+                            match &result {
+                                Ok(summary) => info!("{}", summary),
+                                Err(e) => error!(?e, "Database maintenance failed"),
+                            }
+                            if let Some(entity) = entity {
+                                let response = CharacterLoaderResponse {
+                                    entity,
+                                    result: CharacterLoaderResponseKind::Maintenance(result),
+                                };
+                                if let Err(e) = response_tx.send(response) {
+                                    error!(?e, "Could not send database maintenance response");
+                                }
+                            }
+                        },
+                        CharacterUpdaterEvent::LoadCharacterList {
+                            entity,
+                            player_uuid,
+                        } => {
+                            let response = CharacterLoaderResponse {
+                                entity,
+                                result: CharacterLoaderResponseKind::CharacterList(
+                                    load_character_list(&player_uuid, &conn.connection),
+                                ),
+                            };
+                            if let Err(e) = response_tx.send(response) {
+                                error!(?e, "Could not send character list response");
+                            }
+                        },
                         CharacterUpdaterEvent::DisconnectedSuccess => {
                             info!(
                                 "CharacterUpdater received DisconnectedSuccess event, resuming \
@@ -202,6 +413,9 @@ impl CharacterUpdater {
                             disconnect_all_clients_requested_clone.store(false, Ordering::Relaxed);
                         },
                     }
+                    if is_save {
+                        unwritten_saves_clone.fetch_sub(1, Ordering::Relaxed);
+                    }
                 }
             })
             .unwrap();
@@ -211,10 +425,105 @@ impl CharacterUpdater {
             response_rx,
             handle: Some(handle),
             pending_logout_updates: HashMap::new(),
+            sessions: HashMap::new(),
+            playtime_since: HashMap::new(),
             disconnect_all_clients_requested,
+            unwritten_saves,
         })
     }
 
+    /// Sends a save of a character in the game to the persistence thread
+    fn send_save(
+        &mut self,
+        event: CharacterUpdaterEvent,
+    ) -> Result<(), crossbeam_channel::SendError<CharacterUpdaterEvent>> {
+        self.unwritten_saves.fetch_add(1, Ordering::Relaxed);
+        self.update_tx.as_ref().unwrap().send(event)
+    }
+
+    /// Starts tracking the playtime of a character that entered the game and
+    /// records the time of the login. Saves of earlier sessions of the
+    /// character can't lower its exp from now on.
+    pub fn start_session(&mut self, character_id: CharacterId) {
+        let session = rand::random::<SessionId>();
+        self.sessions.insert(character_id, session);
+        self.playtime_since.insert(character_id, Instant::now());
+        if let Err(e) = self.send_save(CharacterUpdaterEvent::RecordLogin(character_id, session)) {
+            error!(?e, "Could not send login record request");
+        }
+    }
+
+    /// Records that a character left the game. It's sent after the logout save
+    /// of the character, so that the character can't be transferred to
+    /// another server before that is written.
+    fn record_logout(&mut self, character_id: CharacterId) {
+        if let Err(e) = self
+            .update_tx
+            .as_ref()
+            .unwrap()
+            .send(CharacterUpdaterEvent::RecordLogout(character_id))
+        {
+            error!(?e, "Could not send logout record request");
+        }
+    }
+
+    /// Records that a character died, which deletes it if it is a hardcore
+    /// character.
+    pub fn record_death(&mut self, character_id: CharacterId) {
+        if let Err(e) = self.send_save(CharacterUpdaterEvent::RecordDeath(character_id)) {
+            error!(?e, "Could not send death record request");
+        }
+    }
+
+    /// Persists a change of the appearance of a character in the game, see
+    /// [`super::character::update_body`].
+    pub fn update_body(
+        &mut self,
+        requesting_player_uuid: String,
+        character_id: CharacterId,
+        body: comp::Body,
+    ) {
+        if let Err(e) = self.send_save(CharacterUpdaterEvent::UpdateBody {
+            requesting_player_uuid,
+            character_id,
+            body,
+        }) {
+            error!(?e, "Could not send body update request");
+        }
+    }
+
+    /// Records an operation of a player that isn't persisted in the database
+    /// itself, like a ban, in the [audit log](audit_log).
+    pub fn log_event(
+        &mut self,
+        kind: AuditEventKind,
+        player_uuid: String,
+        character_id: Option<CharacterId>,
+        detail: String,
+    ) {
+        if let Err(e) = self
+            .update_tx
+            .as_ref()
+            .unwrap()
+            .send(CharacterUpdaterEvent::AuditLog {
+                kind,
+                player_uuid,
+                character_id,
+                detail,
+            })
+        {
+            error!(?e, "Could not send audit log request");
+        }
+    }
+
+    /// Stops tracking the playtime of a character that left the game, returning
+    /// the seconds played that haven't been persisted yet.
+    pub fn end_session(&mut self, character_id: CharacterId) -> u64 {
+        self.playtime_since
+            .remove(&character_id)
+            .map_or(0, |since| since.elapsed().as_secs())
+    }
+
     /// Adds a character to the list of characters that have recently logged out
     /// and will be persisted in the next batch update.
     pub fn add_pending_logout_update(
@@ -222,18 +531,20 @@ impl CharacterUpdater {
         character_id: CharacterId,
         update_data: CharacterUpdateData,
     ) {
+        let session = self.sessions.remove(&character_id).unwrap_or_default();
         if !self
             .disconnect_all_clients_requested
             .load(Ordering::Relaxed)
         {
             self.pending_logout_updates
-                .insert(character_id, update_data);
+                .insert(character_id, (session, update_data));
         } else {
             warn!(
                 "Ignoring request to add pending logout update for character ID {} as there is a \
                  disconnection of all clients in progress",
                 character_id
             );
+            self.record_logout(character_id);
         }
     }
 
@@ -250,6 +561,37 @@ impl CharacterUpdater {
             .load(Ordering::Relaxed)
     }
 
+    /// Whether characters that are or were just in the game have saves that
+    /// aren't written to the database yet, so that their details in the
+    /// character list may be out of date
+    pub fn has_unwritten_saves(&self) -> bool {
+        !self.pending_logout_updates.is_empty() || self.unwritten_saves.load(Ordering::Relaxed) > 0
+    }
+
+    /// Loads the character list of a player after the saves that were sent
+    /// before, see [`CharacterUpdater::has_unwritten_saves`]
+    pub fn load_character_list(&mut self, entity: Entity, player_uuid: String) {
+        if let Err(e) =
+            self.update_tx
+                .as_ref()
+                .unwrap()
+                .send(CharacterUpdaterEvent::LoadCharacterList {
+                    entity,
+                    player_uuid,
+                })
+        {
+            error!(?e, "Could not send character list load request");
+        }
+    }
+
+    /// Whether the persistence thread is too far behind to accept changes to
+    /// character lists
+    pub fn is_busy(&self) -> bool {
+        self.update_tx
+            .as_ref()
+            .map_or(false, |tx| tx.len() >= MAX_QUEUED_CHARACTER_CHANGES)
+    }
+
     pub fn create_character(
         &mut self,
         entity: Entity,
@@ -318,7 +660,29 @@ impl CharacterUpdater {
             // updates for the character in the event that it has recently logged out.
             // Since the user has actively chosen to delete the character there is no value
             // in the pending update data anyway.
-            self.pending_logout_updates.remove(&character_id);
+            if self.pending_logout_updates.remove(&character_id).is_some() {
+                self.record_logout(character_id);
+            }
+        }
+    }
+
+    pub fn restore_character(
+        &mut self,
+        entity: Entity,
+        requesting_player_uuid: String,
+        character_id: CharacterId,
+    ) {
+        if let Err(e) =
+            self.update_tx
+                .as_ref()
+                .unwrap()
+                .send(CharacterUpdaterEvent::RestoreCharacter {
+                    entity,
+                    requesting_player_uuid,
+                    character_id,
+                })
+        {
+            error!(?e, "Could not send character restore request");
         }
     }
 
@@ -332,11 +696,18 @@ impl CharacterUpdater {
                 &'a comp::Inventory,
                 Vec<PetPersistenceData>,
                 Option<&'a comp::Waypoint>,
+                Option<&'a comp::Pos>,
                 &'a comp::ability::ActiveAbilities,
                 Option<&'a comp::MapMarker>,
+                &'a comp::Hotbar,
+                &'a comp::CharacterSettings,
+                Option<Vitals>,
+                Vec<comp::Buff>,
+                Option<f32>,
             ),
         >,
     ) {
+        let logged_out = self.characters_pending_logout().collect::<Vec<_>>();
         let updates = updates
             .map(
                 |(
@@ -345,32 +716,64 @@ impl CharacterUpdater {
                     inventory,
                     pets,
                     waypoint,
+                    position,
                     active_abilities,
                     map_marker,
+                    hotbar,
+                    settings,
+                    vitals,
+                    buffs,
+                    lantern_fuel,
                 )| {
                     (
                         character_id,
+                        self.sessions
+                            .get(&character_id)
+                            .copied()
+                            .unwrap_or_default(),
                         (
                             skill_set.clone(),
                             inventory.clone(),
                             pets,
                             waypoint.cloned(),
+                            position.copied(),
                             active_abilities.clone(),
                             map_marker.cloned(),
+                            hotbar.clone(),
+                            settings.clone(),
+                            vitals,
+                            buffs,
+                            lantern_fuel,
+                            take_playtime(&mut self.playtime_since, character_id),
                         ),
                     )
                 },
             )
-            .chain(self.pending_logout_updates.drain())
+            .chain(
+                self.pending_logout_updates
+                    .drain()
+                    .map(|(character_id, (session, data))| (character_id, session, data)),
+            )
             .collect::<Vec<_>>();
 
+        if let Err(e) = self.send_save(CharacterUpdaterEvent::BatchUpdate(updates)) {
+            error!(?e, "Could not send stats updates");
+        }
+        for character_id in logged_out {
+            self.record_logout(character_id);
+        }
+    }
+
+    /// Runs [`maintenance::maintenance`] on the persistence thread, so that the
+    /// game doesn't wait for it. The summary is sent to `entity` if given.
+    pub fn run_maintenance(&mut self, entity: Option<Entity>) {
         if let Err(e) = self
             .update_tx
             .as_ref()
             .unwrap()
-            .send(CharacterUpdaterEvent::BatchUpdate(updates))
+            .send(CharacterUpdaterEvent::Maintenance { entity })
         {
-            error!(?e, "Could not send stats updates");
+            error!(?e, "Could not send database maintenance request");
         }
     }
 
@@ -391,30 +794,124 @@ impl CharacterUpdater {
     pub fn messages(&self) -> TryIter<CharacterLoaderResponse> { self.response_rx.try_iter() }
 }
 
+/// Saves the characters in transactions of up to `batch_size` characters each,
+/// so that a large batch doesn't hold the database lock for too long.
 fn execute_batch_update(
-    updates: Vec<(CharacterId, CharacterUpdateData)>,
+    updates: Vec<(CharacterId, SessionId, CharacterUpdateData)>,
+    batch_size: usize,
     connection: &mut VelorenConnection,
 ) -> Result<(), PersistenceError> {
-    let mut transaction = connection.connection.transaction()?;
-    transaction.set_drop_behavior(DropBehavior::Rollback);
-    trace!("Transaction started for character batch update");
-    updates.into_iter().try_for_each(
-        |(character_id, (stats, inventory, pets, waypoint, active_abilities, map_marker))| {
-            super::character::update(
+    let mut updates = updates.into_iter().peekable();
+    while updates.peek().is_some() {
+        let mut transaction = connection.connection.transaction()?;
+        transaction.set_drop_behavior(DropBehavior::Rollback);
+        trace!("Transaction started for character batch update");
+        updates.by_ref().take(batch_size.max(1)).try_for_each(
+            |(
                 character_id,
-                stats,
-                inventory,
-                pets,
-                waypoint,
-                active_abilities,
-                map_marker,
-                &mut transaction,
-            )
-        },
-    )?;
+                session,
+                (
+                    stats,
+                    inventory,
+                    pets,
+                    waypoint,
+                    position,
+                    active_abilities,
+                    map_marker,
+                    hotbar,
+                    settings,
+                    vitals,
+                    buffs,
+                    lantern_fuel,
+                    playtime,
+                ),
+            )| {
+                let stats_write = super::character::update(
+                    character_id,
+                    session,
+                    stats,
+                    inventory,
+                    pets,
+                    waypoint,
+                    position,
+                    active_abilities,
+                    map_marker,
+                    hotbar,
+                    settings,
+                    vitals,
+                    buffs,
+                    lantern_fuel,
+                    playtime,
+                    &mut transaction,
+                )?;
+                if stats_write == StatsWrite::Rejected {
+                    warn!(
+                        "Kept the exp of character ID {} as a save of an older session would have \
+                         lowered it",
+                        character_id
+                    );
+                }
+                Ok::<_, PersistenceError>(())
+            },
+        )?;
+        transaction.commit()?;
+
+        trace!("Commit for character batch update completed");
+    }
+    Ok(())
+}
+
+/// Takes the whole seconds a character played since its playtime was last
+/// accounted for, so that periodic updates persist the playtime of characters
+/// that are still in the game.
+fn take_playtime(playtime_since: &mut HashMap<CharacterId, Instant>, id: CharacterId) -> u64 {
+    playtime_since.get_mut(&id).map_or(0, |since| {
+        let played = since.elapsed().as_secs();
+        *since += Duration::from_secs(played);
+        played
+    })
+}
+
+fn execute_record_login(
+    character_id: CharacterId,
+    session: SessionId,
+    connection: &mut VelorenConnection,
+) -> Result<(), PersistenceError> {
+    let mut transaction = connection.connection.transaction()?;
+    super::character::record_login(character_id, session, &mut transaction)?;
+    transaction.commit()?;
+    Ok(())
+}
+
+fn execute_record_logout(
+    character_id: CharacterId,
+    connection: &mut VelorenConnection,
+) -> Result<(), PersistenceError> {
+    let mut transaction = connection.connection.transaction()?;
+    super::character::record_logout(character_id, &mut transaction)?;
     transaction.commit()?;
+    Ok(())
+}
+
+fn execute_record_death(
+    character_id: CharacterId,
+    connection: &mut VelorenConnection,
+) -> Result<(), PersistenceError> {
+    let mut transaction = connection.connection.transaction()?;
+    super::character::record_death(character_id, &mut transaction)?;
+    transaction.commit()?;
+    Ok(())
+}
 
-    trace!("Commit for character batch update completed");
+fn execute_update_body(
+    requesting_player_uuid: &str,
+    character_id: CharacterId,
+    body: &comp::Body,
+    connection: &mut VelorenConnection,
+) -> Result<(), PersistenceError> {
+    let mut transaction = connection.connection.transaction()?;
+    super::character::update_body(requesting_player_uuid, character_id, body, &mut transaction)?;
+    transaction.commit()?;
     Ok(())
 }
 
@@ -423,17 +920,29 @@ fn execute_character_create(
     alias: String,
     requesting_player_uuid: &str,
     persisted_components: PersistedComponents,
+    max_characters: usize,
+    alias_uniqueness: AliasUniqueness,
     connection: &mut VelorenConnection,
 ) -> Result<CharacterLoaderResponse, PersistenceError> {
-    let mut transaction = connection.connection.transaction()?;
-    let result =
-        CharacterLoaderResponseKind::CharacterCreation(super::character::create_character(
+    // The transaction is rolled back when dropped, so failed attempts leave
+    // nothing behind
+    let result = retry_if_locked(|| {
+        let mut transaction = connection.connection.transaction()?;
+        let created = super::character::create_character(
             requesting_player_uuid,
             &alias,
-            persisted_components,
+            persisted_components.clone(),
+            max_characters,
+            alias_uniqueness,
             &mut transaction,
-        ));
-    check_response(entity, transaction, result)
+        )?;
+        transaction.commit()?;
+        Ok(created)
+    });
+    Ok(CharacterLoaderResponse {
+        entity,
+        result: CharacterLoaderResponseKind::CharacterCreation(result),
+    })
 }
 
 fn execute_character_edit(
@@ -442,6 +951,7 @@ fn execute_character_edit(
     alias: String,
     requesting_player_uuid: &str,
     editable_components: EditableComponents,
+    alias_uniqueness: AliasUniqueness,
     connection: &mut VelorenConnection,
 ) -> Result<CharacterLoaderResponse, PersistenceError> {
     let mut transaction = connection.connection.transaction()?;
@@ -451,6 +961,7 @@ fn execute_character_edit(
         character_id,
         requesting_player_uuid,
         &alias,
+        alias_uniqueness,
     ));
     check_response(entity, transaction, result)
 }
@@ -462,9 +973,29 @@ fn execute_character_delete(
     connection: &mut VelorenConnection,
 ) -> Result<CharacterLoaderResponse, PersistenceError> {
     let mut transaction = connection.connection.transaction()?;
-    let result = CharacterLoaderResponseKind::CharacterList(super::character::delete_character(
+    let result = CharacterLoaderResponseKind::CharacterDeletion(
+        super::character::delete_character(requesting_player_uuid, character_id, &mut transaction)
+            .map(|()| character_id),
+    );
+    check_response(entity, transaction, result)
+}
+
+fn execute_character_restore(
+    entity: Entity,
+    requesting_player_uuid: &str,
+    character_id: CharacterId,
+    retention: Duration,
+    max_characters: usize,
+    alias_uniqueness: AliasUniqueness,
+    connection: &mut VelorenConnection,
+) -> Result<CharacterLoaderResponse, PersistenceError> {
+    let mut transaction = connection.connection.transaction()?;
+    let result = CharacterLoaderResponseKind::CharacterList(super::character::restore_character(
         requesting_player_uuid,
         character_id,
+        retention,
+        max_characters,
+        alias_uniqueness,
         &mut transaction,
     ));
     check_response(entity, transaction, result)
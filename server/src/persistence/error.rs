@@ -2,6 +2,7 @@
 
 extern crate rusqlite;
 
+use common::character::CharacterId;
 use std::fmt;
 
 #[derive(Debug)]
@@ -9,35 +10,96 @@ pub enum PersistenceError {
     // An invalid asset was returned from the database
     AssetError(String),
     // The player has already reached the max character limit
-    CharacterLimitReached,
+    CharacterLimitReached(usize),
     // An error occurred while establish a db connection
     DatabaseConnectionError(rusqlite::Error),
     // An error occurred when performing a database action
     DatabaseError(rusqlite::Error),
+    // The database is locked by another connection, trying again later may work
+    DatabaseBusy,
     // Unable to load body or stats for a character
     CharacterDataError,
     SerializationError(serde_json::Error),
     ConversionError(String),
     OtherError(String),
+    // The database was migrated by a newer version of the server
+    SchemaTooNew { expected: i32, found: i32 },
+    // A character export file that can't be imported
+    InvalidImport(String),
+    // The character doesn't exist, or has been deleted
+    CharacterNotFound(CharacterId),
+    // The character belongs to another player
+    CharacterNotOwned(CharacterId),
+    // The alias can't be used for a character
+    InvalidAlias(String),
+    // Another character already has the alias
+    AliasTaken(String),
+    // The body has features that don't exist
+    InvalidBody(String),
+    // A signed transfer bundle or receipt that can't be used
+    InvalidTransfer(String),
+    // The character is being transferred to another server
+    CharacterTransferLocked(CharacterId),
+    // The character settings are larger than the limit, in bytes
+    CharacterSettingsTooLarge(usize),
 }
 
 impl fmt::Display for PersistenceError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{}", match self {
             Self::AssetError(error) => error.to_string(),
-            Self::CharacterLimitReached => String::from("Character limit exceeded"),
+            Self::CharacterLimitReached(limit) => {
+                format!("You can't have more than {} characters", limit)
+            },
             Self::DatabaseError(error) => error.to_string(),
             Self::DatabaseConnectionError(error) => error.to_string(),
+            Self::DatabaseBusy => String::from("The database is busy, please try again later"),
             Self::CharacterDataError => String::from("Error while loading character data"),
             Self::SerializationError(error) => error.to_string(),
             Self::ConversionError(error) => error.to_string(),
             Self::OtherError(error) => error.to_string(),
+            Self::SchemaTooNew { expected, found } => format!(
+                "Database schema version {} is newer than the latest version known to this server \
+                 ({}), refusing to start. Update the server or restore a backup of the database",
+                found, expected
+            ),
+            Self::InvalidImport(error) => format!("Invalid character export: {}", error),
+            Self::CharacterNotFound(character_id) => {
+                format!("Character {} does not exist", character_id)
+            },
+            Self::CharacterNotOwned(character_id) => {
+                format!("Character {} does not belong to you", character_id)
+            },
+            Self::InvalidAlias(error) => format!("Invalid alias: {}", error),
+            Self::AliasTaken(alias) => format!("There already is a character named {}", alias),
+            Self::InvalidBody(error) => format!("Invalid body: {}", error),
+            Self::InvalidTransfer(error) => format!("Invalid character transfer: {}", error),
+            Self::CharacterTransferLocked(character_id) => format!(
+                "Character {} is being transferred to another server",
+                character_id
+            ),
+            Self::CharacterSettingsTooLarge(size) => format!(
+                "Character settings of {} bytes are larger than the limit of {} bytes",
+                size,
+                super::character_settings::MAX_SETTINGS_SIZE
+            ),
         })
     }
 }
 
 impl From<rusqlite::Error> for PersistenceError {
-    fn from(error: rusqlite::Error) -> PersistenceError { PersistenceError::DatabaseError(error) }
+    fn from(error: rusqlite::Error) -> PersistenceError {
+        match error {
+            rusqlite::Error::SqliteFailure(
+                rusqlite::ffi::Error {
+                    code: rusqlite::ErrorCode::DatabaseBusy | rusqlite::ErrorCode::DatabaseLocked,
+                    ..
+                },
+                _,
+            ) => PersistenceError::DatabaseBusy,
+            error => PersistenceError::DatabaseError(error),
+        }
+    }
 }
 
 impl From<serde_json::Error> for PersistenceError {
@@ -64,6 +64,8 @@ generic_body_from_impl!(comp::bird_medium::Body);
 #[derive(Serialize, Deserialize)]
 pub struct CharacterPosition {
     pub waypoint: Option<Vec3<f32>>,
+    #[serde(default)]
+    pub position: Option<Vec3<f32>>,
     pub map_marker: Option<Vec2<i32>>,
 }
 
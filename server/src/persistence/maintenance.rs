@@ -0,0 +1,97 @@
+//! Periodic upkeep of the database, which can be requested by admins while the
+//! server is running
+
+use crate::persistence::{character, error::PersistenceError};
+use rusqlite::{Connection, NO_PARAMS};
+use std::{fmt, time::Duration};
+
+/// What was cleaned up by [`maintenance`]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MaintenanceSummary {
+    /// Deleted characters which can no longer be restored and were removed
+    pub characters_purged: usize,
+    /// How much smaller the database file got
+    pub bytes_reclaimed: u64,
+}
+
+impl fmt::Display for MaintenanceSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Database maintenance purged {} deleted characters and reclaimed {} KiB",
+            self.characters_purged,
+            self.bytes_reclaimed / 1024
+        )
+    }
+}
+
+/// Permanently removes characters deleted more than `retention` ago, then
+/// rebuilds the database file with `VACUUM` and refreshes the statistics used
+/// by the query planner with `ANALYZE`.
+///
+/// `VACUUM` needs the database to itself and can take a while on a large
+/// database, so this must only be run on the persistence thread.
+pub fn maintenance(
+    retention: Duration,
+    connection: &mut Connection,
+) -> Result<MaintenanceSummary, PersistenceError> {
+    let size_before = database_size(connection)?;
+
+    let mut transaction = connection.transaction()?;
+    let characters_purged = character::purge_deleted_characters(retention, &mut transaction)?;
+    transaction.commit()?;
+
+    // Can't be run in a transaction
+    connection.execute_batch("VACUUM main; ANALYZE main;")?;
+
+    Ok(MaintenanceSummary {
+        characters_purged,
+        bytes_reclaimed: size_before.saturating_sub(database_size(connection)?),
+    })
+}
+
+/// Size of the database file in bytes
+fn database_size(connection: &Connection) -> Result<u64, PersistenceError> {
+    let page_count: i64 =
+        connection.query_row("PRAGMA main.page_count", NO_PARAMS, |row| row.get(0))?;
+    let page_size: i64 =
+        connection.query_row("PRAGMA main.page_size", NO_PARAMS, |row| row.get(0))?;
+    Ok(u64::try_from(page_count * page_size).unwrap_or(0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::persistence::character::tests::{create_test_character, migrated_connection};
+    use common::comp;
+
+    #[test]
+    fn maintenance_purges_expired_deleted_characters() {
+        let mut connection = migrated_connection();
+        let mut transaction = connection.transaction().unwrap();
+        let deleted = create_test_character(&mut transaction, comp::humanoid::Body::random());
+        let kept = create_test_character(&mut transaction, comp::humanoid::Body::random());
+        character::delete_character("player", deleted, &mut transaction).unwrap();
+        transaction.commit().unwrap();
+
+        let summary = maintenance(Duration::ZERO, &mut connection).unwrap();
+        assert_eq!(summary.characters_purged, 1);
+
+        let remaining: Vec<i64> = connection
+            .prepare("SELECT character_id FROM character")
+            .unwrap()
+            .query_map(NO_PARAMS, |row| row.get(0))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(remaining, vec![kept]);
+
+        // Nothing is left to purge the second time
+        assert_eq!(
+            maintenance(Duration::ZERO, &mut connection)
+                .unwrap()
+                .characters_purged,
+            0
+        );
+    }
+}
@@ -1,17 +1,33 @@
 //! DB operations and schema migrations
 
+pub mod audit_log;
+pub mod buffs;
 pub(in crate::persistence) mod character;
 pub mod character_loader;
+pub mod character_settings;
+mod character_transfer;
 pub mod character_updater;
 mod diesel_to_rusqlite;
 pub mod error;
 mod json_models;
+pub mod maintenance;
 mod models;
-
-use crate::persistence::character_updater::PetPersistenceData;
-use common::comp;
+mod retry;
+pub mod signed_transfer;
+
+use crate::{
+    persistence::{
+        audit_log::{AuditLogEntry, AuditLogFilter},
+        character_updater::{PetPersistenceData, Vitals},
+        error::PersistenceError,
+        signed_transfer::{SignedMessage, TransferKey},
+    },
+    settings::{AliasUniqueness, TransferSettings},
+};
+use censor::Censor;
+use common::{character::CharacterId, comp};
 use refinery::Report;
-use rusqlite::{Connection, OpenFlags};
+use rusqlite::{Connection, OpenFlags, NO_PARAMS};
 use std::{
     fs,
     ops::Deref,
@@ -19,19 +35,33 @@ use std::{
     sync::{Arc, RwLock},
     time::Duration,
 };
-use tracing::info;
+use tracing::{error, info};
 
 /// A struct of the components that are persisted to the DB for each character
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct PersistedComponents {
     pub body: comp::Body,
     pub stats: comp::Stats,
     pub skill_set: comp::SkillSet,
     pub inventory: comp::Inventory,
     pub waypoint: Option<comp::Waypoint>,
+    /// Where the character was when it was last persisted
+    pub position: Option<comp::Pos>,
     pub pets: Vec<PetPersistenceData>,
     pub active_abilities: comp::ActiveAbilities,
     pub map_marker: Option<comp::MapMarker>,
+    pub hotbar: comp::Hotbar,
+    pub settings: comp::CharacterSettings,
+    /// Health and energy of the character when it was last persisted
+    pub vitals: Option<Vitals>,
+    /// Buffs that didn't run out since the character was last persisted
+    pub buffs: Vec<comp::Buff>,
+    /// Fuel left in the lantern of the character, see [`comp::LanternFuel`].
+    /// Unknown for characters that were never saved with a lantern, whose
+    /// lantern starts out full.
+    pub lantern_fuel: Option<f32>,
+    /// Whether the character is deleted when it dies
+    pub hardcore: bool,
 }
 
 pub type EditableComponents = (comp::Body,);
@@ -154,8 +184,76 @@ impl ToString for SqlLogMode {
     }
 }
 
-/// Runs any pending database migrations. This is executed during server startup
-pub fn run_migrations(settings: &DatabaseSettings) {
+/// The latest schema version known to this server, i.e. the version of the
+/// newest embedded migration
+fn expected_schema_version() -> i32 {
+    embedded::migrations::runner()
+        .get_migrations()
+        .iter()
+        .map(|migration| migration.version())
+        .max()
+        .unwrap_or(0)
+}
+
+/// The version of the newest migration applied to the database, if any. This
+/// is `None` for new databases and for databases which were never migrated by
+/// refinery.
+fn applied_schema_version(connection: &Connection) -> Result<Option<i32>, PersistenceError> {
+    #[rustfmt::skip]
+    let mut stmt = connection.prepare("
+        SELECT  COUNT(1)
+        FROM    sqlite_master
+        WHERE   type='table'
+        AND     name='refinery_schema_history';
+    ",
+    )?;
+
+    let history_table_exists = stmt.query_row(NO_PARAMS, |row| {
+        let row_count: i32 = row.get(0)?;
+        Ok(row_count > 0)
+    })?;
+    drop(stmt);
+
+    if !history_table_exists {
+        return Ok(None);
+    }
+
+    let mut stmt = connection.prepare("SELECT MAX(version) FROM refinery_schema_history")?;
+    let version = stmt.query_row(NO_PARAMS, |row| row.get(0))?;
+    Ok(version)
+}
+
+/// Checks that the database isn't ahead of this server, e.g. because it was
+/// opened by a newer version before a downgrade. A database that is behind is
+/// fine since [`run_migrations`] will bring it up to date.
+///
+/// This doesn't require any other part of the server to be set up, so it can
+/// be called before binding any sockets.
+pub fn check_schema_version(settings: &DatabaseSettings) -> Result<(), PersistenceError> {
+    // Read-write so that a missing database file gets created rather than
+    // failing the check
+    let conn = establish_connection(settings, ConnectionMode::ReadWrite);
+    let expected = expected_schema_version();
+
+    match applied_schema_version(&conn)? {
+        Some(found) if found > expected => Err(PersistenceError::SchemaTooNew { expected, found }),
+        found => {
+            info!(
+                "Database schema version is {}, latest known version is {}",
+                found.unwrap_or(0),
+                expected
+            );
+            Ok(())
+        },
+    }
+}
+
+/// Runs any pending database migrations. This is executed during server
+/// startup, and fails if the database schema is newer than this server (see
+/// [`check_schema_version`]).
+pub fn run_migrations(settings: &DatabaseSettings) -> Result<(), PersistenceError> {
+    check_schema_version(settings)?;
+
     let mut conn = establish_connection(settings, ConnectionMode::ReadWrite);
 
     diesel_to_rusqlite::migrate_from_diesel(&mut conn)
@@ -170,6 +268,7 @@ pub fn run_migrations(settings: &DatabaseSettings) {
 
     let applied_migrations = report.applied_migrations().len();
     info!("Applied {} database migrations", applied_migrations);
+    Ok(())
 }
 
 /// Runs after the migrations. In some cases, it can reclaim a significant
@@ -184,6 +283,191 @@ pub fn vacuum_database(settings: &DatabaseSettings) {
     info!("Database vacuumed");
 }
 
+/// Permanently removes characters whose deletion is older than `retention`,
+/// after which they can no longer be restored. This is executed during server
+/// startup
+pub fn purge_deleted_characters(settings: &DatabaseSettings, retention: Duration) {
+    let mut conn = establish_connection(settings, ConnectionMode::ReadWrite);
+
+    let mut transaction = conn
+        .connection
+        .transaction()
+        .expect("Failed to start transaction for purging deleted characters");
+    let purged = character::purge_deleted_characters(retention, &mut transaction)
+        .expect("Purging deleted characters failed, server startup aborted");
+    transaction
+        .commit()
+        .expect("Failed to commit purge of deleted characters");
+
+    info!("Purged {} deleted characters", purged);
+}
+
+/// Unlocks characters whose transfer to another server expired before it was
+/// finalized. This is executed during server startup, characters are unlocked
+/// as soon as their transfer expires either way.
+pub fn roll_back_expired_transfers(settings: &DatabaseSettings) {
+    let mut conn = establish_connection(settings, ConnectionMode::ReadWrite);
+
+    let result = conn
+        .connection
+        .transaction()
+        .map_err(PersistenceError::from)
+        .and_then(|mut transaction| {
+            let rolled_back = signed_transfer::roll_back_expired_transfers(
+                chrono::Utc::now().timestamp(),
+                &mut transaction,
+            )?;
+            transaction.commit()?;
+            Ok(rolled_back)
+        });
+    match result {
+        Ok(0) => {},
+        Ok(rolled_back) => info!("Rolled back {} expired character transfers", rolled_back),
+        Err(err) => error!(?err, "Failed to roll back expired character transfers"),
+    }
+}
+
+/// Records that no character is in the game, as logouts aren't recorded when
+/// the server stops without saving its players. This is executed during server
+/// startup.
+pub fn clear_in_game_characters(settings: &DatabaseSettings) {
+    let mut conn = establish_connection(settings, ConnectionMode::ReadWrite);
+
+    let result = conn
+        .connection
+        .transaction()
+        .map_err(PersistenceError::from)
+        .and_then(|mut transaction| {
+            let cleared = character::clear_in_game(&mut transaction)?;
+            transaction.commit()?;
+            Ok(cleared)
+        });
+    match result {
+        Ok(0) => {},
+        Ok(cleared) => info!("Recorded missing logouts of {} characters", cleared),
+        Err(err) => error!(?err, "Failed to record missing logouts of characters"),
+    }
+}
+
+/// Exports a character of the player to a JSON value which can be imported
+/// into another database with [`import_character`]
+pub fn export_character(
+    settings: &DatabaseSettings,
+    player_uuid: &str,
+    character_id: CharacterId,
+) -> Result<serde_json::Value, PersistenceError> {
+    let conn = establish_connection(settings, ConnectionMode::ReadOnly);
+    character_transfer::export_character(player_uuid, character_id, &conn.connection)
+}
+
+/// Creates a new character for the player from a JSON value produced by
+/// [`export_character`], returning the id of the new character
+pub fn import_character(
+    settings: &DatabaseSettings,
+    player_uuid: &str,
+    json: serde_json::Value,
+    censor: &Censor,
+    max_characters: usize,
+    alias_uniqueness: AliasUniqueness,
+) -> Result<CharacterId, PersistenceError> {
+    let mut conn = establish_connection(settings, ConnectionMode::ReadWrite);
+
+    let mut transaction = conn.connection.transaction()?;
+    let character_id = character_transfer::import_character(
+        player_uuid,
+        json,
+        censor,
+        max_characters,
+        alias_uniqueness,
+        &mut transaction,
+    )?;
+    transaction.commit()?;
+
+    Ok(character_id)
+}
+
+/// Exports a character of the player into a signed bundle for the
+/// `destination` server and locks it, see [`signed_transfer::transfer_out`]
+pub fn transfer_character_out(
+    settings: &DatabaseSettings,
+    key: &TransferKey,
+    transfer_settings: &TransferSettings,
+    destination: &str,
+    player_uuid: &str,
+    character_id: CharacterId,
+) -> Result<SignedMessage, PersistenceError> {
+    let mut conn = establish_connection(settings, ConnectionMode::ReadWrite);
+
+    let mut transaction = conn.connection.transaction()?;
+    let bundle = signed_transfer::transfer_out(
+        key,
+        transfer_settings,
+        destination,
+        player_uuid,
+        character_id,
+        chrono::Utc::now().timestamp(),
+        &mut transaction,
+    )?;
+    transaction.commit()?;
+
+    Ok(bundle)
+}
+
+/// Imports a character from a bundle of another server, returning the receipt
+/// for it, see [`signed_transfer::accept_transfer`]
+pub fn accept_character_transfer(
+    settings: &DatabaseSettings,
+    key: &TransferKey,
+    transfer_settings: &TransferSettings,
+    censor: &Censor,
+    max_characters: usize,
+    alias_uniqueness: AliasUniqueness,
+    bundle: &SignedMessage,
+) -> Result<SignedMessage, PersistenceError> {
+    let mut conn = establish_connection(settings, ConnectionMode::ReadWrite);
+
+    let mut transaction = conn.connection.transaction()?;
+    let receipt = signed_transfer::accept_transfer(
+        key,
+        transfer_settings,
+        censor,
+        max_characters,
+        alias_uniqueness,
+        bundle,
+        chrono::Utc::now().timestamp(),
+        &mut transaction,
+    )?;
+    transaction.commit()?;
+
+    Ok(receipt)
+}
+
+/// Purges a character transferred to another server with the receipt of the
+/// destination, see [`signed_transfer::finalize_transfer`]
+pub fn finalize_character_transfer(
+    settings: &DatabaseSettings,
+    transfer_settings: &TransferSettings,
+    receipt: &SignedMessage,
+) -> Result<CharacterId, PersistenceError> {
+    let mut conn = establish_connection(settings, ConnectionMode::ReadWrite);
+
+    let mut transaction = conn.connection.transaction()?;
+    let character_id =
+        signed_transfer::finalize_transfer(transfer_settings, receipt, &mut transaction)?;
+    transaction.commit()?;
+
+    Ok(character_id)
+}
+
+/// Loads the entries of the audit log matching the filter, oldest first
+pub fn query_audit_log(
+    settings: &DatabaseSettings,
+    filter: &AuditLogFilter,
+) -> Result<Vec<AuditLogEntry>, PersistenceError> {
+    let conn = establish_connection(settings, ConnectionMode::ReadOnly);
+    audit_log::query_audit_log(&conn.connection, filter)
+}
+
 // These callbacks use info logging because they are never enabled by default,
 // only when explicitly turned on via CLI arguments or interactive CLI commands.
 // Setting them to anything other than info would remove the ability to get SQL
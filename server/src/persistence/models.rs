@@ -34,6 +34,7 @@ pub struct Pet {
     pub name: String,
     pub body_variant: String,
     pub body_data: String,
+    pub stance: String,
 }
 
 pub struct AbilitySets {
@@ -0,0 +1,162 @@
+//! Retrying of database operations that failed because another connection was
+//! holding a lock on the database for longer than the busy timeout
+
+use super::error::PersistenceError;
+use rusqlite::ErrorCode;
+use std::{thread, time::Duration};
+use tracing::warn;
+
+/// How many times an operation is attempted before its error is returned
+pub(crate) const MAX_ATTEMPTS: u32 = 4;
+/// The backoff before the second attempt, it doubles for each attempt after
+const INITIAL_BACKOFF: Duration = Duration::from_millis(50);
+
+/// Whether an error is caused by a lock held by another connection, and may go
+/// away by just trying again
+fn is_transient(error: &PersistenceError) -> bool {
+    match error {
+        PersistenceError::DatabaseBusy => true,
+        PersistenceError::DatabaseError(rusqlite::Error::SqliteFailure(error, _))
+        | PersistenceError::DatabaseConnectionError(rusqlite::Error::SqliteFailure(error, _)) => {
+            matches!(
+                error.code,
+                ErrorCode::DatabaseBusy | ErrorCode::DatabaseLocked
+            )
+        },
+        _ => false,
+    }
+}
+
+/// Runs `f` until it succeeds, fails with an error which isn't transient or
+/// was attempted [`MAX_ATTEMPTS`] times, with an exponential backoff between
+/// attempts. `f` needs to undo its changes when it fails (e.g. by using a
+/// transaction), so that it can safely be run again.
+pub(crate) fn retry_if_locked<T>(
+    mut f: impl FnMut() -> Result<T, PersistenceError>,
+) -> Result<T, PersistenceError> {
+    let mut backoff = INITIAL_BACKOFF;
+    let mut attempt = 1;
+    loop {
+        match f() {
+            Err(error) if attempt < MAX_ATTEMPTS && is_transient(&error) => {
+                warn!(
+                    ?error,
+                    "Database is locked, retrying in {}ms (attempt {} of {})",
+                    backoff.as_millis(),
+                    attempt,
+                    MAX_ATTEMPTS
+                );
+                thread::sleep(backoff);
+                backoff *= 2;
+                attempt += 1;
+            },
+            result => return result,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rusqlite::{Connection, NO_PARAMS};
+    use std::{fs, path::PathBuf};
+
+    /// A file database, which a second connection holds an exclusive lock on
+    struct LockedDb {
+        path: PathBuf,
+        holder: Option<Connection>,
+        connection: Connection,
+    }
+
+    impl LockedDb {
+        fn new(name: &str) -> Self {
+            let path = std::env::temp_dir().join(format!(
+                "veloren-retry-{}-{}.sqlite",
+                name,
+                std::process::id()
+            ));
+            let _ = fs::remove_file(&path);
+
+            let holder = Connection::open(&path).unwrap();
+            holder
+                .execute_batch("CREATE TABLE test (value INTEGER); BEGIN EXCLUSIVE;")
+                .unwrap();
+            let connection = Connection::open(&path).unwrap();
+            // Fail right away instead of waiting for the lock to be released
+            connection.busy_timeout(Duration::from_millis(0)).unwrap();
+
+            Self {
+                path,
+                holder: Some(holder),
+                connection,
+            }
+        }
+
+        fn insert(&self) -> Result<usize, PersistenceError> {
+            Ok(self
+                .connection
+                .execute("INSERT INTO test (value) VALUES (1)", NO_PARAMS)?)
+        }
+
+        fn release(&mut self) {
+            if let Some(holder) = self.holder.take() {
+                holder.execute_batch("COMMIT;").unwrap();
+            }
+        }
+    }
+
+    impl Drop for LockedDb {
+        fn drop(&mut self) { let _ = fs::remove_file(&self.path); }
+    }
+
+    #[test]
+    fn retries_until_lock_is_released() {
+        let mut db = LockedDb::new("released");
+        let mut attempts = 0;
+
+        let result = retry_if_locked(|| {
+            attempts += 1;
+            let result = db.insert();
+            db.release();
+            result
+        });
+
+        assert_eq!(result.unwrap(), 1);
+        assert_eq!(attempts, 2);
+    }
+
+    #[test]
+    fn gives_up_after_max_attempts() {
+        let db = LockedDb::new("held");
+        let mut attempts = 0;
+
+        let result = retry_if_locked(|| {
+            attempts += 1;
+            db.insert()
+        });
+
+        assert!(
+            matches!(result, Err(PersistenceError::DatabaseBusy)),
+            "{:?}",
+            result
+        );
+        assert_eq!(attempts, MAX_ATTEMPTS);
+    }
+
+    #[test]
+    fn other_errors_are_not_retried() {
+        let mut db = LockedDb::new("other");
+        db.release();
+        let mut attempts = 0;
+
+        let result = retry_if_locked(|| {
+            attempts += 1;
+            Ok(db
+                .connection
+                .execute("INSERT INTO missing (value) VALUES (1)", NO_PARAMS)?)
+        });
+
+        assert!(matches!(result, Err(PersistenceError::DatabaseError(_))));
+        assert_eq!(attempts, 1);
+    }
+}
@@ -0,0 +1,882 @@
+//! Signed transfers of characters between servers that trust each other, so
+//! that communities running several servers can move characters without
+//! trusting plain export files.
+//!
+//! 1. The source server exports the character into a bundle signed with its
+//!    [`TransferKey`], and locks the character so that it can't be played while
+//!    it is transferred ([`transfer_out`]). Characters that are in the game
+//!    can't be transferred.
+//! 2. The destination verifies the bundle with the public key of the source
+//!    from its [settings](crate::settings::TransferSettings), checks the
+//!    character against its own rules, imports it and returns a signed receipt
+//!    ([`accept_transfer`]). Accepting a bundle again returns the same receipt
+//!    without importing the character again, so a lost receipt is recovered by
+//!    accepting the bundle once more.
+//! 3. The source verifies the receipt and purges the character
+//!    ([`finalize_transfer`]).
+//!
+//! Bundles expire with the lock of their character. Destinations refuse
+//! expired bundles and the source unlocks the character again
+//! ([`roll_back_expired_transfers`]), so that a character only exists on both
+//! servers if the receipt of a bundle that was accepted in time arrives late.
+//! Such receipts are still finalized. A character is only transferred again
+//! once its earlier bundles can't be accepted anymore, even by destinations
+//! whose clock is behind, so that it can't be imported by two servers.
+
+use crate::{
+    persistence::{
+        audit_log::{self, AuditEventKind},
+        character::{is_in_game, purge_character},
+        character_transfer::{
+            create_imported_character, decode_export, export_character, ExportedCharacter,
+        },
+        error::PersistenceError,
+    },
+    settings::{AliasUniqueness, TransferSettings},
+};
+use censor::Censor;
+use common::{
+    character::CharacterId,
+    comp::item::{Item, ItemDefinitionId},
+};
+use ring::{
+    rand::SystemRandom,
+    signature::{self, Ed25519KeyPair, KeyPair, UnparsedPublicKey},
+};
+use rusqlite::{Connection, OptionalExtension, ToSql, Transaction};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{collections::BTreeSet, fmt::Write, fs, path::Path};
+use tracing::{info, warn};
+
+/// File in the data directory of the server with its [`TransferKey`]
+const KEY_FILE: &str = "transfer_key.pk8";
+
+// Directions and states of transfers in the `character_transfer` table
+const OUT: &str = "Out";
+const IN: &str = "In";
+const PENDING: &str = "Pending";
+const ROLLED_BACK: &str = "RolledBack";
+const FINALIZED: &str = "Finalized";
+const ACCEPTED: &str = "Accepted";
+
+/// Seconds after their expiry during which bundles might still be accepted by
+/// destinations whose clock is behind the one of the source
+const MAX_CLOCK_SKEW: i64 = 60 * 60;
+
+fn invalid(error: impl Into<String>) -> PersistenceError {
+    PersistenceError::InvalidTransfer(error.into())
+}
+
+/// The key a server signs its transfer bundles and receipts with
+pub struct TransferKey(Ed25519KeyPair);
+
+impl TransferKey {
+    /// Loads the key of the server from its data directory, generating one if
+    /// there is none yet
+    pub fn load_or_generate(data_dir: &Path) -> Result<Self, PersistenceError> {
+        let path = data_dir.join(KEY_FILE);
+        let pkcs8 = if path.exists() {
+            fs::read(&path).map_err(|err| PersistenceError::OtherError(err.to_string()))?
+        } else {
+            let pkcs8 = generate_pkcs8()?;
+            fs::write(&path, &pkcs8)
+                .map_err(|err| PersistenceError::OtherError(err.to_string()))?;
+            info!("Generated a new transfer key at {}", path.display());
+            pkcs8
+        };
+        Self::from_pkcs8(&pkcs8)
+    }
+
+    fn from_pkcs8(pkcs8: &[u8]) -> Result<Self, PersistenceError> {
+        Ed25519KeyPair::from_pkcs8(pkcs8)
+            .map(Self)
+            .map_err(|err| PersistenceError::OtherError(format!("Invalid transfer key: {}", err)))
+    }
+
+    /// The public key other servers need to trust this one, hex encoded
+    pub fn public_key(&self) -> String { to_hex(self.0.public_key().as_ref()) }
+
+    fn sign(
+        &self,
+        signer: &str,
+        payload: &impl Serialize,
+    ) -> Result<SignedMessage, PersistenceError> {
+        let payload = serde_json::to_string(payload)?;
+        Ok(SignedMessage {
+            signer: signer.to_owned(),
+            signature: to_hex(self.0.sign(payload.as_bytes()).as_ref()),
+            payload,
+        })
+    }
+}
+
+fn generate_pkcs8() -> Result<Vec<u8>, PersistenceError> {
+    Ed25519KeyPair::generate_pkcs8(&SystemRandom::new())
+        .map(|pkcs8| pkcs8.as_ref().to_vec())
+        .map_err(|_| PersistenceError::OtherError("Failed to generate a transfer key".to_owned()))
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().fold(String::new(), |mut hex, byte| {
+        let _ = write!(hex, "{:02x}", byte);
+        hex
+    })
+}
+
+fn from_hex(hex: &str) -> Option<Vec<u8>> {
+    if hex.len() % 2 != 0 {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+/// A transfer bundle or receipt, signed by the server named `signer`
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SignedMessage {
+    signer: String,
+    /// JSON of the signed data, kept as text so that the signature is checked
+    /// against exactly the bytes that were signed
+    payload: String,
+    /// Hex encoded Ed25519 signature of the payload
+    signature: String,
+}
+
+impl SignedMessage {
+    /// Decodes the message if it was signed by its signer, which has to be
+    /// one of the trusted servers
+    fn verify<T: DeserializeOwned>(
+        &self,
+        settings: &TransferSettings,
+    ) -> Result<T, PersistenceError> {
+        let public_key = settings
+            .trusted_servers
+            .get(&self.signer)
+            .ok_or_else(|| invalid(format!("{} is not a trusted server", self.signer)))?;
+        let public_key = from_hex(public_key).ok_or_else(|| {
+            invalid(format!(
+                "The key of {} in the settings isn't valid hex",
+                self.signer
+            ))
+        })?;
+        let signature =
+            from_hex(&self.signature).ok_or_else(|| invalid("The signature isn't valid hex"))?;
+        UnparsedPublicKey::new(&signature::ED25519, public_key)
+            .verify(self.payload.as_bytes(), &signature)
+            .map_err(|_| {
+                invalid(format!(
+                    "The signature doesn't match the key of {}",
+                    self.signer
+                ))
+            })?;
+        Ok(serde_json::from_str(&self.payload)?)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct BundlePayload {
+    transfer_id: String,
+    source: String,
+    destination: String,
+    player_uuid: String,
+    /// Time after which the destination refuses the bundle
+    expires_at: i64,
+    character: serde_json::Value,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ReceiptPayload {
+    transfer_id: String,
+    source: String,
+    destination: String,
+    /// Id of the imported character on the destination
+    character_id: CharacterId,
+}
+
+/// The name of this server in transfers, transfers are disabled without one
+fn server_name(settings: &TransferSettings) -> Result<&str, PersistenceError> {
+    settings.name.as_deref().ok_or_else(|| {
+        invalid("Transfers are disabled on this server, set `transfer.name` in its settings")
+    })
+}
+
+/// Whether a character is locked by a transfer to another server that hasn't
+/// expired
+pub fn is_transfer_locked(
+    character_id: CharacterId,
+    now: i64,
+    connection: &Connection,
+) -> Result<bool, PersistenceError> {
+    let mut stmt = connection.prepare_cached(
+        "
+        SELECT  COUNT(*)
+        FROM    character_transfer
+        WHERE   character_id = ?1
+        AND     state = ?2
+        AND     direction = ?3
+        AND     expires_at > ?4",
+    )?;
+
+    let count: i64 = stmt.query_row(
+        &[&character_id as &dyn ToSql, &PENDING, &OUT, &now],
+        |row| row.get(0),
+    )?;
+
+    Ok(count > 0)
+}
+
+/// Exports a character of the player into a bundle for the `destination`
+/// server, and locks it until the transfer is finalized or expires
+pub fn transfer_out(
+    key: &TransferKey,
+    settings: &TransferSettings,
+    destination: &str,
+    player_uuid: &str,
+    character_id: CharacterId,
+    now: i64,
+    transaction: &mut Transaction,
+) -> Result<SignedMessage, PersistenceError> {
+    let source = server_name(settings)?;
+    // The receipt can only be verified with the key of the destination
+    if !settings.trusted_servers.contains_key(destination) {
+        return Err(invalid(format!("{} is not a trusted server", destination)));
+    }
+
+    // Saves of the running server would change the character after it was
+    // exported
+    if is_in_game(character_id, transaction)? {
+        return Err(invalid(format!(
+            "Character {} is in the game, it can only be transferred once the player logged out",
+            character_id
+        )));
+    }
+    // Finalizing an earlier transfer purges the character even if it expired,
+    // so the character could end up on both destinations
+    if let Some(expired_at) = latest_unfinalized_transfer(character_id, transaction)? {
+        let retry_at = expired_at.saturating_add(MAX_CLOCK_SKEW);
+        if now < retry_at {
+            return Err(invalid(format!(
+                "An earlier bundle of character {} could still be accepted, finalize its transfer \
+                 with the receipt or try again in {} seconds",
+                character_id,
+                retry_at - now
+            )));
+        }
+    }
+
+    // Characters that are already locked can't be exported
+    let character = export_character(player_uuid, character_id, transaction)?;
+    let transfer_id = format!("{:032x}", rand::random::<u128>());
+    let lock_timeout = i64::try_from(settings.lock_timeout.as_secs()).unwrap_or(i64::MAX);
+    let expires_at = now.saturating_add(lock_timeout);
+
+    let mut stmt = transaction.prepare_cached(
+        "
+        INSERT INTO character_transfer (transfer_id,
+                                        direction,
+                                        peer,
+                                        character_id,
+                                        state,
+                                        expires_at)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+    )?;
+    stmt.execute(&[
+        &transfer_id as &dyn ToSql,
+        &OUT,
+        &destination,
+        &character_id,
+        &PENDING,
+        &expires_at,
+    ])?;
+    drop(stmt);
+
+    key.sign(source, &BundlePayload {
+        transfer_id,
+        source: source.to_owned(),
+        destination: destination.to_owned(),
+        player_uuid: player_uuid.to_owned(),
+        expires_at,
+        character,
+    })
+}
+
+/// The expiry of the latest transfer of a character to another server that
+/// wasn't finalized
+fn latest_unfinalized_transfer(
+    character_id: CharacterId,
+    connection: &Connection,
+) -> Result<Option<i64>, PersistenceError> {
+    let mut stmt = connection.prepare_cached(
+        "
+        SELECT  MAX(expires_at)
+        FROM    character_transfer
+        WHERE   character_id = ?1
+        AND     direction = ?2
+        AND     state != ?3",
+    )?;
+
+    let expires_at = stmt.query_row(&[&character_id as &dyn ToSql, &OUT, &FINALIZED], |row| {
+        row.get(0)
+    })?;
+
+    Ok(expires_at)
+}
+
+/// Imports the character of a bundle from a trusted server, returning the
+/// receipt for the source. Bundles that were already accepted return their
+/// receipt again, without importing the character again.
+pub fn accept_transfer(
+    key: &TransferKey,
+    settings: &TransferSettings,
+    censor: &Censor,
+    max_characters: usize,
+    alias_uniqueness: AliasUniqueness,
+    bundle: &SignedMessage,
+    now: i64,
+    transaction: &mut Transaction,
+) -> Result<SignedMessage, PersistenceError> {
+    let destination = server_name(settings)?;
+    let payload: BundlePayload = bundle.verify(settings)?;
+    if payload.source != bundle.signer || payload.destination != destination {
+        return Err(invalid(format!(
+            "The bundle is for a transfer from {} to {}",
+            payload.source, payload.destination
+        )));
+    }
+
+    let receipt = transaction
+        .query_row(
+            "
+            SELECT  receipt
+            FROM    character_transfer
+            WHERE   transfer_id = ?1
+            AND     direction = ?2",
+            &[&payload.transfer_id as &dyn ToSql, &IN],
+            |row| row.get::<_, String>(0),
+        )
+        .optional()?;
+    if let Some(receipt) = receipt {
+        info!(
+            "Transfer {} was already accepted, returning its receipt again",
+            payload.transfer_id
+        );
+        return Ok(serde_json::from_str(&receipt)?);
+    }
+    if now >= payload.expires_at {
+        return Err(invalid(format!(
+            "The bundle expired, the character can be played on {} again",
+            payload.source
+        )));
+    }
+
+    let mut exported = decode_export(payload.character)?;
+    apply_rules(settings, &mut exported)?;
+    let character_id = create_imported_character(
+        &payload.player_uuid,
+        exported,
+        censor,
+        max_characters,
+        alias_uniqueness,
+        transaction,
+    )?;
+
+    let receipt = key.sign(destination, &ReceiptPayload {
+        transfer_id: payload.transfer_id.clone(),
+        source: payload.source.clone(),
+        destination: destination.to_owned(),
+        character_id,
+    })?;
+    let mut stmt = transaction.prepare_cached(
+        "
+        INSERT INTO character_transfer (transfer_id,
+                                        direction,
+                                        peer,
+                                        character_id,
+                                        state,
+                                        expires_at,
+                                        receipt)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+    )?;
+    stmt.execute(&[
+        &payload.transfer_id as &dyn ToSql,
+        &IN,
+        &payload.source,
+        &character_id,
+        &ACCEPTED,
+        &payload.expires_at,
+        &serde_json::to_string(&receipt)?,
+    ])?;
+
+    Ok(receipt)
+}
+
+/// The id of an item as used by the item allowlist and mapping
+fn base_id(item: &Item) -> &str {
+    match item.item_definition_id() {
+        ItemDefinitionId::Simple(id)
+        | ItemDefinitionId::Compound {
+            simple_base: id, ..
+        }
+        | ItemDefinitionId::Modular {
+            pseudo_base: id, ..
+        } => id,
+    }
+}
+
+/// Replaces the items of a transferred character according to the item
+/// mapping of this server, and checks it against the other rules
+fn apply_rules(
+    settings: &TransferSettings,
+    exported: &mut ExportedCharacter,
+) -> Result<(), PersistenceError> {
+    if let Some(max_exp) = settings.max_skill_group_exp {
+        if let Some(group) = exported
+            .skill_set
+            .skill_groups()
+            .find(|group| group.earned_exp > max_exp)
+        {
+            return Err(invalid(format!(
+                "The character has {} exp in {:?}, this server allows at most {}",
+                group.earned_exp, group.skill_group_kind, max_exp
+            )));
+        }
+    }
+
+    exported.inventory.replace_items(|item| {
+        let replacement = settings.item_mapping.get(base_id(item))?;
+        match Item::new_from_asset(replacement) {
+            Ok(mut new_item) => {
+                // Items that aren't stackable are only replaced once
+                let _ = new_item.set_amount(item.amount());
+                Some(new_item)
+            },
+            Err(err) => {
+                warn!(?err, "Can't replace {} by {}", base_id(item), replacement);
+                None
+            },
+        }
+    });
+
+    if let Some(allowlist) = &settings.item_allowlist {
+        let disallowed = exported
+            .inventory
+            .slots()
+            .flatten()
+            .chain(exported.inventory.equipped_items())
+            .map(base_id)
+            .filter(|id| !allowlist.contains(*id))
+            .collect::<BTreeSet<_>>();
+        if !disallowed.is_empty() {
+            return Err(invalid(format!(
+                "This server doesn't allow {}",
+                disallowed.into_iter().collect::<Vec<_>>().join(", ")
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+/// Purges a character that was transferred to another server once the
+/// receipt of the destination arrives, returning its id. Receiving the same
+/// receipt again does nothing.
+pub fn finalize_transfer(
+    settings: &TransferSettings,
+    receipt: &SignedMessage,
+    transaction: &mut Transaction,
+) -> Result<CharacterId, PersistenceError> {
+    let source = server_name(settings)?;
+    let payload: ReceiptPayload = receipt.verify(settings)?;
+    if payload.destination != receipt.signer || payload.source != source {
+        return Err(invalid(format!(
+            "The receipt is for a transfer from {} to {}",
+            payload.source, payload.destination
+        )));
+    }
+
+    let (character_id, state, player_uuid) = transaction
+        .query_row(
+            "
+            SELECT  t.character_id,
+                    t.state,
+                    c.player_uuid
+            FROM    character_transfer t
+            LEFT JOIN character c ON (c.character_id = t.character_id)
+            WHERE   t.transfer_id = ?1
+            AND     t.direction = ?2
+            AND     t.peer = ?3",
+            &[
+                &payload.transfer_id as &dyn ToSql,
+                &OUT,
+                &payload.destination,
+            ],
+            |row| {
+                Ok((
+                    row.get::<_, CharacterId>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, Option<String>>(2)?,
+                ))
+            },
+        )
+        .optional()?
+        .ok_or_else(|| {
+            invalid(format!(
+                "There is no transfer {} to {}",
+                payload.transfer_id, payload.destination
+            ))
+        })?;
+    match state.as_str() {
+        FINALIZED => return Ok(character_id),
+        ROLLED_BACK => warn!(
+            "Transfer {} expired before its receipt arrived, purging character {} anyway as it \
+             was accepted by {}",
+            payload.transfer_id, character_id, payload.destination
+        ),
+        _ => {},
+    }
+
+    purge_character(character_id, transaction)?;
+    transaction.execute(
+        "
+        UPDATE  character_transfer
+        SET     state = ?1
+        WHERE   transfer_id = ?2
+        AND     direction = ?3",
+        &[&FINALIZED as &dyn ToSql, &payload.transfer_id, &OUT],
+    )?;
+    audit_log::log_event(
+        transaction,
+        AuditEventKind::CharacterTransferred,
+        player_uuid.as_deref().unwrap_or_default(),
+        Some(character_id),
+        &format!(
+            "to {} as character {}",
+            payload.destination, payload.character_id
+        ),
+    );
+
+    Ok(character_id)
+}
+
+/// Unlocks the characters whose transfers expired before they were finalized,
+/// returning how many were unlocked
+pub fn roll_back_expired_transfers(
+    now: i64,
+    transaction: &mut Transaction,
+) -> Result<usize, PersistenceError> {
+    let mut stmt = transaction.prepare_cached(
+        "
+        UPDATE  character_transfer
+        SET     state = ?1
+        WHERE   direction = ?2
+        AND     state = ?3
+        AND     expires_at <= ?4",
+    )?;
+
+    Ok(stmt.execute(&[&ROLLED_BACK as &dyn ToSql, &OUT, &PENDING, &now])?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::persistence::character::{
+        load_character_data, load_character_list, record_login, record_logout,
+        tests::{create_test_character, migrated_connection},
+    };
+    use common::comp;
+    use std::{collections::HashSet, time::Duration};
+
+    const NOW: i64 = 1_000_000;
+
+    /// The settings of the servers "alpha" and "beta", which trust each other
+    fn servers() -> (
+        (TransferKey, TransferSettings),
+        (TransferKey, TransferSettings),
+    ) {
+        let alpha = TransferKey::from_pkcs8(&generate_pkcs8().unwrap()).unwrap();
+        let beta = TransferKey::from_pkcs8(&generate_pkcs8().unwrap()).unwrap();
+        let settings = |name: &str, peer: &str, peer_key: &TransferKey| TransferSettings {
+            name: Some(name.to_owned()),
+            trusted_servers: [(peer.to_owned(), peer_key.public_key())]
+                .into_iter()
+                .collect(),
+            lock_timeout: Duration::from_secs(60),
+            ..Default::default()
+        };
+        let alpha_settings = settings("alpha", "beta", &beta);
+        let beta_settings = settings("beta", "alpha", &alpha);
+        ((alpha, alpha_settings), (beta, beta_settings))
+    }
+
+    fn characters(transaction: &Transaction) -> usize {
+        load_character_list("player", transaction).unwrap().len()
+    }
+
+    // Both servers use the same database in these tests, which works as
+    // transfers are told apart by their direction
+
+    /// Renames the copy of a character left on the source, which would
+    /// otherwise have the alias of the copy imported by the destination
+    fn rename_source_copy(char_id: CharacterId, transaction: &Transaction) {
+        transaction
+            .execute(
+                "UPDATE character SET alias = 'Source copy' WHERE character_id = ?1",
+                &[char_id],
+            )
+            .unwrap();
+    }
+
+    #[test]
+    fn replayed_bundle_is_imported_once() {
+        let ((alpha, alpha_settings), (beta, beta_settings)) = servers();
+        let mut connection = migrated_connection();
+        let mut transaction = connection.transaction().unwrap();
+        let char_id = create_test_character(&mut transaction, comp::humanoid::Body::random());
+
+        let bundle = transfer_out(
+            &alpha,
+            &alpha_settings,
+            "beta",
+            "player",
+            char_id,
+            NOW,
+            &mut transaction,
+        )
+        .unwrap();
+        rename_source_copy(char_id, &transaction);
+        assert!(matches!(
+            load_character_data("player".to_owned(), char_id, &transaction),
+            Err(PersistenceError::CharacterTransferLocked(id)) if id == char_id
+        ));
+
+        let accept = |transaction: &mut Transaction| {
+            accept_transfer(
+                &beta,
+                &beta_settings,
+                &Censor::Standard,
+                8,
+                AliasUniqueness::PerPlayer,
+                &bundle,
+                NOW + 1,
+                transaction,
+            )
+        };
+        let receipt = accept(&mut transaction).unwrap();
+        assert_eq!(characters(&transaction), 2);
+        assert_eq!(accept(&mut transaction).unwrap(), receipt);
+        assert_eq!(characters(&transaction), 2);
+
+        assert_eq!(
+            finalize_transfer(&alpha_settings, &receipt, &mut transaction).unwrap(),
+            char_id
+        );
+        assert_eq!(characters(&transaction), 1);
+        // Accepting the bundle after the transfer is finalized still doesn't
+        // import the character again
+        assert_eq!(accept(&mut transaction).unwrap(), receipt);
+        assert_eq!(characters(&transaction), 1);
+    }
+
+    #[test]
+    fn lost_receipt_is_recovered() {
+        let ((alpha, alpha_settings), (beta, beta_settings)) = servers();
+        let mut connection = migrated_connection();
+        let mut transaction = connection.transaction().unwrap();
+        let char_id = create_test_character(&mut transaction, comp::humanoid::Body::random());
+
+        let bundle = transfer_out(
+            &alpha,
+            &alpha_settings,
+            "beta",
+            "player",
+            char_id,
+            NOW,
+            &mut transaction,
+        )
+        .unwrap();
+        rename_source_copy(char_id, &transaction);
+        let accept = |transaction: &mut Transaction, now| {
+            accept_transfer(
+                &beta,
+                &beta_settings,
+                &Censor::Standard,
+                8,
+                AliasUniqueness::PerPlayer,
+                &bundle,
+                now,
+                transaction,
+            )
+        };
+        // The receipt of the first acceptance never reaches the source
+        let _lost = accept(&mut transaction, NOW + 1).unwrap();
+
+        // The source already unlocked the character when the receipt is
+        // recovered, it's purged anyway as it exists on the destination
+        assert_eq!(
+            roll_back_expired_transfers(NOW + 60, &mut transaction).unwrap(),
+            1
+        );
+        assert!(load_character_data("player".to_owned(), char_id, &transaction).is_ok());
+        let receipt = accept(&mut transaction, NOW + 60).unwrap();
+        finalize_transfer(&alpha_settings, &receipt, &mut transaction).unwrap();
+        assert!(load_character_data("player".to_owned(), char_id, &transaction).is_err());
+        assert_eq!(characters(&transaction), 1);
+
+        // Finalizing twice does nothing
+        assert_eq!(
+            finalize_transfer(&alpha_settings, &receipt, &mut transaction).unwrap(),
+            char_id
+        );
+    }
+
+    #[test]
+    fn played_or_recently_transferred_characters_are_not_transferred() {
+        let ((alpha, alpha_settings), _) = servers();
+        let mut connection = migrated_connection();
+        let mut transaction = connection.transaction().unwrap();
+        let char_id = create_test_character(&mut transaction, comp::humanoid::Body::random());
+        let transfer = |now, transaction: &mut Transaction| {
+            transfer_out(
+                &alpha,
+                &alpha_settings,
+                "beta",
+                "player",
+                char_id,
+                now,
+                transaction,
+            )
+        };
+
+        record_login(char_id, 1, &mut transaction).unwrap();
+        assert!(matches!(
+            transfer(NOW, &mut transaction),
+            Err(PersistenceError::InvalidTransfer(_))
+        ));
+        record_logout(char_id, &mut transaction).unwrap();
+        transfer(NOW, &mut transaction).unwrap();
+
+        // The character can be played again once the bundle expired, but a
+        // destination whose clock is behind could still accept the bundle
+        assert_eq!(
+            roll_back_expired_transfers(NOW + 60, &mut transaction).unwrap(),
+            1
+        );
+        assert!(load_character_data("player".to_owned(), char_id, &transaction).is_ok());
+        assert!(matches!(
+            transfer(NOW + 60, &mut transaction),
+            Err(PersistenceError::InvalidTransfer(_))
+        ));
+        transfer(NOW + 60 + MAX_CLOCK_SKEW, &mut transaction).unwrap();
+    }
+
+    #[test]
+    fn expired_and_forged_bundles_are_refused() {
+        let ((alpha, alpha_settings), (beta, beta_settings)) = servers();
+        let mut connection = migrated_connection();
+        let mut transaction = connection.transaction().unwrap();
+        let char_id = create_test_character(&mut transaction, comp::humanoid::Body::random());
+
+        let bundle = transfer_out(
+            &alpha,
+            &alpha_settings,
+            "beta",
+            "player",
+            char_id,
+            NOW,
+            &mut transaction,
+        )
+        .unwrap();
+        let accept = |bundle: &SignedMessage, transaction: &mut Transaction| {
+            accept_transfer(
+                &beta,
+                &beta_settings,
+                &Censor::Standard,
+                8,
+                AliasUniqueness::PerPlayer,
+                bundle,
+                NOW + 60,
+                transaction,
+            )
+        };
+        assert!(matches!(
+            accept(&bundle, &mut transaction),
+            Err(PersistenceError::InvalidTransfer(_))
+        ));
+
+        let forged = SignedMessage {
+            payload: bundle.payload.replace(
+                &format!("\"expires_at\":{}", NOW + 60),
+                "\"expires_at\":9999999999",
+            ),
+            ..bundle.clone()
+        };
+        assert!(matches!(
+            accept(&forged, &mut transaction),
+            Err(PersistenceError::InvalidTransfer(_))
+        ));
+        // A receipt signed by the source itself isn't accepted either
+        let self_signed = alpha
+            .sign("beta", &ReceiptPayload {
+                transfer_id: "0".to_owned(),
+                source: "alpha".to_owned(),
+                destination: "beta".to_owned(),
+                character_id: 0,
+            })
+            .unwrap();
+        assert!(finalize_transfer(&alpha_settings, &self_signed, &mut transaction).is_err());
+        assert_eq!(characters(&transaction), 1);
+    }
+
+    #[test]
+    fn destination_rules_are_enforced() {
+        let (_, (_, mut settings)) = servers();
+        let mut connection = migrated_connection();
+        let mut transaction = connection.transaction().unwrap();
+        let char_id = create_test_character(&mut transaction, comp::humanoid::Body::random());
+        let exported = || {
+            let mut exported =
+                decode_export(export_character("player", char_id, &transaction).unwrap()).unwrap();
+            exported
+                .inventory
+                .push(Item::new_from_asset_expect("common.items.food.apple"))
+                .unwrap();
+            exported
+        };
+
+        settings.item_allowlist = Some(
+            ["common.items.food.cheese".to_owned()]
+                .into_iter()
+                .collect(),
+        );
+        assert!(apply_rules(&settings, &mut exported()).is_err());
+
+        settings.item_mapping = [(
+            "common.items.food.apple".to_owned(),
+            "common.items.food.cheese".to_owned(),
+        )]
+        .into_iter()
+        .collect();
+        let mut mapped = exported();
+        apply_rules(&settings, &mut mapped).unwrap();
+        assert_eq!(
+            mapped
+                .inventory
+                .slots()
+                .flatten()
+                .map(base_id)
+                .collect::<HashSet<_>>(),
+            ["common.items.food.cheese"].into_iter().collect()
+        );
+
+        settings.max_skill_group_exp = Some(100);
+        let mut experienced = exported();
+        experienced
+            .skill_set
+            .add_experience(comp::skillset::SkillGroupKind::General, 101);
+        assert!(apply_rules(&settings, &mut experienced).is_err());
+    }
+}
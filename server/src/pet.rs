@@ -1,11 +1,238 @@
 use crate::{client::Client, events::update_map_markers};
 use common::{
-    comp::{self, anchor::Anchor, group::GroupManager, Agent, Alignment, Pet},
-    uid::Uid,
+    combat,
+    comp::{
+        self,
+        agent::Target,
+        anchor::Anchor,
+        group::GroupManager,
+        pet::{is_tameable, PetCommand, PetStance},
+        Agent, Alignment, Pet,
+    },
+    resources::Time,
+    uid::{Uid, UidAllocator},
 };
-use common_net::msg::ServerGeneral;
-use specs::{Entity, WorldExt};
+use common_net::{msg::ServerGeneral, sync::WorldSyncExt};
+use specs::{Entity, Join, WorldExt};
 use tracing::warn;
+use vek::Vec3;
+
+/// Why a pet didn't follow a command of its owner
+#[derive(Debug, PartialEq, Eq)]
+pub enum PetCommandError {
+    Mounted,
+    KnockedOut,
+    TooFar,
+    /// The owner has no target the pet could attack
+    NoTarget,
+    /// The target is a player (or belongs to one) that didn't agree to PvP
+    PeacefulTarget,
+}
+
+impl PetCommandError {
+    pub fn message(&self, pet_name: &str) -> String {
+        match self {
+            Self::Mounted => format!("{} can't follow commands while it is ridden", pet_name),
+            Self::KnockedOut => format!("{} is knocked out", pet_name),
+            Self::TooFar => format!("{} is too far away to hear you", pet_name),
+            Self::NoTarget => format!("{} has nothing to attack", pet_name),
+            Self::PeacefulTarget => format!(
+                "{} won't attack someone who doesn't want to fight",
+                pet_name
+            ),
+        }
+    }
+}
+
+/// Checks whether a pet can follow a command. Pets that are told to follow
+/// their owner come back from any distance, every other command needs the
+/// pet to be within range.
+pub fn check_pet_command(
+    command: PetCommand,
+    mounted: bool,
+    knocked_out: bool,
+    in_range: bool,
+    has_target: bool,
+) -> Result<(), PetCommandError> {
+    if mounted {
+        Err(PetCommandError::Mounted)
+    } else if knocked_out {
+        Err(PetCommandError::KnockedOut)
+    } else if !in_range && command != PetCommand::Follow {
+        Err(PetCommandError::TooFar)
+    } else if matches!(command, PetCommand::Attack(_)) && !has_target {
+        Err(PetCommandError::NoTarget)
+    } else {
+        Ok(())
+    }
+}
+
+/// Applies a command that passed [`check_pet_command`] to a pet and its agent
+pub fn apply_pet_command(
+    pet: &mut Pet,
+    agent: &mut Agent,
+    command: PetCommand,
+    pet_pos: Vec3<f32>,
+    owner: Entity,
+    target: Option<Entity>,
+    time: Time,
+) {
+    match command {
+        PetCommand::Stay => pet.stay_at = Some(pet_pos),
+        PetCommand::Follow => {
+            pet.stay_at = None;
+            agent.target = Some(Target::new(owner, false, time.0, false));
+        },
+        PetCommand::Attack(_) => {
+            if let Some(target) = target {
+                agent.target = Some(Target::new(target, true, time.0, true));
+            }
+        },
+        PetCommand::SetStance(stance) => {
+            pet.stance = stance;
+            // Stop fights the pet wouldn't have started in its new stance
+            if stance == PetStance::Passive && agent.target.map_or(false, |t| t.hostile) {
+                agent.target = None;
+            }
+        },
+    }
+}
+
+/// The pets of `owner` a command is given to: the pet with the uid `pet`, or
+/// all of them. Empty if `pet` isn't one of them.
+pub fn commanded_pets(ecs: &specs::World, owner: Uid, pet: Option<Uid>) -> Vec<Entity> {
+    (
+        &ecs.entities(),
+        &ecs.read_storage::<Pet>(),
+        &ecs.read_storage::<Alignment>(),
+        &ecs.read_storage::<Uid>(),
+    )
+        .join()
+        .filter(|(_, _, alignment, uid)| {
+            **alignment == Alignment::Owned(owner) && pet.map_or(true, |pet| **uid == pet)
+        })
+        .map(|(entity, ..)| entity)
+        .collect()
+}
+
+/// The entity the pets of `owner` are told to attack. Pets only attack living
+/// targets that aren't on their owner's side, and only attack players and
+/// their pets when both sides are in PvP mode.
+pub fn find_attack_target(
+    ecs: &specs::World,
+    owner: Entity,
+    target: Uid,
+) -> Result<Entity, PetCommandError> {
+    let alignments = ecs.read_storage::<Alignment>();
+    let owner_uid = ecs.uid_from_entity(owner);
+    let target = ecs
+        .entity_from_uid(target.0)
+        .filter(|target| {
+            *target != owner
+                && owner_uid.map_or(true, |owner_uid| {
+                    alignments.get(*target) != Some(&Alignment::Owned(owner_uid))
+                })
+                && ecs
+                    .read_storage::<comp::Health>()
+                    .get(*target)
+                    .map_or(false, |health| !health.is_dead)
+        })
+        .ok_or(PetCommandError::NoTarget)?;
+
+    if combat::may_harm(
+        &alignments,
+        &ecs.read_storage::<comp::Player>(),
+        &ecs.read_resource::<UidAllocator>(),
+        Some(owner),
+        target,
+    ) {
+        Ok(target)
+    } else {
+        Err(PetCommandError::PeacefulTarget)
+    }
+}
+
+/// How close a creature has to be to be captured with a collar
+pub const TAME_RANGE: f32 = 5.0;
+/// How many pets a player can have at once
+pub const MAX_PETS: usize = 3;
+
+/// Why a collar didn't capture anything
+#[derive(Debug, PartialEq, Eq)]
+pub enum TameError {
+    TooManyPets,
+    NothingInRange,
+    AlreadyOwned,
+    NotTameable,
+}
+
+impl TameError {
+    pub fn message(&self) -> String {
+        match self {
+            Self::TooManyPets => format!("You can't have more than {} pets", MAX_PETS),
+            Self::NothingInRange => "There is no creature close enough to collar".to_owned(),
+            Self::AlreadyOwned => "This creature already has an owner".to_owned(),
+            Self::NotTameable => "This creature can't be tamed".to_owned(),
+        }
+    }
+}
+
+/// The creature captured by a collar that `tamer` uses: the nearest creature
+/// within [`TAME_RANGE`] that is wild and tameable, or that the tamer knocked
+/// out. If there is none, the nearest creature tells why.
+pub fn find_tame_target(ecs: &specs::World, tamer: Entity) -> Result<Entity, TameError> {
+    let uids = ecs.read_storage::<Uid>();
+    let positions = ecs.read_storage::<comp::Pos>();
+    let (tamer_uid, pos) = match (uids.get(tamer), positions.get(tamer)) {
+        (Some(uid), Some(pos)) => (*uid, pos.0),
+        _ => return Err(TameError::NothingInRange),
+    };
+
+    let alignments = ecs.read_storage::<Alignment>();
+    let pets = (&alignments, &ecs.read_storage::<Agent>())
+        .join()
+        .filter(|(alignment, _)| **alignment == Alignment::Owned(tamer_uid))
+        .count();
+    if pets >= MAX_PETS {
+        return Err(TameError::TooManyPets);
+    }
+
+    let knocked_out = ecs.read_storage::<comp::KnockedOut>();
+    let mut nearby = (
+        &ecs.entities(),
+        &ecs.read_storage::<comp::Body>(),
+        &positions,
+        &alignments,
+        !&ecs.read_storage::<comp::Player>(),
+    )
+        .join()
+        .filter(|(entity, ..)| *entity != tamer)
+        .map(|(entity, body, creature_pos, alignment, _)| {
+            (
+                entity,
+                body,
+                alignment,
+                creature_pos.0.distance_squared(pos),
+            )
+        })
+        .filter(|(.., distance_squared)| *distance_squared < TAME_RANGE.powi(2))
+        .collect::<Vec<_>>();
+    nearby.sort_by(|(.., a), (.., b)| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+
+    // Hostile creatures can only be captured by whoever knocked them out
+    let capturable = nearby.iter().find(|(entity, body, alignment, _)| {
+        (**alignment == Alignment::Wild && is_tameable(body))
+            || knocked_out
+                .get(*entity)
+                .map_or(false, |knocked_out| knocked_out.by == tamer_uid)
+    });
+    match (capturable, nearby.first()) {
+        (Some((entity, ..)), _) => Ok(*entity),
+        (None, Some((_, _, Alignment::Owned(_), _))) => Err(TameError::AlreadyOwned),
+        (None, Some(_)) => Err(TameError::NotTameable),
+        (None, None) => Err(TameError::NothingInRange),
+    }
+}
 
 /// Restores a pet retrieved from the database on login, assigning it to its
 /// owner
@@ -49,6 +276,10 @@ fn tame_pet_internal(ecs: &specs::World, pet_entity: Entity, owner: Entity, pet:
         .write_storage()
         .insert(pet_entity, pet.unwrap_or_default());
 
+    // A captured creature doesn't wake up anymore, it gets a new agent below
+    ecs.write_storage::<common::comp::KnockedOut>()
+        .remove(pet_entity);
+
     // Create an agent for this entity using its body
     if let Some(body) = ecs.read_storage().get(pet_entity) {
         let _ = ecs
@@ -84,3 +315,256 @@ fn tame_pet_internal(ecs: &specs::World, pet_entity: Entity, owner: Entity, pet:
         },
     );
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::comp::{humanoid, Body};
+    use specs::{Builder, World, WorldExt};
+
+    fn agent() -> Agent { Agent::from_body(&Body::Humanoid(humanoid::Body::random())) }
+
+    #[test]
+    fn commands_are_rejected_when_pet_cant_follow_them() {
+        let attack = PetCommand::Attack(Uid(1));
+        assert_eq!(
+            check_pet_command(PetCommand::Stay, true, false, true, false),
+            Err(PetCommandError::Mounted)
+        );
+        assert_eq!(
+            check_pet_command(PetCommand::Follow, false, true, true, false),
+            Err(PetCommandError::KnockedOut)
+        );
+        assert_eq!(
+            check_pet_command(attack, false, false, false, true),
+            Err(PetCommandError::TooFar)
+        );
+        assert_eq!(
+            check_pet_command(attack, false, false, true, false),
+            Err(PetCommandError::NoTarget)
+        );
+        // Pets come back from any distance when told to follow
+        assert_eq!(
+            check_pet_command(PetCommand::Follow, false, false, false, false),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn attack_command_targets_the_owners_target() {
+        let mut world = World::new();
+        let owner = world.create_entity().build();
+        let enemy = world.create_entity().build();
+        let mut pet = Pet::default();
+        let mut agent = agent();
+
+        let command = PetCommand::Attack(Uid(2));
+        assert_eq!(check_pet_command(command, false, false, true, true), Ok(()));
+        apply_pet_command(
+            &mut pet,
+            &mut agent,
+            command,
+            Vec3::zero(),
+            owner,
+            Some(enemy),
+            Time(5.0),
+        );
+        let target = agent.target.expect("pet should have a target");
+        assert_eq!(target.target, enemy);
+        assert!(target.hostile);
+
+        // Following the owner again ends the attack
+        apply_pet_command(
+            &mut pet,
+            &mut agent,
+            PetCommand::Follow,
+            Vec3::zero(),
+            owner,
+            None,
+            Time(6.0),
+        );
+        let target = agent.target.expect("pet should target its owner");
+        assert_eq!(target.target, owner);
+        assert!(!target.hostile);
+    }
+
+    #[test]
+    fn stay_and_stance_are_kept_on_the_pet() {
+        let mut world = World::new();
+        let owner = world.create_entity().build();
+        let mut pet = Pet::default();
+        let mut agent = agent();
+        let pos = Vec3::new(1.0, 2.0, 3.0);
+
+        apply_pet_command(
+            &mut pet,
+            &mut agent,
+            PetCommand::Stay,
+            pos,
+            owner,
+            None,
+            Time(0.0),
+        );
+        assert_eq!(pet.stay_at, Some(pos));
+
+        apply_pet_command(
+            &mut pet,
+            &mut agent,
+            PetCommand::SetStance(PetStance::Passive),
+            pos,
+            owner,
+            None,
+            Time(0.0),
+        );
+        assert_eq!(pet.stance, PetStance::Passive);
+        assert_eq!(pet.stay_at, Some(pos));
+    }
+
+    #[test]
+    fn pets_obey_their_owner_and_leave_peaceful_players_alone() {
+        use common::{resources::BattleMode, uuid::Uuid};
+        use comp::{Health, Player};
+
+        let mut world = World::new();
+        world.register_sync_marker();
+        world.register::<Alignment>();
+        world.register::<Pet>();
+        world.register::<Health>();
+        world.register::<Player>();
+        let body = Body::Humanoid(humanoid::Body::random());
+        let player = |world: &mut World, battle_mode| {
+            world
+                .create_entity_synced()
+                .with(Player::new(
+                    "Player".to_owned(),
+                    battle_mode,
+                    Uuid::nil(),
+                    None,
+                ))
+                .with(Health::new(body, 0))
+                .build()
+        };
+        let pet = |world: &mut World, owner: Uid| {
+            world
+                .create_entity_synced()
+                .with(Pet::default())
+                .with(Alignment::Owned(owner))
+                .with(Health::new(body, 0))
+                .build()
+        };
+        let uid = |world: &World, entity: Entity| world.uid_from_entity(entity).unwrap();
+
+        let owner = player(&mut world, BattleMode::PvP);
+        let owner_uid = uid(&world, owner);
+        let own_pet = pet(&mut world, owner_uid);
+        let stranger = player(&mut world, BattleMode::PvE);
+        let stranger_uid = uid(&world, stranger);
+        let strangers_pet = pet(&mut world, stranger_uid);
+
+        // Pets only take commands from their owner
+        assert_eq!(commanded_pets(&world, owner_uid, None), vec![own_pet]);
+        assert_eq!(
+            commanded_pets(&world, owner_uid, Some(uid(&world, own_pet))),
+            vec![own_pet]
+        );
+        assert!(commanded_pets(&world, owner_uid, Some(uid(&world, strangers_pet))).is_empty());
+        assert!(commanded_pets(&world, stranger_uid, Some(uid(&world, own_pet))).is_empty());
+
+        // They never attack their own side
+        assert_eq!(
+            find_attack_target(&world, owner, owner_uid),
+            Err(PetCommandError::NoTarget)
+        );
+        assert_eq!(
+            find_attack_target(&world, owner, uid(&world, own_pet)),
+            Err(PetCommandError::NoTarget)
+        );
+
+        // Other players and their pets only once both sides are in PvP mode
+        for target in [stranger, strangers_pet] {
+            assert_eq!(
+                find_attack_target(&world, owner, uid(&world, target)),
+                Err(PetCommandError::PeacefulTarget)
+            );
+        }
+        world
+            .write_storage::<Player>()
+            .get_mut(stranger)
+            .unwrap()
+            .battle_mode = BattleMode::PvP;
+        for target in [stranger, strangers_pet] {
+            assert_eq!(
+                find_attack_target(&world, owner, uid(&world, target)),
+                Ok(target)
+            );
+        }
+
+        world
+            .write_storage::<Health>()
+            .get_mut(stranger)
+            .unwrap()
+            .is_dead = true;
+        assert_eq!(
+            find_attack_target(&world, owner, stranger_uid),
+            Err(PetCommandError::NoTarget)
+        );
+    }
+
+    #[test]
+    fn collars_tell_why_they_captured_nothing() {
+        use comp::{humanoid, quadruped_small, KnockedOut, Player, Pos};
+
+        let mut world = World::new();
+        world.register::<Uid>();
+        world.register::<Pos>();
+        world.register::<Alignment>();
+        world.register::<Agent>();
+        world.register::<Body>();
+        world.register::<KnockedOut>();
+        world.register::<Player>();
+        let tamer_uid = Uid(1);
+        let tamer = world
+            .create_entity()
+            .with(tamer_uid)
+            .with(Pos(Vec3::zero()))
+            .build();
+        let rat = Body::QuadrupedSmall(quadruped_small::Body::random());
+        let human = Body::Humanoid(humanoid::Body::random());
+        let spawn = |world: &mut World, distance: f32, body: Body, alignment: Alignment| {
+            world
+                .create_entity()
+                .with(Pos(Vec3::unit_x() * distance))
+                .with(body)
+                .with(alignment)
+                .with(Agent::from_body(&body))
+                .build()
+        };
+
+        spawn(&mut world, TAME_RANGE + 1.0, rat, Alignment::Wild);
+        assert_eq!(
+            find_tame_target(&world, tamer),
+            Err(TameError::NothingInRange)
+        );
+        spawn(&mut world, 2.0, rat, Alignment::Owned(Uid(2)));
+        assert_eq!(
+            find_tame_target(&world, tamer),
+            Err(TameError::AlreadyOwned)
+        );
+        spawn(&mut world, 1.0, human, Alignment::Wild);
+        assert_eq!(find_tame_target(&world, tamer), Err(TameError::NotTameable));
+
+        // Hostile creatures only once the tamer knocked them out
+        let hostile_rat = spawn(&mut world, 3.0, rat, Alignment::Enemy);
+        assert_eq!(find_tame_target(&world, tamer), Err(TameError::NotTameable));
+        world
+            .write_storage()
+            .insert(hostile_rat, KnockedOut::new(tamer_uid, None, Time(0.0)))
+            .unwrap();
+        assert_eq!(find_tame_target(&world, tamer), Ok(hostile_rat));
+
+        for _ in 0..MAX_PETS {
+            spawn(&mut world, 100.0, rat, Alignment::Owned(tamer_uid));
+        }
+        assert_eq!(find_tame_target(&world, tamer), Err(TameError::TooManyPets));
+    }
+}
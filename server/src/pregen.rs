@@ -0,0 +1,409 @@
+//! Pregeneration of the terrain around the spawn point, so that the first
+//! players on a new world don't have to wait for every chunk to be generated.
+//!
+//! Only the base terrain of chunks (see `World::generate_base_chunk`) is
+//! pregenerated and stored. Layers, sites and entities are still applied when
+//! a chunk gets loaded, which keeps spawns working as usual, but it also means
+//! the columns of the chunk still have to be sampled. Pregeneration only saves
+//! sampling the blocks, which makes loading a chunk faster but far from free.
+//!
+//! Chunks are pregenerated without calendar events, so the cache isn't used
+//! while one is going on.
+
+use atomicwrites::{AtomicFile, OverwriteBehavior};
+use common::{slowjob::SlowJobPool, terrain::TerrainChunk};
+use common_net::msg::compression::CompressedData;
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+use std::{
+    fs,
+    io::Write as _,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc,
+    },
+};
+use tracing::{error, info, warn};
+use vek::*;
+
+/// Name of the slow jobs used for background pregeneration
+pub const PREGEN_JOB: &str = "CHUNK_PREGEN";
+
+/// Identifies the world and server version which generated the cached chunks
+const IDENTITY_FILE: &str = "world";
+
+/// Storage of pregenerated base chunks, which are loaded by the chunk
+/// generator without needing anyone to be subscribed to them.
+pub struct ChunkCache {
+    path: PathBuf,
+}
+
+impl ChunkCache {
+    /// Opens the chunk cache in the data directory. Chunks generated by
+    /// another world seed or version of the server are discarded, since they
+    /// wouldn't match the rest of the world anymore.
+    pub fn new(data_dir: &Path, world_seed: u32) -> Self {
+        let path = data_dir.join("pregen");
+        let identity = format!("{} {}", world_seed, *common::util::GIT_HASH);
+        let identity_path = path.join(IDENTITY_FILE);
+
+        if fs::read_to_string(&identity_path).map_or(true, |stored| stored != identity) {
+            if path.exists() {
+                info!("Discarding pregenerated chunks of another world or server version");
+                if let Err(err) = fs::remove_dir_all(&path) {
+                    error!(?err, "Failed to remove pregenerated chunks");
+                }
+            }
+            if let Err(err) =
+                fs::create_dir_all(&path).and_then(|()| fs::write(&identity_path, &identity))
+            {
+                error!(?err, "Failed to create the pregenerated chunk directory");
+            }
+        }
+
+        Self { path }
+    }
+
+    fn path_for(&self, key: Vec2<i32>) -> PathBuf {
+        self.path.join(format!("chunk_{}_{}.dat", key.x, key.y))
+    }
+
+    pub fn contains(&self, key: Vec2<i32>) -> bool { self.path_for(key).exists() }
+
+    pub fn load(&self, key: Vec2<i32>) -> Option<TerrainChunk> {
+        let bytes = fs::read(self.path_for(key)).ok()?;
+        let chunk = bincode::deserialize::<CompressedData<TerrainChunk>>(&bytes)
+            .ok()
+            .and_then(|data| data.decompress());
+        if chunk.is_none() {
+            warn!(
+                ?key,
+                "Failed to load pregenerated chunk, it will be regenerated"
+            );
+        }
+        chunk
+    }
+
+    /// Stores a chunk, the file is replaced atomically so that an interrupted
+    /// pregeneration never leaves a partially written chunk behind.
+    pub fn store(&self, key: Vec2<i32>, chunk: &TerrainChunk) {
+        let bytes = match bincode::serialize(&CompressedData::compress(chunk, 1)) {
+            Ok(bytes) => bytes,
+            Err(err) => {
+                error!(?err, "Failed to serialize pregenerated chunk");
+                return;
+            },
+        };
+        let atomic_file = AtomicFile::new(self.path_for(key), OverwriteBehavior::AllowOverwrite);
+        if let Err(err) = atomic_file.write(|file| file.write_all(&bytes)) {
+            error!(?err, "Failed to write pregenerated chunk to file");
+        }
+    }
+}
+
+/// The chunks within `radius` of `center`, ring by ring starting with the
+/// center, so that the area closest to the center is done first.
+pub fn chunks_around(center: Vec2<i32>, radius: i32) -> impl Iterator<Item = Vec2<i32>> {
+    (0..=radius).flat_map(move |ring| {
+        (-ring..=ring)
+            .flat_map(move |x| (-ring..=ring).map(move |y| Vec2::new(x, y)))
+            .filter(move |offs| offs.x.abs() == ring || offs.y.abs() == ring)
+            .map(move |offs| center + offs)
+    })
+}
+
+/// Generates and stores the chunks that aren't in the cache yet, on `workers`
+/// threads. Chunks which are already stored are skipped, so an interrupted
+/// pregeneration resumes where it stopped. Returns how many chunks were
+/// generated.
+pub fn pregenerate(
+    cache: &ChunkCache,
+    chunks: impl IntoIterator<Item = Vec2<i32>>,
+    workers: usize,
+    generate: impl Fn(Vec2<i32>) -> Option<TerrainChunk> + Sync,
+    should_stop: impl Fn() -> bool + Sync,
+) -> usize {
+    let remaining = chunks
+        .into_iter()
+        .filter(|key| !cache.contains(*key))
+        .collect::<Vec<_>>();
+    let total = remaining.len();
+    if total == 0 {
+        info!("All chunks were already pregenerated");
+        return 0;
+    }
+    info!("Pregenerating {} chunks on {} threads", total, workers);
+
+    let pool = match rayon::ThreadPoolBuilder::new()
+        .num_threads(workers)
+        .thread_name(|i| format!("pregen-{}", i))
+        .build()
+    {
+        Ok(pool) => pool,
+        Err(err) => {
+            error!(?err, "Failed to create the pregeneration thread pool");
+            return 0;
+        },
+    };
+    let done = AtomicUsize::new(0);
+    pool.install(|| {
+        remaining.into_par_iter().for_each(|key| {
+            if should_stop() {
+                return;
+            }
+            if let Some(mut chunk) = generate(key) {
+                chunk.defragment();
+                cache.store(key, &chunk);
+            }
+            let done = done.fetch_add(1, Ordering::Relaxed) + 1;
+            // Report progress every percent
+            if done * 100 / total != (done - 1) * 100 / total {
+                info!(
+                    "Pregenerated {}/{} chunks ({}%)",
+                    done,
+                    total,
+                    done * 100 / total
+                );
+            }
+        })
+    });
+
+    let done = done.into_inner();
+    if done < total {
+        info!(
+            "Pregeneration stopped after {} of {} chunks, run it again to resume",
+            done, total
+        );
+    }
+    done
+}
+
+/// Pregenerates chunks one at a time while the server is running, using
+/// chunk generation capacity that players don't need.
+pub struct BackgroundPregen {
+    chunks: Vec<Vec2<i32>>,
+    /// Index of the next chunk to check in `chunks`
+    next: usize,
+    in_flight: Arc<AtomicBool>,
+}
+
+impl BackgroundPregen {
+    pub fn new(center: Vec2<i32>, radius: i32) -> Self {
+        Self {
+            chunks: chunks_around(center, radius).collect(),
+            next: 0,
+            in_flight: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    pub fn is_done(&self) -> bool { self.next >= self.chunks.len() }
+
+    /// Starts generating the next chunk which isn't in the cache yet. Nothing
+    /// is started while chunks requested by players are pending, and the job
+    /// is only started if it can run right away, so it never ends up in the
+    /// queue ahead of a player request. Returns the chunk being generated.
+    pub fn maintain(
+        &mut self,
+        slow_jobs: &SlowJobPool,
+        player_requests_pending: bool,
+        cache: &Arc<ChunkCache>,
+        generate: impl FnOnce(Vec2<i32>) -> Option<TerrainChunk> + Send + Sync + 'static,
+    ) -> Option<Vec2<i32>> {
+        if player_requests_pending || self.in_flight.load(Ordering::Acquire) {
+            return None;
+        }
+        while self
+            .chunks
+            .get(self.next)
+            .map_or(false, |key| cache.contains(*key))
+        {
+            self.next += 1;
+        }
+        let key = *self.chunks.get(self.next)?;
+
+        let in_flight = Arc::clone(&self.in_flight);
+        let cache = Arc::clone(cache);
+        self.in_flight.store(true, Ordering::Release);
+        let job = slow_jobs.try_run(PREGEN_JOB, move || {
+            if let Some(mut chunk) = generate(key) {
+                chunk.defragment();
+                cache.store(key, &chunk);
+            }
+            in_flight.store(false, Ordering::Release);
+        });
+        match job {
+            Ok(_) => {
+                self.next += 1;
+                if self.is_done() {
+                    info!("Background pregeneration finished");
+                }
+                Some(key)
+            },
+            Err(()) => {
+                self.in_flight.store(false, Ordering::Release);
+                None
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::terrain::{Block, BlockKind, SpriteKind, TerrainChunkMeta};
+    use std::{
+        thread,
+        time::{Duration, Instant},
+    };
+
+    /// A chunk cache in a temporary directory, which is removed on drop
+    struct TestCache {
+        dir: PathBuf,
+        cache: Arc<ChunkCache>,
+    }
+
+    impl TestCache {
+        fn new(name: &str) -> Self {
+            let dir = std::env::temp_dir().join(format!(
+                "veloren-pregen-{}-{}",
+                name,
+                std::process::id()
+            ));
+            let _ = fs::remove_dir_all(&dir);
+            let cache = Arc::new(ChunkCache::new(&dir, 0));
+            Self { dir, cache }
+        }
+    }
+
+    impl Drop for TestCache {
+        fn drop(&mut self) { let _ = fs::remove_dir_all(&self.dir); }
+    }
+
+    fn test_chunk() -> TerrainChunk {
+        TerrainChunk::new(
+            0,
+            Block::new(BlockKind::Rock, Rgb::zero()),
+            Block::air(SpriteKind::Empty),
+            TerrainChunkMeta::void(),
+        )
+    }
+
+    fn wait_for(condition: impl Fn() -> bool) {
+        let start = Instant::now();
+        while !condition() {
+            assert!(start.elapsed() < Duration::from_secs(10), "Timed out");
+            thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    #[test]
+    fn chunks_around_center_first() {
+        let center = Vec2::new(3, -2);
+        let chunks = chunks_around(center, 2).collect::<Vec<_>>();
+        assert_eq!(chunks.len(), 25);
+        assert_eq!(chunks[0], center);
+        assert!(chunks[1..9]
+            .iter()
+            .all(|key| (*key - center).map(i32::abs).reduce_max() == 1));
+        let mut unique = chunks.clone();
+        unique.sort_by_key(|key| (key.x, key.y));
+        unique.dedup();
+        assert_eq!(unique.len(), chunks.len());
+    }
+
+    #[test]
+    fn resumes_after_interrupt() {
+        let test = TestCache::new("resume");
+        let chunks = chunks_around(Vec2::new(3, -2), 2).collect::<Vec<_>>();
+        let generated = AtomicUsize::new(0);
+        let generate = |_: Vec2<i32>| {
+            generated.fetch_add(1, Ordering::Relaxed);
+            Some(test_chunk())
+        };
+
+        // Interrupted part way through
+        let first = pregenerate(&test.cache, chunks.clone(), 1, generate, || {
+            generated.load(Ordering::Relaxed) >= 10
+        });
+        assert_eq!(first, 10);
+        assert_eq!(
+            chunks
+                .iter()
+                .filter(|key| test.cache.contains(**key))
+                .count(),
+            10
+        );
+
+        // Resuming only generates the missing chunks
+        let second = pregenerate(&test.cache, chunks.clone(), 4, generate, || false);
+        assert_eq!(second, 15);
+        assert_eq!(generated.load(Ordering::Relaxed), chunks.len());
+        assert!(chunks.iter().all(|key| test.cache.load(*key).is_some()));
+
+        // And once everything is there, nothing is generated anymore
+        assert_eq!(pregenerate(&test.cache, chunks, 4, generate, || false), 0);
+    }
+
+    #[test]
+    fn background_pregen_yields_to_players() {
+        let test = TestCache::new("yield");
+        let threadpool = rayon::ThreadPoolBuilder::new()
+            .num_threads(2)
+            .build()
+            .unwrap();
+        let slow_jobs = SlowJobPool::new(2, 0, Arc::new(threadpool));
+        slow_jobs.configure("CHUNK_GENERATOR", |n| n);
+        slow_jobs.configure(PREGEN_JOB, |_| 1);
+
+        let pregenerated = Arc::new(AtomicUsize::new(0));
+        let generate = {
+            let pregenerated = Arc::clone(&pregenerated);
+            move || {
+                let pregenerated = Arc::clone(&pregenerated);
+                move |_: Vec2<i32>| {
+                    pregenerated.fetch_add(1, Ordering::SeqCst);
+                    Some(test_chunk())
+                }
+            }
+        };
+        let mut pregen = BackgroundPregen::new(Vec2::zero(), 0);
+
+        // Occupy every thread with player requests, and queue one more
+        let release = Arc::new(AtomicBool::new(false));
+        let served = Arc::new(AtomicUsize::new(0));
+        for _ in 0..3 {
+            let release = Arc::clone(&release);
+            let served = Arc::clone(&served);
+            slow_jobs.spawn("CHUNK_GENERATOR", move || {
+                while !release.load(Ordering::SeqCst) {
+                    thread::sleep(Duration::from_millis(1));
+                }
+                served.fetch_add(1, Ordering::SeqCst);
+            });
+        }
+
+        // Nothing is pregenerated while players are waiting for chunks
+        assert_eq!(
+            pregen.maintain(&slow_jobs, true, &test.cache, generate()),
+            None
+        );
+        // and the job isn't queued behind the player requests either
+        assert_eq!(
+            pregen.maintain(&slow_jobs, false, &test.cache, generate()),
+            None
+        );
+
+        release.store(true, Ordering::SeqCst);
+        wait_for(|| served.load(Ordering::SeqCst) == 3);
+        assert_eq!(pregenerated.load(Ordering::SeqCst), 0);
+
+        // Once the players are served, the idle capacity is used
+        assert_eq!(
+            pregen.maintain(&slow_jobs, false, &test.cache, generate()),
+            Some(Vec2::zero())
+        );
+        wait_for(|| test.cache.contains(Vec2::zero()));
+        assert_eq!(pregenerated.load(Ordering::SeqCst), 1);
+        assert!(pregen.is_done());
+    }
+}
@@ -11,6 +11,9 @@ pub struct Presence {
     pub entity_view_distance: ViewDistance,
     pub kind: PresenceKind,
     pub lossy_terrain_compression: bool,
+    /// Whether the client asked for less traffic at the cost of fidelity,
+    /// see [`common_net::msg::LOW_BANDWIDTH_BUDGET`]
+    pub low_bandwidth: bool,
 }
 
 impl Presence {
@@ -21,6 +24,7 @@ impl Presence {
             entity_view_distance: ViewDistance::new(view_distances.entity, now),
             kind,
             lossy_terrain_compression: false,
+            low_bandwidth: false,
         }
     }
 }
@@ -16,12 +16,15 @@ pub use whitelist::{Whitelist, WhitelistInfo, WhitelistRecord};
 use chrono::Utc;
 use common::{
     calendar::{Calendar, CalendarEvent},
+    character::MAX_CHARACTERS_PER_PLAYER,
+    mounting::MAX_MOUNT_SEPARATION,
     resources::BattleMode,
 };
 use core::time::Duration;
 use portpicker::pick_unused_port;
 use serde::{Deserialize, Serialize};
 use std::{
+    collections::{HashMap, HashSet},
     fs,
     net::{Ipv4Addr, Ipv6Addr, SocketAddr},
     path::{Path, PathBuf},
@@ -83,6 +86,27 @@ pub struct GameplaySettings {
     pub safe_spawn: bool,
     #[serde(default)]
     pub explosion_burn_marks: bool,
+    #[serde(default)]
+    pub traps: TrapSettings,
+    /// How long admins can possess an entity for before they are returned to
+    /// their own body. Possession doesn't time out when this isn't set.
+    #[serde(default)]
+    pub possession_timeout: Option<Duration>,
+    /// How far away admins can possess entities from. With `None` they can
+    /// possess anything near enough to be loaded on their client, e.g. for
+    /// moderation.
+    #[serde(default = "default_possession_range")]
+    pub possession_range: Option<f32>,
+    /// Whether the exact health and energy of everyone are synced to all
+    /// clients, e.g. for co-op servers. Otherwise only the entity itself, its
+    /// group members and admins see exact values, while everyone else only
+    /// gets them rounded to 10% steps, which is enough for health bars.
+    #[serde(default)]
+    pub share_exact_stats: bool,
+    #[serde(default)]
+    pub fire: FireSettings,
+    #[serde(default)]
+    pub death_loot: DeathLootSettings,
 }
 
 impl Default for GameplaySettings {
@@ -91,6 +115,99 @@ impl Default for GameplaySettings {
             battle_mode: ServerBattleMode::default(),
             safe_spawn: false,
             explosion_burn_marks: true,
+            traps: TrapSettings::default(),
+            possession_timeout: None,
+            possession_range: default_possession_range(),
+            share_exact_stats: false,
+            fire: FireSettings::default(),
+            death_loot: DeathLootSettings::default(),
+        }
+    }
+}
+
+/// A few times the distance at which riders are separated from their mount
+fn default_possession_range() -> Option<f32> { Some(MAX_MOUNT_SEPARATION * 4.0) }
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TrapSettings {
+    /// How long placed traps stay in the world before despawning
+    pub lifetime: Duration,
+    /// How many traps a player can have placed at the same time
+    pub max_per_player: usize,
+}
+
+impl Default for TrapSettings {
+    fn default() -> Self {
+        Self {
+            lifetime: Duration::from_secs(30 * 60),
+            max_per_player: 5,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FireSettings {
+    /// Whether burnt blocks are removed, otherwise fire is only cosmetic.
+    /// Wooden buildings in towns burn down too, and the damage is permanent
+    /// with terrain persistence.
+    pub destroy_blocks: bool,
+    /// How many blocks can burn at the same time in each region
+    pub max_burning_per_region: usize,
+    /// How long a block burns before it is burnt away
+    pub burn_duration: Duration,
+    /// Chance for a burning block to set each of its neighbours on fire every
+    /// second
+    pub spread_chance: f32,
+}
+
+impl Default for FireSettings {
+    fn default() -> Self {
+        Self {
+            destroy_blocks: false,
+            max_burning_per_region: 64,
+            burn_duration: Duration::from_secs(20),
+            spread_chance: 0.05,
+        }
+    }
+}
+
+/// How much of their inventory players drop when they die. Equipped items are
+/// always kept.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum DeathLootPolicy {
+    KeepAll,
+    /// Drops this fraction of each stack, and of the items that don't stack,
+    /// rounded down in favour of the player
+    DropFraction(f32),
+    DropAll,
+}
+
+/// Which [`DeathLootPolicy`] applies where, decided by the location of the
+/// player when they died (the same location they are shown in game)
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DeathLootSettings {
+    /// Policy in the safe areas around settlements
+    pub safe: DeathLootPolicy,
+    /// Policy everywhere else
+    pub wilderness: DeathLootPolicy,
+    /// Policies for the areas of sites with these names, overriding the ones
+    /// above
+    pub sites: HashMap<String, DeathLootPolicy>,
+    /// Policy for hardcore characters, overriding all others. Whatever they
+    /// keep is lost along with the character.
+    pub hardcore: Option<DeathLootPolicy>,
+}
+
+impl Default for DeathLootSettings {
+    fn default() -> Self {
+        Self {
+            safe: DeathLootPolicy::KeepAll,
+            wilderness: DeathLootPolicy::KeepAll,
+            sites: HashMap::new(),
+            hardcore: None,
         }
     }
 }
@@ -133,6 +250,19 @@ impl Default for ModerationSettings {
     }
 }
 
+/// Which characters can't share an alias. Deleted characters don't count.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AliasUniqueness {
+    /// Each player's characters have different aliases
+    PerPlayer,
+    /// No two characters on the server have the same alias
+    Server,
+}
+
+impl Default for AliasUniqueness {
+    fn default() -> Self { Self::PerPlayer }
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum CalendarMode {
     None,
@@ -175,6 +305,28 @@ pub struct Settings {
     pub spawn_town: Option<String>,
     pub max_player_for_kill_broadcast: Option<usize>,
     pub calendar_mode: CalendarMode,
+    /// How long deleted characters can be restored for before they are
+    /// permanently removed.
+    pub character_deletion_retention: Duration,
+    /// How many characters each player can have. Lowering this doesn't delete
+    /// any characters, players above the limit just can't create new ones.
+    pub max_characters_per_player: usize,
+    /// Whether aliases only have to differ between the characters of a player,
+    /// or between all characters on the server
+    pub character_alias_uniqueness: AliasUniqueness,
+    /// How many characters are saved per database transaction when
+    /// characters are periodically saved.
+    pub persistence_batch_size: usize,
+    /// Whether database maintenance (see `/db_maintenance`) is run in the
+    /// background when the server starts.
+    pub database_maintenance_on_startup: bool,
+    /// When set, chunks within this many chunks of the spawn point are
+    /// pregenerated in the background while no players are waiting for chunks.
+    pub background_pregen_radius: Option<u32>,
+    /// Asset directories (e.g. `server.custom_items`) with items that aren't
+    /// part of the vanilla assets. Their definitions are sent to clients when
+    /// they join, so they can show these items.
+    pub custom_item_dirs: Vec<String>,
 
     /// Experimental feature. No guaranteed forwards-compatibility, may be
     /// removed at *any time* with no migration.
@@ -185,6 +337,10 @@ pub struct Settings {
     pub gameplay: GameplaySettings,
     #[serde(default)]
     pub moderation: ModerationSettings,
+    #[serde(default)]
+    pub status_api: StatusApiSettings,
+    #[serde(default)]
+    pub transfer: TransferSettings,
 }
 
 impl Default for Settings {
@@ -211,9 +367,97 @@ impl Default for Settings {
             client_timeout: Duration::from_secs(40),
             spawn_town: None,
             max_player_for_kill_broadcast: None,
+            character_deletion_retention: Duration::from_secs(7 * 24 * 3600),
+            max_characters_per_player: MAX_CHARACTERS_PER_PLAYER,
+            character_alias_uniqueness: AliasUniqueness::default(),
+            persistence_batch_size: 64,
+            database_maintenance_on_startup: false,
+            background_pregen_radius: None,
+            custom_item_dirs: Vec::new(),
             experimental_terrain_persistence: false,
             gameplay: GameplaySettings::default(),
             moderation: ModerationSettings::default(),
+            status_api: StatusApiSettings::default(),
+            transfer: TransferSettings::default(),
+        }
+    }
+}
+
+/// Settings of the read-only status API used by third party websites. Every
+/// piece of data is opt-in, so that nothing is shared unless the server owner
+/// explicitly allows it.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StatusApiSettings {
+    /// Address the API is served on over HTTP
+    pub address: SocketAddr,
+    /// Tokens that are allowed to query the API, no tokens disables it
+    pub tokens: Vec<String>,
+    /// How many requests a single token may make per minute
+    pub requests_per_minute: u32,
+    /// Snapshots older than this are not served
+    pub max_snapshot_age: Duration,
+    pub share_player_names: bool,
+    pub share_player_positions: bool,
+    pub share_player_levels: bool,
+    pub share_economy: bool,
+    /// Size (in blocks) of the grid player positions are snapped to
+    pub position_granularity: u32,
+}
+
+impl Default for StatusApiSettings {
+    fn default() -> Self {
+        Self {
+            address: SocketAddr::from((Ipv4Addr::LOCALHOST, 14006)),
+            tokens: Vec::new(),
+            requests_per_minute: 30,
+            max_snapshot_age: Duration::from_secs(5),
+            share_player_names: false,
+            share_player_positions: false,
+            share_player_levels: false,
+            share_economy: false,
+            position_granularity: 1024,
+        }
+    }
+}
+
+/// Settings of signed character transfers between servers, see
+/// [`crate::persistence::signed_transfer`]. Transfers are disabled until
+/// [`TransferSettings::name`] is set.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TransferSettings {
+    /// Name of this server in transfers, the other servers have to use the
+    /// same name for it
+    pub name: Option<String>,
+    /// Public keys of the servers characters can be transferred from and to,
+    /// by their names. The key of a server is printed by the `character
+    /// transfer-key` command.
+    pub trusted_servers: HashMap<String, String>,
+    /// How long a character transferred to another server can't be played.
+    /// If the transfer isn't finalized by then, the destination refuses it
+    /// and the character can be played here again.
+    pub lock_timeout: Duration,
+    /// Items that characters transferred to this server may bring along, all
+    /// items if not set. Modular items are identified by their base.
+    pub item_allowlist: Option<HashSet<String>>,
+    /// Items that are replaced by other items when characters bring them along,
+    /// applied before checking the allowlist. Equipped items aren't replaced.
+    pub item_mapping: HashMap<String, String>,
+    /// The most exp characters transferred to this server may have in each
+    /// skill group
+    pub max_skill_group_exp: Option<u32>,
+}
+
+impl Default for TransferSettings {
+    fn default() -> Self {
+        Self {
+            name: None,
+            trusted_servers: HashMap::new(),
+            lock_timeout: Duration::from_secs(24 * 3600),
+            item_allowlist: None,
+            item_mapping: HashMap::new(),
+            max_skill_group_exp: None,
         }
     }
 }
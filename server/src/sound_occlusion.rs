@@ -0,0 +1,142 @@
+//! Coarse occlusion of the sounds of outcomes by terrain. Clients only have
+//! the terrain around them loaded, so the server works out how much of a sound
+//! is blocked on the way to each client and sends it along with the outcome.
+
+use common::{outcome::SoundOcclusion, terrain::Block};
+use hashbrown::HashMap;
+use vek::*;
+
+/// Outcomes further away than this are quiet enough that occlusion isn't
+/// worth computing
+pub const MAX_OCCLUSION_DISTANCE: f32 = 96.0;
+/// Distance between the blocks sampled along the line between the client and
+/// the outcome
+const SAMPLE_SPACING: f32 = 1.0;
+/// Number of solid blocks in between after which a sound is fully occluded
+const OCCLUDED_SAMPLES: usize = 4;
+/// Outcomes closer together than this share the result of one raycast
+const CLUSTER_SIZE: f32 = 4.0;
+/// Raycasts done for a single client in one tick, outcomes beyond the budget
+/// are sent without occlusion
+const MAX_RAYCASTS_PER_CLIENT: usize = 16;
+
+/// How much of a sound at `source` reaches a listener at `listener`, based on
+/// the number of opaque blocks in between. The blocks at both ends are
+/// skipped, as outcomes such as explosions often happen inside terrain.
+/// Returns `None` if the source is beyond [`MAX_OCCLUSION_DISTANCE`].
+pub fn occlusion(
+    listener: Vec3<f32>,
+    source: Vec3<f32>,
+    get_block: impl Fn(Vec3<i32>) -> Option<Block>,
+) -> Option<SoundOcclusion> {
+    let distance = listener.distance(source);
+    if distance > MAX_OCCLUSION_DISTANCE {
+        return None;
+    }
+    let samples = (distance / SAMPLE_SPACING) as usize;
+    let occluded = (1..samples)
+        .map(|i| Lerp::lerp(listener, source, i as f32 / samples as f32))
+        .filter(|pos| {
+            get_block(pos.map(|e| e.floor() as i32)).map_or(false, |block| block.is_opaque())
+        })
+        .count();
+    Some(SoundOcclusion {
+        factor: (occluded as f32 / OCCLUDED_SAMPLES as f32).min(1.0),
+        distance,
+    })
+}
+
+/// Occlusion computed for one client in one tick. Outcomes tend to come in
+/// bursts at the same place (e.g. the explosions of a bomb), so nearby
+/// outcomes reuse the same raycast, and the number of raycasts is capped.
+#[derive(Default)]
+pub struct OcclusionCache {
+    clusters: HashMap<Vec3<i32>, Option<SoundOcclusion>>,
+    raycasts: usize,
+}
+
+impl OcclusionCache {
+    pub fn get(
+        &mut self,
+        listener: Vec3<f32>,
+        source: Vec3<f32>,
+        get_block: impl Fn(Vec3<i32>) -> Option<Block>,
+    ) -> Option<SoundOcclusion> {
+        let cluster = source.map(|e| (e / CLUSTER_SIZE).floor() as i32);
+        if let Some(cached) = self.clusters.get(&cluster) {
+            return cached.map(|occlusion| SoundOcclusion {
+                distance: listener.distance(source),
+                ..occlusion
+            });
+        }
+        if self.raycasts >= MAX_RAYCASTS_PER_CLIENT {
+            return None;
+        }
+        self.raycasts += 1;
+        let occlusion = occlusion(listener, source, get_block);
+        self.clusters.insert(cluster, occlusion);
+        occlusion
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::terrain::{BlockKind, SpriteKind};
+
+    /// Flat ground with a wall 3 blocks thick at x = 10..13
+    fn walled(pos: Vec3<i32>) -> Option<Block> {
+        Some(if pos.z < 0 || (10..13).contains(&pos.x) {
+            Block::new(BlockKind::Rock, Rgb::zero())
+        } else {
+            Block::air(SpriteKind::Empty)
+        })
+    }
+
+    #[test]
+    fn walls_occlude_explosions() {
+        let explosion = Vec3::new(20.5, 0.5, 1.5);
+
+        let behind_wall = occlusion(Vec3::new(0.5, 0.5, 1.5), explosion, walled).unwrap();
+        let in_open = occlusion(Vec3::new(30.5, 10.5, 1.5), explosion, walled).unwrap();
+        assert!(behind_wall.factor > in_open.factor);
+        assert_eq!(in_open.factor, 0.0);
+        assert!(behind_wall.is_muffled());
+        assert!(!in_open.is_muffled());
+        assert!(behind_wall.volume() < in_open.volume());
+        assert_eq!(behind_wall.distance, 20.0);
+    }
+
+    #[test]
+    fn distant_outcomes_are_not_occluded() {
+        let listener = Vec3::new(0.5, 0.5, 1.5);
+        assert_eq!(
+            occlusion(
+                listener,
+                listener + Vec3::unit_x() * (MAX_OCCLUSION_DISTANCE + 1.0),
+                walled
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn cache_reuses_nearby_raycasts() {
+        let listener = Vec3::new(0.5, 0.5, 1.5);
+        let mut cache = OcclusionCache::default();
+        let first = cache
+            .get(listener, Vec3::new(20.5, 0.5, 1.5), walled)
+            .unwrap();
+        let nearby = cache
+            .get(listener, Vec3::new(21.5, 0.5, 1.5), walled)
+            .unwrap();
+        assert_eq!(cache.raycasts, 1);
+        assert_eq!(first.factor, nearby.factor);
+        assert_eq!(nearby.distance, 21.0);
+
+        for i in 0..MAX_RAYCASTS_PER_CLIENT * 2 {
+            cache.get(listener, Vec3::new(0.5, 20.0 * i as f32, 1.5), walled);
+        }
+        assert_eq!(cache.raycasts, MAX_RAYCASTS_PER_CLIENT);
+    }
+}
@@ -1,8 +1,9 @@
 use crate::{
     automod::AutoMod,
     client::Client,
+    danger::DangerOverlay,
     events::{self, update_map_markers},
-    persistence::PersistedComponents,
+    persistence::{character_updater::CharacterUpdater, PersistedComponents},
     pet::restore_pet,
     presence::{Presence, RepositionOnChunkLoad},
     settings::Settings,
@@ -310,7 +311,7 @@ impl StateExt for State {
         mountable: bool,
     ) -> EcsEntityBuilder {
         let body = comp::Body::Ship(ship);
-        let builder = self
+        let mut builder = self
             .ecs_mut()
             .create_entity_synced()
             .with(pos)
@@ -333,7 +334,7 @@ impl StateExt for State {
             .with(comp::Combo::default());
 
         if mountable {
-            // TODO: Re-add mounting check
+            builder = builder.with(comp::Mountable);
         }
         builder
     }
@@ -544,6 +545,12 @@ impl StateExt for State {
             // Tell the client its request was successful.
             if let Some(client) = self.ecs().read_storage::<Client>().get(entity) {
                 client.send_fallible(ServerGeneral::CharacterSuccess);
+                client.send_fallible(ServerGeneral::DangerOverlay(
+                    self.ecs()
+                        .read_resource::<DangerOverlay>()
+                        .compressed()
+                        .clone(),
+                ));
             }
         }
     }
@@ -569,20 +576,37 @@ impl StateExt for State {
             // Tell the client its request was successful.
             if let Some(client) = self.ecs().read_storage::<Client>().get(entity) {
                 client.send_fallible(ServerGeneral::SpectatorSuccess(spawn_point));
+                client.send_fallible(ServerGeneral::DangerOverlay(
+                    self.ecs()
+                        .read_resource::<DangerOverlay>()
+                        .compressed()
+                        .clone(),
+                ));
             }
         }
     }
 
     fn update_character_data(&mut self, entity: EcsEntity, components: PersistedComponents) {
+        use crate::World;
+        use common::{terrain::TerrainChunkSize, vol::RectVolSize};
+        use std::sync::Arc;
+
         let PersistedComponents {
             body,
             stats,
             skill_set,
             inventory,
             waypoint,
+            position,
             pets,
-            active_abilities,
+            mut active_abilities,
             map_marker,
+            mut hotbar,
+            settings,
+            vitals,
+            buffs,
+            lantern_fuel,
+            hardcore,
         } = components;
 
         if let Some(player_uid) = self.read_component_copied::<Uid>(entity) {
@@ -609,12 +633,50 @@ impl StateExt for State {
                     .skill_level(Skill::General(GeneralSkill::EnergyIncrease))
                     .unwrap_or(0),
             );
-            self.write_component_ignore_entity_dead(entity, comp::Health::new(body, health_level));
-            self.write_component_ignore_entity_dead(entity, comp::Energy::new(body, energy_level));
+            let mut health = comp::Health::new(body, health_level);
+            let mut energy = comp::Energy::new(body, energy_level);
+            // Characters come back with the health and energy they left with, which can't
+            // exceed the maximum of the loaded character
+            if let Some((stored_health, stored_energy)) = vitals {
+                health.restore(stored_health);
+                energy.change_by(stored_energy - energy.current());
+            }
+            self.write_component_ignore_entity_dead(entity, health);
+            self.write_component_ignore_entity_dead(entity, energy);
+            let mut character_buffs = comp::Buffs::default();
+            for buff in buffs {
+                character_buffs.insert(buff);
+            }
+            self.write_component_ignore_entity_dead(entity, character_buffs);
+            self.write_component_ignore_entity_dead(
+                entity,
+                lantern_fuel.map_or_else(comp::LanternFuel::full, |remaining| comp::LanternFuel {
+                    remaining,
+                }),
+            );
             self.write_component_ignore_entity_dead(entity, Poise::new(body));
             self.write_component_ignore_entity_dead(entity, stats);
+            // Abilities may have been locked again since the ability set was persisted
+            // (e.g. by a skill group reset)
+            if active_abilities.remove_locked_abilities(Some(&inventory), Some(&skill_set)) {
+                if let Some(client) = self.ecs().read_storage::<Client>().get(entity) {
+                    client.send_fallible(ServerGeneral::server_msg(
+                        ChatType::CommandInfo,
+                        "Your ability set for the equipped weapons used abilities you don't have \
+                         anymore and was reset to the default one.",
+                    ));
+                }
+            }
             self.write_component_ignore_entity_dead(entity, active_abilities);
             self.write_component_ignore_entity_dead(entity, skill_set);
+            // Items may have been changed since the hotbar was last persisted (e.g. by
+            // migrations), so its bindings are checked again
+            let hotbar_error = hotbar.set_bindings(*hotbar.slots(), &inventory).err();
+            if let Some(client) = self.ecs().read_storage::<Client>().get(entity) {
+                client.send_fallible(ServerGeneral::HotbarUpdate(hotbar.clone(), hotbar_error));
+            }
+            self.write_component_ignore_entity_dead(entity, hotbar);
+            self.write_component_ignore_entity_dead(entity, settings);
             self.write_component_ignore_entity_dead(entity, inventory);
             self.write_component_ignore_entity_dead(
                 entity,
@@ -622,9 +684,30 @@ impl StateExt for State {
             );
 
             if let Some(waypoint) = waypoint {
-                self.write_component_ignore_entity_dead(entity, RepositionOnChunkLoad);
                 self.write_component_ignore_entity_dead(entity, waypoint);
-                self.write_component_ignore_entity_dead(entity, comp::Pos(waypoint.get_pos()));
+            }
+            if hardcore {
+                self.write_component_ignore_entity_dead(entity, comp::Hardcore);
+            }
+
+            // Place the character where they logged out, unless that position isn't inside
+            // the world anymore (e.g. because the world was regenerated with a smaller
+            // size)
+            let world_size = self.ecs().read_resource::<Arc<World>>().sim().get_size()
+                * TerrainChunkSize::RECT_SIZE;
+            let spawn_pos = position
+                .map(|pos| pos.0)
+                .filter(|pos| {
+                    pos.map(|e| e.is_finite()).reduce_and()
+                        && pos.x >= 0.0
+                        && pos.y >= 0.0
+                        && pos.x < world_size.x as f32
+                        && pos.y < world_size.y as f32
+                })
+                .or_else(|| waypoint.map(|waypoint| waypoint.get_pos()));
+            if let Some(spawn_pos) = spawn_pos {
+                self.write_component_ignore_entity_dead(entity, RepositionOnChunkLoad);
+                self.write_component_ignore_entity_dead(entity, comp::Pos(spawn_pos));
                 self.write_component_ignore_entity_dead(entity, comp::Vel(Vec3::zero()));
                 self.write_component_ignore_entity_dead(entity, comp::ForceUpdate::forced());
             }
@@ -671,6 +754,10 @@ impl StateExt for State {
                 ..
             }) = presence
             {
+                self.ecs()
+                    .write_resource::<CharacterUpdater>()
+                    .start_session(*char_id);
+
                 let battlemode_buffer = self.ecs().fetch::<BattleModeBuffer>();
                 let mut players = self.ecs().write_storage::<comp::Player>();
                 if let Some((mode, change)) = battlemode_buffer.get(char_id) {
@@ -1031,6 +1118,9 @@ impl StateExt for State {
         // Cancel extant trades
         events::cancel_trades_for(self, entity);
 
+        // Riders of the entity are put down beside it, and its own mount is let go
+        events::unmount_before_deletion(self, entity);
+
         let (maybe_uid, maybe_pos) = (
             self.ecs().read_storage::<Uid>().get(entity).copied(),
             self.ecs().read_storage::<comp::Pos>().get(entity).copied(),
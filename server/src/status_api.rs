@@ -0,0 +1,428 @@
+//! Read-only status API for third party websites, such as live maps.
+//!
+//! The game loop publishes a [`StatusSnapshot`] about once a second and
+//! queries are answered from the latest published snapshot only. Queries never
+//! touch the ECS, and the lock guarding the snapshot is only held to swap or
+//! clone an [`Arc`], so a slow consumer can't stall the tick.
+//!
+//! Queries are served over HTTP by [`StatusApi::serve`], with one of the
+//! configured tokens as a bearer token:
+//!
+//! - `GET /players`: online players, limited to the fields the privacy settings
+//!   share
+//! - `GET /economy`: price indices of the goods of each site
+//!
+//! There are no endpoints for world events or faction territory, as the server
+//! has neither: nothing changes the world while it runs, and sites don't
+//! belong to factions.
+
+use crate::settings::StatusApiSettings;
+use core::future::Future;
+use hashbrown::HashMap;
+use hyper::{
+    header,
+    service::{make_service_fn, service_fn},
+    Body, Method, Request, Response, StatusCode,
+};
+use serde::Serialize;
+use std::{
+    convert::Infallible,
+    fmt,
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use vek::*;
+
+const RATE_LIMIT_WINDOW: Duration = Duration::from_secs(60);
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct PlayerStatus {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub position: Option<Vec2<i32>>,
+    /// Characters don't have levels, so this is the total amount of skill
+    /// points they earned.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub level: Option<u32>,
+}
+
+impl PlayerStatus {
+    /// Only keeps the fields that the privacy settings allow to be shared.
+    pub fn new(settings: &StatusApiSettings, name: &str, pos: Vec3<f32>, level: u32) -> Self {
+        let granularity = settings.position_granularity.max(1) as i32;
+        Self {
+            name: settings.share_player_names.then(|| name.to_owned()),
+            position: settings.share_player_positions.then(|| {
+                pos.xy()
+                    .map(|e| (e as i32).div_euclid(granularity) * granularity + granularity / 2)
+            }),
+            level: settings.share_player_levels.then(|| level),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct SiteEconomy {
+    pub site: String,
+    /// Price index of each good, relative to the global average
+    pub prices: Vec<(String, f32)>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct StatusSnapshot {
+    #[serde(skip)]
+    pub created: Instant,
+    pub players: Vec<PlayerStatus>,
+    pub economy: Vec<SiteEconomy>,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Endpoint {
+    Players,
+    Economy,
+}
+
+impl Endpoint {
+    fn from_path(path: &str) -> Option<Self> {
+        match path {
+            "/players" => Some(Self::Players),
+            "/economy" => Some(Self::Economy),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum StatusApiError {
+    Unauthorized,
+    RateLimited,
+    /// No snapshot was published recently, e.g. because the server is
+    /// stalling.
+    Stale,
+}
+
+impl StatusApiError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            Self::Unauthorized => StatusCode::UNAUTHORIZED,
+            Self::RateLimited => StatusCode::TOO_MANY_REQUESTS,
+            Self::Stale => StatusCode::SERVICE_UNAVAILABLE,
+        }
+    }
+}
+
+impl fmt::Display for StatusApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Unauthorized => write!(f, "Invalid token"),
+            Self::RateLimited => write!(f, "Too many requests"),
+            Self::Stale => write!(f, "No recent status available"),
+        }
+    }
+}
+
+struct RateLimit {
+    window_start: Instant,
+    requests: u32,
+}
+
+/// Handle to the status API, which can be cloned and handed to whatever
+/// serves the requests.
+#[derive(Clone)]
+pub struct StatusApi {
+    settings: Arc<StatusApiSettings>,
+    latest: Arc<Mutex<Option<Arc<StatusSnapshot>>>>,
+    rate_limits: Arc<Mutex<HashMap<String, RateLimit>>>,
+}
+
+impl StatusApi {
+    pub fn new(settings: StatusApiSettings) -> Self {
+        Self {
+            settings: Arc::new(settings),
+            latest: Arc::new(Mutex::new(None)),
+            rate_limits: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    pub fn settings(&self) -> &StatusApiSettings { &self.settings }
+
+    pub fn is_enabled(&self) -> bool { !self.settings.tokens.is_empty() }
+
+    pub fn publish(&self, snapshot: StatusSnapshot) {
+        let snapshot = Arc::new(snapshot);
+        *self.latest.lock().unwrap() = Some(snapshot);
+    }
+
+    /// The latest snapshot, as long as it isn't older than the configured
+    /// maximum age.
+    pub fn snapshot(&self, now: Instant) -> Option<Arc<StatusSnapshot>> {
+        let snapshot = self.latest.lock().unwrap().clone()?;
+        (now.saturating_duration_since(snapshot.created) <= self.settings.max_snapshot_age)
+            .then(|| snapshot)
+    }
+
+    /// Answers a query with the JSON representation of the requested data.
+    pub fn query(
+        &self,
+        token: &str,
+        endpoint: Endpoint,
+        now: Instant,
+    ) -> Result<String, StatusApiError> {
+        if !self.settings.tokens.iter().any(|t| t == token) {
+            return Err(StatusApiError::Unauthorized);
+        }
+
+        {
+            let mut rate_limits = self.rate_limits.lock().unwrap();
+            let limit = rate_limits.entry(token.to_owned()).or_insert(RateLimit {
+                window_start: now,
+                requests: 0,
+            });
+            if now.saturating_duration_since(limit.window_start) >= RATE_LIMIT_WINDOW {
+                limit.window_start = now;
+                limit.requests = 0;
+            }
+            if limit.requests >= self.settings.requests_per_minute {
+                return Err(StatusApiError::RateLimited);
+            }
+            limit.requests += 1;
+        }
+
+        let snapshot = self.snapshot(now).ok_or(StatusApiError::Stale)?;
+        let json = match endpoint {
+            Endpoint::Players => serde_json::to_string(&snapshot.players),
+            Endpoint::Economy => serde_json::to_string(&snapshot.economy),
+        };
+        Ok(json.expect("Status snapshots can always be serialized"))
+    }
+
+    /// Serves queries over HTTP until `shutdown` completes.
+    pub async fn serve(
+        self,
+        addr: SocketAddr,
+        shutdown: impl Future<Output = ()>,
+    ) -> Result<(), hyper::Error> {
+        let make_service = make_service_fn(move |_| {
+            let api = self.clone();
+            async move {
+                Ok::<_, Infallible>(service_fn(move |request| {
+                    let response = api.respond(&request, Instant::now());
+                    async move { Ok::<_, Infallible>(response) }
+                }))
+            }
+        });
+        hyper::Server::try_bind(&addr)?
+            .serve(make_service)
+            .with_graceful_shutdown(shutdown)
+            .await
+    }
+
+    /// Answers an HTTP request, which passes its token as a bearer token.
+    fn respond(&self, request: &Request<Body>, now: Instant) -> Response<Body> {
+        let endpoint = match Endpoint::from_path(request.uri().path()) {
+            Some(endpoint) => endpoint,
+            None => return text_response(StatusCode::NOT_FOUND, "Unknown endpoint"),
+        };
+        if request.method() != Method::GET {
+            return text_response(StatusCode::METHOD_NOT_ALLOWED, "Only GET is supported");
+        }
+        let token = request
+            .headers()
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .unwrap_or_default();
+
+        match self.query(token, endpoint, now) {
+            Ok(json) => Response::builder()
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(json))
+                .expect("Status responses are always valid"),
+            Err(error) => text_response(error.status_code(), &error.to_string()),
+        }
+    }
+}
+
+fn text_response(status: StatusCode, text: &str) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .header(header::CONTENT_TYPE, "text/plain")
+        .body(Body::from(text.to_owned()))
+        .expect("Status responses are always valid")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{sync::mpsc, thread};
+
+    fn settings() -> StatusApiSettings {
+        StatusApiSettings {
+            tokens: vec!["secret".to_owned()],
+            ..Default::default()
+        }
+    }
+
+    fn snapshot(created: Instant) -> StatusSnapshot {
+        StatusSnapshot {
+            created,
+            players: vec![PlayerStatus::new(
+                &settings(),
+                "Alice",
+                Vec3::new(100.0, 2000.0, 50.0),
+                12,
+            )],
+            economy: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn privacy_toggles() {
+        let pos = Vec3::new(1500.0, 3100.0, 80.0);
+
+        // Nothing is shared by default
+        let status = PlayerStatus::new(&StatusApiSettings::default(), "Alice", pos, 12);
+        assert_eq!(status, PlayerStatus::default());
+        assert_eq!(serde_json::to_string(&status).unwrap(), "{}");
+
+        let status = PlayerStatus::new(
+            &StatusApiSettings {
+                share_player_names: true,
+                share_player_levels: true,
+                ..Default::default()
+            },
+            "Alice",
+            pos,
+            12,
+        );
+        assert_eq!(status.name.as_deref(), Some("Alice"));
+        assert_eq!(status.level, Some(12));
+        assert_eq!(status.position, None);
+
+        let status = PlayerStatus::new(
+            &StatusApiSettings {
+                share_player_positions: true,
+                position_granularity: 1000,
+                ..Default::default()
+            },
+            "Alice",
+            pos,
+            12,
+        );
+        assert_eq!(status.name, None);
+        assert_eq!(status.position, Some(Vec2::new(1500, 3500)));
+    }
+
+    #[test]
+    fn snapshot_staleness_bound() {
+        let api = StatusApi::new(settings());
+        let now = Instant::now();
+        assert_eq!(
+            api.query("secret", Endpoint::Players, now),
+            Err(StatusApiError::Stale)
+        );
+
+        api.publish(snapshot(now));
+        assert!(api.query("secret", Endpoint::Players, now).is_ok());
+
+        let max_age = api.settings().max_snapshot_age;
+        assert!(api.snapshot(now + max_age).is_some());
+        assert!(api
+            .snapshot(now + max_age + Duration::from_millis(1))
+            .is_none());
+    }
+
+    #[test]
+    fn tokens_and_rate_limit() {
+        let api = StatusApi::new(StatusApiSettings {
+            requests_per_minute: 2,
+            ..settings()
+        });
+        let now = Instant::now();
+        api.publish(snapshot(now));
+
+        assert_eq!(
+            api.query("wrong", Endpoint::Players, now),
+            Err(StatusApiError::Unauthorized)
+        );
+        assert!(api.query("secret", Endpoint::Players, now).is_ok());
+        assert!(api.query("secret", Endpoint::Economy, now).is_ok());
+        assert_eq!(
+            api.query("secret", Endpoint::Players, now),
+            Err(StatusApiError::RateLimited)
+        );
+
+        let later = now + RATE_LIMIT_WINDOW;
+        api.publish(snapshot(later));
+        assert!(api.query("secret", Endpoint::Players, later).is_ok());
+    }
+
+    #[test]
+    fn slow_consumer_does_not_block_publishing() {
+        let api = StatusApi::new(settings());
+        api.publish(snapshot(Instant::now()));
+
+        let (held_tx, held_rx) = mpsc::channel();
+        let (release_tx, release_rx) = mpsc::channel::<()>();
+        let consumer = {
+            let api = api.clone();
+            thread::spawn(move || {
+                // Hold on to the snapshot as if it was being sent over a slow connection
+                let snapshot = api.snapshot(Instant::now()).unwrap();
+                held_tx.send(()).unwrap();
+                release_rx.recv().unwrap();
+                snapshot.players.len()
+            })
+        };
+
+        held_rx.recv().unwrap();
+        for _ in 0..100 {
+            api.publish(snapshot(Instant::now()));
+        }
+        release_tx.send(()).unwrap();
+
+        assert_eq!(consumer.join().unwrap(), 1);
+    }
+
+    #[test]
+    fn http_requests() {
+        let api = StatusApi::new(settings());
+        let now = Instant::now();
+        api.publish(snapshot(now));
+        let request = |method, path, token: Option<&str>| {
+            let mut request = Request::builder().method(method).uri(path);
+            if let Some(token) = token {
+                request = request.header(header::AUTHORIZATION, format!("Bearer {}", token));
+            }
+            api.respond(&request.body(Body::empty()).unwrap(), now)
+                .status()
+        };
+
+        assert_eq!(
+            request(Method::GET, "/players", Some("secret")),
+            StatusCode::OK
+        );
+        assert_eq!(
+            request(Method::GET, "/economy", Some("secret")),
+            StatusCode::OK
+        );
+        assert_eq!(
+            request(Method::GET, "/players", Some("wrong")),
+            StatusCode::UNAUTHORIZED
+        );
+        assert_eq!(
+            request(Method::GET, "/players", None),
+            StatusCode::UNAUTHORIZED
+        );
+        assert_eq!(
+            request(Method::GET, "/world_events", Some("secret")),
+            StatusCode::NOT_FOUND
+        );
+        assert_eq!(
+            request(Method::POST, "/players", Some("secret")),
+            StatusCode::METHOD_NOT_ALLOWED
+        );
+    }
+}
@@ -24,7 +24,7 @@ use common::{
     combat::perception_dist_multiplier_from_stealth,
     comp::{
         self,
-        agent::{Sound, SoundKind, Target},
+        agent::{Sound, SoundKind, Target, ALARM_CALL_VOLUME, FIRE_VOLUME},
         buff::BuffKind,
         inventory::slot::EquipSlot,
         item::{
@@ -32,9 +32,10 @@ use common::{
             ConsumableKind, Item, ItemDesc, ItemKind,
         },
         item_drop,
+        pet::PetStance,
         projectile::ProjectileConstructor,
-        Agent, Alignment, Body, CharacterState, ControlAction, ControlEvent, Controller, Health,
-        HealthChange, InputKind, InventoryAction, Pos, Scale, UnresolvedChatMsg, UtteranceKind,
+        Agent, Alignment, Body, CharacterState, ControlAction, Controller, Health, HealthChange,
+        InputKind, InventoryAction, Pos, Scale, UnresolvedChatMsg, UtteranceKind,
     },
     effect::{BuffEffect, Effect},
     event::{Emitter, EventBus, ServerEvent},
@@ -42,7 +43,6 @@ use common::{
     rtsim::RtSimEvent,
     states::basic_beam,
     terrain::{Block, TerrainGrid},
-    time::DayPeriod,
     util::Dir,
     vol::ReadVol,
 };
@@ -96,7 +96,6 @@ impl<'a> System<'a> for Sys {
             &read_data.uids,
             &mut agents,
             &mut controllers,
-            read_data.light_emitter.maybe(),
             read_data.groups.maybe(),
             !&read_data.is_mounts,
         )
@@ -118,7 +117,6 @@ impl<'a> System<'a> for Sys {
                     uid,
                     agent,
                     controller,
-                    light_emitter,
                     group,
                     _,
                 )| {
@@ -219,7 +217,6 @@ impl<'a> System<'a> for Sys {
                         traversal_config,
                         scale,
                         damage: health_fraction,
-                        light_emitter,
                         glider_equipped,
                         is_gliding,
                         health: read_data.healths.get(entity),
@@ -227,6 +224,7 @@ impl<'a> System<'a> for Sys {
                         active_abilities,
                         cached_spatial_grid: &read_data.cached_spatial_grid,
                         msm: &read_data.msm,
+                        pet: read_data.pets.get(entity),
                     };
                     ///////////////////////////////////////////////////////////
                     // Behavior tree
@@ -317,32 +315,6 @@ impl<'a> AgentData<'a> {
         read_data: &ReadData,
         rng: &mut impl Rng,
     ) {
-        // Light lanterns at night
-        // TODO Add a method to turn on NPC lanterns underground
-        let lantern_equipped = self
-            .inventory
-            .equipped(EquipSlot::Lantern)
-            .as_ref()
-            .map_or(false, |item| {
-                matches!(&*item.kind(), comp::item::ItemKind::Lantern(_))
-            });
-        let lantern_turned_on = self.light_emitter.is_some();
-        let day_period = DayPeriod::from(read_data.time_of_day.0);
-        // Only emit event for agents that have a lantern equipped
-        if lantern_equipped && rng.gen_bool(0.001) {
-            if day_period.is_dark() && !lantern_turned_on {
-                // Agents with turned off lanterns turn them on randomly once it's
-                // nighttime and keep them on.
-                // Only emit event for agents that sill need to
-                // turn on their lantern.
-                controller.push_event(ControlEvent::EnableLantern)
-            } else if lantern_turned_on && day_period.is_light() {
-                // agents with turned on lanterns turn them off randomly once it's
-                // daytime and keep them off.
-                controller.push_event(ControlEvent::DisableLantern)
-            }
-        };
-
         if let Some(body) = self.body {
             let attempt_heal = if matches!(body, Body::Humanoid(_)) {
                 self.damage < IDLE_HEALING_ITEM_THRESHOLD
@@ -788,7 +760,7 @@ impl<'a> AgentData<'a> {
         let get_pos = |entity| read_data.positions.get(entity);
         let get_enemy = |(entity, attack_target): (EcsEntity, bool)| {
             if attack_target {
-                if self.is_enemy(entity, read_data) {
+                if self.is_enemy(entity, read_data) && self.seeks_out_enemies() {
                     Some((entity, true))
                 } else if can_ambush(entity, read_data) {
                     controller.clone().push_utterance(UtteranceKind::Ambush);
@@ -799,7 +771,11 @@ impl<'a> AgentData<'a> {
                     );
                     aggro_on = true;
                     Some((entity, true))
-                } else if self.should_defend(entity, read_data) {
+                } else if self.should_defend(entity, read_data)
+                    && self
+                        .pet
+                        .map_or(true, |pet| pet.stance != PetStance::Passive)
+                {
                     if let Some(attacker) = get_attacker(entity, read_data) {
                         if !self.passive_towards(attacker, read_data) {
                             // aggro_on: attack immediately, do not warn/menace.
@@ -1595,6 +1571,60 @@ impl<'a> AgentData<'a> {
         }
     }
 
+    /// Warns the rest of the herd that the agent was startled, so that they
+    /// flee from the same danger instead of coming to help
+    fn alarm_call(
+        &self,
+        agent: &Agent,
+        read_data: &ReadData,
+        event_emitter: &mut Emitter<'_, ServerEvent>,
+    ) {
+        if let (Some(body), Some(startle)) = (self.body, agent.startled) {
+            event_emitter.emit(ServerEvent::Sound {
+                sound: Sound::new(
+                    SoundKind::AlarmCall {
+                        body: *body,
+                        source: startle.source,
+                    },
+                    self.pos.0,
+                    ALARM_CALL_VOLUME,
+                    read_data.time.0,
+                ),
+            });
+        }
+    }
+
+    /// The closest campfire, burning entity or burning block, as a sound heard
+    /// by the agent
+    fn nearest_fire(&self, agent: &Agent, read_data: &ReadData) -> Option<Sound> {
+        let search_dist = agent.psyche.search_dist();
+        let burning_block = read_data
+            .fires
+            .nearest(self.pos.0, search_dist)
+            .map(|block| block.as_::<f32>() + 0.5);
+        self.cached_spatial_grid
+            .0
+            .in_circle_aabr(self.pos.0.xy(), search_dist)
+            .filter(|entity| entity != self.entity)
+            .filter(|entity| {
+                read_data
+                    .bodies
+                    .get(*entity)
+                    .map_or(false, Body::is_campfire)
+                    || read_data
+                        .buffs
+                        .get(*entity)
+                        .map_or(false, |buffs| buffs.kinds.contains_key(&BuffKind::Burning))
+            })
+            .filter_map(|entity| read_data.positions.get(entity).map(|pos| pos.0))
+            .chain(burning_block)
+            .min_by_key(|pos| (pos.distance_squared(self.pos.0) * 100.0) as i32)
+            .map(|pos| {
+                Sound::new(SoundKind::Fire, pos, FIRE_VOLUME, read_data.time.0)
+                    .propagated_to(self.pos.0)
+            })
+    }
+
     fn cry_out(
         &self,
         agent: &Agent,
@@ -1691,6 +1721,13 @@ impl<'a> AgentData<'a> {
                 || (is_villager(self.alignment) && is_dressed_as_cultist(entity, read_data)))
     }
 
+    /// Whether the agent attacks enemies it notices, which pets only do in the
+    /// aggressive stance
+    fn seeks_out_enemies(&self) -> bool {
+        self.pet
+            .map_or(true, |pet| pet.stance == PetStance::Aggressive)
+    }
+
     fn will_ambush(&self) -> bool {
         self.health
             .map_or(false, |h| h.current() / h.maximum() > 0.7)
@@ -3,8 +3,9 @@ use common::{
         agent::{
             AgentEvent, Target, TimerAction, DEFAULT_INTERACTION_TIME, TRADE_INTERACTION_TIME,
         },
+        pet::PetStance,
         Agent, Alignment, BehaviorCapability, BehaviorState, Body, BuffKind, ControlAction,
-        ControlEvent, Controller, InputKind, InventoryEvent, UtteranceKind,
+        ControlEvent, Controller, InputKind, InventoryEvent, Pos, UtteranceKind,
     },
     event::{Emitter, ServerEvent},
     path::TraversalConfig,
@@ -22,7 +23,7 @@ use self::interaction::{
 use super::{
     consts::{
         DAMAGE_MEMORY_DURATION, FLEE_DURATION, HEALING_ITEM_THRESHOLD, MAX_FLEE_DIST,
-        MAX_FOLLOW_DIST, NPC_PICKUP_RANGE, RETARGETING_THRESHOLD_SECONDS,
+        MAX_FOLLOW_DIST, MAX_STAY_DIST, NPC_PICKUP_RANGE, RETARGETING_THRESHOLD_SECONDS,
     },
     data::{AgentData, ReadData, TargetData},
     util::{get_entity_by_id, is_dead, is_dead_or_invulnerable, is_invulnerable, stop_pursuing},
@@ -64,6 +65,8 @@ impl BehaviorTree {
                 target_if_attacked,
                 process_inbox_sound_and_hurt,
                 process_inbox_interaction,
+                startle_if_near_fire,
+                flee_if_startled,
                 do_target_tree_if_target_else_do_idle_tree,
             ],
         }
@@ -88,10 +91,15 @@ impl BehaviorTree {
 
     /// Pet BehaviorTree
     ///
-    /// Follow the owner and attack enemies
+    /// Follow the owner (or stay where the owner said) and attack enemies
     pub fn pet() -> Self {
         Self {
-            tree: vec![follow_if_far_away, attack_if_owner_hurt, do_idle_tree],
+            tree: vec![
+                return_to_stay_position,
+                follow_if_far_away,
+                attack_if_owner_hurt,
+                do_idle_tree,
+            ],
         }
     }
 
@@ -257,6 +265,44 @@ fn target_if_attacked(bdata: &mut BehaviorData) -> bool {
     false
 }
 
+/// Prey keeps its distance from campfires and burning creatures
+fn startle_if_near_fire(bdata: &mut BehaviorData) -> bool {
+    if bdata.agent.psyche.startle_volume.is_some() && bdata.rng.gen_bool(0.1) {
+        if let Some(fire) = bdata.agent_data.nearest_fire(bdata.agent, bdata.read_data) {
+            if bdata.agent.startle(
+                &fire,
+                bdata.agent_data.body,
+                bdata.agent_data.alignment,
+                bdata.read_data.time.0,
+            ) {
+                bdata
+                    .agent_data
+                    .alarm_call(bdata.agent, bdata.read_data, bdata.event_emitter);
+            }
+        }
+    }
+    false
+}
+
+/// Flee from whatever startled the agent, regardless of its target. Creatures
+/// that were tamed while startled calm down.
+fn flee_if_startled(bdata: &mut BehaviorData) -> bool {
+    if let Some(startle) = bdata.agent.startled {
+        let owned = matches!(bdata.agent_data.alignment, Some(Alignment::Owned(_)));
+        if !owned && bdata.agent.is_startled(bdata.read_data.time.0) {
+            bdata.agent_data.flee(
+                bdata.agent,
+                bdata.controller,
+                &Pos(startle.source),
+                &bdata.read_data.terrain,
+            );
+            return true;
+        }
+        bdata.agent.startled = None;
+    }
+    false
+}
+
 /// If the agent has a target, do the target tree, else do the idle tree
 ///
 /// This function will never stop the BehaviorTree
@@ -365,8 +411,31 @@ fn untarget(bdata: &mut BehaviorData) -> bool {
     false
 }
 
-// If too far away, then follow the target
+/// If the pet was told to stay and moved away, go back to where it should stay
+fn return_to_stay_position(bdata: &mut BehaviorData) -> bool {
+    if let Some(stay_at) = bdata.agent_data.pet.and_then(|pet| pet.stay_at) {
+        if bdata.agent_data.pos.0.distance_squared(stay_at) > MAX_STAY_DIST.powi(2) {
+            bdata.agent_data.follow(
+                bdata.agent,
+                bdata.controller,
+                &bdata.read_data.terrain,
+                &Pos(stay_at),
+            );
+            return true;
+        }
+    }
+    false
+}
+
+// If too far away, then follow the target, unless told to stay
 fn follow_if_far_away(bdata: &mut BehaviorData) -> bool {
+    if bdata
+        .agent_data
+        .pet
+        .map_or(false, |pet| pet.stay_at.is_some())
+    {
+        return false;
+    }
     if let Some(Target { target, .. }) = bdata.agent.target {
         if let Some(tgt_pos) = bdata.read_data.positions.get(target) {
             let dist_sqrd = bdata.agent_data.pos.0.distance_squared(tgt_pos.0);
@@ -385,9 +454,16 @@ fn follow_if_far_away(bdata: &mut BehaviorData) -> bool {
     false
 }
 
-/// Attack target's attacker (if there is one)
+/// Attack target's attacker (if there is one), unless the pet is passive
 /// Target is the owner in this case
 fn attack_if_owner_hurt(bdata: &mut BehaviorData) -> bool {
+    if bdata
+        .agent_data
+        .pet
+        .map_or(false, |pet| pet.stance == PetStance::Passive)
+    {
+        return false;
+    }
     if let Some(Target { target, .. }) = bdata.agent.target {
         if bdata.read_data.positions.get(target).is_some() {
             let owner_recently_attacked =
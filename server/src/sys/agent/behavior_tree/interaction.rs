@@ -27,6 +27,18 @@ pub fn process_inbox_sound_and_hurt(bdata: &mut BehaviorData) -> bool {
             let sound = bdata.agent.inbox.pop_front();
             match sound {
                 Some(AgentEvent::ServerSound(sound)) => {
+                    if bdata.agent.startle(
+                        &sound,
+                        bdata.agent_data.body,
+                        bdata.agent_data.alignment,
+                        bdata.read_data.time.0,
+                    ) {
+                        bdata.agent_data.alarm_call(
+                            bdata.agent,
+                            bdata.read_data,
+                            bdata.event_emitter,
+                        );
+                    }
                     bdata.agent.sounds_heard.push(sound);
                 },
                 Some(AgentEvent::Hurt) => {
@@ -1,14 +1,14 @@
-use crate::rtsim::Entity as RtSimData;
+use crate::{fire::Fires, rtsim::Entity as RtSimData};
 use common::{
     comp::{
         buff::Buffs, group, item::MaterialStatManifest, ActiveAbilities, Alignment, Body,
-        CharacterState, Combo, Energy, Health, Inventory, LightEmitter, LootOwner, Ori,
-        PhysicsState, Pos, Scale, SkillSet, Stats, Vel,
+        CharacterState, Combo, Energy, Health, Inventory, LootOwner, Ori, Pet, PhysicsState, Pos,
+        Scale, SkillSet, Stats, Vel,
     },
     link::Is,
     mounting::Mount,
     path::TraversalConfig,
-    resources::{DeltaTime, Time, TimeOfDay},
+    resources::{DeltaTime, Time},
     rtsim::RtSimEntity,
     terrain::TerrainGrid,
     uid::{Uid, UidAllocator},
@@ -36,7 +36,6 @@ pub struct AgentData<'a> {
     pub traversal_config: TraversalConfig,
     pub scale: f32,
     pub damage: f32,
-    pub light_emitter: Option<&'a LightEmitter>,
     pub glider_equipped: bool,
     pub is_gliding: bool,
     pub health: Option<&'a Health>,
@@ -44,6 +43,7 @@ pub struct AgentData<'a> {
     pub active_abilities: &'a ActiveAbilities,
     pub cached_spatial_grid: &'a common::CachedSpatialGrid,
     pub msm: &'a MaterialStatManifest,
+    pub pet: Option<&'a Pet>,
 }
 
 pub struct TargetData<'a> {
@@ -131,6 +131,7 @@ pub struct ReadData<'a> {
     pub dt: Read<'a, DeltaTime>,
     pub time: Read<'a, Time>,
     pub cached_spatial_grid: Read<'a, common::CachedSpatialGrid>,
+    pub fires: Read<'a, Fires>,
     pub group_manager: Read<'a, group::GroupManager>,
     pub energies: ReadStorage<'a, Energy>,
     pub positions: ReadStorage<'a, Pos>,
@@ -149,8 +150,6 @@ pub struct ReadData<'a> {
     pub alignments: ReadStorage<'a, Alignment>,
     pub bodies: ReadStorage<'a, Body>,
     pub is_mounts: ReadStorage<'a, Is<Mount>>,
-    pub time_of_day: Read<'a, TimeOfDay>,
-    pub light_emitter: ReadStorage<'a, LightEmitter>,
     #[cfg(feature = "worldgen")]
     pub world: ReadExpect<'a, Arc<world::World>>,
     pub rtsim_entities: ReadStorage<'a, RtSimEntity>,
@@ -159,6 +158,7 @@ pub struct ReadData<'a> {
     pub active_abilities: ReadStorage<'a, ActiveAbilities>,
     pub loot_owners: ReadStorage<'a, LootOwner>,
     pub msm: ReadExpect<'a, MaterialStatManifest>,
+    pub pets: ReadStorage<'a, Pet>,
 }
 
 pub enum Path {
@@ -2,23 +2,32 @@ use super::sentinel::{DeletedEntities, TrackedStorages, UpdateTrackers};
 use crate::{
     client::Client,
     presence::{Presence, RegionSubscription},
+    settings::Settings,
+    sound_occlusion::OcclusionCache,
     Tick,
 };
 use common::{
     calendar::Calendar,
-    comp::{Collider, ForceUpdate, InventoryUpdate, Last, Ori, Pos, Vel},
+    combat::DamageContributor,
+    comp::{Admin, Collider, ForceUpdate, Group, InventoryUpdate, Last, Ori, Pos, Trap, Vel},
     event::EventBus,
-    outcome::Outcome,
+    outcome::{HealthChangeInfo, Outcome},
     region::{Event as RegionEvent, RegionMap},
     resources::{PlayerPhysicsSettings, TimeOfDay},
-    terrain::TerrainChunkSize,
-    uid::Uid,
-    vol::RectVolSize,
+    terrain::{TerrainChunkSize, TerrainGrid},
+    uid::{Uid, UidAllocator},
+    vol::{ReadVol, RectVolSize},
 };
 use common_ecs::{Job, Origin, Phase, System};
-use common_net::{msg::ServerGeneral, sync::CompSyncPackage};
+use common_net::{
+    msg::{EcsCompPacket, ServerGeneral},
+    sync::{CompSyncPackage, CompUpdateKind, EntityPackage},
+};
 use itertools::Either;
-use specs::{Entities, Join, Read, ReadExpect, ReadStorage, Write, WriteStorage};
+use specs::{
+    hibitset::BitSetLike, saveload::MarkerAllocator, BitSet, Entities, Entity as EcsEntity, Join,
+    Read, ReadExpect, ReadStorage, Write, WriteStorage,
+};
 use vek::*;
 
 /// This system will send physics updates to the client
@@ -49,6 +58,11 @@ impl<'a> System<'a> for Sys {
         WriteStorage<'a, InventoryUpdate>,
         Write<'a, DeletedEntities>,
         Read<'a, EventBus<Outcome>>,
+        Read<'a, Settings>,
+        Read<'a, UidAllocator>,
+        ReadStorage<'a, Admin>,
+        ReadExpect<'a, TerrainGrid>,
+        ReadStorage<'a, Trap>,
     );
 
     const NAME: &'static str = "entity_sync";
@@ -79,9 +93,15 @@ impl<'a> System<'a> for Sys {
             mut inventory_updates,
             mut deleted_entities,
             outcomes,
+            settings,
+            uid_allocator,
+            admins,
+            terrain,
+            traps,
         ): Self::SystemData,
     ) {
         let tick = tick.0;
+        let share_exact_stats = settings.gameplay.share_exact_stats;
 
         // Storages already provided in `TrackedStorages` that we need to use
         // for other things besides change detection.
@@ -90,6 +110,15 @@ impl<'a> System<'a> for Sys {
         let inventories = &tracked_storages.inventory;
         let players = &tracked_storages.player;
         let is_rider = &tracked_storages.is_rider;
+        let groups = &tracked_storages.group;
+        let stats_observer = |entity: EcsEntity| {
+            StatsObserver::new(
+                entity,
+                share_exact_stats || admins.contains(entity),
+                uids,
+                groups,
+            )
+        };
 
         // To send entity updates
         // 1. Iterate through regions
@@ -138,11 +167,18 @@ impl<'a> System<'a> for Sys {
                 )
                     .join()
                     .filter_map(|(client, entity, presence, subscription, pos)| {
-                        if presence.is_some() && subscription.regions.contains(&key) {
-                            Some((client, &subscription.regions, entity, *pos))
-                        } else {
-                            None
-                        }
+                        presence
+                            .filter(|_| subscription.regions.contains(&key))
+                            .map(|presence| {
+                                (
+                                    client,
+                                    &subscription.regions,
+                                    entity,
+                                    *pos,
+                                    presence.low_bandwidth,
+                                    stats_observer(entity),
+                                )
+                            })
                     })
                     .collect::<Vec<_>>();
 
@@ -167,16 +203,26 @@ impl<'a> System<'a> for Sys {
                                     )
                                 })
                             {
+                                let target_group = groups.get(entity);
+                                let bucketed_msg =
+                                    ServerGeneral::CreateEntity(bucket_entity_package(pkg.clone()));
                                 let create_msg = ServerGeneral::CreateEntity(pkg);
-                                for (client, regions, client_entity, _) in &mut subscribers {
+                                for (client, regions, client_entity, _, _, observer) in
+                                    &mut subscribers
+                                {
                                     if maybe_key
                                     .as_ref()
                                     .map(|key| !regions.contains(key))
                                     .unwrap_or(true)
                                     // Client doesn't need to know about itself
                                     && *client_entity != entity
+                                    && observer.sees_trap(traps.get(entity))
                                     {
-                                        client.send_fallible(create_msg.clone());
+                                        if observer.sees_exact_stats(entity, target_group) {
+                                            client.send_fallible(create_msg.clone());
+                                        } else {
+                                            client.send_fallible(bucketed_msg.clone());
+                                        }
                                     }
                                 }
                             }
@@ -184,7 +230,7 @@ impl<'a> System<'a> for Sys {
                         RegionEvent::Left(id, maybe_key) => {
                             // Lookup UID for entity
                             if let Some(&uid) = uids.get(entities.entity(*id)) {
-                                for (client, regions, _, _) in &mut subscribers {
+                                for (client, regions, _, _, _, _) in &mut subscribers {
                                     if maybe_key
                                         .as_ref()
                                         .map(|key| !regions.contains(key))
@@ -199,16 +245,30 @@ impl<'a> System<'a> for Sys {
                 }
 
                 // Sync tracked components
+                // Traps are left out, the trap system sends them to whoever notices them
+                let mut synced_entities = BitSet::new();
+                for id in region.entities().iter() {
+                    if !traps.mask().contains(id) {
+                        synced_entities.add(id);
+                    }
+                }
                 // Get deleted entities in this region from DeletedEntities
-                let (entity_sync_package, comp_sync_package) = trackers.create_sync_packages(
+                let (entity_sync_package, mut comp_sync_package) = trackers.create_sync_packages(
                     &tracked_storages,
-                    region.entities(),
+                    &synced_entities,
                     deleted_entities_in_region,
                 );
+                // Health and energy are sent separately to each client since not all of them
+                // get exact values
+                let stats_updates = if share_exact_stats {
+                    Vec::new()
+                } else {
+                    take_stats_updates(&mut comp_sync_package, &uid_allocator, groups)
+                };
                 // We lazily initialize the the synchronization messages in case there are no
                 // clients.
                 let mut entity_comp_sync = Either::Left((entity_sync_package, comp_sync_package));
-                for (client, _, client_entity, _) in &mut subscribers {
+                for (client, _, client_entity, _, _, _) in &mut subscribers {
                     let msg = entity_comp_sync.right_or_else(
                         |(entity_sync_package, comp_sync_package)| {
                             (
@@ -227,11 +287,13 @@ impl<'a> System<'a> for Sys {
                     entity_comp_sync = Either::Right(msg);
                 }
 
-                for (client, _, client_entity, client_pos) in &mut subscribers {
+                for (client, _, client_entity, client_pos, low_bandwidth, observer) in
+                    &mut subscribers
+                {
                     let mut comp_sync_package = CompSyncPackage::new();
 
                     for (_, entity, &uid, (&pos, last_pos), vel, ori, force_update, collider) in (
-                        region.entities(),
+                        &synced_entities,
                         &entities,
                         uids,
                         (&positions, last_pos.mask().maybe()),
@@ -267,22 +329,8 @@ impl<'a> System<'a> for Sys {
                             let distance_sq = client_pos.0.distance_squared(pos.0);
                             let id_staggered_tick = tick + entity.id() as u64;
 
-                            // More entities farther away so checks start there
-                            if distance_sq > 500.0f32.powi(2) {
-                                id_staggered_tick % 32 == 0
-                            } else if distance_sq > 300.0f32.powi(2) {
-                                id_staggered_tick % 16 == 0
-                            } else if distance_sq > 200.0f32.powi(2) {
-                                id_staggered_tick % 8 == 0
-                            } else if distance_sq > 120.0f32.powi(2) {
-                                id_staggered_tick % 6 == 0
-                            } else if distance_sq > 64.0f32.powi(2) {
-                                id_staggered_tick % 3 == 0
-                            } else if distance_sq > 24.0f32.powi(2) {
-                                id_staggered_tick % 2 == 0
-                            } else {
-                                true
-                            }
+                            id_staggered_tick % physics_update_interval(distance_sq, *low_bandwidth)
+                                == 0
                         };
 
                         if last_pos.is_none() {
@@ -308,6 +356,8 @@ impl<'a> System<'a> for Sys {
                         }
                     }
 
+                    add_stats_updates(&mut comp_sync_package, &stats_updates, observer);
+
                     client.send_fallible(ServerGeneral::CompSync(
                         comp_sync_package,
                         force_updates.get(*client_entity).map_or(0, |f| f.counter()),
@@ -378,9 +428,14 @@ impl<'a> System<'a> for Sys {
 
         // Consume/clear the current outcomes and convert them to a vec
         let outcomes = outcomes.recv_all().collect::<Vec<_>>();
+        let get_block = |pos: Vec3<i32>| terrain.get(pos).ok().copied();
 
         // Sync outcomes
-        for (presence, pos, client) in (presences.maybe(), positions.maybe(), &clients).join() {
+        for (entity, presence, pos, client) in
+            (&entities, presences.maybe(), positions.maybe(), &clients).join()
+        {
+            let observer = stats_observer(entity);
+            let low_bandwidth = presence.map_or(false, |presence| presence.low_bandwidth);
             let is_near = |o_pos: Vec3<f32>| {
                 pos.zip_with(presence, |pos, presence| {
                     pos.0.xy().distance_squared(o_pos.xy())
@@ -390,10 +445,33 @@ impl<'a> System<'a> for Sys {
                 })
             };
 
+            // Clients listen from their eyes
+            let listener = pos.map(|pos| {
+                pos.0
+                    + Vec3::unit_z()
+                        * tracked_storages
+                            .body
+                            .get(entity)
+                            .map_or(0.0, |body| body.eye_height())
+            });
+            let mut occlusion_cache = OcclusionCache::default();
+
             let outcomes = outcomes
                 .iter()
                 .filter(|o| o.get_pos().and_then(&is_near).unwrap_or(true))
-                .cloned()
+                .filter(|o| !(low_bandwidth && o.is_cosmetic()))
+                .filter(|o| match o {
+                    Outcome::HealthChange { info, .. } => {
+                        observer.sees_health_change(info, &uid_allocator, groups)
+                    },
+                    _ => true,
+                })
+                .map(|o| {
+                    let occlusion = listener.zip(o.get_pos()).and_then(|(listener, o_pos)| {
+                        occlusion_cache.get(listener, o_pos, get_block)
+                    });
+                    (o.clone(), occlusion)
+                })
                 .collect::<Vec<_>>();
 
             if !outcomes.is_empty() {
@@ -425,3 +503,298 @@ impl<'a> System<'a> for Sys {
         }
     }
 }
+
+/// Fraction of the maximum that health and energy are rounded up to for
+/// clients that don't get exact values.
+const STATS_BUCKET: f32 = 0.1;
+
+/// A client receiving health and energy, used to decide whether it gets exact
+/// values or only values rounded to [`STATS_BUCKET`] (see
+/// [`crate::settings::GameplaySettings::share_exact_stats`]), and which traps
+/// it is sent.
+#[derive(Clone, Copy)]
+pub(super) struct StatsObserver {
+    entity: EcsEntity,
+    uid: Option<Uid>,
+    group: Option<Group>,
+    /// Set for admins, or for everyone if exact stats are shared
+    sees_all: bool,
+}
+
+impl StatsObserver {
+    pub(super) fn new(
+        entity: EcsEntity,
+        sees_all: bool,
+        uids: &ReadStorage<Uid>,
+        groups: &ReadStorage<Group>,
+    ) -> Self {
+        Self {
+            entity,
+            uid: uids.get(entity).copied(),
+            group: groups.get(entity).copied(),
+            sees_all,
+        }
+    }
+
+    /// Exact values are only sent for the client's own entity and members of
+    /// its group
+    pub(super) fn sees_exact_stats(&self, target: EcsEntity, target_group: Option<&Group>) -> bool {
+        self.sees_all
+            || self.entity == target
+            || self
+                .group
+                .map_or(false, |group| target_group == Some(&group))
+    }
+
+    /// Traps are only shown to the entities that noticed them
+    pub(super) fn sees_trap(&self, trap: Option<&Trap>) -> bool {
+        trap.map_or(true, |trap| {
+            self.uid.map_or(false, |uid| trap.is_visible_to(uid))
+        })
+    }
+
+    /// Rounds the health and energy of an entity package unless this observer
+    /// sees the exact stats of the entity
+    pub(super) fn filter_entity_package(
+        &self,
+        package: EntityPackage<EcsCompPacket>,
+        target: EcsEntity,
+        groups: &ReadStorage<Group>,
+    ) -> EntityPackage<EcsCompPacket> {
+        if self.sees_exact_stats(target, groups.get(target)) {
+            package
+        } else {
+            bucket_entity_package(package)
+        }
+    }
+
+    /// Damage numbers would give away exact health, so they are only sent to
+    /// clients which see the exact health of the target anyway, or which
+    /// caused the change (e.g. to show the damage they dealt)
+    fn sees_health_change(
+        &self,
+        info: &HealthChangeInfo,
+        uid_allocator: &UidAllocator,
+        groups: &ReadStorage<Group>,
+    ) -> bool {
+        let caused = match info.by {
+            Some(DamageContributor::Solo(uid)) => self.uid == Some(uid),
+            Some(DamageContributor::Group { entity_uid, group }) => {
+                self.uid == Some(entity_uid) || self.group == Some(group)
+            },
+            None => false,
+        };
+        caused
+            || uid_allocator
+                .retrieve_entity_internal(info.target.0)
+                .map_or(false, |target| {
+                    self.sees_exact_stats(target, groups.get(target))
+                })
+    }
+}
+
+fn bucket_stats(packet: &mut EcsCompPacket) {
+    match packet {
+        EcsCompPacket::Health(health) => *health = health.bucketed(STATS_BUCKET),
+        EcsCompPacket::Energy(energy) => *energy = energy.bucketed(STATS_BUCKET),
+        _ => {},
+    }
+}
+
+fn bucket_entity_package(
+    mut package: EntityPackage<EcsCompPacket>,
+) -> EntityPackage<EcsCompPacket> {
+    package.comps.iter_mut().for_each(bucket_stats);
+    package
+}
+
+/// A health or energy update, ready to be sent both exactly and rounded
+struct StatsUpdate {
+    uid: u64,
+    target: EcsEntity,
+    target_group: Option<Group>,
+    exact: CompUpdateKind<EcsCompPacket>,
+    bucketed: CompUpdateKind<EcsCompPacket>,
+}
+
+/// Moves the health and energy updates out of a package that is shared by all
+/// clients of a region.
+fn take_stats_updates(
+    package: &mut CompSyncPackage<EcsCompPacket>,
+    uid_allocator: &UidAllocator,
+    groups: &ReadStorage<Group>,
+) -> Vec<StatsUpdate> {
+    let (stats_updates, other_updates): (Vec<_>, Vec<_>) =
+        std::mem::take(&mut package.comp_updates)
+            .into_iter()
+            .partition(|(_, update)| {
+                matches!(
+                    update,
+                    CompUpdateKind::Inserted(EcsCompPacket::Health(_) | EcsCompPacket::Energy(_))
+                        | CompUpdateKind::Modified(
+                            EcsCompPacket::Health(_) | EcsCompPacket::Energy(_)
+                        )
+                )
+            });
+    package.comp_updates = other_updates;
+
+    stats_updates
+        .into_iter()
+        .filter_map(|(uid, exact)| {
+            let target = uid_allocator.retrieve_entity_internal(uid)?;
+            let mut bucketed = exact.clone();
+            if let CompUpdateKind::Inserted(packet) | CompUpdateKind::Modified(packet) =
+                &mut bucketed
+            {
+                bucket_stats(packet);
+            }
+            Some(StatsUpdate {
+                uid,
+                target,
+                target_group: groups.get(target).copied(),
+                exact,
+                bucketed,
+            })
+        })
+        .collect()
+}
+
+fn add_stats_updates(
+    package: &mut CompSyncPackage<EcsCompPacket>,
+    stats_updates: &[StatsUpdate],
+    observer: &StatsObserver,
+) {
+    package
+        .comp_updates
+        .extend(stats_updates.iter().map(|update| {
+            if observer.sees_exact_stats(update.target, update.target_group.as_ref()) {
+                (update.uid, update.exact.clone())
+            } else {
+                (update.uid, update.bucketed.clone())
+            }
+        }));
+}
+
+/// Number of ticks between physics updates of an entity at the given squared
+/// distance from a client. Clients in low-bandwidth mode get half as many
+/// updates, even for entities right next to them.
+fn physics_update_interval(distance_sq: f32, low_bandwidth: bool) -> u64 {
+    // More entities farther away so checks start there
+    let interval = if distance_sq > 500.0f32.powi(2) {
+        32
+    } else if distance_sq > 300.0f32.powi(2) {
+        16
+    } else if distance_sq > 200.0f32.powi(2) {
+        8
+    } else if distance_sq > 120.0f32.powi(2) {
+        6
+    } else if distance_sq > 64.0f32.powi(2) {
+        3
+    } else if distance_sq > 24.0f32.powi(2) {
+        2
+    } else {
+        1
+    };
+
+    if low_bandwidth {
+        interval * 2
+    } else {
+        interval
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn low_bandwidth_halves_physics_updates() {
+        for distance in [0.0f32, 30.0, 100.0, 150.0, 250.0, 400.0, 1000.0] {
+            let distance_sq = distance.powi(2);
+            assert_eq!(
+                physics_update_interval(distance_sq, true),
+                physics_update_interval(distance_sq, false) * 2,
+            );
+        }
+        assert_eq!(physics_update_interval(0.0, false), 1);
+    }
+
+    #[test]
+    fn only_group_members_get_exact_stats() {
+        use common::comp::{self, group, Body, Energy, Health, HealthChange};
+        use common_net::sync::WorldSyncExt;
+        use specs::{Builder, World, WorldExt};
+
+        let mut world = World::new();
+        world.register_sync_marker();
+        world.register::<Group>();
+
+        // Any group will do, as long as the target and one of the observers share it
+        let target = world.create_entity_synced().with(group::NPC).build();
+        let group_member = world.create_entity_synced().with(group::NPC).build();
+        let stranger = world.create_entity_synced().build();
+
+        let body = Body::Humanoid(comp::humanoid::Body::random());
+        let mut health = Health::new(body, 0);
+        health.change_by(HealthChange {
+            amount: -health.maximum() * 0.33,
+            by: None,
+            cause: None,
+            time: common::resources::Time(0.0),
+            crit: false,
+            instance: 0,
+        });
+        let mut energy = Energy::new(body, 0);
+        energy.change_by(-energy.maximum() * 0.55);
+        let target_uid = *world.read_storage::<Uid>().get(target).unwrap();
+
+        let mut shared_package = CompSyncPackage::<EcsCompPacket>::new();
+        shared_package.comp_modified(target_uid, health.clone());
+        shared_package.comp_inserted(target_uid, energy);
+        shared_package.comp_modified(target_uid, Pos(Vec3::zero()));
+
+        let uids = world.read_storage::<Uid>();
+        let groups = world.read_storage::<Group>();
+        let stats_updates = take_stats_updates(
+            &mut shared_package,
+            &world.read_resource::<UidAllocator>(),
+            &groups,
+        );
+        // Only the position is left to be shared by all clients
+        assert_eq!(shared_package.comp_updates.len(), 1);
+
+        let captured_stats = |observer: EcsEntity| {
+            let observer = StatsObserver::new(observer, false, &uids, &groups);
+            let mut package = CompSyncPackage::new();
+            add_stats_updates(&mut package, &stats_updates, &observer);
+            let mut captured = (None, None);
+            for (uid, update) in package.comp_updates {
+                assert_eq!(uid, u64::from(target_uid));
+                match update {
+                    CompUpdateKind::Modified(EcsCompPacket::Health(health)) => {
+                        captured.0 = Some(health.current())
+                    },
+                    CompUpdateKind::Inserted(EcsCompPacket::Energy(energy)) => {
+                        captured.1 = Some(energy.current())
+                    },
+                    update => panic!("Unexpected update {:?}", update),
+                }
+            }
+            captured
+        };
+
+        let exact = (Some(health.current()), Some(energy.current()));
+        let bucketed = (
+            Some(health.bucketed(STATS_BUCKET).current()),
+            Some(energy.bucketed(STATS_BUCKET).current()),
+        );
+        assert_ne!(exact, bucketed);
+        assert_eq!(captured_stats(target), exact);
+        assert_eq!(captured_stats(group_member), exact);
+        assert_eq!(captured_stats(stranger), bucketed);
+        // The stranger only learns the rounded fractions
+        let (health_seen, energy_seen) = captured_stats(stranger);
+        assert!((health_seen.unwrap() / health.maximum() - 0.7).abs() < f32::EPSILON);
+        assert!((energy_seen.unwrap() / energy.maximum() - 0.5).abs() < f32::EPSILON);
+    }
+}
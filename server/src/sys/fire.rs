@@ -0,0 +1,141 @@
+use crate::{
+    fire::{Fires, NEIGHBOURS},
+    settings::Settings,
+    sys::{terrain::TerrainPersistenceData, SysScheduler},
+};
+use common::{
+    comp::{
+        fluid_dynamics::LiquidKind, Buff, BuffCategory, BuffChange, BuffData, BuffKind, BuffSource,
+        Buffs, Health, Pos,
+    },
+    event::{EventBus, ServerEvent},
+    resources::Time,
+    terrain::TerrainGrid,
+    vol::ReadVol,
+    weather::WeatherGrid,
+};
+use common_ecs::{Job, Origin, Phase, System};
+use common_state::{BlockChange, TerrainChanges};
+use specs::{Entities, Join, Read, ReadExpect, ReadStorage, Write};
+use std::time::Duration;
+use vek::*;
+
+/// Rain above which fires are put out
+const RAIN_THRESHOLD: f32 = 0.1;
+
+/// This system spreads fire between flammable blocks, burns them away and
+/// sets entities standing in fire on fire. Burning entities and lava set the
+/// blocks they touch on fire in turn.
+#[derive(Default)]
+pub struct Sys;
+impl<'a> System<'a> for Sys {
+    type SystemData = (
+        Entities<'a>,
+        Read<'a, Time>,
+        Read<'a, Settings>,
+        Read<'a, EventBus<ServerEvent>>,
+        ReadExpect<'a, TerrainGrid>,
+        ReadExpect<'a, WeatherGrid>,
+        Read<'a, TerrainChanges>,
+        Write<'a, BlockChange>,
+        Write<'a, Fires>,
+        Write<'a, SysScheduler<Self>>,
+        ReadStorage<'a, Pos>,
+        ReadStorage<'a, Buffs>,
+        ReadStorage<'a, Health>,
+        TerrainPersistenceData<'a>,
+    );
+
+    const NAME: &'static str = "fire";
+    const ORIGIN: Origin = Origin::Server;
+    const PHASE: Phase = Phase::Create;
+
+    fn run(
+        _job: &mut Job<Self>,
+        (
+            entities,
+            time,
+            settings,
+            server_bus,
+            terrain,
+            weather_grid,
+            terrain_changes,
+            mut block_change,
+            mut fires,
+            mut scheduler,
+            positions,
+            buffs,
+            healths,
+            mut _terrain_persistence,
+        ): Self::SystemData,
+    ) {
+        let settings = &settings.gameplay.fire;
+        let get_block = |pos: Vec3<i32>| terrain.get(pos).ok().copied();
+        let is_lava = |pos: Vec3<i32>| {
+            get_block(pos).and_then(|block| block.liquid_kind()) == Some(LiquidKind::Lava)
+        };
+
+        // Blocks that changed next to lava catch fire, this has to happen every tick
+        // as the changes are only known for one tick
+        for pos in terrain_changes.modified_blocks.keys() {
+            if is_lava(*pos) {
+                for offset in NEIGHBOURS {
+                    fires.ignite(*pos + offset, get_block, *time, settings);
+                }
+            } else if NEIGHBOURS.iter().any(|offset| is_lava(*pos + offset)) {
+                fires.ignite(*pos, get_block, *time, settings);
+            }
+        }
+
+        if !scheduler.should_run() {
+            return;
+        }
+
+        // Burning entities set the blocks they stand on on fire
+        for (pos, buffs) in (&positions, &buffs).join() {
+            if buffs.kinds.contains_key(&BuffKind::Burning) {
+                let below = pos.0.map(|e| e.floor() as i32) - Vec3::unit_z();
+                fires.ignite(below, get_block, *time, settings);
+            }
+        }
+
+        let changes = fires.tick(*time, settings, &mut rand::thread_rng(), get_block, |pos| {
+            weather_grid.get_interpolated(pos.xy().as_()).rain > RAIN_THRESHOLD
+        });
+        for change in changes {
+            let (edits, burnt) = change.block_edits(get_block, settings.destroy_blocks);
+            for (pos, block) in edits {
+                block_change.try_set(pos, block);
+            }
+            if let Some((pos, block)) = burnt {
+                let _was_set = block_change.try_set(pos, block).is_some();
+                #[cfg(feature = "persistent_world")]
+                if _was_set {
+                    if let Some(terrain_persistence) = _terrain_persistence.as_mut() {
+                        terrain_persistence.set_block(pos, block);
+                    }
+                }
+            }
+        }
+
+        if fires.is_empty() {
+            return;
+        }
+        // Entities standing in fire catch fire
+        let mut server_emitter = server_bus.emitter();
+        for (entity, pos, _) in (&entities, &positions, &healths).join() {
+            let below = pos.0.map(|e| e.floor() as i32) - Vec3::unit_z();
+            if fires.is_burning(below) {
+                server_emitter.emit(ServerEvent::Buff {
+                    entity,
+                    buff_change: BuffChange::Add(Buff::new(
+                        BuffKind::Burning,
+                        BuffData::new(5.0, Some(Duration::from_secs(5))),
+                        vec![BuffCategory::Natural],
+                        BuffSource::World,
+                    )),
+                });
+            }
+        }
+    }
+}
@@ -0,0 +1,51 @@
+use common::{
+    comp::{Agent, BuffChange, KnockedOut},
+    event::{EventBus, ServerEvent},
+    resources::Time,
+};
+use common_ecs::{Job, Origin, Phase, System};
+use specs::{Entities, Join, Read, WriteStorage};
+
+/// This system wakes up knocked out creatures that weren't captured in time,
+/// giving them their agent back and enraging them
+#[derive(Default)]
+pub struct Sys;
+impl<'a> System<'a> for Sys {
+    type SystemData = (
+        Entities<'a>,
+        Read<'a, Time>,
+        Read<'a, EventBus<ServerEvent>>,
+        WriteStorage<'a, KnockedOut>,
+        WriteStorage<'a, Agent>,
+    );
+
+    const NAME: &'static str = "knockout";
+    const ORIGIN: Origin = Origin::Server;
+    const PHASE: Phase = Phase::Create;
+
+    fn run(
+        _job: &mut Job<Self>,
+        (entities, time, server_bus, mut knocked_out, mut agents): Self::SystemData,
+    ) {
+        let mut server_emitter = server_bus.emitter();
+
+        let woken = (&entities, &knocked_out)
+            .join()
+            .filter(|(_, knocked_out)| knocked_out.has_woken(*time))
+            .map(|(entity, _)| entity)
+            .collect::<Vec<_>>();
+
+        for entity in woken {
+            if let Some(agent) = knocked_out
+                .remove(entity)
+                .and_then(|knocked_out| knocked_out.agent)
+            {
+                let _ = agents.insert(entity, agent);
+            }
+            server_emitter.emit(ServerEvent::Buff {
+                entity,
+                buff_change: BuffChange::Add(KnockedOut::enrage()),
+            });
+        }
+    }
+}
@@ -0,0 +1,151 @@
+use crate::{client::Client, events::set_lantern, presence::RegionSubscription, sys::SysScheduler};
+use common::{
+    comp::{Agent, Health, Inventory, LightEmitter, Pos},
+    region::RegionMap,
+    resources::TimeOfDay,
+    time::DayPeriod,
+    uid::Uid,
+};
+use common_ecs::{Job, Origin, Phase, System};
+use common_net::msg::ServerGeneral;
+use hashbrown::HashMap;
+use specs::{Entities, Join, Read, ReadExpect, ReadStorage, Write, WriteStorage};
+use vek::Vec2;
+
+/// Whether an NPC carrying a lantern should light it (`Some(true)`) or put it
+/// out (`Some(false)`) at this time of day, `None` if it is fine as it is.
+pub fn lantern_toggle(day_period: DayPeriod, lantern_lit: bool) -> Option<bool> {
+    let should_be_lit = day_period.is_dark();
+    (lantern_lit != should_be_lit).then_some(should_be_lit)
+}
+
+/// This system lights the lanterns of NPCs at dusk and puts them out at dawn.
+/// The changes are collected per region, so that each client only has to be
+/// checked once against the regions it is subscribed to.
+#[derive(Default)]
+pub struct Sys;
+impl<'a> System<'a> for Sys {
+    type SystemData = (
+        Entities<'a>,
+        Read<'a, TimeOfDay>,
+        ReadExpect<'a, RegionMap>,
+        Write<'a, SysScheduler<Self>>,
+        ReadStorage<'a, Uid>,
+        ReadStorage<'a, Pos>,
+        ReadStorage<'a, Agent>,
+        ReadStorage<'a, Health>,
+        ReadStorage<'a, Inventory>,
+        ReadStorage<'a, Client>,
+        ReadStorage<'a, RegionSubscription>,
+        WriteStorage<'a, LightEmitter>,
+    );
+
+    const NAME: &'static str = "lantern";
+    const ORIGIN: Origin = Origin::Server;
+    const PHASE: Phase = Phase::Create;
+
+    fn run(
+        _job: &mut Job<Self>,
+        (
+            entities,
+            time_of_day,
+            region_map,
+            mut scheduler,
+            uids,
+            positions,
+            agents,
+            healths,
+            inventories,
+            clients,
+            subscriptions,
+            mut light_emitters,
+        ): Self::SystemData,
+    ) {
+        if !scheduler.should_run() {
+            return;
+        }
+
+        let day_period = DayPeriod::from(time_of_day.0);
+        let toggles = (&entities, &agents, &inventories, healths.maybe())
+            .join()
+            .filter(|(_, _, inventory, health)| {
+                inventory.lantern_light().is_some() && health.map_or(true, |h| !h.is_dead)
+            })
+            .filter_map(|(entity, _, inventory, _)| {
+                let lantern_lit = light_emitters
+                    .get(entity)
+                    .map_or(false, |light| light.strength > 0.0);
+                lantern_toggle(day_period, lantern_lit).map(|enable| (entity, inventory, enable))
+            })
+            .collect::<Vec<_>>();
+
+        let mut updates = HashMap::<Vec2<i32>, Vec<ServerGeneral>>::new();
+        for (entity, inventory, enable) in toggles {
+            if !set_lantern(&mut light_emitters, entity, Some(inventory), enable) {
+                continue;
+            }
+            if let Some((uid, region)) = uids.get(entity).zip(
+                positions
+                    .get(entity)
+                    .and_then(|pos| region_map.find_region(entity, pos.0)),
+            ) {
+                updates
+                    .entry(region)
+                    .or_default()
+                    .push(ServerGeneral::LanternUpdate(
+                        *uid,
+                        light_emitters.get(entity).copied(),
+                    ));
+            }
+        }
+
+        if updates.is_empty() {
+            return;
+        }
+        for (client, subscription) in (&clients, &subscriptions).join() {
+            for msg in subscription
+                .regions
+                .iter()
+                .filter_map(|region| updates.get(region))
+                .flatten()
+            {
+                client.send_fallible(msg.clone());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HOUR: f64 = 60.0 * 60.0;
+
+    #[test]
+    fn lanterns_toggle_once_per_phase_change() {
+        let mut lantern_lit = false;
+        let mut toggles = Vec::new();
+        // Two days starting at noon, in steps of ten minutes
+        for step in 0..(48 * 6) {
+            let time_of_day = 12.0 * HOUR + step as f64 * HOUR / 6.0;
+            if let Some(enable) = lantern_toggle(DayPeriod::from(time_of_day), lantern_lit) {
+                lantern_lit = enable;
+                toggles.push(((time_of_day / HOUR) as u32, enable));
+            }
+        }
+        // Lit at dusk and put out at dawn, on both days
+        assert_eq!(toggles, vec![
+            (19, true),
+            (30, false),
+            (43, true),
+            (54, false)
+        ]);
+    }
+
+    #[test]
+    fn lantern_state_matching_time_is_kept() {
+        assert_eq!(lantern_toggle(DayPeriod::Night, true), None);
+        assert_eq!(lantern_toggle(DayPeriod::Noon, false), None);
+        assert_eq!(lantern_toggle(DayPeriod::Evening, true), Some(false));
+    }
+}
@@ -0,0 +1,63 @@
+use crate::client::Client;
+use common::{
+    comp::{ChatType, Inventory, LanternFuel, LightEmitter},
+    event::{EventBus, ServerEvent},
+    resources::DeltaTime,
+};
+use common_ecs::{Job, Origin, Phase, System};
+use common_net::msg::ServerGeneral;
+use specs::{Entities, Join, Read, ReadStorage, WriteStorage};
+
+/// This system burns the fuel of lit lanterns, and puts them out once they run
+/// out of it
+#[derive(Default)]
+pub struct Sys;
+impl<'a> System<'a> for Sys {
+    type SystemData = (
+        Entities<'a>,
+        Read<'a, DeltaTime>,
+        Read<'a, EventBus<ServerEvent>>,
+        ReadStorage<'a, Inventory>,
+        ReadStorage<'a, LightEmitter>,
+        ReadStorage<'a, Client>,
+        WriteStorage<'a, LanternFuel>,
+    );
+
+    const NAME: &'static str = "lantern_fuel";
+    const ORIGIN: Origin = Origin::Server;
+    const PHASE: Phase = Phase::Create;
+
+    fn run(
+        _job: &mut Job<Self>,
+        (entities, dt, server_bus, inventories, light_emitters, clients, mut fuels): Self::SystemData,
+    ) {
+        let mut server_emitter = server_bus.emitter();
+
+        for (entity, inventory, light, fuel, client) in (
+            &entities,
+            &inventories,
+            &light_emitters,
+            &mut fuels,
+            clients.maybe(),
+        )
+            .join()
+        {
+            if light.strength <= 0.0 || fuel.is_empty() {
+                continue;
+            }
+            let burn_rate = match inventory.lantern_burn_rate() {
+                Some(burn_rate) => burn_rate,
+                None => continue,
+            };
+            if fuel.burn(burn_rate, dt.0) {
+                if let Some(client) = client {
+                    client.send_fallible(ServerGeneral::server_msg(
+                        ChatType::Meta,
+                        "Your lantern ran out of fuel.",
+                    ));
+                }
+                server_emitter.emit(ServerEvent::DisableLantern(entity));
+            }
+        }
+    }
+}
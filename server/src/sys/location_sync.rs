@@ -0,0 +1,60 @@
+#[cfg(not(feature = "worldgen"))]
+use crate::test_world::{IndexOwned, World};
+#[cfg(feature = "worldgen")]
+use world::{IndexOwned, World};
+
+use crate::{client::Client, danger::DangerOverlay, location::location_at, presence::Presence};
+use common::{comp::Pos, location::LocationTracker};
+use common_ecs::{Job, Origin, Phase, System};
+use common_net::msg::ServerGeneral;
+use specs::{Entities, Join, ReadExpect, ReadStorage, WriteStorage};
+use std::sync::Arc;
+
+/// This system tells in-game clients when their player enters an area with a
+/// different site, biome or danger
+#[derive(Default)]
+pub struct Sys;
+impl<'a> System<'a> for Sys {
+    type SystemData = (
+        Entities<'a>,
+        ReadExpect<'a, Arc<World>>,
+        ReadExpect<'a, IndexOwned>,
+        ReadExpect<'a, DangerOverlay>,
+        ReadStorage<'a, Client>,
+        ReadStorage<'a, Presence>,
+        ReadStorage<'a, Pos>,
+        WriteStorage<'a, LocationTracker>,
+    );
+
+    const NAME: &'static str = "location_sync";
+    const ORIGIN: Origin = Origin::Server;
+    const PHASE: Phase = Phase::Create;
+
+    fn run(
+        _job: &mut Job<Self>,
+        (entities, world, index, danger_overlay, clients, presences, positions, mut trackers): Self::SystemData,
+    ) {
+        // Players that went back to the character screen are told their location
+        // again when they enter the game
+        let left = (&entities, &trackers, !&presences)
+            .join()
+            .map(|(entity, _, _)| entity)
+            .collect::<Vec<_>>();
+        for entity in left {
+            trackers.remove(entity);
+        }
+
+        let index = index.as_index_ref();
+        for (entity, client, _, pos) in (&entities, &clients, &presences, &positions).join() {
+            let tracker = match trackers.entry(entity) {
+                Ok(entry) => entry.or_insert_with(LocationTracker::default),
+                Err(_) => continue,
+            };
+            if let Some(location) = tracker.update(pos.0.xy(), |wpos| {
+                location_at(&world, index, &danger_overlay, wpos)
+            }) {
+                client.send_fallible(ServerGeneral::LocationChanged(location.clone()));
+            }
+        }
+    }
+}
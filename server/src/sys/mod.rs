@@ -2,17 +2,28 @@ pub mod agent;
 pub mod chunk_send;
 pub mod chunk_serialize;
 pub mod entity_sync;
+pub mod fire;
 pub mod invite_timeout;
+pub mod knockout;
+pub mod lantern;
+pub mod lantern_fuel;
+pub mod location_sync;
 pub mod loot;
 pub mod metrics;
 pub mod msg;
 pub mod object;
 pub mod persistence;
 pub mod pets;
+pub mod possession;
+pub mod pregen;
 pub mod sentinel;
+pub mod sitting;
+pub mod status_snapshot;
 pub mod subscription;
 pub mod terrain;
 pub mod terrain_sync;
+pub mod training_dummy;
+pub mod trap;
 pub mod waypoint;
 pub mod wiring;
 
@@ -25,17 +36,31 @@ use std::{
 };
 
 pub type PersistenceScheduler = SysScheduler<persistence::Sys>;
+pub type LanternScheduler = SysScheduler<lantern::Sys>;
+pub type FireScheduler = SysScheduler<fire::Sys>;
+pub type StatusSnapshotScheduler = SysScheduler<status_snapshot::Sys>;
 
 pub fn add_server_systems(dispatch_builder: &mut DispatcherBuilder) {
     dispatch::<melee::Sys>(dispatch_builder, &[&projectile::Sys::sys_name()]);
     //Note: server should not depend on interpolation system
     dispatch::<agent::Sys>(dispatch_builder, &[]);
     dispatch::<terrain::Sys>(dispatch_builder, &[&msg::terrain::Sys::sys_name()]);
+    dispatch::<pregen::Sys>(dispatch_builder, &[&terrain::Sys::sys_name()]);
     dispatch::<waypoint::Sys>(dispatch_builder, &[]);
     dispatch::<invite_timeout::Sys>(dispatch_builder, &[]);
     dispatch::<persistence::Sys>(dispatch_builder, &[]);
     dispatch::<object::Sys>(dispatch_builder, &[]);
+    dispatch::<trap::Sys>(dispatch_builder, &[]);
+    dispatch::<possession::Sys>(dispatch_builder, &[]);
+    dispatch::<sitting::Sys>(dispatch_builder, &[]);
+    dispatch::<training_dummy::Sys>(dispatch_builder, &[]);
+    dispatch::<knockout::Sys>(dispatch_builder, &[]);
+    dispatch::<lantern::Sys>(dispatch_builder, &[]);
+    dispatch::<lantern_fuel::Sys>(dispatch_builder, &[]);
+    dispatch::<fire::Sys>(dispatch_builder, &[]);
     dispatch::<wiring::Sys>(dispatch_builder, &[]);
+    dispatch::<location_sync::Sys>(dispatch_builder, &[]);
+    dispatch::<status_snapshot::Sys>(dispatch_builder, &[]);
     // no dependency, as we only work once per sec anyway.
     dispatch::<chunk_serialize::Sys>(dispatch_builder, &[]);
     // don't depend on chunk_serialize, as we assume everything is done in a SlowJow
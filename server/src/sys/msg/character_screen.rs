@@ -2,7 +2,10 @@ use crate::{
     automod::AutoMod,
     character_creator,
     client::Client,
-    persistence::{character_loader::CharacterLoader, character_updater::CharacterUpdater},
+    persistence::{
+        character_loader::{CharacterListCache, CharacterLoader},
+        character_updater::CharacterUpdater,
+    },
     presence::Presence,
     EditableSettings,
 };
@@ -15,7 +18,7 @@ use common_ecs::{Job, Origin, Phase, System};
 use common_net::msg::{ClientGeneral, ServerGeneral};
 use specs::{Entities, Join, Read, ReadExpect, ReadStorage, WriteExpect};
 use std::sync::{atomic::Ordering, Arc};
-use tracing::debug;
+use tracing::{debug, warn};
 
 impl Sys {
     fn handle_client_character_screen_msg(
@@ -23,6 +26,7 @@ impl Sys {
         entity: specs::Entity,
         client: &Client,
         character_loader: &ReadExpect<'_, CharacterLoader>,
+        character_list_cache: &ReadExpect<'_, CharacterListCache>,
         character_updater: &mut WriteExpect<'_, CharacterUpdater>,
         uids: &ReadStorage<'_, Uid>,
         players: &ReadStorage<'_, Player>,
@@ -129,14 +133,38 @@ impl Sys {
             },
             ClientGeneral::RequestCharacterList => {
                 if let Some(player) = players.get(entity) {
-                    character_loader.load_character_list(entity, player.uuid().to_string())
+                    let player_uuid = player.uuid().to_string();
+                    // Only ask the database if the list has changed since it was last loaded
+                    match character_list_cache.get(&player_uuid) {
+                        Some(list) => {
+                            client.send(ServerGeneral::CharacterListUpdate(list.clone()))?
+                        },
+                        // The list has to be loaded after the saves, e.g. of the character the
+                        // player just left, or it would be cached out of date
+                        None if character_updater.has_unwritten_saves() => {
+                            character_updater.load_character_list(entity, player_uuid)
+                        },
+                        None => character_loader.load_character_list(entity, player_uuid),
+                    }
                 }
             },
+            ClientGeneral::CreateCharacter { .. }
+            | ClientGeneral::EditCharacter { .. }
+            | ClientGeneral::DeleteCharacter(_)
+            | ClientGeneral::RestoreCharacter(_)
+                if character_updater.is_busy() =>
+            {
+                warn!("Refusing character list change, the persistence queue is full");
+                client.send(ServerGeneral::CharacterActionError(
+                    "The server is busy saving characters, please try again in a moment".to_owned(),
+                ))?;
+            },
             ClientGeneral::CreateCharacter {
                 alias,
                 mainhand,
                 offhand,
                 body,
+                hardcore,
             } => {
                 if censor.check(&alias) {
                     debug!(?alias, "denied alias as it contained a banned word");
@@ -152,6 +180,7 @@ impl Sys {
                         mainhand.clone(),
                         offhand.clone(),
                         body,
+                        hardcore,
                         character_updater,
                     ) {
                         debug!(
@@ -199,6 +228,15 @@ impl Sys {
                     );
                 }
             },
+            ClientGeneral::RestoreCharacter(character_id) => {
+                if let Some(player) = players.get(entity) {
+                    character_updater.restore_character(
+                        entity,
+                        player.uuid().to_string(),
+                        character_id,
+                    );
+                }
+            },
             _ => {
                 debug!("Kicking possibly misbehaving client due to invalid character request");
                 server_emitter.emit(ServerEvent::ClientDisconnect(
@@ -219,6 +257,7 @@ impl<'a> System<'a> for Sys {
         Entities<'a>,
         Read<'a, EventBus<ServerEvent>>,
         ReadExpect<'a, CharacterLoader>,
+        ReadExpect<'a, CharacterListCache>,
         WriteExpect<'a, CharacterUpdater>,
         ReadStorage<'a, Uid>,
         ReadStorage<'a, Client>,
@@ -240,6 +279,7 @@ impl<'a> System<'a> for Sys {
             entities,
             server_event_bus,
             character_loader,
+            character_list_cache,
             mut character_updater,
             uids,
             clients,
@@ -260,6 +300,7 @@ impl<'a> System<'a> for Sys {
                     entity,
                     client,
                     &character_loader,
+                    &character_list_cache,
                     &mut character_updater,
                     &uids,
                     &players,
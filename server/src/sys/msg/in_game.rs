@@ -3,8 +3,9 @@ use crate::TerrainPersistence;
 use crate::{client::Client, presence::Presence, Settings};
 use common::{
     comp::{
-        Admin, AdminRole, CanBuild, ControlEvent, Controller, ForceUpdate, Health, Ori, Player,
-        Pos, SkillSet, Vel,
+        hotbar::HotbarAction, slot::Slot, Admin, AdminRole, CanBuild, ControlAction,
+        ControlEvent, Controller, ForceUpdate, Health, Hotbar, Inventory, InventoryAction, Ori,
+        Player, Pos, SkillSet, Vel,
     },
     event::{EventBus, ServerEvent},
     link::Is,
@@ -44,6 +45,8 @@ impl Sys {
         velocities: &mut WriteStorage<'_, Vel>,
         orientations: &mut WriteStorage<'_, Ori>,
         controllers: &mut WriteStorage<'_, Controller>,
+        hotbars: &mut WriteStorage<'_, Hotbar>,
+        inventories: &ReadStorage<'_, Inventory>,
         settings: &Read<'_, Settings>,
         build_areas: &Read<'_, BuildAreas>,
         player_physics_settings: &mut Write<'_, PlayerPhysicsSettings>,
@@ -276,6 +279,9 @@ impl Sys {
             } => {
                 presence.lossy_terrain_compression = lossy_terrain_compression;
             },
+            ClientGeneral::RequestLowBandwidth { low_bandwidth } => {
+                presence.low_bandwidth = low_bandwidth;
+            },
             ClientGeneral::AcknowledgePersistenceLoadError => {
                 skill_sets
                     .get_mut(entity)
@@ -284,6 +290,45 @@ impl Sys {
             ClientGeneral::UpdateMapMarker(update) => {
                 server_emitter.emit(ServerEvent::UpdateMapMarker { entity, update });
             },
+            ClientGeneral::SetHotbar(bindings) => {
+                if let (Some(hotbar), Some(inventory)) =
+                    (hotbars.get_mut(entity), inventories.get(entity))
+                {
+                    let error = hotbar.set_bindings(bindings, inventory).err();
+                    client.send(ServerGeneral::HotbarUpdate(hotbar.clone(), error))?;
+                }
+            },
+            ClientGeneral::ActivateHotbarSlot(slot) => {
+                if presence.kind.controlling_char() {
+                    if let (Some(hotbar), Some(inventory), Some(controller)) = (
+                        hotbars.get_mut(entity),
+                        inventories.get(entity),
+                        controllers.get_mut(entity),
+                    ) {
+                        // The inventory may have changed since the binding was set, so it is
+                        // resolved again here rather than trusting the client
+                        match hotbar.activate(slot, inventory) {
+                            Ok(HotbarAction::Use(inv_slot)) => controller.push_action(
+                                ControlAction::InventoryAction(InventoryAction::Use(
+                                    Slot::Inventory(inv_slot),
+                                )),
+                            ),
+                            Ok(HotbarAction::SwapEquippedWeapons) => {
+                                controller.push_action(ControlAction::SwapEquippedWeapons)
+                            },
+                            Err(error) => client.send(ServerGeneral::HotbarUpdate(
+                                hotbar.clone(),
+                                Some(error),
+                            ))?,
+                        }
+                    }
+                }
+            },
+            ClientGeneral::ChangeAppearance(body) => {
+                if presence.kind.controlling_char() {
+                    server_emitter.emit(ServerEvent::ChangeAppearance { entity, body });
+                }
+            },
             ClientGeneral::SpectatePosition(pos) => {
                 if let Some(admin) = maybe_admin && admin.0 >= AdminRole::Moderator && presence.kind == PresenceKind::Spectator {
                     if let Some(position) = positions.get_mut(entity) {
@@ -295,6 +340,7 @@ impl Sys {
             | ClientGeneral::CreateCharacter { .. }
             | ClientGeneral::EditCharacter { .. }
             | ClientGeneral::DeleteCharacter(_)
+            | ClientGeneral::RestoreCharacter(_)
             | ClientGeneral::Character(_, _)
             | ClientGeneral::Spectate(_)
             | ClientGeneral::TerrainChunkRequest { .. }
@@ -334,6 +380,8 @@ impl<'a> System<'a> for Sys {
         WriteStorage<'a, Presence>,
         WriteStorage<'a, Client>,
         WriteStorage<'a, Controller>,
+        WriteStorage<'a, Hotbar>,
+        ReadStorage<'a, Inventory>,
         Read<'a, Settings>,
         Read<'a, BuildAreas>,
         Write<'a, PlayerPhysicsSettings>,
@@ -364,6 +412,8 @@ impl<'a> System<'a> for Sys {
             mut presences,
             mut clients,
             mut controllers,
+            mut hotbars,
+            inventories,
             settings,
             build_areas,
             mut player_physics_settings,
@@ -405,6 +455,8 @@ impl<'a> System<'a> for Sys {
                     &mut velocities,
                     &mut orientations,
                     &mut controllers,
+                    &mut hotbars,
+                    &inventories,
                     &settings,
                     &build_areas,
                     &mut player_physics_settings,
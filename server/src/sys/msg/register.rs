@@ -11,8 +11,8 @@ use common::{
 };
 use common_ecs::{Job, Origin, Phase, System};
 use common_net::msg::{
-    CharacterInfo, ClientRegister, DisconnectReason, PlayerInfo, PlayerListUpdate, RegisterError,
-    ServerGeneral,
+    CharacterInfo, ClientRegister, CustomItems, DisconnectReason, PlayerInfo, PlayerListUpdate,
+    RegisterError, ServerGeneral,
 };
 use hashbrown::HashMap;
 use plugin_api::Health;
@@ -40,6 +40,7 @@ pub struct ReadData<'a> {
     player_metrics: ReadExpect<'a, PlayerMetrics>,
     settings: ReadExpect<'a, Settings>,
     editable_settings: ReadExpect<'a, EditableSettings>,
+    custom_items: ReadExpect<'a, CustomItems>,
     _healths: ReadStorage<'a, Health>, // used by plugin feature
     _plugin_mgr: ReadPlugin<'a>,       // used by plugin feature
     _uid_allocator: Read<'a, UidAllocator>, // used by plugin feature
@@ -99,6 +100,7 @@ impl<'a> System<'a> for Sys {
         for (entity, client) in (&read_data.entities, &read_data.clients).join() {
             let _ = super::try_recv_all(client, 0, |_, msg: ClientRegister| {
                 trace!(?msg.token_or_username, "defer auth lockup");
+                *client.custom_items.lock().unwrap() = msg.custom_items;
                 let pending = login_provider.verify(&msg.token_or_username);
                 let _ = pending_logins.insert(entity, pending);
                 Ok(())
@@ -200,8 +202,10 @@ impl<'a> System<'a> for Sys {
                             .expect("Inserting into players proves the entity exists.");
                     }
 
-                    // Tell the client its request was successful.
-                    client.send(Ok(()))?;
+                    // Tell the client its request was successful, along with the custom
+                    // items it has to register.
+                    let support = *client.custom_items.lock().unwrap();
+                    client.send(Ok(read_data.custom_items.manifest(support)))?;
 
                     // Send initial player list
                     client.send(ServerGeneral::PlayerListUpdate(PlayerListUpdate::Init(
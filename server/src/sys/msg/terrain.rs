@@ -10,7 +10,7 @@ use common::{
     vol::RectVolSize,
 };
 use common_ecs::{Job, Origin, ParMode, Phase, System};
-use common_net::msg::{ClientGeneral, ServerGeneral};
+use common_net::msg::{ClientGeneral, ServerGeneral, LOW_BANDWIDTH_CHUNKS_PER_TICK};
 use rayon::iter::ParallelIterator;
 use specs::{Entities, Join, ParJoin, Read, ReadExpect, ReadStorage, Write};
 use tracing::{debug, trace};
@@ -58,6 +58,7 @@ impl<'a> System<'a> for Sys {
                 || (chunk_send_bus.emitter(), server_event_bus.emitter()),
                 |(chunk_send_emitter, server_emitter), (entity, client, maybe_presence)| {
                     let mut chunk_requests = Vec::new();
+                    let mut served_requests = 0;
                     let _ = super::try_recv_all(client, 5, |_, msg| {
                         let presence = match maybe_presence {
                             Some(g) => g,
@@ -83,7 +84,11 @@ impl<'a> System<'a> for Sys {
                                 } else {
                                     true
                                 };
-                                if in_vd {
+                                // The client requests the chunk again if it doesn't arrive
+                                let over_budget = presence.low_bandwidth
+                                    && served_requests >= LOW_BANDWIDTH_CHUNKS_PER_TICK;
+                                if in_vd && !over_budget {
+                                    served_requests += 1;
                                     if terrain.get_key_arc(key).is_some() {
                                         network_metrics.chunks_served_from_memory.inc();
                                         chunk_send_emitter.emit(ChunkSendEntry {
@@ -1,8 +1,16 @@
-use crate::{persistence::character_updater, presence::Presence, sys::SysScheduler};
+use crate::{
+    persistence::{
+        buffs::buffs_to_persist,
+        character_updater::{self, vitals_to_persist},
+    },
+    presence::Presence,
+    sys::SysScheduler,
+};
 use common::{
     comp::{
         pet::{is_tameable, Pet},
-        ActiveAbilities, Alignment, Body, Inventory, MapMarker, SkillSet, Stats, Waypoint,
+        ActiveAbilities, Alignment, Body, Buffs, CharacterSettings, Energy, Health, Hotbar,
+        Inventory, LanternFuel, MapMarker, Pos, SkillSet, Stats, Waypoint,
     },
     uid::Uid,
 };
@@ -22,10 +30,17 @@ impl<'a> System<'a> for Sys {
         ReadStorage<'a, Inventory>,
         ReadStorage<'a, Uid>,
         ReadStorage<'a, Waypoint>,
+        ReadStorage<'a, Pos>,
+        ReadStorage<'a, Health>,
+        ReadStorage<'a, Energy>,
+        ReadStorage<'a, Buffs>,
+        ReadStorage<'a, LanternFuel>,
         ReadStorage<'a, MapMarker>,
         ReadStorage<'a, Pet>,
         ReadStorage<'a, Stats>,
         ReadStorage<'a, ActiveAbilities>,
+        ReadStorage<'a, Hotbar>,
+        ReadStorage<'a, CharacterSettings>,
         WriteExpect<'a, character_updater::CharacterUpdater>,
         Write<'a, SysScheduler<Self>>,
     );
@@ -44,10 +59,17 @@ impl<'a> System<'a> for Sys {
             player_inventories,
             uids,
             player_waypoints,
+            positions,
+            healths,
+            energies,
+            buffs,
+            lantern_fuels,
             map_markers,
             pets,
             stats,
             active_abilities,
+            hotbars,
+            character_settings,
             mut updater,
             mut scheduler,
         ): Self::SystemData,
@@ -60,8 +82,15 @@ impl<'a> System<'a> for Sys {
                     &player_inventories,
                     &uids,
                     player_waypoints.maybe(),
+                    positions.maybe(),
+                    healths.maybe(),
+                    energies.maybe(),
+                    buffs.maybe(),
+                    lantern_fuels.maybe(),
                     &active_abilities,
                     map_markers.maybe(),
+                    &hotbars,
+                    &character_settings,
                 )
                     .join()
                     .filter_map(
@@ -71,8 +100,15 @@ impl<'a> System<'a> for Sys {
                             inventory,
                             player_uid,
                             waypoint,
+                            position,
+                            health,
+                            energy,
+                            buffs,
+                            lantern_fuel,
                             active_abilities,
                             map_marker,
+                            hotbar,
+                            settings,
                         )| match presence.kind {
                             PresenceKind::Character(id) => {
                                 let pets = (&alignments, &bodies, &stats, &pets)
@@ -96,8 +132,15 @@ impl<'a> System<'a> for Sys {
                                     inventory,
                                     pets,
                                     waypoint,
+                                    // Dead characters respawn at their waypoint instead
+                                    position.filter(|_| health.map_or(true, |h| !h.is_dead)),
                                     active_abilities,
                                     map_marker,
+                                    hotbar,
+                                    settings,
+                                    vitals_to_persist(health, energy),
+                                    buffs_to_persist(buffs),
+                                    lantern_fuel.map(|fuel| fuel.remaining),
                                 ))
                             },
                             PresenceKind::Spectator | PresenceKind::Possessor => None,
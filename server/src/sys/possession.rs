@@ -0,0 +1,50 @@
+use crate::client::Client;
+use common::{
+    comp::{ChatType, Possessing},
+    event::{EventBus, ServerEvent},
+    resources::Time,
+    uid::Uid,
+};
+use common_ecs::{Job, Origin, Phase, System};
+use common_net::msg::ServerGeneral;
+use specs::{Join, Read, ReadStorage, WriteStorage};
+
+/// This system returns players to their own body once their possession of
+/// another entity expires
+#[derive(Default)]
+pub struct Sys;
+impl<'a> System<'a> for Sys {
+    type SystemData = (
+        Read<'a, Time>,
+        Read<'a, EventBus<ServerEvent>>,
+        ReadStorage<'a, Uid>,
+        ReadStorage<'a, Client>,
+        WriteStorage<'a, Possessing>,
+    );
+
+    const NAME: &'static str = "possession";
+    const ORIGIN: Origin = Origin::Server;
+    const PHASE: Phase = Phase::Create;
+
+    fn run(
+        _job: &mut Job<Self>,
+        (time, server_bus, uids, clients, mut possessing): Self::SystemData,
+    ) {
+        let mut server_emitter = server_bus.emitter();
+
+        for (uid, client, possessing) in (&uids, &clients, &mut possessing).join() {
+            if possessing
+                .expires_at
+                .map_or(false, |expires_at| time.0 >= expires_at.0)
+            {
+                // Only try once, if unpossessing fails the player can still do it manually
+                possessing.expires_at = None;
+                client.send_fallible(ServerGeneral::server_msg(
+                    ChatType::Meta,
+                    "Your possession has run out, returning you to your body",
+                ));
+                server_emitter.emit(ServerEvent::Unpossess(*uid));
+            }
+        }
+    }
+}
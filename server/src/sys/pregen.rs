@@ -0,0 +1,55 @@
+#[cfg(not(feature = "worldgen"))]
+use crate::test_world::{IndexOwned, World};
+#[cfg(feature = "worldgen")]
+use world::{IndexOwned, World};
+
+use crate::{chunk_generator::ChunkGenerator, pregen::BackgroundPregen, ChunkRequest};
+use common::slowjob::SlowJobPool;
+use common_ecs::{Job, Origin, Phase, System};
+use specs::{Read, ReadExpect, Write};
+use std::sync::Arc;
+
+/// This system pregenerates chunks around the spawn point with the chunk
+/// generation capacity that players don't need, if background pregeneration
+/// is enabled
+#[derive(Default)]
+pub struct Sys;
+impl<'a> System<'a> for Sys {
+    type SystemData = (
+        Read<'a, Vec<ChunkRequest>>,
+        ReadExpect<'a, ChunkGenerator>,
+        ReadExpect<'a, SlowJobPool>,
+        ReadExpect<'a, IndexOwned>,
+        ReadExpect<'a, Arc<World>>,
+        Option<Write<'a, BackgroundPregen>>,
+    );
+
+    const NAME: &'static str = "pregen";
+    const ORIGIN: Origin = Origin::Server;
+    const PHASE: Phase = Phase::Create;
+
+    fn run(
+        _job: &mut Job<Self>,
+        (chunk_requests, chunk_generator, slow_jobs, index, world, background_pregen): Self::SystemData,
+    ) {
+        let mut background_pregen = match background_pregen {
+            Some(background_pregen) if !background_pregen.is_done() => background_pregen,
+            _ => return,
+        };
+
+        let player_requests_pending =
+            !chunk_requests.is_empty() || chunk_generator.pending_chunks().next().is_some();
+        let world = Arc::clone(&world);
+        let index = index.clone();
+        background_pregen.maintain(
+            &slow_jobs,
+            player_requests_pending,
+            chunk_generator.cache(),
+            move |key| {
+                world
+                    .generate_base_chunk(index.as_index_ref(), key, || false, None)
+                    .ok()
+            },
+        );
+    }
+}
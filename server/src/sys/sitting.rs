@@ -0,0 +1,51 @@
+use common::{
+    comp::{CharacterState, Pos, Sitting},
+    event::{EventBus, ServerEvent},
+    interaction::{InteractionKind, InteractionRanges},
+    uid::UidAllocator,
+};
+use common_ecs::{Job, Origin, Phase, System};
+use specs::{saveload::MarkerAllocator, Entities, Join, Read, ReadExpect, ReadStorage};
+
+/// This system gets characters up from their seat once they no longer sit on
+/// it: they stood up (e.g. by moving), were moved out of reach of the seat
+/// (e.g. by teleporting), or the seat is gone
+#[derive(Default)]
+pub struct Sys;
+impl<'a> System<'a> for Sys {
+    type SystemData = (
+        Entities<'a>,
+        Read<'a, UidAllocator>,
+        ReadExpect<'a, InteractionRanges>,
+        Read<'a, EventBus<ServerEvent>>,
+        ReadStorage<'a, Pos>,
+        ReadStorage<'a, CharacterState>,
+        ReadStorage<'a, Sitting>,
+    );
+
+    const NAME: &'static str = "sitting";
+    const ORIGIN: Origin = Origin::Server;
+    const PHASE: Phase = Phase::Create;
+
+    fn run(
+        _job: &mut Job<Self>,
+        (entities, uid_allocator, ranges, server_bus, positions, character_states, sittings): Self::SystemData,
+    ) {
+        let mut server_emitter = server_bus.emitter();
+
+        for (entity, sitting) in (&entities, &sittings).join() {
+            let seat = uid_allocator.retrieve_entity_internal(sitting.seat.into());
+            let seated = matches!(character_states.get(entity), Some(CharacterState::Sit))
+                && seat.map_or(false, |seat| {
+                    ranges.positions_in_range(
+                        InteractionKind::Sit,
+                        positions.get(entity),
+                        positions.get(seat),
+                    )
+                });
+            if !seated {
+                server_emitter.emit(ServerEvent::Stand(entity));
+            }
+        }
+    }
+}
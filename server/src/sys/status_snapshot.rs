@@ -0,0 +1,90 @@
+#[cfg(not(feature = "worldgen"))]
+use crate::test_world::IndexOwned;
+#[cfg(feature = "worldgen")]
+use world::IndexOwned;
+
+use crate::{
+    presence::Presence,
+    status_api::{PlayerStatus, SiteEconomy, StatusApi, StatusSnapshot},
+    sys::SysScheduler,
+};
+use common::comp::{Player, Pos, SkillSet};
+use common_ecs::{Job, Origin, Phase, System};
+use specs::{Join, ReadExpect, ReadStorage, Write};
+use std::time::Instant;
+
+/// This system publishes the data served by the status API, about once a
+/// second
+#[derive(Default)]
+pub struct Sys;
+impl<'a> System<'a> for Sys {
+    type SystemData = (
+        ReadExpect<'a, StatusApi>,
+        Write<'a, SysScheduler<Self>>,
+        ReadExpect<'a, IndexOwned>,
+        ReadStorage<'a, Player>,
+        ReadStorage<'a, Presence>,
+        ReadStorage<'a, Pos>,
+        ReadStorage<'a, SkillSet>,
+    );
+
+    const NAME: &'static str = "status_snapshot";
+    const ORIGIN: Origin = Origin::Server;
+    const PHASE: Phase = Phase::Create;
+
+    fn run(
+        _job: &mut Job<Self>,
+        (status_api, mut scheduler, index, players, presences, positions, skill_sets): Self::SystemData,
+    ) {
+        if !status_api.is_enabled() || !scheduler.should_run() {
+            return;
+        }
+        let settings = status_api.settings();
+
+        let players = (&players, &presences, &positions, skill_sets.maybe())
+            .join()
+            .map(|(player, _, pos, skill_set)| {
+                let level = skill_set.map_or(0, |skill_set| {
+                    skill_set
+                        .skill_groups()
+                        .map(|group| u32::from(group.earned_sp))
+                        .sum()
+                });
+                PlayerStatus::new(settings, &player.alias, pos.0, level)
+            })
+            .collect();
+        let economy = if settings.share_economy {
+            site_economies(&index)
+        } else {
+            Vec::new()
+        };
+
+        status_api.publish(StatusSnapshot {
+            created: Instant::now(),
+            players,
+            economy,
+        });
+    }
+}
+
+#[cfg(feature = "worldgen")]
+fn site_economies(index: &IndexOwned) -> Vec<SiteEconomy> {
+    index
+        .sites
+        .iter()
+        .map(|(_, site)| SiteEconomy {
+            site: site.name().to_owned(),
+            prices: site
+                .economy
+                .get_site_prices()
+                .values
+                .into_iter()
+                .map(|(good, price)| (format!("{:?}", good), price))
+                .collect(),
+        })
+        .filter(|economy| !economy.prices.is_empty())
+        .collect()
+}
+
+#[cfg(not(feature = "worldgen"))]
+fn site_economies(_index: &IndexOwned) -> Vec<SiteEconomy> { Vec::new() }
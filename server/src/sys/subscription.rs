@@ -1,10 +1,14 @@
-use super::sentinel::{DeletedEntities, TrackedStorages};
+use super::{
+    entity_sync::StatsObserver,
+    sentinel::{DeletedEntities, TrackedStorages},
+};
 use crate::{
     client::Client,
     presence::{self, Presence, RegionSubscription},
+    settings::Settings,
 };
 use common::{
-    comp::{Ori, Pos, Vel},
+    comp::{Admin, Ori, Pos, Trap, Vel},
     region::{region_in_vd, regions_in_vd, Event as RegionEvent, RegionMap},
     terrain::TerrainChunkSize,
     uid::Uid,
@@ -34,6 +38,9 @@ impl<'a> System<'a> for Sys {
         WriteStorage<'a, RegionSubscription>,
         Read<'a, DeletedEntities>,
         TrackedStorages<'a>,
+        ReadStorage<'a, Admin>,
+        Read<'a, Settings>,
+        ReadStorage<'a, Trap>,
     );
 
     const NAME: &'static str = "subscription";
@@ -55,6 +62,9 @@ impl<'a> System<'a> for Sys {
             mut subscriptions,
             deleted_entities,
             tracked_comps,
+            admins,
+            settings,
+            traps,
         ): Self::SystemData,
     ) {
         // To update subscriptions
@@ -180,6 +190,13 @@ impl<'a> System<'a> for Sys {
                     // already within the set of subscribed regions
                     if subscription.regions.insert(key) {
                         if let Some(region) = region_map.get(key) {
+                            let observer = StatsObserver::new(
+                                client_entity,
+                                settings.gameplay.share_exact_stats
+                                    || admins.contains(client_entity),
+                                &tracked_comps.uid,
+                                &tracked_comps.group,
+                            );
                             (
                                 &positions,
                                 velocities.maybe(),
@@ -189,13 +206,22 @@ impl<'a> System<'a> for Sys {
                             )
                                 .join()
                                 .filter(|(_, _, _, _, e)| *e != client_entity)
+                                .filter(|(_, _, _, _, e)| observer.sees_trap(traps.get(*e)))
                                 .filter_map(|(pos, vel, ori, _, entity)| {
-                                    tracked_comps.create_entity_package(
-                                        entity,
-                                        Some(*pos),
-                                        vel.copied(),
-                                        ori.copied(),
-                                    )
+                                    tracked_comps
+                                        .create_entity_package(
+                                            entity,
+                                            Some(*pos),
+                                            vel.copied(),
+                                            ori.copied(),
+                                        )
+                                        .map(|pkg| {
+                                            observer.filter_entity_package(
+                                                pkg,
+                                                entity,
+                                                &tracked_comps.group,
+                                            )
+                                        })
                                 })
                                 .for_each(|msg| {
                                     // Send message to create entity and tracked components and
@@ -228,6 +254,14 @@ pub fn initialize_region_subscription(world: &World, entity: specs::Entity) {
 
         let region_map = world.read_resource::<RegionMap>();
         let tracked_comps = TrackedStorages::fetch(world);
+        let traps = world.read_storage::<Trap>();
+        let observer = StatsObserver::new(
+            entity,
+            world.read_resource::<Settings>().gameplay.share_exact_stats
+                || world.read_storage::<Admin>().contains(entity),
+            &tracked_comps.uid,
+            &tracked_comps.group,
+        );
         for key in &regions {
             if let Some(region) = region_map.get(*key) {
                 (
@@ -240,6 +274,7 @@ pub fn initialize_region_subscription(world: &World, entity: specs::Entity) {
                 .join()
                 // Don't send client its own components because we do that below
                 .filter(|t| t.4 != entity)
+                .filter(|t| observer.sees_trap(traps.get(t.4)))
                 .filter_map(|(pos, vel, ori, _, entity)|
                     tracked_comps.create_entity_package(
                         entity,
@@ -247,6 +282,7 @@ pub fn initialize_region_subscription(world: &World, entity: specs::Entity) {
                         vel.copied(),
                         ori.copied(),
                     )
+                    .map(|pkg| observer.filter_entity_package(pkg, entity, &tracked_comps.group))
                 )
                 .for_each(|msg| {
                     // Send message to create entity and tracked components and physics components
@@ -238,15 +238,31 @@ impl<'a> System<'a> for Sys {
 
             let chunk_pos = terrain.pos_key(pos.0.map(|e| e as i32));
             if let Some(chunk) = terrain.get_key(chunk_pos) {
-                pos.0 = terrain
-                    .try_find_space(pos.0.as_::<i32>())
-                    .map(|x| x.as_::<f32>())
-                    .unwrap_or_else(|| chunk.find_accessible_pos(pos.0.xy().as_::<i32>(), false));
+                let old_pos = pos.0;
+                let stuck = terrain
+                    .get(old_pos.as_::<i32>())
+                    .map_or(false, |block| block.is_solid());
+                pos.0 = if stuck {
+                    // The terrain changed since the entity was placed here (e.g. after the
+                    // world was regenerated), so move them up out of it
+                    terrain.try_find_space_above(old_pos.as_::<i32>())
+                } else {
+                    terrain.try_find_space(old_pos.as_::<i32>())
+                }
+                .map(|x| x.as_::<f32>())
+                .unwrap_or_else(|| chunk.find_accessible_pos(old_pos.xy().as_::<i32>(), false));
                 repositioned.push(entity);
                 force_update
                     .get_mut(entity)
                     .map(|force_update| force_update.update());
-                let _ = waypoints.insert(entity, Waypoint::new(pos.0, *time));
+                // Entities that were placed at their waypoint get it moved to where they
+                // ended up, a waypoint elsewhere is kept
+                if waypoints
+                    .get(entity)
+                    .map_or(true, |waypoint| waypoint.get_pos() == old_pos)
+                {
+                    let _ = waypoints.insert(entity, Waypoint::new(pos.0, *time));
+                }
             }
         }
         for entity in repositioned {
@@ -0,0 +1,58 @@
+use crate::client::Client;
+use common::{
+    comp::{training_dummy::DamageReport, ChatType, TrainingDummy},
+    resources::Time,
+    uid::UidAllocator,
+};
+use common_ecs::{Job, Origin, Phase, System};
+use common_net::msg::ServerGeneral;
+use specs::{saveload::MarkerAllocator, Join, Read, ReadStorage, WriteStorage};
+
+/// This system sends attackers of training dummies a summary of the damage
+/// they dealt once they stop hitting
+#[derive(Default)]
+pub struct Sys;
+impl<'a> System<'a> for Sys {
+    type SystemData = (
+        Read<'a, Time>,
+        Read<'a, UidAllocator>,
+        WriteStorage<'a, TrainingDummy>,
+        ReadStorage<'a, Client>,
+    );
+
+    const NAME: &'static str = "training_dummy";
+    const ORIGIN: Origin = Origin::Server;
+    const PHASE: Phase = Phase::Create;
+
+    fn run(_job: &mut Job<Self>, (time, uid_allocator, mut dummies, clients): Self::SystemData) {
+        for dummy in (&mut dummies).join() {
+            for (attacker, report) in dummy.take_reports(*time) {
+                if let Some(client) = uid_allocator
+                    .retrieve_entity_internal(attacker.0)
+                    .and_then(|attacker| clients.get(attacker))
+                {
+                    client.send_fallible(ServerGeneral::server_msg(
+                        ChatType::Meta,
+                        format_report(&report),
+                    ));
+                }
+            }
+        }
+    }
+}
+
+fn format_report(report: &DamageReport) -> String {
+    let breakdown = report
+        .by_source
+        .iter()
+        .map(|(source, damage)| format!("{:?}: {:.0}", source, damage))
+        .collect::<Vec<_>>()
+        .join(", ");
+    format!(
+        "Training dummy: {:.0} damage in {:.1}s ({:.1} DPS). {}",
+        report.total,
+        report.duration,
+        report.dps(),
+        breakdown
+    )
+}
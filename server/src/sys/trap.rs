@@ -0,0 +1,196 @@
+use super::sentinel::TrackedStorages;
+use crate::{client::Client, presence::RegionSubscription, settings::Settings};
+use common::{
+    combat::{self, Damage},
+    comp::{
+        item::MaterialStatManifest, trap::TrapTarget, Alignment, BuffChange, BuffKind, Buffs,
+        ChatType, Group, Health, Inventory, Ori, Player, Pos, Stats, Trap, TrapKind,
+    },
+    event::{EventBus, ServerEvent},
+    region::RegionMap,
+    resources::Time,
+    uid::{Uid, UidAllocator},
+};
+use common_ecs::{Job, Origin, Phase, System};
+use common_net::msg::ServerGeneral;
+use specs::{
+    saveload::MarkerAllocator, shred::ResourceId, Entities, Join, Read, ReadExpect, ReadStorage,
+    SystemData, World, WriteStorage,
+};
+
+#[derive(SystemData)]
+pub struct ReadData<'a> {
+    entities: Entities<'a>,
+    time: Read<'a, Time>,
+    settings: Read<'a, Settings>,
+    msm: ReadExpect<'a, MaterialStatManifest>,
+    server_bus: Read<'a, EventBus<ServerEvent>>,
+    uid_allocator: Read<'a, UidAllocator>,
+    region_map: ReadExpect<'a, RegionMap>,
+    tracked_storages: TrackedStorages<'a>,
+    positions: ReadStorage<'a, Pos>,
+    orientations: ReadStorage<'a, Ori>,
+    uids: ReadStorage<'a, Uid>,
+    healths: ReadStorage<'a, Health>,
+    groups: ReadStorage<'a, Group>,
+    alignments: ReadStorage<'a, Alignment>,
+    players: ReadStorage<'a, Player>,
+    buffs: ReadStorage<'a, Buffs>,
+    inventories: ReadStorage<'a, Inventory>,
+    stats: ReadStorage<'a, Stats>,
+    clients: ReadStorage<'a, Client>,
+    subscriptions: ReadStorage<'a, RegionSubscription>,
+}
+
+/// This system is responsible for arming, triggering and despawning traps
+/// placed by players, and for showing them to whoever notices them
+#[derive(Default)]
+pub struct Sys;
+impl<'a> System<'a> for Sys {
+    type SystemData = (ReadData<'a>, WriteStorage<'a, Trap>);
+
+    const NAME: &'static str = "trap";
+    const ORIGIN: Origin = Origin::Server;
+    const PHASE: Phase = Phase::Create;
+
+    fn run(_job: &mut Job<Self>, (read_data, mut traps): Self::SystemData) {
+        let mut server_emitter = read_data.server_bus.emitter();
+        let time = *read_data.time;
+        let lifetime = read_data.settings.gameplay.traps.lifetime.as_secs_f64();
+
+        for (trap_entity, trap, trap_pos) in
+            (&read_data.entities, &mut traps, &read_data.positions).join()
+        {
+            if trap.is_expired(time, lifetime) {
+                server_emitter.emit(ServerEvent::Delete(trap_entity));
+                continue;
+            }
+
+            // Traps aren't synced like other entities (see the entity sync and
+            // subscription systems), they are only sent to clients whose entity
+            // notices them
+            let trap_region = read_data.region_map.find_region(trap_entity, trap_pos.0);
+            for (observer, uid, pos, client, subscription) in (
+                &read_data.entities,
+                &read_data.uids,
+                &read_data.positions,
+                &read_data.clients,
+                &read_data.subscriptions,
+            )
+                .join()
+            {
+                let is_sneaking = read_data
+                    .tracked_storages
+                    .character_state
+                    .get(observer)
+                    .map_or(false, |state| state.is_stealthy());
+                if trap.is_visible_to(*uid)
+                    || !trap_region.map_or(false, |key| subscription.regions.contains(&key))
+                    || !trap.is_detected_by(*uid, pos.0.distance_squared(trap_pos.0), is_sneaking)
+                {
+                    continue;
+                }
+                if let Some(package) = read_data.tracked_storages.create_entity_package(
+                    trap_entity,
+                    Some(*trap_pos),
+                    None,
+                    read_data.orientations.get(trap_entity).copied(),
+                ) {
+                    client.send_fallible(ServerGeneral::CreateEntity(package));
+                    trap.seen_by.push(*uid);
+                }
+            }
+
+            if !trap.is_armed(time) {
+                continue;
+            }
+
+            let owner = read_data
+                .uid_allocator
+                .retrieve_entity_internal(trap.owner.0);
+            let owner_group = owner.and_then(|owner| read_data.groups.get(owner).copied());
+            let radius = trap.kind.trigger_radius();
+
+            let victim = (
+                &read_data.entities,
+                &read_data.uids,
+                &read_data.positions,
+                &read_data.healths,
+            )
+                .join()
+                .filter(|(_, _, pos, health)| {
+                    !health.is_dead && pos.0.distance_squared(trap_pos.0) < radius.powi(2)
+                })
+                .find(|(entity, uid, _, _)| {
+                    let target = TrapTarget {
+                        uid: **uid,
+                        group: read_data.groups.get(*entity).copied(),
+                        owner: match read_data.alignments.get(*entity) {
+                            Some(Alignment::Owned(owner)) => Some(*owner),
+                            _ => None,
+                        },
+                        in_safezone: read_data.buffs.get(*entity).map_or(false, |buffs| {
+                            buffs.kinds.contains_key(&BuffKind::Invulnerability)
+                        }),
+                        may_harm: combat::may_harm(
+                            &read_data.alignments,
+                            &read_data.players,
+                            &read_data.uid_allocator,
+                            owner,
+                            *entity,
+                        ),
+                    };
+                    trap.is_triggered_by(time, owner_group, &target)
+                })
+                .map(|(entity, _, pos, _)| (entity, *pos));
+
+            let (victim, victim_pos) = match victim {
+                Some(victim) => victim,
+                None => continue,
+            };
+
+            match trap.kind {
+                TrapKind::Snare => {
+                    if let Some(buff) = trap.buff() {
+                        server_emitter.emit(ServerEvent::Buff {
+                            entity: victim,
+                            buff_change: BuffChange::Add(buff),
+                        });
+                    }
+                },
+                TrapKind::Spike => {
+                    let damage_reduction = Damage::compute_damage_reduction(
+                        None,
+                        read_data.inventories.get(victim),
+                        read_data.stats.get(victim),
+                        &read_data.msm,
+                    );
+                    if let Some(change) = trap.health_change(damage_reduction, owner_group, time) {
+                        server_emitter.emit(ServerEvent::HealthChange {
+                            entity: victim,
+                            change,
+                        });
+                    }
+                },
+                TrapKind::Alarm => {
+                    if let Some(client) = owner.and_then(|owner| read_data.clients.get(owner)) {
+                        let intruder = read_data
+                            .stats
+                            .get(victim)
+                            .map_or("Something", |stats| stats.name.as_str());
+                        client.send_fallible(ServerGeneral::server_msg(
+                            ChatType::Meta,
+                            format!(
+                                "{} set off your alarm trap at ({:.0}, {:.0}, {:.0})",
+                                intruder, victim_pos.0.x, victim_pos.0.y, victim_pos.0.z,
+                            ),
+                        ));
+                    }
+                },
+            }
+
+            // Traps are single use
+            server_emitter.emit(ServerEvent::Delete(trap_entity));
+        }
+    }
+}
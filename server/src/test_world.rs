@@ -3,7 +3,8 @@ use common::{
     generation::{ChunkSupplement, EntityInfo},
     resources::TimeOfDay,
     terrain::{
-        Block, BlockKind, MapSizeLg, SpriteKind, TerrainChunk, TerrainChunkMeta, TerrainChunkSize,
+        BiomeKind, Block, BlockKind, MapSizeLg, SpriteKind, TerrainChunk, TerrainChunkMeta,
+        TerrainChunkSize,
     },
     vol::{ReadVol, RectVolSize, WriteVol},
 };
@@ -42,6 +43,14 @@ impl World {
     #[inline(always)]
     pub const fn map_size_lg(&self) -> MapSizeLg { DEFAULT_WORLD_CHUNKS_LG }
 
+    pub fn sim(&self) -> &Self { self }
+
+    pub fn get_size(&self) -> Vec2<u32> { self.map_size_lg().chunks().map(u32::from) }
+
+    pub fn site_name_at(&self, _index: IndexRef, _wpos: Vec2<i32>) -> Option<String> { None }
+
+    pub fn biome_at(&self, _wpos: Vec2<i32>) -> BiomeKind { BiomeKind::Void }
+
     pub fn generate_chunk(
         &self,
         _index: IndexRef,
@@ -69,4 +78,29 @@ impl World {
             supplement,
         ))
     }
+
+    pub fn generate_base_chunk(
+        &self,
+        index: IndexRef,
+        chunk_pos: Vec2<i32>,
+        should_continue: impl FnMut() -> bool,
+        _calendar: Option<&Calendar>,
+    ) -> Result<TerrainChunk, ()> {
+        self.generate_chunk(index, chunk_pos, should_continue, None)
+            .map(|(chunk, _)| chunk)
+    }
+
+    pub fn generate_chunk_with_base(
+        &self,
+        index: IndexRef,
+        chunk_pos: Vec2<i32>,
+        base: Option<TerrainChunk>,
+        should_continue: impl FnMut() -> bool,
+        time: Option<(TimeOfDay, Calendar)>,
+    ) -> Result<(TerrainChunk, ChunkSupplement), ()> {
+        match base {
+            Some(base) => Ok((base, ChunkSupplement::default())),
+            None => self.generate_chunk(index, chunk_pos, should_continue, time),
+        }
+    }
 }
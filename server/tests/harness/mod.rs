@@ -0,0 +1,420 @@
+//! Harness for the end-to-end tests: a headless server with a temporary
+//! database, and scripted clients that connect to it through the network stack
+//! the same way players do.
+//!
+//! The server runs at a fixed tick rate on its own thread, since clients need
+//! it to answer while they connect. Scenarios drive their clients tick by tick
+//! and wait for conditions (e.g. "the character list has loaded") instead of
+//! counting ticks, so they don't depend on how fast the machine is.
+
+use client::{addr::ConnectionArgs, Client, Event};
+use common::{
+    character::CharacterId,
+    clock::Clock,
+    comp::{self, ControllerInputs, InputKind},
+    uid::Uid,
+    util::Dir,
+    ViewDistances,
+};
+use common_net::sync::WorldSyncExt;
+use crossbeam_channel::{unbounded, Receiver, Sender, TryRecvError};
+use specs::Entity as EcsEntity;
+use std::{
+    net::{Ipv4Addr, SocketAddr},
+    path::PathBuf,
+    sync::Arc,
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+use tokio::runtime::Runtime;
+use vek::*;
+use veloren_server::{
+    persistence::{character_updater::CharacterUpdater, DatabaseSettings, SqlLogMode},
+    settings::Protocol,
+    EditableSettings, Input, Server, Settings,
+};
+use world::sim::{FileOpts, SizeOpts};
+
+pub const TICK: Duration = Duration::from_millis(33);
+/// How long a scenario waits for anything before it fails
+const TIMEOUT: Duration = Duration::from_secs(60);
+/// Seed of the world, so that every run spawns characters in the same place
+const WORLD_SEED: u32 = 1337;
+/// A small world, big enough for a few sites, that is generated in seconds
+const WORLD_SIZE_LG: u32 = 6;
+const VIEW_DISTANCE: u32 = 3;
+
+type ServerJob = Box<dyn FnOnce(&mut Server) + Send>;
+
+/// A server ticking in the background, which is shut down and has its data
+/// removed when dropped
+pub struct TestServer {
+    address: SocketAddr,
+    runtime: Arc<Runtime>,
+    data_dir: PathBuf,
+    jobs: Option<Sender<ServerJob>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl TestServer {
+    pub fn start() -> Self { Self::start_with(|_| {}) }
+
+    /// Starts a server with changes to the settings the tests normally use
+    pub fn start_with(configure: impl FnOnce(&mut Settings)) -> Self {
+        let data_dir = std::env::temp_dir().join(format!(
+            "veloren-server-test-{}-{}",
+            std::process::id(),
+            portpicker::pick_unused_port().expect("Failed to find unused port!"),
+        ));
+        let address = SocketAddr::from((
+            Ipv4Addr::LOCALHOST,
+            portpicker::pick_unused_port().expect("Failed to find unused port!"),
+        ));
+        let mut settings = Settings {
+            gameserver_protocols: vec![Protocol::Tcp { address }],
+            metrics_address: SocketAddr::from((
+                Ipv4Addr::LOCALHOST,
+                portpicker::pick_unused_port().expect("Failed to find unused port!"),
+            )),
+            auth_server_address: None,
+            world_seed: WORLD_SEED,
+            map_file: Some(FileOpts::Generate(SizeOpts::new(
+                WORLD_SIZE_LG,
+                WORLD_SIZE_LG,
+                2.0,
+            ))),
+            max_view_distance: Some(VIEW_DISTANCE),
+            background_pregen_radius: None,
+            ..Settings::default()
+        };
+        configure(&mut settings);
+        let editable_settings = EditableSettings::load(&data_dir);
+        let database_settings = DatabaseSettings {
+            db_dir: data_dir.join("saves"),
+            sql_log_mode: SqlLogMode::Disabled,
+        };
+        let runtime = Arc::new(Runtime::new().unwrap());
+
+        let (jobs, jobs_rx) = unbounded();
+        let (started_tx, started_rx) = unbounded();
+        let thread = thread::spawn({
+            let data_dir = data_dir.clone();
+            let runtime = Arc::clone(&runtime);
+            move || {
+                let server = Server::new(
+                    settings,
+                    editable_settings,
+                    database_settings,
+                    &data_dir,
+                    runtime,
+                )
+                .expect("Failed to start the server");
+                started_tx.send(()).unwrap();
+                run_server(server, jobs_rx);
+            }
+        });
+        started_rx
+            .recv()
+            .expect("Server thread exited while starting");
+
+        Self {
+            address,
+            runtime,
+            data_dir,
+            jobs: Some(jobs),
+            thread: Some(thread),
+        }
+    }
+
+    /// Runs `f` on the server between two ticks, e.g. to set up a scenario
+    /// or check its outcome on the server side
+    pub fn with<R: Send + 'static>(&self, f: impl FnOnce(&mut Server) -> R + Send + 'static) -> R {
+        let (result_tx, result_rx) = unbounded();
+        self.jobs
+            .as_ref()
+            .unwrap()
+            .send(Box::new(move |server: &mut Server| {
+                let _ = result_tx.send(f(server));
+            }))
+            .expect("Server thread exited");
+        result_rx.recv().expect("Server thread panicked")
+    }
+
+    /// Waits until the logout of a character has been written to the
+    /// database, so that it can be loaded again
+    pub fn wait_for_logout_persisted(&self, character_id: CharacterId) {
+        wait_until("the logout to be persisted", || {
+            self.with(move |server| {
+                !server
+                    .state()
+                    .ecs()
+                    .read_resource::<CharacterUpdater>()
+                    .characters_pending_logout()
+                    .any(|id| id == character_id)
+            })
+        });
+    }
+
+    pub fn connect(&self, username: &str) -> TestClient {
+        let mut client = self
+            .runtime
+            .block_on(Client::new(
+                ConnectionArgs::Tcp {
+                    hostname: self.address.to_string(),
+                    prefer_ipv6: false,
+                },
+                Arc::clone(&self.runtime),
+                &mut None,
+            ))
+            .expect("Failed to connect to the server");
+        self.runtime
+            .block_on(client.register(username.to_owned(), String::new(), |_| false))
+            .expect("Failed to register");
+
+        TestClient {
+            client,
+            clock: Clock::new(TICK),
+            inputs: ControllerInputs::default(),
+            events: Vec::new(),
+        }
+    }
+}
+
+impl Drop for TestServer {
+    fn drop(&mut self) {
+        // Closing the job channel stops the server
+        self.jobs = None;
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+        let _ = std::fs::remove_dir_all(&self.data_dir);
+    }
+}
+
+fn run_server(mut server: Server, jobs: Receiver<ServerJob>) {
+    let mut clock = Clock::new(TICK);
+    loop {
+        loop {
+            match jobs.try_recv() {
+                Ok(job) => job(&mut server),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => return,
+            }
+        }
+
+        clock.tick();
+        server
+            .tick(Input::default(), clock.dt())
+            .expect("Failed to tick server");
+        server.cleanup();
+    }
+}
+
+/// Polls `condition` once per tick until it holds, failing the test if it
+/// doesn't within [`TIMEOUT`]
+pub fn wait_until(what: &str, mut condition: impl FnMut() -> bool) {
+    let mut clock = Clock::new(TICK);
+    for _ in 0..TIMEOUT.as_millis() / TICK.as_millis() {
+        if condition() {
+            return;
+        }
+        clock.tick();
+    }
+    panic!("Timed out waiting for {}", what);
+}
+
+/// A client controlled by a scenario instead of a player
+pub struct TestClient {
+    pub client: Client,
+    clock: Clock,
+    /// What the character is trying to do, sent every tick
+    pub inputs: ControllerInputs,
+    /// Everything the client was told since the last call to
+    /// [`TestClient::take_events`]
+    events: Vec<Event>,
+}
+
+impl TestClient {
+    pub fn tick(&mut self) {
+        self.clock.tick();
+        let events = self
+            .client
+            .tick(self.inputs.clone(), self.clock.dt(), |_| {})
+            .expect("Client lost the connection");
+        self.client.cleanup();
+        for event in &events {
+            if let Event::CharacterError(error) = event {
+                panic!("Character error: {}", error);
+            }
+        }
+        self.events.extend(events);
+    }
+
+    /// Ticks until `condition` holds, failing the test if it doesn't within
+    /// [`TIMEOUT`]
+    pub fn tick_until(&mut self, what: &str, mut condition: impl FnMut(&mut Self) -> bool) {
+        for _ in 0..TIMEOUT.as_millis() / TICK.as_millis() {
+            if condition(self) {
+                return;
+            }
+            self.tick();
+        }
+        panic!("Timed out waiting for {}", what);
+    }
+
+    pub fn take_events(&mut self) -> Vec<Event> { std::mem::take(&mut self.events) }
+
+    /// Creates a character with a starter sword and returns its id
+    pub fn create_character(&mut self, alias: &str) -> CharacterId {
+        self.client.create_character(
+            alias.to_owned(),
+            Some("common.items.weapons.sword.starter".to_owned()),
+            None,
+            comp::humanoid::Body::random().into(),
+            false,
+        );
+        let mut created = None;
+        self.tick_until("the character to be created", |this| {
+            created = this
+                .take_events()
+                .into_iter()
+                .find_map(|event| match event {
+                    Event::CharacterCreated(id) => Some(id),
+                    _ => None,
+                });
+            created.is_some()
+        });
+        created.unwrap()
+    }
+
+    /// Loads the character list and returns the ids of the characters in it
+    pub fn character_ids(&mut self) -> Vec<CharacterId> {
+        self.client.load_character_list();
+        self.tick_until("the character list", |this| {
+            !this.client.character_list().loading
+        });
+        self.client
+            .character_list()
+            .characters
+            .iter()
+            .filter_map(|item| item.character.id)
+            .collect()
+    }
+
+    /// Enters the game with a character, and waits for it to be placed in
+    /// the world with the terrain around it loaded
+    pub fn spawn(&mut self, character_id: CharacterId) {
+        self.client.request_character(character_id, ViewDistances {
+            terrain: VIEW_DISTANCE,
+            entity: VIEW_DISTANCE,
+        });
+        self.tick_until("the character to spawn", |this| {
+            this.client.position().is_some() && this.client.current_chunk().is_some()
+        });
+        // Let the character land before it is told where to go
+        self.tick_until("the character to land", |this| {
+            this.client
+                .current::<comp::PhysicsState>()
+                .map_or(false, |physics| physics.on_ground.is_some())
+        });
+    }
+
+    /// Enters the game as a spectator at the spawn point, which the player has
+    /// to be a moderator for
+    pub fn spectate(&mut self) {
+        self.client.request_spectate(ViewDistances {
+            terrain: VIEW_DISTANCE,
+            entity: VIEW_DISTANCE,
+        });
+        let mut spawn_point = None;
+        self.tick_until("the spectator to spawn", |this| {
+            spawn_point = this
+                .take_events()
+                .into_iter()
+                .find_map(|event| match event {
+                    Event::StartSpectate(spawn_point) => Some(spawn_point),
+                    _ => None,
+                });
+            spawn_point.is_some()
+        });
+        // Spectators place themselves, like the camera does in the game
+        let entity = self.client.entity();
+        self.client
+            .state()
+            .ecs()
+            .write_storage()
+            .insert(entity, comp::Pos(spawn_point.unwrap()))
+            .unwrap();
+    }
+
+    pub fn position(&self) -> Vec3<f32> {
+        self.client.position().expect("Client isn't in the game")
+    }
+
+    /// Walks to each of `waypoints` in turn
+    pub fn walk(&mut self, waypoints: &[Vec2<f32>]) {
+        for &waypoint in waypoints {
+            self.tick_until("the character to reach a waypoint", |this| {
+                let to_waypoint = waypoint - this.position().xy();
+                let arrived = to_waypoint.magnitude() < 1.0;
+                this.inputs.move_dir = if arrived {
+                    Vec2::zero()
+                } else {
+                    to_waypoint.normalized()
+                };
+                arrived
+            });
+        }
+        self.inputs.move_dir = Vec2::zero();
+    }
+
+    /// The client's entity for an entity on the server, once the client has
+    /// been told about it
+    pub fn entity(&mut self, uid: Uid) -> EcsEntity {
+        let mut entity = None;
+        self.tick_until("the entity to be synced", |this| {
+            entity = this.client.state().ecs().entity_from_uid(uid.0);
+            entity.is_some()
+        });
+        entity.unwrap()
+    }
+
+    pub fn pick_up(&mut self, uid: Uid) {
+        let entity = self.entity(uid);
+        self.client.pick_up(entity);
+    }
+
+    /// Wields the weapon and holds the primary attack while looking at a
+    /// target, until `done` holds
+    pub fn attack_until(&mut self, target: Uid, mut done: impl FnMut() -> bool) {
+        let target = self.entity(target);
+        if self.client.is_wielding() != Some(true) {
+            self.client.toggle_wield();
+        }
+        self.client
+            .handle_input(InputKind::Primary, true, None, Some(target));
+        self.tick_until("the attack to land", |this| {
+            if let Some(target) = this
+                .client
+                .state()
+                .read_component_copied::<comp::Pos>(target)
+            {
+                if let Some(dir) = Dir::from_unnormalized(target.0 - this.position()) {
+                    this.inputs.look_dir = dir;
+                }
+            }
+            done()
+        });
+        self.client
+            .handle_input(InputKind::Primary, false, None, None);
+    }
+
+    /// Leaves the game and disconnects
+    pub fn logout(mut self) {
+        self.client.logout();
+        // Give the server a moment to notice
+        for _ in 0..5 {
+            self.clock.tick();
+        }
+    }
+}
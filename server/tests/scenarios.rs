@@ -0,0 +1,911 @@
+//! End-to-end scenarios, with scripted clients playing on a headless server
+//! (see [`harness`])
+
+mod harness;
+
+use client::Event;
+use common::{
+    comp::{
+        self,
+        inventory::item::{Item, ItemDefinitionId},
+        quadruped_medium, quadruped_small, Alignment,
+    },
+    event::{EventBus, ServerEvent},
+    link::Is,
+    mounting::Rider,
+    resources::Time,
+    terrain::{Block, BlockKind, SpriteKind, TerrainChunk, TerrainChunkMeta, TerrainGrid},
+    uid::Uid,
+    vol::ReadVol,
+    weather::WeatherGrid,
+};
+use common_net::{msg::PresenceKind, sync::WorldSyncExt};
+use harness::{wait_until, TestClient, TestServer};
+use specs::{Builder, Entity as EcsEntity, Join, WorldExt};
+use std::time::Duration;
+use vek::*;
+use veloren_server::{
+    client::Client, fire::Fires, presence::Presence, settings::FireSettings, state_ext::StateExt,
+    terrain_persistence::TerrainPersistence, Server,
+};
+
+const APPLE: &str = "common.items.food.apple";
+
+fn count_items(client: &TestClient, item: &str) -> u32 {
+    let client = &client.client;
+    client
+        .inventories()
+        .get(client.entity())
+        .map_or(0, |inventory| {
+            inventory
+                .slots()
+                .flatten()
+                .filter(|i| i.item_definition_id() == ItemDefinitionId::Simple(item))
+                .map(Item::amount)
+                .sum()
+        })
+}
+
+fn make_admin(server: &TestServer, alias: &'static str) {
+    server.with(move |server| {
+        let ecs = server.state().ecs();
+        let player = (&ecs.entities(), &ecs.read_storage::<comp::Player>())
+            .join()
+            .find(|(_, player)| player.alias == alias)
+            .map(|(entity, _)| entity)
+            .unwrap();
+        ecs.write_storage()
+            .insert(player, comp::Admin(comp::AdminRole::Admin))
+            .unwrap();
+    });
+}
+
+#[test]
+fn characters_persist_across_sessions() {
+    let server = TestServer::start();
+    let mut client = server.connect("alice");
+    assert!(client.character_ids().is_empty());
+    let character_id = client.create_character("Alice");
+    assert_eq!(client.character_ids(), vec![character_id]);
+    client.spawn(character_id);
+
+    let start = client.position().xy();
+    client.walk(&[
+        start + Vec2::new(4.0, 0.0),
+        start + Vec2::new(4.0, 4.0),
+        start + Vec2::new(0.0, 4.0),
+    ]);
+
+    let apples = count_items(&client, APPLE);
+    let pos = client.position();
+    let apple = server.with(move |server| {
+        let entity = server
+            .state_mut()
+            .create_item_drop(
+                comp::Pos(pos + Vec3::unit_x()),
+                Item::new_from_asset_expect(APPLE),
+            )
+            .build();
+        server.state().ecs().uid_from_entity(entity).unwrap()
+    });
+    client.pick_up(apple);
+    client.tick_until("the apple to be picked up", |client| {
+        count_items(client, APPLE) == apples + 1
+    });
+
+    let logout_pos = client.position();
+    client.logout();
+    server.wait_for_logout_persisted(character_id);
+
+    let mut client = server.connect("alice");
+    assert_eq!(client.character_ids(), vec![character_id]);
+    client.spawn(character_id);
+    assert_eq!(count_items(&client, APPLE), apples + 1);
+    assert!(client.position().xy().distance(logout_pos.xy()) < 2.0);
+}
+
+#[test]
+fn hotbar_bindings_persist_across_sessions() {
+    let server = TestServer::start();
+    let mut client = server.connect("bob");
+    let character_id = client.create_character("Bob");
+    client.spawn(character_id);
+
+    let apples = count_items(&client, APPLE);
+    let pos = client.position();
+    let apple = server.with(move |server| {
+        let entity = server
+            .state_mut()
+            .create_item_drop(
+                comp::Pos(pos + Vec3::unit_x()),
+                Item::new_from_asset_expect(APPLE),
+            )
+            .build();
+        server.state().ecs().uid_from_entity(entity).unwrap()
+    });
+    client.pick_up(apple);
+    client.tick_until("the apple to be picked up", |client| {
+        count_items(client, APPLE) == apples + 1
+    });
+    let hash = {
+        let client = &client.client;
+        let inventories = client.inventories();
+        let inventory = inventories.get(client.entity()).unwrap();
+        inventory
+            .slots()
+            .flatten()
+            .find(|i| i.item_definition_id() == ItemDefinitionId::Simple(APPLE))
+            .unwrap()
+            .item_hash()
+    };
+
+    let mut bindings = [None; comp::hotbar::HOTBAR_SLOTS];
+    bindings[3] = Some(comp::HotbarBinding::Item(hash));
+    client.client.set_hotbar(bindings);
+    client.tick_until("the hotbar to be confirmed", |client| {
+        client.client.hotbar().map(|hotbar| *hotbar.slots()) == Some(bindings)
+    });
+
+    client.logout();
+    server.wait_for_logout_persisted(character_id);
+
+    let mut client = server.connect("bob");
+    client.spawn(character_id);
+    client.tick_until("the hotbar to be restored", |client| {
+        client.client.hotbar().is_some()
+    });
+    assert_eq!(
+        client.client.hotbar().map(|hotbar| *hotbar.slots()),
+        Some(bindings)
+    );
+
+    // The binding is resolved by the server, wherever the apple is now
+    client.client.activate_hotbar_slot(3);
+    client.tick_until("the apple to be eaten", |client| {
+        count_items(client, APPLE) == apples
+    });
+}
+
+#[test]
+fn characters_can_hurt_npcs() {
+    let server = TestServer::start();
+    let mut client = server.connect("bob");
+    let character_id = client.create_character("Bob");
+    client.spawn(character_id);
+
+    // A rat that doesn't move or fight back, in front of the character
+    let pos = client.position() + Vec3::unit_x() * 2.0;
+    let (rat, rat_uid) = server.with(move |server| {
+        let body = comp::Body::QuadrupedSmall(quadruped_small::Body {
+            species: quadruped_small::Species::Rat,
+            body_type: quadruped_small::BodyType::Female,
+        });
+        let entity = server
+            .state_mut()
+            .create_npc(
+                comp::Pos(pos),
+                comp::Stats::new("Rat".to_owned()),
+                comp::SkillSet::default(),
+                Some(comp::Health::new(body, 0)),
+                comp::Poise::new(body),
+                comp::Inventory::with_empty(),
+                body,
+            )
+            .with(Alignment::Enemy)
+            .build();
+        (
+            entity,
+            server.state().ecs().uid_from_entity(entity).unwrap(),
+        )
+    });
+
+    client.attack_until(rat_uid, || {
+        server.with(move |server| {
+            server
+                .state()
+                .ecs()
+                .read_storage::<comp::Health>()
+                .get(rat)
+                .map_or(true, |health| health.current() < health.maximum())
+        })
+    });
+}
+
+#[test]
+fn players_see_each_other() {
+    let server = TestServer::start();
+    let names = ["carol", "dave", "erin"];
+    let mut clients = names
+        .iter()
+        .map(|name| {
+            let mut client = server.connect(name);
+            let character_id = client.create_character(name);
+            client.spawn(character_id);
+            client
+        })
+        .collect::<Vec<_>>();
+    let uids = clients
+        .iter()
+        .map(|client| client.client.uid().unwrap())
+        .collect::<Vec<_>>();
+
+    wait_until("the players to see each other", || {
+        clients.iter_mut().for_each(TestClient::tick);
+        clients.iter().all(|client| {
+            client.client.players().count() == names.len()
+                && uids
+                    .iter()
+                    .all(|uid| client.client.state().ecs().entity_from_uid(uid.0).is_some())
+        })
+    });
+}
+
+#[test]
+fn traps_are_hidden_from_everyone_who_did_not_notice_them() {
+    let server = TestServer::start();
+    let mut clients = ["frank", "grace"]
+        .iter()
+        .map(|name| {
+            let mut client = server.connect(name);
+            let character_id = client.create_character(name);
+            client.spawn(character_id);
+            client
+        })
+        .collect::<Vec<_>>();
+
+    // Close enough to notice, but out of reach of the alarm
+    let placer = clients[0].client.uid().unwrap();
+    let pos = clients[1].position() + Vec3::unit_x() * 6.0;
+    let trap = server.with(move |server| {
+        let time = *server.state().ecs().read_resource::<Time>();
+        let entity = server
+            .state_mut()
+            .create_object(comp::Pos(pos), comp::TrapKind::Alarm.body())
+            .with(comp::Trap::new(comp::TrapKind::Alarm, placer, time))
+            .build();
+        server.state().ecs().uid_from_entity(entity).unwrap()
+    });
+    let sees_trap = |client: &TestClient| {
+        client
+            .client
+            .state()
+            .ecs()
+            .entity_from_uid(trap.0)
+            .is_some()
+    };
+
+    wait_until("the placer to see the trap", || {
+        clients.iter_mut().for_each(TestClient::tick);
+        sees_trap(&clients[0])
+    });
+    for _ in 0..20 {
+        clients[1].tick();
+    }
+    assert!(!sees_trap(&clients[1]));
+
+    clients[1].client.toggle_sneak();
+    clients[1].tick_until("the sneaking player to notice the trap", |client| {
+        sees_trap(client)
+    });
+}
+
+/// Spawns a horse owned by `owner` at `pos`, which the owner can mount
+fn spawn_pet_horse(server: &TestServer, owner: Uid, pos: Vec3<f32>) -> (EcsEntity, Uid) {
+    server.with(move |server| {
+        let body = comp::Body::QuadrupedMedium(quadruped_medium::Body {
+            species: quadruped_medium::Species::Horse,
+            body_type: quadruped_medium::BodyType::Female,
+        });
+        let entity = server
+            .state_mut()
+            .create_npc(
+                comp::Pos(pos),
+                comp::Stats::new("Horse".to_owned()),
+                comp::SkillSet::default(),
+                Some(comp::Health::new(body, 0)),
+                comp::Poise::new(body),
+                comp::Inventory::with_empty(),
+                body,
+            )
+            .with(Alignment::Owned(owner))
+            .build();
+        (
+            entity,
+            server.state().ecs().uid_from_entity(entity).unwrap(),
+        )
+    })
+}
+
+#[cfg(feature = "persistent_world")]
+#[test]
+fn burnt_blocks_are_synced_and_persisted() {
+    let server = TestServer::start_with(|settings| {
+        settings.experimental_terrain_persistence = true;
+        settings.gameplay.fire = FireSettings {
+            destroy_blocks: true,
+            burn_duration: Duration::from_secs(1),
+            spread_chance: 0.0,
+            ..FireSettings::default()
+        };
+    });
+    let mut client = server.connect("ivan");
+    let character_id = client.create_character("ivan");
+    client.spawn(character_id);
+
+    // Rain would put the fire out before it burns the block away
+    make_admin(&server, "ivan");
+    client.client.send_command("weather_zone".to_owned(), vec![
+        "clear".to_owned(),
+        "500".to_owned(),
+    ]);
+    let pos = (client.position() + Vec3::new(3.0, 0.0, 2.0)).map(|e| e.floor() as i32);
+    wait_until("the rain to stop", || {
+        client.tick();
+        server.with(move |server| {
+            let weather = server.state().ecs().read_resource::<WeatherGrid>();
+            weather.get_interpolated(pos.xy().as_()).rain == 0.0
+        })
+    });
+
+    server.with(move |server| {
+        server
+            .state()
+            .set_block(pos, Block::new(BlockKind::Wood, Rgb::new(100, 60, 20)))
+    });
+    let block_at = |client: &TestClient| client.client.state().terrain().get(pos).ok().copied();
+    client.tick_until("the wood to be synced", |client| {
+        block_at(client).map_or(false, |block| block.kind() == BlockKind::Wood)
+    });
+
+    assert!(server.with(move |server| {
+        let time = *server.state().ecs().read_resource::<Time>();
+        let settings = server.settings().gameplay.fire.clone();
+        let terrain = server.state().terrain();
+        server.state().ecs().write_resource::<Fires>().ignite(
+            pos,
+            |pos| terrain.get(pos).ok().copied(),
+            time,
+            &settings,
+        )
+    }));
+    client.tick_until("the wood to burn away", |client| {
+        block_at(client).map_or(false, |block| !block.is_solid())
+    });
+
+    // The burnt block is removed again when the chunk is generated anew
+    let persisted = server.with(move |server| {
+        let wood = Block::new(BlockKind::Wood, Rgb::new(100, 60, 20));
+        let mut chunk = TerrainChunk::new(0, wood, wood, TerrainChunkMeta::void());
+        server
+            .state()
+            .ecs()
+            .write_resource::<TerrainPersistence>()
+            .apply_changes(TerrainGrid::chunk_key(pos), &mut chunk);
+        chunk.get(TerrainGrid::chunk_offs(pos)).ok().copied()
+    });
+    assert_eq!(persisted, Some(Block::air(SpriteKind::Empty)));
+}
+
+#[test]
+fn riders_are_thrown_off_mounts_that_die() {
+    let server = TestServer::start();
+    let mut client = server.connect("frank");
+    let character_id = client.create_character("Frank");
+    client.spawn(character_id);
+
+    // A pet horse that stands still beside the character
+    let rider_uid = client.client.uid().unwrap();
+    let pos = client.position() + Vec3::unit_x() * 2.0;
+    let (horse, horse_uid) = spawn_pet_horse(&server, rider_uid, pos);
+    let horse_entity = client.entity(horse_uid);
+    client.client.mount(horse_entity);
+    client.tick_until("the character to mount the horse", |client| {
+        client.client.is_riding()
+    });
+
+    server.with(move |server| {
+        let ecs = server.state().ecs();
+        let time = *ecs.read_resource::<Time>();
+        ecs.read_resource::<EventBus<ServerEvent>>()
+            .emit_now(ServerEvent::Destroy {
+                entity: horse,
+                cause: comp::HealthChange {
+                    amount: 0.0,
+                    by: None,
+                    cause: None,
+                    time,
+                    crit: false,
+                    instance: 0,
+                },
+            });
+    });
+    let thrown_off = || {
+        server.with(move |server| {
+            let ecs = server.state().ecs();
+            let rider = ecs.entity_from_uid(rider_uid.0).unwrap();
+            let riding = ecs.read_storage::<Is<Rider>>().contains(rider);
+            let immune = ecs.read_storage::<comp::FallImmunity>().contains(rider);
+            if riding {
+                None
+            } else {
+                Some(immune)
+            }
+        })
+    };
+    wait_until("the rider to be thrown off", || thrown_off().is_some());
+    assert_eq!(thrown_off(), Some(true), "the rider isn't spared the fall");
+
+    // The character can move on its own again
+    client.tick_until("the client to see the rider dismounted", |client| {
+        !client.client.is_riding()
+    });
+    let start = client.position().xy();
+    client.walk(&[start + Vec2::new(0.0, 4.0)]);
+}
+
+#[test]
+fn riders_are_put_down_beside_mounts_that_are_deleted() {
+    let server = TestServer::start();
+    let mut client = server.connect("frank");
+    let character_id = client.create_character("Frank");
+    client.spawn(character_id);
+
+    let rider_uid = client.client.uid().unwrap();
+    let pos = client.position() + Vec3::unit_x() * 2.0;
+    let (horse, horse_uid) = spawn_pet_horse(&server, rider_uid, pos);
+    let horse_entity = client.entity(horse_uid);
+    client.client.mount(horse_entity);
+    client.tick_until("the character to mount the horse", |client| {
+        client.client.is_riding()
+    });
+
+    // The horse is unloaded, e.g. because it wandered off
+    let horse_pos = server.with(move |server| {
+        let ecs = server.state().ecs();
+        let horse_pos = ecs.read_storage::<comp::Pos>().get(horse).unwrap().0;
+        ecs.read_resource::<EventBus<ServerEvent>>()
+            .emit_now(ServerEvent::Delete(horse));
+        horse_pos
+    });
+    let rider_pos = || {
+        server.with(move |server| {
+            let ecs = server.state().ecs();
+            let rider = ecs.entity_from_uid(rider_uid.0).unwrap();
+            if ecs.read_storage::<Is<Rider>>().contains(rider) {
+                None
+            } else {
+                ecs.read_storage::<comp::Pos>().get(rider).map(|pos| pos.0)
+            }
+        })
+    };
+    wait_until("the rider to be dismounted", || rider_pos().is_some());
+    let rider_pos = rider_pos().unwrap();
+    assert!(
+        rider_pos.xy().distance(horse_pos.xy()) > 1.0,
+        "the rider is left where they sat on the horse"
+    );
+    assert!(
+        rider_pos.distance(horse_pos) < 5.0,
+        "the rider isn't beside the horse"
+    );
+
+    client.tick_until("the client to see the rider dismounted", |client| {
+        !client.client.is_riding()
+    });
+}
+
+#[test]
+fn possessed_creatures_are_left_behind_on_disconnect() {
+    let server = TestServer::start();
+    let mut client = server.connect("grace");
+    let character_id = client.create_character("Grace");
+    client.spawn(character_id);
+
+    let player_uid = client.client.uid().unwrap();
+    let pos = client.position() + Vec3::unit_x() * 2.0;
+    let rat_uid = server.with(move |server| {
+        let body = comp::Body::QuadrupedSmall(quadruped_small::Body {
+            species: quadruped_small::Species::Rat,
+            body_type: quadruped_small::BodyType::Female,
+        });
+        let ecs = server.state().ecs();
+        let player = ecs.entity_from_uid(player_uid.0).unwrap();
+        ecs.write_storage()
+            .insert(player, comp::Admin(comp::AdminRole::Admin))
+            .unwrap();
+        let entity = server
+            .state_mut()
+            .create_npc(
+                comp::Pos(pos),
+                comp::Stats::new("Rat".to_owned()),
+                comp::SkillSet::default(),
+                Some(comp::Health::new(body, 0)),
+                comp::Poise::new(body),
+                comp::Inventory::with_empty(),
+                body,
+            )
+            .with(comp::Agent::from_body(&body))
+            .with(Alignment::Wild)
+            .build();
+        let rat_uid = server.state().ecs().uid_from_entity(entity).unwrap();
+        server
+            .state()
+            .ecs()
+            .read_resource::<EventBus<ServerEvent>>()
+            .emit_now(ServerEvent::Possess(player_uid, rat_uid));
+        rat_uid
+    });
+    client.tick_until("the player to possess the rat", |client| {
+        client.client.uid() == Some(rat_uid)
+    });
+
+    client.logout();
+    let rat = || {
+        server.with(move |server| {
+            let ecs = server.state().ecs();
+            ecs.entity_from_uid(rat_uid.0).map(|rat| {
+                (
+                    ecs.read_storage::<Client>().contains(rat),
+                    ecs.read_storage::<comp::Possessing>().contains(rat),
+                    ecs.read_storage::<comp::Agent>().contains(rat),
+                )
+            })
+        })
+    };
+    wait_until("the player to leave the rat", || {
+        rat().map_or(true, |(client, ..)| !client)
+    });
+    assert_eq!(
+        rat(),
+        Some((false, false, true)),
+        "the rat isn't left behind with its own will"
+    );
+}
+
+/// Spawns a wild rat with a single hit point left
+fn spawn_dying_rat(server: &TestServer, pos: Vec3<f32>) -> (EcsEntity, Uid) {
+    server.with(move |server| {
+        let body = comp::Body::QuadrupedSmall(quadruped_small::Body {
+            species: quadruped_small::Species::Rat,
+            body_type: quadruped_small::BodyType::Female,
+        });
+        let mut health = comp::Health::new(body, 0);
+        health.restore(1.0);
+        let entity = server
+            .state_mut()
+            .create_npc(
+                comp::Pos(pos),
+                comp::Stats::new("Rat".to_owned()),
+                comp::SkillSet::default(),
+                Some(health),
+                comp::Poise::new(body),
+                comp::Inventory::with_empty(),
+                body,
+            )
+            .with(comp::Agent::from_body(&body))
+            .with(Alignment::Wild)
+            .build();
+        (
+            entity,
+            server.state().ecs().uid_from_entity(entity).unwrap(),
+        )
+    })
+}
+
+fn possess(server: &TestServer, client: &mut TestClient, possessor: Uid, possessee: Uid) {
+    client.entity(possessee);
+    server.with(move |server| {
+        server
+            .state()
+            .ecs()
+            .read_resource::<EventBus<ServerEvent>>()
+            .emit_now(ServerEvent::Possess(possessor, possessee));
+    });
+    client.tick_until("the player to possess the entity", |client| {
+        client.client.uid() == Some(possessee)
+    });
+}
+
+fn kill(server: &TestServer, entity: EcsEntity) {
+    server.with(move |server| {
+        let ecs = server.state().ecs();
+        let time = *ecs.read_resource::<Time>();
+        ecs.read_resource::<EventBus<ServerEvent>>()
+            .emit_now(ServerEvent::Destroy {
+                entity,
+                cause: comp::HealthChange {
+                    amount: 0.0,
+                    by: None,
+                    cause: None,
+                    time,
+                    crit: false,
+                    instance: 0,
+                },
+            });
+    });
+}
+
+#[test]
+fn possessors_get_their_own_body_back_when_the_possessed_dies() {
+    let server = TestServer::start();
+    let mut client = server.connect("heidi");
+    make_admin(&server, "heidi");
+    client.spectate();
+    let player_uid = client.client.uid().unwrap();
+
+    let (rat, rat_uid) = spawn_dying_rat(&server, client.position() + Vec3::unit_x() * 2.0);
+    possess(&server, &mut client, player_uid, rat_uid);
+    let rat_health = server.with(move |server| {
+        server
+            .state()
+            .ecs()
+            .read_storage::<comp::Health>()
+            .get(rat)
+            .map(|health| (health.current(), health.maximum()))
+    });
+    assert!(
+        rat_health.map_or(false, |(current, maximum)| current == maximum),
+        "the rat isn't possessed with full health"
+    );
+
+    kill(&server, rat);
+    client.tick_until("the player to get their own body back", |client| {
+        client.client.uid() == Some(player_uid)
+    });
+    // The rat died as itself instead of waiting for the player to respawn it
+    wait_until("the rat to be gone", || {
+        server.with(move |server| server.state().ecs().entity_from_uid(rat_uid.0).is_none())
+    });
+}
+
+#[test]
+fn possessors_get_their_own_character_back_when_the_possessed_dies() {
+    fn vitals(server: &Server, entity: EcsEntity) -> Option<(String, f32, f32)> {
+        let ecs = server.state().ecs();
+        Some((
+            ecs.read_storage::<comp::Stats>().get(entity)?.name.clone(),
+            ecs.read_storage::<comp::Health>().get(entity)?.current(),
+            ecs.read_storage::<comp::Energy>().get(entity)?.current(),
+        ))
+    }
+
+    let server = TestServer::start();
+    let mut client = server.connect("ivan");
+    make_admin(&server, "ivan");
+    let character_id = client.create_character("Ivan");
+    client.spawn(character_id);
+    let player_uid = client.client.uid().unwrap();
+    let pos = client.position();
+    let (character, before) = server.with(move |server| {
+        let character = server.state().ecs().entity_from_uid(player_uid.0).unwrap();
+        (character, vitals(server, character))
+    });
+
+    let (rat, rat_uid) = spawn_dying_rat(&server, pos + Vec3::unit_x() * 2.0);
+    possess(&server, &mut client, player_uid, rat_uid);
+    let hidden = server.with(move |server| {
+        let ecs = server.state().ecs();
+        ecs.is_alive(character)
+            && !ecs.read_storage::<comp::Pos>().contains(character)
+            && !ecs.read_storage::<Presence>().contains(character)
+    });
+    assert!(hidden, "the character isn't kept out of the world");
+
+    kill(&server, rat);
+    client.tick_until("the player to get their own character back", |client| {
+        client.client.uid() == Some(player_uid)
+    });
+    let after = server.with(move |server| {
+        let ecs = server.state().ecs();
+        (
+            ecs.entity_from_uid(player_uid.0),
+            ecs.read_storage::<Presence>()
+                .get(character)
+                .map(|presence| presence.kind),
+            vitals(server, character),
+        )
+    });
+    assert_eq!(
+        after,
+        (
+            Some(character),
+            Some(PresenceKind::Character(character_id)),
+            before
+        )
+    );
+    assert_eq!(
+        client.client.presence(),
+        Some(PresenceKind::Character(character_id))
+    );
+    assert!(client.position().distance(pos) < 2.0);
+}
+
+#[test]
+fn characters_get_up_from_seats_they_are_teleported_away_from() {
+    let server = TestServer::start();
+    let mut client = server.connect("ivan");
+    let character_id = client.create_character("Ivan");
+    client.spawn(character_id);
+
+    let player_uid = client.client.uid().unwrap();
+    let pos = client.position() + Vec3::unit_x() * 2.0;
+    let bench_uid = server.with(move |server| {
+        let entity = server
+            .state_mut()
+            .create_object(comp::Pos(pos), comp::object::Body::Bench)
+            .build();
+        server.state().ecs().uid_from_entity(entity).unwrap()
+    });
+    let bench = client.entity(bench_uid);
+    client.client.sit_on(bench);
+
+    let sitting = || {
+        server.with(move |server| {
+            let ecs = server.state().ecs();
+            let player = ecs.entity_from_uid(player_uid.0).unwrap();
+            (
+                ecs.read_storage::<comp::Sitting>().get(player).copied(),
+                matches!(
+                    ecs.read_storage::<comp::CharacterState>().get(player),
+                    Some(comp::CharacterState::Sit)
+                ),
+            )
+        })
+    };
+    client.tick_until("the character to sit down", |_| {
+        sitting() == (Some(comp::Sitting { seat: bench_uid }), true)
+    });
+    client.tick_until("the character to be put on the bench", |client| {
+        client.position().xy().distance(pos.xy()) < 0.5
+    });
+
+    server.with(move |server| {
+        let ecs = server.state().ecs();
+        let player = ecs.entity_from_uid(player_uid.0).unwrap();
+        ecs.write_storage::<comp::Pos>().get_mut(player).unwrap().0 += Vec3::unit_y() * 50.0;
+        if let Some(force_update) = ecs.write_storage::<comp::ForceUpdate>().get_mut(player) {
+            force_update.update();
+        }
+    });
+    client.tick_until("the character to get up", |_| sitting() == (None, false));
+}
+
+#[test]
+fn entities_out_of_possession_range_are_not_possessed() {
+    let server = TestServer::start();
+    let mut client = server.connect("judy");
+    let character_id = client.create_character("Judy");
+    client.spawn(character_id);
+
+    let player_uid = client.client.uid().unwrap();
+    let pos = client.position() + Vec3::unit_x() * 8.0;
+    let rat_uid = server.with(move |server| {
+        server.settings_mut().gameplay.possession_range = Some(5.0);
+        let body = comp::Body::QuadrupedSmall(quadruped_small::Body {
+            species: quadruped_small::Species::Rat,
+            body_type: quadruped_small::BodyType::Female,
+        });
+        let ecs = server.state().ecs();
+        let player = ecs.entity_from_uid(player_uid.0).unwrap();
+        ecs.write_storage()
+            .insert(player, comp::Admin(comp::AdminRole::Admin))
+            .unwrap();
+        let entity = server
+            .state_mut()
+            .create_npc(
+                comp::Pos(pos),
+                comp::Stats::new("Rat".to_owned()),
+                comp::SkillSet::default(),
+                Some(comp::Health::new(body, 0)),
+                comp::Poise::new(body),
+                comp::Inventory::with_empty(),
+                body,
+            )
+            .build();
+        server.state().ecs().uid_from_entity(entity).unwrap()
+    });
+    client.entity(rat_uid);
+    let possess = || {
+        server.with(move |server| {
+            server
+                .state()
+                .ecs()
+                .read_resource::<EventBus<ServerEvent>>()
+                .emit_now(ServerEvent::Possess(player_uid, rat_uid));
+        })
+    };
+
+    possess();
+    client.tick_until("the player to be told the rat is too far", |client| {
+        client.take_events().into_iter().any(
+            |event| matches!(event, Event::Chat(msg) if msg.message.contains("within 5 blocks")),
+        )
+    });
+    assert_eq!(client.client.uid(), Some(player_uid));
+
+    // Admins can be allowed to possess from any distance
+    server.with(|server| server.settings_mut().gameplay.possession_range = None);
+    possess();
+    client.tick_until("the player to possess the rat", |client| {
+        client.client.uid() == Some(rat_uid)
+    });
+}
+
+#[test]
+fn possessed_creatures_keep_their_own_components() {
+    let server = TestServer::start();
+    let mut client = server.connect("kim");
+    make_admin(&server, "kim");
+    client.spectate();
+    let player_uid = client.client.uid().unwrap();
+
+    // A rat that has a waypoint of its own, unlike the spectating player
+    let pos = client.position() + Vec3::unit_x() * 2.0;
+    let (rat, rat_uid) = server.with(move |server| {
+        let body = comp::Body::QuadrupedSmall(quadruped_small::Body {
+            species: quadruped_small::Species::Rat,
+            body_type: quadruped_small::BodyType::Female,
+        });
+        let entity = server
+            .state_mut()
+            .create_npc(
+                comp::Pos(pos),
+                comp::Stats::new("Rat".to_owned()),
+                comp::SkillSet::default(),
+                Some(comp::Health::new(body, 0)),
+                comp::Poise::new(body),
+                comp::Inventory::with_empty(),
+                body,
+            )
+            .with(comp::Waypoint::new(pos, Time(0.0)))
+            .build();
+        (
+            entity,
+            server.state().ecs().uid_from_entity(entity).unwrap(),
+        )
+    });
+    client.entity(rat_uid);
+    let rat_components = || {
+        server.with(move |server| {
+            let ecs = server.state().ecs();
+            (
+                ecs.read_storage::<comp::Admin>().contains(rat),
+                ecs.read_storage::<comp::Waypoint>()
+                    .get(rat)
+                    .map(|waypoint| waypoint.get_pos()),
+            )
+        })
+    };
+
+    server.with(move |server| {
+        server
+            .state()
+            .ecs()
+            .read_resource::<EventBus<ServerEvent>>()
+            .emit_now(ServerEvent::Possess(player_uid, rat_uid));
+    });
+    client.tick_until("the player to possess the rat", |client| {
+        client.client.uid() == Some(rat_uid)
+    });
+    assert_eq!(
+        rat_components(),
+        (true, None),
+        "the rat doesn't have the components of the player"
+    );
+
+    server.with(move |server| {
+        server
+            .state()
+            .ecs()
+            .read_resource::<EventBus<ServerEvent>>()
+            .emit_now(ServerEvent::Unpossess(rat_uid));
+    });
+    client.tick_until("the player to get their own body back", |client| {
+        client.client.uid() == Some(player_uid)
+    });
+    assert_eq!(
+        rat_components(),
+        (false, Some(pos)),
+        "the rat didn't get its own components back"
+    );
+}
@@ -100,7 +100,7 @@ use common::{
         quadruped_low, quadruped_medium, quadruped_small, Body, CharacterAbilityType, Health,
         InventoryUpdateEvent, UtteranceKind,
     },
-    outcome::Outcome,
+    outcome::{Outcome, SoundOcclusion},
     terrain::{BlockKind, TerrainChunk},
     uid::Uid,
     DamageSource,
@@ -416,6 +416,7 @@ impl SfxMgr {
         outcome: &Outcome,
         audio: &mut AudioFrontend,
         client: &Client,
+        occlusion: Option<SoundOcclusion>,
         underwater: bool,
     ) {
         if !audio.sfx_enabled() {
@@ -424,14 +425,17 @@ impl SfxMgr {
         let triggers = self.triggers.read();
         let uids = client.state().ecs().read_storage::<Uid>();
 
-        // TODO handle underwater
+        // Sounds behind terrain are quieter and muffled like sounds underwater
+        let underwater = underwater || occlusion.map_or(false, |o| o.is_muffled());
+        let volume = occlusion.map_or(1.0, |o| o.volume());
+
         match outcome {
             Outcome::Explosion { pos, power, .. } => {
                 let sfx_trigger_item = triggers.get_key_value(&SfxEvent::Explosion);
                 audio.emit_sfx(
                     sfx_trigger_item,
                     *pos,
-                    Some((power.abs() / 2.5).min(1.5)),
+                    Some((power.abs() / 2.5).min(1.5) * volume),
                     underwater,
                 );
             },
@@ -447,7 +451,7 @@ impl SfxMgr {
             },
             Outcome::GroundSlam { pos, .. } => {
                 let sfx_trigger_item = triggers.get_key_value(&SfxEvent::GroundSlam);
-                audio.emit_sfx(sfx_trigger_item, *pos, Some(2.0), underwater);
+                audio.emit_sfx(sfx_trigger_item, *pos, Some(2.0 * volume), underwater);
             },
             Outcome::ProjectileShot { pos, body, .. } => {
                 match body {
@@ -458,7 +462,7 @@ impl SfxMgr {
                         | object::Body::ArrowTurret,
                     ) => {
                         let sfx_trigger_item = triggers.get_key_value(&SfxEvent::ArrowShot);
-                        audio.emit_sfx(sfx_trigger_item, *pos, None, underwater);
+                        audio.emit_sfx(sfx_trigger_item, *pos, Some(volume), underwater);
                     },
                     Body::Object(
                         object::Body::BoltFire
@@ -466,7 +470,7 @@ impl SfxMgr {
                         | object::Body::BoltNature,
                     ) => {
                         let sfx_trigger_item = triggers.get_key_value(&SfxEvent::FireShot);
-                        audio.emit_sfx(sfx_trigger_item, *pos, None, underwater);
+                        audio.emit_sfx(sfx_trigger_item, *pos, Some(volume), underwater);
                     },
                     _ => {
                         // not mapped to sfx file
@@ -488,18 +492,18 @@ impl SfxMgr {
                 ) => {
                     if target.is_none() {
                         let sfx_trigger_item = triggers.get_key_value(&SfxEvent::ArrowMiss);
-                        audio.emit_sfx(sfx_trigger_item, *pos, Some(2.0), underwater);
+                        audio.emit_sfx(sfx_trigger_item, *pos, Some(2.0 * volume), underwater);
                     } else if *source == client.uid() {
                         let sfx_trigger_item = triggers.get_key_value(&SfxEvent::ArrowHit);
                         audio.emit_sfx(
                             sfx_trigger_item,
                             client.position().unwrap_or(*pos),
-                            Some(2.0),
+                            Some(2.0 * volume),
                             underwater,
                         );
                     } else {
                         let sfx_trigger_item = triggers.get_key_value(&SfxEvent::ArrowHit);
-                        audio.emit_sfx(sfx_trigger_item, *pos, Some(2.0), underwater);
+                        audio.emit_sfx(sfx_trigger_item, *pos, Some(2.0 * volume), underwater);
                     }
                 },
                 _ => {},
@@ -516,13 +520,13 @@ impl SfxMgr {
                 beam::FrontendSpecifier::LifestealBeam => {
                     if thread_rng().gen_bool(0.5) {
                         let sfx_trigger_item = triggers.get_key_value(&SfxEvent::SceptreBeam);
-                        audio.emit_sfx(sfx_trigger_item, *pos, None, underwater);
+                        audio.emit_sfx(sfx_trigger_item, *pos, Some(volume), underwater);
                     };
                 },
                 beam::FrontendSpecifier::Flamethrower | beam::FrontendSpecifier::Cultist => {
                     if thread_rng().gen_bool(0.5) {
                         let sfx_trigger_item = triggers.get_key_value(&SfxEvent::FlameThrower);
-                        audio.emit_sfx(sfx_trigger_item, *pos, None, underwater);
+                        audio.emit_sfx(sfx_trigger_item, *pos, Some(volume), underwater);
                     }
                 },
                 beam::FrontendSpecifier::ClayGolem
@@ -535,7 +539,7 @@ impl SfxMgr {
                 audio.emit_sfx(
                     sfx_trigger_item,
                     pos.map(|e| e as f32 + 0.5),
-                    Some(3.0),
+                    Some(3.0 * volume),
                     underwater,
                 );
             },
@@ -545,20 +549,20 @@ impl SfxMgr {
                     && !matches!(info.cause, Some(DamageSource::Buff(_)))
                 {
                     let sfx_trigger_item = triggers.get_key_value(&SfxEvent::Damage);
-                    audio.emit_sfx(sfx_trigger_item, *pos, Some(1.5), underwater);
+                    audio.emit_sfx(sfx_trigger_item, *pos, Some(1.5 * volume), underwater);
                 }
             },
             Outcome::Death { pos, .. } => {
                 let sfx_trigger_item = triggers.get_key_value(&SfxEvent::Death);
-                audio.emit_sfx(sfx_trigger_item, *pos, Some(1.5), underwater);
+                audio.emit_sfx(sfx_trigger_item, *pos, Some(1.5 * volume), underwater);
             },
             Outcome::Block { pos, parry, .. } => {
                 if *parry {
                     let sfx_trigger_item = triggers.get_key_value(&SfxEvent::Parry);
-                    audio.emit_sfx(sfx_trigger_item, *pos, Some(1.5), underwater);
+                    audio.emit_sfx(sfx_trigger_item, *pos, Some(1.5 * volume), underwater);
                 } else {
                     let sfx_trigger_item = triggers.get_key_value(&SfxEvent::Block);
-                    audio.emit_sfx(sfx_trigger_item, *pos, Some(1.5), underwater);
+                    audio.emit_sfx(sfx_trigger_item, *pos, Some(1.5 * volume), underwater);
                 }
             },
             Outcome::PoiseChange { pos, state, .. } => match state {
@@ -566,22 +570,22 @@ impl SfxMgr {
                 PoiseState::Interrupted => {
                     let sfx_trigger_item =
                         triggers.get_key_value(&SfxEvent::PoiseChange(PoiseState::Interrupted));
-                    audio.emit_sfx(sfx_trigger_item, *pos, Some(1.5), underwater);
+                    audio.emit_sfx(sfx_trigger_item, *pos, Some(1.5 * volume), underwater);
                 },
                 PoiseState::Stunned => {
                     let sfx_trigger_item =
                         triggers.get_key_value(&SfxEvent::PoiseChange(PoiseState::Stunned));
-                    audio.emit_sfx(sfx_trigger_item, *pos, Some(1.5), underwater);
+                    audio.emit_sfx(sfx_trigger_item, *pos, Some(1.5 * volume), underwater);
                 },
                 PoiseState::Dazed => {
                     let sfx_trigger_item =
                         triggers.get_key_value(&SfxEvent::PoiseChange(PoiseState::Dazed));
-                    audio.emit_sfx(sfx_trigger_item, *pos, Some(1.5), underwater);
+                    audio.emit_sfx(sfx_trigger_item, *pos, Some(1.5 * volume), underwater);
                 },
                 PoiseState::KnockedDown => {
                     let sfx_trigger_item =
                         triggers.get_key_value(&SfxEvent::PoiseChange(PoiseState::KnockedDown));
-                    audio.emit_sfx(sfx_trigger_item, *pos, Some(1.5), underwater);
+                    audio.emit_sfx(sfx_trigger_item, *pos, Some(1.5 * volume), underwater);
                 },
             },
             Outcome::Utterance { pos, kind, body } => {
@@ -589,7 +593,12 @@ impl SfxMgr {
                     let sfx_trigger_item =
                         triggers.get_key_value(&SfxEvent::Utterance(*kind, voice));
                     if let Some(sfx_trigger_item) = sfx_trigger_item {
-                        audio.emit_sfx(Some(sfx_trigger_item), *pos, Some(1.5), underwater);
+                        audio.emit_sfx(
+                            Some(sfx_trigger_item),
+                            *pos,
+                            Some(1.5 * volume),
+                            underwater,
+                        );
                     } else {
                         debug!(
                             "No utterance sound effect exists for ({:?}, {:?})",
@@ -601,10 +610,10 @@ impl SfxMgr {
             Outcome::Glider { pos, wielded } => {
                 if *wielded {
                     let sfx_trigger_item = triggers.get_key_value(&SfxEvent::GliderOpen);
-                    audio.emit_sfx(sfx_trigger_item, *pos, Some(1.0), underwater);
+                    audio.emit_sfx(sfx_trigger_item, *pos, Some(1.0 * volume), underwater);
                 } else {
                     let sfx_trigger_item = triggers.get_key_value(&SfxEvent::GliderClose);
-                    audio.emit_sfx(sfx_trigger_item, *pos, Some(1.0), underwater);
+                    audio.emit_sfx(sfx_trigger_item, *pos, Some(1.0 * volume), underwater);
                 }
             },
             Outcome::ExpChange { .. }
@@ -797,7 +797,7 @@ fn insert_killing_buff(buff: BuffKind, localized_strings: &Localization, templat
             tracing::error!("Player was killed by a positive buff!");
             "hud-outcome-mysterious"
         },
-        BuffKind::Wet | BuffKind::Ensnared | BuffKind::Poisoned => {
+        BuffKind::Wet | BuffKind::Ensnared | BuffKind::Poisoned | BuffKind::PotionSickness => {
             tracing::error!("Player was killed by a debuff that doesn't do damage!");
             "hud-outcome-mysterious"
         },
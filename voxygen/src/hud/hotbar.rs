@@ -1,6 +1,9 @@
 use common::comp::{
     self,
+    hotbar::HOTBAR_SLOTS,
     inventory::item::{item_key::ItemKey, Item},
+    slot::EquipSlot,
+    HotbarBinding,
 };
 use serde::{Deserialize, Serialize};
 
@@ -62,6 +65,43 @@ impl State {
         ));
     }
 
+    /// The bindings to register with the server, which resolves them when a
+    /// slot is activated.
+    pub fn bindings(&self) -> [Option<HotbarBinding>; HOTBAR_SLOTS] {
+        self.slots.clone().map(|slot| {
+            slot.map(|slot| match slot {
+                SlotContents::Inventory(hash, _) => HotbarBinding::Item(hash),
+                SlotContents::Ability(i) => HotbarBinding::Ability(i),
+            })
+        })
+    }
+
+    /// Takes over the bindings confirmed by the server. Bound items are looked
+    /// up in the inventory to know how to display them.
+    pub fn follow(&mut self, hotbar: &comp::Hotbar, inventory: Option<&comp::Inventory>) {
+        for (slot, binding) in self.slots.iter_mut().zip(hotbar.slots()) {
+            *slot = match *binding {
+                Some(HotbarBinding::Item(hash)) => match slot.take() {
+                    Some(SlotContents::Inventory(h, key)) if h == hash => {
+                        Some(SlotContents::Inventory(h, key))
+                    },
+                    _ => inventory
+                        .and_then(|inv| {
+                            inv.get_slot_from_hash(hash)
+                                .and_then(|slot| inv.get(slot))
+                                .or_else(|| {
+                                    inv.equipped(EquipSlot::InactiveMainhand)
+                                        .filter(|item| item.item_hash() == hash)
+                                })
+                        })
+                        .map(|item| SlotContents::Inventory(hash, ItemKey::from(item))),
+                },
+                Some(HotbarBinding::Ability(i)) => Some(SlotContents::Ability(i)),
+                None => None,
+            };
+        }
+    }
+
     // TODO: remove pending UI
     // Adds ability slots if missing and should be present
     // Removes ability slots if not there and shouldn't be present
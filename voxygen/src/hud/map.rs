@@ -5,13 +5,21 @@ use super::{
 };
 use crate::{
     game_input::GameInput,
+    hud::{Graphic, Ui},
     session::settings_change::{Interface as InterfaceChange, Interface::*},
     ui::{fonts::Fonts, img_ids, ImageFrame, Tooltip, TooltipManager, Tooltipable},
     window::KeyMouse,
     GlobalState,
 };
 use client::{self, Client, SiteInfoRich};
-use common::{comp, comp::group::Role, terrain::TerrainChunkSize, trade::Good, vol::RectVolSize};
+use common::{
+    comp,
+    comp::group::Role,
+    danger::{DangerGrid, CHUNKS_PER_CELL, MAX_DANGER, SAFE_DANGER, WILDERNESS_DANGER},
+    terrain::TerrainChunkSize,
+    trade::Good,
+    vol::RectVolSize,
+};
 use common_net::msg::world_msg::{PoiKind, SiteId, SiteKind};
 use conrod_core::{
     color,
@@ -21,8 +29,9 @@ use conrod_core::{
     widget_ids, Color, Colorable, Labelable, Positionable, Sizeable, UiCell, Widget, WidgetCommon,
 };
 use i18n::Localization;
+use image::{DynamicImage, RgbaImage};
 use specs::{saveload::MarkerAllocator, WorldExt};
-use std::borrow::Cow;
+use std::{borrow::Cow, sync::Arc};
 use vek::*;
 use winit::event::MouseButton;
 
@@ -39,6 +48,7 @@ widget_ids! {
         indicator,
         indicator_overlay,
         map_layers[],
+        danger_overlay,
         map_title,
         qlog_title,
         zoom_slider,
@@ -101,10 +111,62 @@ widget_ids! {
 
 const SHOW_ECONOMY: bool = false; // turn this display off (for 0.9) until we have an improved look
 
+/// The danger map overlay sent by the server, drawn over the map along with the
+/// difficulty of dungeons
+#[derive(Default)]
+pub struct DangerOverlay {
+    /// The image of the overlay and its size in cells
+    image: Option<(conrod_core::image::Id, Vec2<u32>)>,
+}
+
+impl DangerOverlay {
+    /// Creates the image of the overlay once the client has received it
+    pub fn maintain(&mut self, client: &Client, ui: &mut Ui) {
+        if self.image.is_some() {
+            return;
+        }
+        if let Some(grid) = client.danger_overlay() {
+            let image = Self::render(grid);
+            self.image = Some((
+                ui.add_graphic(Graphic::Image(
+                    Arc::new(DynamicImage::ImageRgba8(image)),
+                    Some(Rgba::zero()),
+                )),
+                grid.size(),
+            ));
+        }
+    }
+
+    /// One pixel per cell, with north at the top like the map layers
+    fn render(grid: &DangerGrid) -> RgbaImage {
+        let size = grid.size();
+        RgbaImage::from_fn(size.x, size.y, |x, y| {
+            let cell = Vec2::new(x, size.y - 1 - y).as_();
+            let color = Self::danger_color(grid.get(cell).unwrap_or(WILDERNESS_DANGER));
+            image::Rgba(color.into_array())
+        })
+    }
+
+    /// Safe areas are green, the wilderness is left clear and areas become
+    /// redder the more dangerous they are
+    fn danger_color(danger: u8) -> Rgba<u8> {
+        if danger <= SAFE_DANGER {
+            Rgba::new(60, 200, 80, 70)
+        } else if danger <= WILDERNESS_DANGER {
+            Rgba::zero()
+        } else {
+            let t =
+                f32::from(danger - WILDERNESS_DANGER) / f32::from(MAX_DANGER - WILDERNESS_DANGER);
+            Rgba::new(230, (200.0 * (1.0 - t)) as u8, 40, (50.0 + 100.0 * t) as u8)
+        }
+    }
+}
+
 #[derive(WidgetCommon)]
 pub struct Map<'a> {
     client: &'a Client,
     world_map: &'a (Vec<img_ids::Rotations>, Vec2<u32>),
+    danger_overlay: &'a DangerOverlay,
     imgs: &'a Imgs,
     fonts: &'a Fonts,
     #[conrod(common_builder)]
@@ -123,6 +185,7 @@ impl<'a> Map<'a> {
         imgs: &'a Imgs,
         rot_imgs: &'a ImgsRot,
         world_map: &'a (Vec<img_ids::Rotations>, Vec2<u32>),
+        danger_overlay: &'a DangerOverlay,
         fonts: &'a Fonts,
         pulse: f32,
         localized_strings: &'a Localization,
@@ -135,6 +198,7 @@ impl<'a> Map<'a> {
             imgs,
             rot_imgs,
             world_map,
+            danger_overlay,
             client,
             fonts,
             common: widget::CommonBuilder::default(),
@@ -476,6 +540,25 @@ impl<'a> Widget for Map<'a> {
             }
         }
 
+        // Danger overlay, a cell is several chunks wide and the grid may be padded at
+        // the top to a whole number of cells
+        if let Some((danger_overlay, cells)) = self.danger_overlay.image.filter(|_| show_difficulty)
+        {
+            let cell_size = CHUNKS_PER_CELL as f64;
+            let padding = cells.y as f64 - worldsize.y as f64 / cell_size;
+            let danger_src = position::Rect::from_xy_dim(
+                [rect_src.x() / cell_size, rect_src.y() / cell_size + padding],
+                [rect_src.w() / cell_size, rect_src.h() / cell_size],
+            );
+            Image::new(danger_overlay)
+                .mid_top_with_margin_on(state.ids.map_align, 10.0)
+                .w_h(map_size.x, map_size.y)
+                .parent(state.ids.bg)
+                .source_rectangle(danger_src)
+                .graphics_for(state.ids.map_layers[0])
+                .set(state.ids.danger_overlay, ui);
+        }
+
         // Icon settings
         // Alignment
         Rectangle::fill_with([150.0, 200.0], color::TRANSPARENT)
@@ -42,7 +42,7 @@ use group::Group;
 use img_ids::Imgs;
 use item_imgs::ItemImgs;
 use loot_scroller::LootScroller;
-use map::Map;
+use map::{DangerOverlay, Map};
 use minimap::{MiniMap, VoxelMinimap};
 use popup::Popup;
 use prompt_dialog::PromptDialog;
@@ -91,9 +91,9 @@ use common::{
         skillset::{skills::Skill, SkillGroupKind},
         BuffData, BuffKind, Health, Item, MapMarkerChange,
     },
-    consts::MAX_PICKUP_RANGE,
+    interaction::{InteractionKind, InteractionRanges},
     link::Is,
-    mounting::Mount,
+    mounting::{may_mount, Mount},
     outcome::Outcome,
     slowjob::SlowJobPool,
     terrain::{SpriteKind, TerrainChunk},
@@ -518,6 +518,7 @@ pub enum Event {
         bypass_dialog: bool,
     },
     SwapEquippedWeapons,
+    ActivateHotbarSlot(usize),
     SwapSlots {
         slot_a: comp::slot::Slot,
         slot_b: comp::slot::Slot,
@@ -1096,10 +1097,13 @@ pub struct Hud {
     hp_pulse: f32,
     slot_manager: slots::SlotManager,
     hotbar: hotbar::State,
+    /// The hotbar bindings last confirmed by the server
+    synced_hotbar: Option<comp::Hotbar>,
     events: Vec<Event>,
     crosshair_opacity: f32,
     floaters: Floaters,
     voxel_minimap: VoxelMinimap,
+    danger_overlay: DangerOverlay,
     map_drag: Vec2<f64>,
 }
 
@@ -1160,6 +1164,7 @@ impl Hud {
 
         Self {
             voxel_minimap: VoxelMinimap::new(&mut ui),
+            danger_overlay: DangerOverlay::default(),
             ui,
             imgs,
             world_map,
@@ -1215,6 +1220,7 @@ impl Hud {
             hp_pulse: 0.0,
             slot_manager,
             hotbar: hotbar_state,
+            synced_hotbar: None,
             events: Vec::new(),
             crosshair_opacity: 0.0,
             floaters: Floaters {
@@ -1251,6 +1257,7 @@ impl Hud {
         if global_state.settings.interface.map_show_voxel_map {
             self.voxel_minimap.maintain(client, &mut self.ui);
         }
+        self.danger_overlay.maintain(client, &mut self.ui);
         let (ref mut ui_widgets, ref mut item_tooltip_manager, ref mut tooltip_manager) =
             &mut self.ui.set_widgets();
         // self.ui.set_item_widgets(); pulse time for pulsating elements
@@ -1280,11 +1287,13 @@ impl Hud {
             let inventories = ecs.read_storage::<comp::Inventory>();
             let players = ecs.read_storage::<comp::Player>();
             let msm = ecs.read_resource::<MaterialStatManifest>();
+            let interaction_ranges = ecs.read_resource::<InteractionRanges>();
             let entities = ecs.entities();
             let me = info.viewpoint_entity;
             let poises = ecs.read_storage::<comp::Poise>();
             let alignments = ecs.read_storage::<comp::Alignment>();
             let is_mount = ecs.read_storage::<Is<Mount>>();
+            let mountables = ecs.read_storage::<comp::Mountable>();
 
             // Check if there was a persistence load error of the skillset, and if so
             // display a dialog prompt
@@ -1807,7 +1816,9 @@ impl Hud {
             for (entity, pos, item, distance) in (&entities, &pos, &items)
                 .join()
                 .map(|(entity, pos, item)| (entity, pos, item, pos.0.distance_squared(player_pos)))
-                .filter(|(_, _, _, distance)| distance < &MAX_PICKUP_RANGE.powi(2))
+                .filter(|(_, _, _, distance)| {
+                    distance < &interaction_ranges.range(InteractionKind::Pickup).powi(2)
+                })
             {
                 let overitem_id = overitem_walker.next(
                     &mut self.ids.overitems,
@@ -2093,9 +2104,9 @@ impl Hud {
                     &self.fonts,
                     &global_state.window.key_layout,
                     match alignment {
-                        // TODO: Don't use `MAX_MOUNT_RANGE` here, add dedicated interaction range
                         Some(comp::Alignment::Npc)
-                            if dist_sqr < common::consts::MAX_MOUNT_RANGE.powi(2)
+                            if dist_sqr
+                                < interaction_ranges.range(InteractionKind::Talk).powi(2)
                                 && interactable.as_ref().and_then(|i| i.entity())
                                     == Some(entity) =>
                         {
@@ -2104,12 +2115,13 @@ impl Hud {
                                 (GameInput::Trade, i18n.get_msg("hud-trade").to_string()),
                             ]
                         },
-                        Some(comp::Alignment::Owned(owner))
-                            if Some(*owner) == client.uid()
-                                && !client.is_riding()
-                                && is_mount.is_none()
-                                && is_mountable(body, bodies.get(client.entity()))
-                                && dist_sqr < common::consts::MAX_MOUNT_RANGE.powi(2) =>
+                        _ if client.uid().map_or(false, |uid| {
+                            may_mount(uid, alignment, mountables.contains(entity))
+                        }) && !client.is_riding()
+                            && is_mount.is_none()
+                            && is_mountable(body, bodies.get(client.entity()))
+                            && dist_sqr
+                                < interaction_ranges.range(InteractionKind::Mount).powi(2) =>
                         {
                             vec![(GameInput::Mount, i18n.get_msg("hud-mount").to_string())]
                         },
@@ -3204,6 +3216,7 @@ impl Hud {
                 &self.imgs,
                 &self.rot_imgs,
                 &self.world_map,
+                &self.danger_overlay,
                 &self.fonts,
                 self.pulse,
                 i18n,
@@ -3584,24 +3597,9 @@ impl Hud {
                     } else if let Hotbar(h) = from {
                         // Used from hotbar
                         self.hotbar.get(h).map(|s| match s {
-                            hotbar::SlotContents::Inventory(i, _) => {
-                                if let Some(inv) = inventories.get(info.viewpoint_entity) {
-                                    // If the item in the inactive main hand is the same as the item
-                                    // pressed in the hotbar, then swap active and inactive hands
-                                    // instead of looking for
-                                    // the item in the inventory
-                                    if inv
-                                        .equipped(comp::slot::EquipSlot::InactiveMainhand)
-                                        .map_or(false, |item| item.item_hash() == i)
-                                    {
-                                        events.push(Event::SwapEquippedWeapons);
-                                    } else if let Some(slot) = inv.get_slot_from_hash(i) {
-                                        events.push(Event::UseSlot {
-                                            slot: Slot::Inventory(slot),
-                                            bypass_dialog: false,
-                                        });
-                                    }
-                                }
+                            // The server works out which item the slot refers to
+                            hotbar::SlotContents::Inventory(_, _) => {
+                                events.push(Event::ActivateHotbarSlot(h as usize))
                             },
                             hotbar::SlotContents::Ability(_) => {},
                         });
@@ -3746,6 +3744,22 @@ impl Hud {
             }
         }
         self.hotbar.maintain_abilities(client, &info);
+        if let Some(server_hotbar) = client.hotbar() {
+            if self.synced_hotbar.as_ref() != Some(server_hotbar) {
+                let first_sync = self.synced_hotbar.is_none();
+                self.synced_hotbar = Some(server_hotbar.clone());
+                // Characters from before the server kept track of the hotbar only have
+                // their bindings in the profile, so those are registered instead of
+                // being cleared
+                if !(first_sync && server_hotbar.slots().iter().all(Option::is_none)) {
+                    self.hotbar.follow(
+                        server_hotbar,
+                        client.inventories().get(info.viewpoint_entity),
+                    );
+                }
+                events.push(Event::ChangeHotbarState(Box::new(self.hotbar.to_owned())));
+            }
+        }
 
         // Temporary Example Quest
         let arrow_ani = (self.pulse * 4.0/* speed factor */).cos() * 0.5 + 0.8; //Animation timer
@@ -3986,25 +4000,10 @@ impl Hud {
             } else {
                 let just_pressed = hotbar.process_input(slot, state);
                 hotbar.get(slot).map(|s| match s {
-                    hotbar::SlotContents::Inventory(i, _) => {
+                    hotbar::SlotContents::Inventory(_, _) => {
                         if just_pressed {
-                            if let Some(inv) = client_inventory {
-                                // If the item in the inactive main hand is the same as the item
-                                // pressed in the hotbar, then swap active and inactive hands
-                                // instead of looking for the item
-                                // in the inventory
-                                if inv
-                                    .equipped(comp::slot::EquipSlot::InactiveMainhand)
-                                    .map_or(false, |item| item.item_hash() == i)
-                                {
-                                    events.push(Event::SwapEquippedWeapons);
-                                } else if let Some(slot) = inv.get_slot_from_hash(i) {
-                                    events.push(Event::UseSlot {
-                                        slot: comp::slot::Slot::Inventory(slot),
-                                        bypass_dialog: false,
-                                    });
-                                }
-                            }
+                            // The server works out which item the slot refers to
+                            events.push(Event::ActivateHotbarSlot(slot as usize));
                         }
                     },
                     hotbar::SlotContents::Ability(i) => events.push(Event::Ability(i, state)),
@@ -4267,15 +4266,21 @@ impl Hud {
         }
 
         // Stop selecting a sprite to perform crafting with when out of range
+        let interaction_ranges = client.state().ecs().read_resource::<InteractionRanges>();
         self.show.crafting_fields.craft_sprite =
             self.show.crafting_fields.craft_sprite.filter(|(pos, _)| {
                 self.show.crafting
                     && if let Some(player_pos) = client.position() {
-                        pos.map(|e| e as f32 + 0.5).distance(player_pos) < MAX_PICKUP_RANGE
+                        interaction_ranges.in_range(
+                            InteractionKind::Pickup,
+                            pos.map(|e| e as f32 + 0.5),
+                            player_pos,
+                        )
                     } else {
                         false
                     }
             });
+        drop(interaction_ranges);
 
         // Optimization: skip maintaining UI when it's off.
         if !self.show.ui {
@@ -4579,6 +4584,7 @@ pub fn get_buff_image(buff: BuffKind, imgs: &Imgs) -> conrod_core::image::Id {
         BuffKind::Wet { .. } => imgs.debuff_wet_0,
         BuffKind::Ensnared { .. } => imgs.debuff_ensnared_0,
         BuffKind::Poisoned { .. } => imgs.debuff_poisoned_0,
+        BuffKind::PotionSickness => imgs.buff_potion_0,
     }
 }
 
@@ -4606,6 +4612,7 @@ pub fn get_buff_title(buff: BuffKind, localized_strings: &Localization) -> Cow<s
         BuffKind::Wet { .. } => localized_strings.get_msg("buff-title-wet"),
         BuffKind::Ensnared { .. } => localized_strings.get_msg("buff-title-ensnared"),
         BuffKind::Poisoned { .. } => localized_strings.get_msg("buff-title-poisoned"),
+        BuffKind::PotionSickness => localized_strings.get_msg("buff-title-potion_sickness"),
     }
 }
 
@@ -4639,6 +4646,11 @@ pub fn get_buff_desc(buff: BuffKind, data: BuffData, localized_strings: &Localiz
         BuffKind::Wet { .. } => localized_strings.get_msg("buff-desc-wet"),
         BuffKind::Ensnared { .. } => localized_strings.get_msg("buff-desc-ensnared"),
         BuffKind::Poisoned { .. } => localized_strings.get_msg("buff-desc-poisoned"),
+        BuffKind::PotionSickness => {
+            localized_strings.get_msg_ctx("buff-desc-potion_sickness", &i18n::fluent_args! {
+                "stacks" => data.strength
+            })
+        },
     }
 }
 
@@ -136,7 +136,7 @@ impl<'a> Widget for Overitem<'a> {
         // scaling ruins glyph caching, causing performance issues near lootbags
         // let scale: f64 = ((1.5
         //     - (self.distance_from_player_sqr /
-        //       common::consts::MAX_PICKUP_RANGE.powi(2)))
+        //       interaction_ranges.range(InteractionKind::Pickup).powi(2)))
         //     * 20.0)
         //     .into();
         let scale = 30.0;
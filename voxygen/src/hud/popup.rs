@@ -96,26 +96,32 @@ impl<'a> Widget for Popup<'a> {
         let info_color = |fade| Color::Rgba(1.0, 1.0, 0.0, fade);
         let message_color = |fade| Color::Rgba(1.0, 1.0, 1.0, fade);
 
-        // Push chunk name to message queue
-        if let Some(chunk) = self.client.current_chunk() {
-            if let Some(current) = chunk.meta().name() {
-                // Check if no other popup is displayed and a new one is needed
-                if state.messages.is_empty()
-                    && state
-                        .last_region_name
-                        .as_ref()
-                        .map(|l| l != current)
-                        .unwrap_or(true)
-                {
-                    // Update last_region
-                    state.update(|s| {
-                        if s.messages.is_empty() {
-                            s.last_message_update = Instant::now();
-                        }
-                        s.last_region_name = Some(current.to_owned());
-                        s.messages.push_back(current.to_owned());
-                    });
-                }
+        // Push the name of the site the player entered to the message queue, falling
+        // back to the chunk name until the server told us where we are
+        let location_name = match self.client.location() {
+            Some(location) => location.site_name.clone(),
+            None => self
+                .client
+                .current_chunk()
+                .and_then(|chunk| chunk.meta().name().map(str::to_owned)),
+        };
+        if let Some(current) = location_name.as_deref() {
+            // Check if no other popup is displayed and a new one is needed
+            if state.messages.is_empty()
+                && state
+                    .last_region_name
+                    .as_ref()
+                    .map(|l| l != current)
+                    .unwrap_or(true)
+            {
+                // Update last_region
+                state.update(|s| {
+                    if s.messages.is_empty() {
+                        s.last_message_update = Instant::now();
+                    }
+                    s.last_region_name = Some(current.to_owned());
+                    s.messages.push_back(current.to_owned());
+                });
             }
         }
 
@@ -117,10 +117,11 @@ impl PlayState for CharSelectionState {
                         mainhand,
                         offhand,
                         body,
+                        hardcore,
                     } => {
                         self.client
                             .borrow_mut()
-                            .create_character(alias, mainhand, offhand, body);
+                            .create_character(alias, mainhand, offhand, body, hardcore);
                     },
                     ui::Event::EditCharacter {
                         alias,
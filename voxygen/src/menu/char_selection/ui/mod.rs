@@ -21,7 +21,7 @@ use crate::{
 };
 use client::{Client, ServerInfo};
 use common::{
-    character::{CharacterId, CharacterItem, MAX_CHARACTERS_PER_PLAYER, MAX_NAME_LENGTH},
+    character::{CharacterId, CharacterItem, MAX_NAME_LENGTH},
     comp::{self, humanoid, inventory::slot::EquipSlot, Inventory, Item},
     LoadoutBuilder,
 };
@@ -133,6 +133,7 @@ pub enum Event {
         mainhand: Option<String>,
         offhand: Option<String>,
         body: comp::Body,
+        hardcore: bool,
     },
     EditCharacter {
         alias: String,
@@ -163,6 +164,7 @@ enum Mode {
         inventory: Box<Inventory>,
         mainhand: Option<&'static str>,
         offhand: Option<&'static str>,
+        hardcore: bool,
 
         body_type_buttons: [button::State; 2],
         species_buttons: [button::State; 6],
@@ -174,6 +176,7 @@ enum Mode {
         create_button: button::State,
         rand_character_button: button::State,
         rand_name_button: button::State,
+        hardcore_button: button::State,
         character_id: Option<CharacterId>,
     },
 }
@@ -213,6 +216,7 @@ impl Mode {
             inventory,
             mainhand,
             offhand,
+            hardcore: false,
             body_type_buttons: Default::default(),
             species_buttons: Default::default(),
             tool_buttons: Default::default(),
@@ -223,6 +227,7 @@ impl Mode {
             create_button: Default::default(),
             rand_character_button: Default::default(),
             rand_name_button: Default::default(),
+            hardcore_button: Default::default(),
             character_id: None,
         }
     }
@@ -239,6 +244,7 @@ impl Mode {
             inventory: Box::new(inventory.clone()),
             mainhand: None,
             offhand: None,
+            hardcore: false,
             body_type_buttons: Default::default(),
             species_buttons: Default::default(),
             tool_buttons: Default::default(),
@@ -249,6 +255,7 @@ impl Mode {
             create_button: Default::default(),
             rand_character_button: Default::default(),
             rand_name_button: Default::default(),
+            hardcore_button: Default::default(),
             character_id: Some(character_id),
         }
     }
@@ -297,6 +304,7 @@ enum Message {
     BodyType(humanoid::BodyType),
     Species(humanoid::Species),
     Tool((Option<&'static str>, Option<&'static str>)),
+    Hardcore(bool),
     RandomizeCharacter,
     RandomizeName,
     CancelDeletion,
@@ -480,6 +488,11 @@ impl Controls {
                 let characters = {
                     let characters = &client.character_list().characters;
                     let num = characters.len();
+                    // Dead hardcore characters don't count towards the limit
+                    let num_alive = characters
+                        .iter()
+                        .filter(|character| !character.is_dead())
+                        .count();
                     // Ensure we have enough button states
                     const CHAR_BUTTONS: usize = 3;
                     character_buttons.resize_with(num * CHAR_BUTTONS, Default::default);
@@ -513,7 +526,9 @@ impl Controls {
                                     (select_button, edit_button, delete_button),
                                 ),
                             )| {
-                                let select_col = if Some(i) == selected {
+                                let select_col = if character.is_dead() {
+                                    (97, 97, 97)
+                                } else if Some(i) == selected {
                                     (255, 208, 69)
                                 } else {
                                     (255, 255, 255)
@@ -557,10 +572,12 @@ impl Controls {
                                                 Text::new(&character.character.alias)
                                                     .size(fonts.cyri.scale(26))
                                                     .into(),
-                                                Text::new(
+                                                Text::new(if character.is_dead() {
+                                                    i18n.get_msg("char_selection-dead")
+                                                } else {
                                                     // TODO: Add actual location here
-                                                    i18n.get_msg("char_selection-uncanny_valley"),
-                                                )
+                                                    i18n.get_msg("char_selection-uncanny_valley")
+                                                })
                                                 .into(),
                                             ]),
                                         )
@@ -595,7 +612,8 @@ impl Controls {
                         .collect::<Vec<_>>();
 
                     // Add create new character button
-                    let color = if num >= MAX_CHARACTERS_PER_PLAYER {
+                    let max_characters = client.max_characters_per_player();
+                    let color = if num_alive >= max_characters {
                         (97, 97, 25)
                     } else {
                         (97, 255, 18)
@@ -624,7 +642,7 @@ impl Controls {
                             )
                             .width(Length::Fill)
                             .height(Length::Fill);
-                            if num < MAX_CHARACTERS_PER_PLAYER {
+                            if num_alive < max_characters {
                                 button.on_press(Message::NewCharacter)
                             } else {
                                 button
@@ -832,6 +850,7 @@ impl Controls {
                 inventory: _,
                 mainhand,
                 offhand: _,
+                hardcore,
                 ref mut scroll,
                 ref mut body_type_buttons,
                 ref mut species_buttons,
@@ -842,6 +861,7 @@ impl Controls {
                 ref mut create_button,
                 ref mut rand_character_button,
                 ref mut rand_name_button,
+                ref mut hardcore_button,
                 character_id,
             } => {
                 let unselected_style = style::button::Style::new(imgs.icon_border)
@@ -1214,7 +1234,7 @@ impl Controls {
                     tooltip::text(&tooltip_text, tooltip_style)
                 });
 
-                let column_content = vec![
+                let mut column_content = vec![
                     body_type.into(),
                     tool.into(),
                     species.into(),
@@ -1222,6 +1242,26 @@ impl Controls {
                     rand_character.into(),
                 ];
 
+                // Whether a character is hardcore can't be changed after its creation
+                if character_id.is_none() {
+                    column_content.push(
+                        Container::new(neat_button(
+                            hardcore_button,
+                            i18n.get_msg(if *hardcore {
+                                "char_selection-hardcore_on"
+                            } else {
+                                "char_selection-hardcore_off"
+                            }),
+                            FILL_FRAC_ONE,
+                            button_style,
+                            Some(Message::Hardcore(!*hardcore)),
+                        ))
+                        .width(Length::Units(200))
+                        .height(Length::Units(SMALL_BUTTON_HEIGHT))
+                        .into(),
+                    );
+                }
+
                 let left_column = Container::new(
                     Scrollable::new(scroll)
                         .push(
@@ -1437,7 +1477,11 @@ impl Controls {
                 }
             },
             Message::Select(id) => {
-                if let Mode::Select { .. } = &mut self.mode {
+                // Hardcore characters that died can't be played anymore
+                let dead = characters
+                    .iter()
+                    .any(|character| character.character.id == Some(id) && character.is_dead());
+                if let (Mode::Select { .. }, false) = (&mut self.mode, dead) {
                     self.selected = Some(id);
                     events.push(Event::SelectCharacter(Some(id)))
                 }
@@ -1474,6 +1518,7 @@ impl Controls {
                     body,
                     mainhand,
                     offhand,
+                    hardcore,
                     ..
                 } = &self.mode
                 {
@@ -1482,6 +1527,7 @@ impl Controls {
                         mainhand: mainhand.map(String::from),
                         offhand: offhand.map(String::from),
                         body: comp::Body::Humanoid(*body),
+                        hardcore: *hardcore,
                     });
                     self.mode = Mode::select(Some(InfoContent::CreatingCharacter));
                 }
@@ -1627,6 +1673,11 @@ impl Controls {
                     body.validate();
                 }
             },
+            Message::Hardcore(value) => {
+                if let Mode::CreateOrEdit { hardcore, .. } = &mut self.mode {
+                    *hardcore = value;
+                }
+            },
             Message::DoNothing => {},
         }
     }
@@ -11,6 +11,7 @@ use crate::{
     window::Event,
     Direction, GlobalState, PlayState, PlayStateResult,
 };
+use chrono::{Local, TimeZone};
 use client::{
     addr::ConnectionArgs,
     error::{InitProtocolError, NetworkConnectError, NetworkError},
@@ -461,8 +462,19 @@ fn get_client_msg_error(
             Error::ServerTimeout => localization.get_msg("main-login-timeout").into(),
             Error::ServerShutdown => localization.get_msg("main-login-server_shut_down").into(),
             Error::NotOnWhitelist => localization.get_msg("main-login-not_on_whitelist").into(),
-            Error::Banned(reason) => {
-                format!("{}: {}", localization.get_msg("main-login-banned"), reason)
+            Error::Banned(info) => {
+                let banned = match info
+                    .until
+                    .and_then(|until| Local.timestamp_opt(until, 0).single())
+                {
+                    Some(until) => {
+                        localization.get_msg_ctx("main-login-banned-until", &i18n::fluent_args! {
+                            "end_date" => until.format("%Y-%m-%d %H:%M").to_string(),
+                        })
+                    },
+                    None => localization.get_msg("main-login-banned"),
+                };
+                format!("{}: {}", banned, info.reason)
             },
             Error::InvalidCharacter => localization.get_msg("main-login-invalid_character").into(),
             Error::NetworkErr(NetworkError::ConnectFailed(NetworkConnectError::Handshake(
@@ -31,7 +31,7 @@ use crate::{
 use client::Client;
 use common::{
     comp,
-    outcome::Outcome,
+    outcome::{Outcome, SoundOcclusion},
     resources::DeltaTime,
     terrain::{BlockKind, TerrainChunk},
     vol::ReadVol,
@@ -428,6 +428,7 @@ impl Scene {
     pub fn handle_outcome(
         &mut self,
         outcome: &Outcome,
+        occlusion: Option<SoundOcclusion>,
         scene_data: &SceneData,
         audio: &mut AudioFrontend,
         state: &State,
@@ -441,7 +442,7 @@ impl Scene {
             .unwrap_or(false);
         self.particle_mgr.handle_outcome(outcome, scene_data);
         self.sfx_mgr
-            .handle_outcome(outcome, audio, scene_data.client, underwater);
+            .handle_outcome(outcome, audio, scene_data.client, occlusion, underwater);
 
         match outcome {
             Outcome::Lightning { pos } => {
@@ -9,7 +9,7 @@ use super::{
 use client::Client;
 use common::{
     comp,
-    consts::MAX_PICKUP_RANGE,
+    interaction::{InteractionKind, InteractionRanges},
     link::Is,
     mounting::Mount,
     terrain::Block,
@@ -124,6 +124,9 @@ pub(super) fn select_interactable(
         let colliders = ecs.read_storage::<comp::Collider>();
         let char_states = ecs.read_storage::<comp::CharacterState>();
         let is_mount = ecs.read_storage::<Is<Mount>>();
+        let pickup_range = ecs
+            .read_resource::<InteractionRanges>()
+            .range(InteractionKind::Pickup);
 
         let player_cylinder = Cylinder::from_components(
             player_pos,
@@ -147,12 +150,12 @@ pub(super) fn select_interactable(
                 (e, cylinder)
             })
             // Roughly filter out entities farther than interaction distance
-            .filter(|(_, cylinder)| player_cylinder.approx_in_range(*cylinder, MAX_PICKUP_RANGE))
+            .filter(|(_, cylinder)| player_cylinder.approx_in_range(*cylinder, pickup_range))
             .map(|(e, cylinder)| (e, player_cylinder.min_distance(cylinder)))
             .min_by_key(|(_, dist)| OrderedFloat(*dist));
 
         // Only search as far as closest interactable entity
-        let search_dist = closest_interactable_entity.map_or(MAX_PICKUP_RANGE, |(_, dist)| dist);
+        let search_dist = closest_interactable_entity.map_or(pickup_range, |(_, dist)| dist);
         let player_chunk = player_pos.xy().map2(TerrainChunk::RECT_SIZE, |e, sz| {
             (e.floor() as i32).div_euclid(sz as i32)
         });
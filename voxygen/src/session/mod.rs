@@ -23,10 +23,10 @@ use common::{
         },
         ChatMsg, ChatType, InputKind, InventoryUpdateEvent, Pos, Stats, UtteranceKind, Vel,
     },
-    consts::MAX_MOUNT_RANGE,
+    interaction::{InteractionKind, InteractionRanges},
     link::Is,
-    mounting::Mount,
-    outcome::Outcome,
+    mounting::{may_mount, Mount},
+    outcome::{Outcome, SoundOcclusion},
     recipe,
     terrain::{Block, BlockKind},
     trade::TradeResult,
@@ -117,6 +117,7 @@ impl SessionState {
             client.request_lossy_terrain_compression(
                 global_state.settings.networking.lossy_terrain_compression,
             );
+            client.request_low_bandwidth(global_state.settings.networking.low_bandwidth);
             #[cfg(not(target_os = "macos"))]
             if let Some(uid) = client.uid() {
                 let identiy = if let Some(info) = client.player_list().get(&uid) {
@@ -175,7 +176,7 @@ impl SessionState {
         &mut self,
         dt: Duration,
         global_state: &mut GlobalState,
-        outcomes: &mut Vec<Outcome>,
+        outcomes: &mut Vec<(Outcome, Option<SoundOcclusion>)>,
     ) -> Result<TickAction, Error> {
         span!(_guard, "tick", "Session::tick");
 
@@ -352,7 +353,7 @@ impl SessionState {
                     self.hud.new_notification(n);
                 },
                 client::Event::SetViewDistance(_vd) => {},
-                client::Event::Outcome(outcome) => outcomes.push(outcome),
+                client::Event::Outcome(outcome, occlusion) => outcomes.push((outcome, occlusion)),
                 client::Event::CharacterCreated(_) => {},
                 client::Event::CharacterEdited(_) => {},
                 client::Event::CharacterError(error) => {
@@ -798,23 +799,42 @@ impl PlayState for SessionState {
                                         .get(client.entity())
                                         .copied();
                                     if let Some(player_pos) = player_pos {
+                                        let mount_range = client
+                                            .state()
+                                            .ecs()
+                                            .read_resource::<InteractionRanges>()
+                                            .range(InteractionKind::Mount);
                                         // Find closest mountable entity
                                         let closest_mountable_entity = (
                                             &client.state().ecs().entities(),
                                             &client.state().ecs().read_storage::<Pos>(),
-                                            // TODO: More cleverly filter by things that can actually be mounted
+                                            // TODO: More cleverly filter by things that can
+                                            // actually be mounted
                                             !&client.state().ecs().read_storage::<Is<Mount>>(),
-                                            client.state().ecs().read_storage::<comp::Alignment>().maybe(),
+                                            client
+                                                .state()
+                                                .ecs()
+                                                .read_storage::<comp::Alignment>()
+                                                .maybe(),
+                                            client
+                                                .state()
+                                                .ecs()
+                                                .read_storage::<comp::Mountable>()
+                                                .maybe(),
                                         )
                                             .join()
-                                            .filter(|(entity, _, _, _)| *entity != client.entity())
-                                            .filter(|(_, _, _, alignment)| matches!(alignment, Some(comp::Alignment::Owned(owner)) if Some(*owner) == client.uid()))
-                                            .map(|(entity, pos, _, _)| {
-                                                (entity, player_pos.0.distance_squared(pos.0))
+                                            .filter(|(entity, _, _, _, _)| {
+                                                *entity != client.entity()
                                             })
-                                            .filter(|(_, dist_sqr)| {
-                                                *dist_sqr < MAX_MOUNT_RANGE.powi(2)
+                                            .filter(|(_, _, _, alignment, mountable)| {
+                                                client.uid().map_or(false, |uid| {
+                                                    may_mount(uid, *alignment, mountable.is_some())
+                                                })
                                             })
+                                            .map(|(entity, pos, _, _, _)| {
+                                                (entity, player_pos.0.distance_squared(pos.0))
+                                            })
+                                            .filter(|(_, dist_sqr)| *dist_sqr < mount_range.powi(2))
                                             .min_by_key(|(_, dist_sqr)| OrderedFloat(*dist_sqr));
                                         if let Some((mountee_entity, _)) = closest_mountable_entity
                                         {
@@ -1407,6 +1427,9 @@ impl PlayState for SessionState {
                     HudEvent::SwapEquippedWeapons => {
                         self.client.borrow_mut().swap_loadout();
                     },
+                    HudEvent::ActivateHotbarSlot(slot) => {
+                        self.client.borrow_mut().activate_hotbar_slot(slot);
+                    },
                     HudEvent::SwapSlots {
                         slot_a,
                         slot_b,
@@ -1527,7 +1550,14 @@ impl PlayState for SessionState {
                         self.client.borrow_mut().sort_inventory();
                     },
                     HudEvent::ChangeHotbarState(state) => {
-                        let client = self.client.borrow();
+                        let bindings = state.bindings();
+                        let mut client = self.client.borrow_mut();
+                        if client
+                            .hotbar()
+                            .map_or(true, |hotbar| *hotbar.slots() != bindings)
+                        {
+                            client.set_hotbar(bindings);
+                        }
 
                         let server_name = &client.server_info().name;
                         // If we are changing the hotbar state this CANNOT be None.
@@ -1734,9 +1764,10 @@ impl PlayState for SessionState {
                     );
 
                     // Process outcomes from client
-                    for outcome in outcomes {
+                    for (outcome, occlusion) in outcomes {
                         self.scene.handle_outcome(
                             &outcome,
+                            occlusion,
                             &scene_data,
                             &mut global_state.audio,
                             client.state(),
@@ -4,7 +4,7 @@ use vek::*;
 use client::{self, Client};
 use common::{
     comp,
-    consts::MAX_PICKUP_RANGE,
+    interaction::{InteractionKind, InteractionRanges},
     link::Is,
     mounting::Mount,
     terrain::Block,
@@ -77,6 +77,9 @@ pub(super) fn targets_under_cursor(
         char_states.get(player_entity),
     );
     let terrain = client.state().terrain();
+    let pickup_range = ecs
+        .read_resource::<InteractionRanges>()
+        .range(InteractionKind::Pickup);
 
     let find_pos = |hit: fn(Block) -> bool| {
         let cam_ray = terrain
@@ -88,7 +91,7 @@ pub(super) fn targets_under_cursor(
 
         if matches!(
             cam_ray.1,
-            Ok(Some(_)) if player_cylinder.min_distance(cam_pos + cam_dir * (cam_dist + 0.01)) <= MAX_PICKUP_RANGE
+            Ok(Some(_)) if player_cylinder.min_distance(cam_pos + cam_dir * (cam_dist + 0.01)) <= pickup_range
         ) {
             (
                 Some(cam_pos + cam_dir * (cam_dist + 0.01)),
@@ -12,6 +12,7 @@ pub struct NetworkingSettings {
     pub use_quic: bool,
     pub player_physics_behavior: bool,
     pub lossy_terrain_compression: bool,
+    pub low_bandwidth: bool,
     pub enable_discord_integration: bool,
 }
 
@@ -28,6 +29,7 @@ impl Default for NetworkingSettings {
             use_quic: false,
             player_physics_behavior: false,
             lossy_terrain_compression: false,
+            low_bandwidth: false,
             enable_discord_integration: true,
         }
     }
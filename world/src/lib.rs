@@ -43,6 +43,7 @@ pub use common::terrain::site::{DungeonKindMeta, SettlementKindMeta};
 pub use index::{IndexOwned, IndexRef};
 
 use crate::{
+    block::ZCache,
     column::ColumnGen,
     index::Index,
     layer::spot::Spot,
@@ -52,11 +53,13 @@ use crate::{
 use common::{
     assets,
     calendar::Calendar,
+    danger::{DangerSource, DangerSourceKind},
     generation::{ChunkSupplement, EntityInfo},
     lod,
     resources::TimeOfDay,
     terrain::{
-        Block, BlockKind, SpriteKind, TerrainChunk, TerrainChunkMeta, TerrainChunkSize, TerrainGrid,
+        BiomeKind, Block, BlockKind, SpriteKind, TerrainChunk, TerrainChunkMeta, TerrainChunkSize,
+        TerrainGrid,
     },
     vol::{ReadVol, RectVolSize, WriteVol},
 };
@@ -197,6 +200,56 @@ impl World {
         })
     }
 
+    /// Sites that affect the danger of their surroundings, used to build the
+    /// danger map overlay.
+    pub fn danger_sources(&self, index: IndexRef) -> Vec<DangerSource> {
+        self.civs()
+            .sites()
+            .filter_map(|site| {
+                let kind = match &site.kind {
+                    civ::SiteKind::Dungeon => DangerSourceKind::Dungeon {
+                        difficulty: match site.site_tmp.map(|id| &index.sites[id].kind) {
+                            Some(SiteKind::Dungeon(d)) => d.dungeon_difficulty()?,
+                            _ => return None,
+                        },
+                    },
+                    civ::SiteKind::Settlement
+                    | civ::SiteKind::Refactor
+                    | civ::SiteKind::CliffTown
+                    | civ::SiteKind::DesertCity => DangerSourceKind::Settlement,
+                    _ => return None,
+                };
+                Some(DangerSource {
+                    wpos: TerrainChunkSize::center_wpos(site.center).as_(),
+                    kind,
+                })
+            })
+            .collect()
+    }
+
+    /// The name of the closest site covering a world position, or else of the
+    /// point of interest there. Chunks are named after the site at their
+    /// center.
+    pub fn site_name_at(&self, index: IndexRef, wpos: Vec2<i32>) -> Option<String> {
+        let sim_chunk = self.sim.get_wpos(wpos)?;
+        sim_chunk
+            .sites
+            .iter()
+            .filter(|id| {
+                index.sites[**id].get_origin().distance_squared(wpos) as f32
+                    <= index.sites[**id].radius().powi(2)
+            })
+            .min_by_key(|id| index.sites[**id].get_origin().distance_squared(wpos))
+            .map(|id| index.sites[*id].name().to_string())
+            .or_else(|| sim_chunk.poi.map(|poi| self.civs.pois[poi].name.clone()))
+    }
+
+    pub fn biome_at(&self, wpos: Vec2<i32>) -> BiomeKind {
+        self.sim
+            .get_wpos(wpos)
+            .map_or(BiomeKind::Void, |chunk| chunk.get_biome())
+    }
+
     pub fn sample_columns(
         &self,
     ) -> impl Sampler<
@@ -231,31 +284,78 @@ impl World {
         index: IndexRef,
         chunk_pos: Vec2<i32>,
         // TODO: misleading name
-        mut should_continue: impl FnMut() -> bool,
+        should_continue: impl FnMut() -> bool,
         time: Option<(TimeOfDay, Calendar)>,
     ) -> Result<(TerrainChunk, ChunkSupplement), ()> {
-        let calendar = time.as_ref().map(|(_, cal)| cal);
+        self.generate_chunk_with_base(index, chunk_pos, None, should_continue, time)
+    }
 
+    /// Generates the base terrain of a chunk, which is the chunk before any
+    /// layers, sites or entities are applied to it. Base chunks can be
+    /// generated ahead of time and passed to
+    /// [`World::generate_chunk_with_base`], which skips sampling the blocks.
+    ///
+    /// The base terrain depends on the calendar (e.g. snow at Christmas), so
+    /// it should only be reused while the same calendar events are going on.
+    #[allow(clippy::result_unit_err)]
+    pub fn generate_base_chunk(
+        &self,
+        index: IndexRef,
+        chunk_pos: Vec2<i32>,
+        // TODO: misleading name
+        mut should_continue: impl FnMut() -> bool,
+        calendar: Option<&Calendar>,
+    ) -> Result<TerrainChunk, ()> {
         let mut sampler = self.sample_blocks();
 
         let chunk_wpos2d = chunk_pos * TerrainChunkSize::RECT_SIZE.map(|e| e as i32);
-        let chunk_center_wpos2d = chunk_wpos2d + TerrainChunkSize::RECT_SIZE.map(|e| e as i32 / 2);
         let grid_border = 4;
         let zcache_grid = Grid::populate_from(
             TerrainChunkSize::RECT_SIZE.map(|e| e as i32) + grid_border * 2,
             |offs| sampler.get_z_cache(chunk_wpos2d - grid_border + offs, index, calendar),
         );
 
-        let air = Block::air(SpriteKind::Empty);
-        let stone = Block::new(
-            BlockKind::Rock,
-            zcache_grid
-                .get(grid_border + TerrainChunkSize::RECT_SIZE.map(|e| e as i32) / 2)
-                .and_then(|zcache| zcache.as_ref())
-                .map(|zcache| zcache.sample.stone_col)
-                .unwrap_or_else(|| index.colors.deep_stone_color.into()),
+        match self.sim.get_base_z(chunk_pos) {
+            Some(base_z) => self.sample_base_chunk(
+                index,
+                chunk_pos,
+                base_z as i32,
+                &mut sampler,
+                &zcache_grid,
+                grid_border,
+                &mut should_continue,
+            ),
+            None => Ok(Self::void_chunk()),
+        }
+    }
+
+    /// Like [`World::generate_chunk`], but starts from the given base chunk
+    /// (see [`World::generate_base_chunk`]) instead of sampling the blocks
+    /// again. The base chunk must have been generated by the same world.
+    ///
+    /// The columns of the chunk are still sampled, since layers, sites and
+    /// entities are placed using them, so this only saves the block sampling
+    /// and not the whole cost of generating the chunk.
+    #[allow(clippy::result_unit_err)]
+    pub fn generate_chunk_with_base(
+        &self,
+        index: IndexRef,
+        chunk_pos: Vec2<i32>,
+        base: Option<TerrainChunk>,
+        // TODO: misleading name
+        mut should_continue: impl FnMut() -> bool,
+        time: Option<(TimeOfDay, Calendar)>,
+    ) -> Result<(TerrainChunk, ChunkSupplement), ()> {
+        let calendar = time.as_ref().map(|(_, cal)| cal);
+
+        let mut sampler = self.sample_blocks();
+
+        let chunk_wpos2d = chunk_pos * TerrainChunkSize::RECT_SIZE.map(|e| e as i32);
+        let grid_border = 4;
+        let zcache_grid = Grid::populate_from(
+            TerrainChunkSize::RECT_SIZE.map(|e| e as i32) + grid_border * 2,
+            |offs| sampler.get_z_cache(chunk_wpos2d - grid_border + offs, index, calendar),
         );
-        let water = Block::new(BlockKind::Water, Rgb::zero());
 
         let (base_z, sim_chunk) = match self
             .sim
@@ -269,80 +369,22 @@ impl World {
             Some(base_z) => (base_z as i32, self.sim.get(chunk_pos).unwrap()),
             // Some((base_z, sim_chunk)) => (base_z as i32, sim_chunk),
             None => {
-                return Ok((
-                    TerrainChunk::new(
-                        CONFIG.sea_level as i32,
-                        water,
-                        air,
-                        TerrainChunkMeta::void(),
-                    ),
-                    ChunkSupplement::default(),
-                ));
+                return Ok((Self::void_chunk(), ChunkSupplement::default()));
             },
         };
 
-        let meta = TerrainChunkMeta::new(
-            sim_chunk
-                .sites
-                .iter()
-                .filter(|id| {
-                    index.sites[**id]
-                        .get_origin()
-                        .distance_squared(chunk_center_wpos2d) as f32
-                        <= index.sites[**id].radius().powi(2)
-                })
-                .min_by_key(|id| {
-                    index.sites[**id]
-                        .get_origin()
-                        .distance_squared(chunk_center_wpos2d)
-                })
-                .map(|id| index.sites[*id].name().to_string())
-                .or_else(|| sim_chunk.poi.map(|poi| self.civs.pois[poi].name.clone())),
-            sim_chunk.get_biome(),
-            sim_chunk.alt,
-            sim_chunk.tree_density,
-            sim_chunk.cave.1.alt != 0.0,
-            sim_chunk.river.is_river(),
-            sim_chunk.river.velocity,
-            sim_chunk.temp,
-            sim_chunk.humidity,
-            sim_chunk
-                .sites
-                .iter()
-                .find_map(|site| index.sites[*site].kind.convert_to_meta()),
-        );
-
-        let mut chunk = TerrainChunk::new(base_z, stone, air, meta);
-
-        for y in 0..TerrainChunkSize::RECT_SIZE.y as i32 {
-            for x in 0..TerrainChunkSize::RECT_SIZE.x as i32 {
-                if should_continue() {
-                    return Err(());
-                };
-
-                let offs = Vec2::new(x, y);
-
-                let z_cache = match zcache_grid.get(grid_border + offs) {
-                    Some(Some(z_cache)) => z_cache,
-                    _ => continue,
-                };
-
-                let (min_z, max_z) = z_cache.get_z_limits();
-
-                (base_z..min_z as i32).for_each(|z| {
-                    let _ = chunk.set(Vec3::new(x, y, z), stone);
-                });
-
-                (min_z as i32..max_z as i32).for_each(|z| {
-                    let lpos = Vec3::new(x, y, z);
-                    let wpos = Vec3::from(chunk_wpos2d) + lpos;
-
-                    if let Some(block) = sampler.get_with_z_cache(wpos, Some(z_cache)) {
-                        let _ = chunk.set(lpos, block);
-                    }
-                });
-            }
-        }
+        let mut chunk = match base {
+            Some(base) => base,
+            None => self.sample_base_chunk(
+                index,
+                chunk_pos,
+                base_z,
+                &mut sampler,
+                &zcache_grid,
+                grid_border,
+                &mut should_continue,
+            )?,
+        };
 
         let sample_get = |offs| {
             zcache_grid
@@ -475,6 +517,93 @@ impl World {
         Ok((chunk, supplement))
     }
 
+    /// Chunk used for positions outside of the world
+    fn void_chunk() -> TerrainChunk {
+        TerrainChunk::new(
+            CONFIG.sea_level as i32,
+            Block::new(BlockKind::Water, Rgb::zero()),
+            Block::air(SpriteKind::Empty),
+            TerrainChunkMeta::void(),
+        )
+    }
+
+    /// Samples the blocks of a chunk from the world columns
+    #[allow(clippy::too_many_arguments)]
+    fn sample_base_chunk(
+        &self,
+        index: IndexRef,
+        chunk_pos: Vec2<i32>,
+        base_z: i32,
+        sampler: &mut BlockGen,
+        zcache_grid: &Grid<Option<ZCache>>,
+        grid_border: i32,
+        should_continue: &mut impl FnMut() -> bool,
+    ) -> Result<TerrainChunk, ()> {
+        let sim_chunk = self.sim.get(chunk_pos).ok_or(())?;
+        let chunk_wpos2d = chunk_pos * TerrainChunkSize::RECT_SIZE.map(|e| e as i32);
+        let chunk_center_wpos2d = chunk_wpos2d + TerrainChunkSize::RECT_SIZE.map(|e| e as i32 / 2);
+
+        let air = Block::air(SpriteKind::Empty);
+        let stone = Block::new(
+            BlockKind::Rock,
+            zcache_grid
+                .get(grid_border + TerrainChunkSize::RECT_SIZE.map(|e| e as i32) / 2)
+                .and_then(|zcache| zcache.as_ref())
+                .map(|zcache| zcache.sample.stone_col)
+                .unwrap_or_else(|| index.colors.deep_stone_color.into()),
+        );
+
+        let meta = TerrainChunkMeta::new(
+            self.site_name_at(index, chunk_center_wpos2d),
+            sim_chunk.get_biome(),
+            sim_chunk.alt,
+            sim_chunk.tree_density,
+            sim_chunk.cave.1.alt != 0.0,
+            sim_chunk.river.is_river(),
+            sim_chunk.river.velocity,
+            sim_chunk.temp,
+            sim_chunk.humidity,
+            sim_chunk
+                .sites
+                .iter()
+                .find_map(|site| index.sites[*site].kind.convert_to_meta()),
+        );
+
+        let mut chunk = TerrainChunk::new(base_z, stone, air, meta);
+
+        for y in 0..TerrainChunkSize::RECT_SIZE.y as i32 {
+            for x in 0..TerrainChunkSize::RECT_SIZE.x as i32 {
+                if should_continue() {
+                    return Err(());
+                };
+
+                let offs = Vec2::new(x, y);
+
+                let z_cache = match zcache_grid.get(grid_border + offs) {
+                    Some(Some(z_cache)) => z_cache,
+                    _ => continue,
+                };
+
+                let (min_z, max_z) = z_cache.get_z_limits();
+
+                (base_z..min_z as i32).for_each(|z| {
+                    let _ = chunk.set(Vec3::new(x, y, z), stone);
+                });
+
+                (min_z as i32..max_z as i32).for_each(|z| {
+                    let lpos = Vec3::new(x, y, z);
+                    let wpos = Vec3::from(chunk_wpos2d) + lpos;
+
+                    if let Some(block) = sampler.get_with_z_cache(wpos, Some(z_cache)) {
+                        let _ = chunk.set(lpos, block);
+                    }
+                });
+            }
+        }
+
+        Ok(chunk)
+    }
+
     // Zone coordinates
     pub fn get_lod_zone(&self, pos: Vec2<i32>, index: IndexRef) -> lod::Zone {
         let min_wpos = pos.map(lod::to_wpos);
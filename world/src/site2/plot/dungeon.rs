@@ -9,6 +9,7 @@ use crate::{
 use common::{
     assets::{self, AssetExt, AssetHandle},
     astar::Astar,
+    danger::dungeon_tier,
     generation::{ChunkSupplement, EntityInfo},
     store::{Id, Store},
     terrain::{
@@ -226,12 +227,12 @@ impl Room {
 
         // Toss mobs in the center of the room
         if tile_pos == enemy_spawn_tile && wpos2d == tile_wcenter.xy() {
-            let entities = match self.difficulty {
-                1 => enemy_1(dynamic_rng, tile_wcenter),
-                2 => enemy_2(dynamic_rng, tile_wcenter),
-                3 => enemy_3(dynamic_rng, tile_wcenter),
-                4 => enemy_4(dynamic_rng, tile_wcenter),
-                5 => enemy_5(dynamic_rng, tile_wcenter),
+            let entities = match dungeon_tier(self.difficulty) {
+                Some(1) => enemy_1(dynamic_rng, tile_wcenter),
+                Some(2) => enemy_2(dynamic_rng, tile_wcenter),
+                Some(3) => enemy_3(dynamic_rng, tile_wcenter),
+                Some(4) => enemy_4(dynamic_rng, tile_wcenter),
+                Some(5) => enemy_5(dynamic_rng, tile_wcenter),
                 _ => enemy_fallback(dynamic_rng, tile_wcenter),
             };
 
@@ -251,12 +252,12 @@ impl Room {
                                 - 16
                         })
                         .map(|e| e as f32 / 16.0);
-                match self.difficulty {
-                    3 => {
+                match dungeon_tier(self.difficulty) {
+                    Some(3) => {
                         let turret = turret_3(dynamic_rng, pos);
                         supplement.add_entity(turret);
                     },
-                    5 => {
+                    Some(5) => {
                         let turret = turret_5(dynamic_rng, pos);
                         supplement.add_entity(turret);
                     },
@@ -284,12 +285,12 @@ impl Room {
         let miniboss_spawn_tile = miniboss_spawn_tile + if miniboss_tile_is_pillar { 1 } else { 0 };
 
         if tile_pos == miniboss_spawn_tile && tile_wcenter.xy() == wpos2d {
-            let entities = match self.difficulty {
-                1 => mini_boss_1(dynamic_rng, tile_wcenter),
-                2 => mini_boss_2(dynamic_rng, tile_wcenter),
-                3 => mini_boss_3(dynamic_rng, tile_wcenter),
-                4 => mini_boss_4(dynamic_rng, tile_wcenter),
-                5 => mini_boss_5(dynamic_rng, tile_wcenter),
+            let entities = match dungeon_tier(self.difficulty) {
+                Some(1) => mini_boss_1(dynamic_rng, tile_wcenter),
+                Some(2) => mini_boss_2(dynamic_rng, tile_wcenter),
+                Some(3) => mini_boss_3(dynamic_rng, tile_wcenter),
+                Some(4) => mini_boss_4(dynamic_rng, tile_wcenter),
+                Some(5) => mini_boss_5(dynamic_rng, tile_wcenter),
                 _ => mini_boss_fallback(dynamic_rng, tile_wcenter),
             };
 
@@ -317,12 +318,12 @@ impl Room {
         let boss_spawn_tile = boss_spawn_tile + if boss_tile_is_pillar { 1 } else { 0 };
 
         if tile_pos == boss_spawn_tile && wpos2d == tile_wcenter.xy() {
-            let entities = match self.difficulty {
-                1 => boss_1(dynamic_rng, tile_wcenter),
-                2 => boss_2(dynamic_rng, tile_wcenter),
-                3 => boss_3(dynamic_rng, tile_wcenter),
-                4 => boss_4(dynamic_rng, tile_wcenter),
-                5 => boss_5(dynamic_rng, tile_wcenter),
+            let entities = match dungeon_tier(self.difficulty) {
+                Some(1) => boss_1(dynamic_rng, tile_wcenter),
+                Some(2) => boss_2(dynamic_rng, tile_wcenter),
+                Some(3) => boss_3(dynamic_rng, tile_wcenter),
+                Some(4) => boss_4(dynamic_rng, tile_wcenter),
+                Some(5) => boss_5(dynamic_rng, tile_wcenter),
                 _ => boss_fallback(dynamic_rng, tile_wcenter),
             };
 